@@ -0,0 +1,138 @@
+#ifndef EFUSE_API_H
+#define EFUSE_API_H
+
+/* Generated by cbindgen from src/ffi.rs; don't edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define EFUSE_OK 0
+
+/**
+ * a required pointer was null
+ */
+#define EFUSE_ERR_NULL -1
+
+/**
+ * an input buffer had the wrong length
+ */
+#define EFUSE_ERR_LENGTH -2
+
+/**
+ * the output buffer is too short; the length needed is stored through `written`
+ */
+#define EFUSE_ERR_BUFFER_TOO_SMALL -3
+
+/**
+ * a staged value was out of range, e.g. CNTL bits that aren't fuses
+ */
+#define EFUSE_ERR_RANGE -4
+
+/**
+ * the fused state can't be reached from the snapshot
+ */
+#define EFUSE_REFUSED_INVALID 1
+
+/**
+ * see EfuseError::UserMismatch
+ */
+#define EFUSE_REFUSED_USER_MISMATCH 2
+
+/**
+ * see EfuseError::WeakKey
+ */
+#define EFUSE_REFUSED_WEAK_KEY 3
+
+/**
+ * any other validation failure
+ */
+#define EFUSE_REFUSED_OTHER 4
+
+/**
+ * Opaque handle holding a fused state and an intended state
+ */
+typedef struct EfusePlanner EfusePlanner;
+
+/**
+ * Create a planner whose fused state is blank. Free it with efuse_planner_free().
+ */
+struct EfusePlanner *efuse_planner_new(void);
+
+/**
+ * # Safety
+ *
+ * `planner` must be null or a pointer from efuse_planner_new() that hasn't been freed.
+ */
+void efuse_planner_free(struct EfusePlanner *planner);
+
+/**
+ * Use an encoded FuseSnapshot (FuseSnapshot::WIRE_LEN bytes) as the fused state.
+ *
+ * # Safety
+ *
+ * `planner` must be live; `snapshot` must point to `len` readable bytes.
+ */
+int32_t efuse_planner_load_snapshot(struct EfusePlanner *planner,
+                                    const uint8_t *snapshot,
+                                    size_t len);
+
+/**
+ * Stage the intended key: 32 bytes, key[0] first.
+ *
+ * # Safety
+ *
+ * `planner` must be live; `key` must point to `len` readable bytes.
+ */
+int32_t efuse_planner_stage_key(struct EfusePlanner *planner, const uint8_t *key, size_t len);
+
+/**
+ * Stage the intended USER value: 4 bytes, little-endian.
+ *
+ * # Safety
+ *
+ * `planner` must be live; `user` must point to `len` readable bytes.
+ */
+int32_t efuse_planner_stage_user(struct EfusePlanner *planner, const uint8_t *user, size_t len);
+
+/**
+ * Stage the intended CNTL value: 1 byte. Returns EFUSE_ERR_RANGE, staging nothing, if it
+ * sets bits that aren't fuses.
+ *
+ * # Safety
+ *
+ * `planner` must be live; `cntl` must point to `len` readable bytes.
+ */
+int32_t efuse_planner_stage_cntl(struct EfusePlanner *planner, const uint8_t *cntl, size_t len);
+
+/**
+ * Validate the staged state against the fused state, rendering the outcome as NUL-terminated
+ * text into `report`. Returns the validation status, or EFUSE_ERR_BUFFER_TOO_SMALL with the
+ * length needed (NUL included) stored through `written`.
+ *
+ * # Safety
+ *
+ * `planner` must be live; `report` must point to `len` writable bytes; `written` may be null.
+ */
+int32_t efuse_planner_validate(struct EfusePlanner *planner,
+                               uint8_t *report,
+                               size_t len,
+                               size_t *written);
+
+/**
+ * Compile the DR words a burn of the staged state would shift, as 8-byte little-endian
+ * values in the order they're shifted. The commit sequence isn't included. Nothing is
+ * written if the staged state fails validation.
+ *
+ * # Safety
+ *
+ * `planner` must be live; `words` must point to `len` writable bytes; `written` may be null.
+ */
+int32_t efuse_planner_burn_words(struct EfusePlanner *planner,
+                                 uint8_t *words,
+                                 size_t len,
+                                 size_t *written);
+
+#endif /* EFUSE_API_H */
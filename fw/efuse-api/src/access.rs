@@ -0,0 +1,76 @@
+//! Telling a blank device from one whose fuses can't be read
+//!
+//! A device running an encrypted bitstream with its security lockout set doesn't answer the
+//! fuse readback instructions usefully: they come back zero-filled, exactly as a blank device
+//! reads, or with TDO held high. Taken at face value, a locked-down device looks like one that
+//! is ready to be provisioned. fetch() therefore looks at the readbacks as a whole before it
+//! believes them:
+//!
+//! * everything reads all ones: no fuse state reads like that (R_EN_B_KEY, among the CNTL
+//!   bits, would have zeroed the key), so the readbacks aren't reaching the fuses;
+//! * everything reads zero: the configuration registers decide. A part whose STAT says it's
+//!   running a decrypted bitstream, and whose CTL0 security bits are set, is reported as
+//!   blocked rather than blank. A blank part running a BBRAM-encrypted bitstream without the
+//!   lockout still reads as blank, as it is.
+//!
+//! Only a blank-looking fetch costs the extra register reads.
+
+use core::fmt;
+
+use jtag::*;
+
+use crate::keysource::*;
+
+/// How fetch() recognized that the fuse readbacks were blocked
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BlockedDetail {
+    /// every readback came back zero while a secured bitstream with its security bits set is
+    /// running; the registers as read
+    ZeroedWhileSecured { stat: u32, ctl0: u32 },
+    /// every readback came back all ones
+    Saturated,
+}
+
+/// What can be said about a device's fuses
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LockStatus {
+    /// the fuses read back and not one is blown
+    Blank,
+    /// the fuses read back; `cntl` is the CNTL bits as fused
+    Readable { cntl: u8 },
+    /// the fuse readbacks are blocked by the running configuration, so nothing is known
+    AccessRestricted { detail: BlockedDetail },
+}
+
+impl fmt::Display for LockStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LockStatus::Blank => write!(f, "blank: no fuses blown"),
+            LockStatus::Readable { cntl } => write!(f, "programmed: CNTL {:#04x}", cntl),
+            LockStatus::AccessRestricted { detail: BlockedDetail::ZeroedWhileSecured { stat, ctl0 } } =>
+                write!(f, "access restricted: fuses unreadable while a secured bitstream runs (STAT {:#010x}, CTL0 {:#010x}); state unknown", stat, ctl0),
+            LockStatus::AccessRestricted { detail: BlockedDetail::Saturated } =>
+                write!(f, "access restricted: fuse readbacks return all ones; state unknown"),
+        }
+    }
+}
+
+/// Checks the raw readbacks of a fetch for the blocked signature, reading STAT and CTL0 if
+/// they look blank. `cntl` is all `cntl_bits` bits FUSE_CNTL returned.
+pub(crate) fn blocked<T: JtagPhy>(key: &[u8; 32], user: u32, cntl: u32, cntl_bits: usize, jm: &mut JtagMach, jp: &mut T) -> Result<Option<BlockedDetail>, JtagError> {
+    let cntl_ones: u32 = ((1u64 << cntl_bits) - 1) as u32;
+    if key.iter().all(|&b| b == 0xFF) && user == !0 && cntl == cntl_ones {
+        return Ok(Some(BlockedDetail::Saturated));
+    }
+    if key.iter().all(|&b| b == 0) && user == 0 && cntl == 0 {
+        jp.pause(2000);
+        let stat: u32 = read_config_register(jm, jp, REG_STAT)?;
+        if stat & STAT_PART_SECURED != 0 {
+            let ctl0: u32 = read_config_register(jm, jp, REG_CTL0)?;
+            if ctl0 & CTL0_SBITS != 0 {
+                return Ok(Some(BlockedDetail::ZeroedWhileSecured { stat, ctl0 }));
+            }
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,117 @@
+//! Confirming the fused key boots before the device is locked down
+//!
+//! Locking the device down is the one step that can't be undone when the key is wrong: once
+//! CFG_AES_ONLY is blown only bitstreams encrypted with the eFUSE key load, and with the key's
+//! readback disabled nothing tells a wrong key from a right one until a boot fails. So the
+//! two-phase flow has a check between its phases: burn() puts the key and USER down, a
+//! BootVerifier then proves the device boots an encrypted bitstream with that key, and only
+//! then does burn_cntl() lock it.
+//!
+//! EfuseApi::verify_boot() runs the verifier and records a pass; burn_cntl() refuses with
+//! BootNotVerified without one. A later burn() invalidates the pass, since the key the device
+//! booted with may no longer be the key that's fused. Where a boot can't be tried, such as on
+//! a bench without a flash, override_boot_verification() lets the lockdown through anyway,
+//! and the reason given goes into the lockdown's BurnReport.
+//!
+//! JtagBootVerifier is the verifier for boards that boot from their configuration flash: it
+//! pulses JPROGRAM, gives the device time to load the bitstream, then reads STAT and wants DONE
+//! and PART_SECURED set and DEC_ERROR clear. Any FnMut closure returning the same result is a
+//! verifier too, for boards that confirm the boot some other way.
+
+use jtag::*;
+
+use crate::keysource::{read_config_register, REG_STAT, STAT_DEC_ERROR, STAT_DONE, STAT_PART_SECURED};
+use crate::sequences::{Ir, IR_BITS};
+
+/// Why a boot didn't verify
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BootVerifyError {
+    /// the JTAG traffic to the device failed
+    Jtag { err: JtagError },
+    /// the bitstream didn't decrypt; most likely the fused key isn't the bitstream's key
+    DecryptError { stat: u32 },
+    /// configuration didn't finish
+    NotDone { stat: u32 },
+    /// a bitstream loaded, but it wasn't an encrypted one
+    NotSecured { stat: u32 },
+    /// a board-specific check failed, with a code of its own
+    Rejected { code: u32 },
+}
+
+/// Something that can confirm the device boots an encrypted bitstream with its fused key
+pub trait BootVerifier {
+    fn verify_encrypted_boot(&mut self) -> Result<(), BootVerifyError>;
+}
+
+impl<F: FnMut() -> Result<(), BootVerifyError>> BootVerifier for F {
+    fn verify_encrypted_boot(&mut self) -> Result<(), BootVerifyError> {
+        self()
+    }
+}
+
+/// How the lockdown in a BurnReport was let through
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum BootCheck {
+    /// the burn wasn't a lockdown; also what reports decode with, as this isn't carried on
+    /// the wire
+    #[default]
+    NotRequired,
+    /// a BootVerifier passed since the last burn()
+    Verified,
+    /// override_boot_verification() was called, for this reason
+    Overridden { reason: &'static str },
+}
+
+/// Reboots the device from its configuration flash over JTAG and checks STAT
+pub struct JtagBootVerifier<'a, T: JtagPhy> {
+    jm: &'a mut JtagMach,
+    jp: &'a mut T,
+    boot_us: u32,
+}
+
+impl<'a, T: JtagPhy> JtagBootVerifier<'a, T> {
+    /// time allowed for the bitstream to load, unless with_boot_time() says otherwise
+    pub const DEFAULT_BOOT_US: u32 = 1_000_000;
+
+    pub fn new(jm: &'a mut JtagMach, jp: &'a mut T) -> Self {
+        JtagBootVerifier { jm, jp, boot_us: Self::DEFAULT_BOOT_US }
+    }
+
+    /// wait `us` after JPROGRAM before reading STAT; a large bitstream on a slow flash needs more
+    /// than the default
+    pub fn with_boot_time(self, us: u32) -> Self {
+        JtagBootVerifier { boot_us: us, ..self }
+    }
+
+    fn jprogram(&mut self) -> Result<(), JtagError> {
+        self.jm.try_reset(self.jp)?;
+        let mut ir_leg: JtagLeg = JtagLeg::new(JtagChain::IR, "jprogram");
+        ir_leg.push_u32(Ir::Jprogram.code(), IR_BITS, JtagEndian::Little).unwrap();
+        self.jm.add(ir_leg);
+        let result = self.jm.run_to_completion(self.jp);
+        if result.is_err() {
+            self.jm.clear_pending();
+        }
+        self.jm.drain_completed(|_| {});
+        result.map(|_| ())
+    }
+}
+
+impl<'a, T: JtagPhy> BootVerifier for JtagBootVerifier<'a, T> {
+    fn verify_encrypted_boot(&mut self) -> Result<(), BootVerifyError> {
+        let fail = |err: JtagError| BootVerifyError::Jtag { err };
+        self.jprogram().map_err(fail)?;
+        self.jp.pause(self.boot_us);
+        let stat: u32 = read_config_register(self.jm, self.jp, REG_STAT).map_err(fail)?;
+        if stat & STAT_DEC_ERROR != 0 {
+            Err(BootVerifyError::DecryptError { stat })
+        } else if stat & STAT_DONE == 0 {
+            Err(BootVerifyError::NotDone { stat })
+        } else if stat & STAT_PART_SECURED == 0 {
+            Err(BootVerifyError::NotSecured { stat })
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! Provisioning one device of several on a scan chain
+//!
+//! A panel of units on one JTAG chain is provisioned a unit at a time, without re-cabling. The
+//! unit being provisioned is addressed through the JtagMach's ChainPadding: every other device
+//! sees only BYPASS in its IR and a single padding bit in its DR, so no EFUSE or FUSE_CTS
+//! instruction is ever latched anywhere but in the addressed unit.
+//!
+//! Devices are numbered from 0 along the cable: device 0 is the one TDI reaches first, and the
+//! last device drives TDO.
+
+use alloc::vec::Vec;
+
+use jtag::*;
+
+use crate::messages::ProvisioningManifest;
+
+/// IDCODE bits that identify the part; the top four are the silicon revision
+pub const IDCODE_PART_MASK: u32 = 0x0FFF_FFFF;
+
+/// The device on the chain a flow is aimed at
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ChainTarget {
+    pub index: usize,
+}
+
+/// The devices on a scan chain, by IR length
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScanChain {
+    ir_bits: Vec<usize>,
+}
+
+impl ScanChain {
+    /// a chain with these IR lengths, device 0 first
+    pub fn new(ir_bits: &[usize]) -> Self {
+        ScanChain { ir_bits: ir_bits.to_vec() }
+    }
+
+    /// `devices` devices with the same IR length, as on a panel of identical units
+    pub fn uniform(devices: usize, ir_bits: usize) -> Self {
+        ScanChain { ir_bits: alloc::vec![ir_bits; devices] }
+    }
+
+    pub fn len(&self) -> usize {
+        self.ir_bits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ir_bits.is_empty()
+    }
+
+    /// the padding that addresses `target`, or None if there's no such device
+    pub fn padding(&self, target: ChainTarget) -> Option<ChainPadding> {
+        if target.index >= self.len() {
+            return None;
+        }
+        Some(ChainPadding::around(&self.ir_bits[..target.index], &self.ir_bits[target.index + 1..]))
+    }
+}
+
+/// What to provision one unit with, and how to make sure it's the right unit
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TargetManifest {
+    pub provisioning: ProvisioningManifest,
+    /// expected IDCODE; the revision bits aren't compared (see IDCODE_PART_MASK)
+    pub idcode: u32,
+    /// expected DNA, if the unit is known by it
+    pub dna: Option<u64>,
+}
+
+impl TargetManifest {
+    /// true if a device reading back `idcode` and `dna` is the one this manifest is for
+    pub fn matches(&self, idcode: u32, dna: u64) -> bool {
+        (idcode ^ self.idcode) & IDCODE_PART_MASK == 0 && self.dna.is_none_or(|expected| expected == dna)
+    }
+}
@@ -0,0 +1,62 @@
+//! An 8-bit checksum of the key, kept in USER
+//!
+//! Once R_EN_B_KEY is blown nothing reads the key back, so whether a unit holds the key some
+//! record says it does can only be taken on trust. With `BurnConfig::key_checksum_field` set,
+//! the burn that writes the key also writes a checksum of it into that USER field, which stays
+//! readable (unless R_EN_B_USER is blown too), and check_key_checksum() compares it against a
+//! candidate key later on.
+//!
+//! The checksum is the first nonzero byte of SHA-256 of the 32 key bytes, in the order
+//! set_key() takes them. Skipping zero bytes keeps a blank field distinguishable from a
+//! written one. Its bits go into the field's bits lowest first; a field of more than eight bits
+//! leaves the rest 0, and one of fewer keeps only the low bits of the checksum.
+//!
+//! What it can and can't say:
+//!
+//! * a mismatch is conclusive: the unit doesn't hold that key, or the field was damaged;
+//! * a match isn't: with 255 possible values, about one key in 255 matches any given unit, so
+//!   a match only confirms a record that is otherwise believed, it can't pick a key out of a
+//!   list. A narrower field matches more often still, and can't tell a checksum whose kept
+//!   bits are all 0 from a blank field;
+//! * the field gives away 8 bits about the key to anyone who can read USER. That narrows a
+//!   search of the key space by the same factor, which is negligible at 256 bits but is why
+//!   the field is opt-in.
+
+use crate::keyhex::KeyBytes;
+use crate::sha256::sha256;
+
+/// The checksum of `key` that a burn records
+pub fn key_checksum(key: &KeyBytes) -> u8 {
+    // SHA-256 output that is zero throughout isn't a practical concern
+    sha256(key).iter().copied().find(|&b| b != 0).unwrap_or(0xFF)
+}
+
+/// Outcome of EfuseApi::check_key_checksum()
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum KeyChecksumCheck {
+    /// the fused checksum is that of the key; see the module docs for what that's worth
+    Match,
+    /// the fused checksum is a different one: the unit doesn't hold the key
+    Mismatch { fused: u8, expected: u8 },
+    /// the field is blank: no checksum was burned, or USER reads as 0 because R_EN_B_USER is blown
+    NotRecorded,
+}
+
+/// the USER bits of `field` that record `checksum`
+pub(crate) fn deposit(field: u32, checksum: u8) -> u32 {
+    field_bits(field).enumerate()
+        .filter(|&(index, _)| checksum & (1 << index) != 0)
+        .fold(0, |user, (_, bit)| user | bit)
+}
+
+/// the checksum recorded in the `field` bits of `user`, or as much of it as the field holds
+pub(crate) fn extract(field: u32, user: u32) -> u8 {
+    field_bits(field).enumerate()
+        .filter(|&(_, bit)| user & bit != 0)
+        .fold(0, |checksum, (index, _)| checksum | (1 << index))
+}
+
+/// the first eight bits of `field`, lowest first
+fn field_bits(field: u32) -> impl Iterator<Item = u32> {
+    (0..32).map(|bit| 1u32 << bit).filter(move |&bit| field & bit != 0).take(8)
+}
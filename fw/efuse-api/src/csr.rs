@@ -0,0 +1,175 @@
+//! JtagPhy on the SoC's own JTAG master, through its CSRs
+//!
+//! The betrusted SoC drives the FPGA's JTAG port from an internal block, the one JtagGpioPhy in
+//! the jtag crate talks to through the PAC. CsrPhy drives the same block through a CsrBlock,
+//! so it needs neither the PAC nor an allocator, and a test can stand in a fake register block
+//! to check the bits it writes.
+//!
+//! The block has three registers, at the offsets a CsrMap gives:
+//!   * NEXT: TDI in bit 0 and TMS in bit 1. A write clocks one TCK cycle with them.
+//!   * TDO: TDO in bit 0, READY in bit 1. READY is low while a cycle is under way; TDO is what
+//!     was sampled before TCK rose, valid once READY is set again.
+//!   * MANUAL: TDI in bit 0, TMS in bit 1, TCK in bit 2 and ENABLE in bit 3. While ENABLE is
+//!     set the pins follow the register instead of the shifter, and TDO reads the pin as it is.
+//!
+//! sync() waits for READY, writes NEXT and waits for READY again. nosync() drives the pins
+//! through MANUAL; when TCK and TDI or TMS change at once it writes twice, so that TDI and TMS
+//! are set up before TCK rises and held until after it falls. The next sync() hands the pins
+//! back to the shifter.
+
+use jtag::*;
+
+/// Register access to the JTAG block; `offset` is a byte offset from the block's base
+pub trait CsrBlock {
+    fn read(&mut self, offset: usize) -> u32;
+    fn write(&mut self, offset: usize, value: u32);
+    /// waits at least `us` microseconds
+    fn delay_us(&mut self, us: u32);
+}
+
+/// Where the block's registers are
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CsrMap {
+    pub next: usize,
+    pub tdo: usize,
+    pub manual: usize,
+}
+
+impl CsrMap {
+    /// the block as the SoC lays it out, a register per 32-bit word
+    pub const BETRUSTED: CsrMap = CsrMap { next: 0x0, tdo: 0x4, manual: 0x8 };
+}
+
+pub const NEXT_TDI: u32 = 0x1;
+pub const NEXT_TMS: u32 = 0x2;
+pub const TDO_TDO: u32 = 0x1;
+pub const TDO_READY: u32 = 0x2;
+pub const MANUAL_TDI: u32 = 0x1;
+pub const MANUAL_TMS: u32 = 0x2;
+pub const MANUAL_TCK: u32 = 0x4;
+pub const MANUAL_ENABLE: u32 = 0x8;
+
+/// The block at a fixed address, through volatile reads and writes
+pub struct MmioCsr {
+    base: usize,
+    spins_per_us: u32,
+}
+
+impl MmioCsr {
+    /// `spins_per_us` is how many turns of a spin loop take a microsecond, for delay_us()
+    ///
+    /// # Safety
+    /// `base` must be the JTAG block's address, mapped, and not driven by anything else while
+    /// this is.
+    pub const unsafe fn new(base: usize, spins_per_us: u32) -> Self {
+        MmioCsr { base, spins_per_us }
+    }
+}
+
+impl CsrBlock for MmioCsr {
+    fn read(&mut self, offset: usize) -> u32 {
+        // safety: new() was promised the block is at `base`
+        unsafe { core::ptr::read_volatile((self.base + offset) as *const u32) }
+    }
+
+    fn write(&mut self, offset: usize, value: u32) {
+        // safety: as for read()
+        unsafe { core::ptr::write_volatile((self.base + offset) as *mut u32, value) }
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        for _ in 0..(us as u64 * self.spins_per_us as u64) {
+            core::hint::spin_loop();
+        }
+    }
+}
+
+/// A JtagPhy on the JTAG block
+pub struct CsrPhy<R: CsrBlock> {
+    regs: R,
+    map: CsrMap,
+    /// reads of TDO to wait for READY before giving up on a cycle
+    ready_polls: u32,
+    /// what was last written to MANUAL, if the pins are there rather than with the shifter
+    manual: Option<u32>,
+}
+
+impl<R: CsrBlock> CsrPhy<R> {
+    pub const DEFAULT_READY_POLLS: u32 = 10_000;
+
+    pub fn new(regs: R, map: CsrMap) -> Self {
+        CsrPhy { regs, map, ready_polls: CsrPhy::<R>::DEFAULT_READY_POLLS, manual: None }
+    }
+
+    pub fn regs(&self) -> &R {
+        &self.regs
+    }
+
+    pub fn regs_mut(&mut self) -> &mut R {
+        &mut self.regs
+    }
+
+    pub fn set_ready_polls(&mut self, polls: u32) {
+        self.ready_polls = polls.max(1);
+    }
+
+    /// hands the pins back to the shifter, if nosync() took them
+    pub fn release(&mut self) {
+        if let Some(pins) = self.manual.take() {
+            self.regs.write(self.map.manual, pins & !MANUAL_ENABLE);
+        }
+    }
+
+    /// the TDO register once READY is set; None if it didn't set in time
+    fn ready(&mut self) -> Option<u32> {
+        for _ in 0..self.ready_polls {
+            let tdo: u32 = self.regs.read(self.map.tdo);
+            if tdo & TDO_READY != 0 {
+                return Some(tdo);
+            }
+        }
+        None
+    }
+}
+
+impl<R: CsrBlock> JtagPhy for CsrPhy<R> {
+    fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+        self.try_sync(tdi, tms).unwrap_or(false)
+    }
+
+    /// drives the pins by hand through MANUAL, and reads TDO as it is
+    fn nosync(&mut self, tdi: bool, tms: bool, tck: bool) -> bool {
+        let mut pins: u32 = MANUAL_ENABLE;
+        if tdi { pins |= MANUAL_TDI; }
+        if tms { pins |= MANUAL_TMS; }
+        if tck { pins |= MANUAL_TCK; }
+        // the shifter leaves TCK low
+        let was: u32 = self.manual.unwrap_or(MANUAL_ENABLE);
+        let data: u32 = MANUAL_TDI | MANUAL_TMS;
+        if (was ^ pins) & MANUAL_TCK != 0 && (was ^ pins) & data != 0 {
+            // rising, TDI and TMS first; falling, TCK first
+            let first: u32 = if tck { (was & !data) | (pins & data) } else { (was & data) | (pins & !data) };
+            self.regs.write(self.map.manual, first);
+        }
+        self.regs.write(self.map.manual, pins);
+        self.manual = Some(pins);
+        self.regs.read(self.map.tdo) & TDO_TDO != 0
+    }
+
+    fn pause(&mut self, us: u32) {
+        self.regs.delay_us(us);
+    }
+
+    /// Busy if the block is still shifting and nothing was written; Transport if a cycle was
+    /// started and never finished
+    fn try_sync(&mut self, tdi: bool, tms: bool) -> Result<bool, PhyError> {
+        self.release();
+        self.ready().ok_or(PhyError::Busy)?;
+        let mut next: u32 = 0;
+        if tdi { next |= NEXT_TDI; }
+        if tms { next |= NEXT_TMS; }
+        self.regs.write(self.map.next, next);
+        let tdo: u32 = self.ready().ok_or(PhyError::Transport)?;
+        Ok(tdo & TDO_TDO != 0)
+    }
+}
@@ -0,0 +1,229 @@
+//! FPGA families and device profiles
+//!
+//! What EfuseApi and EfusePhy need to know of the part they're driving: the words that program
+//! its fuses, the instructions that select its eFUSE registers, the commit sequence, and how
+//! the logical KEY/USER/CNTL values are packed into its banks. Both take a DeviceFamily as a
+//! type parameter, SevenSeries if it isn't named, so `EfuseApi` on its own is the 7-series
+//! API, shifting exactly what it did before families existed (tests/golden_vector_tests.rs
+//! holds it to that).
+//!
+//! The numbers, as opposed to the layout, are a DeviceProfile: opcodes, the unlock word, bank
+//! select codes and DR lengths. A family has a default one, but it's a value, so a variant
+//! or silicon revision of a family that differs only in those takes
+//! EfuseApi::new_with_profile() rather than a family of its own.
+//!
+//! Another family, e.g. UltraScale+ with its FUSE_USER128, gets a type of its own implementing
+//! DeviceFamily, and EfuseApi::<ThatFamily>::for_family(). Its bank image is still
+//! FUSE_BANKS words; a family with fewer banks leaves the rest blank, and one with more needs
+//! the image widened first. The bank-at-a-time API, the fuse map and the Vivado exports name
+//! 7-series banks, so they're only on EfuseApi<SevenSeries>. So are the reads outside the
+//! eFUSE registers, STAT and CTL0 when checking for a secured device, which shift Ir's codes.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use jtag::{JtagChain, SeqCmd};
+
+use crate::layout::{self, EfuseLogical, FUSE_BANKS};
+use crate::secrets;
+use crate::sequences::{DeviceParams, Ir, CMD_EFUSE, CMD_JSTART, IR_BITS};
+
+/// The opcodes and lengths a device's eFUSE interface is driven with
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DeviceProfile {
+    /// the programming words: unlock, bank and word select codes, DR length
+    pub params: DeviceParams,
+    /// length of the instruction register, in bits
+    pub ir_bits: usize,
+    /// JSTART, issued before opening the programming port
+    pub jstart: u32,
+    /// FUSE_CTS, the programming port every programming word is shifted under
+    pub fuse_cts: u32,
+    pub fuse_key: u32,
+    pub fuse_user: u32,
+    pub fuse_cntl: u32,
+    pub fuse_dna: u32,
+    /// FUSE_CNTL bits a fetch shifts; verifying a CNTL burn shifts params.cntl_readback_bits.
+    /// Fewer than reach the redundant copy, and a fetch takes it to match the primary one;
+    /// EfuseApi::read_cntl_copies() reads both.
+    pub cntl_fetch_bits: usize,
+    /// FUSE_DNA bits
+    pub dna_bits: usize,
+    /// ISC_ENABLE/ISC_DISABLE around a burn's programming words and commit, and around each
+    /// CNTL copy's with burn_cntl(); None shifts them without it
+    pub isc: Option<IscBracket>,
+}
+
+/// The instructions a burn opens and closes in-system configuration mode with, as Xilinx's
+/// programming flows do. burn() issues each once, however many banks it programs.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct IscBracket {
+    pub enable: u32,
+    pub disable: u32,
+    /// TCK cycles held in RUN_TEST/IDLE after ISC_ENABLE, before the first programming word
+    pub enable_idle: u32,
+}
+
+impl IscBracket {
+    pub const SEVEN_SERIES: IscBracket = IscBracket {
+        enable: Ir::IscEnable as u32,
+        disable: Ir::IscDisable as u32,
+        enable_idle: 12,
+    };
+}
+
+impl DeviceProfile {
+    pub const SPARTAN7: DeviceProfile = DeviceProfile {
+        params: DeviceParams::SEVEN_SERIES,
+        ir_bits: IR_BITS,
+        jstart: CMD_JSTART,
+        fuse_cts: CMD_EFUSE,
+        fuse_key: Ir::FuseKey as u32,
+        fuse_user: Ir::FuseUser as u32,
+        fuse_cntl: Ir::FuseCntl as u32,
+        fuse_dna: Ir::FuseDna as u32,
+        // only the bottom 6 of the 14 are documented
+        cntl_fetch_bits: 14,
+        dna_bits: 64,
+        isc: Some(IscBracket::SEVEN_SERIES),
+    };
+
+    /// code of `ir` on this device: the profile's for the instructions it has, Ir's own for
+    /// the rest
+    pub fn code(&self, ir: Ir) -> u32 {
+        match ir {
+            Ir::Jstart => self.jstart,
+            Ir::FuseCts => self.fuse_cts,
+            Ir::FuseKey => self.fuse_key,
+            Ir::FuseUser => self.fuse_user,
+            Ir::FuseCntl => self.fuse_cntl,
+            Ir::FuseDna => self.fuse_dna,
+            _ => ir.code(),
+        }
+    }
+}
+
+impl Default for DeviceProfile {
+    fn default() -> Self {
+        DeviceProfile::SPARTAN7
+    }
+}
+
+/// An FPGA family's eFUSE interface and bank layout
+pub trait DeviceFamily {
+    /// the profile EfuseApi::for_family() starts with
+    const PROFILE: DeviceProfile;
+    /// commits the bits programmed so far, on a device driven as `profile` says
+    fn commit_seq(profile: &DeviceProfile) -> Vec<SeqCmd>;
+
+    /// mask of the bits of bank `index` that are fuses
+    fn bank_fuses(index: usize) -> u32;
+
+    /// whether `word` reads back cleanly as bank `index`: its ECC, or its copies, agree. By
+    /// default, whether it's what the bank's image of its own decoding would be.
+    fn bank_consistent(index: usize, word: u32) -> bool {
+        let mut banks: [u32; FUSE_BANKS] = [0; FUSE_BANKS];
+        banks[index] = word;
+        let mut logical: EfuseLogical = Self::from_banks(&banks);
+        let consistent: bool = Self::bank_image_ecc(index, &logical) == word;
+        secrets::wipe_bytes(&mut logical.key);
+        consistent
+    }
+
+    /// whether bank `index` holds any key bits
+    fn holds_key(index: usize) -> bool {
+        let mut banks: [u32; FUSE_BANKS] = [0; FUSE_BANKS];
+        banks[index] = Self::bank_fuses(index);
+        Self::from_banks(&banks).key != [0; 32]
+    }
+
+    /// raw (pre-ECC) contents of bank `index` for `logical`
+    fn bank_image(index: usize, logical: &EfuseLogical) -> u32;
+
+    /// contents of bank `index` for `logical` as it's fused, ECC included
+    fn bank_image_ecc(index: usize, logical: &EfuseLogical) -> u32;
+
+    /// the logical state a physical image decodes to
+    fn from_banks(banks: &[u32; FUSE_BANKS]) -> EfuseLogical;
+
+    /// USER, decoded from a physical image
+    fn user_from_banks(banks: &[u32; FUSE_BANKS]) -> u32;
+
+    /// the physical image of `logical`
+    fn to_banks(logical: &EfuseLogical) -> [u32; FUSE_BANKS] {
+        let mut banks: [u32; FUSE_BANKS] = [0; FUSE_BANKS];
+        for (index, bank) in banks.iter_mut().enumerate() {
+            *bank = Self::bank_image_ecc(index, logical);
+        }
+        banks
+    }
+}
+
+/// 7-series: the 13 banks of the layout module, driven as DeviceProfile::SPARTAN7 says
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SevenSeries;
+
+impl DeviceFamily for SevenSeries {
+    const PROFILE: DeviceProfile = DeviceProfile::SPARTAN7;
+    /// The commit as Vivado shifts it: the commit word under FUSE_CTS, then scans of the USER
+    /// registers. The instructions are the profile's, so they take its IR length.
+    fn commit_seq(profile: &DeviceProfile) -> Vec<SeqCmd> {
+        let ir = |ir: Ir, tag: &'static str| SeqCmd::new(JtagChain::IR, profile.ir_bits, profile.code(ir) as u64, tag);
+        let dr = |bits: usize, value: u64, tag: &'static str| SeqCmd::new(JtagChain::DR, bits, value, tag);
+        vec![
+            dr(profile.params.dr_bits, 0xff000000ff, "EFUSE_COMMIT"),
+            ir(Ir::User1, "USER1"),
+            dr(32, 0, "USER1"),
+            ir(Ir::User1, "USER1"),
+            dr(17, 0xF000, "USER1"),
+            dr(75, 0xA9, "USER1"),
+            ir(Ir::User3, "USER3"),
+            dr(17, 0xF000, "USER3"),
+            dr(75, 0xA9, "USER3"),
+            ir(Ir::Bypass, "BYPASS"),
+            ir(Ir::User2, "USER2"),
+            dr(32, 0x0, "USER2"),
+            ir(Ir::Bypass, "BYPASS"),
+            ir(Ir::User2, "USER2"),
+            dr(42, 0x69, "USER2"),
+            ir(Ir::Bypass, "BYPASS"),
+            ir(Ir::User2, "USER2"),
+            dr(6, 0xC, "USER2"),
+            dr(42, 0x69, "USER2"),
+            ir(Ir::Bypass, "BYPASS"),
+            ir(Ir::User2, "USER2"),
+            dr(36, 0x0, "USER2"),
+        ]
+    }
+
+    fn bank_fuses(index: usize) -> u32 {
+        layout::bank_fuses(index)
+    }
+
+    fn bank_consistent(index: usize, word: u32) -> bool {
+        layout::BankView::decode(index, word).is_consistent()
+    }
+
+    fn holds_key(index: usize) -> bool {
+        matches!(layout::Bank::ALL[index].kind(), layout::BankKind::Key | layout::BankKind::Shared)
+    }
+
+    fn bank_image(index: usize, logical: &EfuseLogical) -> u32 {
+        layout::bank_image(index, &logical.key, logical.user, logical.cntl)
+    }
+
+    fn bank_image_ecc(index: usize, logical: &EfuseLogical) -> u32 {
+        layout::bank_image_ecc(index, &logical.key, logical.user, logical.cntl)
+    }
+
+    fn from_banks(banks: &[u32; FUSE_BANKS]) -> EfuseLogical {
+        layout::from_banks(banks)
+    }
+
+    fn user_from_banks(banks: &[u32; FUSE_BANKS]) -> u32 {
+        layout::user_from_banks(banks)
+    }
+
+    fn to_banks(logical: &EfuseLogical) -> [u32; FUSE_BANKS] {
+        layout::to_banks(logical)
+    }
+}
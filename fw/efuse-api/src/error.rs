@@ -0,0 +1,250 @@
+//! Errors reported by the eFUSE API
+
+use core::fmt;
+
+use jtag::*;
+use crate::access::BlockedDetail;
+use crate::boot::BootVerifyError;
+use crate::keycheck::WeakKeyReason;
+use crate::keyhex::HexError;
+use crate::layout::{CntlCopy, FUSE_BANKS};
+use crate::userlayout::UserFieldError;
+use crate::xadc::{EnvViolation, XadcReadings};
+use crate::ConsistencyError;
+
+/// What the API was doing when an error occurred
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// reading back the fuse state
+    Fetch,
+    /// programming fuse bits
+    Burn,
+    /// running the commit sequence after programming
+    Commit,
+}
+
+/// One of the three fuse readbacks a fetch makes
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Readback {
+    /// FUSE_KEY
+    Key,
+    /// FUSE_USER
+    User,
+    /// FUSE_CNTL
+    Cntl,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EfuseError {
+    /// the phy's time budget ran out (see jtag::DeadlinePhy)
+    Timeout { phase: Phase },
+    /// any other JTAG failure
+    Jtag { phase: Phase, err: JtagError },
+    /// `readback` came back with `captured` of its `expected` bits: the phy's transport failed
+    /// partway through the shift, or the data never came out of the machine. The fused state
+    /// was left as it was, and the fetch can simply be tried again.
+    ShortReadback { readback: Readback, captured: usize, expected: usize },
+    /// burn() was called before the fused state was known: neither fetched nor loaded from a
+    /// snapshot; nothing was burned
+    NotFetched,
+    /// the intended state can't be reached from the fused state; nothing was burned
+    Invalid,
+    /// the intended state would need `mask` of `bank`, which are blown, to be clear again;
+    /// `bank` is the lowest such bank; EfuseApi::conflicts() has the rest. Nothing was burned.
+    IllegalTransition { bank: usize, mask: u32 },
+    /// set_cntl() was given `bits` outside CNTL_MASK, which aren't fuses; nothing was staged
+    CntlReserved { bits: u8 },
+    /// USER is to be changed, but FUSE_USER and the decoded banks disagree on its current value
+    UserMismatch { direct: u32, derived: u32 },
+    /// the key looks non-random and the burn also sets CNTL bits; see EfuseApi::allow_weak_key
+    WeakKey { reason: WeakKeyReason },
+    /// a raw burn named a bank that doesn't exist, or bits that aren't fuses; nothing was burned
+    OutOfRange { bank: usize, ones: u32 },
+    /// no more bits of the provisioning-event counter can be blown, and its CounterSpec
+    /// refuses to burn uncounted
+    CounterExhausted { count: u32 },
+    /// burn_cntl() was asked to lock the device while key or USER bits were still to be burned
+    CntlNotLast,
+    /// burn_single_bank() was asked for the CNTL bank, which only burn_cntl() burns
+    CntlSingleBank,
+    /// a copy of the CNTL bits didn't read back as programmed after its commit
+    CntlVerify { copy: CntlCopy, expected: u8, read: u8 },
+    /// a compiled sequence doesn't hash to its manifest, or was compiled for other device
+    /// parameters; nothing was burned
+    ManifestMismatch,
+    /// a compiled sequence would re-program `fuses` of `bank`, which are already blown; nothing
+    /// was burned
+    SequenceIncompatible { bank: usize, fuses: u32 },
+    /// the chain target doesn't exist on a chain of `devices` devices
+    NoSuchTarget { index: usize, devices: usize },
+    /// the addressed device read back this IDCODE and DNA, which aren't the manifest's; nothing
+    /// was burned
+    WrongDevice { idcode: u32, dna: u64 },
+    /// the two independently computed validation verdicts disagreed, or one was neither valid
+    /// nor invalid; a sign of a fault, so nothing was burned
+    ValidationIntegrity,
+    /// the staged key, USER or CNTL no longer match the checksum they were armed with (see
+    /// EfuseApi::arm); nothing was burned
+    StagedStateCorrupted,
+    /// verify_burn() read back `fuses` of `bank` blown, which neither the fused state nor the
+    /// last burn called for (see EfuseApi::allow_stray_fuses)
+    StrayFuses { bank: usize, fuses: u32 },
+    /// the fuse readbacks look blocked by the device's running secure configuration, so the
+    /// fused state is unknown; it was left as it was (see the access module)
+    AccessBlockedBySecurity { detail: BlockedDetail },
+    /// set_user_masked() would have cleared `bits` of USER, which are blown; nothing was staged
+    ClearsBlownUserBits { bits: u32 },
+    /// the programming port reported an error during the burn, or its status wasn't clean
+    /// after the commit; `bank` is the lowest one whose programming reported an error, if
+    /// any, and `raw_status` the status word as read (see the status module)
+    DeviceReportedError { bank: Option<usize>, raw_status: u32 },
+    /// the BootVerifier given to verify_boot() failed
+    BootVerifyFailed { err: BootVerifyError },
+    /// burn_cntl() was asked to lock the device before verify_boot() passed, with no override
+    /// (see the boot module); nothing was burned
+    BootNotVerified,
+    /// dna() read the DNA as 0, which no die has
+    DnaUnreadable,
+    /// dna() read the DNA twice and got two different values
+    DnaUnstable { first: u64, second: u64 },
+    /// idcode() read `read`, which has bit 0 clear and so isn't an IDCODE
+    NoIdcode { read: u32 },
+    /// the device's IDCODE is `read`, which doesn't match `expected` under `mask` (see
+    /// BurnConfig::expected_idcode); nothing was burned
+    IdcodeMismatch { expected: u32, mask: u32, read: u32 },
+    /// the XADC read `readings`, with `violation` outside BurnConfig::environment, and there
+    /// was no override; nothing was burned
+    OutOfSpec { violation: EnvViolation, readings: XadcReadings },
+    /// R_EN_B_KEY is blown, so the fused key isn't known, and the intended state changes the
+    /// key or the USER bits sharing bank 11 with it; nothing was burned
+    KeyUnreadable,
+    /// no bit of a RollbackCounter's range can be added to the fused USER banks; the counter
+    /// stays at `count`
+    RollbackExhausted { count: u32 },
+    /// plan_patch() found no data record for `bank` that keeps every fused fuse; `conflicts`
+    /// are the fuses the desired record itself would need cleared
+    NoPatch { bank: usize, conflicts: u32 },
+    /// set_key_slice() was given `len` bytes rather than 32; nothing was staged
+    KeyLength { len: usize },
+    /// set_key_hex() was given something that isn't a key in hex; nothing was staged
+    KeyHex { err: HexError },
+    /// apply_intent() was given an all-zero key by an intent that doesn't allow one; nothing
+    /// was staged
+    ZeroKey,
+    /// cross_check_user() found USERCODE disagreeing with another USER readback
+    Consistency { err: ConsistencyError },
+    /// fetch_stable() read these data bits, per bank, differently from one read to the next:
+    /// fuses that may be only partly blown. The fused state was left as it was.
+    MarginalBits { bits: [u32; FUSE_BANKS] },
+    /// a UserLayout refused a field, or get_field() or set_field() couldn't use one; nothing
+    /// was registered or staged
+    UserField { err: UserFieldError },
+    /// a key bank already holds key bits other than the intended key's, which would be ORed
+    /// into them (see EfuseApi::force_key_patch); nothing was burned
+    KeyAlreadyProgrammed,
+}
+
+impl EfuseError {
+    /// attribute a JTAG error to `phase`; an exceeded deadline is reported as a Timeout
+    pub fn from_jtag(phase: Phase, err: JtagError) -> Self {
+        match err {
+            JtagError::Phy(PhyError::DeadlineExceeded) => EfuseError::Timeout { phase },
+            _ => EfuseError::Jtag { phase, err },
+        }
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Phase::Fetch => "fetch",
+            Phase::Burn => "burn",
+            Phase::Commit => "commit",
+        })
+    }
+}
+
+impl fmt::Display for Readback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Readback::Key => "FUSE_KEY",
+            Readback::User => "FUSE_USER",
+            Readback::Cntl => "FUSE_CNTL",
+        })
+    }
+}
+
+impl fmt::Display for EfuseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use EfuseError::*;
+        match self {
+            Timeout { phase } => write!(f, "{}: JTAG time budget ran out", phase),
+            Jtag { phase, err } => write!(f, "{}: JTAG failure: {:?}", phase, err),
+            ShortReadback { readback, captured, expected } =>
+                write!(f, "{} readback came back with {} of {} bits", readback, captured, expected),
+            NotFetched => write!(f, "the fused state isn't known; fetch first"),
+            Invalid => write!(f, "the intended state can't be reached from the fused state"),
+            IllegalTransition { bank, mask } =>
+                write!(f, "bank {}: blown fuses {:#010x} would have to be cleared", bank, mask),
+            CntlReserved { bits } => write!(f, "CNTL bits {:#04x} aren't fuses", bits),
+            UserMismatch { direct, derived } =>
+                write!(f, "FUSE_USER reads {:#010x} but the banks decode to {:#010x}", direct, derived),
+            WeakKey { reason } => write!(f, "the key looks non-random ({:?}) and CNTL bits are to be set", reason),
+            OutOfRange { bank, ones } => write!(f, "bank {}, bits {:#010x}: not fuses", bank, ones),
+            CounterExhausted { count } => write!(f, "the provisioning-event counter is full at {}", count),
+            CntlNotLast => write!(f, "key or USER bits are still to be burned; CNTL goes last"),
+            CntlSingleBank => write!(f, "CNTL is burned with burn_cntl(), not as a single bank"),
+            CntlVerify { copy, expected, read } =>
+                write!(f, "{:?} CNTL copy read back {:#04x}, expected {:#04x}", copy, read, expected),
+            ManifestMismatch => write!(f, "the compiled sequence doesn't match its manifest or these device parameters"),
+            SequenceIncompatible { bank, fuses } =>
+                write!(f, "bank {}: the sequence would re-program blown fuses {:#010x}", bank, fuses),
+            NoSuchTarget { index, devices } => write!(f, "no device {} on a chain of {}", index, devices),
+            WrongDevice { idcode, dna } => write!(f, "wrong device: IDCODE {:#010x}, DNA {:#018x}", idcode, dna),
+            ValidationIntegrity => write!(f, "the redundant validation verdicts disagree"),
+            StagedStateCorrupted => write!(f, "the staged state changed since it was armed"),
+            StrayFuses { bank, fuses } => write!(f, "bank {}: stray fuses {:#010x} blown", bank, fuses),
+            AccessBlockedBySecurity { detail } => write!(f, "fuse readbacks blocked by the running configuration: {:?}", detail),
+            ClearsBlownUserBits { bits } => write!(f, "USER bits {:#010x} are blown and can't be cleared", bits),
+            DeviceReportedError { bank: Some(bank), raw_status } =>
+                write!(f, "bank {}: programming port reported status {:#010x}", bank, raw_status),
+            DeviceReportedError { bank: None, raw_status } =>
+                write!(f, "programming port status {:#010x} after the commit", raw_status),
+            BootVerifyFailed { err } => write!(f, "boot verification failed: {:?}", err),
+            BootNotVerified => write!(f, "lockdown refused: the encrypted boot hasn't been verified"),
+            DnaUnreadable => write!(f, "the device DNA reads as 0"),
+            DnaUnstable { first, second } => write!(f, "the device DNA read {:#018x}, then {:#018x}", first, second),
+            NoIdcode { read } => write!(f, "no IDCODE: read {:#010x}", read),
+            IdcodeMismatch { expected, mask, read } =>
+                write!(f, "wrong part: IDCODE {:#010x}, expected {:#010x} under {:#010x}", read, expected, mask),
+            OutOfSpec { violation, readings } => write!(f, "{:?} out of limits for burning: {}", violation, readings),
+            KeyUnreadable => write!(f, "the key can't be read back, so neither it nor USER[7:0] can be changed"),
+            RollbackExhausted { count } => write!(f, "the rollback counter can't go past {}", count),
+            NoPatch { bank, conflicts } =>
+                write!(f, "bank {}: no patch keeps blown fuses {:#010x}", bank, conflicts),
+            KeyLength { len } => write!(f, "a key is 32 bytes, not {}", len),
+            KeyHex { err } => write!(f, "not a key in hex: {:?}", err),
+            ZeroKey => write!(f, "the intended key is all zeros, and the intent doesn't allow that"),
+            Consistency { err: ConsistencyError::FuseUser { usercode, fuse_user } } =>
+                write!(f, "USERCODE reads {:#010x} but FUSE_USER {:#010x}", usercode, fuse_user),
+            Consistency { err: ConsistencyError::Banks { usercode, derived } } =>
+                write!(f, "USERCODE reads {:#010x} but the banks decode to {:#010x}", usercode, derived),
+            MarginalBits { bits } => {
+                write!(f, "fuses read differently from one fetch to the next:")?;
+                for (bank, bits) in bits.iter().enumerate().filter(|&(_, &bits)| bits != 0) {
+                    write!(f, " bank {} {:#010x}", bank, bits)?;
+                }
+                Ok(())
+            }
+            UserField { err: UserFieldError::Overlap { name, with } } =>
+                write!(f, "USER field {:?} overlaps {:?}", name, with),
+            UserField { err: UserFieldError::Duplicate { name } } => write!(f, "USER field {:?} is already registered", name),
+            UserField { err: UserFieldError::OutOfRange { name } } => write!(f, "USER field {:?} doesn't fit in USER", name),
+            UserField { err: UserFieldError::Unknown } => write!(f, "no such USER field"),
+            UserField { err: UserFieldError::TooWide { name, value } } =>
+                write!(f, "{:#x} doesn't fit in USER field {:?}", value, name),
+            KeyAlreadyProgrammed => write!(f, "a different key is already fused; burning this one would corrupt both"),
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! Events from fetch, validation and burn, for debugging a provisioning run in the field
+//!
+//! With the `log` feature these go to the log crate, under the target "efuse_api"; with
+//! `defmt`, out through defmt; with both, to both. With neither, every function here is empty,
+//! so nothing is formatted and nothing is linked in for a no_std build that doesn't opt in.
+//!
+//! No key byte goes into an event. A key is named by its fingerprint(), and by how many of its
+//! fuses are blown. An error goes in by its Display under log, which names the bank it's about;
+//! defmt can't format one, so under defmt an event has just the bank, if the error names one.
+//!
+//! In the order a provisioning run sees them:
+//!   * info, a fetch completed: key fingerprint and fuses blown, USER, CNTL; warn, it failed
+//!   * warn, validate() refused the intended state, with the bank it names
+//!   * debug, a bank's burn started, with the fuses to blow, and debug, it finished; warn, a
+//!     bank failed part way, and the commit was skipped
+//!   * info, the commit sequence started; info, it finished clean, or warn, it didn't
+
+// errors are only formatted for log, and with neither feature nothing is
+#![cfg_attr(not(feature = "log"), allow(unused_variables))]
+
+use crate::error::EfuseError;
+use crate::sha256::{sha256, wipe};
+
+#[cfg(feature = "log")]
+const TARGET: &str = "efuse_api";
+
+/// Names a key without giving it away: the first 8 bytes of its SHA-256, big-endian, the same
+/// bytes as kdf::KeyFingerprint
+pub fn fingerprint(key: &[u8; 32]) -> u64 {
+    let mut digest: [u8; 32] = sha256(key);
+    let mut first: [u8; 8] = [0; 8];
+    first.copy_from_slice(&digest[..8]);
+    wipe(&mut digest);
+    u64::from_be_bytes(first)
+}
+
+/// the bank `err` is about, if it's about one
+#[cfg(feature = "defmt")]
+fn bank_of(err: &EfuseError) -> Option<usize> {
+    match *err {
+        EfuseError::IllegalTransition { bank, .. } |
+        EfuseError::OutOfRange { bank, .. } |
+        EfuseError::SequenceIncompatible { bank, .. } |
+        EfuseError::StrayFuses { bank, .. } |
+        EfuseError::NoPatch { bank, .. } => Some(bank),
+        EfuseError::DeviceReportedError { bank, .. } => bank,
+        _ => None,
+    }
+}
+
+/// a fetch read `key`, unless the key readback is disabled, `user` and `cntl`
+pub(crate) fn fetched(key: &[u8; 32], readable: bool, user: u32, cntl: u8) {
+    #[cfg(any(feature = "log", feature = "defmt"))]
+    {
+        if readable {
+            let blown: u32 = key.iter().map(|b| b.count_ones()).sum();
+            let fingerprint: u64 = fingerprint(key);
+            #[cfg(feature = "log")]
+            log::info!(target: TARGET, "fetched: key {:016x}, {} key fuses blown, user {:#010x}, cntl {:#04x}", fingerprint, blown, user, cntl);
+            #[cfg(feature = "defmt")]
+            defmt::info!("efuse: fetched: key {=u64:x}, {=u32} key fuses blown, user {=u32:#x}, cntl {=u8:#x}", fingerprint, blown, user, cntl);
+        } else {
+            #[cfg(feature = "log")]
+            log::info!(target: TARGET, "fetched: key unreadable, user {:#010x}, cntl {:#04x}", user, cntl);
+            #[cfg(feature = "defmt")]
+            defmt::info!("efuse: fetched: key unreadable, user {=u32:#x}, cntl {=u8:#x}", user, cntl);
+        }
+    }
+}
+
+pub(crate) fn fetch_failed(err: &EfuseError) {
+    #[cfg(feature = "log")]
+    log::warn!(target: TARGET, "fetch failed: {}", err);
+    #[cfg(feature = "defmt")]
+    defmt::warn!("efuse: fetch failed");
+}
+
+pub(crate) fn validation_failed(err: &EfuseError) {
+    #[cfg(feature = "log")]
+    log::warn!(target: TARGET, "validation failed: {}", err);
+    #[cfg(feature = "defmt")]
+    defmt::warn!("efuse: validation failed, bank {=?}", bank_of(err));
+}
+
+pub(crate) fn bank_started(bank: usize, fuses: u32) {
+    #[cfg(feature = "log")]
+    log::debug!(target: TARGET, "bank {}: burning {} fuses", bank, fuses);
+    #[cfg(feature = "defmt")]
+    defmt::debug!("efuse: bank {=usize}: burning {=u32} fuses", bank, fuses);
+}
+
+pub(crate) fn bank_finished(bank: usize) {
+    #[cfg(feature = "log")]
+    log::debug!(target: TARGET, "bank {}: burned", bank);
+    #[cfg(feature = "defmt")]
+    defmt::debug!("efuse: bank {=usize}: burned", bank);
+}
+
+pub(crate) fn bank_failed(bank: usize, err: &EfuseError) {
+    #[cfg(feature = "log")]
+    log::warn!(target: TARGET, "bank {}: burn failed, not committing: {}", bank, err);
+    #[cfg(feature = "defmt")]
+    defmt::warn!("efuse: bank {=usize}: burn failed, not committing", bank);
+}
+
+pub(crate) fn commit_started() {
+    #[cfg(feature = "log")]
+    log::info!(target: TARGET, "committing");
+    #[cfg(feature = "defmt")]
+    defmt::info!("efuse: committing");
+}
+
+pub(crate) fn commit_finished(result: &Result<(), EfuseError>) {
+    match result {
+        Ok(()) => {
+            #[cfg(feature = "log")]
+            log::info!(target: TARGET, "committed, status clean");
+            #[cfg(feature = "defmt")]
+            defmt::info!("efuse: committed, status clean");
+        }
+        Err(err) => {
+            #[cfg(feature = "log")]
+            log::warn!(target: TARGET, "commit failed: {}", err);
+            #[cfg(feature = "defmt")]
+            defmt::warn!("efuse: commit failed, bank {=?}", bank_of(err));
+        }
+    }
+}
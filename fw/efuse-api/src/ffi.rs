@@ -0,0 +1,259 @@
+//! C interface to the offline planning API, for host-side factory tooling
+//!
+//! This covers loading a snapshot, staging the intended state, validating it and compiling
+//! the DR words a burn would shift. Anything that drives a live JTAG port stays Rust-only.
+//! The C declarations are in include/efuse_api.h, generated with cbindgen (see cbindgen.toml).
+//!
+//! The crate is no_std, so there is no catch_unwind to stand behind: every function here is
+//! written not to panic. Pointers are checked for null, lengths are checked before anything
+//! is copied, and nothing is indexed that hasn't been bounds checked.
+//!
+//! Functions return EFUSE_OK, a negative EFUSE_ERR_* code for a bad call, or a positive
+//! EFUSE_REFUSED_* code when the plan itself fails validation.
+
+use alloc::boxed::Box;
+use core::fmt::{self, Write};
+use core::slice;
+
+use crate::messages::FuseSnapshot;
+use crate::{EfuseApi, EfuseError, ValidationReport};
+
+pub const EFUSE_OK: i32 = 0;
+/// a required pointer was null
+pub const EFUSE_ERR_NULL: i32 = -1;
+/// an input buffer had the wrong length
+pub const EFUSE_ERR_LENGTH: i32 = -2;
+/// the output buffer is too short; the length needed is stored through `written`
+pub const EFUSE_ERR_BUFFER_TOO_SMALL: i32 = -3;
+/// a staged value was out of range, e.g. CNTL bits that aren't fuses
+pub const EFUSE_ERR_RANGE: i32 = -4;
+/// the fused state can't be reached from the snapshot
+pub const EFUSE_REFUSED_INVALID: i32 = 1;
+/// see EfuseError::UserMismatch
+pub const EFUSE_REFUSED_USER_MISMATCH: i32 = 2;
+/// see EfuseError::WeakKey
+pub const EFUSE_REFUSED_WEAK_KEY: i32 = 3;
+/// any other validation failure
+pub const EFUSE_REFUSED_OTHER: i32 = 4;
+
+/// Opaque handle holding a fused state and an intended state
+pub struct EfusePlanner {
+    api: EfuseApi,
+}
+
+fn refusal(err: &EfuseError) -> i32 {
+    match err {
+        EfuseError::Invalid | EfuseError::IllegalTransition { .. } => EFUSE_REFUSED_INVALID,
+        EfuseError::UserMismatch { .. } => EFUSE_REFUSED_USER_MISMATCH,
+        EfuseError::WeakKey { .. } => EFUSE_REFUSED_WEAK_KEY,
+        _ => EFUSE_REFUSED_OTHER,
+    }
+}
+
+/// `len` bytes at `ptr`; a null pointer is only accepted for an empty buffer
+unsafe fn input<'a>(ptr: *const u8, len: usize) -> Option<&'a [u8]> {
+    if ptr.is_null() {
+        if len == 0 { Some(&[]) } else { None }
+    } else {
+        Some(slice::from_raw_parts(ptr, len))
+    }
+}
+
+/// Copies as much as fits into `out`, counting everything it's given
+struct Output<'a> {
+    out: &'a mut [u8],
+    len: usize,
+}
+
+impl Output<'_> {
+    fn put(&mut self, bytes: &[u8]) {
+        if let Some(dest) = self.out.get_mut(self.len..self.len + bytes.len()) {
+            dest.copy_from_slice(bytes);
+        }
+        self.len += bytes.len();
+    }
+
+    /// reports the full length through `written`; EFUSE_ERR_BUFFER_TOO_SMALL if it didn't all fit
+    unsafe fn finish(self, written: *mut usize) -> i32 {
+        if !written.is_null() {
+            *written = self.len;
+        }
+        if self.len > self.out.len() { EFUSE_ERR_BUFFER_TOO_SMALL } else { EFUSE_OK }
+    }
+}
+
+impl Write for Output<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.put(s.as_bytes());
+        Ok(())
+    }
+}
+
+unsafe fn output<'a>(ptr: *mut u8, len: usize) -> Option<Output<'a>> {
+    let out: &mut [u8] = if ptr.is_null() {
+        if len != 0 {
+            return None;
+        }
+        &mut []
+    } else {
+        slice::from_raw_parts_mut(ptr, len)
+    };
+    Some(Output { out, len: 0 })
+}
+
+fn render(out: &mut Output, result: &Result<ValidationReport, EfuseError>) -> fmt::Result {
+    match result {
+        Ok(report) => {
+            write!(out, "ok")?;
+            for warning in report.warnings.iter() {
+                write!(out, "\nwarning: {:?}", warning)?;
+            }
+            if report.weak_key_overridden {
+                write!(out, "\nweak key overridden")?;
+            }
+        },
+        Err(e) => write!(out, "refused: {:?}", e)?,
+    }
+    Ok(())
+}
+
+/// Create a planner whose fused state is blank. Free it with efuse_planner_free().
+#[no_mangle]
+pub extern "C" fn efuse_planner_new() -> *mut EfusePlanner {
+    Box::into_raw(Box::new(EfusePlanner { api: EfuseApi::new() }))
+}
+
+/// # Safety
+///
+/// `planner` must be null or a pointer from efuse_planner_new() that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn efuse_planner_free(planner: *mut EfusePlanner) {
+    if !planner.is_null() {
+        drop(Box::from_raw(planner));
+    }
+}
+
+/// Use an encoded FuseSnapshot (FuseSnapshot::WIRE_LEN bytes) as the fused state.
+///
+/// # Safety
+///
+/// `planner` must be live; `snapshot` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn efuse_planner_load_snapshot(planner: *mut EfusePlanner, snapshot: *const u8, len: usize) -> i32 {
+    let (planner, bytes) = match (planner.as_mut(), input(snapshot, len)) {
+        (Some(p), Some(b)) => (p, b),
+        _ => return EFUSE_ERR_NULL,
+    };
+    match FuseSnapshot::decode(bytes) {
+        Some(snapshot) => {
+            planner.api.load_snapshot(&snapshot);
+            EFUSE_OK
+        },
+        None => EFUSE_ERR_LENGTH,
+    }
+}
+
+/// Stage the intended key: 32 bytes, key[0] first.
+///
+/// # Safety
+///
+/// `planner` must be live; `key` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn efuse_planner_stage_key(planner: *mut EfusePlanner, key: *const u8, len: usize) -> i32 {
+    let (planner, bytes) = match (planner.as_mut(), input(key, len)) {
+        (Some(p), Some(b)) => (p, b),
+        _ => return EFUSE_ERR_NULL,
+    };
+    let mut key: [u8; 32] = [0; 32];
+    if bytes.len() != key.len() {
+        return EFUSE_ERR_LENGTH;
+    }
+    key.copy_from_slice(bytes);
+    planner.api.set_key(key);
+    EFUSE_OK
+}
+
+/// Stage the intended USER value: 4 bytes, little-endian.
+///
+/// # Safety
+///
+/// `planner` must be live; `user` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn efuse_planner_stage_user(planner: *mut EfusePlanner, user: *const u8, len: usize) -> i32 {
+    let (planner, bytes) = match (planner.as_mut(), input(user, len)) {
+        (Some(p), Some(b)) => (p, b),
+        _ => return EFUSE_ERR_NULL,
+    };
+    let mut user: [u8; 4] = [0; 4];
+    if bytes.len() != user.len() {
+        return EFUSE_ERR_LENGTH;
+    }
+    user.copy_from_slice(bytes);
+    planner.api.set_user(u32::from_le_bytes(user));
+    EFUSE_OK
+}
+
+/// Stage the intended CNTL value: 1 byte. Returns EFUSE_ERR_RANGE, staging nothing, if it
+/// sets bits that aren't fuses.
+///
+/// # Safety
+///
+/// `planner` must be live; `cntl` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn efuse_planner_stage_cntl(planner: *mut EfusePlanner, cntl: *const u8, len: usize) -> i32 {
+    let (planner, bytes) = match (planner.as_mut(), input(cntl, len)) {
+        (Some(p), Some(b)) => (p, b),
+        _ => return EFUSE_ERR_NULL,
+    };
+    match bytes {
+        [cntl] => match planner.api.set_cntl(*cntl) {
+            Ok(()) => EFUSE_OK,
+            Err(_) => EFUSE_ERR_RANGE,
+        },
+        _ => EFUSE_ERR_LENGTH,
+    }
+}
+
+/// Validate the staged state against the fused state, rendering the outcome as NUL-terminated
+/// text into `report`. Returns the validation status, or EFUSE_ERR_BUFFER_TOO_SMALL with the
+/// length needed (NUL included) stored through `written`.
+///
+/// # Safety
+///
+/// `planner` must be live; `report` must point to `len` writable bytes; `written` may be null.
+#[no_mangle]
+pub unsafe extern "C" fn efuse_planner_validate(planner: *mut EfusePlanner, report: *mut u8, len: usize, written: *mut usize) -> i32 {
+    let (planner, mut out) = match (planner.as_mut(), output(report, len)) {
+        (Some(p), Some(o)) => (p, o),
+        _ => return EFUSE_ERR_NULL,
+    };
+    let result: Result<ValidationReport, EfuseError> = planner.api.validate();
+    let _ = render(&mut out, &result);
+    out.put(&[0]);
+    match out.finish(written) {
+        EFUSE_OK => result.map_or_else(|e| refusal(&e), |_| EFUSE_OK),
+        e => e,
+    }
+}
+
+/// Compile the DR words a burn of the staged state would shift, as 8-byte little-endian
+/// values in the order they're shifted. The commit sequence isn't included. Nothing is
+/// written if the staged state fails validation.
+///
+/// # Safety
+///
+/// `planner` must be live; `words` must point to `len` writable bytes; `written` may be null.
+#[no_mangle]
+pub unsafe extern "C" fn efuse_planner_burn_words(planner: *mut EfusePlanner, words: *mut u8, len: usize, written: *mut usize) -> i32 {
+    let (planner, mut out) = match (planner.as_mut(), output(words, len)) {
+        (Some(p), Some(o)) => (p, o),
+        _ => return EFUSE_ERR_NULL,
+    };
+    if let Err(e) = planner.api.validate() {
+        return refusal(&e);
+    }
+    for word in planner.api.program_words() {
+        out.put(&word.value.to_le_bytes());
+    }
+    out.finish(written)
+}
@@ -0,0 +1,385 @@
+//! JTAG over an FTDI MPSSE adapter, for host-side provisioning
+//!
+//! MpssePhy drives the JTAG pins of an FT2232H/FT232H channel in MPSSE mode: TCK on ADBUS0,
+//! TDI on ADBUS1, TDO on ADBUS2 and TMS on ADBUS3, as every FTDI JTAG cable wires them. Data
+//! goes out on the falling edge of TCK and TDO is sampled on the rising edge, LSB first, using
+//! the clock-data commands of FTDI AN_108: 0x39/0x3B for bytes and bits of TDI with TMS low,
+//! 0x6B for cycles that need TMS high. shift_bulk() packs a whole DR leg into one USB write and
+//! one read; sync() is a round trip per cycle.
+//!
+//! The MPSSE command stream goes through an MpsseLink. FtdiDevice is the link to a real
+//! adapter, over libusb; anything else that speaks MPSSE, a model in a test included, can stand
+//! in for it. A failed transfer fails the cycle with PhyError::Transport, which leaves the
+//! JtagMach desynced until it's reset; the cause is kept for last_error(), and the phy carries
+//! on with the next cycle, so unplugging and replugging the cable doesn't need a new phy.
+
+use alloc::vec::Vec;
+use std::time::{Duration, Instant};
+
+use jtag::*;
+
+/// clock data bytes in and out, LSB first, out on -ve edge and in on +ve edge
+pub const MPSSE_BYTES_OUT_IN: u8 = 0x39;
+/// as MPSSE_BYTES_OUT_IN, for 1 to 8 bits
+pub const MPSSE_BITS_OUT_IN: u8 = 0x3B;
+/// clock 1 to 7 bits out on TMS, reading TDO; bit 7 of the data byte is held on TDI
+pub const MPSSE_TMS_OUT_IN: u8 = 0x6B;
+/// set the ADBUS pins' levels and directions
+pub const MPSSE_SET_LOW: u8 = 0x80;
+/// read the ADBUS pins
+pub const MPSSE_GET_LOW: u8 = 0x81;
+/// connect TDI to TDO inside the chip
+pub const MPSSE_LOOPBACK_ON: u8 = 0x84;
+pub const MPSSE_LOOPBACK_OFF: u8 = 0x85;
+/// set the TCK divisor
+pub const MPSSE_SET_DIVISOR: u8 = 0x86;
+/// send what has been read back now, rather than when the latency timer runs out
+pub const MPSSE_SEND_IMMEDIATE: u8 = 0x87;
+/// clock from 60 MHz rather than 12 MHz
+pub const MPSSE_DIV5_OFF: u8 = 0x8A;
+pub const MPSSE_THREE_PHASE_OFF: u8 = 0x8D;
+pub const MPSSE_ADAPTIVE_OFF: u8 = 0x97;
+/// what the MPSSE answers an opcode it doesn't know with, followed by the opcode
+pub const MPSSE_BAD_COMMAND: u8 = 0xFA;
+
+/// ADBUS pins
+const PIN_TCK: u8 = 0x01;
+const PIN_TDI: u8 = 0x02;
+const PIN_TDO: u8 = 0x04;
+const PIN_TMS: u8 = 0x08;
+
+/// the MPSSE clock with divide-by-5 off
+const BASE_CLOCK_HZ: u32 = 60_000_000;
+
+/// Why a transfer with the adapter failed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FtdiError {
+    /// no adapter with the VID, PID and serial asked for is attached
+    NotFound,
+    /// libusb failed the transfer; a cable pulled mid-burn ends up here
+    Usb(rusb::Error),
+    /// the adapter didn't send back all it should have in time
+    Timeout,
+    /// the adapter didn't answer as an MPSSE does: it isn't in MPSSE mode, or the command
+    /// stream and the replies have come apart
+    Desync,
+    /// the TCK frequency asked for can't be made
+    Frequency(u32),
+}
+
+impl From<rusb::Error> for FtdiError {
+    fn from(e: rusb::Error) -> Self {
+        match e {
+            rusb::Error::Timeout => FtdiError::Timeout,
+            e => FtdiError::Usb(e),
+        }
+    }
+}
+
+/// A byte pipe to an MPSSE
+pub trait MpsseLink {
+    /// sends MPSSE commands
+    fn write(&mut self, data: &[u8]) -> Result<(), FtdiError>;
+    /// fills all of `data` with what the MPSSE sent back, or fails
+    fn read(&mut self, data: &mut [u8]) -> Result<(), FtdiError>;
+}
+
+/// An FTDI adapter's first channel, opened over libusb in MPSSE mode
+pub struct FtdiDevice {
+    handle: rusb::DeviceHandle<rusb::Context>,
+    timeout: Duration,
+}
+
+impl FtdiDevice {
+    /// the FT2232H's VID and PID, as shipped
+    pub const FT2232H: (u16, u16) = (0x0403, 0x6010);
+
+    const INTERFACE: u8 = 0;
+    /// wIndex of the channel's vendor requests
+    const INDEX: u16 = 1;
+    const EP_OUT: u8 = 0x02;
+    const EP_IN: u8 = 0x81;
+    /// high speed bulk packets, each read back with two modem status bytes in front
+    const PACKET: usize = 512;
+
+    const SIO_RESET: u8 = 0x00;
+    const SIO_SET_LATENCY_TIMER: u8 = 0x09;
+    const SIO_SET_BITMODE: u8 = 0x0B;
+    const SIO_RESET_PURGE_RX: u16 = 1;
+    const SIO_RESET_PURGE_TX: u16 = 2;
+    const BITMODE_RESET: u16 = 0x0000;
+    const BITMODE_MPSSE: u16 = 0x0200;
+
+    /// Opens the first adapter with `vid` and `pid`, and `serial` if given, and puts its first
+    /// channel in MPSSE mode. The kernel's serial driver is detached from the channel where
+    /// the platform allows.
+    pub fn open(vid: u16, pid: u16, serial: Option<&str>) -> Result<Self, FtdiError> {
+        use rusb::UsbContext;
+
+        let context = rusb::Context::new()?;
+        for device in context.devices()?.iter() {
+            let descriptor = device.device_descriptor()?;
+            if descriptor.vendor_id() != vid || descriptor.product_id() != pid {
+                continue;
+            }
+            // an adapter another process holds, or one we may not open, isn't the one
+            let handle = match device.open() {
+                Ok(handle) => handle,
+                Err(_) => continue,
+            };
+            if let Some(serial) = serial {
+                if handle.read_serial_number_string_ascii(&descriptor).ok().as_deref() != Some(serial) {
+                    continue;
+                }
+            }
+            let mut ftdi = FtdiDevice { handle, timeout: Duration::from_millis(1000) };
+            ftdi.init()?;
+            return Ok(ftdi);
+        }
+        Err(FtdiError::NotFound)
+    }
+
+    /// How long a transfer may take before it fails with FtdiError::Timeout
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    fn control(&mut self, request: u8, value: u16) -> Result<(), FtdiError> {
+        let request_type: u8 = rusb::request_type(rusb::Direction::Out, rusb::RequestType::Vendor, rusb::Recipient::Device);
+        self.handle.write_control(request_type, request, value, FtdiDevice::INDEX, &[], self.timeout)?;
+        Ok(())
+    }
+
+    fn init(&mut self) -> Result<(), FtdiError> {
+        // not every platform can detach drivers; where it can't, none is attached
+        let _ = self.handle.set_auto_detach_kernel_driver(true);
+        self.handle.claim_interface(FtdiDevice::INTERFACE)?;
+        self.control(FtdiDevice::SIO_RESET, 0)?;
+        self.control(FtdiDevice::SIO_RESET, FtdiDevice::SIO_RESET_PURGE_RX)?;
+        self.control(FtdiDevice::SIO_RESET, FtdiDevice::SIO_RESET_PURGE_TX)?;
+        self.control(FtdiDevice::SIO_SET_LATENCY_TIMER, 1)?;
+        self.control(FtdiDevice::SIO_SET_BITMODE, FtdiDevice::BITMODE_RESET)?;
+        self.control(FtdiDevice::SIO_SET_BITMODE, FtdiDevice::BITMODE_MPSSE)
+    }
+}
+
+impl MpsseLink for FtdiDevice {
+    fn write(&mut self, data: &[u8]) -> Result<(), FtdiError> {
+        let mut sent: usize = 0;
+        while sent < data.len() {
+            sent += self.handle.write_bulk(FtdiDevice::EP_OUT, &data[sent..], self.timeout)?;
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, data: &mut [u8]) -> Result<(), FtdiError> {
+        let deadline: Instant = Instant::now() + self.timeout;
+        let mut packet = [0u8; FtdiDevice::PACKET];
+        let mut got: usize = 0;
+        while got < data.len() {
+            if Instant::now() > deadline {
+                return Err(FtdiError::Timeout);
+            }
+            let len: usize = self.handle.read_bulk(FtdiDevice::EP_IN, &mut packet, self.timeout)?;
+            // the status bytes come with every packet, data or not
+            let payload: &[u8] = packet.get(2..len).unwrap_or(&[]);
+            let take: usize = payload.len().min(data.len() - got);
+            if take < payload.len() {
+                return Err(FtdiError::Desync);
+            }
+            data[got..got + take].copy_from_slice(payload);
+            got += take;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for FtdiDevice {
+    fn drop(&mut self) {
+        // leave the channel as a serial port again
+        let _ = self.control(FtdiDevice::SIO_SET_BITMODE, FtdiDevice::BITMODE_RESET);
+        let _ = self.handle.release_interface(FtdiDevice::INTERFACE);
+    }
+}
+
+/// A JtagPhy on an MPSSE
+pub struct MpssePhy<L: MpsseLink> {
+    link: L,
+    tck_hz: u32,
+    last_error: Option<FtdiError>,
+}
+
+/// A JtagPhy on an FTDI adapter
+pub type FtdiPhy = MpssePhy<FtdiDevice>;
+
+impl FtdiPhy {
+    /// opens an adapter, as FtdiDevice::open() does, and sets it up for JTAG at `tck_hz`
+    pub fn open(vid: u16, pid: u16, serial: Option<&str>, tck_hz: u32) -> Result<Self, FtdiError> {
+        MpssePhy::new(FtdiDevice::open(vid, pid, serial)?, tck_hz)
+    }
+}
+
+impl<L: MpsseLink> MpssePhy<L> {
+    /// Sets up the MPSSE on `link` for JTAG at `tck_hz` or the nearest frequency below it,
+    /// with TMS high and TCK low. Checks first that the MPSSE answers a bad command as it
+    /// should, so a link that isn't in MPSSE mode fails here rather than mid-burn.
+    pub fn new(link: L, tck_hz: u32) -> Result<Self, FtdiError> {
+        let mut phy = MpssePhy { link, tck_hz: 0, last_error: None };
+        phy.link.write(&[0xAA, MPSSE_SEND_IMMEDIATE])?;
+        let mut echo = [0u8; 2];
+        phy.link.read(&mut echo)?;
+        if echo != [MPSSE_BAD_COMMAND, 0xAA] {
+            return Err(FtdiError::Desync);
+        }
+        phy.link.write(&[MPSSE_DIV5_OFF, MPSSE_ADAPTIVE_OFF, MPSSE_THREE_PHASE_OFF, MPSSE_LOOPBACK_OFF,
+            MPSSE_SET_LOW, PIN_TMS, PIN_TCK | PIN_TDI | PIN_TMS])?;
+        phy.set_frequency(tck_hz)?;
+        Ok(phy)
+    }
+
+    pub fn link(&self) -> &L {
+        &self.link
+    }
+
+    pub fn link_mut(&mut self) -> &mut L {
+        &mut self.link
+    }
+
+    /// Sets TCK to `hz` or, as the divisor allows, the nearest frequency below it; returns the
+    /// frequency set. Between 458 Hz and 30 MHz.
+    pub fn set_frequency(&mut self, hz: u32) -> Result<u32, FtdiError> {
+        let half: u32 = BASE_CLOCK_HZ / 2;
+        if hz == 0 || hz > half {
+            return Err(FtdiError::Frequency(hz));
+        }
+        let divisor: u32 = half.div_ceil(hz) - 1;
+        if divisor > u16::MAX as u32 {
+            return Err(FtdiError::Frequency(hz));
+        }
+        self.link.write(&[MPSSE_SET_DIVISOR, divisor as u8, (divisor >> 8) as u8])?;
+        self.tck_hz = half / (divisor + 1);
+        Ok(self.tck_hz)
+    }
+
+    /// the TCK frequency set
+    pub fn frequency(&self) -> u32 {
+        self.tck_hz
+    }
+
+    /// Connects TDI to TDO inside the MPSSE, so what is shifted out comes straight back and
+    /// the cable's devices see nothing; for checking the adapter and the bit order
+    pub fn set_loopback(&mut self, on: bool) -> Result<(), FtdiError> {
+        self.link.write(&[if on { MPSSE_LOOPBACK_ON } else { MPSSE_LOOPBACK_OFF }])
+    }
+
+    /// why the last cycle to fail with PhyError::Transport failed, if one has
+    pub fn last_error(&self) -> Option<FtdiError> {
+        self.last_error
+    }
+
+    fn failed(&mut self, e: FtdiError) -> PhyError {
+        self.last_error = Some(e);
+        PhyError::Transport
+    }
+
+    /// Sends `commands`, ending with SEND_IMMEDIATE, and reads back `reply.len()` bytes
+    fn exchange(&mut self, commands: &mut Vec<u8>, reply: &mut [u8]) -> Result<(), PhyError> {
+        commands.push(MPSSE_SEND_IMMEDIATE);
+        let result: Result<(), FtdiError> = self.link.write(commands).and_then(|_| self.link.read(reply));
+        result.map_err(|e| self.failed(e))
+    }
+}
+
+/// Appends to `commands` the MPSSE commands that shift `nbits` cycles of `tdi` and `tms`, and
+/// returns how to pick TDO out of the reply: per command, its reply bytes and the bits in them
+fn encode(tdi: &[u8], tms: &[u8], nbits: usize, commands: &mut Vec<u8>, replies: &mut Vec<(usize, usize)>) {
+    let bit = |bytes: &[u8], n: usize| (bytes[n / 8] >> (n % 8)) & 0x1 == 1;
+    let mut n: usize = 0;
+    while n < nbits {
+        if bit(tms, n) {
+            // one cycle per command, so a run of TMS high needs no TDI of its own
+            commands.extend_from_slice(&[MPSSE_TMS_OUT_IN, 0, ((bit(tdi, n) as u8) << 7) | 1]);
+            replies.push((1, 1));
+            n += 1;
+            continue;
+        }
+        // a run of cycles with TMS low: whole bytes, then the bits left over
+        let run: usize = (n..nbits).take_while(|&k| !bit(tms, k)).count();
+        let pack = |from: usize, len: usize| (0..len).filter(|&k| bit(tdi, from + k)).fold(0u8, |byte, k| byte | 1 << k);
+        let bytes: usize = run / 8;
+        let mut done: usize = 0;
+        while done < bytes {
+            // a command takes up to 64k bytes
+            let len: usize = (bytes - done).min(0x1_0000);
+            commands.extend_from_slice(&[MPSSE_BYTES_OUT_IN, (len - 1) as u8, ((len - 1) >> 8) as u8]);
+            commands.extend((done..done + len).map(|b| pack(n + b * 8, 8)));
+            replies.push((len, len * 8));
+            done += len;
+        }
+        let rest: usize = run - bytes * 8;
+        if rest > 0 {
+            commands.extend_from_slice(&[MPSSE_BITS_OUT_IN, (rest - 1) as u8, pack(n + bytes * 8, rest)]);
+            replies.push((1, rest));
+        }
+        n += run;
+    }
+}
+
+/// Unpacks the TDO of the commands encode() made from `reply` into `tdo_out`
+fn decode(reply: &[u8], replies: &[(usize, usize)], tdo_out: &mut [u8]) {
+    let mut at: usize = 0;
+    let mut n: usize = 0;
+    for &(len, bits) in replies.iter() {
+        for k in 0..bits {
+            // bit replies are shifted in from the top, the first bit ending up lowest
+            let tdo: bool = if bits == len * 8 {
+                (reply[at + k / 8] >> (k % 8)) & 0x1 == 1
+            } else {
+                (reply[at] >> (8 - bits + k)) & 0x1 == 1
+            };
+            if tdo {
+                tdo_out[n / 8] |= 1 << (n % 8);
+            } else {
+                tdo_out[n / 8] &= !(1 << (n % 8));
+            }
+            n += 1;
+        }
+        at += len;
+    }
+}
+
+impl<L: MpsseLink> JtagPhy for MpssePhy<L> {
+    fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+        self.try_sync(tdi, tms).unwrap_or(false)
+    }
+
+    /// sets the pins as given, without the MPSSE clocking them, and reads TDO
+    fn nosync(&mut self, tdi: bool, tms: bool, tck: bool) -> bool {
+        let level: u8 = [(tck, PIN_TCK), (tdi, PIN_TDI), (tms, PIN_TMS)].iter().filter(|&&(on, _)| on).fold(0, |level, &(_, pin)| level | pin);
+        let mut commands: Vec<u8> = alloc::vec![MPSSE_SET_LOW, level, PIN_TCK | PIN_TDI | PIN_TMS, MPSSE_GET_LOW];
+        let mut pins = [0u8; 1];
+        self.exchange(&mut commands, &mut pins).is_ok() && pins[0] & PIN_TDO != 0
+    }
+
+    fn pause(&mut self, us: u32) {
+        std::thread::sleep(Duration::from_micros(us as u64));
+    }
+
+    fn try_sync(&mut self, tdi: bool, tms: bool) -> Result<bool, PhyError> {
+        let mut tdo = [0u8; 1];
+        self.shift_bulk(&[tdi as u8], &[tms as u8], &mut tdo, 1)?;
+        Ok(tdo[0] & 0x1 == 1)
+    }
+
+    /// all `nbits` in one write and one read
+    fn shift_bulk(&mut self, tdi: &[u8], tms: &[u8], tdo_out: &mut [u8], nbits: usize) -> Result<usize, PhyError> {
+        let mut commands: Vec<u8> = Vec::new();
+        let mut replies: Vec<(usize, usize)> = Vec::new();
+        encode(tdi, tms, nbits, &mut commands, &mut replies);
+        let mut reply: Vec<u8> = alloc::vec![0; replies.iter().map(|&(len, _)| len).sum()];
+        self.exchange(&mut commands, &mut reply)?;
+        decode(&reply, &replies, tdo_out);
+        Ok(nbits)
+    }
+}
@@ -0,0 +1,101 @@
+//! A character map of the 13 banks, for chasing bank-level problems by eye
+//!
+//! One line per bank, one character per fuse position, bit 29 on the left:
+//!
+//! ```text
+//! bank kind   ecc    data                       fields                decode
+//!    1 key    11E+!1 ........ ........ 1.....+! key[2:0]              ecc ok
+//! ```
+//!
+//! * `.` a data fuse that's blank and stays blank
+//! * `E` an ECC fuse that's blank and stays blank
+//! * `1` a fuse that's burned
+//! * `+` a fuse the burn plan would blow
+//! * `!` a burned fuse the staged state wants blank, which no burn can do
+//! * ` ` a position that isn't a fuse, in the CNTL bank
+//!
+//! The fused side comes from the banks as of the last fetch and their banks_logical() decode;
+//! the staged side from the same planned USER and bank images that burn() works from. The
+//! output is at most FUSE_MAP_LEN bytes, and nothing is allocated.
+
+use core::fmt;
+
+use crate::layout::*;
+
+/// longest line written, newline included
+pub const FUSE_MAP_LINE: usize = 82;
+/// most bytes write_fuse_map() writes: a header, a line per bank and a legend
+pub const FUSE_MAP_LEN: usize = FUSE_MAP_LINE * (FUSE_BANKS + 2);
+
+/// fuse positions shown per bank, bits 29:0
+const POSITIONS: u32 = 30;
+
+fn kind_name(kind: BankKind) -> &'static str {
+    match kind {
+        BankKind::Cntl => "cntl",
+        BankKind::Key => "key",
+        BankKind::Shared => "shared",
+        BankKind::User => "user",
+    }
+}
+
+fn decode_name(view: &BankView) -> &'static str {
+    match view.ecc_status {
+        EccStatus::Valid => "ecc ok",
+        EccStatus::Mismatch { .. } => "ecc BAD",
+        EccStatus::Duplicated { primary, copy } if primary == copy => "copies agree",
+        EccStatus::Duplicated { .. } => "copies differ",
+    }
+}
+
+/// key bytes held by key banks 1-10
+const KEY_FIELDS: [&str; 10] = [
+    "key[2:0]", "key[5:3]", "key[8:6]", "key[11:9]", "key[14:12]",
+    "key[17:15]", "key[20:18]", "key[23:21]", "key[26:24]", "key[29:27]",
+];
+
+fn fields(index: usize) -> &'static str {
+    match BankKind::of(index) {
+        BankKind::Cntl => "cntl[5:0] copy[19:14]",
+        BankKind::Key => KEY_FIELDS[index - 1],
+        BankKind::Shared => "key[31:30] user[7:0]",
+        BankKind::User => "user[31:8]",
+    }
+}
+
+/// the character for fuse position `bit` of bank `index`
+fn position(index: usize, bit: u32, fused: u32, requested: u32, conflicts: u32) -> char {
+    let mask: u32 = 1 << bit;
+    if bank_fuses(index) & mask == 0 {
+        ' '
+    } else if conflicts & mask != 0 {
+        '!'
+    } else if fused & mask != 0 {
+        '1'
+    } else if requested & mask != 0 {
+        '+'
+    } else if bit >= 24 {
+        'E'
+    } else {
+        '.'
+    }
+}
+
+/// Writes the map. `fused` are the banks as fetched, `requested` the fuses the burn plan would
+/// blow and `conflicts` the burned fuses the staged images want blank.
+pub(crate) fn write<W: fmt::Write>(out: &mut W, fused: &[u32; FUSE_BANKS], requested: &[u32; FUSE_BANKS], conflicts: &[u32; FUSE_BANKS]) -> fmt::Result {
+    writeln!(out, "bank kind   ecc    data                       fields                decode")?;
+    let views: [BankView; FUSE_BANKS] = banks_logical(fused);
+    for (index, view) in views.iter().enumerate() {
+        write!(out, "{:>4} {:<6} ", index, kind_name(view.kind))?;
+        for bit in (0..POSITIONS).rev() {
+            if bit == 23 || bit == 15 || bit == 7 {
+                out.write_char(' ')?;
+            }
+            out.write_char(position(index, bit, fused[index], requested[index], conflicts[index]))?;
+        }
+        out.write_char(' ')?;
+        writeln!(out, "{:<21} {}", fields(index), decode_name(view))?;
+    }
+    writeln!(out, ". blank  E blank ECC  1 burned  + to burn  ! burned but staged blank")
+}
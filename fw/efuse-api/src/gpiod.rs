@@ -0,0 +1,199 @@
+//! Bit-banged JTAG on Linux GPIOs, for fixtures that wire the pins straight to a host
+//!
+//! GpiodPhy toggles four GPIO lines, and TRST if there is one, through the GPIO character
+//! device, as libgpiod does. A cycle sets TDI and TMS with TCK low, waits half a period, reads
+//! TDO, which the device has been driving since the last falling edge, then raises TCK for the
+//! device to sample TDI and TMS, waits the other half and drops TCK again. nosync() sets the
+//! three outputs as given and reads TDO, so a caller can clock the TAP by hand.
+//!
+//! Every line goes through a GpioLine. CdevLine is a line of a /dev/gpiochip, requested through
+//! gpio-cdev; a test can stand in lines of its own and check the sequencing without hardware.
+//! A line that fails fails the cycle with PhyError::Transport, the cause kept for
+//! last_error(), as the ftdi module does.
+
+use alloc::string::{String, ToString};
+use std::time::Duration;
+
+use jtag::*;
+
+/// Why a GPIO line couldn't be requested, set or read
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum GpioError {
+    /// the chip couldn't be opened, or the line requested from it
+    Request { offset: u32, message: String },
+    /// the line was requested but setting or reading it failed
+    Access { offset: u32, message: String },
+}
+
+/// One GPIO line, requested as an output or, for TDO, an input
+pub trait GpioLine {
+    fn set(&mut self, high: bool) -> Result<(), GpioError>;
+    fn get(&mut self) -> Result<bool, GpioError>;
+}
+
+/// The line offsets on the chip the JTAG pins are wired to
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct GpiodPins {
+    pub tck: u32,
+    pub tms: u32,
+    pub tdi: u32,
+    pub tdo: u32,
+    /// active low; None if the TAP's TRST isn't wired, or it has none
+    pub trst: Option<u32>,
+}
+
+/// A line of a GPIO character device
+pub struct CdevLine {
+    handle: gpio_cdev::LineHandle,
+    offset: u32,
+}
+
+impl CdevLine {
+    const CONSUMER: &'static str = "efuse-api-jtag";
+
+    /// requests line `offset` of `chip` as an output starting at `high`, or as an input
+    pub fn request(chip: &mut gpio_cdev::Chip, offset: u32, output: Option<bool>) -> Result<Self, GpioError> {
+        let failed = |e: gpio_cdev::Error| GpioError::Request { offset, message: e.to_string() };
+        let (flags, default) = match output {
+            Some(high) => (gpio_cdev::LineRequestFlags::OUTPUT, high as u8),
+            None => (gpio_cdev::LineRequestFlags::INPUT, 0),
+        };
+        let handle = chip.get_line(offset).map_err(failed)?.request(flags, default, CdevLine::CONSUMER).map_err(failed)?;
+        Ok(CdevLine { handle, offset })
+    }
+}
+
+impl GpioLine for CdevLine {
+    fn set(&mut self, high: bool) -> Result<(), GpioError> {
+        self.handle.set_value(high as u8).map_err(|e| GpioError::Access { offset: self.offset, message: e.to_string() })
+    }
+
+    fn get(&mut self) -> Result<bool, GpioError> {
+        self.handle.get_value().map(|value| value != 0).map_err(|e| GpioError::Access { offset: self.offset, message: e.to_string() })
+    }
+}
+
+/// A JtagPhy on GPIO lines
+pub struct GpiodPhy<L: GpioLine> {
+    tck: L,
+    tms: L,
+    tdi: L,
+    tdo: L,
+    trst: Option<L>,
+    half_period: Duration,
+    last_error: Option<GpioError>,
+}
+
+impl GpiodPhy<CdevLine> {
+    /// Requests the `pins` of the GPIO chip at `chip_path`, e.g. /dev/gpiochip0, and sets
+    /// them up as new() does
+    pub fn open(chip_path: &str, pins: GpiodPins, half_period: Duration) -> Result<Self, GpioError> {
+        let mut chip = gpio_cdev::Chip::new(chip_path).map_err(|e| GpioError::Request { offset: pins.tck, message: e.to_string() })?;
+        let tck: CdevLine = CdevLine::request(&mut chip, pins.tck, Some(false))?;
+        let tms: CdevLine = CdevLine::request(&mut chip, pins.tms, Some(true))?;
+        let tdi: CdevLine = CdevLine::request(&mut chip, pins.tdi, Some(false))?;
+        let tdo: CdevLine = CdevLine::request(&mut chip, pins.tdo, None)?;
+        let trst: Option<CdevLine> = match pins.trst {
+            Some(offset) => Some(CdevLine::request(&mut chip, offset, Some(true))?),
+            None => None,
+        };
+        GpiodPhy::new(tck, tms, tdi, tdo, trst, half_period)
+    }
+}
+
+impl<L: GpioLine> GpiodPhy<L> {
+    /// Takes the lines, TDO an input and the rest outputs, and drives TCK low, TMS high, TDI
+    /// low and TRST, if there is one, high. `half_period` is how long TCK stays low and high;
+    /// zero toggles as fast as the lines go.
+    pub fn new(tck: L, tms: L, tdi: L, tdo: L, trst: Option<L>, half_period: Duration) -> Result<Self, GpioError> {
+        let mut phy = GpiodPhy { tck, tms, tdi, tdo, trst, half_period, last_error: None };
+        phy.tck.set(false)?;
+        phy.tms.set(true)?;
+        phy.tdi.set(false)?;
+        if let Some(trst) = phy.trst.as_mut() {
+            trst.set(true)?;
+        }
+        Ok(phy)
+    }
+
+    pub fn half_period(&self) -> Duration {
+        self.half_period
+    }
+
+    pub fn set_half_period(&mut self, half_period: Duration) {
+        self.half_period = half_period;
+    }
+
+    /// Pulses TRST low for a TCK period, resetting the TAP as five cycles of TMS high would.
+    /// Returns false if there's no TRST line.
+    pub fn pulse_trst(&mut self) -> Result<bool, GpioError> {
+        let half_period: Duration = self.half_period;
+        match self.trst.as_mut() {
+            Some(trst) => {
+                trst.set(false)?;
+                GpiodPhy::<L>::wait(half_period * 2);
+                trst.set(true)?;
+                Ok(true)
+            },
+            None => Ok(false),
+        }
+    }
+
+    /// why the last cycle to fail with PhyError::Transport failed, if one has
+    pub fn last_error(&self) -> Option<&GpioError> {
+        self.last_error.as_ref()
+    }
+
+    fn wait(time: Duration) {
+        if time > Duration::ZERO {
+            std::thread::sleep(time);
+        }
+    }
+
+    fn cycle(&mut self, tdi: bool, tms: bool) -> Result<bool, GpioError> {
+        self.tdi.set(tdi)?;
+        self.tms.set(tms)?;
+        GpiodPhy::<L>::wait(self.half_period);
+        let tdo: bool = self.tdo.get()?;
+        self.tck.set(true)?;
+        GpiodPhy::<L>::wait(self.half_period);
+        self.tck.set(false)?;
+        Ok(tdo)
+    }
+
+    fn pins(&mut self, tdi: bool, tms: bool, tck: bool) -> Result<bool, GpioError> {
+        self.tdi.set(tdi)?;
+        self.tms.set(tms)?;
+        self.tck.set(tck)?;
+        self.tdo.get()
+    }
+}
+
+impl<L: GpioLine> JtagPhy for GpiodPhy<L> {
+    fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+        self.try_sync(tdi, tms).unwrap_or(false)
+    }
+
+    /// sets TDI, TMS and then TCK as given, and reads TDO; false if a line fails
+    fn nosync(&mut self, tdi: bool, tms: bool, tck: bool) -> bool {
+        match self.pins(tdi, tms, tck) {
+            Ok(tdo) => tdo,
+            Err(e) => {
+                self.last_error = Some(e);
+                false
+            },
+        }
+    }
+
+    fn pause(&mut self, us: u32) {
+        GpiodPhy::<L>::wait(Duration::from_micros(us as u64));
+    }
+
+    fn try_sync(&mut self, tdi: bool, tms: bool) -> Result<bool, PhyError> {
+        self.cycle(tdi, tms).map_err(|e| {
+            self.last_error = Some(e);
+            PhyError::Transport
+        })
+    }
+}
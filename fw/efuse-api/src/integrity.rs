@@ -0,0 +1,138 @@
+//! Redundant validation verdict
+//!
+//! Whether a plan can be burned is the one decision standing between a glitch and a bricked
+//! device, so it isn't left to a single branch. The verdict is worked out twice, by two pieces
+//! of code that share neither their algorithm nor their copy of the fused banks:
+//!
+//! * per bank: every fused bit must also be set in the bank's target image, i.e. the burn
+//!   only ever flips 0->1;
+//! * by readback: the banks the burn would leave behind (fused OR requested) are decoded the
+//!   way a fetch would decode them, and must carry the intended key, USER and CNTL, with every
+//!   bank's ECC or duplicate copy consistent.
+//!
+//! Neither produces a boolean. Each writes its own sentinel word, and the two paths' words are
+//! complements of each other, for "valid" and for "invalid" alike, so that agreement is
+//! `per_bank ^ readback == !0`. Any other combination, including one path's word being
+//! corrupted into something that is neither of its sentinels, is a ValidationIntegrity error.
+//! A skipped compare in combine() can't turn an invalid verdict into a valid one, because the
+//! valid sentinels are still compared separately afterwards.
+//!
+//! The paths are kept apart from the optimizer as far as stable Rust allows: each is
+//! `#[inline(never)]`, works on its own copy of the banks, and its inputs and sentinel go
+//! through `core::hint::black_box`. That's best effort, not a guarantee; check the disassembly
+//! of release builds when the compiler is upgraded.
+//!
+//! burn() takes the verdict once in validate() and again right before the first unlock word
+//! is shifted; burn_cntl() does the same, with the second taken before each CNTL copy. With the `fault-injection` feature, EfuseApi::inject_fault() corrupts one path's
+//! copy of the banks at one of those two points, for the tests.
+
+use core::hint::black_box;
+
+use crate::device::DeviceFamily;
+use crate::error::EfuseError;
+use crate::layout::{EfuseLogical, FUSE_BANKS};
+use crate::secrets;
+
+const PER_BANK_VALID: u32 = 0x3CA5_96E1;
+const PER_BANK_INVALID: u32 = 0xA55A_0FF0;
+const READBACK_VALID: u32 = !PER_BANK_VALID;
+const READBACK_INVALID: u32 = !PER_BANK_INVALID;
+
+/// Every bank, as the scope of a verdict on the whole intended state
+pub(crate) const ALL_BANKS: u32 = (1 << FUSE_BANKS) - 1;
+
+/// What a burn takes its verdict on before the first unlock word: the banks it requests, and
+/// the banks (a mask of bank indices) whose intended state it answers for
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Guard {
+    pub requested: [u32; FUSE_BANKS],
+    pub scope: u32,
+}
+
+/// The per-bank verdict: no fused bit in a bank in `scope` is missing from its bank's image
+/// of `intended`
+#[inline(never)]
+pub(crate) fn per_bank_verdict<F: DeviceFamily>(fused: &[u32; FUSE_BANKS], intended: &EfuseLogical, scope: u32) -> u32 {
+    let fused: &[u32; FUSE_BANKS] = black_box(fused);
+    let scope: u32 = black_box(scope);
+    let mut stray: u32 = 0;
+    for (index, &bank) in fused.iter().enumerate().filter(|&(index, _)| scope & 1 << index != 0) {
+        stray |= bank & !F::bank_image_ecc(index, intended);
+    }
+    black_box(if stray == 0 { PER_BANK_VALID } else { PER_BANK_INVALID })
+}
+
+/// The readback verdict: the banks in `scope` left after burning `requested` decode to
+/// `intended`, as far as they hold it
+#[inline(never)]
+pub(crate) fn readback_verdict<F: DeviceFamily>(fused: &[u32; FUSE_BANKS], requested: &[u32; FUSE_BANKS], intended: &EfuseLogical, scope: u32) -> u32 {
+    let fused: &[u32; FUSE_BANKS] = black_box(fused);
+    let scope: u32 = black_box(scope);
+    let in_scope = |index: usize| scope & 1 << index != 0;
+    let mut predicted: [u32; FUSE_BANKS] = [0; FUSE_BANKS];
+    for ((p, &f), &r) in predicted.iter_mut().zip(fused.iter()).zip(requested.iter()) {
+        *p = f | r;
+    }
+
+    let mut ok: bool = (0..FUSE_BANKS).filter(|&index| in_scope(index)).all(|index| {
+        predicted[index] & !F::bank_fuses(index) == 0 && F::bank_consistent(index, predicted[index])
+    });
+    // every fuse of the banks in scope, decoded: which key, USER and CNTL bits they hold
+    let mut held: [u32; FUSE_BANKS] = [0; FUSE_BANKS];
+    for (index, bank) in held.iter_mut().enumerate().filter(|&(index, _)| in_scope(index)) {
+        *bank = F::bank_fuses(index);
+    }
+    let held: EfuseLogical = F::from_banks(&held);
+    let mut decoded: EfuseLogical = F::from_banks(&predicted);
+    for ((&read, &byte), &mask) in decoded.key.iter().zip(intended.key.iter()).zip(held.key.iter()) {
+        ok &= (read ^ byte) & mask == 0;
+    }
+    ok &= (decoded.user ^ intended.user) & held.user == 0;
+    ok &= (decoded.cntl ^ intended.cntl) & held.cntl == 0;
+    secrets::wipe_bytes(&mut decoded.key);
+    black_box(if ok { READBACK_VALID } else { READBACK_INVALID })
+}
+
+/// Combines the two sentinels: Ok if both say valid, Invalid if both say invalid, and
+/// ValidationIntegrity otherwise
+pub(crate) fn combine(per_bank: u32, readback: u32) -> Result<(), EfuseError> {
+    let per_bank: u32 = black_box(per_bank);
+    let readback: u32 = black_box(readback);
+    if per_bank ^ readback != !0 {
+        return Err(EfuseError::ValidationIntegrity);
+    }
+    if per_bank == PER_BANK_INVALID && readback == READBACK_INVALID {
+        return Err(EfuseError::Invalid);
+    }
+    if per_bank != PER_BANK_VALID || readback != READBACK_VALID {
+        return Err(EfuseError::ValidationIntegrity);
+    }
+    Ok(())
+}
+
+/// The two ways the verdict is taken
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum VerdictPath {
+    PerBank,
+    Readback,
+}
+
+/// When the verdict is taken
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum VerdictStage {
+    /// in validate()
+    Validate,
+    /// in burn() or burn_cntl(), right before the first unlock word
+    PreUnlock,
+}
+
+/// Flips `flip` in bank `bank` of `path`'s copy of the fused banks, whenever the verdict is
+/// taken at `stage`
+#[cfg(feature = "fault-injection")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct VerdictFault {
+    pub path: VerdictPath,
+    pub stage: VerdictStage,
+    pub bank: usize,
+    pub flip: u32,
+}
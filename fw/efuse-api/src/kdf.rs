@@ -0,0 +1,100 @@
+//! Per-device key derivation
+//!
+//! A device key is HKDF-SHA256 (RFC 5869) of the master secret, salted with the device DNA:
+//! `HKDF(ikm = master, salt = DNA as 8 big-endian bytes, info)`, 32 bytes of output. A leaked
+//! device key then says nothing about the master or about any other device's key.
+//!
+//! HMAC and HKDF are implemented here, on the crate's own SHA-256, rather than pulled in, so
+//! the firmware build doesn't grow a dependency; they're checked against the FIPS 180-2,
+//! RFC 4231 and RFC 5869 vectors in tests/kdf_tests.rs.
+//!
+//! As with keys elsewhere in this crate, nothing holding secret material derives Debug, and
+//! every intermediate buffer is wiped before it goes out of scope. A derived key only ever
+//! leaves this module into the staged state; callers get its KeyFingerprint.
+
+use core::fmt;
+
+use crate::keyhex::KeyBytes;
+pub use crate::sha256::{sha256, Sha256};
+use crate::sha256::{wipe, BLOCK};
+
+/// HMAC-SHA256 of the concatenation of `parts`
+pub fn hmac_sha256(key: &[u8], parts: &[&[u8]]) -> [u8; 32] {
+    let mut block: [u8; BLOCK] = [0; BLOCK];
+    if key.len() > BLOCK {
+        let mut digest: [u8; 32] = sha256(key);
+        block[..32].copy_from_slice(&digest);
+        wipe(&mut digest);
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut pad: [u8; BLOCK] = [0; BLOCK];
+    for (p, k) in pad.iter_mut().zip(block.iter()) {
+        *p = k ^ 0x36;
+    }
+    let mut inner: Sha256 = Sha256::new();
+    inner.update(&pad);
+    for part in parts.iter() {
+        inner.update(part);
+    }
+    let mut inner_digest: [u8; 32] = inner.finish();
+
+    for (p, k) in pad.iter_mut().zip(block.iter()) {
+        *p = k ^ 0x5c;
+    }
+    let mut outer: Sha256 = Sha256::new();
+    outer.update(&pad);
+    outer.update(&inner_digest);
+
+    wipe(&mut block);
+    wipe(&mut pad);
+    wipe(&mut inner_digest);
+    outer.finish()
+}
+
+/// Fills `okm` with HKDF-SHA256 output; at most 255 * 32 bytes can be produced.
+pub fn hkdf_sha256(salt: &[u8], ikm: &[u8], info: &[u8], okm: &mut [u8]) {
+    assert!(okm.len() <= 255 * 32);
+    let mut prk: [u8; 32] = hmac_sha256(salt, &[ikm]);
+    let mut t: [u8; 32] = [0; 32];
+    let mut t_len: usize = 0;
+    for (i, chunk) in okm.chunks_mut(32).enumerate() {
+        t = hmac_sha256(&prk, &[&t[..t_len], info, &[i as u8 + 1]]);
+        t_len = t.len();
+        chunk.copy_from_slice(&t[..chunk.len()]);
+    }
+    wipe(&mut prk);
+    wipe(&mut t);
+}
+
+/// The key for the device with DNA `dna`, derived from `master`
+pub(crate) fn derive_device_key(master: &KeyBytes, dna: u64, info: &[u8]) -> KeyBytes {
+    let mut key: KeyBytes = [0; 32];
+    hkdf_sha256(&dna.to_be_bytes(), master, info, &mut key);
+    key
+}
+
+/// Identifies a key without revealing it: the first 8 bytes of its SHA-256
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct KeyFingerprint(pub [u8; 8]);
+
+impl KeyFingerprint {
+    pub fn of(key: &KeyBytes) -> Self {
+        let mut digest: [u8; 32] = sha256(key);
+        let mut fingerprint: [u8; 8] = [0; 8];
+        fingerprint.copy_from_slice(&digest[..8]);
+        wipe(&mut digest);
+        KeyFingerprint(fingerprint)
+    }
+}
+
+/// lowercase hex, as recorded in provisioning logs
+impl fmt::Display for KeyFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for b in self.0.iter() {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+//! Heuristics that catch obviously non-random keys
+//!
+//! These aren't an entropy estimate: a key that passes can still be weak. They exist to catch
+//! the mistakes that actually happen in bring-up scripts, such as a default-filled key buffer
+//! that was never overwritten. A random 32-byte key trips any of them with vanishingly small
+//! probability.
+//!
+//! ct_eq_32() compares two keys in constant time, for checking a fetched key against the
+//! expected one without leaking through timing how many leading bytes matched.
+
+use core::hint::black_box;
+
+/// Fewest distinct byte values a key may contain (a random key averages about 30)
+pub const MIN_DISTINCT_BYTES: usize = 16;
+/// Shortest run of 0x00 or 0xFF bytes that is reported
+pub const MAX_FILL_RUN: usize = 4;
+
+/// Why a key looks suspicious
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WeakKeyReason {
+    /// every byte is `byte`
+    AllIdentical { byte: u8 },
+    /// the key repeats every `period` bytes
+    Periodic { period: usize },
+    /// `len` consecutive bytes are `byte`, which is 0x00 or 0xFF
+    FillRun { byte: u8, len: usize },
+    /// only `distinct` different byte values occur
+    FewDistinct { distinct: usize },
+}
+
+pub fn all_identical(key: &[u8; 32]) -> bool {
+    key.iter().all(|&b| b == key[0])
+}
+
+/// true if the key repeats with the given period
+pub fn has_period(key: &[u8; 32], period: usize) -> bool {
+    key.iter().zip(key[period..].iter()).all(|(a, b)| a == b)
+}
+
+/// the longest run of 0x00 or 0xFF bytes, as (byte, length); None if there are none
+pub fn longest_fill_run(key: &[u8; 32]) -> Option<(u8, usize)> {
+    let mut longest: Option<(u8, usize)> = None;
+    let mut run: usize = 0;
+    for (i, &b) in key.iter().enumerate() {
+        if b != 0x00 && b != 0xFF {
+            run = 0;
+            continue;
+        }
+        run = if i > 0 && key[i - 1] == b { run + 1 } else { 1 };
+        match longest {
+            Some((_, len)) if len >= run => {},
+            _ => longest = Some((b, run)),
+        }
+    }
+    longest
+}
+
+/// number of different byte values in the key
+pub fn distinct_bytes(key: &[u8; 32]) -> usize {
+    let mut seen: [bool; 256] = [false; 256];
+    for &b in key.iter() {
+        seen[b as usize] = true;
+    }
+    seen.iter().filter(|&&s| s).count()
+}
+
+/// Runs every heuristic, returning the first that trips.
+pub fn weak_key_reason(key: &[u8; 32]) -> Option<WeakKeyReason> {
+    if all_identical(key) {
+        return Some(WeakKeyReason::AllIdentical { byte: key[0] });
+    }
+    for &period in [4, 8].iter() {
+        if has_period(key, period) {
+            return Some(WeakKeyReason::Periodic { period });
+        }
+    }
+    if let Some((byte, len)) = longest_fill_run(key) {
+        if len >= MAX_FILL_RUN {
+            return Some(WeakKeyReason::FillRun { byte, len });
+        }
+    }
+    let distinct: usize = distinct_bytes(key);
+    if distinct < MIN_DISTINCT_BYTES {
+        return Some(WeakKeyReason::FewDistinct { distinct });
+    }
+    None
+}
+
+/// true if the keys are equal, in constant time: every byte pair is looked at, and the
+/// differences are ORed together with no branch on them until the single test at the end
+#[inline(never)]
+pub fn ct_eq_32(a: &[u8; 32], b: &[u8; 32]) -> bool {
+    let (a, b) = (black_box(a), black_box(b));
+    let mut diff: u8 = 0;
+    for i in 0..32 {
+        diff |= a[i] ^ b[i];
+    }
+    black_box(diff) == 0
+}
@@ -0,0 +1,141 @@
+//! Hex encoding of keys, for the console and for key files
+//!
+//! Keys are written the way Xilinx tools write them in .nky files and the way the console
+//! prints them: most significant byte first. The most significant byte is the last byte of
+//! the key array, i.e. the top byte of fuse bank 11, so the first two hex digits of a key
+//! string end up in `key[31]`. `KeyOrder` makes that explicit for callers that need the
+//! other order.
+
+use core::fmt;
+
+pub type KeyBytes = [u8; 32];
+
+/// Number of hex digits in a key
+pub const KEY_DIGITS: usize = 64;
+
+/// Order of the bytes in a hex string, relative to the key array
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum KeyOrder {
+    /// the first digits are `key[31]`, as in .nky files and on the console
+    MsbFirst,
+    /// the first digits are `key[0]`, i.e. the order of the array in memory
+    LsbFirst,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HexCase {
+    Lower,
+    Upper,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HexError {
+    /// `found`, at byte offset `position` of the input, isn't a hex digit or separator
+    InvalidDigit { position: usize, found: char },
+    /// the input holds `digits` hex digits instead of KEY_DIGITS
+    WrongLength { digits: usize },
+    /// a key file had no "Key 0" line
+    MissingKey,
+}
+
+/// Parse a key written most significant byte first. See parse_ordered().
+pub fn parse(s: &str) -> Result<KeyBytes, HexError> {
+    parse_ordered(s, KeyOrder::MsbFirst)
+}
+
+/// Parse a key of exactly 64 hex digits in either case. Leading and trailing whitespace and a
+/// "0x" prefix are ignored, as are spaces and underscores between digits.
+pub fn parse_ordered(s: &str, order: KeyOrder) -> Result<KeyBytes, HexError> {
+    let trimmed: &str = s.trim_start();
+    let mut offset: usize = s.len() - trimmed.len();
+    let body: &str = if trimmed.starts_with("0x") || trimmed.starts_with("0X") {
+        offset += 2;
+        &trimmed[2..]
+    } else {
+        trimmed
+    };
+
+    let mut key: KeyBytes = [0; 32];
+    let mut digits: usize = 0;
+    for (position, c) in body.char_indices() {
+        if c.is_whitespace() || c == '_' {
+            continue;
+        }
+        let nibble: u8 = match c.to_digit(16) {
+            Some(n) => n as u8,
+            None => return Err(HexError::InvalidDigit { position: offset + position, found: c }),
+        };
+        if digits < KEY_DIGITS {
+            let index: usize = match order {
+                KeyOrder::MsbFirst => 31 - digits / 2,
+                KeyOrder::LsbFirst => digits / 2,
+            };
+            let shift: u8 = if digits & 1 == 0 { 4 } else { 0 };
+            key[index] |= nibble << shift;
+        }
+        digits += 1;
+    }
+    if digits != KEY_DIGITS {
+        return Err(HexError::WrongLength { digits });
+    }
+    Ok(key)
+}
+
+/// Write `key` as 64 hex digits, most significant byte first. This writes the key in the
+/// clear; only call it where the key is meant to be shown.
+pub fn format<W: fmt::Write>(key: &KeyBytes, out: &mut W, case: HexCase) -> fmt::Result {
+    format_ordered(key, out, case, KeyOrder::MsbFirst)
+}
+
+/// Write `key` as 64 hex digits in the given byte order
+pub fn format_ordered<W: fmt::Write>(key: &KeyBytes, out: &mut W, case: HexCase, order: KeyOrder) -> fmt::Result {
+    for i in 0..32 {
+        let byte: u8 = match order {
+            KeyOrder::MsbFirst => key[31 - i],
+            KeyOrder::LsbFirst => key[i],
+        };
+        match case {
+            HexCase::Lower => write!(out, "{:02x}", byte)?,
+            HexCase::Upper => write!(out, "{:02X}", byte)?,
+        }
+    }
+    Ok(())
+}
+
+/// Write `key` into `out` as 64 hex digits in the given byte order, for callers without a
+/// fmt::Write to hand, e.g. a fixed buffer going out over a serial link
+pub fn format_into(key: &KeyBytes, out: &mut [u8; KEY_DIGITS], case: HexCase, order: KeyOrder) {
+    let digits: &[u8; 16] = match case {
+        HexCase::Lower => b"0123456789abcdef",
+        HexCase::Upper => b"0123456789ABCDEF",
+    };
+    for (i, pair) in out.chunks_exact_mut(2).enumerate() {
+        let byte: u8 = match order {
+            KeyOrder::MsbFirst => key[31 - i],
+            KeyOrder::LsbFirst => key[i],
+        };
+        pair[0] = digits[(byte >> 4) as usize];
+        pair[1] = digits[(byte & 0xF) as usize];
+    }
+}
+
+/// Write `key` as a minimal Xilinx .nky file for `device` (e.g. "xc7s50"): the Device line and
+/// Key 0, which is all eFUSE programming reads. This writes the key in the clear.
+pub fn write_nky<W: fmt::Write>(key: &KeyBytes, device: &str, out: &mut W) -> fmt::Result {
+    writeln!(out, "Device {};", device)?;
+    write!(out, "Key 0 ")?;
+    format(key, out, HexCase::Upper)?;
+    writeln!(out, ";")
+}
+
+/// Extract the AES key from the text of a Xilinx .nky file (the "Key 0" line)
+pub fn key_from_nky(nky: &str) -> Result<KeyBytes, HexError> {
+    for line in nky.lines() {
+        let mut words = line.split_whitespace();
+        if words.next() == Some("Key") && words.next() == Some("0") {
+            let value: &str = words.next().ok_or(HexError::MissingKey)?;
+            return parse(value.trim_end_matches(';'));
+        }
+    }
+    Err(HexError::MissingKey)
+}
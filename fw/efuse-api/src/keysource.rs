@@ -0,0 +1,166 @@
+//! Which AES key the device will decrypt its bitstream with
+//!
+//! The answer comes from three places. The CNTL fuses: with CFG_AES_ONLY blown, only bitstreams
+//! encrypted with the eFUSE key are accepted, whatever else is on the device. The eFUSE key
+//! itself, if it can still be read. And the configuration logic's STAT and CTL0 registers,
+//! which tell how the bitstream that's running now was loaded: a secured part whose CTL0 has
+//! EFUSE_KEY clear was decrypted with the BBRAM key, so there is one.
+//!
+//! There's no way to read the BBRAM key, or even whether there is one, so BBRAM presence is
+//! only known when the running bitstream proves it; otherwise it's Unknown.
+//!
+//! Configuration registers are read as in UG470's "Reading a configuration register through
+//! JTAG": sync, a type-1 read under CFG_IN, the word out under CFG_OUT, then desync. Words go
+//! in and come out MSB first.
+
+use jtag::*;
+
+use crate::layout::*;
+use crate::sequences::Ir;
+use crate::{EfuseError, EfusePhy, Phase};
+use crate::device::{DeviceProfile, SevenSeries};
+
+/// STAT: the device is in secure mode, i.e. the running bitstream was decrypted
+pub const STAT_PART_SECURED: u32 = 1 << 1;
+/// STAT: the device finished configuring and released the startup sequence
+pub const STAT_DONE: u32 = 1 << 14;
+/// STAT: the last decryption failed
+pub const STAT_DEC_ERROR: u32 = 1 << 16;
+/// CTL0: the bitstream selected the eFUSE key rather than the BBRAM key
+pub const CTL0_EFUSE_KEY: u32 = 1 << 31;
+/// CTL0: the security level the bitstream set; 01 disables readback, 1x reconfiguration too
+pub const CTL0_SBITS: u32 = 0b11 << 4;
+
+/// configuration register addresses
+pub const REG_CTL0: u8 = 0x05;
+pub const REG_STAT: u8 = 0x07;
+
+const SYNC: u32 = 0xAA99_5566;
+const NOOP: u32 = 0x2000_0000;
+/// type-1 write of one word to CMD
+const WRITE_CMD: u32 = 0x3000_8001;
+const CMD_DESYNC: u32 = 0x0000_000D;
+
+/// type-1 packet header reading one word from `reg`
+fn read_header(reg: u8) -> u32 {
+    0x2800_0001 | ((reg as u32 & 0x1F) << 13)
+}
+
+/// What the running bitstream says about the BBRAM key
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BbramKey {
+    /// the running bitstream was decrypted with it
+    Present,
+    /// can't be told from here
+    Unknown,
+}
+
+/// Key the device will use at its next boot
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum KeySource {
+    /// CFG_AES_ONLY is blown: only bitstreams encrypted with the eFUSE key load
+    EfuseForced = 1,
+    /// there's a BBRAM key, and bitstreams that select it load with it
+    BbramPresent = 2,
+    /// there's an eFUSE key and no sign of a BBRAM key, but plaintext bitstreams still load
+    EfuseByDefault = 3,
+    /// neither key is known to be there
+    NoKey = 4,
+}
+
+impl KeySource {
+    pub fn from_code(code: u8) -> Option<KeySource> {
+        use KeySource::*;
+        [EfuseForced, BbramPresent, EfuseByDefault, NoKey].iter().copied().find(|s| *s as u8 == code)
+    }
+}
+
+/// What boot_key_source() based its conclusion on
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KeySourceEvidence {
+    /// CNTL fuses as fetched
+    pub cntl: u8,
+    /// the eFUSE key read back as nonzero
+    pub efuse_key_set: bool,
+    /// R_EN_B_KEY is blown, so the eFUSE key reads back as zeros whatever it is
+    pub efuse_key_hidden: bool,
+    pub stat: u32,
+    pub ctl0: u32,
+    pub bbram: BbramKey,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KeySourceStatus {
+    pub source: KeySource,
+    pub evidence: KeySourceEvidence,
+}
+
+impl KeySourceStatus {
+    /// the conclusion `evidence` supports
+    pub fn from_evidence(evidence: KeySourceEvidence) -> Self {
+        let source: KeySource = if evidence.cntl & CNTL_CFG_AES_ONLY != 0 {
+            KeySource::EfuseForced
+        } else if evidence.bbram == BbramKey::Present {
+            KeySource::BbramPresent
+        } else if evidence.efuse_key_set || evidence.efuse_key_hidden {
+            // a key is burned before its readback is disabled, never after
+            KeySource::EfuseByDefault
+        } else {
+            KeySource::NoKey
+        };
+        KeySourceStatus { source, evidence }
+    }
+}
+
+fn shift_cfg_in<T: JtagPhy>(jm: &mut JtagMach, jp: &mut T, words: &[u32]) -> Result<(), JtagError> {
+    let mut ir_leg: JtagLeg = JtagLeg::new(JtagChain::IR, "cfg_in");
+    ir_leg.push_u32(Ir::CfgIn.code(), crate::sequences::IR_BITS, JtagEndian::Little).unwrap();
+    jm.add(ir_leg);
+    let mut data_leg: JtagLeg = JtagLeg::new(JtagChain::DR, "cfg_in");
+    // the leg shifts out the last bits pushed first
+    for &word in words.iter().rev() {
+        data_leg.push_u32(word, 32, JtagEndian::Big).unwrap();
+    }
+    jm.add(data_leg);
+    let result = jm.run_to_completion(jp);
+    if result.is_err() {
+        jm.clear_pending();
+    }
+    jm.drain_completed(|_| {});
+    result.map(|_| ())
+}
+
+/// read configuration register `reg`
+pub(crate) fn read_config_register<T: JtagPhy>(jm: &mut JtagMach, jp: &mut T, reg: u8) -> Result<u32, JtagError> {
+    shift_cfg_in(jm, jp, &[SYNC, NOOP, read_header(reg), NOOP, NOOP])?;
+    let mut data_leg: JtagLeg = JtagLeg::new(JtagChain::DR, "cfg_out");
+    data_leg.push_u32(0, 32, JtagEndian::Big).unwrap();
+    let value: u32 = match EfusePhy::<SevenSeries>::readback(&DeviceProfile::SPARTAN7, jm, jp, Ir::CfgOut, data_leg)? {
+        Some(mut data) => data.pop_u32(32, JtagEndian::Big).unwrap(),
+        None => 0,
+    };
+    shift_cfg_in(jm, jp, &[WRITE_CMD, CMD_DESYNC, NOOP, NOOP])?;
+    Ok(value)
+}
+
+/// gathers the evidence, given the fused key and CNTL already fetched
+pub(crate) fn evidence<T: JtagPhy>(key: &[u8; 32], cntl: u8, jm: &mut JtagMach, jp: &mut T) -> Result<KeySourceEvidence, EfuseError> {
+    let fail = |e: JtagError| EfuseError::from_jtag(Phase::Fetch, e);
+    jp.pause(2000);
+    let stat: u32 = read_config_register(jm, jp, REG_STAT).map_err(fail)?;
+    let ctl0: u32 = read_config_register(jm, jp, REG_CTL0).map_err(fail)?;
+    let bbram: BbramKey = if stat & STAT_PART_SECURED != 0 && ctl0 & CTL0_EFUSE_KEY == 0 {
+        BbramKey::Present
+    } else {
+        BbramKey::Unknown
+    };
+    Ok(KeySourceEvidence {
+        cntl,
+        efuse_key_set: key.iter().any(|&b| b != 0),
+        efuse_key_hidden: cntl & CNTL_R_EN_B_KEY != 0,
+        stat,
+        ctl0,
+        bbram,
+    })
+}
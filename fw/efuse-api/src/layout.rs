@@ -0,0 +1,383 @@
+//! Physical bank layout of the 7-series eFUSE array
+//!
+//! This is the one place that knows how the logical KEY/USER/CNTL values are packed into
+//! the 13 physical fuse banks. Everything else (fetch, validation, burning, host tooling)
+//! should call into here rather than re-deriving the packing by hand.
+//!
+//! Bank mapping as follows:
+//!   * 0 - cntl (6 bits, plus a redundant copy at bit 14; no ECC)
+//!   * 1-10 - key, 3 bytes per bank, lowest key byte in the lowest bits
+//!   * 11 - key bytes 30/31 in the bottom 16 bits, user[7:0] in bits 23:16
+//!   * 12 - user[31:8]
+//!
+//! Banks 1-12 carry a 6-bit ECC code in bits 29:24 once encoded.
+//!
+//! EfuseLogical is the logical view; to_banks() and from_banks() convert it to the physical
+//! image and back.
+
+use core::ops::{BitAnd, BitOr, BitOrAssign, Sub};
+
+use efuse_ecc::efuse_ecc::*;
+
+/// There are 13 banks of fuses, 12 of which (key/user) are "hamming" ECC, 1 of which (config) is "dup" ECC.
+pub const FUSE_BANKS: usize = 13;
+/// Number of banks that hold (at least part of) the key
+pub const KEY_BANKS: usize = 11;
+/// The CNTL bank, which uses duplication instead of ECC
+pub const CNTL_BANK: usize = 0;
+/// The bank shared between the top two key bytes and the bottom byte of USER
+pub const SHARED_BANK: usize = 11;
+/// The bank holding the upper 24 bits of USER
+pub const USER_BANK: usize = 12;
+
+/// Valid bits of the CNTL fuse word
+pub const CNTL_MASK: u8 = 0x3F;
+// CNTL bits, as named in UG470; bit 0 is reserved
+
+/// only bitstreams encrypted with the eFUSE key are accepted
+pub const CNTL_CFG_AES_ONLY: u8 = 1 << 1;
+/// disables JTAG access to the configuration logic while the eFUSE key is in use
+pub const CNTL_AES_EXCLUSIVE: u8 = 1 << 2;
+/// disables further programming of KEY and USER
+pub const CNTL_W_EN_B_KEY_USER: u8 = 1 << 3;
+/// disables readback of KEY
+pub const CNTL_R_EN_B_KEY: u8 = 1 << 4;
+/// disables readback of USER
+pub const CNTL_R_EN_B_USER: u8 = 1 << 5;
+
+/// The named CNTL bits, as a set. Only the bits above can be in it, so a CntlFlags can't
+/// stage reserved bit 0 or anything outside CNTL_MASK.
+///
+/// There's no W_EN_B_CNTL here: it isn't one of the six CNTL fuses modelled, so the CNTL
+/// bits can't be write-protected through this API.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CntlFlags(u8);
+
+impl CntlFlags {
+    pub const CFG_AES_ONLY: CntlFlags = CntlFlags(CNTL_CFG_AES_ONLY);
+    pub const AES_EXCLUSIVE: CntlFlags = CntlFlags(CNTL_AES_EXCLUSIVE);
+    pub const W_EN_B_KEY_USER: CntlFlags = CntlFlags(CNTL_W_EN_B_KEY_USER);
+    pub const R_EN_B_KEY: CntlFlags = CntlFlags(CNTL_R_EN_B_KEY);
+    pub const R_EN_B_USER: CntlFlags = CntlFlags(CNTL_R_EN_B_USER);
+
+    pub const fn empty() -> Self {
+        CntlFlags(0)
+    }
+
+    /// every named bit
+    pub const fn all() -> Self {
+        CntlFlags(CNTL_CFG_AES_ONLY | CNTL_AES_EXCLUSIVE | CNTL_W_EN_B_KEY_USER | CNTL_R_EN_B_KEY | CNTL_R_EN_B_USER)
+    }
+
+    /// the raw CNTL value, as set_cntl() takes it
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// the flags in `bits`, or None if it has any bit that isn't one
+    pub const fn from_bits(bits: u8) -> Option<Self> {
+        if bits & !Self::all().0 == 0 { Some(CntlFlags(bits)) } else { None }
+    }
+
+    /// the flags in `bits`, dropping any bit that isn't one
+    pub const fn from_bits_truncate(bits: u8) -> Self {
+        CntlFlags(bits & Self::all().0)
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// true if every flag of `other` is set here
+    pub const fn contains(self, other: CntlFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: CntlFlags) {
+        self.0 |= other.0;
+    }
+
+    pub fn remove(&mut self, other: CntlFlags) {
+        self.0 &= !other.0;
+    }
+
+    /// sets or clears the flags of `other`
+    pub fn set(&mut self, other: CntlFlags, value: bool) {
+        if value { self.insert(other) } else { self.remove(other) }
+    }
+}
+
+impl BitOr for CntlFlags {
+    type Output = CntlFlags;
+    fn bitor(self, other: CntlFlags) -> CntlFlags {
+        CntlFlags(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for CntlFlags {
+    fn bitor_assign(&mut self, other: CntlFlags) {
+        self.0 |= other.0;
+    }
+}
+
+impl BitAnd for CntlFlags {
+    type Output = CntlFlags;
+    fn bitand(self, other: CntlFlags) -> CntlFlags {
+        CntlFlags(self.0 & other.0)
+    }
+}
+
+impl Sub for CntlFlags {
+    type Output = CntlFlags;
+    fn sub(self, other: CntlFlags) -> CntlFlags {
+        CntlFlags(self.0 & !other.0)
+    }
+}
+
+impl From<CntlFlags> for u8 {
+    fn from(flags: CntlFlags) -> u8 {
+        flags.0
+    }
+}
+
+/// Bit offset of the redundant copy of the CNTL bits within the CNTL bank
+pub const CNTL_COPY_SHIFT: u32 = 14;
+
+/// One of the two copies of the CNTL bits. They're separate fuses, so one can fail to blow
+/// while the other is fine.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CntlCopy {
+    /// bits 5:0 of the CNTL bank
+    Primary,
+    /// bits 19:14 of the CNTL bank
+    Redundant,
+}
+
+impl CntlCopy {
+    /// both copies, in the order they're programmed
+    pub const ALL: [CntlCopy; 2] = [CntlCopy::Primary, CntlCopy::Redundant];
+
+    /// bit offset of this copy within the CNTL bank
+    pub fn shift(self) -> u32 {
+        match self {
+            CntlCopy::Primary => 0,
+            CntlCopy::Redundant => CNTL_COPY_SHIFT,
+        }
+    }
+
+    /// this copy's CNTL bits, out of a raw CNTL bank value
+    pub fn extract(self, bank: u32) -> u8 {
+        ((bank >> self.shift()) as u8) & CNTL_MASK
+    }
+
+    /// `bits` placed as this copy within the CNTL bank
+    pub fn deposit(self, bits: u8) -> u32 {
+        ((bits & CNTL_MASK) as u32) << self.shift()
+    }
+}
+
+/// Physical fuses of a key/user bank: 24 data bits plus the 6-bit ECC code
+pub const ECC_BANK_FUSES: u32 = 0x3FFF_FFFF;
+
+/// Returns the mask of bits of bank `index` that correspond to actual fuses.
+pub fn bank_fuses(index: usize) -> u32 {
+    assert!(index < FUSE_BANKS);
+    if index == CNTL_BANK {
+        let cntl: u32 = CNTL_MASK as u32;
+        cntl | (cntl << CNTL_COPY_SHIFT)
+    } else {
+        ECC_BANK_FUSES
+    }
+}
+
+/// Returns the raw (pre-ECC) contents of bank `index` for the given logical fuse state.
+pub fn bank_image(index: usize, key: &[u8; 32], user: u32, cntl: u8) -> u32 {
+    assert!(index < FUSE_BANKS);
+    match index {
+        CNTL_BANK => {
+            let cntl: u32 = (cntl & CNTL_MASK) as u32;
+            cntl | (cntl << CNTL_COPY_SHIFT)
+        },
+        SHARED_BANK => ((user & 0xFF) << 16) | (key[31] as u32) << 8 | key[30] as u32,
+        USER_BANK => (user >> 8) & 0xFF_FF_FF,
+        _ => {
+            let base: usize = (index - 1) * 3;
+            (key[base + 2] as u32) << 16 | (key[base + 1] as u32) << 8 | key[base] as u32
+        }
+    }
+}
+
+/// Returns the contents of bank `index` as it is physically fused, i.e. with the ECC code
+/// added for the key/user banks. The CNTL bank has no ECC, so its image is unchanged.
+pub fn bank_image_ecc(index: usize, key: &[u8; 32], user: u32, cntl: u8) -> u32 {
+    let raw: u32 = bank_image(index, key, user, cntl);
+    if index == CNTL_BANK {
+        raw
+    } else {
+        add_ecc(raw)
+    }
+}
+
+/// Decodes the USER value from its two banks (ECC bits are ignored).
+pub fn user_from_banks(banks: &[u32; FUSE_BANKS]) -> u32 {
+    ((banks[SHARED_BANK] >> 16) & 0xFF) | ((banks[USER_BANK] & 0xFF_FF_FF) << 8)
+}
+
+/// Computes the physical image of all banks at once.
+pub fn banks_image_ecc(key: &[u8; 32], user: u32, cntl: u8) -> [u32; FUSE_BANKS] {
+    let mut banks: [u32; FUSE_BANKS] = [0; FUSE_BANKS];
+    for (index, bank) in banks.iter_mut().enumerate() {
+        *bank = bank_image_ecc(index, key, user, cntl);
+    }
+    banks
+}
+
+/// The fuse state as KEY, USER and CNTL read it, rather than as the banks hold it
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub struct EfuseLogical {
+    pub key: [u8; 32],
+    pub user: u32,
+    /// only the CNTL_MASK bits are fuses; to_banks() drops the rest
+    pub cntl: u8,
+}
+
+/// Decodes the logical state from a physical image: the ECC bits are ignored, and CNTL is
+/// taken from the primary copy. from_banks(&to_banks(x)) is x for any x with no CNTL bits
+/// outside CNTL_MASK.
+pub fn from_banks(banks: &[u32; FUSE_BANKS]) -> EfuseLogical {
+    let mut key: [u8; 32] = [0; 32];
+    for (i, k) in key.iter_mut().enumerate() {
+        *k = (banks[i / 3 + 1] >> ((i % 3) * 8)) as u8;
+    }
+    EfuseLogical { key, user: user_from_banks(banks), cntl: CntlCopy::Primary.extract(banks[CNTL_BANK]) }
+}
+
+/// The physical image of a logical state, ECC codes and both CNTL copies included
+pub fn to_banks(logical: &EfuseLogical) -> [u32; FUSE_BANKS] {
+    banks_image_ecc(&logical.key, logical.user, logical.cntl)
+}
+
+/// What a physical bank holds
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BankKind {
+    /// CNTL bits, stored twice
+    Cntl,
+    /// three key bytes
+    Key,
+    /// key bytes 30/31 and user[7:0]
+    Shared,
+    /// user[31:8]
+    User,
+}
+
+impl BankKind {
+    pub fn of(index: usize) -> BankKind {
+        assert!(index < FUSE_BANKS);
+        match index {
+            CNTL_BANK => BankKind::Cntl,
+            SHARED_BANK => BankKind::Shared,
+            USER_BANK => BankKind::User,
+            _ => BankKind::Key,
+        }
+    }
+}
+
+/// A physical bank by name, for the bank-at-a-time API; the discriminants are the indices
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Bank {
+    Cntl,
+    Key1,
+    Key2,
+    Key3,
+    Key4,
+    Key5,
+    Key6,
+    Key7,
+    Key8,
+    Key9,
+    Key10,
+    Shared,
+    User,
+}
+
+impl Bank {
+    /// every bank, in index order
+    pub const ALL: [Bank; FUSE_BANKS] = [
+        Bank::Cntl, Bank::Key1, Bank::Key2, Bank::Key3, Bank::Key4, Bank::Key5, Bank::Key6,
+        Bank::Key7, Bank::Key8, Bank::Key9, Bank::Key10, Bank::Shared, Bank::User,
+    ];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// None past the last bank
+    pub fn from_index(index: usize) -> Option<Bank> {
+        Bank::ALL.get(index).copied()
+    }
+
+    pub fn kind(self) -> BankKind {
+        BankKind::of(self.index())
+    }
+}
+
+/// Integrity of a bank's contents
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EccStatus {
+    /// the stored ECC code matches the data
+    Valid,
+    /// the stored ECC code doesn't match; `expected` is the code the data calls for, and
+    /// `flipped` the one bit of the word that would make it valid, if there is one (see
+    /// efuse_ecc::decode_ecc)
+    Mismatch { expected: u8, flipped: Option<u32> },
+    /// the CNTL bank has no ECC; instead it holds two copies, which should agree
+    Duplicated { primary: u8, copy: u8 },
+}
+
+/// A physical bank decoded into its parts
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BankView {
+    pub kind: BankKind,
+    /// the data record; for the CNTL bank, the primary copy
+    pub data: u32,
+    /// the stored ECC code; 0 for the CNTL bank
+    pub ecc: u8,
+    pub ecc_status: EccStatus,
+}
+
+impl BankView {
+    /// decode `word`, the physical contents of bank `index`
+    pub fn decode(index: usize, word: u32) -> BankView {
+        let kind: BankKind = BankKind::of(index);
+        if kind == BankKind::Cntl {
+            let primary: u8 = (word as u8) & CNTL_MASK;
+            let copy: u8 = ((word >> CNTL_COPY_SHIFT) as u8) & CNTL_MASK;
+            return BankView { kind, data: primary as u32, ecc: 0, ecc_status: EccStatus::Duplicated { primary, copy } };
+        }
+        let (data, ecc) = split_ecc(word);
+        let ecc_status: EccStatus = if verify_ecc(word) {
+            EccStatus::Valid
+        } else {
+            EccStatus::Mismatch { expected: split_ecc(add_ecc(data)).1, flipped: decode_ecc(word).ok().and_then(|fix| fix.flipped) }
+        };
+        BankView { kind, data, ecc, ecc_status }
+    }
+
+    /// true if the ECC code checks out, or for the CNTL bank, if both copies agree
+    pub fn is_consistent(&self) -> bool {
+        match self.ecc_status {
+            EccStatus::Valid => true,
+            EccStatus::Mismatch { .. } => false,
+            EccStatus::Duplicated { primary, copy } => primary == copy,
+        }
+    }
+}
+
+/// Decodes every bank of a physical image.
+pub fn banks_logical(banks: &[u32; FUSE_BANKS]) -> [BankView; FUSE_BANKS] {
+    let mut views: [BankView; FUSE_BANKS] = [BankView::decode(CNTL_BANK, 0); FUSE_BANKS];
+    for (index, view) in views.iter_mut().enumerate() {
+        *view = BankView::decode(index, banks[index]);
+    }
+    views
+}
@@ -1,386 +1,2407 @@
 #![no_std]
 
+//! efuse API for 7-series FPGAs
+//! 
+//! There are three fuse types to burn: USER, KEY, and CNTL
+//! 
+//! USER and KEY fuses share a similar ECC structure ,and in fact, the USER fuses partially
+//! share a fuse bank with the KEY.
+//! 
+//! CNTL fuses are unique in that instead of having ECC, each fuse has two copies, and are burned
+//! in duplicate for reliability. 
+//! 
+//! Fuses are write-once. It's also not possible within the documented command set to read out the
+//! raw fuse values once burned -- they can only be implied through a set of readback calls. 
+//! This means the fuse life cycle looks like this:
+//!   * Initial, unprogrammed factory state is all 0's
+//!   * USER/KEY data is coded by blowing only the 1's. An ECC code must also be blown simultaneously 
+//!     to match the final pattern of 1's for correct readout
+//!   * It seems that patches to fuses can be done, so long as it only involves changing 0->1 and results
+//!     in a valid state after ECC is factored in. This is especially true for data values striped across
+//!     multiple banks.
+//! 
+//! Patching support may be particularly valuable in the case that e.g. anti-rollback fusing is desired.
+//! 
+//! This API implements the following features:
+//!   * retrieve the current fuse state
+//!   * validate if a proposed state change results in a valid operation (only 0->1 including ECC mods)
+//!   * perform the actual burn operation
+//! 
+//! In order to represent the fusing structure more accurately, this module models the state of fuses
+//! not by their logical function, but by their physical mapping into the bank. There is then a layer
+//! of code that can convert the physical bank information into the logical view. Validation code thus
+//! works with a set of calls that can validate bank-by-bank, which are then called by the meta-functions
+//! which will implement the logical KEY/USER/CNTL requests. 
 
-/// efuse API for 7-series FPGAs
-/// 
-/// There are three fuse types to burn: USER, KEY, and CNTL
-/// 
-/// USER and KEY fuses share a similar ECC structure ,and in fact, the USER fuses partially
-/// share a fuse bank with the KEY.
-/// 
-/// CNTL fuses are unique in that instead of having ECC, each fuse has two copies, and are burned
-/// in duplicate for reliability. 
-/// 
-/// Fuses are write-once. It's also not possible within the documented command set to read out the
-/// raw fuse values once burned -- they can only be implied through a set of readback calls. 
-/// This means the fuse life cycle looks like this:
-///   * Initial, unprogrammed factory state is all 0's
-///   * USER/KEY data is coded by blowing only the 1's. An ECC code must also be blown simultaneously 
-///     to match the final pattern of 1's for correct readout
-///   * It seems that patches to fuses can be done, so long as it only involves changing 0->1 and results
-///     in a valid state after ECC is factored in. This is especially true for data values striped across
-///     multiple banks.
-/// 
-/// Patching support may be particularly valuable in the case that e.g. anti-rollback fusing is desired.
-/// 
-/// This API implements the following features:
-///   * retrieve the current fuse state
-///   * validate if a proposed state change results in a valid operation (only 0->1 including ECC mods)
-///   * perform the actual burn operation
-/// 
-/// In order to represent the fusing structure more accurately, this module models the state of fuses
-/// not by their logical function, but by their physical mapping into the bank. There is then a layer
-/// of code that can convert the physical bank information into the logical view. Validation code thus
-/// works with a set of calls that can validate bank-by-bank, which are then called by the meta-functions
-/// which will implement the logical KEY/USER/CNTL requests. 
-/// 
+extern crate alloc;
+#[cfg(any(feature = "python", feature = "ftdi", feature = "gpiod"))]
+extern crate std;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
 
 use jtag::*;
-use efuse_ecc::efuse_ecc::*;
 
-/// There are 13 banks of fuses, 12 of which (key/user) are "hamming" ECC, 1 of which (config) is "dup" ECC.
-pub struct EfusePhy {
+pub mod layout;
+use layout::*;
+pub mod sequences;
+use sequences::*;
+pub mod device;
+use device::{DeviceFamily, DeviceProfile, IscBracket, SevenSeries};
+pub mod test_utils;
+pub mod error;
+pub use error::*;
+pub mod messages;
+use messages::*;
+pub mod transport;
+pub mod protocol;
+pub mod keycheck;
+pub mod keysource;
+use keycheck::*;
+pub mod chain;
+pub mod integrity;
+use integrity::{Guard, VerdictPath, VerdictStage, ALL_BANKS};
+pub mod verify;
+pub mod access;
+pub mod status;
+use status::FuseStatus;
+pub mod checksum;
+use checksum::KeyChecksumCheck;
+pub mod boot;
+pub mod fusemap;
+pub mod xadc;
+pub mod rollback;
+pub mod userlayout;
+use userlayout::{UserField, UserLayout};
+pub mod patch;
+pub mod observer;
+use observer::BurnObserver;
+use xadc::{EnvLimits, PreBurnCheck};
+use boot::{BootCheck, BootVerifier};
+pub mod keyhex;
+pub mod vivado;
+pub mod sha256;
+pub mod csr;
+pub mod secrets;
+pub mod events;
+#[cfg(feature = "kdf")]
+pub mod kdf;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "svf")]
+pub mod svf;
+#[cfg(feature = "svf")]
+pub mod xsvf;
+#[cfg(feature = "vcd")]
+pub mod vcd;
+#[cfg(feature = "ftdi")]
+pub mod ftdi;
+#[cfg(feature = "gpiod")]
+pub mod gpiod;
+
+/// Agreement between the USER value read through FUSE_USER and the one decoded from banks 11/12
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum UserConsistency {
+    Match,
+    Mismatch { direct: u32, derived: u32 },
+}
+
+impl UserConsistency {
+    pub fn check(direct: u32, banks: &[u32; FUSE_BANKS]) -> Self {
+        UserConsistency::compare(direct, user_from_banks(banks))
+    }
+
+    /// as check(), with USER already decoded from the banks
+    pub fn compare(direct: u32, derived: u32) -> Self {
+        if derived == direct {
+            UserConsistency::Match
+        } else {
+            UserConsistency::Mismatch { direct, derived }
+        }
+    }
+}
+
+/// A USER readback disagreeing with USERCODE, with both values; see
+/// EfuseApi::cross_check_user()
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConsistencyError {
+    /// FUSE_USER, as of the last fetch, read something else
+    FuseUser { usercode: u32, fuse_user: u32 },
+    /// banks 11/12, as of the last fetch, decode to something else
+    Banks { usercode: u32, derived: u32 },
+}
+
+/// Whether FUSE_KEY gives the fused key back
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum KeyReadback {
+    Readable,
+    /// R_EN_B_KEY is blown, so whatever FUSE_KEY returns isn't the key: the key banks, and
+    /// the ECC of the bank the key shares with USER, aren't known
+    Unreadable,
+}
+
+impl KeyReadback {
+    /// what the fused CNTL bits `cntl` leave of the key readback
+    pub fn of(cntl: u8) -> Self {
+        if cntl & CNTL_R_EN_B_KEY != 0 { KeyReadback::Unreadable } else { KeyReadback::Readable }
+    }
+}
+
+/// Cross-checks made while fetching
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FetchReport {
+    pub user: UserConsistency,
+    pub key: KeyReadback,
+}
+
+/// Something about the intended state that is legal but probably a mistake
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ValidationWarning {
+    /// the key to be burned looks non-random (see keycheck)
+    SuspiciousKey { reason: WeakKeyReason },
+    /// no more bits of the provisioning-event counter can be blown, so this burn won't be counted
+    CounterExhausted { count: u32 },
+    /// the two fused copies of the CNTL bits differ, one of them having failed to program. A
+    /// burn of any valid plan programs the missing bits; repair_cntl() programs just those.
+    CntlCopyMismatch { primary: u8, redundant: u8 },
+}
+
+/// Outcome of a successful validate()
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct ValidationReport {
+    pub warnings: Vec<ValidationWarning>,
+    /// a warning would have been an error, but allow_weak_key() let it through
+    pub weak_key_overridden: bool,
+}
+
+/// Blown fuses of one bank that the intended state wants clear, split by field. The CNTL bank
+/// has no ECC, so its conflicts, in either copy, are all data.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub struct BankConflict {
+    /// in bits 23:0
+    pub data: u32,
+    /// in the ECC code, bits 29:24
+    pub ecc: u32,
+}
+
+impl BankConflict {
+    pub fn new(index: usize, mask: u32) -> Self {
+        match BankKind::of(index) {
+            BankKind::Cntl => BankConflict { data: mask, ecc: 0 },
+            _ => BankConflict { data: mask & 0xFF_FFFF, ecc: mask & 0x3F00_0000 },
+        }
+    }
+
+    pub fn mask(&self) -> u32 { self.data | self.ecc }
+}
+
+/// Conflicts in the bank shared by the key and USER, by the value they belong to
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SharedConflict {
+    /// key bytes 30/31, bits 15:0
+    pub key: u32,
+    /// user[7:0], bits 23:16
+    pub user: u32,
+    pub ecc: u32,
+}
+
+/// Per bank, the blown fuses the intended state wants clear; see EfuseApi::conflicts()
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TransitionConflicts {
+    pub banks: [BankConflict; FUSE_BANKS],
+}
+
+impl TransitionConflicts {
+    /// conflicts of the `fused` banks with the `image` the intended state encodes to
+    pub fn new(fused: &[u32; FUSE_BANKS], image: &[u32; FUSE_BANKS]) -> Self {
+        let mut banks: [BankConflict; FUSE_BANKS] = [BankConflict::default(); FUSE_BANKS];
+        for (index, bank) in banks.iter_mut().enumerate() {
+            *bank = BankConflict::new(index, fused[index] & !image[index]);
+        }
+        TransitionConflicts { banks }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.banks.iter().all(|bank| bank.mask() == 0)
+    }
+
+    /// per bank, data and ECC together
+    pub fn masks(&self) -> [u32; FUSE_BANKS] {
+        let mut masks: [u32; FUSE_BANKS] = [0; FUSE_BANKS];
+        for (mask, bank) in masks.iter_mut().zip(self.banks.iter()) {
+            *mask = bank.mask();
+        }
+        masks
+    }
+
+    /// the lowest bank with conflicts, and its mask
+    pub fn first(&self) -> Option<(usize, u32)> {
+        self.banks.iter().enumerate().map(|(index, bank)| (index, bank.mask())).find(|&(_, mask)| mask != 0)
+    }
+
+    /// the shared bank's conflicts, if it has any
+    pub fn shared(&self) -> Option<SharedConflict> {
+        let bank: BankConflict = self.banks[SHARED_BANK];
+        if bank.mask() == 0 {
+            return None;
+        }
+        Some(SharedConflict { key: bank.data & 0xFFFF, user: bank.data & 0xFF_0000, ecc: bank.ecc })
+    }
+}
+
+/// Upper bound on a single jittered gap, whatever a JitterSpec asks for
+pub const MAX_JITTER_CYCLES: u32 = 4096;
+
+/// Random-length idle gaps between bit-program operations
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct JitterSpec {
+    pub min_cycles: u32,
+    /// clamped to MAX_JITTER_CYCLES
+    pub max_cycles: u32,
+    pub seed: u64,
+}
+
+impl JitterSpec {
+    /// length, in TCK cycles spent in RUN_TEST/IDLE, of the gap after the `index`th programmed bit
+    pub fn gap(&self, index: usize) -> u32 {
+        let max: u32 = self.max_cycles.min(MAX_JITTER_CYCLES);
+        let min: u32 = self.min_cycles.min(max);
+        let mut state: u64 = self.seed ^ (index as u64).wrapping_mul(0xA076_1D64_78BD_642F);
+        min + (splitmix64(&mut state) % (max - min + 1) as u64) as u32
+    }
+}
+
+/// Timing options for burn()
+///
+/// A programming pulse lasts as long as the TAP leaves it, so with only the wait word after
+/// each bit its width depends on the phy's TCK rate. The idle and delay settings hold the TAP
+/// in RUN_TEST/IDLE, then pause, after each bit-program word and each bank select word, before
+/// the next word is shifted; the defaults add nothing, as burns did before they existed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub struct BurnTiming {
+    /// if set, each bit-program operation is followed by an idle gap of seeded random length
+    pub inter_bit_jitter: Option<JitterSpec>,
+    /// TCK cycles spent in RUN_TEST/IDLE after each bit-program word
+    pub bit_idle_cycles: u32,
+    /// pause, in microseconds, after each bit-program word
+    pub bit_delay_us: u32,
+    /// TCK cycles spent in RUN_TEST/IDLE after each bank select word
+    pub select_idle_cycles: u32,
+    /// pause, in microseconds, after each bank select word
+    pub select_delay_us: u32,
+}
+
+impl BurnTiming {
+    /// the idle TCK cycles and pause, in microseconds, that follow a word of kind `kind`
+    pub fn after(&self, kind: WordKind) -> (u32, u32) {
+        match kind {
+            WordKind::Bit(_) => (self.bit_idle_cycles, self.bit_delay_us),
+            WordKind::BankSelect => (self.select_idle_cycles, self.select_delay_us),
+            WordKind::Unlock | WordKind::Wait => (0, 0),
+        }
+    }
+}
+
+/// Longest a PerWord critical section lasts, in TCK cycles: the longest leg burn() shifts is
+/// the 75-bit DR of the commit sequence, plus the TAP transitions around it.
+#[cfg(feature = "critical-section")]
+pub const CS_MAX_WORD_CYCLES: u32 = 85;
+
+/// How much of a burn runs with interrupts masked, via critical_section::with(). Pauses are
+/// always outside critical sections.
+#[cfg(feature = "critical-section")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum CsPolicy {
+    #[default]
+    None,
+    /// each TCK cycle in its own critical section; interrupts wait at most 1 / f_TCK plus the
+    /// phy's per-cycle overhead
+    PerBit,
+    /// each IR or DR leg in one critical section, so a word is never split; interrupts wait at
+    /// most CS_MAX_WORD_CYCLES / f_TCK plus overhead, i.e. 85us at 1MHz. TAP resets and jitter
+    /// gaps only hold TMS steady, and run unmasked.
+    PerWord,
+}
+
+/// USER bits that count the burns that wrote fuses, for tamper evidence.
+///
+/// Fuses can't be cleared, so the count is the number of field bits blown, and each counted
+/// burn blows one more. Which one is the lowest clear bit that still leaves its bank with a
+/// valid ECC code: USER sits in ECC banks, and adding a data bit to a burned bank usually
+/// needs an ECC bit cleared, which can't be done. So the field has to be chosen for the USER
+/// value it's used with; in a bank otherwise left blank, e.g. USER bits 12 and 22 count two
+/// burns, and no two bits of bank 12 count more than that.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CounterSpec {
+    /// USER bits making up the field
+    pub field: u32,
+    /// once no field bit can be added, refuse to burn instead of only warning
+    pub refuse_when_exhausted: bool,
+}
+
+impl CounterSpec {
+    /// the count held in `user`
+    pub fn count(&self, user: u32) -> u32 {
+        (user & self.field).count_ones()
+    }
+}
+
+/// true if a burn that ended in `result` ran its commit sequence
+fn committed(result: &Result<(), EfuseError>) -> bool {
+    matches!(result, Ok(()) | Err(EfuseError::DeviceReportedError { .. }))
+}
+
+/// `base`, with the bits of USER in `mask` taken from `value`
+fn merge_user(base: u32, value: u32, mask: u32) -> u32 {
+    (base & !mask) | (value & mask)
+}
+
+/// Options controlling how burn() programs the fuses
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub struct BurnConfig {
+    pub order: BitOrderPolicy,
+    pub timing: BurnTiming,
+    /// if set, every burn that writes fuses also counts itself in these USER bits; whatever is
+    /// staged for them is ignored
+    pub event_counter: Option<CounterSpec>,
+    /// if set, a burn that writes a key also records its checksum in these USER bits, in place
+    /// of whatever is staged for them; see the checksum module
+    pub key_checksum_field: Option<u32>,
+    /// if set, every burn first reads the IDCODE and refuses with IdcodeMismatch, before
+    /// programming anything, unless it matches
+    pub expected_idcode: Option<IdcodeMatch>,
+    /// if set, every burn first reads the die temperature and supplies through the XADC, and
+    /// refuses with OutOfSpec unless they're within these limits; see the xadc module
+    pub environment: Option<EnvLimits>,
+    #[cfg(feature = "critical-section")]
+    pub critical_sections: CsPolicy,
+}
+
+/// An IDCODE to check the device against, under a mask
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct IdcodeMatch {
+    pub idcode: u32,
+    pub mask: u32,
+}
+
+impl IdcodeMatch {
+    /// the part of `idcode`, whatever its silicon revision (see chain::IDCODE_PART_MASK)
+    pub fn part(idcode: u32) -> Self {
+        IdcodeMatch { idcode, mask: chain::IDCODE_PART_MASK }
+    }
+
+    pub fn matches(&self, idcode: u32) -> bool {
+        (idcode ^ self.idcode) & self.mask == 0
+    }
+}
+
+/// Runs every cycle of the wrapped phy in a critical section of its own
+#[cfg(feature = "critical-section")]
+struct CsPhy<'a, T: JtagPhy>(&'a mut T);
+
+#[cfg(feature = "critical-section")]
+impl<T: JtagPhy> JtagPhy for CsPhy<'_, T> {
+    fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+        critical_section::with(|_| self.0.sync(tdi, tms))
+    }
+    fn nosync(&mut self, tdi: bool, tms: bool, tck: bool) -> bool {
+        critical_section::with(|_| self.0.nosync(tdi, tms, tck))
+    }
+    fn pause(&mut self, us: u32) {
+        self.0.pause(us)
+    }
+    fn try_sync(&mut self, tdi: bool, tms: bool) -> Result<bool, PhyError> {
+        critical_section::with(|_| self.0.try_sync(tdi, tms))
+    }
+}
+
+/// Length of the device DNA, which fills the top of the 64-bit FUSE_DNA register
+pub const DNA_BITS: u32 = 57;
+
+/// How long a burn takes: TCK cycles driven plus time spent in pause()
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BurnDuration {
+    pub cycles: u64,
+    pub pause_us: u64,
+}
+
+impl BurnDuration {
+    pub fn total_us(&self, tck_hz: u32) -> u64 {
+        self.pause_us + self.cycles * 1_000_000 / tck_hz as u64
+    }
+}
+
+/// The fuses burn() would blow, per bank, ECC bits and both CNTL copies included. With the
+/// `serde` feature only `bits` is serialized; deserializing counts the pulses again, and
+/// fails with OutOfRange on bits that aren't fuses.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(into = "PlanFields", try_from = "PlanFields"))]
+pub struct BurnPlan {
+    pub bits: [u32; FUSE_BANKS],
+    /// one programming pulse per fuse
+    pub pulses: u32,
+}
+
+/// BurnPlan as it's serialized
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct PlanFields {
+    bits: [u32; FUSE_BANKS],
+}
+
+#[cfg(feature = "serde")]
+impl From<BurnPlan> for PlanFields {
+    fn from(plan: BurnPlan) -> Self {
+        PlanFields { bits: plan.bits }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl core::convert::TryFrom<PlanFields> for BurnPlan {
+    type Error = EfuseError;
+
+    fn try_from(f: PlanFields) -> Result<Self, EfuseError> {
+        for (bank, &bits) in f.bits.iter().enumerate() {
+            let ones: u32 = bits & !bank_fuses(bank);
+            if ones != 0 {
+                return Err(EfuseError::OutOfRange { bank, ones });
+            }
+        }
+        Ok(BurnPlan::new(f.bits))
+    }
+}
+
+impl BurnPlan {
+    pub fn new(bits: [u32; FUSE_BANKS]) -> Self {
+        BurnPlan { bits, pulses: bits.iter().map(|b| b.count_ones()).sum() }
+    }
+
+    /// (bank, fuses) for each bank with fuses to blow, in burn order
+    pub fn banks(&self) -> impl Iterator<Item = (usize, u32)> + '_ {
+        (0..FUSE_BANKS).rev().map(move |bank| (bank, self.bits[bank])).filter(|&(_, bits)| bits != 0)
+    }
+}
+
+impl core::fmt::Display for BurnPlan {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        for (bank, bits) in self.banks() {
+            writeln!(f, "bank {:>2}: {:#010x}, {} fuses", bank, bits, bits.count_ones())?;
+        }
+        write!(f, "{} pulses", self.pulses)
+    }
+}
+
+/// What the intended state changes against the fused state, field by field; see
+/// EfuseApi::diff(). Each field has the bits a burn would blow, which are legal, and the blown
+/// bits the intended state wants clear, which aren't.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub struct StateDiff {
+    /// key bytes with bits to blow, as a mask: bit i is key byte i
+    pub key_set: u32,
+    /// key bytes with blown bits the intended key wants clear, as key_set
+    pub key_cleared: u32,
+    pub user_set: u32,
+    pub user_cleared: u32,
+    /// in either copy
+    pub cntl_set: u8,
+    pub cntl_cleared: u8,
+    /// banks whose ECC code needs blown fuses cleared, as a mask: bit i is bank i
+    pub ecc_cleared: u32,
+    /// fuses a burn would blow, ECC bits and both CNTL copies included
+    pub fuses: u32,
+}
+
+impl StateDiff {
+    /// the key bytes that change, legally or not, lowest first
+    pub fn changed_key_bytes(&self) -> impl Iterator<Item = usize> {
+        let changed: u32 = self.key_set | self.key_cleared;
+        (0..32).filter(move |&i| changed & (1 << i) != 0)
+    }
+
+    /// whether every change is a legal 0->1 one
+    pub fn is_legal(&self) -> bool {
+        self.key_cleared == 0 && self.user_cleared == 0 && self.cntl_cleared == 0 && self.ecc_cleared == 0
+    }
+}
+
+/// What a burn keeps track of as it shifts the banks' programming words
+struct BurnProgress<'a> {
+    /// programming pulses shifted so far, which the jitter schedule goes by
+    bits_done: usize,
+    /// the bank the last programming pulse was in, if there's been one
+    pulsed: Option<usize>,
+    /// errors the programming words captured, per bank
+    statuses: &'a mut [u32; FUSE_BANKS],
+    /// the fuses whose programming pulse has been shifted, per bank
+    programmed: &'a mut [u32; FUSE_BANKS],
+    observer: &'a mut dyn BurnObserver,
+}
+
+/// Passes every cycle through to the wrapped phy, counting the bits shifted in the current
+/// DR scan: None until one starts
+struct ShiftCounter<'a, T: JtagPhy> {
+    phy: &'a mut T,
+    tap: TapState,
+    shifted: Option<usize>,
+}
+
+impl<'a, T: JtagPhy> ShiftCounter<'a, T> {
+    /// the TAP state is taken to be Test-Logic-Reset; it is after the caller's first reset
+    fn new(phy: &'a mut T) -> Self {
+        ShiftCounter { phy, tap: TapState::TestLogicReset, shifted: None }
+    }
+
+    fn clock(&mut self, tms: bool) {
+        match self.tap {
+            TapState::CaptureDr => self.shifted = Some(0),
+            TapState::ShiftDr => self.shifted = self.shifted.map(|bits| bits + 1),
+            _ => {},
+        }
+        self.tap = self.tap.next(tms);
+    }
+}
+
+impl<T: JtagPhy> JtagPhy for ShiftCounter<'_, T> {
+    fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+        let tdo: bool = self.phy.sync(tdi, tms);
+        self.clock(tms);
+        tdo
+    }
+    fn nosync(&mut self, tdi: bool, tms: bool, tck: bool) -> bool {
+        self.phy.nosync(tdi, tms, tck)
+    }
+    fn pause(&mut self, us: u32) {
+        self.phy.pause(us)
+    }
+    fn try_sync(&mut self, tdi: bool, tms: bool) -> Result<bool, PhyError> {
+        let tdo: bool = self.phy.try_sync(tdi, tms)?;
+        self.clock(tms);
+        Ok(tdo)
+    }
+    /// passed on whole, so a phy that batches still can
+    fn shift_bulk(&mut self, tdi: &[u8], tms: &[u8], tdo_out: &mut [u8], nbits: usize) -> Result<usize, PhyError> {
+        let shifted: usize = self.phy.shift_bulk(tdi, tms, tdo_out, nbits)?;
+        for n in 0..shifted {
+            self.clock((tms[n / 8] >> (n % 8)) & 0x1 == 1);
+        }
+        Ok(shifted)
+    }
+}
+
+/// Phy that drives nothing and only counts, for dry runs
+struct CountingPhy {
+    cycles: u64,
+    pause_us: u64,
+    tap: TapState,
+    /// IR bits shifted so far in this scan
+    ir_bits: u32,
+    /// shifted out of the IR, so that a checked scan passes
+    capture: u32,
+}
+
+impl CountingPhy {
+    fn new(capture: u32) -> Self {
+        CountingPhy { cycles: 0, pause_us: 0, tap: TapState::TestLogicReset, ir_bits: 0, capture }
+    }
+}
+
+impl JtagPhy for CountingPhy {
+    fn sync(&mut self, _tdi: bool, tms: bool) -> bool {
+        self.cycles += 1;
+        let tdo: bool = self.tap == TapState::ShiftIr && self.ir_bits < 32 && (self.capture >> self.ir_bits) & 0x1 == 1;
+        self.ir_bits = if self.tap == TapState::ShiftIr { self.ir_bits + 1 } else { 0 };
+        self.tap = self.tap.next(tms);
+        tdo
+    }
+    fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool {
+        false
+    }
+    fn pause(&mut self, us: u32) {
+        self.pause_us += us as u64;
+    }
+}
+
+/// Physical state of the fuses, as read back from a device of family `F`.
+pub struct EfusePhy<F: DeviceFamily = SevenSeries> {
     banks: [u32; 13],
     key: [u8; 32],
     user: u32,
     cntl: u8,
+    report: FetchReport,
+    /// set once the banks hold a fetch or a snapshot
+    fetched: bool,
+    profile: DeviceProfile,
+    family: PhantomData<F>,
 }
 
-const FUSE_BANKS: usize = 13;
-const KEY_BANKS: usize = 11;
-const CMD_FUSE_USER: u32 = 0b110011;
-const CMD_FUSE_KEY: u32 = 0b110001;
-const CMD_FUSE_CNTL: u32 = 0b110100;
-
 impl EfusePhy {
-
     pub fn new() -> Self {
+        EfusePhy::for_family()
+    }
+}
+
+impl Default for EfusePhy {
+    fn default() -> Self { EfusePhy::new() }
+}
+
+impl<F: DeviceFamily> EfusePhy<F> {
+    /// new(), for a family other than the default
+    pub fn for_family() -> Self {
         EfusePhy {
-            /// bank mapping as follows: 
-            /// 0 - config
-            /// 1-11 - key (11 shared with user LSB)
-            /// 12 - user
+            // bank mapping as follows: 
+            // 0 - config
+            // 1-11 - key (11 shared with user LSB)
+            // 12 - user
             banks: [0; FUSE_BANKS],
             key: [0; 32],
             user: 0,
             cntl: 0,
+            report: FetchReport { user: UserConsistency::Match, key: KeyReadback::Readable },
+            fetched: false,
+            profile: F::PROFILE,
+            family: PhantomData,
         }
     }
 
     pub fn user(&self) -> u32 { self.user }
     pub fn cntl(&self) -> u8 { self.cntl }
+    /// the primary and redundant copies of the CNTL bits, as fused; cntl() is the two ORed
+    pub fn cntl_copies(&self) -> (u8, u8) {
+        (CntlCopy::Primary.extract(self.banks[CNTL_BANK]), CntlCopy::Redundant.extract(self.banks[CNTL_BANK]))
+    }
     pub fn key(&self) -> [u8; 32] { self.key }
+    pub fn report(&self) -> FetchReport { self.report }
+    /// whether the banks hold a fetch or a snapshot, rather than the blank state new() starts from
+    pub fn fetched(&self) -> bool { self.fetched }
+
+    /// Overwrites the key and the banks carrying it with zeros; see the secrets module. The
+    /// state no longer reflects the device, so it counts as not fetched until the next fetch.
+    pub fn clear_secrets(&mut self) {
+        secrets::wipe_bytes(&mut self.key);
+        secrets::wipe_key_banks(&mut self.banks);
+        self.fetched = false;
+    }
+
+    /// each bank decoded into its data and ECC fields
+    pub fn banks_logical(&self) -> [BankView; FUSE_BANKS] {
+        banks_logical(&self.banks)
+    }
+
+    /// the data bits of each bank, as the readbacks returned them: the image without ECC
+    fn data_bits(&self) -> [u32; FUSE_BANKS] {
+        let mut logical: EfuseLogical = EfuseLogical { key: self.key, user: self.user, cntl: self.cntl };
+        let mut banks: [u32; FUSE_BANKS] = [0; FUSE_BANKS];
+        for (index, bank) in banks.iter_mut().enumerate() {
+            *bank = F::bank_image(index, &logical);
+        }
+        // the CNTL copies as they were read, rather than as the bits they're ORed into
+        banks[CNTL_BANK] = self.banks[CNTL_BANK];
+        secrets::wipe_bytes(&mut logical.key);
+        banks
+    }
 
     /// this is a TEST FUNCTION ONLY. Unfortunately, the Rust test directive does not
     /// like this no_std runtime / std test environment.
     pub fn bank_patch(&mut self, index: usize, data: u32) { // this is just for test routines
         self.banks[index] = data;
         // re-derive key bits from bank data
-        for i in 0..32 {
-            self.key[i] = ((self.banks[((i / 3) + 1) as usize] >> ((i % 3) * 8)) & 0xFF) as u8;
-        }
+        self.key = F::from_banks(&self.banks).key;
+        // the USER readback isn't patched, so a patched user bank shows up as a mismatch
+        self.report.user = UserConsistency::compare(self.user, F::user_from_banks(&self.banks));
     }
 
-    /// fetch the current fuse state
-    pub fn fetch<T: JtagPhy>(&mut self, jm: &mut JtagMach, jp: &mut T) {
-        jm.reset(jp);
+    /// Take the fuse state from a snapshot instead of a fetch, e.g. to plan a burn offline.
+    /// There is no separate FUSE_USER readout to cross-check against, so USER is taken from
+    /// the banks.
+    pub fn load_snapshot(&mut self, snapshot: &FuseSnapshot) {
+        self.banks = snapshot.banks;
+        let mut logical: EfuseLogical = F::from_banks(&self.banks);
+        self.key = logical.key;
+        self.user = logical.user;
+        // as a fetch takes it, from both copies
+        self.cntl = logical.cntl | CntlCopy::Redundant.extract(self.banks[CNTL_BANK]);
+        secrets::wipe_bytes(&mut logical.key);
+        self.report.user = UserConsistency::Match;
+        // the snapshot's key banks are whatever its FUSE_KEY returned
+        self.report.key = KeyReadback::of(self.cntl);
+        self.fetched = true;
+    }
 
-        // get the KEY fuse
-        jp.pause(2000);
-        let mut ir_leg: JtagLeg = JtagLeg::new(JtagChain::IR, "cmd");
-        ir_leg.push_u32(CMD_FUSE_KEY, 6, JtagEndian::Little);
+    /// issue the readback instruction `cmd`, then shift `data_leg` through the DR it selects,
+    /// under POLL_BUDGET. Returns the data leg with the captured bits, or None if it didn't come
+    /// out of the machine.
+    fn readback<T: JtagPhy>(profile: &DeviceProfile, jm: &mut JtagMach, jp: &mut T, cmd: Ir, data_leg: JtagLeg) -> Result<Option<JtagLeg>, JtagError> {
+        let mut ir_leg: JtagLeg = JtagLeg::new(JtagChain::IR, "cmd").with_budget(IR_BUDGET);
+        ir_leg.push_u32(profile.code(cmd), profile.ir_bits, JtagEndian::Little).unwrap();
+        let tag: &'static str = data_leg.tag();
         jm.add(ir_leg);
-        jm.next(jp);
-        assert!(jm.get().is_some());
+        jm.add(data_leg.with_budget(POLL_BUDGET));
+        if let Err(e) = jm.run_to_completion(jp) {
+            jm.clear_pending();
+            return Err(e);
+        }
+        let data: Option<JtagLeg> = jm.get_by_tag(tag);
+        // the instruction leg's capture isn't needed
+        jm.drain_completed(|_| ());
+        Ok(data)
+    }
+
+    /// readback(), for the fuse readback `which`: the data leg comes back with all its bits, or
+    /// this fails with ShortReadback, saying how many made it
+    fn read_fuses<T: JtagPhy>(profile: &DeviceProfile, jm: &mut JtagMach, jp: &mut ShiftCounter<T>, which: Readback, cmd: Ir, data_leg: JtagLeg) -> Result<JtagLeg, EfuseError> {
+        let expected: usize = data_leg.dbg_i_len();
+        jp.shifted = None;
+        match (Self::readback(profile, jm, jp, cmd, data_leg), jp.shifted) {
+            (Ok(Some(data)), _) if data.dbg_o_len() >= expected => Ok(data),
+            (Ok(data), _) => Err(EfuseError::ShortReadback { readback: which, captured: data.map_or(0, |d| d.dbg_o_len()), expected }),
+            // the cable or the target went away in the middle of the data
+            (Err(JtagError::Phy(PhyError::Transport)), Some(captured)) =>
+                Err(EfuseError::ShortReadback { readback: which, captured, expected }),
+            (Err(e), _) => Err(EfuseError::from_jtag(Phase::Fetch, e)),
+        }
+    }
 
+    /// Reads FUSE_KEY, all 256 bits in one leg. The register shifts out bank 1 first and the
+    /// 16 key bits of the shared bank last, each LSB first, so the key comes out as a single
+    /// LSB-first number: key byte 0 is its bottom byte.
+    fn read_key<T: JtagPhy>(profile: &DeviceProfile, jm: &mut JtagMach, jp: &mut ShiftCounter<T>) -> Result<[u8; 32], EfuseError> {
         let mut data_leg: JtagLeg = JtagLeg::new(JtagChain::DR, "fuse");
-        data_leg.push_u128(0, 128, JtagEndian::Big);
-        data_leg.push_u128(0, 128, JtagEndian::Big);
-        jm.add(data_leg);
-        jm.next(jp);
-        if let Some(mut data) = jm.get() {
-            let mut bank_data: u32;
-            for index in 0..KEY_BANKS {
-                if index == 0 {
-                    // first bank is special because it's split with the user fuse
-                    bank_data = data.pop_u32(16, JtagEndian::Little).unwrap();
-                    self.banks[11-index] = bank_data;
-                } else {
-                    bank_data = data.pop_u32(24, JtagEndian::Little).unwrap();
-                    self.banks[11-index] = add_ecc(bank_data);
-                }
-            }
-        } else {
-            assert!(false);
+        data_leg.push_bytes(&[0; 32], JtagEndian::Big).unwrap();
+        let mut data: JtagLeg = Self::read_fuses(profile, jm, jp, Readback::Key, Ir::FuseKey, data_leg)?;
+        let mut key: [u8; 32] = [0; 32];
+        // read_fuses() made sure all 256 bits came back
+        data.pop_bytes(&mut key, JtagEndian::Little).unwrap();
+        Ok(key)
+    }
+
+    /// Reads the banks back as raw as the readback instructions allow: the data bits of the key
+    /// and USER banks, and all `cntl_bits` of the CNTL bank, both copies included. Returns the
+    /// banks and, per bank, the fuses that were read.
+    fn capture_banks<T: JtagPhy>(profile: &DeviceProfile, jm: &mut JtagMach, jp: &mut T, cntl_bits: usize) -> Result<([u32; FUSE_BANKS], [u32; FUSE_BANKS]), EfuseError> {
+        let jp: &mut ShiftCounter<T> = &mut ShiftCounter::new(jp);
+        jm.try_reset(jp).map_err(|e| EfuseError::from_jtag(Phase::Fetch, e))?;
+        let mut banks: [u32; FUSE_BANKS] = [0; FUSE_BANKS];
+        let mut observed: [u32; FUSE_BANKS] = [0xFF_FFFF; FUSE_BANKS];
+
+        jp.pause(2000);
+        let key: [u8; 32] = Self::read_key(profile, jm, jp)?;
+        let mut logical: EfuseLogical = EfuseLogical { key, user: 0, cntl: 0 };
+
+        jp.pause(2000);
+        let mut data_leg: JtagLeg = JtagLeg::new(JtagChain::DR, "user");
+        data_leg.push_u32(0, 32, JtagEndian::Little).unwrap();
+        let mut data: JtagLeg = Self::read_fuses(profile, jm, jp, Readback::User, Ir::FuseUser, data_leg)?;
+        logical.user = data.pop_u32(32, JtagEndian::Little).unwrap();
+        for (index, bank) in banks.iter_mut().enumerate().filter(|&(index, _)| index != CNTL_BANK) {
+            *bank = F::bank_image(index, &logical);
+        }
+        secrets::wipe_bytes(&mut logical.key);
+
+        jp.pause(2000);
+        let mut data_leg: JtagLeg = JtagLeg::new(JtagChain::DR, "cntl");
+        data_leg.push_u32(0, cntl_bits, JtagEndian::Little).unwrap();
+        let mut data: JtagLeg = Self::read_fuses(profile, jm, jp, Readback::Cntl, Ir::FuseCntl, data_leg)?;
+        banks[CNTL_BANK] = data.pop_u32(cntl_bits, JtagEndian::Little).unwrap();
+        observed[CNTL_BANK] = F::bank_fuses(CNTL_BANK) & ((1u64 << cntl_bits) - 1) as u32;
+        banks[CNTL_BANK] &= observed[CNTL_BANK];
+        Ok((banks, observed))
+    }
+
+    /// Fetch the current fuse state. If the readbacks carry the signature of a device whose
+    /// security settings block them (see the access module), this fails with
+    /// AccessBlockedBySecurity and the fused state is left as it was. So it is if a readback
+    /// comes back short, with ShortReadback.
+    pub fn fetch<T: JtagPhy>(&mut self, jm: &mut JtagMach, jp: &mut T) -> Result<(), EfuseError> {
+        let result: Result<(), EfuseError> = self.read_state(jm, jp);
+        match result {
+            Ok(()) => events::fetched(&self.key, self.report.key == KeyReadback::Readable, self.user, self.cntl),
+            Err(ref e) => events::fetch_failed(e),
         }
-        // derive bits from bank data, to debug any bit-order issues on readout, etc.
-        for index in 0..32 {
-            self.key[index] = ((self.banks[((index / 3) + 1) as usize] >> ((index % 3) * 8)) & 0xFF) as u8;
+        result
+    }
+
+    /// Reads FUSE_CNTL at its full length, params.cntl_readback_bits, and takes both copies of
+    /// the CNTL bits into the fused state; see EfuseApi::read_cntl_copies()
+    pub fn read_cntl_copies<T: JtagPhy>(&mut self, jm: &mut JtagMach, jp: &mut T) -> Result<(u8, u8), EfuseError> {
+        if !self.fetched {
+            return Err(EfuseError::NotFetched);
         }
+        let jp: &mut ShiftCounter<T> = &mut ShiftCounter::new(jp);
+        jm.try_reset(jp).map_err(|e| EfuseError::from_jtag(Phase::Fetch, e))?;
+        jp.pause(2000);
+        let bits: usize = self.profile.params.cntl_readback_bits;
+        let mut data_leg: JtagLeg = JtagLeg::new(JtagChain::DR, "cntl");
+        data_leg.push_u32(0, bits, JtagEndian::Little).unwrap();
+        let mut data: JtagLeg = Self::read_fuses(&self.profile, jm, jp, Readback::Cntl, Ir::FuseCntl, data_leg)?;
+        let cntl_data: u32 = data.pop_u32(bits, JtagEndian::Little).unwrap();
+        let (primary, redundant) = (CntlCopy::Primary.extract(cntl_data), CntlCopy::Redundant.extract(cntl_data));
+        let mut key: [u8; 32] = self.key;
+        self.settle(&key, self.user, primary, redundant);
+        secrets::wipe_bytes(&mut key);
+        Ok((primary, redundant))
+    }
+
+    fn read_state<T: JtagPhy>(&mut self, jm: &mut JtagMach, jp: &mut T) -> Result<(), EfuseError> {
+        let fail = |e: JtagError| EfuseError::from_jtag(Phase::Fetch, e);
+        let profile: &DeviceProfile = &self.profile;
+        let jp: &mut ShiftCounter<T> = &mut ShiftCounter::new(jp);
+        jm.try_reset(jp).map_err(fail)?;
+
+        // get the KEY fuse
+        jp.pause(2000);
+        let mut key: [u8; 32] = Self::read_key(profile, jm, jp)?;
 
         jp.pause(2000);
         // get the USER fuse and populate the split bank
-        let mut ir_leg: JtagLeg = JtagLeg::new(JtagChain::IR, "cmd");
-        ir_leg.push_u32(CMD_FUSE_USER, 6, JtagEndian::Little);
-        jm.add(ir_leg);
-        jm.next(jp);
-        assert!(jm.get().is_some());
-
         let mut data_leg: JtagLeg = JtagLeg::new(JtagChain::DR, "user");
-        data_leg.push_u32(0, 32, JtagEndian::Little);
-        jm.add(data_leg);
-        jm.next(jp);
-        if let Some(mut data) = jm.get() {
-            let user_data: u32 = data.pop_u32(32, JtagEndian::Little).unwrap();
-            self.user = user_data;
-            self.banks[11] |= (user_data & 0xFF) << 16;
-            self.banks[11] = add_ecc(self.banks[11]);
-
-            self.banks[12] = add_ecc( (user_data >> 8) & 0xFF_FF_FF);
-        } else {
-            assert!(false);
-        }
+        data_leg.push_u32(0, 32, JtagEndian::Little).unwrap();
+        let mut data: JtagLeg = Self::read_fuses(profile, jm, jp, Readback::User, Ir::FuseUser, data_leg)?;
+        let user: u32 = data.pop_u32(32, JtagEndian::Little).unwrap();
 
         jp.pause(2000);
         // get the CNTL fuse
-        let mut ir_leg: JtagLeg = JtagLeg::new(JtagChain::IR, "cmd");
-        ir_leg.push_u32(CMD_FUSE_CNTL, 6, JtagEndian::Little);
-        jm.add(ir_leg);
-        jm.next(jp);
-        assert!(jm.get().is_some());
-
         let mut data_leg: JtagLeg = JtagLeg::new(JtagChain::DR, "cntl");
-        data_leg.push_u32(0, 14, JtagEndian::Little); // cntl only has 14 bits length, but only bottom 6 bits are documented
-        jm.add(data_leg);
-        jm.next(jp);
-        if let Some(mut data) = jm.get() {
-            let cntl_data: u32 = data.pop_u32(14, JtagEndian::Little).unwrap();
-            self.cntl = (cntl_data & 0x3F) as u8;
-            self.banks[0] = cntl_data & 0x3F;
-            self.banks[0] |= (cntl_data & 0x3F) << 14; // ths is the redundant value, no ECC on this bank
-        } else {
-            assert!(false);
+        data_leg.push_u32(0, profile.cntl_fetch_bits, JtagEndian::Little).unwrap();
+        let mut data: JtagLeg = Self::read_fuses(profile, jm, jp, Readback::Cntl, Ir::FuseCntl, data_leg)?;
+        let cntl_data: u32 = data.pop_u32(profile.cntl_fetch_bits, JtagEndian::Little).unwrap();
+
+        // a locked-down device can read just like a blank one; don't take its word for it
+        if let Some(detail) = access::blocked(&key, user, cntl_data, profile.cntl_fetch_bits, jm, jp).map_err(fail)? {
+            return Err(EfuseError::AccessBlockedBySecurity { detail });
         }
+        // a read too short to reach the redundant copy can't tell the copies apart
+        let primary: u8 = CntlCopy::Primary.extract(cntl_data);
+        let redundant: u8 = if profile.cntl_fetch_bits >= (CNTL_COPY_SHIFT + CNTL_MASK.count_ones()) as usize {
+            CntlCopy::Redundant.extract(cntl_data)
+        } else {
+            primary
+        };
+        self.settle(&key, user, primary, redundant);
+        secrets::wipe_bytes(&mut key);
+        Ok(())
+    }
+
+    /// takes the readbacks `key` and `user`, and the CNTL copies, as the fused state
+    fn settle(&mut self, key: &[u8; 32], user: u32, primary: u8, redundant: u8) {
+        self.user = user;
+        // the copies are separate fuses, so a bit blown in either one counts as blown
+        self.cntl = primary | redundant;
+        // with the key readback disabled, what came back is dropped rather than planned against
+        self.report.key = KeyReadback::of(self.cntl);
+        self.key = match self.report.key {
+            KeyReadback::Readable => *key,
+            KeyReadback::Unreadable => [0; 32],
+        };
+
+        // The physical image follows from the logical values we just read. FUSE_KEY and
+        // FUSE_USER shift out data bits only, so the ECC bits are derived rather than read: for
+        // a bank programmed by burn() they're the ones in the silicon, but an ECC fuse blown on
+        // its own can't be seen from here. The CNTL copies are as read.
+        self.banks = F::to_banks(&EfuseLogical { key: self.key, user: self.user, cntl: self.cntl });
+        self.banks[CNTL_BANK] = CntlCopy::Primary.deposit(primary) | CntlCopy::Redundant.deposit(redundant);
+        // and decoding the image must give back what FUSE_USER reported
+        self.report.user = UserConsistency::compare(self.user, F::user_from_banks(&self.banks));
+        self.fetched = true;
+    }
+}
+
+impl<F: DeviceFamily> Drop for EfusePhy<F> {
+    fn drop(&mut self) {
+        self.clear_secrets();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<F: DeviceFamily> zeroize::Zeroize for EfusePhy<F> {
+    fn zeroize(&mut self) {
+        self.clear_secrets();
     }
 }
 
-pub struct EfuseApi {
+#[cfg(feature = "zeroize")]
+impl<F: DeviceFamily> zeroize::ZeroizeOnDrop for EfusePhy<F> {}
+
+/// Intended fuse state, and the fused state it's planned against.
+///
+/// EfuseApi holds only plain data, so it's Send and Sync, as are the snapshots, manifests,
+/// plans and reports it hands out: planning can run on any thread, and a shared EfuseApi can
+/// be planned against from several. Talking to a device takes `&mut` and a JtagMach and phy
+/// of its own, so parallel fetches and burns need one of each per adapter.
+///
+/// `F` is the FPGA family, 7-series unless named; see the device module.
+pub struct EfuseApi<F: DeviceFamily = SevenSeries> {
     key: [u8; 32],
     user: u32,
     cntl: u8,
-    phy: EfusePhy,
+    phy: EfusePhy<F>,
+    report: Option<BurnReport>,
+    /// the banks the last burn should have left behind
+    predicted: Option<[u32; FUSE_BANKS]>,
+    verification: Option<verify::VerificationOutcome>,
+    allow_user_mismatch: bool,
+    allow_weak_key: bool,
+    allow_stray_fuses: bool,
+    force_key_patch: bool,
+    config: BurnConfig,
+    /// checksum of the staged state as of arm()
+    armed: Option<u32>,
+    /// what lets burn_cntl() lock the device; see the boot module
+    boot_check: BootCheck,
+    /// lets the next burn through an environment check it fails, for this reason
+    environment_override: Option<&'static str>,
+    #[cfg(feature = "fault-injection")]
+    fault: Option<integrity::VerdictFault>,
 }
 
 impl EfuseApi {
     pub fn new() -> Self {
+        EfuseApi::for_family()
+    }
+
+    /// new(), driving the device as `profile` says instead of DeviceProfile::SPARTAN7: for a
+    /// 7-series part or revision whose opcodes or programming words differ
+    pub fn new_with_profile(profile: DeviceProfile) -> Self {
+        let mut api: EfuseApi = EfuseApi::new();
+        api.phy.profile = profile;
+        api
+    }
+}
+
+impl Default for EfuseApi {
+    fn default() -> Self { EfuseApi::new() }
+}
+
+impl<F: DeviceFamily> EfuseApi<F> {
+    /// new(), for a family other than the default
+    pub fn for_family() -> Self {
         EfuseApi {
             key: [0; 32],
             user: 0,
             cntl: 0,
-            phy: EfusePhy::new(),
+            phy: EfusePhy::for_family(),
+            report: None,
+            predicted: None,
+            verification: None,
+            allow_user_mismatch: false,
+            allow_weak_key: false,
+            allow_stray_fuses: false,
+            force_key_patch: false,
+            config: BurnConfig::default(),
+            armed: None,
+            boot_check: BootCheck::NotRequired,
+            environment_override: None,
+            #[cfg(feature = "fault-injection")]
+            fault: None,
         }
     }
-    /// phy_ series of calls returns the current "phy" state, that is, the actual programmed state
+    /// phy_ series of calls returns the current "phy" state, that is, the actual programmed state.
+    /// phy_key() hands out a copy of the key; see the secrets module
     pub fn phy_key(&self) -> [u8; 32] { self.phy.key() }
     pub fn phy_user(&self) -> u32 { self.phy.user() }
     pub fn phy_cntl(&self) -> u8 { self.phy.cntl() }
+    /// the fused CNTL bits as flags; a fused reserved bit 0 is left out, as phy_cntl() has it
+    pub fn phy_cntl_flags(&self) -> CntlFlags { CntlFlags::from_bits_truncate(self.phy.cntl()) }
+    /// the primary and redundant copies of the fused CNTL bits; phy_cntl() is the two ORed
+    pub fn cntl_copies(&self) -> (u8, u8) { self.phy.cntl_copies() }
 
-    /// api_ series of call returns the current "api" state, which is the intended state to be programmed if not yet programmed
+    /// api_ series of call returns the current "api" state, which is the intended state to be programmed if not yet programmed.
+    /// api_key() hands out a copy of the key, as phy_key() does
     pub fn api_key(&self) -> [u8; 32] { self.key }
     pub fn api_user(&self) -> u32 { self.user }
     pub fn api_cntl(&self) -> u8 { self.cntl }
+    /// the staged CNTL bits as flags; a staged reserved bit 0 is left out, as api_cntl() has it
+    pub fn api_cntl_flags(&self) -> CntlFlags { CntlFlags::from_bits_truncate(self.cntl) }
+
+    /// true if the fetched key is `expected`, compared in constant time; compare against the
+    /// staged key with ct_eq_32(&api.api_key(), expected)
+    pub fn key_matches(&self, expected: &[u8; 32]) -> bool { ct_eq_32(&self.phy.key, expected) }
+
+    /// Overwrites the staged key, the fetched one, and the banks carrying them in the last
+    /// burn's plan and verification, with zeros; see the secrets module. A burn needs a fetch
+    /// and a key staged again.
+    pub fn clear_secrets(&mut self) {
+        secrets::wipe_bytes(&mut self.key);
+        self.phy.clear_secrets();
+        if let Some(predicted) = self.predicted.as_mut() {
+            secrets::wipe_key_banks(predicted);
+        }
+        if let Some(report) = self.report.as_mut() {
+            secrets::wipe_key_banks(&mut report.requested);
+            secrets::wipe_key_banks(&mut report.programmed);
+        }
+        if let Some(outcome) = self.verification.as_mut() {
+            secrets::wipe_key_banks(&mut outcome.predicted);
+            secrets::wipe_key_banks(&mut outcome.captured);
+        }
+    }
 
     /// this is a TEST FUNCTION ONLY. Unfortunately, the Rust test directive does not
     /// like this no_std runtime / std test environment.
     pub fn bank_patch(&mut self, index: usize, data: u32) { self.phy.bank_patch(index, data); }
 
-    // synchronizes the API state with the hardware. Needs to be called first.
-    pub fn fetch<T: JtagPhy>(&mut self, jm: &mut JtagMach, jp: &mut T) {
-        self.phy.fetch(jm, jp);
+    /// the fused banks, decoded into their data and ECC fields
+    pub fn phy_banks_logical(&self) -> [BankView; FUSE_BANKS] { self.phy.banks_logical() }
+
+    /// cross-checks made by the last fetch
+    pub fn fetch_report(&self) -> FetchReport { self.phy.report() }
+
+    /// let validate() plan USER changes even though the two USER readouts disagree
+    pub fn allow_user_mismatch(&mut self, allow: bool) { self.allow_user_mismatch = allow; }
+
+    pub fn burn_config(&self) -> BurnConfig { self.config }
+    /// the opcodes and programming words the device is driven with
+    pub fn profile(&self) -> &DeviceProfile { &self.phy.profile }
+    pub fn set_burn_config(&mut self, config: BurnConfig) { self.config = config; }
+
+    /// let validate() accept a suspicious key even alongside a CNTL change
+    pub fn allow_weak_key(&mut self, allow: bool) { self.allow_weak_key = allow; }
+
+    /// let verify_burn() report stray fuses in its outcome rather than fail with StrayFuses
+    pub fn allow_stray_fuses(&mut self, allow: bool) { self.allow_stray_fuses = allow; }
+
+    /// let validate() plan a key on top of a different one already fused, which ORs the two;
+    /// for patching a key whose bits were only partly blown
+    pub fn force_key_patch(&mut self, force: bool) { self.force_key_patch = force; }
+
+    /// Runs `verifier` between burn() and burn_cntl(); a pass is what lets burn_cntl() lock the
+    /// device (see the boot module). A failure withdraws an earlier pass or override.
+    pub fn verify_boot<V: BootVerifier>(&mut self, verifier: &mut V) -> Result<(), EfuseError> {
+        self.boot_check = BootCheck::NotRequired;
+        verifier.verify_encrypted_boot().map_err(|err| EfuseError::BootVerifyFailed { err })?;
+        self.boot_check = BootCheck::Verified;
+        Ok(())
     }
 
-    pub fn set_key(&mut self, new_key: [u8; 32]) {
-        for i in 0..32 {
-            self.key[i] = new_key[i];
+    /// let burn_cntl() lock the device without a verified boot; `reason` goes into its report
+    pub fn override_boot_verification(&mut self, reason: &'static str) { self.boot_check = BootCheck::Overridden { reason }; }
+
+    /// let the next burn go ahead even if its environment check fails; the readings are still
+    /// taken, and `reason` goes into its report along with them
+    pub fn override_environment_check(&mut self, reason: &'static str) { self.environment_override = Some(reason); }
+
+    /// Reads the die temperature, VCCINT and VCCAUX through the XADC and checks them against
+    /// `limits`. Nothing is refused here; the verdict is in the returned check.
+    pub fn check_environment<T: JtagPhy>(&mut self, limits: &EnvLimits, jm: &mut JtagMach, jp: &mut T) -> Result<PreBurnCheck, EfuseError> {
+        let readings = xadc::read(jm, jp).map_err(|e| EfuseError::from_jtag(Phase::Fetch, e))?;
+        Ok(PreBurnCheck::new(readings, limits))
+    }
+
+    /// the environment check BurnConfig::environment asks for, if any, taking up an override
+    fn pre_burn_check<T: JtagPhy>(&mut self, jm: &mut JtagMach, jp: &mut T) -> Result<Option<PreBurnCheck>, EfuseError> {
+        let overridden: Option<&'static str> = self.environment_override.take();
+        let limits: EnvLimits = match self.config.environment {
+            Some(limits) => limits,
+            None => return Ok(None),
+        };
+        let check = PreBurnCheck { overridden, ..self.check_environment(&limits, jm, jp)? };
+        match check.violation {
+            Some(violation) if !check.passed() => Err(EfuseError::OutOfSpec { violation, readings: check.readings }),
+            _ => Ok(Some(check)),
         }
     }
-    pub fn set_user(&mut self, new_user: u32) { self.user = new_user; }
-    pub fn set_cntl(&mut self, new_cntl: u8) { self.cntl = new_cntl; }
 
-    pub fn is_valid(&mut self) -> bool {
-        let mut valid: bool = true;
+    // synchronizes the API state with the hardware. Needs to be called first.
+    pub fn fetch<T: JtagPhy>(&mut self, jm: &mut JtagMach, jp: &mut T) -> Result<(), EfuseError> {
+        self.phy.fetch(jm, jp)
+    }
 
-        // go through each bank and check if the current configuratiion only involves 0->1 flips or no change
-        for index in 0..KEY_BANKS {
-            if index == 0 {
-                // handle cntl special case
-                if ((self.phy.banks[0] & 0x3F) as u8 ^ self.cntl) & (self.phy.banks[0] & 0x3F) as u8 != 0 {
-                    valid = false;
-                }
-            } else if index == 12 {
-                // handle user special case
-                if ((self.phy.banks[index] ^ add_ecc(self.user >> 8)) & self.phy.banks[index]) != 0 {
-                    valid = false;
-                }
-            } else if index == 11 {
-                // handle user + key special case
-                let raw_fuse: u32 = ((self.user & 0xFF) << 16) | (self.key[31] as u32) << 8 | self.key[30] as u32;
-                if ((self.phy.banks[index] ^ add_ecc(raw_fuse)) & self.phy.banks[index]) != 0 {
-                    valid = false;
-                }
-            } else {
-                // handle key fuses (most of the bank)
-                let mut raw_fuse: u32 = 0;
-                for i in 0..3 { 
-                    raw_fuse <<= 8;
-                    raw_fuse |= self.key[(index-1)*3 + 2-i] as u32; 
-                }
-                if ((self.phy.banks[index] ^ add_ecc(raw_fuse)) & self.phy.banks[index]) != 0 {
-                    valid = false;
-                }
+    /// fetch(), `n_reads` times over (at least once), for catching fuses that are only partly
+    /// blown, which can read differently from one read to the next. If every read agrees, the
+    /// fused state is what they read, as fetch() leaves it. If any data bit differs, this fails
+    /// with MarginalBits naming them per bank, and the fused state is left as it was; so it is
+    /// if any read fails. The ECC bits aren't read by a fetch, so they aren't compared.
+    pub fn fetch_stable<T: JtagPhy>(&mut self, n_reads: u32, jm: &mut JtagMach, jp: &mut T) -> Result<(), EfuseError> {
+        let mut agreed: EfusePhy<F> = EfusePhy::for_family();
+        agreed.profile = self.phy.profile;
+        agreed.fetch(jm, jp)?;
+        let mut first: [u32; FUSE_BANKS] = agreed.data_bits();
+        let mut marginal: [u32; FUSE_BANKS] = [0; FUSE_BANKS];
+        for _ in 1..n_reads {
+            let mut read: EfusePhy<F> = EfusePhy::for_family();
+            read.profile = self.phy.profile;
+            if let Err(e) = read.fetch(jm, jp) {
+                secrets::wipe_key_banks(&mut first);
+                return Err(e);
+            }
+            let mut bits: [u32; FUSE_BANKS] = read.data_bits();
+            for ((marginal, &first), &bits) in marginal.iter_mut().zip(first.iter()).zip(bits.iter()) {
+                *marginal |= first ^ bits;
             }
+            secrets::wipe_key_banks(&mut bits);
         }
-        valid
+        secrets::wipe_key_banks(&mut first);
+        if marginal.iter().any(|&bits| bits != 0) {
+            return Err(EfuseError::MarginalBits { bits: marginal });
+        }
+        self.phy = agreed;
+        Ok(())
     }
 
-    fn jtag_seq<T: JtagPhy>(&mut self, jm: &mut JtagMach, jp: &mut T, cmds: &[(JtagChain, usize, u64, &str)] ) -> u128 {
-        let mut ret: u128 = 0;
+    /// Reads both copies of the CNTL bits into the fused state, returning them as
+    /// cntl_copies() does. fetch() reads only as far as the primary copy and takes the
+    /// redundant one to match it, so copies that disagree, one of them having failed to
+    /// program, only show up in validate() and cntl_copies() after this. It's the full-length
+    /// read burn_cntl() verifies with. Refuses with NotFetched.
+    pub fn read_cntl_copies<T: JtagPhy>(&mut self, jm: &mut JtagMach, jp: &mut T) -> Result<(u8, u8), EfuseError> {
+        self.phy.read_cntl_copies(jm, jp)
+    }
 
-        for tuple in cmds.iter() {
-            let (chain, count, value, comment) = *tuple;
-            let mut leg: JtagLeg = JtagLeg::new(chain, comment);
-            leg.push_u128(value as u128, count, JtagEndian::Little);
-            jm.add(leg);
+    pub fn set_key(&mut self, new_key: [u8; 32]) {
+        self.key.copy_from_slice(&new_key);
+    }
+    /// Stages a key of runtime length, e.g. from a protocol message or a storage blob. Fails
+    /// with KeyLength, leaving the staged key as it was, unless `new_key` is exactly 32 bytes.
+    pub fn set_key_slice(&mut self, new_key: &[u8]) -> Result<(), EfuseError> {
+        if new_key.len() != 32 {
+            return Err(EfuseError::KeyLength { len: new_key.len() });
         }
-        while jm.has_pending() {
-            jp.pause(200); // 200us pause before starting a new series of commands
-            jm.next(jp);
-            if let Some(mut data) = jm.get() {
-                // it's safe to just pop the "max length" because pop is "best effort only"
-                ret = data.pop_u128(128, JtagEndian::Little).unwrap();
+        self.key.copy_from_slice(new_key);
+        Ok(())
+    }
+    /// Stages a key given as words, each little-endian: key byte 0 is the bottom byte of word 0
+    pub fn set_key_words(&mut self, new_key: &[u32; 8]) {
+        for (bytes, word) in self.key.chunks_exact_mut(4).zip(new_key.iter()) {
+            bytes.copy_from_slice(&word.to_le_bytes());
+        }
+    }
+    pub fn set_user(&mut self, new_user: u32) { self.user = new_user; }
+    /// Stages the `mask` bits of USER from `value`. The other bits become what a burn would
+    /// leave them as anyway: the staged value, plus whatever is fused as of the last fetch.
+    /// Fails without staging anything if that would clear a blown bit in the mask.
+    pub fn set_user_masked(&mut self, value: u32, mask: u32) -> Result<(), EfuseError> {
+        let cleared: u32 = self.phy.user() & mask & !value;
+        if cleared != 0 {
+            return Err(EfuseError::ClearsBlownUserBits { bits: cleared });
+        }
+        self.user = merge_user(self.user | self.phy.user(), value, mask);
+        Ok(())
+    }
+    /// the field `name` of `layout`, in the staged USER
+    pub fn get_field(&self, layout: &UserLayout, name: &str) -> Result<u32, EfuseError> {
+        Ok(layout.lookup(name)?.extract(self.user))
+    }
+    /// the field `name` of `layout`, in the fused USER
+    pub fn phy_field(&self, layout: &UserLayout, name: &str) -> Result<u32, EfuseError> {
+        Ok(layout.lookup(name)?.extract(self.phy.user()))
+    }
+    /// Stages `value` into the field `name` of `layout`, leaving the rest of the staged USER as
+    /// it is. Fails without staging anything if the value doesn't fit the field, or, with
+    /// ClearsBlownUserBits, if it would clear a bit of the field that's blown.
+    pub fn set_field(&mut self, layout: &UserLayout, name: &str, value: u32) -> Result<(), EfuseError> {
+        let field: UserField = layout.lookup(name)?;
+        let bits: u32 = field.deposit(value).map_err(|err| EfuseError::UserField { err })?;
+        let cleared: u32 = self.phy.user() & field.mask() & !bits;
+        if cleared != 0 {
+            return Err(EfuseError::ClearsBlownUserBits { bits: cleared });
+        }
+        self.user = merge_user(self.user, bits, field.mask());
+        Ok(())
+    }
+    /// Stages CNTL; fails with CntlReserved, staging nothing, for bits outside CNTL_MASK.
+    pub fn set_cntl(&mut self, new_cntl: u8) -> Result<(), EfuseError> {
+        let bits: u8 = new_cntl & !CNTL_MASK;
+        if bits != 0 {
+            return Err(EfuseError::CntlReserved { bits });
+        }
+        self.cntl = new_cntl;
+        Ok(())
+    }
+    /// Stages CNTL as named flags, which can't hold reserved bits
+    pub fn set_cntl_flags(&mut self, flags: CntlFlags) { self.cntl = flags.bits(); }
+
+    /// Stages the fused state as of the last fetch or snapshot, key, USER and CNTL, dropping
+    /// whatever was staged; nothing is shifted. An arm() seal is dropped with it. Afterwards
+    /// has_pending_changes() is false, unless the fused CNTL copies disagree, which a burn of
+    /// the fused CNTL would repair.
+    pub fn reset_pending(&mut self) {
+        self.key = self.phy.key;
+        self.user = self.phy.user();
+        self.cntl = self.phy.cntl();
+        self.armed = None;
+    }
+
+    /// whether the intended state's image differs from the fused banks in any bank, i.e.
+    /// whether burn() would have anything to program; USER is as burn() would write it, and
+    /// nothing is shifted
+    pub fn has_pending_changes(&self) -> bool {
+        let mut image: [u32; FUSE_BANKS] = self.image_with(self.planned_user().0);
+        let differs: bool = image.iter().zip(self.phy.banks.iter()).fold(false, |any, (image, fused)| any | (image != fused));
+        secrets::wipe_key_banks(&mut image);
+        differs
+    }
+
+    /// raw bank contents as of the last fetch
+    pub fn snapshot(&self) -> FuseSnapshot {
+        FuseSnapshot { banks: self.phy.banks }
+    }
+
+    /// Fetches the fuses and reads the configuration status, and from those works out which
+    /// key the device will decrypt bitstreams with at its next boot; see keysource.
+    pub fn boot_key_source<T: JtagPhy>(&mut self, jm: &mut JtagMach, jp: &mut T) -> Result<keysource::KeySourceStatus, EfuseError> {
+        self.fetch(jm, jp)?;
+        let evidence = keysource::evidence(&self.phy.key, self.phy.cntl(), jm, jp)?;
+        Ok(keysource::KeySourceStatus::from_evidence(evidence))
+    }
+
+    /// Fetches the fuses and says whether the device is blank, programmed, or can't be
+    /// inspected at all because its running configuration blocks the readbacks.
+    pub fn lock_status<T: JtagPhy>(&mut self, jm: &mut JtagMach, jp: &mut T) -> Result<access::LockStatus, EfuseError> {
+        match self.fetch(jm, jp) {
+            Err(EfuseError::AccessBlockedBySecurity { detail }) => Ok(access::LockStatus::AccessRestricted { detail }),
+            Err(e) => Err(e),
+            Ok(()) if self.phy.banks.iter().all(|&bank| bank == 0) => Ok(access::LockStatus::Blank),
+            Ok(()) => Ok(access::LockStatus::Readable { cntl: self.phy.cntl() }),
+        }
+    }
+
+    /// read the 64-bit device DNA through FUSE_DNA
+    pub fn read_dna<T: JtagPhy>(&mut self, jm: &mut JtagMach, jp: &mut T) -> Result<u64, EfuseError> {
+        let fail = |e: JtagError| EfuseError::from_jtag(Phase::Fetch, e);
+        jm.try_reset(jp).map_err(fail)?;
+        jp.pause(2000);
+        let mut data_leg: JtagLeg = JtagLeg::new(JtagChain::DR, "dna");
+        let bits: usize = self.phy.profile.dna_bits;
+        data_leg.push_u128(0, bits, JtagEndian::Little).unwrap();
+        match EfusePhy::<F>::readback(&self.phy.profile, jm, jp, Ir::FuseDna, data_leg).map_err(fail)? {
+            Some(mut data) => Ok(data.pop_u128(bits, JtagEndian::Little).unwrap() as u64),
+            None => Ok(0),
+        }
+    }
+
+    /// Reads the 57-bit device DNA, right-aligned: the top DNA_BITS of the FUSE_DNA register.
+    /// It's read twice, and refused with DnaUnreadable if it reads as 0, or DnaUnstable if the
+    /// two reads disagree, so a flaky cable can't tie a key to the wrong die.
+    pub fn dna<T: JtagPhy>(&mut self, jm: &mut JtagMach, jp: &mut T) -> Result<u64, EfuseError> {
+        let first: u64 = self.read_dna(jm, jp)? >> (64 - DNA_BITS);
+        let second: u64 = self.read_dna(jm, jp)? >> (64 - DNA_BITS);
+        if first != second {
+            return Err(EfuseError::DnaUnstable { first, second });
+        }
+        if first == 0 {
+            return Err(EfuseError::DnaUnreadable);
+        }
+        Ok(first)
+    }
+
+    /// Reads the IDCODE, refusing with NoIdcode if its bit 0 is clear: IEEE 1149.1 fixes that
+    /// bit at 1, so nothing answered, or the device has no IDCODE register.
+    pub fn idcode<T: JtagPhy>(&mut self, jm: &mut JtagMach, jp: &mut T) -> Result<u32, EfuseError> {
+        let read: u32 = self.read_idcode(jm, jp)?;
+        if read & 1 == 0 {
+            return Err(EfuseError::NoIdcode { read });
+        }
+        Ok(read)
+    }
+
+    /// read the 32-bit IDCODE
+    pub fn read_idcode<T: JtagPhy>(&mut self, jm: &mut JtagMach, jp: &mut T) -> Result<u32, EfuseError> {
+        let fail = |e: JtagError| EfuseError::from_jtag(Phase::Fetch, e);
+        jm.try_reset(jp).map_err(fail)?;
+        let mut data_leg: JtagLeg = JtagLeg::new(JtagChain::DR, "idcode");
+        data_leg.push_u32(0, 32, JtagEndian::Little).unwrap();
+        match EfusePhy::<F>::readback(&self.phy.profile, jm, jp, Ir::Idcode, data_leg).map_err(fail)? {
+            Some(mut data) => Ok(data.pop_u32(32, JtagEndian::Little).unwrap()),
+            None => Ok(0),
+        }
+    }
+
+    /// read USERCODE, which reflects the USER fuses
+    pub fn usercode<T: JtagPhy>(&mut self, jm: &mut JtagMach, jp: &mut T) -> Result<u32, EfuseError> {
+        let fail = |e: JtagError| EfuseError::from_jtag(Phase::Fetch, e);
+        jm.try_reset(jp).map_err(fail)?;
+        let mut data_leg: JtagLeg = JtagLeg::new(JtagChain::DR, "usercode");
+        data_leg.push_u32(0, 32, JtagEndian::Little).unwrap();
+        match EfusePhy::<F>::readback(&self.phy.profile, jm, jp, Ir::Usercode, data_leg).map_err(fail)? {
+            Some(mut data) => Ok(data.pop_u32(32, JtagEndian::Little).unwrap()),
+            None => Ok(0),
+        }
+    }
+
+    /// Reads USERCODE and checks it against the USER the last fetch read through FUSE_USER and
+    /// decoded from the banks. USERCODE is a separate path to the same fuses, so a disagreement
+    /// is a read path bug or a marginal fuse; it fails with Consistency, carrying the values
+    /// that disagree, FUSE_USER checked first. Returns USERCODE if all three agree.
+    pub fn cross_check_user<T: JtagPhy>(&mut self, jm: &mut JtagMach, jp: &mut T) -> Result<u32, EfuseError> {
+        if !self.phy.fetched() {
+            return Err(EfuseError::NotFetched);
+        }
+        let usercode: u32 = self.usercode(jm, jp)?;
+        let fuse_user: u32 = self.phy.user();
+        if usercode != fuse_user {
+            return Err(EfuseError::Consistency { err: ConsistencyError::FuseUser { usercode, fuse_user } });
+        }
+        let derived: u32 = F::user_from_banks(&self.phy.banks);
+        if usercode != derived {
+            return Err(EfuseError::Consistency { err: ConsistencyError::Banks { usercode, derived } });
+        }
+        Ok(usercode)
+    }
+
+    /// Stages the key derived for this device from `master` (see kdf): reads the device's DNA,
+    /// then stages HKDF-SHA256(master, salt = DNA, info). The key itself is never handed out;
+    /// the fingerprint is returned for the provisioning record.
+    #[cfg(feature = "kdf")]
+    pub fn stage_derived_key<T: JtagPhy>(&mut self, master: &keyhex::KeyBytes, info: &[u8], jm: &mut JtagMach, jp: &mut T) -> Result<kdf::KeyFingerprint, EfuseError> {
+        let dna: u64 = self.read_dna(jm, jp)?;
+        let mut key: keyhex::KeyBytes = kdf::derive_device_key(master, dna, info);
+        self.set_key(key);
+        sha256::wipe(&mut key);
+        Ok(kdf::KeyFingerprint::of(&self.key))
+    }
+
+    /// use a snapshot (e.g. one taken on another machine) as the fused state, in place of fetch()
+    pub fn load_snapshot(&mut self, snapshot: &FuseSnapshot) { self.phy.load_snapshot(snapshot); }
+
+    /// the intended state, as a manifest
+    pub fn manifest(&self) -> ProvisioningManifest {
+        ProvisioningManifest { key: self.key, user: self.user, cntl: self.cntl }
+    }
+
+    /// outcome of the last burn that got as far as programming, if any
+    pub fn last_report(&self) -> Option<BurnReport> {
+        self.report
+    }
+
+    /// outcome of the last verify_burn() that got as far as reading the banks back, if any
+    pub fn last_verification(&self) -> Option<&verify::VerificationOutcome> {
+        self.verification.as_ref()
+    }
+
+    /// Reads the banks back raw and compares them, fuse by fuse, against what the last burn
+    /// should have left behind: the fused state it was planned against plus every fuse it
+    /// requested. Without a burn, the prediction is the fused state as of the last fetch.
+    ///
+    /// Missing fuses are only reported in the outcome; burning the same plan again retries
+    /// them. Stray fuses fail with StrayFuses, naming the first bank that has any, as they mean
+    /// pulses went where they weren't addressed, unless allow_stray_fuses() is set. Either way
+    /// the outcome is kept for last_verification(), and its counts go into the last report.
+    /// The ECC codes can't be read back, so they
+    /// aren't compared; see the verify module.
+    pub fn verify_burn<T: JtagPhy>(&mut self, jm: &mut JtagMach, jp: &mut T) -> Result<verify::VerificationOutcome, EfuseError> {
+        let predicted: [u32; FUSE_BANKS] = self.predicted.unwrap_or(self.phy.banks);
+        let (captured, observed) = EfusePhy::<F>::capture_banks(&self.phy.profile, jm, jp, self.phy.profile.params.cntl_readback_bits)?;
+        let outcome: verify::VerificationOutcome = verify::VerificationOutcome::new(predicted, captured, observed);
+        self.verification = Some(outcome.clone());
+        if let Some(report) = self.report.as_mut() {
+            report.verify = Some(outcome.summary());
+        }
+        match outcome.first_stray() {
+            Some((bank, fuses)) if !self.allow_stray_fuses => Err(EfuseError::StrayFuses { bank, fuses }),
+            _ => Ok(outcome),
+        }
+    }
+
+    /// burn(), then verify_burn() on the same device; fails as burn() does without reading
+    /// anything back
+    pub fn burn_and_verify<T: JtagPhy>(&mut self, jm: &mut JtagMach, jp: &mut T) -> Result<verify::VerificationOutcome, EfuseError> {
+        self.burn(jm, jp)?;
+        self.verify_burn(jm, jp)
+    }
+
+    /// Stages a key written as 64 hex digits, most significant byte first as Vivado and .nky
+    /// files have it: the first two digits are key[31], the last two key[0]. See keyhex::parse()
+    /// for what else is accepted. Nothing is staged if it fails.
+    pub fn set_key_hex(&mut self, hex: &str) -> Result<(), EfuseError> {
+        let mut key: keyhex::KeyBytes = keyhex::parse(hex).map_err(|err| EfuseError::KeyHex { err })?;
+        self.set_key(key);
+        secrets::wipe_bytes(&mut key);
+        Ok(())
+    }
+
+    /// Writes the staged key into `out` as 64 uppercase hex digits, in the order set_key_hex()
+    /// reads them. This writes the key in the clear.
+    pub fn format_key_hex(&self, out: &mut [u8; 64]) {
+        keyhex::format_into(&self.key, out, keyhex::HexCase::Upper, keyhex::KeyOrder::MsbFirst);
+    }
+
+    /// set the intended key from the text of a Xilinx .nky file
+    pub fn stage_from_nky(&mut self, nky: &str) -> Result<(), keyhex::HexError> {
+        self.set_key(keyhex::key_from_nky(nky)?);
+        Ok(())
+    }
+
+    /// set the intended state from a manifest; nothing is staged if its CNTL is refused
+    pub fn stage(&mut self, manifest: &ProvisioningManifest) -> Result<(), EfuseError> {
+        self.set_cntl(manifest.cntl)?;
+        self.set_key(manifest.key);
+        self.set_user(manifest.user);
+        Ok(())
+    }
+
+    /// Stages an intent: its key, USER and CNTL as stage() would, and its weak key override.
+    /// Nothing is staged if EfuseIntent::check() refuses it.
+    pub fn apply_intent(&mut self, intent: &EfuseIntent) -> Result<(), EfuseError> {
+        intent.check()?;
+        self.stage(&ProvisioningManifest { key: intent.key, user: intent.user, cntl: intent.cntl })?;
+        self.allow_weak_key(intent.allow_weak_key);
+        Ok(())
+    }
+
+    /// Seals the staged state for the next burn() or burn_cntl(), returning its checksum (that
+    /// of manifest()).
+    ///
+    /// burn() then checks the staged key, USER and CNTL against the checksum before it starts,
+    /// and again right before the first unlock word; burn_cntl() before it starts and again
+    /// before committing each copy. Either refuses with StagedStateCorrupted if they've
+    /// changed: a staged state that sat in RAM waiting for an operator isn't trusted blindly.
+    /// Staging anything after arming counts as a change; arm again to burn it. The seal is used
+    /// up by the first burn() or burn_cntl() that gets as far as programming.
+    pub fn arm(&mut self) -> u32 {
+        let checksum: u32 = self.manifest().checksum();
+        self.armed = Some(checksum);
+        checksum
+    }
+
+    /// drop the seal set by arm(); burn() goes back to burning whatever is staged
+    pub fn disarm(&mut self) {
+        self.armed = None;
+    }
+
+    pub fn is_armed(&self) -> bool {
+        self.armed.is_some()
+    }
+
+    fn check_armed(&self) -> Result<(), EfuseError> {
+        match self.armed {
+            Some(checksum) if checksum != self.manifest().checksum() => Err(EfuseError::StagedStateCorrupted),
+            _ => Ok(()),
+        }
+    }
+
+    /// Flips `flip` in byte `offset` of the staged state's wire encoding (key, USER, CNTL;
+    /// see ProvisioningManifest::encode) behind arm()'s back, for testing its checks.
+    #[cfg(feature = "fault-injection")]
+    pub fn corrupt_staged(&mut self, offset: usize, flip: u8) {
+        let mut bytes: [u8; ProvisioningManifest::WIRE_LEN] = [0; ProvisioningManifest::WIRE_LEN];
+        self.manifest().encode(&mut bytes);
+        bytes[offset] ^= flip;
+        let corrupted: ProvisioningManifest = ProvisioningManifest::decode(&bytes).unwrap();
+        self.key = corrupted.key;
+        self.user = corrupted.user;
+        self.cntl = corrupted.cntl;
+    }
+
+    /// validate() as a bool, for callers that only need a yes or no
+    pub fn is_valid(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    /// Every blown fuse the intended state wants clear, per bank, split into data and ECC. Where
+    /// validate() fails with IllegalTransition this has the full picture; empty means no 1->0
+    /// transition is needed, though validate() can still refuse for other reasons.
+    pub fn conflicts(&self) -> TransitionConflicts {
+        self.conflicts_with(self.planned_user().0)
+    }
+
+    /// burns counted by the configured event counter so far, per the fused state; None if no
+    /// counter is configured
+    pub fn provisioning_events(&self) -> Option<u32> {
+        self.config.event_counter.map(|spec| spec.count(self.phy.user()))
+    }
+
+    /// Compares the key checksum fused in the configured field against that of `expected_key`,
+    /// per the fused state; None if no field is configured. Works whether or not the key reads
+    /// back, but a match is only weak evidence: see the checksum module.
+    pub fn check_key_checksum(&self, expected_key: &keyhex::KeyBytes) -> Option<KeyChecksumCheck> {
+        let field: u32 = self.config.key_checksum_field?;
+        let fused: u8 = checksum::extract(field, self.phy.user());
+        let expected: u8 = checksum::extract(field, checksum::deposit(field, checksum::key_checksum(expected_key)));
+        Some(if fused == 0 {
+            KeyChecksumCheck::NotRecorded
+        } else if fused == expected {
+            KeyChecksumCheck::Match
+        } else {
+            KeyChecksumCheck::Mismatch { fused, expected }
+        })
+    }
+
+    /// checks that the intended state can be burned on top of the fused state
+    pub fn validate(&self) -> Result<ValidationReport, EfuseError> {
+        let result: Result<ValidationReport, EfuseError> = self.check_intended();
+        if let Err(ref e) = result {
+            events::validation_failed(e);
+        }
+        result
+    }
+
+    fn check_intended(&self) -> Result<ValidationReport, EfuseError> {
+        let (user, counter_exhausted) = self.planned_user();
+        // if we can't tell what USER currently is, don't plan changes to it
+        if let UserConsistency::Mismatch { direct, derived } = self.phy.report().user {
+            if user != direct && !self.allow_user_mismatch {
+                return Err(EfuseError::UserMismatch { direct, derived });
+            }
+        }
+
+        // the key banks can't be planned against without the key: leaving them alone is all
+        // that's safe, and that includes the USER bits sharing a bank with the key
+        if self.phy.report().key == KeyReadback::Unreadable {
+            let key_banks: bool = self.requested_with(user).iter().enumerate().any(|(index, &ones)| F::holds_key(index) && ones != 0);
+            if !ct_eq_32(&self.key, &self.phy.key) || key_banks {
+                return Err(EfuseError::KeyUnreadable);
             }
         }
-        // only the very last sequence value is returned
-        ret
+
+        // go through each bank and check if the current configuratiion only involves 0->1 flips or
+        // no change, twice over; see the integrity module
+        self.verdict(VerdictStage::Validate, user, &Guard { requested: self.requested_with(user), scope: ALL_BANKS })
+            .map_err(|e| if e == EfuseError::Invalid { self.illegal_transition(user) } else { e })?;
+        // A key burned over another one leaves their OR, which is neither, even where only
+        // 0->1 transitions are needed. Banks are compared by their key bytes alone, so a change
+        // to the USER bits beside them in bank 11 isn't one, and blank banks of a key burned
+        // partway can still be filled in.
+        if (0..FUSE_BANKS).filter(|&index| F::holds_key(index)).fold(false, |any, index| any | self.key_overwritten(index)) && !self.force_key_patch {
+            return Err(EfuseError::KeyAlreadyProgrammed);
+        }
+
+        let mut report: ValidationReport = ValidationReport::default();
+        let (primary, redundant) = self.phy.cntl_copies();
+        if primary != redundant {
+            report.warnings.push(ValidationWarning::CntlCopyMismatch { primary, redundant });
+        }
+        if let (Some(spec), true) = (self.config.event_counter, counter_exhausted) {
+            let count: u32 = spec.count(user);
+            if spec.refuse_when_exhausted {
+                return Err(EfuseError::CounterExhausted { count });
+            }
+            report.warnings.push(ValidationWarning::CounterExhausted { count });
+        }
+        // only a key that is actually being changed is checked; a blank key left alone is fine
+        if !ct_eq_32(&self.key, &self.phy.key) {
+            if let Some(reason) = weak_key_reason(&self.key) {
+                report.warnings.push(ValidationWarning::SuspiciousKey { reason });
+                // every CNTL bit restricts the device, so burning any of them alongside a
+                // suspicious key could lock in a key nobody meant to use
+                let locking: bool = (self.cntl & !self.phy.cntl() & CNTL_MASK) != 0;
+                if locking {
+                    if !self.allow_weak_key {
+                        return Err(EfuseError::WeakKey { reason });
+                    }
+                    report.weak_key_overridden = true;
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// whether key bank `index` has key bits fused, and not those of the staged key
+    fn key_overwritten(&self, index: usize) -> bool {
+        let mut fused: EfuseLogical = EfuseLogical { key: self.phy.key, user: 0, cntl: 0 };
+        let mut staged: EfuseLogical = EfuseLogical { key: self.key, user: 0, cntl: 0 };
+        let fused_bank: u32 = F::bank_image(index, &fused);
+        let overwritten: bool = (fused_bank != 0) & (fused_bank != F::bank_image(index, &staged));
+        secrets::wipe_bytes(&mut fused.key);
+        secrets::wipe_bytes(&mut staged.key);
+        overwritten
     }
 
-    fn burn_bank<T: JtagPhy>(&mut self, bank: usize, ones: u32, jm: &mut JtagMach, jp: &mut T) {
-        if ones == 0 { // skip the bank if nothing to burn
-            return;
+    /// Shifts `cmds` and returns what the last one shifted out; see jtag_seq_each()
+    fn jtag_seq<T: JtagPhy>(&self, jm: &mut JtagMach, jp: &mut T, cmds: &[SeqCmd]) -> Result<u128, JtagError> {
+        let mut ret: u128 = 0;
+        self.jtag_seq_each(jm, jp, cmds, |_, capture| ret = capture)?;
+        Ok(ret)
+    }
+
+    /// Shifts `cmds`, handing `each` the tag and capture of every command in order, up to 128
+    /// bits of it. The first command whose capture doesn't match its compare fails the sequence
+    /// with CompareFailed, and the commands after it are dropped; `each` still sees it.
+    fn jtag_seq_each<T: JtagPhy, C: FnMut(&'static str, u128)>(&self, jm: &mut JtagMach, jp: &mut T, cmds: &[SeqCmd], mut each: C) -> Result<(), JtagError> {
+        let mut compared: Result<(), JtagError> = Ok(());
+
+        jm.add_seq(cmds)?;
+        while jm.has_pending() && compared.is_ok() {
+            jp.pause(200); // 200us pause before starting each command
+            #[cfg(feature = "critical-section")]
+            {
+                if self.config.critical_sections == CsPolicy::PerWord {
+                    critical_section::with(|_| jm.try_next(jp))?;
+                } else {
+                    jm.try_next(jp)?;
+                }
+            }
+            #[cfg(not(feature = "critical-section"))]
+            jm.try_next(jp)?;
+            jm.drain_completed(|mut data| {
+                if let Some(Err(e)) = data.compare_result() {
+                    compared = compared.and(Err(e));
+                }
+                // it's safe to just pop the "max length" because pop is "best effort only"
+                each(data.tag(), data.pop_u128(128, JtagEndian::Little).unwrap());
+            });
+        }
+        if compared.is_err() {
+            jm.clear_pending();
         }
+        compared
+    }
+
+    /// The EFUSE instruction, which every programming word is shifted under. A corrupted scan
+    /// could latch some other instruction right before a programming word, so it's checked
+    /// as the device params say.
+    fn efuse_ir(&self) -> SeqCmd {
+        SeqCmd::new(JtagChain::IR, self.phy.profile.ir_bits, self.phy.profile.fuse_cts as u64, "EFUSE")
+            .with_budget(IR_BUDGET)
+            .with_ir_verification(self.phy.profile.params.ir_verification)
+    }
+
+    /// Shifts one bank's programming words, re-issuing the instructions each kind of word needs.
+    /// The status captured by the bit and wait words is decoded, and any error or'd into
+    /// the bank's status in `progress`, whose observer is told of each bit once its wait word
+    /// is through.
+    fn burn_words<T: JtagPhy, I: Iterator<Item = ProgramWord>>(&self, bank: usize, words: I, progress: &mut BurnProgress, jm: &mut JtagMach, jp: &mut T) -> Result<(), JtagError> {
         jp.pause(2500); // 2.5ms pause between banks
 
-        let mut bank_select: u8 = 1; // bank 0 by default (special case)
-        let mut word_select: u8 = 3;
-        if bank > 0 { // rest of banks
-            bank_select = (bank as u8 - 1) * 8 + 0xA1;
-            word_select = bank_select | 0b10;
-        }
-
-        let bank_fuse: [(JtagChain, usize, u64, &str); 7] = [
-            (JtagChain::IR, 6, 0b001100, "JSTART"),
-            (JtagChain::IR, 6, 0b110000, "EFUSE"),
-            (JtagChain::DR, 64, 0xa08a28ac00004001, "KEY_UNLOCK1"),
-            (JtagChain::DR, 64, 0xa08a28ac00004001, "KEY_UNLOCK2"),
-            (JtagChain::IR, 6, 0b110000, "EFUSE"),
-            (JtagChain::DR, 64, 0xa08a28ac00000000 | bank_select as u64, "KEY_BANK"),
-            (JtagChain::DR, 64, 0x0, "KEY_BANK_WAIT"),
-        ];
-        self.jtag_seq(jm, jp, &bank_fuse);
-        let mut curbit = ones;
-        for i in 0..32 {
-            if (curbit & 0x1) == 1 {
-                let bit_burn: [(JtagChain, usize, u64, &str); 3] = [
-                    (JtagChain::IR, 6, 0b110000, "EFUSE"),
-                    (JtagChain::DR, 64, (0xa08a28ac00004000 | (word_select as u64)) + ((i as u64) << 8), "KEY_BIT"),
-                    (JtagChain::DR, 64, 0x0, "KEY_BIT_WAIT"),
-                ];
-                self.jtag_seq(jm, jp, &bit_burn);
+        let mut prev: Option<WordKind> = None;
+        for word in words {
+            // the instruction the word needs, if any, then the word itself
+            let (ir, tag): (Option<SeqCmd>, &'static str) = match word.kind {
+                WordKind::Unlock => {
+                    if prev != Some(WordKind::Unlock) {
+                        // the first of the unlock pair re-opens the programming port
+                        self.jtag_seq(jm, jp, &[SeqCmd::new(JtagChain::IR, self.phy.profile.ir_bits, self.phy.profile.jstart as u64, "JSTART").with_budget(IR_BUDGET),
+                            self.efuse_ir()])?;
+                        // the unlock has to start from RUN_TEST/IDLE, where every scan ends
+                        jm.expect_state(TapState::RunTestIdle)?;
+                        (None, "KEY_UNLOCK1")
+                    } else {
+                        (None, "KEY_UNLOCK2")
+                    }
+                },
+                WordKind::BankSelect => (Some(self.efuse_ir()), "KEY_BANK"),
+                WordKind::Bit(_) => (Some(self.efuse_ir()), "KEY_BIT"),
+                WordKind::Wait => (None, "KEY_WAIT"),
+            };
+            let budget: u32 = if word.kind == WordKind::Unlock { UNLOCK_BUDGET } else { PROGRAM_BUDGET };
+            let dr: SeqCmd = SeqCmd::new(JtagChain::DR, self.phy.profile.params.dr_bits, word.value, tag).with_budget(budget);
+            let cmds: &[SeqCmd] = match ir {
+                Some(ir) => &[ir, dr],
+                None => &[dr],
+            };
+            // every word shifted under FUSE_CTS shifts the port's status out, the unlock and
+            // bank select words as much as the pulses; see the status module. Those two come
+            // before this bank's first pulse, so an error they show is an earlier bank's, and
+            // being sticky, it's new only if no word has shown one yet.
+            let mut status: FuseStatus = FuseStatus::default();
+            self.jtag_seq_each(jm, jp, cmds, |leg, capture| if leg == tag { status = FuseStatus::from_capture(capture) })?;
+            if status.is_error() {
+                match word.kind {
+                    WordKind::Unlock | WordKind::BankSelect => {
+                        if progress.statuses.iter().all(|&raw| raw == 0) {
+                            progress.statuses[progress.pulsed.unwrap_or(bank)] |= status.raw;
+                        }
+                    },
+                    _ => progress.statuses[bank] |= status.raw,
+                }
+            }
+            let (idle, delay) = self.config.timing.after(word.kind);
+            if idle != 0 {
+                jm.try_idle(jp, idle)?;
+            }
+            if delay != 0 {
+                jp.pause(delay);
             }
-            curbit >>= 1;
+            if let (Some(WordKind::Bit(bit)), WordKind::Wait) = (prev, word.kind) {
+                if let Some(jitter) = self.config.timing.inter_bit_jitter {
+                    jm.try_idle(jp, jitter.gap(progress.bits_done))?;
+                }
+                progress.bits_done += 1;
+                progress.pulsed = Some(bank);
+                progress.programmed[bank] |= 1 << bit;
+                progress.observer.bit_burned(bank, bit as u32);
+            }
+            prev = Some(word.kind);
         }
-        self.jtag_seq(jm, jp, &bank_fuse);
+        Ok(())
     }
 
     // burns fuses to the FPGA bank
-    pub fn burn<T: JtagPhy>(&mut self, jm: &mut JtagMach, jp: &mut T) -> bool {
-        const COMMIT_SEQ: [(JtagChain, usize, u64, &str); 22] = 
-            [
-                (JtagChain::DR, 64, 0xff000000ff, "EFUSE_COMMIT"),
-                (JtagChain::IR, 6, 0b000010, "USER1"),
-                (JtagChain::DR, 32, 0, "USER1"),
-                (JtagChain::IR, 6, 0b000010, "USER1"),
-                (JtagChain::DR, 17, 0xF000, "USER1"),
-                (JtagChain::DR, 75, 0xA9, "USER1"),
-                (JtagChain::IR, 6, 0b100010, "USER3"),
-                (JtagChain::DR, 17, 0xF000, "USER3"),
-                (JtagChain::DR, 75, 0xA9, "USER3"),
-                (JtagChain::IR, 6, 0b111111, "BYPASS"),
-                (JtagChain::IR, 6, 0b000011, "USER2"),
-                (JtagChain::DR, 32, 0x0, "USER2"),
-                (JtagChain::IR, 6, 0b111111, "BYPASS"),
-                (JtagChain::IR, 6, 0b000011, "USER2"),
-                (JtagChain::DR, 42, 0x69, "USER2"),
-                (JtagChain::IR, 6, 0b111111, "BYPASS"),
-                (JtagChain::IR, 6, 0b000011, "USER2"),
-                (JtagChain::DR, 6, 0xC, "USER2"),
-                (JtagChain::DR, 42, 0x69, "USER2"),
-                (JtagChain::IR, 6, 0b111111, "BYPASS"),
-                (JtagChain::IR, 6, 0b000011, "USER2"),
-                (JtagChain::DR, 36, 0x0, "USER2"),
-            ];
-
-        let ok: bool = true;
+    pub fn burn<T: JtagPhy>(&mut self, jm: &mut JtagMach, jp: &mut T) -> Result<(), EfuseError> {
+        self.burn_with_observer(&mut (), jm, jp)
+    }
+
+    /// burn(), returning the report it records; see BurnReport. A failed burn records one too,
+    /// for last_report().
+    pub fn burn_report<T: JtagPhy>(&mut self, jm: &mut JtagMach, jp: &mut T) -> Result<BurnReport, EfuseError> {
+        self.burn(jm, jp)?;
+        Ok(self.report.expect("a burn that succeeds records a report"))
+    }
 
+    /// burn(), telling `observer` how it's going; see the observer module
+    pub fn burn_with_observer<T: JtagPhy, O: BurnObserver>(&mut self, observer: &mut O, jm: &mut JtagMach, jp: &mut T) -> Result<(), EfuseError> {
+        // a plan against the blank state new() starts from could re-program anything
+        if !self.phy.fetched() {
+            return Err(EfuseError::NotFetched);
+        }
+        // if armed, what's staged must still be what was armed
+        self.check_armed()?;
         // first check if we're valid
-        if !self.is_valid() {
-            return false;
+        let validation: ValidationReport = self.validate()?;
+        let requested: [u32; FUSE_BANKS] = self.requested();
+        let sections = bank_sections(requested, &self.phy.profile.params, self.config.order);
+        let report = BurnReport { requested, committed: false, weak_key_overridden: validation.weak_key_overridden, order: self.config.order, manifest: None, status_errors: [0; FUSE_BANKS], boot_check: BootCheck::NotRequired, pre_burn: None, programmed: [0; FUSE_BANKS], pulses: 0, verify: None };
+        let result: Result<(), EfuseError> = self.program(sections, report, Some(Guard { requested, scope: ALL_BANKS }), observer, jm, jp);
+        // one burn per arming, whatever the outcome
+        self.armed = None;
+        result
+    }
+
+    /// What the intended state changes, per key byte, USER bit and CNTL bit, split into legal
+    /// 0->1 changes and illegal 1->0 ones, with the fuses a burn would blow; see StateDiff. It's
+    /// worked out from the bank images validate() checks, USER as burn() would write it, but
+    /// doesn't validate. Nothing is shifted.
+    pub fn diff(&self) -> StateDiff {
+        let mut image: [u32; FUSE_BANKS] = self.image_with(self.planned_user().0);
+        let mut set: [u32; FUSE_BANKS] = [0; FUSE_BANKS];
+        let mut cleared: [u32; FUSE_BANKS] = [0; FUSE_BANKS];
+        for index in 0..FUSE_BANKS {
+            set[index] = image[index] & !self.phy.banks[index];
+            cleared[index] = self.phy.banks[index] & !image[index];
         }
+        secrets::wipe_key_banks(&mut image);
 
-        // reset the machine before doing any burning
-        jp.pause(2000); 
+        // the masks decode as the banks do: bit for bit into key bytes, USER and CNTL
+        let mut key_set: [u8; 32] = F::from_banks(&set).key;
+        let mut key_cleared: [u8; 32] = F::from_banks(&cleared).key;
+        let bytes = |key: &[u8; 32]| key.iter().enumerate().fold(0u32, |mask, (i, &b)| mask | ((b != 0) as u32) << i);
+        let diff = StateDiff {
+            key_set: bytes(&key_set),
+            key_cleared: bytes(&key_cleared),
+            user_set: F::user_from_banks(&set),
+            user_cleared: F::user_from_banks(&cleared),
+            cntl_set: CntlCopy::Primary.extract(set[CNTL_BANK]) | CntlCopy::Redundant.extract(set[CNTL_BANK]),
+            cntl_cleared: CntlCopy::Primary.extract(cleared[CNTL_BANK]) | CntlCopy::Redundant.extract(cleared[CNTL_BANK]),
+            ecc_cleared: (0..FUSE_BANKS).filter(|&index| BankConflict::new(index, cleared[index]).ecc != 0).fold(0, |mask, index| mask | 1 << index),
+            fuses: set.iter().map(|bits| bits.count_ones()).sum(),
+        };
+        secrets::wipe_bytes(&mut key_set);
+        secrets::wipe_bytes(&mut key_cleared);
+        secrets::wipe_key_banks(&mut set);
+        secrets::wipe_key_banks(&mut cleared);
+        diff
+    }
+
+    /// The fuses burn() would blow for the current plan, from the same bank images; refuses as
+    /// validate() does. Nothing is shifted.
+    pub fn plan(&self) -> Result<BurnPlan, EfuseError> {
+        self.validate()?;
+        Ok(BurnPlan::new(self.requested()))
+    }
+
+    /// The words burn() would shift for the current plan, frozen; see burn_from_manifest().
+    pub fn compile(&self) -> Result<CompiledSequence, EfuseError> {
+        self.validate()?;
+        Ok(CompiledSequence::new(&self.requested(), &self.phy.profile.params, self.config.order))
+    }
+
+    /// Burns a compiled sequence exactly as it was qualified.
+    ///
+    /// Refuses with ManifestMismatch, before touching the device, if `seq` doesn't hash to
+    /// `manifest` or was compiled for other device parameters than these. It then fetches, and
+    /// refuses with SequenceIncompatible if the sequence would re-program a fuse that's already
+    /// blown, or with Invalid if a bank it leaves behind wouldn't decode: a key or user bank
+    /// failing ECC, or CNTL copies that disagree. The staged state plays no part; the words are
+    /// shifted as compiled, and the manifest is recorded in the report.
+    pub fn burn_from_manifest<T: JtagPhy>(&mut self, seq: &CompiledSequence, manifest: &VectorManifest, jm: &mut JtagMach, jp: &mut T) -> Result<(), EfuseError> {
+        if seq.manifest() != *manifest || manifest.device_params_id != self.phy.profile.params.id() {
+            return Err(EfuseError::ManifestMismatch);
+        }
+        self.fetch(jm, jp)?;
+        let programmed: [u32; FUSE_BANKS] = seq.programmed();
+        for (index, &ones) in programmed.iter().enumerate() {
+            let fused: u32 = self.phy.banks[index];
+            if fused & ones != 0 {
+                return Err(EfuseError::SequenceIncompatible { bank: index, fuses: fused & ones });
+            }
+            if !F::bank_consistent(index, fused | ones) {
+                return Err(EfuseError::Invalid);
+            }
+        }
+        let sections = seq.sections().map(|(index, words)| (index, words.iter().copied()));
+        let report = BurnReport { requested: programmed, committed: false, weak_key_overridden: false, order: seq.order(), manifest: Some(*manifest), status_errors: [0; FUSE_BANKS], boot_check: BootCheck::NotRequired, pre_burn: None, programmed: [0; FUSE_BANKS], pulses: 0, verify: None };
+        self.program(sections, report, None, &mut (), jm, jp)
+    }
+
+    /// Provisions device `target` of the scan chain `chain` with `manifest`.
+    ///
+    /// The machine's padding is set to address `target`, then the device's IDCODE and DNA are
+    /// read and checked against the manifest, refusing with WrongDevice before anything is
+    /// staged. The manifest is then staged, the fuses fetched and the burn carried out. Whatever
+    /// the outcome, the padding is put back as it was and the TAPs are reset, so the next
+    /// target starts from a clean chain.
+    pub fn apply_manifest<T: JtagPhy>(&mut self, chain: &chain::ScanChain, target: chain::ChainTarget, manifest: &chain::TargetManifest, jm: &mut JtagMach, jp: &mut T) -> Result<(), EfuseError> {
+        let padding: ChainPadding = chain.padding(target)
+            .ok_or(EfuseError::NoSuchTarget { index: target.index, devices: chain.len() })?;
+        let previous: ChainPadding = jm.padding();
+        jm.set_padding(padding);
+        let result: Result<(), EfuseError> = self.apply_to_target(manifest, jm, jp);
+        jm.set_padding(previous);
+        let reset: Result<(), JtagError> = jm.try_reset(jp);
+        result?;
+        reset.map_err(|e| EfuseError::from_jtag(Phase::Commit, e))
+    }
+
+    fn apply_to_target<T: JtagPhy>(&mut self, manifest: &chain::TargetManifest, jm: &mut JtagMach, jp: &mut T) -> Result<(), EfuseError> {
+        let idcode: u32 = self.read_idcode(jm, jp)?;
+        let dna: u64 = self.read_dna(jm, jp)?;
+        if !manifest.matches(idcode, dna) {
+            return Err(EfuseError::WrongDevice { idcode, dna });
+        }
+        self.fetch(jm, jp)?;
+        self.stage(&manifest.provisioning)?;
+        self.burn(jm, jp)
+    }
+
+    /// Burns the staged CNTL bits on their own, as the final lockdown step.
+    ///
+    /// The CNTL bits restrict what can be done with the device afterwards, so they go last:
+    /// this refuses with CntlNotLast while any key or USER bits are still to be burned. Each
+    /// copy of the CNTL bits is programmed and committed separately, in an ISC bracket of its
+    /// own if the profile has one, then read back through FUSE_CNTL; a copy that doesn't read
+    /// back as staged fails the burn with CntlVerify, naming the copy, and a failed primary
+    /// copy stops the redundant one from being touched. As in burn(), the validation verdict is
+    /// taken again before each copy's first programming word.
+    ///
+    /// burn() still programs CNTL along with everything else, in one pass and unverified. To
+    /// use this path, burn() with CNTL staged as fused, then stage CNTL and call burn_cntl().
+    ///
+    /// In between, verify_boot() has to confirm the device boots with the burned key, or
+    /// override_boot_verification() say why it can't; otherwise this refuses with
+    /// BootNotVerified before touching the device.
+    pub fn burn_cntl<T: JtagPhy>(&mut self, jm: &mut JtagMach, jp: &mut T) -> Result<(), EfuseError> {
+        // if armed, what's staged must still be what was armed
+        self.check_armed()?;
+        let validation: ValidationReport = self.validate()?;
+        let requested: [u32; FUSE_BANKS] = self.requested();
+        if requested.iter().enumerate().any(|(index, &ones)| index != CNTL_BANK && ones != 0) {
+            return Err(EfuseError::CntlNotLast);
+        }
+        if self.boot_check == BootCheck::NotRequired {
+            return Err(EfuseError::BootNotVerified);
+        }
+        self.check_idcode(jm, jp)?;
+        let pre_burn: Option<PreBurnCheck> = self.pre_burn_check(jm, jp)?;
+        let report: BurnReport = BurnReport {
+            requested,
+            committed: false,
+            weak_key_overridden: validation.weak_key_overridden,
+            order: BitOrderPolicy::Ascending,
+            manifest: None,
+            status_errors: [0; FUSE_BANKS],
+            boot_check: self.boot_check,
+            pre_burn,
+            programmed: [0; FUSE_BANKS],
+            pulses: 0,
+            verify: None,
+        };
+        let result: Result<(), EfuseError> = self.burn_cntl_copies(self.cntl & CNTL_MASK, Some(Guard { requested, scope: ALL_BANKS }), report, jm, jp);
+        // one burn per arming, whatever the outcome
+        self.armed = None;
+        result
+    }
+
+    /// Programs the CNTL bits one copy is missing and the other has, after a burn that left
+    /// the copies disagreeing (see ValidationWarning::CntlCopyMismatch). The copies are read
+    /// first, as read_cntl_copies() reads them, and if they agree nothing is burned. Otherwise
+    /// each copy goes as with burn_cntl(), committed and read back, but nothing else is burned
+    /// and nothing staged is looked at: the bits are already in effect through the other copy,
+    /// so no boot verification is asked for. Refuses with NotFetched before touching the
+    /// device; a copy that doesn't read back repaired fails with CntlVerify. The burn is
+    /// recorded as the last report, like any other.
+    pub fn repair_cntl<T: JtagPhy>(&mut self, jm: &mut JtagMach, jp: &mut T) -> Result<(), EfuseError> {
+        let (primary, redundant) = self.phy.read_cntl_copies(jm, jp)?;
+        if primary == redundant {
+            return Ok(());
+        }
+        let both: u8 = primary | redundant;
+        let mut requested: [u32; FUSE_BANKS] = [0; FUSE_BANKS];
+        requested[CNTL_BANK] = (CntlCopy::Primary.deposit(both) | CntlCopy::Redundant.deposit(both)) & !self.phy.banks[CNTL_BANK];
+        self.check_idcode(jm, jp)?;
+        let pre_burn: Option<PreBurnCheck> = self.pre_burn_check(jm, jp)?;
+        let report = BurnReport { requested, committed: false, weak_key_overridden: false, order: BitOrderPolicy::Ascending, manifest: None, status_errors: [0; FUSE_BANKS], boot_check: self.boot_check, pre_burn, programmed: [0; FUSE_BANKS], pulses: 0, verify: None };
+        self.burn_cntl_copies(both, None, report, jm, jp)
+    }
+
+    /// Programs `staged` into each copy of the CNTL bits, recording `report` as the last one.
+    /// With `guard`, the validation verdict and arm()'s seal are checked again as with burn().
+    fn burn_cntl_copies<T: JtagPhy>(&mut self, staged: u8, guard: Option<Guard>, mut report: BurnReport, jm: &mut JtagMach, jp: &mut T) -> Result<(), EfuseError> {
+        #[cfg(feature = "critical-section")]
+        let result: Result<(), EfuseError> = match self.config.critical_sections {
+            CsPolicy::PerBit => self.program_cntl_copies(staged, guard, &mut report, jm, &mut CsPhy(jp)),
+            _ => self.program_cntl_copies(staged, guard, &mut report, jm, jp),
+        };
+        #[cfg(not(feature = "critical-section"))]
+        let result: Result<(), EfuseError> = self.program_cntl_copies(staged, guard, &mut report, jm, jp);
+        report.committed = committed(&result);
+        self.predicted = Some(self.predict(&report.requested));
+        self.report = Some(report);
+        result
+    }
+
+    /// fills in the statuses, programmed fuses and pulses of `report`
+    fn program_cntl_copies<T: JtagPhy>(&self, staged: u8, guard: Option<Guard>, report: &mut BurnReport, jm: &mut JtagMach, jp: &mut T) -> Result<(), EfuseError> {
+        jp.pause(2000);
+        jm.try_reset(jp).map_err(|e| EfuseError::from_jtag(Phase::Burn, e))?;
+        jp.pause(2000);
+
+        let mut progress: BurnProgress = BurnProgress { bits_done: 0, pulsed: None, statuses: &mut report.status_errors, programmed: &mut report.programmed, observer: &mut () };
+        let mut result: Result<(), EfuseError> = Ok(());
+        for copy in CntlCopy::ALL.iter().copied() {
+            let to_set: u8 = staged & !copy.extract(self.phy.banks[CNTL_BANK]);
+            if to_set != 0 {
+                result = self.isc_bracketed(jm, jp, |jm, jp| {
+                    // re-derived before each copy's first programming word, as burn() does
+                    if let Some(guard) = guard {
+                        self.verdict(VerdictStage::PreUnlock, self.planned_user().0, &guard)
+                            .map_err(|_| EfuseError::ValidationIntegrity)?;
+                    }
+                    self.program_cntl_copy(copy, to_set, guard.is_some(), &mut progress, jm, jp)
+                });
+            }
+            result = result.and_then(|_| self.verify_cntl_copy(copy, staged, jm, jp));
+            if result.is_err() {
+                jm.clear_pending();
+                break;
+            }
+        }
+        report.pulses = progress.bits_done as u32;
+        jp.pause(2000);
         jm.reset(jp);
-        jp.pause(2000); 
-        
-        // iterate through banks, careful to make bank 0 the last
-        for index in (0..FUSE_BANKS).rev() {
-            if index == 0 {
-                // handle cntl special case
-                if ((self.phy.banks[0] & 0x3F) as u8 ^ self.cntl) != 0 {
-                    // 1111_1100_0000_0011_1111
-                    let new_cntl: u32 = (self.cntl as u32) | ((self.cntl as u32) << 14);
-                    self.burn_bank(index, ((self.phy.banks[0] & 0xFC03F) ^ new_cntl) & new_cntl, jm, jp);
-                }
-            } else if index == 12 {
-                // handle user special case
-                if (self.phy.banks[index] ^ add_ecc(self.user >> 8)) != 0 {
-                    // compute just the 0->1's and pass that on to burn_bank
-                    self.burn_bank(index, self.phy.banks[index] ^ add_ecc(self.user >> 8) & add_ecc(self.user >> 8), jm, jp);
-                }
-            } else if index == 11 {
-                // handle user + key special case
-                let raw_fuse: u32 = ((self.user & 0xFF) << 16) | (self.key[31] as u32) << 8 | self.key[30] as u32;
-                if (self.phy.banks[index] ^ add_ecc(raw_fuse)) != 0 {
-                    self.burn_bank(index, (self.phy.banks[index] ^ add_ecc(raw_fuse)) & add_ecc(raw_fuse), jm, jp);
-                }
-            } else {
-                // handle key fuses (most of the bank)
-                let mut raw_fuse: u32 = 0;
-                for i in 0..3 { 
-                    raw_fuse <<= 8;
-                    raw_fuse |= self.key[(index-1)*3 + 2-i] as u32; 
-                }
-                if (self.phy.banks[index] ^ add_ecc(raw_fuse)) != 0 {
-                    self.burn_bank(index, (self.phy.banks[index] ^ add_ecc(raw_fuse)) & add_ecc(raw_fuse), jm, jp);
-                }
+        result
+    }
+
+    /// programs `to_set` into one copy of the CNTL bits and commits it, then checks the port's
+    /// status; if `sealed`, the commit waits on the staged state still matching arm()'s seal
+    fn program_cntl_copy<T: JtagPhy>(&self, copy: CntlCopy, to_set: u8, sealed: bool, progress: &mut BurnProgress, jm: &mut JtagMach, jp: &mut T) -> Result<(), EfuseError> {
+        self.burn_words(CNTL_BANK, program_cntl_copy(to_set, copy, &self.phy.profile.params), progress, jm, jp)
+            .map_err(|e| EfuseError::from_jtag(Phase::Burn, e))?;
+        if sealed {
+            self.check_armed()?;
+        }
+        jp.pause(2000);
+        self.jtag_seq(jm, jp, &F::commit_seq(&self.phy.profile)).map_err(|e| EfuseError::from_jtag(Phase::Commit, e))?;
+        self.check_status(progress.statuses, jm, jp)
+    }
+
+    /// checks that one copy of the CNTL bits reads back as `staged`
+    fn verify_cntl_copy<T: JtagPhy>(&self, copy: CntlCopy, staged: u8, jm: &mut JtagMach, jp: &mut T) -> Result<(), EfuseError> {
+        jp.pause(2000);
+        let bits: usize = self.phy.profile.params.cntl_readback_bits;
+        let mut data_leg: JtagLeg = JtagLeg::new(JtagChain::DR, "cntl");
+        data_leg.push_u32(0, bits, JtagEndian::Little).unwrap();
+        let read: u8 = match EfusePhy::<F>::readback(&self.phy.profile, jm, jp, Ir::FuseCntl, data_leg).map_err(|e| EfuseError::from_jtag(Phase::Fetch, e))? {
+            Some(mut data) => copy.extract(data.pop_u32(bits, JtagEndian::Little).unwrap()),
+            None => 0,
+        };
+        if read != staged {
+            return Err(EfuseError::CntlVerify { copy, expected: staged, read });
+        }
+        Ok(())
+    }
+
+    /// Time burn() would take with the current intended state and config. This is a dry run of
+    /// the programming and commit sequence burn() drives, jitter included, so it's exact for
+    /// that part as long as the phy doesn't fail; it doesn't validate. The reads burn() makes
+    /// first aren't included: the IDCODE read for BurnConfig::expected_idcode and the XADC
+    /// readings for BurnConfig::environment.
+    pub fn estimate_burn_duration(&self) -> BurnDuration {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut counter: CountingPhy = CountingPhy::new(self.phy.profile.params.ir_verification.capture());
+        let requested: [u32; FUSE_BANKS] = self.requested();
+        let sections = bank_sections(requested, &self.phy.profile.params, self.config.order);
+        let mut progress: BurnProgress = BurnProgress { bits_done: 0, pulsed: None, statuses: &mut [0; FUSE_BANKS], programmed: &mut [0; FUSE_BANKS], observer: &mut () };
+        // the counting phy can't fail
+        let _ = self.program_banks(sections, &requested, None, &mut progress, &mut jm, &mut counter);
+        BurnDuration { cycles: counter.cycles, pause_us: counter.pause_us }
+    }
+
+    /// The DR words burn() would shift to program the current plan, bank by bank in burn order.
+    /// The fixed commit sequence that follows them isn't included.
+    pub fn program_words(&self) -> impl Iterator<Item = ProgramWord> + '_ {
+        bank_sections(self.requested(), &self.phy.profile.params, self.config.order).flat_map(|(_, words)| words)
+    }
+
+    /// Plans a patch of bank `bank` from its fused word to the data record `desired`, or a
+    /// superset of it differing only in `dont_care`; see the patch module. Nothing is staged.
+    pub fn plan_patch(&self, bank: usize, desired: u32, dont_care: u32) -> Result<patch::PatchPlan, EfuseError> {
+        let fused: u32 = if bank < FUSE_BANKS { self.phy.banks[bank] } else { 0 };
+        patch::plan_patch(bank, fused, desired, dont_care)
+    }
+
+    /// the 0->1's needed to get from the fused state to the intended state, per bank
+    fn requested(&self) -> [u32; FUSE_BANKS] {
+        self.requested_with(self.planned_user().0)
+    }
+
+    /// The USER value a burn writes, and whether the event counter should have counted it but
+    /// is full. Without a counter that's just the staged value; with one, the counter field is
+    /// taken from the fused state, plus one if the burn writes anything else.
+    fn planned_user(&self) -> (u32, bool) {
+        let staged: u32 = match self.config.key_checksum_field {
+            Some(field) if self.key != [0; 32] =>
+                merge_user(self.user, checksum::deposit(field, checksum::key_checksum(&self.key)), field),
+            _ => self.user,
+        };
+        let spec: CounterSpec = match self.config.event_counter {
+            Some(spec) => spec,
+            None => return (staged, false),
+        };
+        let user: u32 = merge_user(staged, self.phy.user(), spec.field);
+        if self.requested_with(user).iter().all(|&ones| ones == 0) {
+            return (user, false);
+        }
+        let counted: Option<u32> = (0..32).map(|bit| 1u32 << bit)
+            .filter(|&bit| spec.field & !user & bit != 0)
+            .map(|bit| user | bit)
+            .find(|&counted| self.reachable(counted));
+        match counted {
+            Some(counted) => (counted, false),
+            None => (user, true),
+        }
+    }
+
+    /// true if the banks for `user`, and the staged key and CNTL, only add fuses to the fused state
+    fn reachable(&self, user: u32) -> bool {
+        let mut image: [u32; FUSE_BANKS] = self.image_with(user);
+        let reachable: bool = image.iter().zip(self.phy.banks.iter()).all(|(&bank, &phy_bank)| ((phy_bank ^ bank) & phy_bank) == 0);
+        secrets::wipe_key_banks(&mut image);
+        reachable
+    }
+
+    fn requested_with(&self, user: u32) -> [u32; FUSE_BANKS] {
+        let mut image: [u32; FUSE_BANKS] = self.image_with(user);
+        let mut requested: [u32; FUSE_BANKS] = [0; FUSE_BANKS];
+        for ((ones, &bank), &phy_bank) in requested.iter_mut().zip(image.iter()).zip(self.phy.banks.iter()) {
+            *ones = (phy_bank ^ bank) & bank;
+        }
+        secrets::wipe_key_banks(&mut image);
+        requested
+    }
+
+    /// The physical image of the staged key and CNTL, with USER as `user`. validate(), burn()
+    /// and everything else that plans against the fused state start from this, so none of
+    /// them can work out a bank differently.
+    fn image_with(&self, user: u32) -> [u32; FUSE_BANKS] {
+        let mut logical: EfuseLogical = EfuseLogical { key: self.key, user, cntl: self.cntl };
+        let image: [u32; FUSE_BANKS] = F::to_banks(&logical);
+        secrets::wipe_bytes(&mut logical.key);
+        image
+    }
+
+    /// Programs exactly `ones` into physical bank `bank`, bypassing validation and ECC.
+    ///
+    /// This exists for failure analysis on scrapped units, e.g. to blow a single ECC bit and
+    /// see how the device reacts. The only checks are that the bank exists and that `ones`
+    /// names real fuses; the programming and commit sequences are the same as `burn()`'s,
+    /// and the outcome is recorded as the last report.
+    ///
+    /// # Safety
+    ///
+    /// Nothing stops this from burning a combination that fails ECC, or CNTL bits that lock
+    /// the device. Either can permanently brick the FPGA. Only use it on hardware you are
+    /// prepared to lose.
+    #[cfg(feature = "forensics")]
+    pub unsafe fn burn_raw_bank<T: JtagPhy>(&mut self, bank: usize, ones: u32, jm: &mut JtagMach, jp: &mut T) -> Result<(), EfuseError> {
+        if bank >= FUSE_BANKS || ones & !F::bank_fuses(bank) != 0 {
+            return Err(EfuseError::OutOfRange { bank, ones });
+        }
+        let mut requested: [u32; FUSE_BANKS] = [0; FUSE_BANKS];
+        requested[bank] = ones;
+        let sections = bank_sections(requested, &self.phy.profile.params, self.config.order);
+        let report = BurnReport { requested, committed: false, weak_key_overridden: false, order: self.config.order, manifest: None, status_errors: [0; FUSE_BANKS], boot_check: BootCheck::NotRequired, pre_burn: None, programmed: [0; FUSE_BANKS], pulses: 0, verify: None };
+        self.program(sections, report, None, &mut (), jm, jp)
+    }
+
+    /// Takes the validation verdict both ways and combines them; see the integrity module.
+    /// `requested` is what the burn is to program for the staged state with USER as `user`.
+    /// Narrows an Invalid verdict down to the lowest bank with blown fuses the intended state
+    /// wants clear; stays Invalid if there's none.
+    fn illegal_transition(&self, user: u32) -> EfuseError {
+        self.conflicts_with(user).first()
+            .map_or(EfuseError::Invalid, |(bank, mask)| EfuseError::IllegalTransition { bank, mask })
+    }
+
+    fn conflicts_with(&self, user: u32) -> TransitionConflicts {
+        let mut image: [u32; FUSE_BANKS] = self.image_with(user);
+        let conflicts: TransitionConflicts = TransitionConflicts::new(&self.phy.banks, &image);
+        secrets::wipe_key_banks(&mut image);
+        conflicts
+    }
+
+    fn verdict(&self, stage: VerdictStage, user: u32, guard: &Guard) -> Result<(), EfuseError> {
+        let mut per_bank_fused: [u32; FUSE_BANKS] = self.phy.banks;
+        self.inject(VerdictPath::PerBank, stage, &mut per_bank_fused);
+        let mut intended: EfuseLogical = EfuseLogical { key: self.key, user, cntl: self.cntl };
+        let per_bank: u32 = integrity::per_bank_verdict::<F>(&per_bank_fused, &intended, guard.scope);
+        let mut readback_fused: [u32; FUSE_BANKS] = self.phy.banks;
+        self.inject(VerdictPath::Readback, stage, &mut readback_fused);
+        let readback: u32 = integrity::readback_verdict::<F>(&readback_fused, &guard.requested, &intended, guard.scope);
+        secrets::wipe_bytes(&mut intended.key);
+        integrity::combine(per_bank, readback)
+    }
+
+    /// Corrupts one verdict path's copy of the fused banks, for testing the integrity checks;
+    /// None stops it.
+    #[cfg(feature = "fault-injection")]
+    pub fn inject_fault(&mut self, fault: Option<integrity::VerdictFault>) {
+        self.fault = fault;
+    }
+
+    #[cfg(feature = "fault-injection")]
+    fn inject(&self, path: VerdictPath, stage: VerdictStage, banks: &mut [u32; FUSE_BANKS]) {
+        if let Some(fault) = self.fault {
+            if fault.path == path && fault.stage == stage {
+                banks[fault.bank] ^= fault.flip;
             }
+        }
+    }
+
+    #[cfg(not(feature = "fault-injection"))]
+    fn inject(&self, _path: VerdictPath, _stage: VerdictStage, _banks: &mut [u32; FUSE_BANKS]) {}
+
+    /// Burns `sections` (programming words, bank by bank), commits them and records the outcome
+    /// as `report`. With `guard`, on the requested banks the sections were made from, the
+    /// validation verdict is taken again before the first programming word. `observer` is told
+    /// of the progress through the sections.
+    fn program<T, S, W>(&mut self, sections: S, mut report: BurnReport, guard: Option<Guard>, observer: &mut dyn BurnObserver, jm: &mut JtagMach, jp: &mut T) -> Result<(), EfuseError>
+    where T: JtagPhy, S: Iterator<Item = (usize, W)>, W: Iterator<Item = ProgramWord> {
+        // the wrong part is refused before it's touched, and without a report
+        self.check_idcode(jm, jp)?;
+        // and so is a die out of spec, unless that's overridden
+        report.pre_burn = self.pre_burn_check(jm, jp)?;
+        let requested: [u32; FUSE_BANKS] = report.requested;
+        let mut progress: BurnProgress = BurnProgress { bits_done: 0, pulsed: None, statuses: &mut report.status_errors, programmed: &mut report.programmed, observer };
+        #[cfg(feature = "critical-section")]
+        let result: Result<(), EfuseError> = match self.config.critical_sections {
+            CsPolicy::PerBit => self.program_banks(sections, &requested, guard, &mut progress, jm, &mut CsPhy(jp)),
+            _ => self.program_banks(sections, &requested, guard, &mut progress, jm, jp),
+        };
+        #[cfg(not(feature = "critical-section"))]
+        let result: Result<(), EfuseError> = self.program_banks(sections, &requested, guard, &mut progress, jm, jp);
+        report.pulses = progress.bits_done as u32;
+        report.committed = committed(&result);
+        self.predicted = Some(self.predict(&report.requested));
+        self.report = Some(report);
+        // whatever boot was verified, it wasn't necessarily with what's fused now
+        self.boot_check = BootCheck::NotRequired;
+        result
+    }
 
+    /// refuses with IdcodeMismatch unless the IDCODE matches BurnConfig::expected_idcode, if set
+    fn check_idcode<T: JtagPhy>(&mut self, jm: &mut JtagMach, jp: &mut T) -> Result<(), EfuseError> {
+        if let Some(expected) = self.config.expected_idcode {
+            let read: u32 = self.idcode(jm, jp)?;
+            if !expected.matches(read) {
+                return Err(EfuseError::IdcodeMismatch { expected: expected.idcode, mask: expected.mask, read });
+            }
         }
+        Ok(())
+    }
+
+    /// Reads the programming port's status after a commit. It has to be clean, and the
+    /// programming words must not have reported an error either (see the status module).
+    fn check_status<T: JtagPhy>(&self, statuses: &[u32; FUSE_BANKS], jm: &mut JtagMach, jp: &mut T) -> Result<(), EfuseError> {
+        let read = [self.efuse_ir(), SeqCmd::new(JtagChain::DR, self.phy.profile.params.dr_bits, 0, "KEY_STATUS").with_budget(PROGRAM_BUDGET)];
+        let status: FuseStatus = FuseStatus::from_capture(self.jtag_seq(jm, jp, &read).map_err(|e| EfuseError::from_jtag(Phase::Commit, e))?);
+        let bank: Option<usize> = statuses.iter().position(|&raw| raw != 0);
+        match bank {
+            _ if !status.is_clean() => Err(EfuseError::DeviceReportedError { bank, raw_status: status.raw }),
+            Some(index) => Err(EfuseError::DeviceReportedError { bank, raw_status: statuses[index] }),
+            None => Ok(()),
+        }
+    }
+
+    /// the banks burning `requested` should leave behind
+    fn predict(&self, requested: &[u32; FUSE_BANKS]) -> [u32; FUSE_BANKS] {
+        let mut predicted: [u32; FUSE_BANKS] = self.phy.banks;
+        for (p, &ones) in predicted.iter_mut().zip(requested.iter()) {
+            *p |= ones;
+        }
+        predicted
+    }
+
+    /// `requested` is what the sections program, per bank
+    fn program_banks<T, S, W>(&self, sections: S, requested: &[u32; FUSE_BANKS], guard: Option<Guard>, progress: &mut BurnProgress, jm: &mut JtagMach, jp: &mut T) -> Result<(), EfuseError>
+    where T: JtagPhy, S: Iterator<Item = (usize, W)>, W: Iterator<Item = ProgramWord> {
+        // reset the machine before doing any burning
+        jp.pause(2000); 
+        jm.try_reset(jp).map_err(|e| EfuseError::from_jtag(Phase::Burn, e))?;
         jp.pause(2000); 
-        self.jtag_seq(jm, jp, &COMMIT_SEQ);
+
+        // re-derived rather than trusted from validate(), so that one glitched branch there
+        // isn't enough; a verdict that no longer holds at all is just as suspect. The staged
+        // state it's derived from is checked against arm()'s seal first.
+        let result: Result<(), EfuseError> = match guard {
+            Some(guard) => self.check_armed().and_then(|_| {
+                self.verdict(VerdictStage::PreUnlock, self.planned_user().0, &guard)
+                    .map_err(|_| EfuseError::ValidationIntegrity)
+            }),
+            None => Ok(()),
+        };
+        // once for the whole burn, whichever banks it programs; sections come in burn order,
+        // bank 0 last
+        let result: Result<(), EfuseError> = result.and_then(|_| self.isc_bracketed(jm, jp, |jm, jp| {
+            for (bank, words) in sections {
+                progress.observer.bank_started(bank, requested[bank].count_ones());
+                events::bank_started(bank, requested[bank].count_ones());
+                if let Err(e) = self.burn_words(bank, words, progress, jm, jp) {
+                    // don't commit a partial burn
+                    let e: EfuseError = EfuseError::from_jtag(Phase::Burn, e);
+                    events::bank_failed(bank, &e);
+                    return Err(e);
+                }
+                progress.observer.bank_finished(bank);
+                events::bank_finished(bank);
+            }
+            events::commit_started();
+            jp.pause(2000); 
+            let mut result: Result<(), EfuseError> = self.jtag_seq(jm, jp, &F::commit_seq(&self.phy.profile)).map(|_| ()).map_err(|e| EfuseError::from_jtag(Phase::Commit, e));
+            if result.is_ok() {
+                result = self.check_status(progress.statuses, jm, jp);
+            }
+            events::commit_finished(&result);
+            result
+        }));
         jp.pause(2000); 
         jm.reset(jp);
-        ok
+        result
     }
 
+    /// Runs `body`, which programs and commits fuses, between the profile's ISC_ENABLE, with
+    /// its dwell, and its ISC_DISABLE; without an IscBracket it's just `body`. If `body` fails,
+    /// whatever was left of its sequence is dropped.
+    fn isc_bracketed<T, B>(&self, jm: &mut JtagMach, jp: &mut T, body: B) -> Result<(), EfuseError>
+    where T: JtagPhy, B: FnOnce(&mut JtagMach, &mut T) -> Result<(), EfuseError> {
+        let ir = |code: u32, tag: &'static str| SeqCmd::new(JtagChain::IR, self.phy.profile.ir_bits, code as u64, tag).with_budget(IR_BUDGET);
+        let isc: Option<IscBracket> = self.phy.profile.isc;
+        let mut result: Result<(), EfuseError> = match isc {
+            Some(isc) => self.jtag_seq(jm, jp, &[ir(isc.enable, "ISC_ENABLE")])
+                .and_then(|_| jm.try_idle(jp, isc.enable_idle))
+                .map_err(|e| EfuseError::from_jtag(Phase::Burn, e)),
+            None => Ok(()),
+        };
+        if result.is_ok() {
+            result = body(jm, jp);
+        }
+        if result.is_err() {
+            jm.clear_pending();
+        }
+        // closed whether or not the burn went through; after a failure, from a reset, since
+        // the failure may have left the TAP anywhere
+        if let Some(isc) = isc {
+            let disable: [SeqCmd; 1] = [ir(isc.disable, "ISC_DISABLE")];
+            if result.is_ok() {
+                result = self.jtag_seq(jm, jp, &disable).map(|_| ()).map_err(|e| EfuseError::from_jtag(Phase::Commit, e));
+            } else if jm.try_reset(jp).is_ok() && self.jtag_seq(jm, jp, &disable).is_err() {
+                jm.clear_pending();
+            }
+        }
+        result
+    }
+
+}
+
+/// These name 7-series banks, or hand the state to 7-series tooling
+impl EfuseApi {
+    /// checks the fused state, as of the last fetch, against what Vivado reported for the device
+    pub fn compare_with_vivado(&self, report: &vivado::VivadoEfuseReport) -> vivado::ComparisonResult {
+        vivado::compare(&self.phy.key, self.phy.user(), self.phy.cntl(), report)
+    }
+
+    /// Draws the banks as fetched, with the fuses burn() would blow and any it can't get
+    /// around; see the fusemap module.
+    pub fn write_fuse_map<W: core::fmt::Write>(&self, out: &mut W) -> core::fmt::Result {
+        let user: u32 = self.planned_user().0;
+        fusemap::write(out, &self.phy.banks, &self.requested_with(user), &self.conflicts_with(user).masks())
+    }
+
+    /// Writes a Vivado Tcl script that programs the staged state, for sites where the fuses
+    /// have to be burned from Vivado. See vivado::export_tcl().
+    pub fn export_vivado_tcl<W: core::fmt::Write>(&self, out: &mut W, opts: &vivado::TclOptions) -> Result<(), vivado::TclExportError> {
+        vivado::export_tcl(self, out, opts)
+    }
+
+    /// the fused word of `bank`, ECC bits included
+    pub fn bank(&self, bank: Bank) -> u32 {
+        self.phy.banks[bank.index()]
+    }
+
+    /// the word the intended state puts in `bank`, ECC bits included; USER is as burn() would
+    /// write it
+    pub fn proposed_bank(&self, bank: Bank) -> u32 {
+        bank_image_ecc(bank.index(), &self.key, self.planned_user().0, self.cntl)
+    }
+
+    /// Checks that `bank` on its own can go from its fused word to its proposed one: only
+    /// 0->1 transitions, and the cross-checks validate() makes on the fields the bank holds.
+    /// The other banks aren't looked at.
+    pub fn validate_bank(&self, bank: Bank) -> Result<(), EfuseError> {
+        let index: usize = bank.index();
+        let fused: u32 = self.phy.banks[index];
+        let proposed: u32 = self.proposed_bank(bank);
+        let changing: bool = fused != proposed;
+        let holds_user: bool = matches!(bank.kind(), BankKind::Shared | BankKind::User);
+        let holds_key: bool = matches!(bank.kind(), BankKind::Key | BankKind::Shared);
+        if let UserConsistency::Mismatch { direct, derived } = self.phy.report().user {
+            if holds_user && changing && !self.allow_user_mismatch {
+                return Err(EfuseError::UserMismatch { direct, derived });
+            }
+        }
+        if holds_key && changing && self.phy.report().key == KeyReadback::Unreadable {
+            return Err(EfuseError::KeyUnreadable);
+        }
+        let mask: u32 = fused & !proposed;
+        if mask != 0 {
+            return Err(EfuseError::IllegalTransition { bank: index, mask });
+        }
+        if holds_key && self.key_overwritten(index) && !self.force_key_patch {
+            return Err(EfuseError::KeyAlreadyProgrammed);
+        }
+        Ok(())
+    }
+
+    /// Burns `bank` alone to its proposed word, for bring-up and fixture qualification; the
+    /// other banks are left as they're fused, whatever is staged for them. Refuses as
+    /// validate_bank() does, and with NotFetched, before touching the device. The burn is
+    /// recorded as the last report, like any other. As with burn(), the bank's verdict is taken
+    /// again before the first unlock word.
+    ///
+    /// CNTL isn't burned this way: Bank::Cntl is refused with CntlSingleBank, since burn_cntl()
+    /// is the checked lockdown path.
+    pub fn burn_single_bank<T: JtagPhy>(&mut self, bank: Bank, jm: &mut JtagMach, jp: &mut T) -> Result<(), EfuseError> {
+        if bank == Bank::Cntl {
+            return Err(EfuseError::CntlSingleBank);
+        }
+        if !self.phy.fetched() {
+            return Err(EfuseError::NotFetched);
+        }
+        self.check_armed()?;
+        self.validate_bank(bank)?;
+        let mut requested: [u32; FUSE_BANKS] = [0; FUSE_BANKS];
+        requested[bank.index()] = self.requested()[bank.index()];
+        let sections = bank_sections(requested, &self.phy.profile.params, self.config.order);
+        let report = BurnReport { requested, committed: false, weak_key_overridden: false, order: self.config.order, manifest: None, status_errors: [0; FUSE_BANKS], boot_check: BootCheck::NotRequired, pre_burn: None, programmed: [0; FUSE_BANKS], pulses: 0, verify: None };
+        let guard: Guard = Guard { requested, scope: 1 << bank.index() };
+        let result: Result<(), EfuseError> = self.program(sections, report, Some(guard), &mut (), jm, jp);
+        self.armed = None;
+        result
+    }
+}
+
+impl<F: DeviceFamily> Drop for EfuseApi<F> {
+    fn drop(&mut self) {
+        self.clear_secrets();
+    }
 }
+
+#[cfg(feature = "zeroize")]
+impl<F: DeviceFamily> zeroize::Zeroize for EfuseApi<F> {
+    fn zeroize(&mut self) {
+        self.clear_secrets();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<F: DeviceFamily> zeroize::ZeroizeOnDrop for EfuseApi<F> {}
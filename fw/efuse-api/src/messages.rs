@@ -0,0 +1,298 @@
+//! Messages exchanged with host-side provisioning tooling
+//!
+//! Each message has a fixed-length little-endian wire encoding, so that both ends can encode
+//! and decode without allocating. Framing for a byte stream is handled by the transport module.
+//!
+//! With the `serde` feature, EfuseIntent, BurnReport and what they carry, and BurnPlan, are
+//! also Serialize and Deserialize, for servers that would rather ship them in a format of
+//! their own. Keys go out as 32 numbers, key[0] first, whatever the format. Deserializing
+//! checks what the wire decoders check, and an intent's CNTL besides; what BurnReport leaves
+//! off the wire, serde leaves off too.
+
+#[cfg(feature = "serde")]
+use core::convert::TryFrom;
+
+use crate::error::EfuseError;
+use crate::layout::*;
+use crate::boot::BootCheck;
+use crate::xadc::PreBurnCheck;
+use crate::sequences::BitOrderPolicy;
+use crate::transport::crc32;
+use crate::verify::VerifySummary;
+
+fn put_u32(out: &mut [u8], at: usize, value: u32) {
+    out[at..at + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn get_u32(bytes: &[u8], at: usize) -> u32 {
+    let mut word: [u8; 4] = [0; 4];
+    word.copy_from_slice(&bytes[at..at + 4]);
+    u32::from_le_bytes(word)
+}
+
+/// Raw contents of every fuse bank, as derived by a fetch
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FuseSnapshot {
+    pub banks: [u32; FUSE_BANKS],
+}
+
+impl FuseSnapshot {
+    pub const WIRE_LEN: usize = FUSE_BANKS * 4;
+
+    /// encode into the front of `out`, which must be at least WIRE_LEN long
+    pub fn encode(&self, out: &mut [u8]) -> usize {
+        for (i, &bank) in self.banks.iter().enumerate() {
+            put_u32(out, i * 4, bank);
+        }
+        FuseSnapshot::WIRE_LEN
+    }
+
+    /// decode from exactly WIRE_LEN bytes
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != FuseSnapshot::WIRE_LEN {
+            return None;
+        }
+        let mut banks: [u32; FUSE_BANKS] = [0; FUSE_BANKS];
+        for (i, bank) in banks.iter_mut().enumerate() {
+            *bank = get_u32(bytes, i * 4);
+        }
+        Some(FuseSnapshot { banks })
+    }
+}
+
+/// The state a device is to be provisioned to
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ProvisioningManifest {
+    pub key: [u8; 32],
+    pub user: u32,
+    pub cntl: u8,
+}
+
+impl ProvisioningManifest {
+    pub const WIRE_LEN: usize = 32 + 4 + 1;
+
+    /// encode into the front of `out`, which must be at least WIRE_LEN long
+    pub fn encode(&self, out: &mut [u8]) -> usize {
+        out[..32].copy_from_slice(&self.key);
+        put_u32(out, 32, self.user);
+        out[36] = self.cntl;
+        ProvisioningManifest::WIRE_LEN
+    }
+
+    /// decode from exactly WIRE_LEN bytes
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != ProvisioningManifest::WIRE_LEN {
+            return None;
+        }
+        let mut key: [u8; 32] = [0; 32];
+        key.copy_from_slice(&bytes[..32]);
+        Some(ProvisioningManifest { key, user: get_u32(bytes, 32), cntl: bytes[36] })
+    }
+
+    /// CRC-32 of the wire encoding; both ends compute it to confirm what was staged
+    pub fn checksum(&self) -> u32 {
+        let mut bytes: [u8; ProvisioningManifest::WIRE_LEN] = [0; ProvisioningManifest::WIRE_LEN];
+        self.encode(&mut bytes);
+        crc32(&bytes)
+    }
+}
+
+/// The state a device is to be provisioned to, as a provisioning server decides it, with the
+/// overrides it means to allow; see EfuseApi::apply_intent()
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(try_from = "IntentFields"))]
+pub struct EfuseIntent {
+    pub key: [u8; 32],
+    pub user: u32,
+    /// at most CNTL_MASK; deserializing anything above fails
+    pub cntl: u8,
+    /// stage an all-zero key, leaving the key unburned, rather than refuse it as one the
+    /// server never filled in
+    pub allow_zero_key: bool,
+    /// see EfuseApi::allow_weak_key()
+    pub allow_weak_key: bool,
+}
+
+impl EfuseIntent {
+    /// Refuses what EfuseApi::apply_intent() would: CNTL bits outside CNTL_MASK, and an
+    /// all-zero key the intent doesn't allow
+    pub fn check(&self) -> Result<(), EfuseError> {
+        let bits: u8 = self.cntl & !CNTL_MASK;
+        if bits != 0 {
+            return Err(EfuseError::CntlReserved { bits });
+        }
+        if !self.allow_zero_key && crate::keycheck::ct_eq_32(&self.key, &[0; 32]) {
+            return Err(EfuseError::ZeroKey);
+        }
+        Ok(())
+    }
+}
+
+/// EfuseIntent as it's deserialized, before its CNTL is checked
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct IntentFields {
+    key: [u8; 32],
+    user: u32,
+    cntl: u8,
+    allow_zero_key: bool,
+    allow_weak_key: bool,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<IntentFields> for EfuseIntent {
+    type Error = EfuseError;
+
+    fn try_from(f: IntentFields) -> Result<Self, EfuseError> {
+        let bits: u8 = f.cntl & !CNTL_MASK;
+        if bits != 0 {
+            return Err(EfuseError::CntlReserved { bits });
+        }
+        Ok(EfuseIntent { key: f.key, user: f.user, cntl: f.cntl, allow_zero_key: f.allow_zero_key, allow_weak_key: f.allow_weak_key })
+    }
+}
+
+/// Identifies a compiled programming sequence; see sequences::CompiledSequence::manifest
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VectorManifest {
+    /// SHA-256 of the DR words, each as 8 little-endian bytes, in shift order
+    pub sha256_of_words: [u8; 32],
+    pub word_count: u32,
+    /// DeviceParams::id() of the parameters the words were generated for
+    pub device_params_id: u32,
+    /// bits programmed, per bank
+    pub plan_summary: [u8; FUSE_BANKS],
+}
+
+impl VectorManifest {
+    pub const WIRE_LEN: usize = 32 + 4 + 4 + FUSE_BANKS;
+
+    /// encode into the front of `out`, which must be at least WIRE_LEN long
+    pub fn encode(&self, out: &mut [u8]) -> usize {
+        out[..32].copy_from_slice(&self.sha256_of_words);
+        put_u32(out, 32, self.word_count);
+        put_u32(out, 36, self.device_params_id);
+        out[40..40 + FUSE_BANKS].copy_from_slice(&self.plan_summary);
+        VectorManifest::WIRE_LEN
+    }
+
+    /// decode from exactly WIRE_LEN bytes
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != VectorManifest::WIRE_LEN {
+            return None;
+        }
+        let mut sha256_of_words: [u8; 32] = [0; 32];
+        sha256_of_words.copy_from_slice(&bytes[..32]);
+        let mut plan_summary: [u8; FUSE_BANKS] = [0; FUSE_BANKS];
+        plan_summary.copy_from_slice(&bytes[40..]);
+        Some(VectorManifest { sha256_of_words, word_count: get_u32(bytes, 32), device_params_id: get_u32(bytes, 36), plan_summary })
+    }
+}
+
+/// Outcome of a burn
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BurnReport {
+    /// bits that were to be programmed, per bank
+    pub requested: [u32; FUSE_BANKS],
+    /// true if the commit sequence ran
+    pub committed: bool,
+    /// true if the key looked non-random, and the burn went ahead only because that was allowed
+    pub weak_key_overridden: bool,
+    /// the bit order used, so the exact word sequence can be reproduced
+    pub order: BitOrderPolicy,
+    /// the manifest of the sequence, if the burn replayed a compiled one
+    pub manifest: Option<VectorManifest>,
+    /// the error statuses the programming port reported, per bank and or'd together; they
+    /// aren't carried on the wire, and decode as 0
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub status_errors: [u32; FUSE_BANKS],
+    /// how a lockdown was let through; not carried on the wire either
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub boot_check: BootCheck,
+    /// what the environment check before the burn read, if BurnConfig asked for one; not
+    /// carried on the wire
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub pre_burn: Option<PreBurnCheck>,
+    /// the fuses that had their programming pulse, per bank; not carried on the wire
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub programmed: [u32; FUSE_BANKS],
+    /// programming pulses shifted; not carried on the wire
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub pulses: u32,
+    /// what verify_burn() found, once it's been run after the burn; not carried on the wire
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub verify: Option<VerifySummary>,
+}
+
+impl BurnReport {
+    /// length without a manifest
+    pub const WIRE_LEN: usize = FUSE_BANKS * 4 + 2 + 9;
+    /// length with a manifest, which is appended
+    pub const MAX_WIRE_LEN: usize = BurnReport::WIRE_LEN + VectorManifest::WIRE_LEN;
+
+    /// encode into the front of `out`, which must be at least WIRE_LEN long, or MAX_WIRE_LEN
+    /// with a manifest
+    pub fn encode(&self, out: &mut [u8]) -> usize {
+        for (i, &bits) in self.requested.iter().enumerate() {
+            put_u32(out, i * 4, bits);
+        }
+        out[FUSE_BANKS * 4] = self.committed as u8;
+        out[FUSE_BANKS * 4 + 1] = self.weak_key_overridden as u8;
+        let (shuffled, seed): (u8, u64) = match self.order {
+            BitOrderPolicy::Ascending => (0, 0),
+            BitOrderPolicy::Shuffled { seed } => (1, seed),
+        };
+        out[FUSE_BANKS * 4 + 2] = shuffled;
+        out[FUSE_BANKS * 4 + 3..FUSE_BANKS * 4 + 11].copy_from_slice(&seed.to_le_bytes());
+        match self.manifest {
+            Some(manifest) => BurnReport::WIRE_LEN + manifest.encode(&mut out[BurnReport::WIRE_LEN..]),
+            None => BurnReport::WIRE_LEN,
+        }
+    }
+
+    /// decode from exactly WIRE_LEN bytes, or MAX_WIRE_LEN with a manifest
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let manifest: Option<VectorManifest> = match bytes.len() {
+            BurnReport::WIRE_LEN => None,
+            BurnReport::MAX_WIRE_LEN => Some(VectorManifest::decode(&bytes[BurnReport::WIRE_LEN..])?),
+            _ => return None,
+        };
+        let mut requested: [u32; FUSE_BANKS] = [0; FUSE_BANKS];
+        for (i, bits) in requested.iter_mut().enumerate() {
+            *bits = get_u32(bytes, i * 4);
+        }
+        let flag = |byte: u8| match byte {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        };
+        let committed: bool = flag(bytes[FUSE_BANKS * 4])?;
+        let weak_key_overridden: bool = flag(bytes[FUSE_BANKS * 4 + 1])?;
+        let mut seed: [u8; 8] = [0; 8];
+        seed.copy_from_slice(&bytes[FUSE_BANKS * 4 + 3..FUSE_BANKS * 4 + 11]);
+        let order: BitOrderPolicy = match (flag(bytes[FUSE_BANKS * 4 + 2])?, u64::from_le_bytes(seed)) {
+            (false, 0) => BitOrderPolicy::Ascending,
+            (false, _) => return None,
+            (true, seed) => BitOrderPolicy::Shuffled { seed },
+        };
+        Some(BurnReport { requested, committed, weak_key_overridden, order, manifest, status_errors: [0; FUSE_BANKS], boot_check: BootCheck::NotRequired, pre_burn: None, programmed: [0; FUSE_BANKS], pulses: 0, verify: None })
+    }
+}
+
+/// Bank by bank, in burn order, then the pulses, the commit and the verification
+impl core::fmt::Display for BurnReport {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        for bank in (0..FUSE_BANKS).rev() {
+            if self.requested[bank] != 0 || self.programmed[bank] != 0 {
+                writeln!(f, "bank {:>2}: {:#010x} requested, {:#010x} programmed", bank, self.requested[bank], self.programmed[bank])?;
+            }
+        }
+        write!(f, "{} pulses, {}", self.pulses, if self.committed { "committed" } else { "not committed" })?;
+        match self.verify {
+            Some(summary) => write!(f, ", verified: {} missing, {} stray", summary.missing, summary.stray),
+            None => write!(f, ", not verified"),
+        }
+    }
+}
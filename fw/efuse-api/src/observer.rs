@@ -0,0 +1,26 @@
+//! Progress of a burn as it happens
+//!
+//! A full key burn is a few hundred programming pulses, each followed by a wait, so it takes
+//! long enough that a UI wants to show it moving. burn_with_observer() calls a BurnObserver as
+//! it goes: bank_started() before a bank's first word, bit_burned() once the wait after each
+//! programming pulse has been shifted, and bank_finished() after the bank's last word. Banks
+//! come in burn order, bank 0 last, and bits in the order the BitOrderPolicy puts them, so the
+//! stream follows BurnPlan::banks() for the same staged state.
+//!
+//! A bank that fails part way gets no bank_finished(); the error comes back from the burn as
+//! usual. Nothing is reported for the commit that follows the banks. Every callback defaults
+//! to doing nothing, and `()` is the observer that does nothing at all.
+
+/// Something told about a burn's progress, bank by bank and bit by bit
+pub trait BurnObserver {
+    /// bank `bank` is about to be programmed, with `bits_to_burn` fuses to blow
+    fn bank_started(&mut self, _bank: usize, _bits_to_burn: u32) {}
+
+    /// fuse `bit` of bank `bank` has had its programming pulse
+    fn bit_burned(&mut self, _bank: usize, _bit: u32) {}
+
+    /// every fuse of bank `bank` has had its programming pulse
+    fn bank_finished(&mut self, _bank: usize) {}
+}
+
+impl BurnObserver for () {}
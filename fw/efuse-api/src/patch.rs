@@ -0,0 +1,100 @@
+//! Planning a patch of one bank, ECC included
+//!
+//! A bank can be patched as long as every fuse already blown stays blown, ECC fuses included.
+//! The data bits are easy to check, but the ECC code of the new data record is another
+//! matter: adding a data bit flips several ECC bits, and any that goes from 1 to 0 makes the
+//! patch impossible, however harmless the data change looks.
+//!
+//! When the desired record itself can't be reached, there may still be a record that can: one
+//! with some more data bits set, in positions the caller doesn't care about. plan_patch()
+//! looks for the one with the fewest such bits, lowest first, and stops looking past
+//! PATCH_MAX_EXTRA of them. The CNTL bank has no ECC, so only its data bits count.
+
+use efuse_ecc::efuse_ecc::*;
+
+use crate::layout::*;
+use crate::EfuseError;
+
+/// most don't-care bits plan_patch() adds to the desired record
+pub const PATCH_MAX_EXTRA: u32 = 4;
+
+/// How to get one bank from its fused word to (a superset of) the desired record
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PatchPlan {
+    pub bank: usize,
+    /// the word the bank holds afterwards, ECC included
+    pub target: u32,
+    /// the fuses to blow: those of `target` not fused yet
+    pub program: u32,
+    /// don't-care data bits set beyond the desired record; 0 if it could be burned as it is
+    pub extra: u32,
+}
+
+/// the physical word for data record `data` of bank `bank`
+fn encode(bank: usize, data: u32) -> u32 {
+    if bank == CNTL_BANK { data } else { add_ecc(data) }
+}
+
+/// the data bits of bank `bank`
+fn data_bits(bank: usize) -> u32 {
+    if bank == CNTL_BANK { bank_fuses(CNTL_BANK) } else { 0xFF_FFFF }
+}
+
+/// Plans the patch of bank `bank`, which holds `fused`, to the data record `desired`. Bits
+/// in `dont_care` may end up set either way; fused ones among them stay set. Fails with
+/// OutOfRange if `desired` has bits that aren't data fuses of the bank, and with NoPatch
+/// if no record within reach keeps every fused fuse.
+pub fn plan_patch(bank: usize, fused: u32, desired: u32, dont_care: u32) -> Result<PatchPlan, EfuseError> {
+    if bank >= FUSE_BANKS || desired & !data_bits(bank) != 0 {
+        return Err(EfuseError::OutOfRange { bank, ones: desired });
+    }
+    let dont_care: u32 = dont_care & data_bits(bank);
+    let conflicts: u32 = fused & !encode(bank, desired);
+    let no_patch = EfuseError::NoPatch { bank, conflicts };
+    // fused data bits stay, so any outside the record have to be ones the caller doesn't mind
+    let base: u32 = desired | (fused & dont_care);
+    if fused & data_bits(bank) & !base != 0 {
+        return Err(no_patch);
+    }
+    let free: u32 = dont_care & !base;
+    let positions: u32 = free.count_ones();
+    for extra_bits in 0..=positions.min(PATCH_MAX_EXTRA) {
+        let mut pick: u32 = if extra_bits == 0 { 0 } else { (1 << extra_bits) - 1 };
+        while (pick as u64) < (1u64 << positions) {
+            let extra: u32 = deposit(pick, free);
+            let target: u32 = encode(bank, base | extra);
+            if fused & !target == 0 {
+                return Ok(PatchPlan { bank, target, program: target & !fused, extra: (base | extra) & !desired });
+            }
+            if pick == 0 {
+                break;
+            }
+            pick = next_combination(pick);
+        }
+    }
+    Err(no_patch)
+}
+
+/// the bits of `mask`, picked by the low bits of `pick`: bit n of `pick` selects the nth
+/// lowest bit of `mask`
+fn deposit(pick: u32, mask: u32) -> u32 {
+    let mut out: u32 = 0;
+    let mut rest: u32 = mask;
+    let mut n: u32 = 0;
+    while rest != 0 {
+        let low: u32 = rest & rest.wrapping_neg();
+        if pick & (1 << n) != 0 {
+            out |= low;
+        }
+        rest &= !low;
+        n += 1;
+    }
+    out
+}
+
+/// the next larger number with as many bits set as `pick`, which mustn't be 0
+fn next_combination(pick: u32) -> u32 {
+    let low: u32 = pick & pick.wrapping_neg();
+    let ripple: u32 = pick.wrapping_add(low);
+    ripple | (((ripple ^ pick) >> 2) / low)
+}
@@ -0,0 +1,545 @@
+//! Remote provisioning protocol
+//!
+//! The host sends one Command per frame and the device answers every frame with exactly one
+//! Response, so the host always knows whose turn it is. A session opens with a Hello carrying
+//! the host's protocol version; the device refuses everything else until the major versions
+//! agree.
+//!
+//! Burning is a three-step affair: the host stages a manifest, arms the burn by echoing the
+//! manifest's checksum back, then executes. Any Abort, or a new manifest, disarms.
+//!
+//! Commands and responses travel as transport frames: the frame kind is the opcode and the
+//! payload is the operand, if any. A device that doesn't know an opcode answers Unsupported
+//! rather than failing, so newer hosts can probe older firmware.
+
+use alloc::boxed::Box;
+
+use jtag::*;
+use crate::{EfuseApi, EfuseError};
+use crate::access::LockStatus;
+use crate::keysource::KeySource;
+use crate::messages::*;
+use crate::transport::*;
+
+/// Version of the protocol spoken by this end
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ProtocolVersion {
+    pub major: u8,
+    pub minor: u8,
+}
+
+/// 1.1: LockStatus carries the boot key source, for hosts that say they're 1.1 or later
+pub const PROTOCOL_VERSION: ProtocolVersion = ProtocolVersion { major: 1, minor: 1 };
+
+impl ProtocolVersion {
+    /// minor versions only add commands, so ends with the same major version can talk
+    pub fn compatible(&self, other: &ProtocolVersion) -> bool {
+        self.major == other.major
+    }
+}
+
+const OP_HELLO: u8 = 0x40;
+const OP_GET_SNAPSHOT: u8 = 0x41;
+const OP_GET_LOCK_STATUS: u8 = 0x42;
+const OP_STAGE_MANIFEST: u8 = 0x43;
+const OP_ARM: u8 = 0x44;
+const OP_EXECUTE_BURN: u8 = 0x45;
+const OP_GET_REPORT: u8 = 0x46;
+const OP_ABORT: u8 = 0x47;
+
+const RSP_HELLO: u8 = 0xC0;
+const RSP_SNAPSHOT: u8 = 0xC1;
+const RSP_LOCK_STATUS: u8 = 0xC2;
+const RSP_STAGED: u8 = 0xC3;
+const RSP_ARMED: u8 = 0xC4;
+const RSP_BURNED: u8 = 0xC5;
+const RSP_REPORT: u8 = 0xC6;
+const RSP_ABORTED: u8 = 0xC7;
+const RSP_ERROR: u8 = 0xFE;
+const RSP_UNSUPPORTED: u8 = 0xFF;
+
+/// Requests from the host
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// open a session, offering the host's protocol version
+    Hello(ProtocolVersion),
+    /// fetch and return the raw bank contents
+    GetSnapshot,
+    /// fetch and return the CNTL fuses, and for 1.1 hosts the boot key source
+    GetLockStatus,
+    /// set the intended state; disarms any previous manifest
+    StageManifest(ProvisioningManifest),
+    /// confirm the staged manifest by its checksum
+    Arm { checksum: u32 },
+    /// burn the armed manifest
+    ExecuteBurn,
+    /// return the outcome of the last burn
+    GetReport,
+    /// disarm and drop the staged manifest
+    Abort,
+}
+
+/// Reasons the device declined a command
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ProtocolError {
+    /// the command frame was corrupt, its operand had the wrong length, or a staged manifest
+    /// set CNTL bits that aren't fuses
+    BadFrame = 1,
+    /// the host's major version differs from the device's
+    VersionMismatch = 2,
+    /// no Hello has been accepted yet
+    NoSession = 3,
+    /// Arm was sent with nothing staged
+    NotStaged = 4,
+    /// Arm's checksum doesn't match the staged manifest
+    ChecksumMismatch = 5,
+    /// ExecuteBurn was sent without a successful Arm
+    NotArmed = 6,
+    /// reading back the fuses failed
+    FetchFailed = 7,
+    /// the staged manifest is unreachable from the fused state, changed after arming, or
+    /// programming failed
+    BurnFailed = 8,
+    /// no burn has been run
+    NoReport = 9,
+    /// the fuses can't be read while the device runs its secured configuration; unlike a
+    /// blank device, nothing is known about them
+    AccessRestricted = 10,
+}
+
+impl ProtocolError {
+    pub fn from_code(code: u8) -> Option<ProtocolError> {
+        use ProtocolError::*;
+        [BadFrame, VersionMismatch, NoSession, NotStaged, ChecksumMismatch, NotArmed, FetchFailed, BurnFailed, NoReport, AccessRestricted]
+            .iter().copied().find(|e| *e as u8 == code)
+    }
+}
+
+/// Answers from the device, one per command
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Response {
+    /// session open; carries the device's protocol version
+    Hello(ProtocolVersion),
+    Snapshot(FuseSnapshot),
+    /// `key_source` is None for hosts older than 1.1, or if it couldn't be read
+    LockStatus { cntl: u8, key_source: Option<KeySource> },
+    /// the manifest was staged; carries the checksum to arm with
+    Staged { checksum: u32 },
+    Armed,
+    Burned(BurnReport),
+    Report(BurnReport),
+    Aborted,
+    Error(ProtocolError),
+    /// the device doesn't know this opcode
+    Unsupported { opcode: u8 },
+}
+
+/// Result of decoding a command frame
+enum Decoded {
+    Command(Command),
+    Unknown(u8),
+    Malformed,
+}
+
+fn decode_version(payload: &[u8]) -> Option<ProtocolVersion> {
+    if payload.len() == 2 {
+        Some(ProtocolVersion { major: payload[0], minor: payload[1] })
+    } else {
+        None
+    }
+}
+
+fn decode_u32(payload: &[u8]) -> Option<u32> {
+    if payload.len() == 4 {
+        Some(u32::from_le_bytes([payload[0], payload[1], payload[2], payload[3]]))
+    } else {
+        None
+    }
+}
+
+fn decode_empty<T>(payload: &[u8], value: T) -> Option<T> {
+    if payload.is_empty() {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+impl Command {
+    /// write this command as one frame
+    pub fn send<W: ByteSink>(&self, sink: &mut W) -> Result<(), TransportError> {
+        let mut payload: [u8; MAX_PAYLOAD] = [0; MAX_PAYLOAD];
+        let (op, len): (u8, usize) = match self {
+            Command::Hello(v) => {
+                payload[0] = v.major;
+                payload[1] = v.minor;
+                (OP_HELLO, 2)
+            },
+            Command::GetSnapshot => (OP_GET_SNAPSHOT, 0),
+            Command::GetLockStatus => (OP_GET_LOCK_STATUS, 0),
+            Command::StageManifest(m) => (OP_STAGE_MANIFEST, m.encode(&mut payload)),
+            Command::Arm { checksum } => {
+                payload[..4].copy_from_slice(&checksum.to_le_bytes());
+                (OP_ARM, 4)
+            },
+            Command::ExecuteBurn => (OP_EXECUTE_BURN, 0),
+            Command::GetReport => (OP_GET_REPORT, 0),
+            Command::Abort => (OP_ABORT, 0),
+        };
+        send_frame(sink, op, &payload[..len])
+    }
+
+    fn decode(op: u8, payload: &[u8]) -> Decoded {
+        let cmd: Option<Command> = match op {
+            OP_HELLO => decode_version(payload).map(Command::Hello),
+            OP_GET_SNAPSHOT => decode_empty(payload, Command::GetSnapshot),
+            OP_GET_LOCK_STATUS => decode_empty(payload, Command::GetLockStatus),
+            OP_STAGE_MANIFEST => ProvisioningManifest::decode(payload).map(Command::StageManifest),
+            OP_ARM => decode_u32(payload).map(|checksum| Command::Arm { checksum }),
+            OP_EXECUTE_BURN => decode_empty(payload, Command::ExecuteBurn),
+            OP_GET_REPORT => decode_empty(payload, Command::GetReport),
+            OP_ABORT => decode_empty(payload, Command::Abort),
+            other => return Decoded::Unknown(other),
+        };
+        match cmd {
+            Some(cmd) => Decoded::Command(cmd),
+            None => Decoded::Malformed,
+        }
+    }
+}
+
+impl Response {
+    /// write this response as one frame
+    pub fn send<W: ByteSink>(&self, sink: &mut W) -> Result<(), TransportError> {
+        let mut payload: [u8; MAX_PAYLOAD] = [0; MAX_PAYLOAD];
+        let (op, len): (u8, usize) = match self {
+            Response::Hello(v) => {
+                payload[0] = v.major;
+                payload[1] = v.minor;
+                (RSP_HELLO, 2)
+            },
+            Response::Snapshot(s) => (RSP_SNAPSHOT, s.encode(&mut payload)),
+            Response::LockStatus { cntl, key_source } => {
+                payload[0] = *cntl;
+                match key_source {
+                    Some(source) => {
+                        payload[1] = *source as u8;
+                        (RSP_LOCK_STATUS, 2)
+                    },
+                    None => (RSP_LOCK_STATUS, 1),
+                }
+            },
+            Response::Staged { checksum } => {
+                payload[..4].copy_from_slice(&checksum.to_le_bytes());
+                (RSP_STAGED, 4)
+            },
+            Response::Armed => (RSP_ARMED, 0),
+            Response::Burned(r) => (RSP_BURNED, r.encode(&mut payload)),
+            Response::Report(r) => (RSP_REPORT, r.encode(&mut payload)),
+            Response::Aborted => (RSP_ABORTED, 0),
+            Response::Error(e) => {
+                payload[0] = *e as u8;
+                (RSP_ERROR, 1)
+            },
+            Response::Unsupported { opcode } => {
+                payload[0] = *opcode;
+                (RSP_UNSUPPORTED, 1)
+            },
+        };
+        send_frame(sink, op, &payload[..len])
+    }
+
+    /// read the next response frame
+    pub fn receive<R: ByteSource>(source: &mut R) -> Result<Response, TransportError> {
+        let mut buf: [u8; MAX_FRAME] = [0; MAX_FRAME];
+        let (op, payload) = receive_frame(source, &mut buf)?;
+        match op {
+            RSP_HELLO => decode_version(payload).map(Response::Hello),
+            RSP_SNAPSHOT => FuseSnapshot::decode(payload).map(Response::Snapshot),
+            RSP_LOCK_STATUS => match payload {
+                [cntl] => Some(Response::LockStatus { cntl: *cntl, key_source: None }),
+                [cntl, source] => KeySource::from_code(*source).map(|s| Response::LockStatus { cntl: *cntl, key_source: Some(s) }),
+                _ => None,
+            },
+            RSP_STAGED => decode_u32(payload).map(|checksum| Response::Staged { checksum }),
+            RSP_ARMED => decode_empty(payload, Response::Armed),
+            RSP_BURNED => BurnReport::decode(payload).map(Response::Burned),
+            RSP_REPORT => BurnReport::decode(payload).map(Response::Report),
+            RSP_ABORTED => decode_empty(payload, Response::Aborted),
+            RSP_ERROR => {
+                if payload.len() == 1 { ProtocolError::from_code(payload[0]).map(Response::Error) } else { None }
+            },
+            RSP_UNSUPPORTED => {
+                if payload.len() == 1 { Some(Response::Unsupported { opcode: payload[0] }) } else { None }
+            },
+            other => return Err(TransportError::UnknownType(other)),
+        }.ok_or(TransportError::Truncated)
+    }
+}
+
+fn fetch_error(err: EfuseError) -> ProtocolError {
+    match err {
+        EfuseError::AccessBlockedBySecurity { .. } => ProtocolError::AccessRestricted,
+        _ => ProtocolError::FetchFailed,
+    }
+}
+
+/// Device side of the protocol: session and arming state across commands
+pub struct Server {
+    session: bool,
+    /// minor version the host offered in its Hello
+    host_minor: u8,
+    staged: Option<u32>,
+    armed: bool,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Server { session: false, host_minor: 0, staged: None, armed: false }
+    }
+
+    /// carry out one command against the API
+    pub fn handle<T: JtagPhy>(&mut self, api: &mut EfuseApi, cmd: Command, jm: &mut JtagMach, jp: &mut T) -> Response {
+        if let Command::Hello(version) = cmd {
+            self.session = PROTOCOL_VERSION.compatible(&version);
+            self.host_minor = version.minor;
+            return if self.session {
+                Response::Hello(PROTOCOL_VERSION)
+            } else {
+                Response::Error(ProtocolError::VersionMismatch)
+            };
+        }
+        if !self.session {
+            return Response::Error(ProtocolError::NoSession);
+        }
+
+        match cmd {
+            Command::Hello(_) => unreachable!(),
+            Command::GetSnapshot => match api.fetch(jm, jp) {
+                Ok(()) => Response::Snapshot(api.snapshot()),
+                Err(e) => Response::Error(fetch_error(e)),
+            },
+            Command::GetLockStatus => match api.lock_status(jm, jp) {
+                Ok(LockStatus::AccessRestricted { .. }) => Response::Error(ProtocolError::AccessRestricted),
+                Ok(_) => {
+                    // older hosts expect the one-byte form
+                    let key_source: Option<KeySource> = if self.host_minor >= 1 {
+                        api.boot_key_source(jm, jp).ok().map(|status| status.source)
+                    } else {
+                        None
+                    };
+                    Response::LockStatus { cntl: api.phy_cntl(), key_source }
+                },
+                Err(e) => Response::Error(fetch_error(e)),
+            },
+            Command::StageManifest(manifest) => match api.stage(&manifest) {
+                Ok(()) => {
+                    let checksum: u32 = manifest.checksum();
+                    self.staged = Some(checksum);
+                    self.armed = false;
+                    api.disarm();
+                    Response::Staged { checksum }
+                },
+                Err(_) => Response::Error(ProtocolError::BadFrame),
+            },
+            Command::Arm { checksum } => match self.staged {
+                None => Response::Error(ProtocolError::NotStaged),
+                Some(staged) if staged != checksum => Response::Error(ProtocolError::ChecksumMismatch),
+                Some(_) => {
+                    // the API checks the staged state against this again when it burns
+                    api.arm();
+                    self.armed = true;
+                    Response::Armed
+                },
+            },
+            Command::ExecuteBurn => {
+                if !self.armed {
+                    return Response::Error(ProtocolError::NotArmed);
+                }
+                // one burn per arming, whatever the outcome
+                self.armed = false;
+                self.staged = None;
+                // burn works against the fused state, so bring that up to date first
+                if let Err(e) = api.fetch(jm, jp) {
+                    return Response::Error(fetch_error(e));
+                }
+                match (api.burn(jm, jp), api.last_report()) {
+                    (Ok(()), Some(report)) => Response::Burned(report),
+                    _ => Response::Error(ProtocolError::BurnFailed),
+                }
+            },
+            Command::GetReport => match api.last_report() {
+                Some(report) => Response::Report(report),
+                None => Response::Error(ProtocolError::NoReport),
+            },
+            Command::Abort => {
+                self.armed = false;
+                self.staged = None;
+                api.disarm();
+                // fall back to the fused state as the intended state
+                // the fused CNTL is masked, so it's always accepted
+                api.stage(&ProvisioningManifest { key: api.phy_key(), user: api.phy_user(), cntl: api.phy_cntl() }).unwrap();
+                Response::Aborted
+            },
+        }
+    }
+
+    /// read one command from `io`, carry it out and write the response. A corrupt frame is
+    /// answered with BadFrame; Err is returned only if `io` itself fails or closes.
+    pub fn serve_one<S, T>(&mut self, api: &mut EfuseApi, io: &mut S, jm: &mut JtagMach, jp: &mut T) -> Result<(), TransportError>
+    where S: ByteSource + ByteSink, T: JtagPhy {
+        let mut buf: [u8; MAX_FRAME] = [0; MAX_FRAME];
+        let response: Response = match receive_frame(io, &mut buf) {
+            Ok((op, payload)) => match Command::decode(op, payload) {
+                Decoded::Command(cmd) => self.handle(api, cmd, jm, jp),
+                Decoded::Unknown(opcode) => Response::Unsupported { opcode },
+                Decoded::Malformed => Response::Error(ProtocolError::BadFrame),
+            },
+            Err(e @ TransportError::Closed) | Err(e @ TransportError::MissingDelimiter) | Err(e @ TransportError::Io) => return Err(e),
+            Err(_) => Response::Error(ProtocolError::BadFrame),
+        };
+        response.send(io)
+    }
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Serve commands from `io` until the stream closes
+pub fn serve<S, T>(api: &mut EfuseApi, io: &mut S, jm: &mut JtagMach, jp: &mut T) -> Result<(), TransportError>
+where S: ByteSource + ByteSink, T: JtagPhy {
+    let mut server: Server = Server::new();
+    loop {
+        match server.serve_one(api, io, jm, jp) {
+            Ok(()) => {},
+            Err(TransportError::Closed) => return Ok(()),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Errors seen by the host
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClientError {
+    Transport(TransportError),
+    /// the device declined the command
+    Remote(ProtocolError),
+    /// the device doesn't know the command's opcode
+    Unsupported(u8),
+    /// the device answered with a response that doesn't fit the command; boxed, as a Report
+    /// can carry a whole VectorManifest
+    Unexpected(Box<Response>),
+}
+
+impl From<TransportError> for ClientError {
+    fn from(err: TransportError) -> Self {
+        ClientError::Transport(err)
+    }
+}
+
+/// Host side of the protocol
+pub struct Client<S> {
+    io: S,
+}
+
+impl<S: ByteSource + ByteSink> Client<S> {
+    pub fn new(io: S) -> Self {
+        Client { io }
+    }
+
+    pub fn io(&mut self) -> &mut S {
+        &mut self.io
+    }
+
+    pub fn into_inner(self) -> S {
+        self.io
+    }
+
+    /// send `cmd` and wait for its response; Error and Unsupported responses come back as Err
+    pub fn request(&mut self, cmd: &Command) -> Result<Response, ClientError> {
+        cmd.send(&mut self.io)?;
+        match Response::receive(&mut self.io)? {
+            Response::Error(e) => Err(ClientError::Remote(e)),
+            Response::Unsupported { opcode } => Err(ClientError::Unsupported(opcode)),
+            rsp => Ok(rsp),
+        }
+    }
+
+    /// open a session, returning the device's protocol version
+    pub fn hello(&mut self) -> Result<ProtocolVersion, ClientError> {
+        match self.request(&Command::Hello(PROTOCOL_VERSION))? {
+            Response::Hello(version) => Ok(version),
+            rsp => Err(ClientError::Unexpected(Box::new(rsp))),
+        }
+    }
+
+    pub fn snapshot(&mut self) -> Result<FuseSnapshot, ClientError> {
+        match self.request(&Command::GetSnapshot)? {
+            Response::Snapshot(snapshot) => Ok(snapshot),
+            rsp => Err(ClientError::Unexpected(Box::new(rsp))),
+        }
+    }
+
+    pub fn lock_status(&mut self) -> Result<u8, ClientError> {
+        match self.request(&Command::GetLockStatus)? {
+            Response::LockStatus { cntl, .. } => Ok(cntl),
+            rsp => Err(ClientError::Unexpected(Box::new(rsp))),
+        }
+    }
+
+    /// the key the device will boot with; None if the device is older than 1.1 or couldn't tell
+    pub fn key_source(&mut self) -> Result<Option<KeySource>, ClientError> {
+        match self.request(&Command::GetLockStatus)? {
+            Response::LockStatus { key_source, .. } => Ok(key_source),
+            rsp => Err(ClientError::Unexpected(Box::new(rsp))),
+        }
+    }
+
+    /// stage `manifest`, returning the checksum the device computed for it
+    pub fn stage(&mut self, manifest: &ProvisioningManifest) -> Result<u32, ClientError> {
+        match self.request(&Command::StageManifest(*manifest))? {
+            Response::Staged { checksum } => Ok(checksum),
+            rsp => Err(ClientError::Unexpected(Box::new(rsp))),
+        }
+    }
+
+    pub fn arm(&mut self, checksum: u32) -> Result<(), ClientError> {
+        match self.request(&Command::Arm { checksum })? {
+            Response::Armed => Ok(()),
+            rsp => Err(ClientError::Unexpected(Box::new(rsp))),
+        }
+    }
+
+    pub fn execute_burn(&mut self) -> Result<BurnReport, ClientError> {
+        match self.request(&Command::ExecuteBurn)? {
+            Response::Burned(report) => Ok(report),
+            rsp => Err(ClientError::Unexpected(Box::new(rsp))),
+        }
+    }
+
+    pub fn report(&mut self) -> Result<BurnReport, ClientError> {
+        match self.request(&Command::GetReport)? {
+            Response::Report(report) => Ok(report),
+            rsp => Err(ClientError::Unexpected(Box::new(rsp))),
+        }
+    }
+
+    pub fn abort(&mut self) -> Result<(), ClientError> {
+        match self.request(&Command::Abort)? {
+            Response::Aborted => Ok(()),
+            rsp => Err(ClientError::Unexpected(Box::new(rsp))),
+        }
+    }
+
+    /// stage, arm and burn `manifest`. Arming uses the locally computed checksum, so a
+    /// manifest that was corrupted on the way to the device is never burned.
+    pub fn provision(&mut self, manifest: &ProvisioningManifest) -> Result<BurnReport, ClientError> {
+        self.stage(manifest)?;
+        self.arm(manifest.checksum())?;
+        self.execute_burn()
+    }
+}
@@ -0,0 +1,233 @@
+//! Python bindings for host-side analysis
+//!
+//! Only the offline model is wrapped: snapshots, planning and validation, and the .nky parser.
+//! Build the extension with `cargo rustc --release --features python --crate-type cdylib` (or
+//! maturin) and import it as `efuse_api`.
+//!
+//! Keys can be staged from Python but never read back, neither directly nor through the
+//! plan: plans are reported as bit counts per bank, not as the words that would be shifted.
+//! A key is identified by its fingerprint, the first 8 bytes of its SHA-256 in hex.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use sha2::{Digest, Sha256};
+
+use crate::keyhex::{self, HexError};
+use crate::layout::FUSE_BANKS;
+use crate::messages::FuseSnapshot;
+use crate::EfuseApi;
+
+create_exception!(efuse_api, EfuseError, PyException);
+create_exception!(efuse_api, InvalidPlan, EfuseError);
+create_exception!(efuse_api, UserMismatch, EfuseError);
+create_exception!(efuse_api, WeakKey, EfuseError);
+create_exception!(efuse_api, KeyFormatError, PyValueError);
+
+/// `err`, with `attrs` set on the exception object
+fn with_attrs(py: Python, err: PyErr, attrs: &[(&str, PyObject)]) -> PyErr {
+    for (name, value) in attrs.iter() {
+        if let Err(e) = err.value(py).setattr(*name, value) {
+            return e;
+        }
+    }
+    err
+}
+
+fn efuse_err(py: Python, e: crate::EfuseError) -> PyErr {
+    match e {
+        crate::EfuseError::Invalid | crate::EfuseError::IllegalTransition { .. } => InvalidPlan::new_err("the planned state can't be reached from the fused state"),
+        crate::EfuseError::UserMismatch { direct, derived } => with_attrs(py,
+            UserMismatch::new_err(format!("FUSE_USER reads {:#010x} but the banks decode to {:#010x}", direct, derived)),
+            &[("direct", direct.into_py(py)), ("derived", derived.into_py(py))]),
+        crate::EfuseError::WeakKey { reason } => with_attrs(py,
+            WeakKey::new_err(format!("staged key looks non-random: {:?}", reason)),
+            &[("reason", format!("{:?}", reason).into_py(py))]),
+        e => EfuseError::new_err(format!("{:?}", e)),
+    }
+}
+
+fn hex_err(py: Python, e: HexError) -> PyErr {
+    match e {
+        HexError::InvalidDigit { position, found } => with_attrs(py,
+            KeyFormatError::new_err(format!("{:?} at offset {} isn't a hex digit", found, position)),
+            &[("position", position.into_py(py)), ("found", found.into_py(py))]),
+        HexError::WrongLength { digits } => with_attrs(py,
+            KeyFormatError::new_err(format!("a key has {} hex digits, not {}", keyhex::KEY_DIGITS, digits)),
+            &[("digits", digits.into_py(py))]),
+        HexError::MissingKey => KeyFormatError::new_err("no \"Key 0\" line"),
+    }
+}
+
+fn fingerprint(key: &[u8; 32]) -> String {
+    Sha256::digest(key)[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Raw contents of every fuse bank
+#[pyclass(name = "FuseSnapshot")]
+#[derive(Clone)]
+pub struct PySnapshot {
+    inner: FuseSnapshot,
+}
+
+#[pymethods]
+impl PySnapshot {
+    #[new]
+    fn new(banks: Vec<u32>) -> PyResult<Self> {
+        let mut snapshot = FuseSnapshot { banks: [0; FUSE_BANKS] };
+        if banks.len() != FUSE_BANKS {
+            return Err(PyValueError::new_err(format!("a snapshot has {} banks, not {}", FUSE_BANKS, banks.len())));
+        }
+        snapshot.banks.copy_from_slice(&banks);
+        Ok(PySnapshot { inner: snapshot })
+    }
+
+    /// from the wire encoding used by the provisioning protocol
+    #[staticmethod]
+    fn decode(bytes: &[u8]) -> PyResult<Self> {
+        FuseSnapshot::decode(bytes)
+            .map(|inner| PySnapshot { inner })
+            .ok_or_else(|| PyValueError::new_err(format!("a snapshot is {} bytes, not {}", FuseSnapshot::WIRE_LEN, bytes.len())))
+    }
+
+    fn encode<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        let mut bytes = [0u8; FuseSnapshot::WIRE_LEN];
+        self.inner.encode(&mut bytes);
+        PyBytes::new(py, &bytes)
+    }
+
+    #[getter]
+    fn banks(&self) -> Vec<u32> {
+        self.inner.banks.to_vec()
+    }
+
+    /// (bank, this value, other value) for each bank that differs, in bank order
+    fn diff(&self, other: PyRef<PySnapshot>) -> Vec<(usize, u32, u32)> {
+        self.inner.banks.iter().zip(other.inner.banks.iter()).enumerate()
+            .filter(|(_, (a, b))| a != b)
+            .map(|(bank, (&a, &b))| (bank, a, b))
+            .collect()
+    }
+
+    fn __eq__(&self, other: PyRef<PySnapshot>) -> bool {
+        self.inner == other.inner
+    }
+}
+
+/// A fused state, loaded from a snapshot, and the state it's planned to be burned to
+#[pyclass(name = "Planner")]
+pub struct PyPlanner {
+    api: EfuseApi,
+}
+
+#[pymethods]
+impl PyPlanner {
+    #[staticmethod]
+    fn from_snapshot(snapshot: PyRef<PySnapshot>) -> Self {
+        let mut api = EfuseApi::new();
+        api.load_snapshot(&snapshot.inner);
+        // start from the fused state, so that anything not staged is left alone
+        api.set_key(api.phy_key());
+        api.set_user(api.phy_user());
+        // the fused CNTL is masked, so it's always accepted
+        api.set_cntl(api.phy_cntl()).unwrap();
+        PyPlanner { api }
+    }
+
+    /// stage the key from 32 bytes, key[0] first
+    fn stage_key(&mut self, key: &[u8]) -> PyResult<()> {
+        let mut staged = [0u8; 32];
+        if key.len() != staged.len() {
+            return Err(PyValueError::new_err(format!("a key is 32 bytes, not {}", key.len())));
+        }
+        staged.copy_from_slice(key);
+        self.api.set_key(staged);
+        Ok(())
+    }
+
+    /// stage the key from the text of a Xilinx .nky file
+    fn stage_nky(&mut self, py: Python, nky: &str) -> PyResult<()> {
+        self.api.stage_from_nky(nky).map_err(|e| hex_err(py, e))
+    }
+
+    fn key_fingerprint(&self) -> String {
+        fingerprint(&self.api.api_key())
+    }
+
+    fn fused_key_fingerprint(&self) -> String {
+        fingerprint(&self.api.phy_key())
+    }
+
+    #[getter]
+    fn user(&self) -> u32 {
+        self.api.api_user()
+    }
+
+    #[setter]
+    fn set_user(&mut self, user: u32) {
+        self.api.set_user(user);
+    }
+
+    #[getter]
+    fn cntl(&self) -> u8 {
+        self.api.api_cntl()
+    }
+
+    #[setter]
+    fn set_cntl(&mut self, py: Python, cntl: u8) -> PyResult<()> {
+        self.api.set_cntl(cntl).map_err(|e| efuse_err(py, e))
+    }
+
+    fn allow_weak_key(&mut self, allow: bool) {
+        self.api.allow_weak_key(allow);
+    }
+
+    /// Checks the plan, raising if it can't or shouldn't be burned. Returns the warnings.
+    fn validate(&mut self, py: Python) -> PyResult<Vec<String>> {
+        let report = self.api.validate().map_err(|e| efuse_err(py, e))?;
+        Ok(report.warnings.iter().map(|w| format!("{:?}", w)).collect())
+    }
+
+    /// (bank, bits to blow) for each bank the burn programs, in burn order
+    fn plan(&mut self, py: Python) -> PyResult<Vec<(usize, u32)>> {
+        let plan = self.api.plan().map_err(|e| efuse_err(py, e))?;
+        Ok(plan.banks().map(|(bank, bits)| (bank, bits.count_ones())).collect())
+    }
+
+    /// time the burn would take at the given TCK frequency
+    fn estimated_duration_us(&self, tck_hz: u32) -> PyResult<u64> {
+        if tck_hz == 0 {
+            return Err(PyValueError::new_err("tck_hz must be nonzero"));
+        }
+        Ok(self.api.estimate_burn_duration().total_us(tck_hz))
+    }
+}
+
+/// fingerprint of the key in a .nky file, without staging it anywhere
+#[pyfunction]
+fn nky_fingerprint(py: Python, nky: &str) -> PyResult<String> {
+    keyhex::key_from_nky(nky).map(|key| fingerprint(&key)).map_err(|e| hex_err(py, e))
+}
+
+/// adds the classes, functions and exceptions to `m`
+pub fn register(py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<PySnapshot>()?;
+    m.add_class::<PyPlanner>()?;
+    m.add_function(wrap_pyfunction!(nky_fingerprint, m)?)?;
+    m.add("EfuseError", py.get_type::<EfuseError>())?;
+    m.add("InvalidPlan", py.get_type::<InvalidPlan>())?;
+    m.add("UserMismatch", py.get_type::<UserMismatch>())?;
+    m.add("WeakKey", py.get_type::<WeakKey>())?;
+    m.add("KeyFormatError", py.get_type::<KeyFormatError>())?;
+    Ok(())
+}
+
+#[pymodule]
+fn efuse_api(py: Python, m: &PyModule) -> PyResult<()> {
+    register(py, m)
+}
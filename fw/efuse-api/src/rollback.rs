@@ -0,0 +1,82 @@
+//! Anti-rollback counting in the USER fuses
+//!
+//! A RollbackCounter treats a range of USER bits as a unary counter: the count is the number
+//! of its bits blown, and raising it blows one more. Firmware refuses to run an image whose
+//! rollback index is below the fused count, so an old, vulnerable image can't be put back.
+//!
+//! Banks 11 and 12 carry ECC, so not every clear bit can be added to a burned bank: the new
+//! ECC code has to keep every ECC fuse already blown. increment() therefore takes the lowest
+//! clear bit of the range that leaves both USER banks with a reachable code, rather than
+//! strictly the next one up; counting in strict order would run out after a bit or two in
+//! most banks. max_count() walks the same choice to its end, so it's the count the range can
+//! actually reach from the fused state, and usually well short of its width.
+//!
+//! The counter only stages USER; burn() does the rest, as with any other change.
+
+use crate::layout::*;
+use crate::{EfuseApi, EfuseError};
+
+/// USER bits `low` up to `low + bits`, used as an anti-rollback counter
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RollbackCounter {
+    /// USER bits making up the counter
+    pub field: u32,
+}
+
+impl RollbackCounter {
+    pub fn new(low: u32, bits: u32) -> Self {
+        assert!(bits > 0 && low + bits <= 32, "the counter has to fit in USER");
+        let ones: u32 = if bits == 32 { !0 } else { (1 << bits) - 1 };
+        RollbackCounter { field: ones << low }
+    }
+
+    /// the count fused
+    pub fn read_count(&self, efuse: &EfuseApi) -> u32 {
+        (efuse.phy_user() & self.field).count_ones()
+    }
+
+    /// the count the fused state can be taken to, one burn per increment, with the key, CNTL
+    /// and the rest of USER as staged
+    pub fn max_count(&self, efuse: &EfuseApi) -> u32 {
+        let mut banks: [u32; FUSE_BANKS] = efuse.snapshot().banks;
+        let mut user: u32 = self.staged_user(efuse);
+        while let Some(next) = self.next(efuse, &banks, user) {
+            user = next;
+            for index in [SHARED_BANK, USER_BANK].iter().copied() {
+                banks[index] = bank_image_ecc(index, &efuse.api_key(), user, efuse.api_cntl());
+            }
+        }
+        (user & self.field).count_ones()
+    }
+
+    /// Stages USER with the counter one above the fused count, and validates the result;
+    /// returns the count staged. Fails with RollbackExhausted if no bit of the range can be
+    /// added, or with whatever validate() refuses, staging nothing in either case.
+    pub fn increment(&self, efuse: &mut EfuseApi) -> Result<u32, EfuseError> {
+        let staged: u32 = efuse.api_user();
+        let user: u32 = self.staged_user(efuse);
+        let next: u32 = self.next(efuse, &efuse.snapshot().banks, user)
+            .ok_or(EfuseError::RollbackExhausted { count: (user & self.field).count_ones() })?;
+        efuse.set_user(next);
+        if let Err(e) = efuse.validate() {
+            efuse.set_user(staged);
+            return Err(e);
+        }
+        Ok((next & self.field).count_ones())
+    }
+
+    /// the staged USER with the counter bits as fused
+    fn staged_user(&self, efuse: &EfuseApi) -> u32 {
+        (efuse.api_user() & !self.field) | (efuse.phy_user() & self.field)
+    }
+
+    /// `user` with the lowest clear counter bit whose USER banks can be burned over `banks`
+    fn next(&self, efuse: &EfuseApi, banks: &[u32; FUSE_BANKS], user: u32) -> Option<u32> {
+        (0..32).map(|bit| 1u32 << bit)
+            .filter(|&bit| self.field & !user & bit != 0)
+            .map(|bit| user | bit)
+            .find(|&next| [SHARED_BANK, USER_BANK].iter().all(|&index| {
+                banks[index] & !bank_image_ecc(index, &efuse.api_key(), next, efuse.api_cntl()) == 0
+            }))
+    }
+}
@@ -0,0 +1,49 @@
+//! Wiping key material
+//!
+//! The staged key, the fetched one and the banks that carry them stay in RAM once an EfuseApi
+//! or EfusePhy is done with, unless something overwrites them. clear_secrets() on either does,
+//! and both call it when dropped. The writes here are volatile and followed by a fence, so the
+//! optimizer can't drop them as dead stores to memory that's about to go away.
+//!
+//! With the `zeroize` feature the wiping is left to the zeroize crate instead, and EfuseApi
+//! and EfusePhy implement its Zeroize and ZeroizeOnDrop.
+//!
+//! This covers the copies the API holds, not ones handed out: phy_key() and api_key() return
+//! the key by value, and a caller that takes a copy should wipe it with wipe_bytes() when done.
+//! Prefer EfuseApi::key_matches() where a comparison is all that's needed. Nor does it reach
+//! bytes the compiler leaves unused, such as those of an enum variant smaller than the enum,
+//! which can pick up stray words of the stack when the field is assigned.
+
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use crate::layout::*;
+
+/// overwrites `bytes` with zeros
+pub fn wipe_bytes(bytes: &mut [u8]) {
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(bytes);
+    #[cfg(not(feature = "zeroize"))]
+    for b in bytes.iter_mut() {
+        // safety: `b` is a valid, aligned &mut u8
+        unsafe { core::ptr::write_volatile(b, 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// overwrites `words` with zeros
+pub fn wipe_words(words: &mut [u32]) {
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(words);
+    #[cfg(not(feature = "zeroize"))]
+    for w in words.iter_mut() {
+        // safety: as for wipe_bytes()
+        unsafe { core::ptr::write_volatile(w, 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// overwrites the banks that hold key bits, 1 to SHARED_BANK, with zeros; the USER bits of the
+/// shared bank go with them
+pub fn wipe_key_banks(banks: &mut [u32; FUSE_BANKS]) {
+    wipe_words(&mut banks[1..=SHARED_BANK]);
+}
@@ -0,0 +1,378 @@
+//! JTAG command sequences used to program the eFUSE array
+//!
+//! The programming interface is driven entirely through 64-bit words shifted into the DR
+//! after selecting the EFUSE instruction. The exact words for a given bank are produced by
+//! `dr_words_for_bank`, so that the words `burn()` executes and the words any audit or
+//! export tooling sees are generated by the same code.
+
+use alloc::vec::Vec;
+
+use jtag::IrVerification;
+
+use crate::layout::*;
+use crate::messages::VectorManifest;
+use crate::sha256::Sha256;
+
+/// JSTART instruction, issued before opening the programming port
+pub const CMD_JSTART: u32 = 0b001100;
+/// eFUSE programming port instruction (FUSE_CTS)
+pub const CMD_EFUSE: u32 = 0b110000;
+
+/// Length of the 7-series instruction register, in bits
+pub const IR_BITS: usize = 6;
+
+/// Budgets, in phy operations, for the legs a burn or fetch shifts (see jtag::JtagLeg::set_budget).
+/// A clean traversal takes the leg's length plus at most jtag::JtagLeg::OVERHEAD_CYCLES; a leg
+/// that runs past its budget means the phy is wedged.
+pub const IR_BUDGET: u32 = 64;
+/// each word of the unlock pair
+pub const UNLOCK_BUDGET: u32 = 256;
+/// bank select, bit program and wait words
+pub const PROGRAM_BUDGET: u32 = 256;
+/// readback of a fuse or configuration register, up to FUSE_KEY's 256 bits
+pub const POLL_BUDGET: u32 = 1024;
+
+/// 7-series JTAG instructions (UG470, table 6-3)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Ir {
+    Sample = 0b000001,
+    User1 = 0b000010,
+    User2 = 0b000011,
+    CfgOut = 0b000100,
+    CfgIn = 0b000101,
+    Usercode = 0b001000,
+    Idcode = 0b001001,
+    HighZ = 0b001010,
+    Jprogram = 0b001011,
+    Jstart = 0b001100,
+    Jshutdown = 0b001101,
+    IscEnable = 0b010000,
+    IscProgram = 0b010001,
+    IscNoop = 0b010100,
+    IscDisable = 0b010110,
+    XscDna = 0b010111,
+    User3 = 0b100010,
+    User4 = 0b100011,
+    Extest = 0b100110,
+    FuseCts = 0b110000,
+    FuseKey = 0b110001,
+    FuseDna = 0b110010,
+    FuseUser = 0b110011,
+    FuseCntl = 0b110100,
+    XadcDrp = 0b110111,
+    Bypass = 0b111111,
+}
+
+impl Ir {
+    const ALL: [Ir; 26] = [
+        Ir::Sample, Ir::User1, Ir::User2, Ir::CfgOut, Ir::CfgIn, Ir::Usercode, Ir::Idcode,
+        Ir::HighZ, Ir::Jprogram, Ir::Jstart, Ir::Jshutdown, Ir::IscEnable, Ir::IscProgram,
+        Ir::IscNoop, Ir::IscDisable, Ir::XscDna, Ir::User3, Ir::User4, Ir::Extest, Ir::FuseCts,
+        Ir::FuseKey, Ir::FuseDna, Ir::FuseUser, Ir::FuseCntl, Ir::XadcDrp, Ir::Bypass,
+    ];
+
+    /// instruction code, as shifted into the IR
+    pub fn code(self) -> u32 {
+        self as u32
+    }
+
+    /// decode an instruction code; None if it isn't a documented instruction
+    pub fn from_code(code: u32) -> Option<Ir> {
+        Ir::ALL.iter().copied().find(|ir| ir.code() == code)
+    }
+}
+
+/// Device-specific constants of the eFUSE programming interface
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DeviceParams {
+    /// length of the programming DR, in bits
+    pub dr_bits: usize,
+    /// fixed upper word common to every programming DR word
+    pub dr_header: u64,
+    /// unlock word, shifted twice to open the programming port
+    pub unlock: u64,
+    /// flag set in every bit-program word
+    pub program_flag: u64,
+    /// bank select code of bank 1; subsequent banks are `bank_select_stride` apart
+    pub bank_select_base: u8,
+    pub bank_select_stride: u8,
+    /// bank select code of the CNTL bank, which doesn't follow the key/user encoding
+    pub cntl_bank_select: u8,
+    /// word select code of the CNTL bank
+    pub cntl_word_select: u8,
+    /// FUSE_CNTL bits to shift to read back both copies of the CNTL bits
+    pub cntl_readback_bits: usize,
+    /// or'd into the bank select code to form the word select code of key/user banks
+    pub word_select_flag: u8,
+    /// position of the 5-bit bit index within a bit-program word
+    pub bit_shift: u32,
+    /// check on the IR scans that select the programming port; Capture where the device's
+    /// capture value is known, otherwise at least Mandatory
+    pub ir_verification: IrVerification,
+}
+
+impl DeviceParams {
+    pub const SEVEN_SERIES: DeviceParams = DeviceParams {
+        dr_bits: 64,
+        dr_header: 0xa08a_28ac_0000_0000,
+        unlock: 0xa08a_28ac_0000_4001,
+        program_flag: 0x4000,
+        bank_select_base: 0xA1,
+        bank_select_stride: 8,
+        cntl_bank_select: 1,
+        cntl_word_select: 3,
+        cntl_readback_bits: 20,
+        word_select_flag: 0b10,
+        bit_shift: 8,
+        // the upper capture bits are DONE, INIT_COMPLETE, ISC_ENABLED and ISC_DONE, which
+        // depend on the configuration state, so only the mandatory 01 is certain
+        ir_verification: IrVerification::Mandatory,
+    };
+
+    /// code selecting `bank` for programming
+    pub fn bank_select(&self, bank: usize) -> u8 {
+        assert!(bank < FUSE_BANKS);
+        if bank == CNTL_BANK {
+            self.cntl_bank_select
+        } else {
+            (bank as u8 - 1) * self.bank_select_stride + self.bank_select_base
+        }
+    }
+
+    /// code selecting the word within `bank` that individual bits are programmed into
+    pub fn word_select(&self, bank: usize) -> u8 {
+        if bank == CNTL_BANK {
+            self.cntl_word_select
+        } else {
+            self.bank_select(bank) | self.word_select_flag
+        }
+    }
+
+    /// DR word selecting `bank`
+    pub fn bank_word(&self, bank: usize) -> u64 {
+        self.dr_header | self.bank_select(bank) as u64
+    }
+
+    /// DR word programming `bit` of `bank`. For SEVEN_SERIES that's the header in bits 63:32,
+    /// the program flag in bit 14, the bit index in 12:8 and the word select code in 7:0;
+    /// e.g. bit 4 of bank 1 is 0xa08a_28ac_0000_44a3.
+    pub fn bit_word(&self, bank: usize, bit: u8) -> u64 {
+        assert!(bit < 32);
+        let base: u64 = self.dr_header | self.program_flag | self.word_select(bank) as u64;
+        // the bit index must land in a field of its own, or it'd address another word
+        debug_assert_eq!(base & (0x1F << self.bit_shift), 0);
+        base | ((bit as u64) << self.bit_shift)
+    }
+
+    /// Short identifier of these parameters: the first 4 bytes, little-endian, of the SHA-256
+    /// of every field in declaration order, integers as little-endian u64. ir_verification
+    /// counts as three: 0, 1 or 2 for Off, Mandatory or Capture, then the value and mask.
+    pub fn id(&self) -> u32 {
+        let (verify, capture, mask): (u64, u64, u64) = match self.ir_verification {
+            IrVerification::Off => (0, 0, 0),
+            IrVerification::Mandatory => (1, 0, 0),
+            IrVerification::Capture { value, mask } => (2, value as u64, mask as u64),
+        };
+        let mut h: Sha256 = Sha256::new();
+        for field in [self.dr_bits as u64, self.dr_header, self.unlock, self.program_flag,
+            self.bank_select_base as u64, self.bank_select_stride as u64, self.cntl_bank_select as u64,
+            self.cntl_word_select as u64, self.cntl_readback_bits as u64, self.word_select_flag as u64,
+            self.bit_shift as u64, verify, capture, mask].iter() {
+            h.update(&field.to_le_bytes());
+        }
+        let digest: [u8; 32] = h.finish();
+        u32::from_le_bytes([digest[0], digest[1], digest[2], digest[3]])
+    }
+}
+
+impl Default for DeviceParams {
+    fn default() -> Self {
+        DeviceParams::SEVEN_SERIES
+    }
+}
+
+/// What a programming word does
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WordKind {
+    /// unlocks the programming port; always comes in pairs
+    Unlock,
+    /// selects the bank to program
+    BankSelect,
+    /// programs the given bit of the selected bank
+    Bit(u8),
+    /// idle word, giving the previous operation time to complete
+    Wait,
+}
+
+/// A single 64-bit word shifted into the EFUSE DR
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ProgramWord {
+    pub value: u64,
+    pub kind: WordKind,
+}
+
+/// Order in which the bits within a bank are programmed
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BitOrderPolicy {
+    #[default]
+    Ascending,
+    /// a permutation derived from `seed`, different for each bank. Programming bits in a
+    /// fixed order makes it easier to correlate supply current with the bits being blown.
+    Shuffled { seed: u64 },
+}
+
+/// splitmix64 step; good enough to shuffle 32 items, and needs no dependencies
+pub(crate) fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z: u64 = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Returns the bits set in `ones`, in the order `order` programs them into `bank`, along with
+/// how many there are.
+pub fn bit_order(bank: usize, ones: u32, order: BitOrderPolicy) -> ([u8; 32], usize) {
+    let mut bits: [u8; 32] = [0; 32];
+    let mut count: usize = 0;
+    for bit in 0..32u8 {
+        if (ones >> bit) & 0x1 == 1 {
+            bits[count] = bit;
+            count += 1;
+        }
+    }
+    if let BitOrderPolicy::Shuffled { seed } = order {
+        // Fisher-Yates, from a stream that depends on both the seed and the bank
+        let mut state: u64 = seed ^ (bank as u64).wrapping_mul(0xD1B5_4A32_D192_ED03);
+        for i in (1..count).rev() {
+            let j: usize = (splitmix64(&mut state) % (i as u64 + 1)) as usize;
+            bits.swap(i, j);
+        }
+    }
+    (bits, count)
+}
+
+/// Returns the exact sequence of DR words that programs the bits set in `ones` into `bank`.
+///
+/// The sequence opens the port and selects the bank, programs each bit in ascending order
+/// (each followed by a wait word), then repeats the open/select bracket to close out the
+/// bank. If `ones` is 0, nothing needs to be programmed and the sequence is empty.
+pub fn dr_words_for_bank(bank: usize, ones: u32, params: &DeviceParams) -> impl Iterator<Item = ProgramWord> {
+    dr_words_for_bank_ordered(bank, ones, params, BitOrderPolicy::Ascending)
+}
+
+/// As dr_words_for_bank, with the bits programmed in the order given by `order`. The bracket
+/// around the bits is the same whatever the order.
+pub fn dr_words_for_bank_ordered(bank: usize, ones: u32, params: &DeviceParams, order: BitOrderPolicy) -> impl Iterator<Item = ProgramWord> {
+    let unlock = ProgramWord { value: params.unlock, kind: WordKind::Unlock };
+    let select = ProgramWord { value: params.bank_word(bank), kind: WordKind::BankSelect };
+    let wait = ProgramWord { value: 0, kind: WordKind::Wait };
+    let bracket: [ProgramWord; 4] = [unlock, unlock, select, wait];
+    let bracket_len: usize = if ones == 0 { 0 } else { bracket.len() };
+
+    let params: DeviceParams = *params;
+    let (order, count) = bit_order(bank, ones, order);
+    let bits = IntoIterator::into_iter(order).take(count)
+        .flat_map(move |bit| {
+            let program = ProgramWord { value: params.bit_word(bank, bit), kind: WordKind::Bit(bit) };
+            IntoIterator::into_iter([program, wait])
+        });
+
+    IntoIterator::into_iter(bracket).take(bracket_len)
+        .chain(bits)
+        .chain(IntoIterator::into_iter(bracket).take(bracket_len))
+}
+
+/// Returns the DR words that program `bits_to_set` (CNTL bits, as in layout's CNTL_* constants)
+/// into one copy of the CNTL bank.
+///
+/// Each copy gets its own open/select bracket, so a burn can read back and check the first
+/// copy before it touches the second. Bits are programmed in ascending order.
+pub fn program_cntl_copy(bits_to_set: u8, copy: CntlCopy, params: &DeviceParams) -> impl Iterator<Item = ProgramWord> {
+    dr_words_for_bank(CNTL_BANK, ((bits_to_set & CNTL_MASK) as u32) << copy.shift(), params)
+}
+
+/// Returns the DR words that program `bits_to_set` into both copies of the CNTL bank, the
+/// primary copy first. Unlike the key and user banks there's no ECC to add: the redundant
+/// copy is what protects the CNTL bits.
+pub fn program_cntl(bits_to_set: u8, params: &DeviceParams) -> impl Iterator<Item = ProgramWord> {
+    program_cntl_copy(bits_to_set, CntlCopy::Primary, params)
+        .chain(program_cntl_copy(bits_to_set, CntlCopy::Redundant, params))
+}
+
+/// Returns the words that program `requested` (bits to blow, per bank), one section per bank
+/// with bits to blow, in burn order: bank 0, which holds CNTL, comes last.
+pub fn bank_sections(requested: [u32; FUSE_BANKS], params: &DeviceParams, order: BitOrderPolicy) -> impl Iterator<Item = (usize, impl Iterator<Item = ProgramWord>)> {
+    let params: DeviceParams = *params;
+    (0..FUSE_BANKS).rev()
+        .filter(move |&index| requested[index] != 0)
+        .map(move |index| (index, dr_words_for_bank_ordered(index, requested[index], &params, order)))
+}
+
+/// A burn's programming words, frozen so that the sequence qualified on one unit can be
+/// replayed exactly on others. The commit sequence isn't included; it's fixed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompiledSequence {
+    params: DeviceParams,
+    order: BitOrderPolicy,
+    sections: Vec<(usize, Vec<ProgramWord>)>,
+}
+
+impl CompiledSequence {
+    /// the words programming `requested`, as burn() would shift them
+    pub fn new(requested: &[u32; FUSE_BANKS], params: &DeviceParams, order: BitOrderPolicy) -> Self {
+        CompiledSequence {
+            params: *params,
+            order,
+            sections: bank_sections(*requested, params, order).map(|(index, words)| (index, words.collect())).collect(),
+        }
+    }
+
+    pub fn params(&self) -> &DeviceParams {
+        &self.params
+    }
+
+    pub fn order(&self) -> BitOrderPolicy {
+        self.order
+    }
+
+    /// (bank, words) for each bank programmed, in burn order
+    pub fn sections(&self) -> impl Iterator<Item = (usize, &[ProgramWord])> + '_ {
+        self.sections.iter().map(|(index, words)| (*index, &words[..]))
+    }
+
+    pub fn words(&self) -> impl Iterator<Item = ProgramWord> + '_ {
+        self.sections.iter().flat_map(|(_, words)| words.iter().copied())
+    }
+
+    /// bits programmed, per bank
+    pub fn programmed(&self) -> [u32; FUSE_BANKS] {
+        let mut programmed: [u32; FUSE_BANKS] = [0; FUSE_BANKS];
+        for (index, words) in self.sections.iter() {
+            for word in words.iter() {
+                if let WordKind::Bit(bit) = word.kind {
+                    programmed[*index] |= 1 << bit;
+                }
+            }
+        }
+        programmed
+    }
+
+    /// Fingerprint of the sequence. The hash covers each word's value as 8 little-endian
+    /// bytes, in shift order.
+    pub fn manifest(&self) -> VectorManifest {
+        let mut h: Sha256 = Sha256::new();
+        let mut word_count: u32 = 0;
+        for word in self.words() {
+            h.update(&word.value.to_le_bytes());
+            word_count += 1;
+        }
+        let mut plan_summary: [u8; FUSE_BANKS] = [0; FUSE_BANKS];
+        for (bits, ones) in plan_summary.iter_mut().zip(self.programmed().iter()) {
+            *bits = ones.count_ones() as u8;
+        }
+        VectorManifest { sha256_of_words: h.finish(), word_count, device_params_id: self.params.id(), plan_summary }
+    }
+}
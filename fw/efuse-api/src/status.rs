@@ -0,0 +1,46 @@
+//! Status the programming port shifts back
+//!
+//! Every word shifted into the FUSE_CTS data register shifts the port's status out. Its low
+//! bits say whether a programming pulse is still in progress (busy) and whether a pulse
+//! failed (program error); the rest of the capture is reserved. The error indication is
+//! sticky until the TAP resets, so it also shows up in captures after the failing pulse.
+//!
+//! burn() decodes the capture of every word it shifts, unlock and bank select words included,
+//! and accumulates the errors per bank in its BurnReport. An unlock or bank select comes
+//! before its bank's first pulse, so an error first seen there goes to the bank pulsed last.
+//! After the commit it reads the status once more, with a wait
+//! word, and that read has to come back clean before the burn is reported as done: anything
+//! else fails with DeviceReportedError, carrying the raw status. The reset that ends every
+//! burn clears the indication for the next one.
+
+/// A status word captured from the programming port
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub struct FuseStatus {
+    /// the capture as read, reserved bits included
+    pub raw: u32,
+}
+
+impl FuseStatus {
+    /// a programming pulse is in progress
+    pub const BUSY: u32 = 1 << 0;
+    /// a programming pulse failed; sticky until the TAP resets
+    pub const PROGRAM_ERROR: u32 = 1 << 1;
+
+    /// the status in the low bits of a programming DR capture
+    pub fn from_capture(capture: u128) -> Self {
+        FuseStatus { raw: capture as u32 }
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.raw & FuseStatus::BUSY != 0
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.raw & FuseStatus::PROGRAM_ERROR != 0
+    }
+
+    /// neither busy nor reporting an error
+    pub fn is_clean(&self) -> bool {
+        !self.is_busy() && !self.is_error()
+    }
+}
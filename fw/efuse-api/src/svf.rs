@@ -0,0 +1,211 @@
+//! Serial Vector Format export of the JTAG traffic
+//!
+//! A factory with a standalone SVF player can replay a burn without this crate: SvfWriter is a
+//! phy that wraps another one and writes everything driven through it as SVF text. Each IR or DR
+//! scan becomes an SIR or SDR with its TDI bits, cycles held in RUN_TEST/IDLE a RUNTEST with a
+//! TCK count, pauses a RUNTEST in seconds, and a trip through TEST_LOGIC_RESET a STATE RESET.
+//! The xsvf module writes the same steps as compiled XSVF.
+//!
+//! The wrapped phy supplies TDO, as the JtagMach still checks what it captures: the
+//! EfuseModelPhy or a ScriptedPhy, loaded with the fused state, stand in for the device when
+//! the vectors are made offline. The file carries no TDO, so the player checks nothing it reads
+//! back; vectors made this way are only as good as the fused state they were planned against.
+//!
+//! The JtagMach ends every scan in RUN_TEST/IDLE, and the file has the player do the same
+//! (ENDIR/ENDDR IDLE), so only the cycles spent there beyond the first are written out.
+
+use alloc::vec::Vec;
+use core::fmt;
+use jtag::*;
+
+/// A step of the TAP worth writing down
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Step {
+    /// the TAP went to TEST_LOGIC_RESET
+    Reset,
+    /// the TAP left TEST_LOGIC_RESET for RUN_TEST/IDLE
+    Idle,
+    /// an IR scan, or a DR scan, completed with these bits shifted in and out, first shifted
+    /// first
+    Scan { ir: bool, tdi: Vec<bool>, tdo: Vec<bool> },
+}
+
+/// Follows the TAP through the cycles driven, turning them into steps
+pub(crate) struct ScanTracker {
+    pub(crate) tap: TapState,
+    tdi: Vec<bool>,
+    tdo: Vec<bool>,
+    /// cycles held in RUN_TEST/IDLE since the last call to take_idle()
+    idle: u32,
+}
+
+impl ScanTracker {
+    /// the TAP is taken to be in TEST_LOGIC_RESET, as the JtagMach takes it to be
+    pub(crate) fn new() -> Self {
+        ScanTracker { tap: TapState::TestLogicReset, tdi: Vec::new(), tdo: Vec::new(), idle: 0 }
+    }
+
+    /// the step clocking the TAP with `tdi` and `tms` completed, if any, with `tdo` what the
+    /// device drove for the cycle
+    pub(crate) fn clock(&mut self, tdi: bool, tms: bool, tdo: bool) -> Option<Step> {
+        let from: TapState = self.tap;
+        let to: TapState = from.next(tms);
+        self.tap = to;
+        match from {
+            TapState::ShiftIr | TapState::ShiftDr => {
+                self.tdi.push(tdi);
+                self.tdo.push(tdo);
+            },
+            // entering idle is the player's own move; only the cycles spent there count
+            TapState::RunTestIdle if to == TapState::RunTestIdle => self.idle += 1,
+            _ => {},
+        }
+        if from == to {
+            return None;
+        }
+        match to {
+            TapState::TestLogicReset => Some(Step::Reset),
+            TapState::RunTestIdle if from == TapState::TestLogicReset => Some(Step::Idle),
+            TapState::UpdateIr | TapState::UpdateDr => Some(Step::Scan {
+                ir: to == TapState::UpdateIr,
+                tdi: core::mem::take(&mut self.tdi),
+                tdo: core::mem::take(&mut self.tdo),
+            }),
+            _ => None,
+        }
+    }
+
+    /// the cycles held in RUN_TEST/IDLE since the last call
+    pub(crate) fn take_idle(&mut self) -> u32 {
+        core::mem::take(&mut self.idle)
+    }
+}
+
+/// Wraps a phy, writing every cycle driven through it to `out` as SVF
+pub struct SvfWriter<P: JtagPhy, W: fmt::Write> {
+    inner: P,
+    out: W,
+    tracker: ScanTracker,
+    started: bool,
+    /// set once `out` fails; every cycle after that fails too
+    failed: bool,
+}
+
+impl<P: JtagPhy, W: fmt::Write> SvfWriter<P, W> {
+    /// starts a file on `out`; the TAP is taken to be in TEST_LOGIC_RESET, as the JtagMach
+    /// takes it to be, and the file puts the player's TAP there first
+    pub fn new(inner: P, out: W) -> Self {
+        SvfWriter { inner, out, tracker: ScanTracker::new(), started: false, failed: false }
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// Writes out any idle cycles still held back and hands back the phy and the writer.
+    /// Fails if any write to `out` did.
+    pub fn finish(mut self) -> Result<(P, W), fmt::Error> {
+        let flushed: fmt::Result = self.flush_idle();
+        if self.failed || flushed.is_err() {
+            return Err(fmt::Error);
+        }
+        Ok((self.inner, self.out))
+    }
+
+    fn header(&mut self) -> fmt::Result {
+        if !self.started {
+            self.started = true;
+            writeln!(self.out, "! efuse-api JTAG vectors")?;
+            writeln!(self.out, "TRST ABSENT;")?;
+            writeln!(self.out, "ENDIR IDLE;")?;
+            writeln!(self.out, "ENDDR IDLE;")?;
+            writeln!(self.out, "HIR 0;")?;
+            writeln!(self.out, "TIR 0;")?;
+            writeln!(self.out, "HDR 0;")?;
+            writeln!(self.out, "TDR 0;")?;
+            writeln!(self.out, "STATE RESET;")?;
+        }
+        Ok(())
+    }
+
+    fn flush_idle(&mut self) -> fmt::Result {
+        let idle: u32 = self.tracker.take_idle();
+        if idle != 0 {
+            self.header()?;
+            writeln!(self.out, "RUNTEST IDLE {} TCK;", idle)?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, step: Step) -> fmt::Result {
+        self.flush_idle()?;
+        self.header()?;
+        match step {
+            Step::Reset => writeln!(self.out, "STATE RESET;"),
+            Step::Idle => writeln!(self.out, "STATE IDLE;"),
+            Step::Scan { ir, tdi, .. } => {
+                write!(self.out, "{} {} TDI (", if ir { "SIR" } else { "SDR" }, tdi.len())?;
+                if tdi.is_empty() {
+                    write!(self.out, "0")?;
+                }
+                // most significant digit first
+                for digit in (0..tdi.len().div_ceil(4)).rev() {
+                    let nibble: u32 = (0..4).filter(|&b| tdi.get(digit * 4 + b) == Some(&true)).map(|b| 1 << b).sum();
+                    write!(self.out, "{:X}", nibble)?;
+                }
+                writeln!(self.out, ");")
+            },
+        }
+    }
+
+    /// the SVF name of a stable state, where a RUNTEST can wait
+    fn stable(state: TapState) -> Option<&'static str> {
+        match state {
+            TapState::TestLogicReset => Some("RESET"),
+            TapState::RunTestIdle => Some("IDLE"),
+            TapState::PauseDr => Some("DRPAUSE"),
+            TapState::PauseIr => Some("IRPAUSE"),
+            _ => None,
+        }
+    }
+}
+
+impl<P: JtagPhy, W: fmt::Write> JtagPhy for SvfWriter<P, W> {
+    fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+        self.try_sync(tdi, tms).unwrap_or(false)
+    }
+
+    /// drives the pins directly, unrecorded; the JtagMach never does
+    fn nosync(&mut self, tdi: bool, tms: bool, tck: bool) -> bool {
+        self.inner.nosync(tdi, tms, tck)
+    }
+
+    /// A pause is a RUNTEST in seconds, in whichever stable state the TAP is in. The SVF has no
+    /// way to wait anywhere else, so a pause mid-scan fails the next cycle.
+    fn pause(&mut self, us: u32) {
+        let written: fmt::Result = match Self::stable(self.tracker.tap) {
+            Some(state) => self.flush_idle()
+                .and_then(|_| self.header())
+                .and_then(|_| writeln!(self.out, "RUNTEST {} {}.{:06} SEC;", state, us / 1_000_000, us % 1_000_000)),
+            None => Err(fmt::Error),
+        };
+        if written.is_err() {
+            self.failed = true;
+        }
+        self.inner.pause(us);
+    }
+
+    fn try_sync(&mut self, tdi: bool, tms: bool) -> Result<bool, PhyError> {
+        if self.failed {
+            return Err(PhyError::Transport);
+        }
+        let tdo: bool = self.inner.try_sync(tdi, tms)?;
+        if let Some(step) = self.tracker.clock(tdi, tms, tdo) {
+            if self.write(step).is_err() {
+                self.failed = true;
+                return Err(PhyError::Transport);
+            }
+        }
+        Ok(tdo)
+    }
+}
@@ -0,0 +1,865 @@
+//! Phys for exercising the API without hardware
+//!
+//! These exist for the host-side tests; nothing on the firmware path uses them. They follow the
+//! TAP from the TMS stream the same way a device would, so the JtagMach and the sequences in this
+//! crate are driven exactly as they would be on the bench.
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use jtag::*;
+
+use crate::keysource::{REG_CTL0, REG_STAT, STAT_PART_SECURED};
+use crate::layout::*;
+use crate::sequences::*;
+use crate::status::FuseStatus;
+use crate::transport::*;
+use crate::xadc;
+
+/// Value captured into the IR on Capture-IR; 1149.1 mandates 01 in the two LSBs
+pub const IR_CAPTURE: u32 = 0b000001;
+
+/// What happened on a TCK edge, as far as the phys below care
+enum TapEvent {
+    None,
+    Reset,
+    CaptureDr,
+    /// a DR scan completed with these bits, in shift order
+    UpdateDr(Vec<bool>),
+    /// an instruction was latched
+    UpdateIr(u32),
+}
+
+/// Follows the TAP state and the IR from the TMS/TDI stream
+struct TapTracker {
+    tap: TapState,
+    ir: u32,
+    ir_in: Vec<bool>,
+    dr_in: Vec<bool>,
+    cycles: usize,
+}
+
+impl TapTracker {
+    fn new() -> Self {
+        TapTracker {
+            tap: TapState::TestLogicReset,
+            ir: Ir::Idcode.code(),
+            ir_in: Vec::new(),
+            dr_in: Vec::new(),
+            cycles: 0,
+        }
+    }
+
+    /// Bit shifted out on TDO this cycle; `dr_bit` gives bit n of the captured DR. Past the
+    /// captured bits, the IR and the one-bit BYPASS register pass TDI through, as they do for
+    /// devices further down a chain.
+    fn tdo<F: Fn(usize) -> bool>(&self, dr_bit: F) -> bool {
+        match self.tap {
+            TapState::ShiftDr if self.ir == Ir::Bypass.code() => {
+                let pos: usize = self.dr_in.len();
+                pos > 0 && self.dr_in[pos - 1]
+            },
+            TapState::ShiftDr => dr_bit(self.dr_in.len()),
+            TapState::ShiftIr => {
+                let pos: usize = self.ir_in.len();
+                if pos < IR_BITS {
+                    (IR_CAPTURE >> pos) & 0x1 == 1
+                } else {
+                    self.ir_in[pos - IR_BITS]
+                }
+            },
+            _ => false,
+        }
+    }
+
+    /// clock the TAP once
+    fn clock(&mut self, tdi: bool, tms: bool) -> TapEvent {
+        self.cycles += 1;
+        match self.tap {
+            TapState::ShiftDr => self.dr_in.push(tdi),
+            TapState::ShiftIr => self.ir_in.push(tdi),
+            _ => {},
+        }
+
+        self.tap = self.tap.next(tms);
+        match self.tap {
+            TapState::TestLogicReset => {
+                self.ir = Ir::Idcode.code();
+                TapEvent::Reset
+            },
+            TapState::CaptureDr => {
+                self.dr_in.clear();
+                TapEvent::CaptureDr
+            },
+            TapState::CaptureIr => {
+                self.ir_in.clear();
+                TapEvent::None
+            },
+            TapState::UpdateDr => TapEvent::UpdateDr(core::mem::take(&mut self.dr_in)),
+            TapState::UpdateIr => {
+                // the IR keeps the last IR_BITS bits shifted in; the earliest of those is the LSB
+                let start: usize = self.ir_in.len().saturating_sub(IR_BITS);
+                self.ir = bits_value(&self.ir_in[start..]) as u32;
+                TapEvent::UpdateIr(self.ir)
+            },
+            _ => TapEvent::None,
+        }
+    }
+}
+
+/// the bottom 128 bits of a bit vector, first bit as the LSB
+fn bits_value(bits: &[bool]) -> u128 {
+    bits.iter().take(128).enumerate()
+        .fold(0, |acc, (i, &bit)| if bit { acc | (1 << i) } else { acc })
+}
+
+/// bit `n` of an LSB-first byte string, 0 past `len` bits
+fn byte_bit(bytes: &[u8], len: usize, n: usize) -> bool {
+    n < len && (bytes[n / 8] >> (n % 8)) & 0x1 == 1
+}
+
+/// A DR scan recorded by ScriptedPhy
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DrWrite {
+    /// instruction that was active during the scan
+    pub ir: u32,
+    /// bits shifted in, in shift order (first shifted is bit 0)
+    pub bits: Vec<bool>,
+}
+
+impl DrWrite {
+    /// the instruction that was active, if it is a documented one
+    pub fn instruction(&self) -> Option<Ir> {
+        Ir::from_code(self.ir)
+    }
+
+    pub fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.is_empty()
+    }
+
+    /// the bottom 128 bits of the scan as a number
+    pub fn value(&self) -> u128 {
+        bits_value(&self.bits)
+    }
+}
+
+/// DR responses registered for one instruction
+struct Script {
+    ir: u32,
+    bits: usize,
+    /// each response is stored LSB-first, one byte per 8 bits
+    responses: Vec<Vec<u8>>,
+    /// number of times the DR has been captured under this instruction
+    reads: usize,
+}
+
+/// A phy that plays back per-instruction DR responses and records everything shifted into the DR.
+///
+/// It tracks the TAP state and the latched instruction from the TMS/TDI stream. On Capture-DR
+/// the response registered for the current instruction is loaded and shifted out LSB first;
+/// scans under instructions without a response read back as zeros. Every DR scan is recorded,
+/// reads included, and can be looked up by the instruction that was active at the time.
+///
+/// Configuration registers can be given values too: a type-1 read shifted under CFG_IN picks
+/// the register the next CFG_OUT scan returns, MSB first.
+pub struct ScriptedPhy {
+    t: TapTracker,
+    dr_out: Vec<u8>,
+    dr_out_bits: usize,
+    scripts: Vec<Script>,
+    registers: Vec<(u8, u32)>,
+    /// register named by the last type-1 read
+    register: Option<u8>,
+    writes: Vec<DrWrite>,
+    irs: Vec<u32>,
+    elapsed_us: u64,
+}
+
+impl ScriptedPhy {
+    pub fn new() -> Self {
+        ScriptedPhy {
+            t: TapTracker::new(),
+            dr_out: Vec::new(),
+            dr_out_bits: 0,
+            scripts: Vec::new(),
+            registers: Vec::new(),
+            register: None,
+            writes: Vec::new(),
+            irs: Vec::new(),
+            elapsed_us: 0,
+        }
+    }
+
+    /// A device running a secured bitstream that keeps its fuses from being read: every fuse
+    /// readback is zero, STAT has PART_SECURED set and CTL0 reads `ctl0`.
+    pub fn secured_device(ctl0: u32) -> Self {
+        let mut jp = ScriptedPhy::new();
+        jp.on_config_register(REG_STAT, STAT_PART_SECURED);
+        jp.on_config_register(REG_CTL0, ctl0);
+        jp
+    }
+
+    /// return `value` whenever configuration register `reg` is read
+    pub fn on_config_register(&mut self, reg: u8, value: u32) {
+        self.registers.retain(|&(r, _)| r != reg);
+        self.registers.push((reg, value));
+    }
+
+    /// respond to every DR capture under `ir` with the bottom `bits` of `value`
+    pub fn on_dr(&mut self, ir: Ir, bits: usize, value: u128) {
+        self.on_dr_seq(ir, bits, &[value]);
+    }
+
+    /// respond to successive DR captures under `ir` with successive `values`; once the
+    /// sequence runs out, the last value keeps being returned
+    pub fn on_dr_seq(&mut self, ir: Ir, bits: usize, values: &[u128]) {
+        assert!(bits <= 128);
+        let responses: Vec<Vec<u8>> = values.iter()
+            .map(|v| v.to_le_bytes()[..bits.div_ceil(8)].to_vec())
+            .collect();
+        self.script(ir, bits, responses);
+    }
+
+    /// respond to every DR capture under `ir` with `bits` bits taken LSB-first from `bytes`, for
+    /// registers wider than 128 bits
+    pub fn on_dr_bytes(&mut self, ir: Ir, bits: usize, bytes: &[u8]) {
+        assert!(bytes.len() * 8 >= bits);
+        self.script(ir, bits, alloc::vec![bytes.to_vec()]);
+    }
+
+    fn script(&mut self, ir: Ir, bits: usize, responses: Vec<Vec<u8>>) {
+        assert!(!responses.is_empty());
+        self.scripts.retain(|s| s.ir != ir.code());
+        self.scripts.push(Script { ir: ir.code(), bits, responses, reads: 0 });
+    }
+
+    /// current TAP state
+    pub fn tap(&self) -> TapState {
+        self.t.tap
+    }
+
+    /// currently latched instruction, if it is a documented one
+    pub fn ir(&self) -> Option<Ir> {
+        Ir::from_code(self.t.ir)
+    }
+
+    /// every instruction latched on Update-IR, in order
+    pub fn ir_history(&self) -> &[u32] {
+        &self.irs
+    }
+
+    /// every DR scan, in order
+    pub fn dr_writes_all(&self) -> &[DrWrite] {
+        &self.writes
+    }
+
+    /// the DR scans made while `ir` was active, in order
+    pub fn dr_writes(&self, ir: Ir) -> impl Iterator<Item = &DrWrite> {
+        self.writes.iter().filter(move |w| w.ir == ir.code())
+    }
+
+    /// number of DR captures made under `ir`
+    pub fn reads(&self, ir: Ir) -> usize {
+        self.scripts.iter().find(|s| s.ir == ir.code()).map_or_else(
+            || self.dr_writes(ir).count(),
+            |s| s.reads)
+    }
+
+    /// number of TCK cycles driven so far
+    pub fn cycles(&self) -> usize {
+        self.t.cycles
+    }
+
+    /// total time spent in pause(), in microseconds
+    pub fn elapsed_us(&self) -> u64 {
+        self.elapsed_us
+    }
+
+    fn capture_dr(&mut self) {
+        self.dr_out.clear();
+        self.dr_out_bits = 0;
+        let ir: u32 = self.t.ir;
+        let register: Option<u32> = self.register
+            .filter(|_| ir == Ir::CfgOut.code())
+            .and_then(|reg| self.registers.iter().find(|&&(r, _)| r == reg))
+            .map(|&(_, value)| value);
+        if let Some(value) = register {
+            // shifted out MSB first
+            self.dr_out.extend_from_slice(&value.reverse_bits().to_le_bytes());
+            self.dr_out_bits = 32;
+        } else if let Some(script) = self.scripts.iter_mut().find(|s| s.ir == ir) {
+            let index: usize = script.reads.min(script.responses.len() - 1);
+            self.dr_out.extend_from_slice(&script.responses[index]);
+            self.dr_out_bits = script.bits;
+            script.reads += 1;
+        }
+    }
+}
+
+impl Default for ScriptedPhy {
+    fn default() -> Self {
+        ScriptedPhy::new()
+    }
+}
+
+impl JtagPhy for ScriptedPhy {
+    fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+        let tdo: bool = self.t.tdo(|n| byte_bit(&self.dr_out, self.dr_out_bits, n));
+        match self.t.clock(tdi, tms) {
+            TapEvent::CaptureDr => self.capture_dr(),
+            TapEvent::UpdateDr(bits) => {
+                if self.t.ir == Ir::CfgIn.code() {
+                    // packets go in MSB first, a word at a time
+                    for chunk in bits.chunks(32) {
+                        let word: u32 = chunk.iter().fold(0, |acc, &b| (acc << 1) | b as u32);
+                        if word & !(0x1F << 13) == 0x2800_0001 {
+                            self.register = Some(((word >> 13) & 0x1F) as u8);
+                        }
+                    }
+                }
+                self.writes.push(DrWrite { ir: self.t.ir, bits });
+            },
+            TapEvent::UpdateIr(ir) => self.irs.push(ir),
+            TapEvent::Reset | TapEvent::None => {},
+        }
+        tdo
+    }
+
+    fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool {
+        unimplemented!();
+    }
+
+    fn pause(&mut self, us: u32) {
+        self.elapsed_us += us as u64;
+    }
+}
+
+/// DR word shifted under FUSE_CTS to commit the programmed fuses
+pub const COMMIT_WORD: u64 = 0xff_0000_00ff;
+
+/// XADC status register values for 25 C, 1.0 V and 1.8 V
+pub const NOMINAL_TEMP: u16 = 2423 << 4;
+pub const NOMINAL_VCCINT: u16 = 1365 << 4;
+pub const NOMINAL_VCCAUX: u16 = 2458 << 4;
+
+/// A key for the tests, with every byte distinct; different seeds give different keys
+pub fn test_key(seed: u8) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    for (i, k) in key.iter_mut().enumerate() {
+        *k = (i as u8).wrapping_mul(71) ^ seed;
+    }
+    key
+}
+
+/// Behavioral model of the 7-series eFUSE array, driven purely by JTAG traffic.
+///
+/// The model keeps the 13 physical banks and serves the FUSE_KEY, FUSE_USER and FUSE_CNTL
+/// readbacks from them. It decodes the programming words shifted under FUSE_CTS: after JSTART
+/// the port has to be unlocked twice, then a bank selected, after which each bit-program word
+/// blows one fuse of the selected bank. Like real fuses, bits only ever go from 0 to 1.
+/// Programming words that arrive out of sequence are counted and otherwise ignored.
+///
+/// The two copies of the CNTL bits are separate fuses: FUSE_CNTL reads out the primary copy in
+/// bits 5:0 and the redundant copy in bits 19:14, and stick() can make either one fail.
+///
+/// Unless enforce_read_disable() is called, the readbacks ignore R_EN_B_KEY and R_EN_B_USER.
+/// USERCODE reads the USER fuses too, whatever CNTL says, unless set_usercode() overrides it.
+///
+/// The programming port's status is clean unless report_status() or status_after_commit()
+/// say otherwise. Captures under FUSE_CTS while a bank is selected read that bank's status;
+/// with none selected, as after a JSTART or the commit, they read the error indications
+/// latched since the last TAP reset.
+///
+/// XADC_DRP answers reads of the XADC's temperature, VCCINT and VCCAUX registers, one scan
+/// late as the real port does; they read nominal values unless set_xadc() says otherwise.
+pub struct EfuseModelPhy {
+    t: TapTracker,
+    params: DeviceParams,
+    banks: [u32; FUSE_BANKS],
+    stuck: [u32; FUSE_BANKS],
+    collateral: [u32; FUSE_BANKS],
+    read_disable: bool,
+    bank_status: [u32; FUSE_BANKS],
+    commit_status: u32,
+    latched_status: u32,
+    dna: u64,
+    idcode: u32,
+    usercode: Option<u32>,
+    xadc: [u16; 3],
+    drp_out: u32,
+    dr_out: [u8; 32],
+    dr_out_bits: usize,
+    unlocks: usize,
+    selected: Option<usize>,
+    programmed: Vec<(usize, u8)>,
+    rejected: usize,
+    commits: usize,
+    irs: Vec<u32>,
+    elapsed_us: u64,
+}
+
+impl EfuseModelPhy {
+    /// a factory-fresh device: every fuse is 0
+    pub fn new() -> Self {
+        EfuseModelPhy::with_banks([0; FUSE_BANKS])
+    }
+
+    /// a device whose fuses are already programmed to `banks`
+    pub fn with_banks(banks: [u32; FUSE_BANKS]) -> Self {
+        EfuseModelPhy {
+            t: TapTracker::new(),
+            params: DeviceParams::SEVEN_SERIES,
+            banks,
+            stuck: [0; FUSE_BANKS],
+            collateral: [0; FUSE_BANKS],
+            read_disable: false,
+            bank_status: [0; FUSE_BANKS],
+            commit_status: 0,
+            latched_status: 0,
+            dna: 0,
+            idcode: 0,
+            usercode: None,
+            xadc: [NOMINAL_TEMP, NOMINAL_VCCINT, NOMINAL_VCCAUX],
+            drp_out: 0,
+            dr_out: [0; 32],
+            dr_out_bits: 0,
+            unlocks: 0,
+            selected: None,
+            programmed: Vec::new(),
+            rejected: 0,
+            commits: 0,
+            irs: Vec::new(),
+            elapsed_us: 0,
+        }
+    }
+
+    /// current physical fuse contents
+    pub fn banks(&self) -> [u32; FUSE_BANKS] {
+        self.banks
+    }
+
+    /// the device DNA served through FUSE_DNA; 0 unless set
+    pub fn set_dna(&mut self, dna: u64) {
+        self.dna = dna;
+    }
+
+    /// the IDCODE served through IDCODE; 0 unless set
+    pub fn set_idcode(&mut self, idcode: u32) {
+        self.idcode = idcode;
+    }
+
+    /// the value served through USERCODE in place of the USER fuses, as a path that reads
+    /// them wrong would
+    pub fn set_usercode(&mut self, usercode: u32) {
+        self.usercode = Some(usercode);
+    }
+
+    /// the raw value XADC status register `reg` (temperature, VCCINT or VCCAUX) reads as; they
+    /// start out at 25 C, 1.0 V and 1.8 V
+    pub fn set_xadc(&mut self, reg: u16, raw: u16) {
+        self.xadc[reg as usize] = raw;
+    }
+
+    /// make the fuses in `fuses` of `bank` fail to blow: programming them is accepted and
+    /// recorded, but they stay 0
+    pub fn stick(&mut self, bank: usize, fuses: u32) {
+        self.stuck[bank] |= fuses;
+    }
+
+    /// make programming any fuse of `bank` also blow `fuses` of it, as a pulse that reaches
+    /// more than the addressed fuse would
+    pub fn collateral(&mut self, bank: usize, fuses: u32) {
+        self.collateral[bank] |= fuses;
+    }
+
+    /// make FUSE_KEY read zero once R_EN_B_KEY is blown, and FUSE_USER once R_EN_B_USER is,
+    /// as a locked-down part does
+    pub fn enforce_read_disable(&mut self) {
+        self.read_disable = true;
+    }
+
+    /// true if the readback of the fuses `cntl_bit` disables is blocked
+    fn read_disabled(&self, cntl_bit: u8) -> bool {
+        self.read_disable && self.banks[CNTL_BANK] & cntl_bit as u32 != 0
+    }
+
+    /// make the programming port read `raw` as its status while `bank` is selected; an error
+    /// in it is latched when it's read
+    pub fn report_status(&mut self, bank: usize, raw: u32) {
+        self.bank_status[bank] = raw;
+    }
+
+    /// make every commit latch `raw` into the port's status
+    pub fn status_after_commit(&mut self, raw: u32) {
+        self.commit_status = raw;
+    }
+
+    /// every (bank, bit) programmed so far, in order; includes bits that were already blown
+    pub fn programmed(&self) -> &[(usize, u8)] {
+        &self.programmed
+    }
+
+    /// number of programming words that arrived without the port being unlocked and a bank
+    /// selected, or that didn't decode
+    pub fn rejected(&self) -> usize {
+        self.rejected
+    }
+
+    /// number of times the commit word was shifted in
+    pub fn commits(&self) -> usize {
+        self.commits
+    }
+
+    /// every instruction latched on Update-IR, in order
+    pub fn ir_history(&self) -> &[u32] {
+        &self.irs
+    }
+
+    /// current TAP state
+    pub fn tap(&self) -> TapState {
+        self.t.tap
+    }
+
+    /// number of TCK cycles driven so far
+    pub fn cycles(&self) -> usize {
+        self.t.cycles
+    }
+
+    /// total time spent in pause(), in microseconds
+    pub fn elapsed_us(&self) -> u64 {
+        self.elapsed_us
+    }
+
+    fn capture_dr(&mut self) {
+        self.dr_out = [0; 32];
+        self.dr_out_bits = 0;
+        match Ir::from_code(self.t.ir) {
+            Some(Ir::FuseKey) => {
+                // the key register reads out key byte 0 first
+                if !self.read_disabled(CNTL_R_EN_B_KEY) {
+                    for (i, byte) in self.dr_out.iter_mut().enumerate() {
+                        *byte = (self.banks[i / 3 + 1] >> ((i % 3) * 8)) as u8;
+                    }
+                }
+                self.dr_out_bits = 256;
+            },
+            Some(Ir::FuseUser) => {
+                let user: u32 = ((self.banks[SHARED_BANK] >> 16) & 0xFF) | ((self.banks[USER_BANK] & 0xFF_FFFF) << 8);
+                let user: u32 = if self.read_disabled(CNTL_R_EN_B_USER) { 0 } else { user };
+                self.dr_out[..4].copy_from_slice(&user.to_le_bytes());
+                self.dr_out_bits = 32;
+            },
+            Some(Ir::Usercode) => {
+                let user: u32 = ((self.banks[SHARED_BANK] >> 16) & 0xFF) | ((self.banks[USER_BANK] & 0xFF_FFFF) << 8);
+                self.dr_out[..4].copy_from_slice(&self.usercode.unwrap_or(user).to_le_bytes());
+                self.dr_out_bits = 32;
+            },
+            Some(Ir::FuseDna) => {
+                self.dr_out[..8].copy_from_slice(&self.dna.to_le_bytes());
+                self.dr_out_bits = 64;
+            },
+            Some(Ir::Idcode) => {
+                self.dr_out[..4].copy_from_slice(&self.idcode.to_le_bytes());
+                self.dr_out_bits = 32;
+            },
+            Some(Ir::FuseCntl) => {
+                let cntl: u32 = self.banks[CNTL_BANK] & bank_fuses(CNTL_BANK);
+                self.dr_out[..4].copy_from_slice(&cntl.to_le_bytes());
+                self.dr_out_bits = self.params.cntl_readback_bits;
+            },
+            Some(Ir::XadcDrp) => {
+                self.dr_out[..4].copy_from_slice(&self.drp_out.to_le_bytes());
+                self.dr_out_bits = 32;
+            },
+            Some(Ir::FuseCts) => {
+                let status: u32 = match self.selected {
+                    Some(bank) => self.bank_status[bank],
+                    None => self.latched_status,
+                };
+                if status & FuseStatus::PROGRAM_ERROR != 0 {
+                    self.latched_status |= status;
+                }
+                self.dr_out[..4].copy_from_slice(&status.to_le_bytes());
+                self.dr_out_bits = 32;
+            },
+            _ => {},
+        }
+    }
+
+    /// a DRP read latches the register's value, for the next scan to carry out
+    fn drp_word(&mut self, value: u32) {
+        let address: usize = ((value >> 16) & 0x3FF) as usize;
+        self.drp_out = match (value >> 26) & 0xF {
+            xadc::DRP_READ if address < self.xadc.len() => self.xadc[address] as u32,
+            _ => 0,
+        };
+    }
+
+    /// decode the bank a bank-select code refers to
+    fn select_code_bank(&self, code: u8) -> Option<usize> {
+        if code == self.params.cntl_bank_select {
+            return Some(CNTL_BANK);
+        }
+        let offset: u8 = code.checked_sub(self.params.bank_select_base)?;
+        if !offset.is_multiple_of(self.params.bank_select_stride) {
+            return None;
+        }
+        let bank: usize = (offset / self.params.bank_select_stride) as usize + 1;
+        if bank < FUSE_BANKS { Some(bank) } else { None }
+    }
+
+    fn program_word(&mut self, value: u64) {
+        let header_mask: u64 = !0xFFFF;
+        if value == self.params.unlock {
+            self.unlocks += 1;
+        } else if value == 0 {
+            // wait word
+        } else if value == COMMIT_WORD {
+            self.commits += 1;
+            self.latched_status |= self.commit_status;
+            self.selected = None;
+        } else if value & header_mask != self.params.dr_header {
+            self.rejected += 1;
+        } else if value & self.params.program_flag != 0 {
+            let word_select: u8 = value as u8;
+            let bit: u8 = ((value >> self.params.bit_shift) & 0x1F) as u8;
+            match self.selected {
+                Some(bank) if self.unlocks >= 2 && self.params.word_select(bank) == word_select => {
+                    self.banks[bank] |= ((1 << bit) | self.collateral[bank]) & !self.stuck[bank];
+                    self.programmed.push((bank, bit));
+                },
+                _ => self.rejected += 1,
+            }
+        } else if self.unlocks >= 2 {
+            self.selected = self.select_code_bank(value as u8);
+            if self.selected.is_none() {
+                self.rejected += 1;
+            }
+        } else {
+            self.rejected += 1;
+        }
+    }
+}
+
+impl Default for EfuseModelPhy {
+    fn default() -> Self {
+        EfuseModelPhy::new()
+    }
+}
+
+impl JtagPhy for EfuseModelPhy {
+    fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+        let tdo: bool = self.t.tdo(|n| byte_bit(&self.dr_out, self.dr_out_bits, n));
+        match self.t.clock(tdi, tms) {
+            TapEvent::Reset => {
+                self.unlocks = 0;
+                self.selected = None;
+                self.latched_status = 0;
+            },
+            TapEvent::CaptureDr => self.capture_dr(),
+            TapEvent::UpdateIr(ir) => {
+                self.irs.push(ir);
+                if ir == Ir::Jstart.code() {
+                    // JSTART opens a new programming bracket
+                    self.unlocks = 0;
+                    self.selected = None;
+                }
+            },
+            TapEvent::UpdateDr(bits) => {
+                // like the IR, the DR keeps the last bits shifted in
+                if self.t.ir == Ir::FuseCts.code() && bits.len() >= self.params.dr_bits {
+                    self.program_word(bits_value(&bits[bits.len() - self.params.dr_bits..]) as u64);
+                }
+                if self.t.ir == Ir::XadcDrp.code() && bits.len() >= 32 {
+                    self.drp_word(bits_value(&bits[bits.len() - 32..]) as u32);
+                }
+            },
+            TapEvent::None => {},
+        }
+        tdo
+    }
+
+    fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool {
+        unimplemented!();
+    }
+
+    fn pause(&mut self, us: u32) {
+        self.elapsed_us += us as u64;
+    }
+}
+
+/// Several devices on one scan chain. TDI goes into `devices[0]`, whose TDO feeds the next
+/// device, and so on; TDO comes out of the last one. TMS and the pauses go to every device.
+pub struct ChainPhy<T: JtagPhy> {
+    devices: Vec<T>,
+}
+
+impl<T: JtagPhy> ChainPhy<T> {
+    pub fn new(devices: Vec<T>) -> Self {
+        assert!(!devices.is_empty());
+        ChainPhy { devices }
+    }
+
+    pub fn devices(&self) -> &[T] {
+        &self.devices
+    }
+
+    pub fn device_mut(&mut self, index: usize) -> &mut T {
+        &mut self.devices[index]
+    }
+}
+
+impl<T: JtagPhy> JtagPhy for ChainPhy<T> {
+    fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+        self.devices.iter_mut().fold(tdi, |bit, device| device.sync(bit, tms))
+    }
+
+    fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool {
+        unimplemented!();
+    }
+
+    fn pause(&mut self, us: u32) {
+        for device in self.devices.iter_mut() {
+            device.pause(us);
+        }
+    }
+}
+
+/// A phy whose cable drops partway through a readback: once `after` bits of a DR scan under
+/// `ir` have been shifted, every cycle fails with PhyError::Transport until reconnect()
+pub struct CutoffPhy<T: JtagPhy> {
+    t: TapTracker,
+    phy: T,
+    ir: Ir,
+    after: usize,
+    armed: bool,
+    dropped: bool,
+}
+
+impl<T: JtagPhy> CutoffPhy<T> {
+    pub fn new(phy: T, ir: Ir, after: usize) -> Self {
+        CutoffPhy { t: TapTracker::new(), phy, ir, after, armed: true, dropped: false }
+    }
+
+    /// true once the cable has dropped, until reconnect()
+    pub fn dropped(&self) -> bool {
+        self.dropped
+    }
+
+    /// plug the cable back in; it stays in
+    pub fn reconnect(&mut self) {
+        self.armed = false;
+        self.dropped = false;
+    }
+
+    pub fn phy(&self) -> &T {
+        &self.phy
+    }
+}
+
+impl<T: JtagPhy> JtagPhy for CutoffPhy<T> {
+    fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+        self.t.clock(tdi, tms);
+        self.phy.sync(tdi, tms)
+    }
+
+    fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool {
+        unimplemented!();
+    }
+
+    fn pause(&mut self, us: u32) {
+        self.phy.pause(us);
+    }
+
+    fn try_sync(&mut self, tdi: bool, tms: bool) -> Result<bool, PhyError> {
+        let cut: bool = self.t.tap == TapState::ShiftDr && self.t.ir == self.ir.code() && self.t.dr_in.len() >= self.after;
+        if self.dropped || (self.armed && cut) {
+            self.dropped = true;
+            return Err(PhyError::Transport);
+        }
+        self.t.clock(tdi, tms);
+        self.phy.try_sync(tdi, tms)
+    }
+}
+
+/// An in-memory byte pipe: bytes written to it can be read back in order
+#[derive(Default)]
+pub struct Pipe {
+    bytes: VecDeque<u8>,
+}
+
+impl Pipe {
+    pub fn new() -> Self {
+        Pipe { bytes: VecDeque::new() }
+    }
+
+    /// number of bytes waiting to be read
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// direct access to the buffered bytes, e.g. to corrupt them in flight
+    pub fn bytes_mut(&mut self) -> &mut VecDeque<u8> {
+        &mut self.bytes
+    }
+}
+
+impl ByteSink for Pipe {
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), TransportError> {
+        self.bytes.extend(bytes.iter().copied());
+        Ok(())
+    }
+}
+
+impl ByteSource for Pipe {
+    fn read_byte(&mut self) -> Option<u8> {
+        self.bytes.pop_front()
+    }
+}
+
+/// The critical-section implementation for host builds with the critical-section feature, so
+/// that every test binary links. It masks nothing; it counts critical sections, their nesting,
+/// and their length in whatever CYCLES counts. Firmware brings its own implementation.
+#[cfg(all(feature = "critical-section", not(target_arch = "riscv32")))]
+pub mod mock_cs {
+    use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering::SeqCst};
+
+    /// advanced by the caller, e.g. once per phy cycle
+    pub static CYCLES: AtomicU64 = AtomicU64::new(0);
+    /// how deeply nested the current critical section is, 0 outside one
+    pub static DEPTH: AtomicUsize = AtomicUsize::new(0);
+    pub static MAX_DEPTH: AtomicUsize = AtomicUsize::new(0);
+    /// outermost critical sections entered
+    pub static SECTIONS: AtomicUsize = AtomicUsize::new(0);
+    /// CYCLES when the current outermost critical section was entered
+    pub static START: AtomicU64 = AtomicU64::new(0);
+    /// the longest outermost critical section, in CYCLES
+    pub static LONGEST: AtomicU64 = AtomicU64::new(0);
+
+    struct MockCs;
+    critical_section::set_impl!(MockCs);
+
+    unsafe impl critical_section::Impl for MockCs {
+        unsafe fn acquire() -> critical_section::RawRestoreState {
+            let depth: usize = DEPTH.fetch_add(1, SeqCst) + 1;
+            MAX_DEPTH.fetch_max(depth, SeqCst);
+            if depth == 1 {
+                SECTIONS.fetch_add(1, SeqCst);
+                START.store(CYCLES.load(SeqCst), SeqCst);
+            }
+        }
+
+        unsafe fn release(_: critical_section::RawRestoreState) {
+            if DEPTH.fetch_sub(1, SeqCst) == 1 {
+                LONGEST.fetch_max(CYCLES.load(SeqCst) - START.load(SeqCst), SeqCst);
+            }
+        }
+    }
+}
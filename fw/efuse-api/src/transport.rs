@@ -0,0 +1,262 @@
+//! Framing of messages over a byte stream
+//!
+//! A frame is a message type byte, the message's wire encoding and a CRC16 over both,
+//! COBS-encoded and terminated with a 0x00 delimiter. COBS guarantees the delimiter never
+//! appears inside a frame, so a receiver that loses sync (or starts listening mid-frame) only
+//! has to wait for the next delimiter to recover.
+//!
+//! Nothing here allocates: frames are built and decoded in fixed-size buffers.
+
+use crate::messages::*;
+
+/// Errors reported while sending or receiving frames
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TransportError {
+    /// the frame is longer than the receive buffer; it was discarded up to its delimiter
+    Oversized,
+    /// the byte stream ended between frames
+    Closed,
+    /// the byte stream ended partway through a frame
+    MissingDelimiter,
+    /// the frame isn't valid COBS
+    Encoding,
+    /// the frame is too short to hold a type and CRC, or its payload has the wrong length
+    Truncated,
+    /// the CRC doesn't match the frame contents
+    Crc,
+    /// the message type isn't one this end knows about
+    UnknownType(u8),
+    /// the underlying sink refused the data
+    Io,
+}
+
+/// Destination of outgoing frames
+pub trait ByteSink {
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), TransportError>;
+}
+
+/// Source of incoming frames
+pub trait ByteSource {
+    /// next byte of the stream, or None if the stream has ended
+    fn read_byte(&mut self) -> Option<u8>;
+}
+
+/// Frame delimiter
+pub const DELIMITER: u8 = 0x00;
+
+const TYPE_SNAPSHOT: u8 = 1;
+const TYPE_MANIFEST: u8 = 2;
+const TYPE_REPORT: u8 = 3;
+const TYPE_ERROR: u8 = 0x7F;
+
+/// A message carried by a frame
+// a report is much the largest, but messages are handled one at a time, and boxing it would
+// cost Copy
+#[allow(clippy::large_enum_variant)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    Snapshot(FuseSnapshot),
+    Manifest(ProvisioningManifest),
+    Report(BurnReport),
+    /// the far end failed to carry out a request; the code is application-defined
+    Error(u16),
+}
+
+/// Longest frame payload
+pub const MAX_PAYLOAD: usize = BurnReport::MAX_WIRE_LEN;
+/// Longest unencoded frame: type, payload, CRC
+pub const MAX_RAW_FRAME: usize = 1 + MAX_PAYLOAD + 2;
+/// Longest encoded frame, excluding the delimiter
+pub const MAX_FRAME: usize = cobs_max_len(MAX_RAW_FRAME);
+
+/// worst-case length of `len` bytes once COBS-encoded
+pub const fn cobs_max_len(len: usize) -> usize {
+    len + len / 254 + 1
+}
+
+/// COBS-encode `src` into `dst`, returning the encoded length. The encoding contains no zero
+/// bytes; the delimiter is not appended.
+pub fn cobs_encode(src: &[u8], dst: &mut [u8]) -> Result<usize, TransportError> {
+    if dst.len() < cobs_max_len(src.len()) {
+        return Err(TransportError::Oversized);
+    }
+    let mut code_at: usize = 0;
+    let mut out: usize = 1;
+    let mut code: u8 = 1;
+    for &byte in src {
+        if byte == 0 {
+            dst[code_at] = code;
+            code_at = out;
+            out += 1;
+            code = 1;
+        } else {
+            dst[out] = byte;
+            out += 1;
+            code += 1;
+            if code == 0xFF {
+                dst[code_at] = code;
+                code_at = out;
+                out += 1;
+                code = 1;
+            }
+        }
+    }
+    dst[code_at] = code;
+    Ok(out)
+}
+
+/// Decode a COBS frame (without its delimiter) in place, returning the decoded length
+pub fn cobs_decode_in_place(buf: &mut [u8]) -> Result<usize, TransportError> {
+    let mut read: usize = 0;
+    let mut write: usize = 0;
+    while read < buf.len() {
+        let code: u8 = buf[read];
+        if code == 0 {
+            return Err(TransportError::Encoding);
+        }
+        read += 1;
+        for _ in 1..code {
+            if read >= buf.len() || buf[read] == 0 {
+                return Err(TransportError::Encoding);
+            }
+            buf[write] = buf[read];
+            write += 1;
+            read += 1;
+        }
+        if code != 0xFF && read < buf.len() {
+            buf[write] = 0;
+            write += 1;
+        }
+    }
+    Ok(write)
+}
+
+/// CRC-16/CCITT-FALSE (polynomial 0x1021, initial value 0xFFFF)
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            if crc & 0x8000 != 0 {
+                crc = (crc << 1) ^ 0x1021;
+            } else {
+                crc <<= 1;
+            }
+        }
+    }
+    crc
+}
+
+/// CRC-32 (IEEE 802.3, reflected polynomial 0xEDB88320)
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xFFFF_FFFF;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            if crc & 0x1 != 0 {
+                crc = (crc >> 1) ^ 0xEDB8_8320;
+            } else {
+                crc >>= 1;
+            }
+        }
+    }
+    !crc
+}
+
+/// Frame a raw `kind`/`payload` pair and write it, delimiter included, to `sink`
+pub fn send_frame<W: ByteSink>(sink: &mut W, kind: u8, payload: &[u8]) -> Result<(), TransportError> {
+    if payload.len() > MAX_PAYLOAD {
+        return Err(TransportError::Oversized);
+    }
+    let mut raw: [u8; MAX_RAW_FRAME] = [0; MAX_RAW_FRAME];
+    let len: usize = 1 + payload.len();
+    raw[0] = kind;
+    raw[1..len].copy_from_slice(payload);
+    let crc: u16 = crc16(&raw[..len]);
+    raw[len..len + 2].copy_from_slice(&crc.to_le_bytes());
+
+    let mut frame: [u8; MAX_FRAME] = [0; MAX_FRAME];
+    let encoded: usize = cobs_encode(&raw[..len + 2], &mut frame)?;
+    sink.write_bytes(&frame[..encoded])?;
+    sink.write_bytes(&[DELIMITER])
+}
+
+/// Frame `msg` and write it, delimiter included, to `sink`
+pub fn send<W: ByteSink>(sink: &mut W, msg: &Message) -> Result<(), TransportError> {
+    let mut payload: [u8; MAX_PAYLOAD] = [0; MAX_PAYLOAD];
+    let (kind, len): (u8, usize) = match msg {
+        Message::Snapshot(s) => (TYPE_SNAPSHOT, s.encode(&mut payload)),
+        Message::Manifest(m) => (TYPE_MANIFEST, m.encode(&mut payload)),
+        Message::Report(r) => (TYPE_REPORT, r.encode(&mut payload)),
+        Message::Error(code) => {
+            payload[..2].copy_from_slice(&code.to_le_bytes());
+            (TYPE_ERROR, 2)
+        },
+    };
+    send_frame(sink, kind, &payload[..len])
+}
+
+/// Read the next frame from `source` into `buf` and check it, returning the frame's kind and
+/// payload. Empty frames (back-to-back delimiters) are skipped. A frame that fails to decode is
+/// consumed up to its delimiter, so the next call starts cleanly on the following frame.
+pub fn receive_frame<'a, R: ByteSource>(source: &mut R, buf: &'a mut [u8; MAX_FRAME]) -> Result<(u8, &'a [u8]), TransportError> {
+    let mut len: usize = 0;
+    let mut oversized: bool = false;
+    loop {
+        match source.read_byte() {
+            None => {
+                if len == 0 && !oversized {
+                    return Err(TransportError::Closed);
+                }
+                return Err(TransportError::MissingDelimiter);
+            },
+            Some(DELIMITER) => {
+                if oversized {
+                    return Err(TransportError::Oversized);
+                }
+                if len > 0 {
+                    break;
+                }
+            },
+            Some(byte) => {
+                if len < buf.len() {
+                    buf[len] = byte;
+                    len += 1;
+                } else {
+                    oversized = true;
+                }
+            },
+        }
+    }
+
+    let len: usize = cobs_decode_in_place(&mut buf[..len])?;
+    if len < 3 {
+        return Err(TransportError::Truncated);
+    }
+    let mut crc: [u8; 2] = [0; 2];
+    crc.copy_from_slice(&buf[len - 2..len]);
+    if crc16(&buf[..len - 2]) != u16::from_le_bytes(crc) {
+        return Err(TransportError::Crc);
+    }
+    Ok((buf[0], &buf[1..len - 2]))
+}
+
+/// Read the next frame from `source` and decode it as a Message (see receive_frame)
+pub fn receive<R: ByteSource>(source: &mut R) -> Result<Message, TransportError> {
+    let mut buf: [u8; MAX_FRAME] = [0; MAX_FRAME];
+    let (kind, payload) = receive_frame(source, &mut buf)?;
+    match kind {
+        TYPE_SNAPSHOT => FuseSnapshot::decode(payload).map(Message::Snapshot),
+        TYPE_MANIFEST => ProvisioningManifest::decode(payload).map(Message::Manifest),
+        TYPE_REPORT => BurnReport::decode(payload).map(Message::Report),
+        TYPE_ERROR => {
+            if payload.len() == 2 {
+                Some(Message::Error(u16::from_le_bytes([payload[0], payload[1]])))
+            } else {
+                None
+            }
+        },
+        other => return Err(TransportError::UnknownType(other)),
+    }.ok_or(TransportError::Truncated)
+}
@@ -0,0 +1,114 @@
+//! Named bit fields of USER
+//!
+//! USER is one 32-bit value to the fuses, but a product usually packs several independent
+//! things into it: a rollback counter, a board revision, provisioning flags. A UserLayout names
+//! them once, as a bit offset and a width each, and EfuseApi::get_field() and set_field() then
+//! read and stage a field by name, leaving the rest of USER alone.
+//!
+//! Registration refuses a field that overlaps one already registered, or that doesn't fit in
+//! USER, so a layout always describes disjoint bit ranges. A value is placed in its field lowest
+//! bit first, at the field's offset.
+//!
+//! USER[7:0] share bank 11 with key bytes 30 and 31, and USER[31:8] are bank 12, so a field
+//! can straddle the two banks; it's staged and burned as one value all the same. As with any
+//! change to USER, changing the bits in bank 11 needs the key to read back.
+
+use alloc::vec::Vec;
+
+use crate::error::EfuseError;
+
+/// Why a UserLayout refused a field, or a field operation failed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum UserFieldError {
+    /// `name` would share bits with the field `with`, already registered
+    Overlap { name: &'static str, with: &'static str },
+    /// a field named `name` is already registered
+    Duplicate { name: &'static str },
+    /// `name` is empty or runs past bit 31
+    OutOfRange { name: &'static str },
+    /// no field of that name is registered
+    Unknown,
+    /// `value` has bits set above the width of `name`
+    TooWide { name: &'static str, value: u32 },
+}
+
+/// USER bits `offset` up to `offset + width`, under a name
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct UserField {
+    pub name: &'static str,
+    /// the field's lowest USER bit
+    pub offset: u32,
+    /// in bits
+    pub width: u32,
+}
+
+impl UserField {
+    /// the USER bits making up the field
+    pub fn mask(&self) -> u32 {
+        let ones: u32 = if self.width == 32 { !0 } else { (1 << self.width) - 1 };
+        ones << self.offset
+    }
+
+    /// the field's value in `user`
+    pub fn extract(&self, user: u32) -> u32 {
+        (user & self.mask()) >> self.offset
+    }
+
+    /// the USER bits holding `value` in the field; fails with TooWide if it doesn't fit
+    pub fn deposit(&self, value: u32) -> Result<u32, UserFieldError> {
+        if value & !(self.mask() >> self.offset) != 0 {
+            return Err(UserFieldError::TooWide { name: self.name, value });
+        }
+        Ok(value << self.offset)
+    }
+}
+
+/// The named fields packed into USER; see the module docs
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct UserLayout {
+    fields: Vec<UserField>,
+}
+
+impl UserLayout {
+    pub fn new() -> Self {
+        UserLayout { fields: Vec::new() }
+    }
+
+    /// Registers USER bits `offset` up to `offset + width` as the field `name`. Fails, leaving
+    /// the layout as it was, if the field is empty or runs past bit 31, if the name is taken,
+    /// or if it overlaps a field already registered.
+    pub fn register(&mut self, name: &'static str, offset: u32, width: u32) -> Result<UserField, EfuseError> {
+        let field: UserField = UserField { name, offset, width };
+        if width == 0 || width > 32 || offset > 32 - width {
+            return Err(EfuseError::UserField { err: UserFieldError::OutOfRange { name } });
+        }
+        if self.field(name).is_some() {
+            return Err(EfuseError::UserField { err: UserFieldError::Duplicate { name } });
+        }
+        if let Some(other) = self.fields.iter().find(|other| other.mask() & field.mask() != 0) {
+            return Err(EfuseError::UserField { err: UserFieldError::Overlap { name, with: other.name } });
+        }
+        self.fields.push(field);
+        Ok(field)
+    }
+
+    /// the field registered as `name`
+    pub fn field(&self, name: &str) -> Option<UserField> {
+        self.fields.iter().copied().find(|field| field.name == name)
+    }
+
+    /// every field, in the order they were registered
+    pub fn fields(&self) -> &[UserField] {
+        &self.fields
+    }
+
+    /// the USER bits no field covers
+    pub fn unassigned(&self) -> u32 {
+        !self.fields.iter().fold(0, |mask, field| mask | field.mask())
+    }
+
+    /// the field registered as `name`, as an error if there's none
+    pub(crate) fn lookup(&self, name: &str) -> Result<UserField, EfuseError> {
+        self.field(name).ok_or(EfuseError::UserField { err: UserFieldError::Unknown })
+    }
+}
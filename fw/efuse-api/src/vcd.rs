@@ -0,0 +1,154 @@
+//! Value Change Dump trace of the JTAG pins
+//!
+//! VcdPhy wraps another phy and writes every cycle driven through it as a VCD waveform of
+//! TCK, TMS, TDI and TDO, for viewing in GTKWave or diffing against a logic analyzer capture of
+//! the same burn. It passes everything through, so it can sit in front of a real phy as well
+//! as a model one.
+//!
+//! The trace counts in nanoseconds. Each cycle is two timesteps long: TMS and TDI change with
+//! TCK falling, and TCK rises a timestep later with TDO as the wrapped phy sampled it. TCK
+//! stays high until the next cycle, a pause or finish() lowers it. A pause holds the pins for
+//! its length. Only the pins that change are written, as VCD has it; the timestep, half the
+//! TCK period, is whatever matches the capture being compared against.
+
+use core::fmt;
+use jtag::*;
+
+/// VCD identifiers of the pins, in $var order
+const TCK: char = '!';
+const TMS: char = '"';
+const TDI: char = '#';
+const TDO: char = '$';
+
+/// Wraps a phy, writing every cycle driven through it to `out` as VCD
+pub struct VcdPhy<P: JtagPhy, W: fmt::Write> {
+    inner: P,
+    out: W,
+    /// half a TCK period, in ns
+    timestep_ns: u32,
+    /// ns since the start of the trace
+    time: u64,
+    /// the time of the last timestamp written
+    stamped: u64,
+    /// the pins as last written: tck, tms, tdi, tdo
+    pins: [bool; 4],
+    /// set once `out` fails; every cycle after that fails too
+    failed: bool,
+}
+
+impl<P: JtagPhy, W: fmt::Write> VcdPhy<P, W> {
+    /// starts a trace on `out`, with TCK high for `timestep_ns` and low for as long again; every
+    /// pin starts out low
+    pub fn new(inner: P, out: W, timestep_ns: u32) -> Self {
+        assert!(timestep_ns > 0, "a cycle needs some length");
+        let mut phy = VcdPhy { inner, out, timestep_ns, time: 0, stamped: 0, pins: [false; 4], failed: false };
+        if phy.header().is_err() {
+            phy.failed = true;
+        }
+        phy
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// ns since the start of the trace
+    pub fn time_ns(&self) -> u64 {
+        self.time
+    }
+
+    /// Ends the trace with TCK low and hands back the phy and the writer. Fails if any write
+    /// to `out` did.
+    pub fn finish(mut self) -> Result<(P, W), fmt::Error> {
+        if self.failed || self.tck_low().is_err() {
+            return Err(fmt::Error);
+        }
+        Ok((self.inner, self.out))
+    }
+
+    fn header(&mut self) -> fmt::Result {
+        writeln!(self.out, "$version efuse-api JTAG trace $end")?;
+        writeln!(self.out, "$timescale 1 ns $end")?;
+        writeln!(self.out, "$scope module jtag $end")?;
+        for (id, name) in [(TCK, "tck"), (TMS, "tms"), (TDI, "tdi"), (TDO, "tdo")].iter() {
+            writeln!(self.out, "$var wire 1 {} {} $end", id, name)?;
+        }
+        writeln!(self.out, "$upscope $end")?;
+        writeln!(self.out, "$enddefinitions $end")?;
+        writeln!(self.out, "#0")?;
+        writeln!(self.out, "$dumpvars")?;
+        for id in [TCK, TMS, TDI, TDO].iter() {
+            writeln!(self.out, "0{}", id)?;
+        }
+        writeln!(self.out, "$end")
+    }
+
+    /// writes the pins that differ from the last written, at the current time
+    fn change(&mut self, pins: [bool; 4]) -> fmt::Result {
+        if pins != self.pins {
+            if self.time != self.stamped {
+                writeln!(self.out, "#{}", self.time)?;
+                self.stamped = self.time;
+            }
+            for (n, id) in [TCK, TMS, TDI, TDO].iter().enumerate() {
+                if pins[n] != self.pins[n] {
+                    writeln!(self.out, "{}{}", pins[n] as u8, id)?;
+                }
+            }
+            self.pins = pins;
+        }
+        Ok(())
+    }
+
+    fn tck_low(&mut self) -> fmt::Result {
+        let [_, tms, tdi, tdo] = self.pins;
+        self.change([false, tms, tdi, tdo])
+    }
+
+    /// a full TCK cycle; TCK is left high, to fall with the next cycle's TMS and TDI
+    fn cycle(&mut self, tdi: bool, tms: bool, tdo: bool) -> fmt::Result {
+        let held: bool = self.pins[3];
+        self.change([false, tms, tdi, held])?;
+        self.time += self.timestep_ns as u64;
+        self.change([true, tms, tdi, tdo])?;
+        self.time += self.timestep_ns as u64;
+        Ok(())
+    }
+}
+
+impl<P: JtagPhy, W: fmt::Write> JtagPhy for VcdPhy<P, W> {
+    fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+        self.try_sync(tdi, tms).unwrap_or(false)
+    }
+
+    /// drives the pins directly, recorded as they are set
+    fn nosync(&mut self, tdi: bool, tms: bool, tck: bool) -> bool {
+        let tdo: bool = self.inner.nosync(tdi, tms, tck);
+        if self.change([tck, tms, tdi, tdo]).is_err() {
+            self.failed = true;
+        }
+        self.time += self.timestep_ns as u64;
+        tdo
+    }
+
+    /// TCK goes low, if it isn't, and stays there for the pause
+    fn pause(&mut self, us: u32) {
+        if self.tck_low().is_err() {
+            self.failed = true;
+        }
+        self.time += us as u64 * 1000;
+        self.inner.pause(us);
+    }
+
+    fn try_sync(&mut self, tdi: bool, tms: bool) -> Result<bool, PhyError> {
+        if self.failed {
+            return Err(PhyError::Transport);
+        }
+        let tdo: bool = self.inner.try_sync(tdi, tms)?;
+        if self.cycle(tdi, tms, tdo).is_err() {
+            self.failed = true;
+            return Err(PhyError::Transport);
+        }
+        Ok(tdo)
+    }
+}
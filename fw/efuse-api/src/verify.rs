@@ -0,0 +1,85 @@
+//! Raw post-burn verification
+//!
+//! Comparing the key, USER and CNTL read back after a burn against the staged values can't
+//! tell a clean burn from one that also blew an unrelated fuse the logical decode doesn't
+//! look at, such as a bit of the redundant CNTL copy. Raw verification compares the banks
+//! bit for bit instead: the prediction (the fused state before the burn, plus every fuse the
+//! burn requested) against what the readback instructions return, and names each fuse that
+//! differs.
+//!
+//! The readback instructions see the data bits of every bank and both CNTL copies, but not
+//! the ECC codes in bits 29:24 of the key and USER banks. Those are left out of the comparison;
+//! `observed` says which fuses were compared.
+
+use alloc::vec::Vec;
+
+use crate::layout::*;
+
+/// One fuse, by bank and bit
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FusePosition {
+    pub bank: usize,
+    pub bit: u8,
+}
+
+/// A VerificationOutcome's counts, as the burn report carries them
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct VerifySummary {
+    pub missing: u32,
+    pub stray: u32,
+}
+
+impl VerifySummary {
+    pub fn is_clean(&self) -> bool {
+        self.missing == 0 && self.stray == 0
+    }
+}
+
+/// Outcome of EfuseApi::verify_burn()
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerificationOutcome {
+    /// the banks the last burn should have left behind
+    pub predicted: [u32; FUSE_BANKS],
+    /// the banks as read back, within `observed`
+    pub captured: [u32; FUSE_BANKS],
+    /// the fuses the readback instructions return, per bank
+    pub observed: [u32; FUSE_BANKS],
+    /// fuses that should have blown but read back as 0: a missed or weak programming pulse
+    pub missing: Vec<FusePosition>,
+    /// fuses that read back as 1 but no burn asked for: a mis-addressed pulse, or a device
+    /// that isn't in the state it was fetched in
+    pub stray: Vec<FusePosition>,
+}
+
+impl VerificationOutcome {
+    pub fn new(predicted: [u32; FUSE_BANKS], captured: [u32; FUSE_BANKS], observed: [u32; FUSE_BANKS]) -> Self {
+        let positions = |index: usize, fuses: u32| (0..32u8)
+            .filter(move |&bit| fuses & (1 << bit) != 0)
+            .map(move |bit| FusePosition { bank: index, bit });
+        let mut missing: Vec<FusePosition> = Vec::new();
+        let mut stray: Vec<FusePosition> = Vec::new();
+        for index in 0..FUSE_BANKS {
+            let predicted: u32 = predicted[index] & observed[index];
+            let captured: u32 = captured[index] & observed[index];
+            missing.extend(positions(index, predicted & !captured));
+            stray.extend(positions(index, captured & !predicted));
+        }
+        VerificationOutcome { predicted, captured, observed, missing, stray }
+    }
+
+    /// true if every observed fuse is as predicted
+    pub fn is_clean(&self) -> bool {
+        self.missing.is_empty() && self.stray.is_empty()
+    }
+
+    pub fn summary(&self) -> VerifySummary {
+        VerifySummary { missing: self.missing.len() as u32, stray: self.stray.len() as u32 }
+    }
+
+    /// the stray fuses of the first bank that has any, as a mask
+    pub fn first_stray(&self) -> Option<(usize, u32)> {
+        let bank: usize = self.stray.first()?.bank;
+        let fuses: u32 = self.stray.iter().filter(|p| p.bank == bank).fold(0, |fuses, p| fuses | (1 << p.bit));
+        Some((bank, fuses))
+    }
+}
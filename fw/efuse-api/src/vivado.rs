@@ -0,0 +1,307 @@
+//! Vivado's view of the eFUSE registers, for cross-checking a fetch
+//!
+//! Vivado's hardware manager reports the eFUSE registers as properties of the hw_device, one
+//! per line, e.g. from `report_property [current_hw_device] REGISTER.EFUSE.*`:
+//!
+//! ```text
+//! REGISTER.EFUSE.FUSE_CNTL    string   true       true       0002
+//! REGISTER.EFUSE.FUSE_KEY     string   true       true       3A5F...(64 digits)
+//! REGISTER.EFUSE.FUSE_USER    string   true       true       12345678
+//! ```
+//!
+//! Only the register name and the last field on its line are looked at, so the GUI's register
+//! dump parses too. A register whose readback is disabled is shown with one of the placeholders
+//! in UNREADABLE instead of a hex value; any other non-hex value is an error.
+//!
+//! Going the other way, export_tcl() writes a script that has Vivado program a staged state.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::keycheck::ct_eq_32;
+use crate::keyhex;
+use crate::layout::*;
+use crate::{EfuseApi, EfuseError};
+
+/// Spellings Vivado uses for a register it couldn't read back
+const UNREADABLE: [&str; 4] = ["N/A", "NA", "-", "<unreadable>"];
+
+/// A register value from the report
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Reported<T> {
+    Value(T),
+    /// readback of the register is disabled
+    Unreadable,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Register {
+    Key,
+    User,
+    Cntl,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum VivadoParseError {
+    /// the report doesn't mention `register`
+    Missing { register: Register },
+    /// `register` appears twice, the second time on `line` (counting from 1)
+    Duplicate { register: Register, line: usize },
+    /// the value of `register` on `line` isn't a valid value for it
+    BadValue { register: Register, line: usize },
+}
+
+/// The FUSE_KEY/FUSE_USER/FUSE_CNTL registers as Vivado read them
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct VivadoEfuseReport {
+    pub key: Reported<[u8; 32]>,
+    pub user: Reported<u32>,
+    /// all 14 bits of the register; only CNTL_MASK of them are fuses we program
+    pub cntl: Reported<u16>,
+}
+
+fn register(name: &str) -> Option<Register> {
+    // match whole names, so that e.g. FUSE_USER_128 on UltraScale parts isn't taken for FUSE_USER
+    let name: &str = name.rsplit('.').next().unwrap_or(name);
+    match name {
+        "FUSE_KEY" => Some(Register::Key),
+        "FUSE_USER" => Some(Register::User),
+        "FUSE_CNTL" => Some(Register::Cntl),
+        _ => None,
+    }
+}
+
+fn hex_u32(value: &str, max_digits: usize) -> Option<u32> {
+    let digits: &str = value.trim_start_matches("0x");
+    if digits.is_empty() || digits.len() > max_digits {
+        return None;
+    }
+    u32::from_str_radix(digits, 16).ok()
+}
+
+impl VivadoEfuseReport {
+    pub fn parse(text: &str) -> Result<VivadoEfuseReport, VivadoParseError> {
+        let mut key: Option<Reported<[u8; 32]>> = None;
+        let mut user: Option<Reported<u32>> = None;
+        let mut cntl: Option<Reported<u16>> = None;
+
+        for (index, line) in text.lines().enumerate() {
+            let line_no: usize = index + 1;
+            let mut fields = line.split_whitespace();
+            let reg: Register = match fields.next().and_then(register) {
+                Some(r) => r,
+                None => continue,
+            };
+            let value: &str = fields.last().unwrap_or("");
+            let bad = VivadoParseError::BadValue { register: reg, line: line_no };
+            let unreadable: bool = UNREADABLE.contains(&value);
+            let duplicate: bool = match reg {
+                Register::Key => key.replace(if unreadable {
+                    Reported::Unreadable
+                } else {
+                    Reported::Value(keyhex::parse(value).map_err(|_| bad)?)
+                }).is_some(),
+                Register::User => user.replace(if unreadable {
+                    Reported::Unreadable
+                } else {
+                    Reported::Value(hex_u32(value, 8).ok_or(bad)?)
+                }).is_some(),
+                Register::Cntl => cntl.replace(if unreadable {
+                    Reported::Unreadable
+                } else {
+                    let raw: u32 = hex_u32(value, 4).filter(|&c| c < 1 << CNTL_COPY_SHIFT).ok_or(bad)?;
+                    Reported::Value(raw as u16)
+                }).is_some(),
+            };
+            if duplicate {
+                return Err(VivadoParseError::Duplicate { register: reg, line: line_no });
+            }
+        }
+
+        Ok(VivadoEfuseReport {
+            key: key.ok_or(VivadoParseError::Missing { register: Register::Key })?,
+            user: user.ok_or(VivadoParseError::Missing { register: Register::User })?,
+            cntl: cntl.ok_or(VivadoParseError::Missing { register: Register::Cntl })?,
+        })
+    }
+}
+
+/// How one register compares between a fetch and a Vivado report
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Agreement {
+    Match,
+    Mismatch,
+    /// the values differ or one is missing, but readback of the register is disabled, so the
+    /// difference is expected and says nothing about the fuses
+    Unverifiable,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ComparisonResult {
+    pub key: Agreement,
+    pub user: Agreement,
+    pub cntl: Agreement,
+}
+
+impl ComparisonResult {
+    /// true unless some register positively disagrees
+    pub fn is_consistent(&self) -> bool {
+        [self.key, self.user, self.cntl].iter().all(|&a| a != Agreement::Mismatch)
+    }
+}
+
+fn agreement<T: PartialEq>(fetched: T, reported: Reported<T>, read_disabled: bool) -> Agreement {
+    match reported {
+        Reported::Value(v) if v == fetched => Agreement::Match,
+        Reported::Value(_) if !read_disabled => Agreement::Mismatch,
+        _ => Agreement::Unverifiable,
+    }
+}
+
+/// Compares fetched KEY/USER/CNTL values against `report`. A read-disable bit set on either side
+/// makes a disagreement on the register it covers Unverifiable rather than a Mismatch.
+pub fn compare(key: &[u8; 32], user: u32, cntl: u8, report: &VivadoEfuseReport) -> ComparisonResult {
+    let reported_cntl: Reported<u8> = match report.cntl {
+        Reported::Value(c) => Reported::Value((c as u8) & CNTL_MASK),
+        Reported::Unreadable => Reported::Unreadable,
+    };
+    let read_disable: u8 = match reported_cntl {
+        Reported::Value(c) => cntl | c,
+        Reported::Unreadable => cntl,
+    };
+    ComparisonResult {
+        key: agreement(*key, report.key, read_disable & CNTL_R_EN_B_KEY != 0),
+        user: agreement(user, report.user, read_disable & CNTL_R_EN_B_USER != 0),
+        cntl: agreement(cntl & CNTL_MASK, reported_cntl, false),
+    }
+}
+
+/// CNTL bits in the order, and with the names, Vivado's -control_efuse option uses
+const CNTL_OPTIONS: [(u8, &str); 5] = [
+    (CNTL_CFG_AES_ONLY, "CFG_AES_ONLY"),
+    (CNTL_AES_EXCLUSIVE, "AES_EXCLUSIVE"),
+    (CNTL_W_EN_B_KEY_USER, "W_EN_B_KEY_USER"),
+    (CNTL_R_EN_B_KEY, "R_EN_B_KEY"),
+    (CNTL_R_EN_B_USER, "R_EN_B_USER"),
+];
+
+/// Where the script gets the key from
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TclKey<'a> {
+    /// an existing .nky file holding the staged key, e.g. one written with keyhex::write_nky()
+    NkyFile(&'a str),
+    /// the script writes the key to this .nky file itself, so the key is in the script
+    Inline { nky_file: &'a str },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TclOptions<'a> {
+    /// part name, e.g. "xc7s50"; selects the hw_device and goes in the .nky Device line
+    pub device: &'a str,
+    pub key: TclKey<'a>,
+}
+
+/// A part of a staged state that Vivado's eFUSE flow can't program
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Inexpressible {
+    /// the key is partly burned already; Vivado only programs a whole key into blank fuses
+    KeyPatch,
+    /// USER is partly burned already, and Vivado only programs a whole USER value
+    UserPatch { fused: u32, staged: u32 },
+    /// CNTL bits Vivado has no option for
+    ReservedCntl { bits: u8 },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TclExportError {
+    /// the staged state doesn't validate
+    Invalid(EfuseError),
+    /// every part of the staged state Vivado can't express; nothing was written
+    Inexpressible(Vec<Inexpressible>),
+    /// the staged state is already fused
+    NothingToProgram,
+    /// the writer failed
+    Fmt,
+}
+
+impl From<fmt::Error> for TclExportError {
+    fn from(_: fmt::Error) -> Self {
+        TclExportError::Fmt
+    }
+}
+
+/// Writes a Tcl script that has Vivado's hardware manager program the state staged in `api`
+/// into the fuses of the current hw_target, as one program_hw_devices -key efuse call.
+///
+/// Vivado only programs whole values, so a staged state that patches a partly burned KEY or
+/// USER is refused, as is one that sets CNTL bits Vivado can't name; the error lists all of
+/// them. Values that are already fused are left out of the script.
+pub fn export_tcl<W: fmt::Write>(api: &EfuseApi, out: &mut W, opts: &TclOptions) -> Result<(), TclExportError> {
+    api.validate().map_err(TclExportError::Invalid)?;
+
+    let (key, fused_key) = (api.api_key(), api.phy_key());
+    let (user, fused_user) = (api.api_user(), api.phy_user());
+    let (cntl, fused_cntl) = (api.api_cntl() & CNTL_MASK, api.phy_cntl());
+    let mut inexpressible: Vec<Inexpressible> = Vec::new();
+    let program_key: bool = !ct_eq_32(&key, &fused_key);
+    if program_key && fused_key != [0; 32] {
+        inexpressible.push(Inexpressible::KeyPatch);
+    }
+    if user != fused_user && fused_user != 0 {
+        inexpressible.push(Inexpressible::UserPatch { fused: fused_user, staged: user });
+    }
+    let named: u8 = CNTL_OPTIONS.iter().fold(0, |bits, &(bit, _)| bits | bit);
+    let reserved: u8 = cntl & !fused_cntl & !named;
+    if reserved != 0 {
+        inexpressible.push(Inexpressible::ReservedCntl { bits: reserved });
+    }
+    if !inexpressible.is_empty() {
+        return Err(TclExportError::Inexpressible(inexpressible));
+    }
+
+    let program_user: bool = user != fused_user;
+    let program_cntl: bool = cntl != fused_cntl;
+    if !(program_key || program_user || program_cntl) {
+        return Err(TclExportError::NothingToProgram);
+    }
+
+    writeln!(out, "# eFUSE programming script generated by efuse-api")?;
+    writeln!(out, "# fuses are one-time programmable: check the device and values before running")?;
+    writeln!(out, "set hw_device [lindex [get_hw_devices {}*] 0]", opts.device)?;
+    writeln!(out, "current_hw_device $hw_device")?;
+    let nky_file: &str = match opts.key {
+        TclKey::NkyFile(path) => path,
+        TclKey::Inline { nky_file } => {
+            if program_key {
+                let mut nky: String = String::new();
+                keyhex::write_nky(&key, opts.device, &mut nky)?;
+                writeln!(out, "set nky [open {{{}}} w]", nky_file)?;
+                for line in nky.lines() {
+                    writeln!(out, "puts $nky {{{}}}", line)?;
+                }
+                writeln!(out, "close $nky")?;
+            }
+            nky_file
+        },
+    };
+
+    write!(out, "program_hw_devices")?;
+    if program_key {
+        write!(out, " -key {{efuse}} -nky {{{}}}", nky_file)?;
+    }
+    if program_user {
+        write!(out, " -user_efuse {{{:08X}}}", user)?;
+    }
+    if program_cntl {
+        write!(out, " -control_efuse {{")?;
+        for (i, &(bit, name)) in CNTL_OPTIONS.iter().enumerate() {
+            let sep: &str = if i == 0 { "" } else { " " };
+            write!(out, "{}{} {}", sep, name, if cntl & bit != 0 { "YES" } else { "NO" })?;
+        }
+        write!(out, "}}")?;
+    }
+    writeln!(out, " $hw_device")?;
+    writeln!(out, "refresh_hw_device $hw_device")?;
+    Ok(())
+}
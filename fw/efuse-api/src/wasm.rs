@@ -0,0 +1,127 @@
+//! wasm-bindgen adapter for the browser-based provisioning validator
+//!
+//! The page hands over a snapshot and a manifest as JSON and gets a JSON report back:
+//!
+//!   snapshot: `{"banks": [13 raw bank values]}`
+//!   manifest: `{"key": "<64 hex digits, as in a .nky file>", "user": 305419896, "cntl": 0}`
+//!   report:   `{"valid": true, "error": null, "warnings": [], "weak_key_overridden": false,
+//!              "plan": [{"bank": 12, "bits": 7}, ...]}`
+//!
+//! `error` is `{"kind": ..., "detail": ...}` when the inputs don't parse or the plan is refused.
+//! The plan lists bits to blow per bank, in burn order; it never includes the key itself.
+//! Only the offline model is used here, so nothing in this build touches a JTAG port.
+//!
+//! Build with `cargo build --target wasm32-unknown-unknown --features wasm` (or wasm-pack);
+//! tests/wasm_tests.rs runs natively and under `wasm-pack test --node -- --features wasm`.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::keyhex;
+use crate::layout::{banks_image_ecc, FUSE_BANKS};
+use crate::messages::FuseSnapshot;
+use crate::{EfuseApi, EfuseError};
+
+#[derive(Deserialize)]
+struct SnapshotJson {
+    banks: [u32; FUSE_BANKS],
+}
+
+#[derive(Deserialize)]
+struct ManifestJson {
+    key: String,
+    user: u32,
+    #[serde(default)]
+    cntl: u8,
+    #[serde(default)]
+    allow_weak_key: bool,
+}
+
+#[derive(Serialize)]
+struct ErrorJson {
+    kind: &'static str,
+    detail: String,
+}
+
+#[derive(Serialize)]
+struct PlannedBank {
+    bank: usize,
+    bits: u32,
+}
+
+#[derive(Serialize, Default)]
+struct ReportJson {
+    valid: bool,
+    error: Option<ErrorJson>,
+    warnings: Vec<String>,
+    weak_key_overridden: bool,
+    plan: Vec<PlannedBank>,
+}
+
+fn refused(kind: &'static str, detail: String) -> ReportJson {
+    ReportJson { error: Some(ErrorJson { kind, detail }), ..ReportJson::default() }
+}
+
+fn report(snapshot_json: &str, manifest_json: &str) -> ReportJson {
+    let snapshot: SnapshotJson = match serde_json::from_str(snapshot_json) {
+        Ok(s) => s,
+        Err(e) => return refused("BadSnapshot", format!("{}", e)),
+    };
+    let manifest: ManifestJson = match serde_json::from_str(manifest_json) {
+        Ok(m) => m,
+        Err(e) => return refused("BadManifest", format!("{}", e)),
+    };
+    let key: [u8; 32] = match keyhex::parse(&manifest.key) {
+        Ok(k) => k,
+        Err(e) => return refused("BadKey", format!("{:?}", e)),
+    };
+
+    let mut api: EfuseApi = EfuseApi::new();
+    api.load_snapshot(&FuseSnapshot { banks: snapshot.banks });
+    api.set_key(key);
+    api.set_user(manifest.user);
+    if let Err(e) = api.set_cntl(manifest.cntl) {
+        return refused("BadCntl", format!("{:?}", e));
+    }
+    api.allow_weak_key(manifest.allow_weak_key);
+    let validation = match api.validate() {
+        Ok(v) => v,
+        Err(e) => {
+            let kind: &'static str = match e {
+                EfuseError::Invalid | EfuseError::IllegalTransition { .. } => "Invalid",
+                EfuseError::UserMismatch { .. } => "UserMismatch",
+                EfuseError::WeakKey { .. } => "WeakKey",
+                _ => "Other",
+            };
+            return refused(kind, format!("{:?}", e));
+        },
+    };
+
+    let image: [u32; FUSE_BANKS] = banks_image_ecc(&key, manifest.user, manifest.cntl);
+    ReportJson {
+        valid: true,
+        error: None,
+        warnings: validation.warnings.iter().map(|w| format!("{:?}", w)).collect(),
+        weak_key_overridden: validation.weak_key_overridden,
+        plan: (0..FUSE_BANKS).rev()
+            .map(|bank| PlannedBank { bank, bits: (image[bank] & !snapshot.banks[bank]).count_ones() })
+            .filter(|p| p.bits != 0)
+            .collect(),
+    }
+}
+
+/// Validates provisioning `manifest_json` against `snapshot_json`, returning the report as JSON.
+/// This is validate_manifest() without the wasm-bindgen wrapper, for native callers and tests.
+pub fn validate_manifest_json(snapshot_json: &str, manifest_json: &str) -> String {
+    // the report is plain data, so serializing it can't fail
+    serde_json::to_string(&report(snapshot_json, manifest_json)).unwrap_or_default()
+}
+
+#[wasm_bindgen]
+pub fn validate_manifest(snapshot_json: &str, manifest_json: &str) -> String {
+    validate_manifest_json(snapshot_json, manifest_json)
+}
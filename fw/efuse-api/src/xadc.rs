@@ -0,0 +1,187 @@
+//! Checking the die's temperature and supplies before fuses are blown
+//!
+//! A fuse blown with the die too cold, or with VCCINT or VCCAUX out of tolerance, may blow
+//! only partway: it can read back as blown today and as blank after a few years. So a burn
+//! can be made to read the XADC's status registers first, and refuse with OutOfSpec unless
+//! every reading is within the EnvLimits in BurnConfig::environment.
+//!
+//! The XADC's dynamic reconfiguration port (DRP) is reachable from JTAG through the XADC_DRP
+//! instruction, as one 32-bit DR:
+//!
+//! ```text
+//! 31:30 0   29:26 command   25:16 DRP address   15:0 DRP data
+//! ```
+//!
+//! A read command (0b0001) only starts the read; its data comes out in bits 15:0 of the next
+//! DR scan. Reading the three registers therefore takes four scans, the last one a NOP.
+//!
+//! The registers hold a 12-bit code in bits 15:4. The conversions are the ones UG480 gives,
+//! kept in integers: temperature = code * 503.975 / 4096 - 273.15 degrees C, and a supply
+//! voltage = code * 3 V / 4096.
+//!
+//! The readings and the verdict go into BurnReport::pre_burn as a PreBurnCheck, for the
+//! provisioning log. Where the XADC can't be trusted, EfuseApi::override_environment_check()
+//! lets the next burn through whatever it reads; the reason goes into the report alongside.
+
+use core::fmt;
+
+use jtag::*;
+
+use crate::sequences::{Ir, IR_BITS, IR_BUDGET, POLL_BUDGET};
+
+/// XADC status register: die temperature
+pub const REG_TEMP: u16 = 0x00;
+/// XADC status register: VCCINT
+pub const REG_VCCINT: u16 = 0x01;
+/// XADC status register: VCCAUX
+pub const REG_VCCAUX: u16 = 0x02;
+
+/// DRP command: read the register at the address
+pub const DRP_READ: u32 = 0b0001;
+/// DRP command: no operation; the scan just carries out the last read's data
+pub const DRP_NOP: u32 = 0b0000;
+
+/// the DR word carrying `command` for DRP address `address`
+pub fn drp_word(command: u32, address: u16) -> u32 {
+    (command & 0xF) << 26 | (address as u32 & 0x3FF) << 16
+}
+
+/// the 12-bit code in the top of a status register
+fn code(raw: u16) -> u32 {
+    (raw >> 4) as u32
+}
+
+/// The three status registers, as read
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct XadcReadings {
+    pub temp: u16,
+    pub vccint: u16,
+    pub vccaux: u16,
+}
+
+impl XadcReadings {
+    /// die temperature in thousandths of a degree C
+    pub fn temp_mc(&self) -> i32 {
+        (code(self.temp) * 503_975 / 4096) as i32 - 273_150
+    }
+
+    /// VCCINT in mV
+    pub fn vccint_mv(&self) -> u32 {
+        code(self.vccint) * 3000 / 4096
+    }
+
+    /// VCCAUX in mV
+    pub fn vccaux_mv(&self) -> u32 {
+        code(self.vccaux) * 3000 / 4096
+    }
+}
+
+impl fmt::Display for XadcReadings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let temp: i32 = self.temp_mc() / 100;
+        let sign: &str = if temp < 0 { "-" } else { "" };
+        write!(f, "{}{}.{} C, VCCINT {} mV, VCCAUX {} mV", sign, temp.abs() / 10, temp.abs() % 10, self.vccint_mv(), self.vccaux_mv())
+    }
+}
+
+/// The reading that was out of its limits; the first of them, in the order they're checked
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EnvViolation {
+    Temperature,
+    Vccint,
+    Vccaux,
+}
+
+fn within<V: PartialOrd>(value: V, (min, max): (V, V)) -> bool {
+    min <= value && value <= max
+}
+
+/// Inclusive (min, max) bounds a burn needs every reading within
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct EnvLimits {
+    pub temp_mc: (i32, i32),
+    pub vccint_mv: (u32, u32),
+    pub vccaux_mv: (u32, u32),
+}
+
+impl EnvLimits {
+    /// 15 to 85 degrees C, and both supplies within 5% of nominal (1.0 V and 1.8 V)
+    pub const SEVEN_SERIES: EnvLimits = EnvLimits {
+        temp_mc: (15_000, 85_000),
+        vccint_mv: (950, 1050),
+        vccaux_mv: (1710, 1890),
+    };
+
+    /// the first reading outside its limits, if any
+    pub fn violation(&self, readings: &XadcReadings) -> Option<EnvViolation> {
+        if !within(readings.temp_mc(), self.temp_mc) {
+            Some(EnvViolation::Temperature)
+        } else if !within(readings.vccint_mv(), self.vccint_mv) {
+            Some(EnvViolation::Vccint)
+        } else if !within(readings.vccaux_mv(), self.vccaux_mv) {
+            Some(EnvViolation::Vccaux)
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for EnvLimits {
+    fn default() -> Self {
+        EnvLimits::SEVEN_SERIES
+    }
+}
+
+/// What the environment check before a burn read and concluded
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PreBurnCheck {
+    pub readings: XadcReadings,
+    /// the reading that was out of limits, if any
+    pub violation: Option<EnvViolation>,
+    /// set if override_environment_check() let the burn through, for this reason
+    pub overridden: Option<&'static str>,
+}
+
+impl PreBurnCheck {
+    /// `readings`, checked against `limits`
+    pub fn new(readings: XadcReadings, limits: &EnvLimits) -> Self {
+        PreBurnCheck { readings, violation: limits.violation(&readings), overridden: None }
+    }
+
+    /// true if the burn may go ahead: the readings are within limits, or that was overridden
+    pub fn passed(&self) -> bool {
+        self.violation.is_none() || self.overridden.is_some()
+    }
+}
+
+/// Reads the temperature, VCCINT and VCCAUX registers through XADC_DRP
+pub(crate) fn read<T: JtagPhy>(jm: &mut JtagMach, jp: &mut T) -> Result<XadcReadings, JtagError> {
+    let mut ir_leg: JtagLeg = JtagLeg::new(JtagChain::IR, "xadc_drp").with_budget(IR_BUDGET);
+    ir_leg.push_u32(Ir::XadcDrp.code(), IR_BITS, JtagEndian::Little).unwrap();
+    jm.add(ir_leg);
+    let words: [u32; 4] = [
+        drp_word(DRP_READ, REG_TEMP),
+        drp_word(DRP_READ, REG_VCCINT),
+        drp_word(DRP_READ, REG_VCCAUX),
+        drp_word(DRP_NOP, 0),
+    ];
+    for &word in words.iter() {
+        let mut data_leg: JtagLeg = JtagLeg::new(JtagChain::DR, "drp").with_budget(POLL_BUDGET);
+        data_leg.push_u32(word, 32, JtagEndian::Little).unwrap();
+        jm.add(data_leg);
+    }
+    if let Err(e) = jm.run_to_completion(jp) {
+        jm.clear_pending();
+        return Err(e);
+    }
+    // the instruction leg, then a data leg per word; each scan carries out the read before it
+    let mut out: [u16; 5] = [0; 5];
+    let mut index: usize = 0;
+    jm.drain_completed(|mut leg| {
+        if index > 0 && index < out.len() {
+            out[index] = leg.pop_u32(32, JtagEndian::Little).unwrap_or(0) as u16;
+        }
+        index += 1;
+    });
+    Ok(XadcReadings { temp: out[2], vccint: out[3], vccaux: out[4] })
+}
@@ -0,0 +1,152 @@
+//! Compiled XSVF export of the JTAG traffic
+//!
+//! Some fixtures only take XSVF, the binary form of SVF from Xilinx XAPP503. XsvfWriter is a
+//! phy like SvfWriter, following the TAP through the same steps (see the svf module) and
+//! writing them as XSVF records instead: XSIR for IR scans (XSIR2 past 255 bits), XSDRTDO for
+//! DR scans, XSTATE for resets, and XWAIT for pauses and idle cycles. finish() closes the
+//! file with XCOMPLETE.
+//!
+//! Scan data is packed as XAPP503 has it: ceil(bits / 8) bytes, most significant byte first,
+//! the first bit shifted being bit 0 of the last byte. XSDRTDO carries the TDO the wrapped phy
+//! drove, but the TDO mask is all zeros, so the player compares none of it; see the svf module
+//! for why. The header sets XRUNTEST 0 so the player adds no waits of its own after a scan.
+//!
+//! XSVF counts waits in microseconds, not TCK cycles, so idle cycles are converted at the TCK
+//! rate given to new(), rounding up.
+
+use alloc::vec::Vec;
+use jtag::*;
+
+use crate::svf::{ScanTracker, Step};
+
+pub const XCOMPLETE: u8 = 0x00;
+pub const XTDOMASK: u8 = 0x01;
+pub const XSIR: u8 = 0x02;
+pub const XRUNTEST: u8 = 0x04;
+pub const XREPEAT: u8 = 0x07;
+pub const XSDRSIZE: u8 = 0x08;
+pub const XSDRTDO: u8 = 0x09;
+pub const XSTATE: u8 = 0x12;
+pub const XENDIR: u8 = 0x13;
+pub const XENDDR: u8 = 0x14;
+pub const XSIR2: u8 = 0x15;
+pub const XWAIT: u8 = 0x17;
+
+/// XSTATE/XWAIT code of a TAP state; XAPP503 numbers them as TapState declares them
+pub fn xsvf_state(state: TapState) -> u8 {
+    state as u8
+}
+
+/// `bits`, first shifted first, packed into bytes as XSVF has them
+pub fn pack_bits(bits: &[bool]) -> Vec<u8> {
+    let len: usize = bits.len().div_ceil(8);
+    let mut bytes: Vec<u8> = alloc::vec![0; len];
+    for (n, &bit) in bits.iter().enumerate() {
+        if bit {
+            bytes[len - 1 - n / 8] |= 1 << (n % 8);
+        }
+    }
+    bytes
+}
+
+/// Wraps a phy, writing every cycle driven through it as XSVF records
+pub struct XsvfWriter<P: JtagPhy> {
+    inner: P,
+    out: Vec<u8>,
+    tracker: ScanTracker,
+    tck_hz: u32,
+    /// the length XSDRSIZE and XTDOMASK were last set for
+    sdr_bits: Option<usize>,
+}
+
+impl<P: JtagPhy> XsvfWriter<P> {
+    /// starts a file, with idle cycles to be counted at `tck_hz`; the TAP is taken to be in
+    /// TEST_LOGIC_RESET, and the file puts the player's TAP there first
+    pub fn new(inner: P, tck_hz: u32) -> Self {
+        assert!(tck_hz > 0, "idle cycles need a TCK rate");
+        let mut out: Vec<u8> = Vec::new();
+        out.extend_from_slice(&[XREPEAT, 0, XENDIR, 0, XENDDR, 0, XRUNTEST, 0, 0, 0, 0]);
+        out.extend_from_slice(&[XSTATE, xsvf_state(TapState::TestLogicReset)]);
+        XsvfWriter { inner, out, tracker: ScanTracker::new(), tck_hz, sdr_bits: None }
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// Writes out any idle cycles still held back and XCOMPLETE, and hands back the phy and
+    /// the file.
+    pub fn finish(mut self) -> (P, Vec<u8>) {
+        self.flush_idle();
+        self.out.push(XCOMPLETE);
+        (self.inner, self.out)
+    }
+
+    fn wait(&mut self, state: TapState, us: u32) {
+        self.out.extend_from_slice(&[XWAIT, xsvf_state(state), xsvf_state(state)]);
+        self.out.extend_from_slice(&us.to_be_bytes());
+    }
+
+    fn flush_idle(&mut self) {
+        let idle: u32 = self.tracker.take_idle();
+        if idle != 0 {
+            let us: u64 = (idle as u64 * 1_000_000).div_ceil(self.tck_hz as u64);
+            self.wait(TapState::RunTestIdle, us.min(u32::MAX as u64) as u32);
+        }
+    }
+
+    fn write(&mut self, step: Step) {
+        self.flush_idle();
+        match step {
+            Step::Reset => self.out.extend_from_slice(&[XSTATE, xsvf_state(TapState::TestLogicReset)]),
+            Step::Idle => self.out.extend_from_slice(&[XSTATE, xsvf_state(TapState::RunTestIdle)]),
+            Step::Scan { ir: true, tdi, .. } => {
+                if tdi.len() <= u8::MAX as usize {
+                    self.out.extend_from_slice(&[XSIR, tdi.len() as u8]);
+                } else {
+                    self.out.push(XSIR2);
+                    self.out.extend_from_slice(&(tdi.len() as u16).to_be_bytes());
+                }
+                self.out.extend_from_slice(&pack_bits(&tdi));
+            },
+            Step::Scan { ir: false, tdi, tdo } => {
+                if self.sdr_bits != Some(tdi.len()) {
+                    self.sdr_bits = Some(tdi.len());
+                    self.out.push(XSDRSIZE);
+                    self.out.extend_from_slice(&(tdi.len() as u32).to_be_bytes());
+                    self.out.push(XTDOMASK);
+                    self.out.extend_from_slice(&pack_bits(&alloc::vec![false; tdi.len()]));
+                }
+                self.out.push(XSDRTDO);
+                self.out.extend_from_slice(&pack_bits(&tdi));
+                self.out.extend_from_slice(&pack_bits(&tdo));
+            },
+        }
+    }
+}
+
+impl<P: JtagPhy> JtagPhy for XsvfWriter<P> {
+    fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+        self.try_sync(tdi, tms).unwrap_or(false)
+    }
+
+    /// drives the pins directly, unrecorded; the JtagMach never does
+    fn nosync(&mut self, tdi: bool, tms: bool, tck: bool) -> bool {
+        self.inner.nosync(tdi, tms, tck)
+    }
+
+    /// a pause is an XWAIT in whichever state the TAP is in
+    fn pause(&mut self, us: u32) {
+        self.flush_idle();
+        self.wait(self.tracker.tap, us);
+        self.inner.pause(us);
+    }
+
+    fn try_sync(&mut self, tdi: bool, tms: bool) -> Result<bool, PhyError> {
+        let tdo: bool = self.inner.try_sync(tdi, tms)?;
+        if let Some(step) = self.tracker.clock(tdi, tms, tdo) {
+            self.write(step);
+        }
+        Ok(tdo)
+    }
+}
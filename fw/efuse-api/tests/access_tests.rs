@@ -0,0 +1,88 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::access::*;
+    use efuse_api::keysource::*;
+    use efuse_api::layout::*;
+    use efuse_api::messages::*;
+    use efuse_api::protocol::*;
+    use efuse_api::sequences::*;
+    use efuse_api::test_utils::*;
+
+    /// CTL0 of a bitstream that disabled readback
+    const LOCKED: u32 = 0b01 << 4;
+
+    #[test]
+    fn secured_device_isnt_blank() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = ScriptedPhy::secured_device(LOCKED);
+        let mut efuse: EfuseApi = EfuseApi::new();
+        let before = FuseSnapshot { banks: banks_image_ecc(&[0x11; 32], 0x1234, CNTL_CFG_AES_ONLY) };
+        efuse.load_snapshot(&before);
+        assert_eq!(efuse.fetch(&mut jm, &mut jp), Err(EfuseError::AccessBlockedBySecurity {
+            detail: BlockedDetail::ZeroedWhileSecured { stat: STAT_PART_SECURED, ctl0: LOCKED },
+        }));
+        // the fused state isn't replaced by the zeros that were read
+        assert_eq!(efuse.snapshot(), before);
+        assert_eq!(jp.reads(Ir::CfgOut), 2);
+    }
+
+    #[test]
+    fn secured_without_lockout_reads_blank() {
+        // a BBRAM-encrypted bitstream that left readback alone: the zeros are real
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = ScriptedPhy::secured_device(0);
+        let mut efuse: EfuseApi = EfuseApi::new();
+        assert_eq!(efuse.lock_status(&mut jm, &mut jp), Ok(LockStatus::Blank));
+
+        // and a plaintext one doesn't get CTL0 read at all
+        let mut jp = ScriptedPhy::new();
+        assert_eq!(efuse.lock_status(&mut jm, &mut jp), Ok(LockStatus::Blank));
+        assert_eq!(jp.reads(Ir::CfgOut), 1);
+    }
+
+    #[test]
+    fn saturated_readbacks() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = ScriptedPhy::new();
+        jp.on_dr_bytes(Ir::FuseKey, 256, &[0xFF; 32]);
+        jp.on_dr(Ir::FuseUser, 32, 0xFFFF_FFFF);
+        jp.on_dr(Ir::FuseCntl, 14, 0x3FFF);
+        let mut efuse: EfuseApi = EfuseApi::new();
+        assert_eq!(efuse.lock_status(&mut jm, &mut jp),
+            Ok(LockStatus::AccessRestricted { detail: BlockedDetail::Saturated }));
+        // nothing about that needed the configuration registers
+        assert_eq!(jp.reads(Ir::CfgOut), 0);
+    }
+
+    #[test]
+    fn lock_status() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        assert_eq!(efuse.lock_status(&mut jm, &mut EfuseModelPhy::new()), Ok(LockStatus::Blank));
+        let mut jp = EfuseModelPhy::with_banks(banks_image_ecc(&[0x22; 32], 0, CNTL_W_EN_B_KEY_USER));
+        assert_eq!(efuse.lock_status(&mut jm, &mut jp), Ok(LockStatus::Readable { cntl: CNTL_W_EN_B_KEY_USER }));
+        let restricted = efuse.lock_status(&mut jm, &mut ScriptedPhy::secured_device(LOCKED)).unwrap();
+
+        let blank: String = LockStatus::Blank.to_string();
+        let restricted: String = restricted.to_string();
+        assert!(blank.starts_with("blank"));
+        assert!(restricted.starts_with("access restricted"));
+        assert!(restricted.contains("unknown"));
+        assert!(!restricted.contains("blank"));
+    }
+
+    #[test]
+    fn reported_over_the_protocol() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = ScriptedPhy::secured_device(LOCKED);
+        let mut efuse: EfuseApi = EfuseApi::new();
+        let mut server: Server = Server::new();
+        server.handle(&mut efuse, Command::Hello(PROTOCOL_VERSION), &mut jm, &mut jp);
+        for cmd in [Command::GetLockStatus, Command::GetSnapshot].iter().copied() {
+            assert_eq!(server.handle(&mut efuse, cmd, &mut jm, &mut jp), Response::Error(ProtocolError::AccessRestricted));
+        }
+        assert_eq!(ProtocolError::from_code(10), Some(ProtocolError::AccessRestricted));
+    }
+}
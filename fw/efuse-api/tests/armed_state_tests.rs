@@ -0,0 +1,119 @@
+#![cfg(feature = "fault-injection")]
+
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::messages::*;
+    use efuse_api::protocol::*;
+    use efuse_api::test_utils::*;
+
+    fn manifest() -> ProvisioningManifest {
+        let mut key = [0u8; 32];
+        for (i, k) in key.iter_mut().enumerate() {
+            *k = (i as u8).wrapping_mul(29) ^ 0xC3;
+        }
+        ProvisioningManifest { key, user: 0x0012_3456, cntl: 0 }
+    }
+
+    fn staged(jm: &mut JtagMach, jp: &mut EfuseModelPhy) -> EfuseApi {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(jm, jp).unwrap();
+        efuse.stage(&manifest()).unwrap();
+        efuse
+    }
+
+    #[test]
+    fn armed_burn_goes_through() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse = staged(&mut jm, &mut jp);
+        assert_eq!(efuse.arm(), manifest().checksum());
+        assert!(efuse.is_armed());
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert!(!efuse.is_armed());
+        assert_eq!(jp.banks(), banks_image_ecc(&manifest().key, manifest().user, 0));
+    }
+
+    #[test]
+    fn corruption_between_arm_and_burn_stops_it() {
+        // first and last key byte, USER, CNTL
+        for &offset in [0, 31, 33, 36].iter() {
+            let mut jm: JtagMach = JtagMach::new();
+            let mut jp = EfuseModelPhy::new();
+            let mut efuse = staged(&mut jm, &mut jp);
+            efuse.arm();
+            efuse.corrupt_staged(offset, 0x10);
+            assert_eq!(efuse.burn(&mut jm, &mut jp), Err(EfuseError::StagedStateCorrupted), "offset {}", offset);
+            assert!(jp.programmed().is_empty());
+            assert_eq!(jp.commits(), 0);
+            assert_eq!(efuse.last_report(), None);
+            // still armed, so it keeps refusing until the state is staged and armed afresh
+            assert_eq!(efuse.burn(&mut jm, &mut jp), Err(EfuseError::StagedStateCorrupted));
+            efuse.stage(&manifest()).unwrap();
+            efuse.arm();
+            efuse.burn(&mut jm, &mut jp).unwrap();
+            assert_eq!(jp.banks(), banks_image_ecc(&manifest().key, manifest().user, 0));
+        }
+    }
+
+    #[test]
+    fn corruption_between_arm_and_burn_cntl_stops_it() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse = staged(&mut jm, &mut jp);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.override_boot_verification("no boot image on the bench");
+        efuse.set_cntl(CNTL_W_EN_B_KEY_USER).unwrap();
+        efuse.arm();
+        // the CNTL byte
+        efuse.corrupt_staged(36, 0x10);
+        let programmed: usize = jp.programmed().len();
+        let commits: usize = jp.commits();
+        assert_eq!(efuse.burn_cntl(&mut jm, &mut jp), Err(EfuseError::StagedStateCorrupted));
+        assert_eq!(jp.programmed().len(), programmed);
+        assert_eq!(jp.commits(), commits);
+        assert_eq!(efuse.burn_cntl(&mut jm, &mut jp), Err(EfuseError::StagedStateCorrupted));
+
+        efuse.set_cntl(CNTL_W_EN_B_KEY_USER).unwrap();
+        efuse.arm();
+        efuse.burn_cntl(&mut jm, &mut jp).unwrap();
+        assert!(!efuse.is_armed());
+        assert_eq!(jp.banks(), banks_image_ecc(&manifest().key, manifest().user, CNTL_W_EN_B_KEY_USER));
+    }
+
+    #[test]
+    fn disarmed_state_isnt_checked() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse = staged(&mut jm, &mut jp);
+        efuse.arm();
+        efuse.disarm();
+        efuse.corrupt_staged(32, 0x01);
+        assert_ne!(efuse.manifest(), manifest());
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert_eq!(jp.banks(), banks_image_ecc(&efuse.api_key(), efuse.api_user(), 0));
+    }
+
+    #[test]
+    fn server_burns_only_what_was_armed() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        let mut server: Server = Server::new();
+        server.handle(&mut efuse, Command::Hello(PROTOCOL_VERSION), &mut jm, &mut jp);
+        server.handle(&mut efuse, Command::StageManifest(manifest()), &mut jm, &mut jp);
+        assert!(!efuse.is_armed());
+        assert_eq!(server.handle(&mut efuse, Command::Arm { checksum: manifest().checksum() }, &mut jm, &mut jp), Response::Armed);
+        assert!(efuse.is_armed());
+
+        efuse.corrupt_staged(7, 0x80);
+        assert_eq!(server.handle(&mut efuse, Command::ExecuteBurn, &mut jm, &mut jp), Response::Error(ProtocolError::BurnFailed));
+        assert!(jp.programmed().is_empty());
+
+        server.handle(&mut efuse, Command::Abort, &mut jm, &mut jp);
+        assert!(!efuse.is_armed());
+    }
+}
@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::test_utils::*;
+
+    const USER: u32 = 0x00C0_FFEE;
+
+    #[test]
+    fn banks_by_name() {
+        for (index, &bank) in Bank::ALL.iter().enumerate() {
+            assert_eq!(bank.index(), index);
+            assert_eq!(Bank::from_index(index), Some(bank));
+            assert_eq!(bank.kind(), BankKind::of(index));
+        }
+        assert_eq!(Bank::from_index(FUSE_BANKS), None);
+        assert_eq!((Bank::Cntl.index(), Bank::Key1.index(), Bank::Shared.index(), Bank::User.index()), (CNTL_BANK, 1, SHARED_BANK, USER_BANK));
+    }
+
+    #[test]
+    fn one_key_bank_alone() {
+        let mut jp = EfuseModelPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(test_key(0x96));
+        efuse.set_user(USER);
+        let proposed: [u32; FUSE_BANKS] = banks_image_ecc(&test_key(0x96), USER, 0);
+        for &bank in Bank::ALL.iter() {
+            assert_eq!(efuse.bank(bank), 0);
+            assert_eq!(efuse.proposed_bank(bank), proposed[bank.index()]);
+        }
+
+        efuse.burn_single_bank(Bank::Key3, &mut jm, &mut jp).unwrap();
+        let mut expected: [u32; FUSE_BANKS] = [0; FUSE_BANKS];
+        expected[3] = proposed[3];
+        assert_eq!(jp.banks(), expected);
+        assert_eq!(efuse.last_report().unwrap().requested, expected);
+
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.bank(Bank::Key3), proposed[3]);
+        assert_eq!(efuse.phy_key()[6..9], test_key(0x96)[6..9]);
+        // nothing left to do for it, and the rest still goes through a full burn
+        efuse.burn_single_bank(Bank::Key3, &mut jm, &mut jp).unwrap();
+        assert_eq!(jp.banks(), expected);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert_eq!(jp.banks(), proposed);
+    }
+
+    #[test]
+    fn cntl_goes_through_burn_cntl() {
+        let mut jp = EfuseModelPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(test_key(0x96));
+        efuse.set_cntl(CNTL_W_EN_B_KEY_USER).unwrap();
+        let shifted: usize = jp.ir_history().len();
+        assert_eq!(efuse.burn_single_bank(Bank::Cntl, &mut jm, &mut jp), Err(EfuseError::CntlSingleBank));
+        assert_eq!(jp.ir_history().len(), shifted);
+        assert_eq!(jp.banks(), [0; FUSE_BANKS]);
+        assert_eq!(efuse.last_report(), None);
+    }
+
+    #[test]
+    fn illegal_transitions_are_per_bank() {
+        // bank 5 has fuses the staged key wants clear
+        let mut fused: [u32; FUSE_BANKS] = [0; FUSE_BANKS];
+        fused[5] = bank_image_ecc(5, &[0xFF; 32], 0, 0);
+        let mut jp = EfuseModelPhy::with_banks(fused);
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(test_key(0x96));
+
+        let mask: u32 = fused[5] & !efuse.proposed_bank(Bank::Key5);
+        assert_eq!(efuse.validate_bank(Bank::Key5), Err(EfuseError::IllegalTransition { bank: 5, mask }));
+        assert_eq!(efuse.burn_single_bank(Bank::Key5, &mut jm, &mut jp), Err(EfuseError::IllegalTransition { bank: 5, mask }));
+        assert_eq!(jp.banks(), fused);
+        assert!(efuse.validate().is_err());
+
+        // the other banks can still go one at a time
+        assert_eq!(efuse.validate_bank(Bank::Key1), Ok(()));
+        efuse.burn_single_bank(Bank::Key1, &mut jm, &mut jp).unwrap();
+        assert_eq!(jp.banks()[1], efuse.proposed_bank(Bank::Key1));
+        assert_eq!(jp.banks()[5], fused[5]);
+    }
+
+    #[test]
+    fn not_without_a_fetch() {
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.set_key(test_key(0x96));
+        assert_eq!(efuse.burn_single_bank(Bank::Key1, &mut JtagMach::new(), &mut jp), Err(EfuseError::NotFetched));
+        assert_eq!(jp.banks(), [0; FUSE_BANKS]);
+    }
+}
@@ -0,0 +1,121 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::boot::*;
+    use efuse_api::keysource::*;
+    use efuse_api::layout::*;
+    use efuse_api::sequences::*;
+    use efuse_api::test_utils::*;
+
+    const CNTL: u8 = CNTL_CFG_AES_ONLY | CNTL_W_EN_B_KEY_USER;
+
+    /// an EfuseApi that has burned test_key(0xC3) with its readback disabled, with the lockdown staged
+    fn data_burned(jm: &mut JtagMach, jp: &mut EfuseModelPhy) -> EfuseApi {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(jm, jp).unwrap();
+        efuse.set_key(test_key(0xC3));
+        efuse.set_cntl(CNTL_R_EN_B_KEY).unwrap();
+        efuse.burn(jm, jp).unwrap();
+        efuse.fetch(jm, jp).unwrap();
+        // the key doesn't read back any more, so it's left as fused
+        efuse.set_key([0; 32]);
+        efuse.set_cntl(CNTL_R_EN_B_KEY | CNTL).unwrap();
+        efuse
+    }
+
+    #[test]
+    fn pass() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse = data_burned(&mut jm, &mut jp);
+        let mut boots: usize = 0;
+        efuse.verify_boot(&mut || { boots += 1; Ok(()) }).unwrap();
+        assert_eq!(boots, 1);
+        efuse.burn_cntl(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.last_report().unwrap().boot_check, BootCheck::Verified);
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.phy_cntl(), CNTL_R_EN_B_KEY | CNTL);
+    }
+
+    #[test]
+    fn fail() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse = data_burned(&mut jm, &mut jp);
+        let commits: usize = jp.commits();
+
+        // never verified
+        assert_eq!(efuse.burn_cntl(&mut jm, &mut jp), Err(EfuseError::BootNotVerified));
+
+        // verified, then the verifier fails; the earlier pass doesn't count any more
+        efuse.verify_boot(&mut || Ok(())).unwrap();
+        let failure = BootVerifyError::DecryptError { stat: STAT_DEC_ERROR };
+        assert_eq!(efuse.verify_boot(&mut || Err(failure)), Err(EfuseError::BootVerifyFailed { err: failure }));
+        assert_eq!(efuse.burn_cntl(&mut jm, &mut jp), Err(EfuseError::BootNotVerified));
+        assert_eq!(jp.commits(), commits);
+        assert_eq!(jp.banks()[CNTL_BANK] & (CNTL as u32), 0);
+        // the report is still the data burn's
+        assert_eq!(efuse.last_report().unwrap().boot_check, BootCheck::NotRequired);
+    }
+
+    #[test]
+    fn another_burn_needs_another_boot() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse = data_burned(&mut jm, &mut jp);
+        efuse.verify_boot(&mut || Ok(())).unwrap();
+        efuse.set_cntl(CNTL_R_EN_B_KEY).unwrap();
+        efuse.set_user(0x0000_4200);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_cntl(CNTL_R_EN_B_KEY | CNTL).unwrap();
+        assert_eq!(efuse.burn_cntl(&mut jm, &mut jp), Err(EfuseError::BootNotVerified));
+    }
+
+    #[test]
+    fn overridden() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse = data_burned(&mut jm, &mut jp);
+        efuse.override_boot_verification("bench board, no configuration flash");
+        efuse.burn_cntl(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.last_report().unwrap().boot_check,
+            BootCheck::Overridden { reason: "bench board, no configuration flash" });
+        assert_eq!(jp.banks()[CNTL_BANK] & (CNTL as u32), CNTL as u32);
+    }
+
+    fn jtag_verify(stat: u32) -> (Result<(), BootVerifyError>, ScriptedPhy) {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = ScriptedPhy::new();
+        jp.on_config_register(REG_STAT, stat);
+        let result = JtagBootVerifier::new(&mut jm, &mut jp).with_boot_time(250_000).verify_encrypted_boot();
+        (result, jp)
+    }
+
+    #[test]
+    fn jtag_verifier() {
+        let booted: u32 = STAT_DONE | STAT_PART_SECURED;
+        let (result, jp) = jtag_verify(booted);
+        assert_eq!(result, Ok(()));
+        // JPROGRAM, then the STAT read, after the boot time
+        assert_eq!(jp.ir_history().first(), Some(&Ir::Jprogram.code()));
+        assert_eq!(jp.reads(Ir::CfgOut), 1);
+        assert!(jp.elapsed_us() >= 250_000);
+
+        assert_eq!(jtag_verify(booted | STAT_DEC_ERROR).0, Err(BootVerifyError::DecryptError { stat: booted | STAT_DEC_ERROR }));
+        assert_eq!(jtag_verify(STAT_PART_SECURED).0, Err(BootVerifyError::NotDone { stat: STAT_PART_SECURED }));
+        assert_eq!(jtag_verify(STAT_DONE).0, Err(BootVerifyError::NotSecured { stat: STAT_DONE }));
+    }
+
+    #[test]
+    fn jtag_verifier_gates_the_lockdown() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse = data_burned(&mut jm, &mut jp);
+        // the model has no configuration logic, so STAT reads as zeros: nothing booted
+        let result = efuse.verify_boot(&mut JtagBootVerifier::new(&mut jm, &mut jp));
+        assert_eq!(result, Err(EfuseError::BootVerifyFailed { err: BootVerifyError::NotDone { stat: 0 } }));
+        assert_eq!(efuse.burn_cntl(&mut jm, &mut jp), Err(EfuseError::BootNotVerified));
+    }
+}
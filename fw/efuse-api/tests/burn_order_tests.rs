@@ -0,0 +1,88 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::sequences::*;
+    use efuse_api::test_utils::*;
+
+    fn burn(order: BitOrderPolicy) -> (EfuseApi, EfuseModelPhy) {
+        let mut jp = EfuseModelPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(test_key(0x96));
+        efuse.set_user(0x0BAD_F00D);
+        efuse.set_burn_config(BurnConfig { order, ..BurnConfig::default() });
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        (efuse, jp)
+    }
+
+    #[test]
+    fn same_fuses_either_way() {
+        let (_, ascending) = burn(BitOrderPolicy::Ascending);
+        let (efuse, shuffled) = burn(BitOrderPolicy::Shuffled { seed: 0xC0FF_EE00_1234 });
+        assert_eq!(ascending.banks(), banks_image_ecc(&test_key(0x96), 0x0BAD_F00D, 0));
+        assert_eq!(shuffled.banks(), ascending.banks());
+        assert_eq!(shuffled.rejected(), 0);
+        assert_ne!(shuffled.programmed(), ascending.programmed());
+        assert_eq!(efuse.last_report().unwrap().order, BitOrderPolicy::Shuffled { seed: 0xC0FF_EE00_1234 });
+    }
+
+    #[test]
+    fn recorded_seed_reproduces_the_order() {
+        let (efuse, jp) = burn(BitOrderPolicy::Shuffled { seed: 42 });
+        let report = efuse.last_report().unwrap();
+
+        // rebuild the word order from nothing but the report
+        let params = DeviceParams::SEVEN_SERIES;
+        let mut expected: Vec<(usize, u8)> = Vec::new();
+        for bank in (0..FUSE_BANKS).rev() {
+            for word in dr_words_for_bank_ordered(bank, report.requested[bank], &params, report.order) {
+                if let WordKind::Bit(bit) = word.kind {
+                    expected.push((bank, bit));
+                }
+            }
+        }
+        assert_eq!(jp.programmed(), &expected[..]);
+    }
+
+    /// the banks programmed, in order, each once per run of words
+    fn bank_runs(programmed: &[(usize, u8)]) -> Vec<usize> {
+        let mut runs: Vec<usize> = programmed.iter().map(|&(bank, _)| bank).collect();
+        runs.dedup();
+        runs
+    }
+
+    #[test]
+    fn banks_burn_from_the_top_with_cntl_last() {
+        let mut jp = EfuseModelPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(test_key(0x96));
+        efuse.set_user(0x0BAD_F00D);
+        efuse.set_cntl(CNTL_W_EN_B_KEY_USER).unwrap();
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert_eq!(bank_runs(jp.programmed()), (0..FUSE_BANKS).rev().collect::<Vec<usize>>());
+        assert_eq!(jp.banks(), banks_image_ecc(&test_key(0x96), 0x0BAD_F00D, CNTL_W_EN_B_KEY_USER));
+        assert_eq!(jp.rejected(), 0);
+    }
+
+    #[test]
+    fn banks_without_changes_are_skipped() {
+        // everything fused but bank 5
+        let mut banks = banks_image_ecc(&test_key(0x96), 0x0BAD_F00D, 0);
+        banks[5] = 0;
+        let mut jp = EfuseModelPhy::with_banks(banks);
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(test_key(0x96));
+        efuse.set_user(0x0BAD_F00D);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert_eq!(bank_runs(jp.programmed()), [5]);
+        assert_eq!(jp.programmed().len(), bank_image_ecc(5, &test_key(0x96), 0x0BAD_F00D, 0).count_ones() as usize);
+        assert_eq!(jp.banks(), banks_image_ecc(&test_key(0x96), 0x0BAD_F00D, 0));
+    }
+}
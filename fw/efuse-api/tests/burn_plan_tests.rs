@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::messages::*;
+    use efuse_api::test_utils::*;
+
+    const USER: u32 = 0x0070_0D1E;
+
+    /// the fuses `programmed` blew, as a mask per bank
+    fn blown(programmed: &[(usize, u8)]) -> [u32; FUSE_BANKS] {
+        let mut banks = [0u32; FUSE_BANKS];
+        for &(bank, bit) in programmed {
+            banks[bank] |= 1 << bit;
+        }
+        banks
+    }
+
+    /// plans test_key(0xC3), USER and `cntl` against `jp`, then burns them; returns the plan
+    fn plan_and_burn(jp: &mut EfuseModelPhy, cntl: u8) -> BurnPlan {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, jp).unwrap();
+        efuse.set_key(test_key(0xC3));
+        efuse.set_user(USER);
+        efuse.set_cntl(cntl).unwrap();
+        let plan: BurnPlan = efuse.plan().unwrap();
+        assert_eq!(jp.programmed().len(), 0);
+        efuse.burn(&mut jm, jp).unwrap();
+        plan
+    }
+
+    #[test]
+    fn burn_blows_exactly_the_plan() {
+        let mut jp = EfuseModelPhy::new();
+        let plan: BurnPlan = plan_and_burn(&mut jp, CNTL_W_EN_B_KEY_USER);
+        assert_eq!(plan.bits, banks_image_ecc(&test_key(0xC3), USER, CNTL_W_EN_B_KEY_USER));
+        assert_eq!(blown(jp.programmed()), plan.bits);
+        assert_eq!(jp.programmed().len(), plan.pulses as usize);
+        assert_eq!(plan.banks().map(|(bank, _)| bank).collect::<Vec<usize>>(), (0..FUSE_BANKS).rev().collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn a_patch_plans_only_the_new_fuses() {
+        // key and USER already fused, only CNTL to come
+        let before = banks_image_ecc(&test_key(0xC3), USER, 0);
+        let mut jp = EfuseModelPhy::with_banks(before);
+        let plan: BurnPlan = plan_and_burn(&mut jp, CNTL_CFG_AES_ONLY);
+        assert_eq!(plan.banks().collect::<Vec<(usize, u32)>>(), [(CNTL_BANK, (CNTL_CFG_AES_ONLY as u32) * (1 | 1 << CNTL_COPY_SHIFT))]);
+        assert_eq!(plan.pulses, 2);
+        assert_eq!(blown(jp.programmed()), plan.bits);
+    }
+
+    #[test]
+    fn refused_like_validate() {
+        let mut banks = [0u32; FUSE_BANKS];
+        banks[USER_BANK] = 0x10;
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.load_snapshot(&FuseSnapshot { banks });
+        efuse.set_user(USER);
+        assert_eq!(efuse.plan(), Err(efuse.validate().unwrap_err()));
+    }
+
+    #[test]
+    fn display() {
+        let mut bits = [0u32; FUSE_BANKS];
+        bits[CNTL_BANK] = 0x0000_4001;
+        bits[7] = 0x0300_0080;
+        assert_eq!(format!("{}", BurnPlan::new(bits)), "bank  7: 0x03000080, 3 fuses\nbank  0: 0x00004001, 2 fuses\n5 pulses");
+        assert_eq!(format!("{}", BurnPlan::new([0; FUSE_BANKS])), "0 pulses");
+    }
+}
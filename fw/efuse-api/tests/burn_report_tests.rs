@@ -0,0 +1,134 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::messages::*;
+    use efuse_api::sequences::*;
+    use efuse_api::test_utils::*;
+    use efuse_api::verify::*;
+
+    const USER: u32 = 0x0042_4242;
+
+    fn staged() -> EfuseApi {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.set_key(test_key(0x1C));
+        efuse.set_user(USER);
+        efuse
+    }
+
+    #[test]
+    fn counts_match_the_plan() {
+        let mut jp = EfuseModelPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = staged();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        let plan: BurnPlan = efuse.plan().unwrap();
+
+        let report: BurnReport = efuse.burn_report(&mut jm, &mut jp).unwrap();
+        assert_eq!(report.requested, plan.bits);
+        assert_eq!(report.programmed, plan.bits);
+        assert_eq!(report.pulses, plan.pulses);
+        assert_eq!(report.pulses as usize, jp.programmed().len());
+        assert!(report.committed);
+        assert_eq!(report.verify, None);
+        assert_eq!(efuse.last_report(), Some(report));
+
+        efuse.verify_burn(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.last_report().unwrap().verify, Some(VerifySummary { missing: 0, stray: 0 }));
+    }
+
+    #[test]
+    fn a_fuse_that_does_not_blow() {
+        let mut jp = EfuseModelPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = staged();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        let plan: BurnPlan = efuse.plan().unwrap();
+        let bit: u32 = plan.bits[4] & plan.bits[4].wrapping_neg() & 0xFF_FFFF;
+        jp.stick(4, bit);
+
+        let outcome: VerificationOutcome = efuse.burn_and_verify(&mut jm, &mut jp).unwrap();
+        let report: BurnReport = efuse.last_report().unwrap();
+        // it had its pulse all the same
+        assert_eq!(report.programmed, plan.bits);
+        assert_eq!(report.verify, Some(outcome.summary()));
+        assert_eq!(report.verify, Some(VerifySummary { missing: 1, stray: 0 }));
+        assert!(!report.verify.unwrap().is_clean());
+    }
+
+    /// Fails every cycle once `left` have gone through to the model
+    struct Dropping {
+        model: EfuseModelPhy,
+        left: usize,
+    }
+
+    impl JtagPhy for Dropping {
+        fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+            self.try_sync(tdi, tms).unwrap_or(false)
+        }
+
+        fn nosync(&mut self, tdi: bool, tms: bool, tck: bool) -> bool {
+            self.model.nosync(tdi, tms, tck)
+        }
+
+        fn pause(&mut self, us: u32) {
+            self.model.pause(us);
+        }
+
+        fn try_sync(&mut self, tdi: bool, tms: bool) -> Result<bool, PhyError> {
+            if self.left == 0 {
+                return Err(PhyError::Transport);
+            }
+            self.left -= 1;
+            self.model.try_sync(tdi, tms)
+        }
+    }
+
+    #[test]
+    fn a_burn_cut_short() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = staged();
+        let mut model = EfuseModelPhy::new();
+        efuse.fetch(&mut jm, &mut model).unwrap();
+        let plan: BurnPlan = efuse.plan().unwrap();
+        let cycles: usize = efuse.estimate_burn_duration().cycles as usize;
+
+        // the cable goes halfway through the programming
+        let mut jp = Dropping { model, left: cycles / 2 };
+        assert!(efuse.burn_report(&mut jm, &mut jp).is_err());
+        let report: BurnReport = efuse.last_report().unwrap();
+        assert!(!report.committed);
+        assert!(report.pulses > 0 && report.pulses < plan.pulses, "{}", report.pulses);
+        assert_eq!(report.pulses as usize, jp.model.programmed().len());
+        assert_eq!(report.programmed.iter().map(|b| b.count_ones()).sum::<u32>(), report.pulses);
+        assert!(report.programmed.iter().zip(plan.bits.iter()).all(|(&done, &planned)| done & !planned == 0));
+    }
+
+    #[test]
+    fn printed() {
+        let mut requested: [u32; FUSE_BANKS] = [0; FUSE_BANKS];
+        requested[USER_BANK] = 0x0300_0011;
+        requested[2] = 0x1;
+        let mut programmed: [u32; FUSE_BANKS] = requested;
+        programmed[2] = 0;
+        let report = BurnReport {
+            requested,
+            committed: false,
+            weak_key_overridden: false,
+            order: BitOrderPolicy::Ascending,
+            manifest: None,
+            status_errors: [0; FUSE_BANKS],
+            boot_check: efuse_api::boot::BootCheck::NotRequired,
+            pre_burn: None,
+            programmed,
+            pulses: 4,
+            verify: None,
+        };
+        assert_eq!(report.to_string(), "bank 12: 0x03000011 requested, 0x03000011 programmed\n\
+            bank  2: 0x00000001 requested, 0x00000000 programmed\n\
+            4 pulses, not committed, not verified");
+        let report = BurnReport { committed: true, verify: Some(VerifySummary { missing: 0, stray: 2 }), ..report };
+        assert!(report.to_string().ends_with("4 pulses, committed, verified: 0 missing, 2 stray"));
+    }
+}
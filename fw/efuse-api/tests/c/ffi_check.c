@@ -0,0 +1,23 @@
+/* Compiled by ffi_tests.rs to check that efuse_api.h is usable from C */
+#include "efuse_api.h"
+
+int plan(const uint8_t *snapshot, size_t snapshot_len, const uint8_t key[32], uint32_t user, uint8_t cntl) {
+    uint8_t user_bytes[4] = { user & 0xFF, (user >> 8) & 0xFF, (user >> 16) & 0xFF, user >> 24 };
+    char report[256];
+    uint8_t words[4096];
+    size_t written = 0;
+    int32_t status;
+
+    EfusePlanner *planner = efuse_planner_new();
+    if (planner == NULL) {
+        return EFUSE_ERR_NULL;
+    }
+    status = efuse_planner_load_snapshot(planner, snapshot, snapshot_len);
+    if (status == EFUSE_OK) status = efuse_planner_stage_key(planner, key, 32);
+    if (status == EFUSE_OK) status = efuse_planner_stage_user(planner, user_bytes, sizeof(user_bytes));
+    if (status == EFUSE_OK) status = efuse_planner_stage_cntl(planner, &cntl, 1);
+    if (status == EFUSE_OK) status = efuse_planner_validate(planner, (uint8_t *)report, sizeof(report), &written);
+    if (status == EFUSE_OK) status = efuse_planner_burn_words(planner, words, sizeof(words), &written);
+    efuse_planner_free(planner);
+    return status;
+}
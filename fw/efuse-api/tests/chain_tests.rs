@@ -0,0 +1,146 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::chain::*;
+    use efuse_api::layout::*;
+    use efuse_api::messages::*;
+    use efuse_api::sequences::*;
+    use efuse_api::test_utils::*;
+
+    const IDCODE: u32 = 0x1362_D093;
+    const UNITS: usize = 4;
+
+    fn dna(unit: usize) -> u64 {
+        0x0040_0000_1234_0000 | unit as u64
+    }
+
+    fn key(unit: usize) -> [u8; 32] {
+        let mut key = [0u8; 32];
+        for (i, k) in key.iter_mut().enumerate() {
+            *k = (i as u8).wrapping_mul(37) ^ (0x51 + unit as u8);
+        }
+        key
+    }
+
+    fn manifest(unit: usize) -> TargetManifest {
+        TargetManifest {
+            provisioning: ProvisioningManifest { key: key(unit), user: 0x0100 + unit as u32, cntl: 0 },
+            idcode: IDCODE,
+            dna: Some(dna(unit)),
+        }
+    }
+
+    /// a panel of blank units; unit n is device n on the chain
+    fn panel() -> ChainPhy<EfuseModelPhy> {
+        let units: Vec<EfuseModelPhy> = (0..UNITS).map(|unit| {
+            let mut jp = EfuseModelPhy::new();
+            // a later silicon revision than the manifests name
+            jp.set_idcode(IDCODE | 0x2000_0000);
+            jp.set_dna(dna(unit));
+            jp
+        }).collect();
+        ChainPhy::new(units)
+    }
+
+    #[test]
+    fn padding() {
+        let chain = ScanChain::new(&[6, 4, 6]);
+        assert_eq!(chain.padding(ChainTarget { index: 0 }),
+            Some(ChainPadding { ir_lead: 10, ir_trail: 0, dr_lead: 2, dr_trail: 0 }));
+        assert_eq!(chain.padding(ChainTarget { index: 1 }),
+            Some(ChainPadding { ir_lead: 6, ir_trail: 6, dr_lead: 1, dr_trail: 1 }));
+        assert_eq!(chain.padding(ChainTarget { index: 2 }),
+            Some(ChainPadding { ir_lead: 0, ir_trail: 10, dr_lead: 0, dr_trail: 2 }));
+        assert_eq!(chain.padding(ChainTarget { index: 3 }), None);
+        assert_eq!(ScanChain::uniform(1, IR_BITS).padding(ChainTarget { index: 0 }), Some(ChainPadding::NONE));
+    }
+
+    #[test]
+    fn only_the_target_sees_efuse_instructions() {
+        let chain = ScanChain::uniform(UNITS, IR_BITS);
+        let mut jp = panel();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.apply_manifest(&chain, ChainTarget { index: 1 }, &manifest(1), &mut jm, &mut jp).unwrap();
+        assert_eq!(jm.padding(), ChainPadding::NONE);
+        assert!(efuse.last_report().unwrap().committed);
+
+        let unit = &jp.devices()[1];
+        assert_eq!(unit.banks(), banks_image_ecc(&key(1), 0x0101, 0));
+        assert_eq!(unit.rejected(), 0);
+        assert_eq!(unit.commits(), 1);
+        assert!(unit.ir_history().contains(&Ir::FuseCts.code()));
+        for (index, bystander) in jp.devices().iter().enumerate().filter(|&(index, _)| index != 1) {
+            assert!(bystander.ir_history().iter().all(|&ir| ir == Ir::Bypass.code()), "unit {}", index);
+            assert!(!bystander.ir_history().is_empty());
+            assert!(bystander.programmed().is_empty());
+            assert_eq!(bystander.rejected(), 0);
+            assert_eq!(bystander.commits(), 0);
+            assert_eq!(bystander.banks(), [0; FUSE_BANKS]);
+        }
+    }
+
+    #[test]
+    fn whole_panel_in_turn() {
+        let chain = ScanChain::uniform(UNITS, IR_BITS);
+        let mut jp = panel();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        for unit in 0..UNITS {
+            efuse.apply_manifest(&chain, ChainTarget { index: unit }, &manifest(unit), &mut jm, &mut jp).unwrap();
+        }
+        for (unit, device) in jp.devices().iter().enumerate() {
+            assert_eq!(device.banks(), banks_image_ecc(&key(unit), 0x0100 + unit as u32, 0));
+            assert_eq!(device.commits(), 1);
+            assert_eq!(device.rejected(), 0);
+        }
+    }
+
+    #[test]
+    fn refuses_the_wrong_unit() {
+        let chain = ScanChain::uniform(UNITS, IR_BITS);
+        let mut jp = panel();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+
+        // unit 3's manifest aimed at unit 2
+        assert_eq!(efuse.apply_manifest(&chain, ChainTarget { index: 2 }, &manifest(3), &mut jm, &mut jp),
+            Err(EfuseError::WrongDevice { idcode: IDCODE | 0x2000_0000, dna: dna(2) }));
+        // another part altogether
+        let other = TargetManifest { idcode: 0x0362_7093, dna: None, ..manifest(2) };
+        assert_eq!(efuse.apply_manifest(&chain, ChainTarget { index: 2 }, &other, &mut jm, &mut jp),
+            Err(EfuseError::WrongDevice { idcode: IDCODE | 0x2000_0000, dna: dna(2) }));
+        assert_eq!(efuse.apply_manifest(&chain, ChainTarget { index: UNITS }, &manifest(2), &mut jm, &mut jp),
+            Err(EfuseError::NoSuchTarget { index: UNITS, devices: UNITS }));
+
+        assert_eq!(jm.padding(), ChainPadding::NONE);
+        assert!(jp.devices().iter().all(|device| device.programmed().is_empty()));
+        assert_eq!(efuse.last_report(), None);
+    }
+
+    #[test]
+    fn fetch_and_burn_behind_another_device() {
+        // a debug pod: the unit's FPGA is the second device on the chain, the one nearer TDO
+        let mut jp = ChainPhy::new(vec![EfuseModelPhy::new(), EfuseModelPhy::new()]);
+        jp.device_mut(1).set_idcode(IDCODE);
+        let mut jm: JtagMach = JtagMach::new();
+        jm.set_padding(ChainPadding::around(&[IR_BITS], &[]));
+
+        // the plain calls, with nothing told of the chain but the JtagMach
+        let mut efuse: EfuseApi = EfuseApi::new();
+        assert_eq!(efuse.read_idcode(&mut jm, &mut jp), Ok(IDCODE));
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(key(1));
+        efuse.set_user(0x0101);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert_eq!(jp.devices()[1].banks(), banks_image_ecc(&key(1), 0x0101, 0));
+        assert!(jp.devices()[0].ir_history().iter().all(|&ir| ir == Ir::Bypass.code()));
+        assert_eq!(jp.devices()[0].banks(), [0; FUSE_BANKS]);
+
+        let mut check: EfuseApi = EfuseApi::new();
+        check.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(check.phy_key(), key(1));
+        assert_eq!(check.phy_user(), 0x0101);
+    }
+}
@@ -0,0 +1,115 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::test_utils::*;
+
+    const USER: u32 = 0x5EED_0123;
+    const CNTL: u8 = CNTL_CFG_AES_ONLY | CNTL_W_EN_B_KEY_USER;
+
+    /// a device provisioned with test_key(0x6E), USER and CNTL, whose CNTL copies hold `primary` and
+    /// `redundant`
+    fn device(primary: u8, redundant: u8) -> EfuseModelPhy {
+        let mut banks: [u32; FUSE_BANKS] = banks_image_ecc(&test_key(0x6E), USER, 0);
+        banks[CNTL_BANK] = CntlCopy::Primary.deposit(primary) | CntlCopy::Redundant.deposit(redundant);
+        EfuseModelPhy::with_banks(banks)
+    }
+
+    /// an EfuseApi that has fetched `jp`, staging what it read
+    fn fetched(jm: &mut JtagMach, jp: &mut EfuseModelPhy) -> EfuseApi {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(jm, jp).unwrap();
+        efuse.set_key(efuse.phy_key());
+        efuse.set_user(efuse.phy_user());
+        efuse.set_cntl(efuse.phy_cntl()).unwrap();
+        efuse
+    }
+
+    fn bits(mask: u32) -> Vec<u8> {
+        (0..32).filter(|&bit| mask & (1 << bit) != 0).collect()
+    }
+
+    #[test]
+    fn the_redundant_copy_failed_to_program() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = device(CNTL, 0);
+        let mut efuse = fetched(&mut jm, &mut jp);
+        // a fetch doesn't reach the redundant copy
+        assert_eq!(efuse.cntl_copies(), (CNTL, CNTL));
+        assert_eq!(efuse.validate().unwrap().warnings, []);
+
+        assert_eq!(efuse.read_cntl_copies(&mut jm, &mut jp), Ok((CNTL, 0)));
+        assert_eq!(efuse.cntl_copies(), (CNTL, 0));
+        assert_eq!(efuse.phy_cntl(), CNTL);
+        assert_eq!(efuse.validate().unwrap().warnings, [ValidationWarning::CntlCopyMismatch { primary: CNTL, redundant: 0 }]);
+
+        // only the redundant copy's bits are programmed, with a commit of their own
+        efuse.repair_cntl(&mut jm, &mut jp).unwrap();
+        let programmed: Vec<u8> = jp.programmed().iter().map(|&(bank, bit)| { assert_eq!(bank, CNTL_BANK); bit }).collect();
+        assert_eq!(programmed, bits(CntlCopy::Redundant.deposit(CNTL)));
+        assert_eq!(jp.commits(), 1);
+        assert_eq!(jp.banks()[CNTL_BANK], CntlCopy::Primary.deposit(CNTL) | CntlCopy::Redundant.deposit(CNTL));
+        let report = efuse.last_report().unwrap();
+        assert!(report.committed);
+        assert_eq!(report.requested[CNTL_BANK], CntlCopy::Redundant.deposit(CNTL));
+
+        assert_eq!(efuse.read_cntl_copies(&mut jm, &mut jp), Ok((CNTL, CNTL)));
+        assert_eq!(efuse.validate().unwrap().warnings, []);
+    }
+
+    #[test]
+    fn the_primary_copy_is_behind() {
+        // the redundant copy alone has R_EN_B_KEY, which is enough to take the key readback
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = device(CNTL, CNTL | CNTL_R_EN_B_KEY);
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.fetch_report().key, KeyReadback::Readable);
+
+        assert_eq!(efuse.read_cntl_copies(&mut jm, &mut jp), Ok((CNTL, CNTL | CNTL_R_EN_B_KEY)));
+        assert_eq!(efuse.phy_cntl(), CNTL | CNTL_R_EN_B_KEY);
+        assert_eq!(efuse.fetch_report().key, KeyReadback::Unreadable);
+        assert_eq!(efuse.phy_key(), [0; 32]);
+        assert_eq!(efuse.phy_user(), USER);
+
+        efuse.repair_cntl(&mut jm, &mut jp).unwrap();
+        assert_eq!(jp.programmed(), [(CNTL_BANK, 4)]);
+        assert_eq!(efuse.read_cntl_copies(&mut jm, &mut jp), Ok((CNTL | CNTL_R_EN_B_KEY, CNTL | CNTL_R_EN_B_KEY)));
+    }
+
+    #[test]
+    fn copies_that_agree_are_left_alone() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        let mut jp = device(CNTL, CNTL);
+        assert_eq!(efuse.repair_cntl(&mut jm, &mut jp), Err(EfuseError::NotFetched));
+        assert_eq!(efuse.read_cntl_copies(&mut jm, &mut jp), Err(EfuseError::NotFetched));
+        assert!(jp.ir_history().is_empty());
+
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.repair_cntl(&mut jm, &mut jp).unwrap();
+        assert_eq!(jp.programmed(), []);
+        assert_eq!(jp.commits(), 0);
+        assert_eq!(efuse.last_report(), None);
+    }
+
+    #[test]
+    fn a_repair_that_doesnt_take() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = device(CNTL, CNTL_CFG_AES_ONLY);
+        jp.stick(CNTL_BANK, CntlCopy::Redundant.deposit(CNTL_W_EN_B_KEY_USER));
+        let mut efuse = fetched(&mut jm, &mut jp);
+        assert_eq!(efuse.repair_cntl(&mut jm, &mut jp),
+            Err(EfuseError::CntlVerify { copy: CntlCopy::Redundant, expected: CNTL, read: CNTL_CFG_AES_ONLY }));
+        assert!(!efuse.last_report().unwrap().committed);
+        assert_eq!(efuse.cntl_copies(), (CNTL, CNTL_CFG_AES_ONLY));
+
+        // a full burn would have programmed the same bits
+        let mut jp = device(CNTL, CNTL_CFG_AES_ONLY);
+        let mut efuse = fetched(&mut jm, &mut jp);
+        efuse.read_cntl_copies(&mut jm, &mut jp).unwrap();
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert_eq!(jp.banks()[CNTL_BANK], CntlCopy::Primary.deposit(CNTL) | CntlCopy::Redundant.deposit(CNTL));
+    }
+}
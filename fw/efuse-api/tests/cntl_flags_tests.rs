@@ -0,0 +1,83 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::test_utils::*;
+
+    const NAMED: [(CntlFlags, u8); 5] = [
+        (CntlFlags::CFG_AES_ONLY, CNTL_CFG_AES_ONLY),
+        (CntlFlags::AES_EXCLUSIVE, CNTL_AES_EXCLUSIVE),
+        (CntlFlags::W_EN_B_KEY_USER, CNTL_W_EN_B_KEY_USER),
+        (CntlFlags::R_EN_B_KEY, CNTL_R_EN_B_KEY),
+        (CntlFlags::R_EN_B_USER, CNTL_R_EN_B_USER),
+    ];
+
+    #[test]
+    fn named_bits() {
+        for &(flag, bit) in NAMED.iter() {
+            assert_eq!(flag.bits(), bit);
+            assert_eq!(u8::from(flag), bit);
+        }
+        assert_eq!(CntlFlags::all().bits(), 0x3E);
+        assert_eq!(CntlFlags::empty().bits(), 0);
+        assert_eq!(CntlFlags::default(), CntlFlags::empty());
+    }
+
+    #[test]
+    fn round_trip() {
+        for raw in 0..=u8::MAX {
+            match CntlFlags::from_bits(raw) {
+                Some(flags) => assert_eq!(flags.bits(), raw),
+                None => assert_ne!(raw & !0x3E, 0),
+            }
+            assert_eq!(CntlFlags::from_bits_truncate(raw).bits(), raw & 0x3E);
+        }
+        for flags in (0..=u8::MAX).filter_map(CntlFlags::from_bits) {
+            assert_eq!(CntlFlags::from_bits(flags.bits()), Some(flags));
+        }
+    }
+
+    #[test]
+    fn reserved_bits_cant_be_named() {
+        assert_eq!(CntlFlags::from_bits(0x01), None);
+        assert_eq!(CntlFlags::from_bits(0x40 | CNTL_R_EN_B_KEY), None);
+        assert_eq!(CntlFlags::from_bits_truncate(0xFF), CntlFlags::all());
+    }
+
+    #[test]
+    fn per_bit_setters() {
+        let mut flags = CntlFlags::empty();
+        flags.set(CntlFlags::CFG_AES_ONLY, true);
+        flags.insert(CntlFlags::R_EN_B_KEY);
+        flags |= CntlFlags::W_EN_B_KEY_USER;
+        assert_eq!(flags, CntlFlags::CFG_AES_ONLY | CntlFlags::R_EN_B_KEY | CntlFlags::W_EN_B_KEY_USER);
+        assert!(flags.contains(CntlFlags::CFG_AES_ONLY | CntlFlags::R_EN_B_KEY));
+        assert!(!flags.contains(CntlFlags::R_EN_B_USER));
+
+        flags.set(CntlFlags::R_EN_B_KEY, false);
+        flags.remove(CntlFlags::W_EN_B_KEY_USER);
+        assert_eq!(flags, CntlFlags::CFG_AES_ONLY);
+        assert_eq!(CntlFlags::all() - flags, CntlFlags::from_bits(0x3C).unwrap());
+        assert_eq!(CntlFlags::all() & flags, flags);
+        assert!((flags - CntlFlags::CFG_AES_ONLY).is_empty());
+    }
+
+    #[test]
+    fn staged_and_fused() {
+        let flags = CntlFlags::CFG_AES_ONLY | CntlFlags::R_EN_B_USER;
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.set_cntl_flags(flags);
+        assert_eq!(efuse.api_cntl(), CNTL_CFG_AES_ONLY | CNTL_R_EN_B_USER);
+        assert_eq!(efuse.api_cntl_flags(), flags);
+        efuse.set_cntl(CNTL_W_EN_B_KEY_USER).unwrap();
+        assert_eq!(efuse.api_cntl_flags(), CntlFlags::W_EN_B_KEY_USER);
+
+        let mut banks = [0u32; FUSE_BANKS];
+        banks[CNTL_BANK] = bank_image(CNTL_BANK, &[0; 32], 0, flags.bits());
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::with_banks(banks);
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.phy_cntl_flags(), flags);
+    }
+}
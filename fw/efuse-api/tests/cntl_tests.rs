@@ -0,0 +1,97 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::sequences::*;
+    use efuse_api::test_utils::*;
+
+    const CNTL: u8 = CNTL_CFG_AES_ONLY | CNTL_W_EN_B_KEY_USER | CNTL_R_EN_B_KEY;
+
+    /// an EfuseApi that has burned test_key(0xA5) and USER onto `jp`, with CNTL staged on top
+    fn provisioned(jm: &mut JtagMach, jp: &mut EfuseModelPhy) -> EfuseApi {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(jm, jp).unwrap();
+        efuse.set_key(test_key(0xA5));
+        efuse.set_user(0x0BAD_F00D);
+        efuse.burn(jm, jp).unwrap();
+        efuse.verify_boot(&mut || Ok(())).unwrap();
+        efuse.fetch(jm, jp).unwrap();
+        efuse.set_cntl(CNTL).unwrap();
+        efuse
+    }
+
+    #[test]
+    fn program_cntl_words() {
+        let params = DeviceParams::default();
+        let words: Vec<ProgramWord> = program_cntl(CNTL, &params).collect();
+        let bits: Vec<u8> = words.iter().filter_map(|w| match w.kind { WordKind::Bit(b) => Some(b), _ => None }).collect();
+        assert_eq!(bits, [1, 3, 4, 15, 17, 18]);
+        // each copy has its own bracket around its bits
+        let primary: Vec<ProgramWord> = dr_words_for_bank(CNTL_BANK, CNTL as u32, &params).collect();
+        let redundant: Vec<ProgramWord> = dr_words_for_bank(CNTL_BANK, (CNTL as u32) << CNTL_COPY_SHIFT, &params).collect();
+        assert_eq!(words, [primary, redundant].concat());
+        assert_eq!(words[2].value, params.bank_word(CNTL_BANK));
+        assert_eq!(program_cntl(0, &params).count(), 0);
+        // only CNTL bits are programmed
+        assert_eq!(program_cntl_copy(0xFF, CntlCopy::Redundant, &params).filter(|w| matches!(w.kind, WordKind::Bit(_))).count(), 6);
+    }
+
+    #[test]
+    fn burns_and_verifies_both_copies() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse = provisioned(&mut jm, &mut jp);
+        let before: usize = jp.programmed().len();
+        efuse.burn_cntl(&mut jm, &mut jp).unwrap();
+
+        let cntl_bits: Vec<u8> = jp.programmed()[before..].iter().map(|&(bank, bit)| { assert_eq!(bank, CNTL_BANK); bit }).collect();
+        assert_eq!(cntl_bits, [1, 3, 4, 15, 17, 18]);
+        assert_eq!(jp.banks()[CNTL_BANK], (CNTL as u32) | ((CNTL as u32) << CNTL_COPY_SHIFT));
+        // one commit per copy
+        assert_eq!(jp.commits(), 3);
+        assert_eq!(jp.rejected(), 0);
+        let report = efuse.last_report().unwrap();
+        assert!(report.committed);
+        assert_eq!(report.requested[CNTL_BANK], bank_fuses(CNTL_BANK) & ((CNTL as u32) * (1 | (1 << CNTL_COPY_SHIFT))));
+
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.phy_cntl(), CNTL);
+    }
+
+    #[test]
+    fn failed_copy_is_named() {
+        // the redundant copy of W_EN_B_KEY_USER won't blow
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse = provisioned(&mut jm, &mut jp);
+        jp.stick(CNTL_BANK, (CNTL_W_EN_B_KEY_USER as u32) << CNTL_COPY_SHIFT);
+        assert_eq!(efuse.burn_cntl(&mut jm, &mut jp),
+            Err(EfuseError::CntlVerify { copy: CntlCopy::Redundant, expected: CNTL, read: CNTL & !CNTL_W_EN_B_KEY_USER }));
+        assert!(!efuse.last_report().unwrap().committed);
+        assert_eq!(jp.tap(), TapState::TestLogicReset);
+
+        // the primary copy fails: the redundant copy is left alone
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse = provisioned(&mut jm, &mut jp);
+        jp.stick(CNTL_BANK, CNTL_R_EN_B_KEY as u32);
+        let before: usize = jp.programmed().len();
+        assert_eq!(efuse.burn_cntl(&mut jm, &mut jp),
+            Err(EfuseError::CntlVerify { copy: CntlCopy::Primary, expected: CNTL, read: CNTL & !CNTL_R_EN_B_KEY }));
+        assert!(jp.programmed()[before..].iter().all(|&(_, bit)| (bit as u32) < CNTL_COPY_SHIFT));
+        assert_eq!(jp.banks()[CNTL_BANK] >> CNTL_COPY_SHIFT, 0);
+    }
+
+    #[test]
+    fn cntl_goes_last() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(test_key(0xA5));
+        efuse.set_cntl(CNTL).unwrap();
+        assert_eq!(efuse.burn_cntl(&mut jm, &mut jp), Err(EfuseError::CntlNotLast));
+        assert!(jp.programmed().is_empty());
+        assert!(efuse.last_report().is_none());
+    }
+}
@@ -0,0 +1,78 @@
+#[cfg(test)]
+mod tests {
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::messages::*;
+    use efuse_api::test_utils::*;
+
+    const USER: u32 = 0x00A5_0F3C;
+
+    /// planned against test_key(0x81), USER and `cntl` fused
+    fn planner(cntl: u8) -> EfuseApi {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.load_snapshot(&FuseSnapshot { banks: banks_image_ecc(&test_key(0x81), USER, cntl) });
+        efuse.set_key(test_key(0x81));
+        efuse.set_user(USER);
+        efuse.set_cntl(cntl).unwrap();
+        efuse
+    }
+
+    #[test]
+    fn nothing_to_clear() {
+        let efuse = planner(CNTL_CFG_AES_ONLY);
+        assert!(efuse.conflicts().is_empty());
+        assert_eq!(efuse.conflicts().first(), None);
+        assert_eq!(efuse.conflicts().shared(), None);
+    }
+
+    #[test]
+    fn data_legal_but_ecc_illegal_key_patch() {
+        let mut efuse = planner(0);
+        // a blank data bit of bank 3 whose ECC code would drop a blown ECC bit
+        let fused: u32 = bank_image_ecc(3, &test_key(0x81), USER, 0);
+        let bit: u32 = (0..24)
+            .filter(|&bit| fused & (1 << bit) == 0)
+            .find(|&bit| fused & !bank_image_ecc(3, &patched(bit), USER, 0) != 0)
+            .unwrap();
+        efuse.set_key(patched(bit));
+
+        let conflicts: TransitionConflicts = efuse.conflicts();
+        assert_eq!(conflicts.banks[3].data, 0);
+        assert_ne!(conflicts.banks[3].ecc, 0);
+        assert_eq!(conflicts.banks[3].ecc & !0x3F00_0000, 0);
+        assert_eq!(conflicts.first(), Some((3, conflicts.banks[3].ecc)));
+        assert!(conflicts.banks.iter().enumerate().all(|(index, bank)| index == 3 || bank.mask() == 0));
+        assert_eq!(efuse.validate().err(), Some(EfuseError::IllegalTransition { bank: 3, mask: conflicts.banks[3].ecc }));
+        assert!(!efuse.is_valid());
+    }
+
+    /// test_key(0x81) with data bit `bit` of bank 3 set
+    fn patched(bit: u32) -> [u8; 32] {
+        let mut key = test_key(0x81);
+        key[6 + (bit / 8) as usize] |= 1 << (bit % 8);
+        key
+    }
+
+    #[test]
+    fn shared_bank_by_owner() {
+        let mut efuse = planner(0);
+        let mut staged = test_key(0x81);
+        staged[30] = 0;
+        efuse.set_key(staged);
+        efuse.set_user(0);
+
+        let shared: SharedConflict = efuse.conflicts().shared().unwrap();
+        assert_eq!(shared.key, (test_key(0x81)[30] as u32) & 0xFFFF);
+        assert_eq!(shared.user, (USER & 0xFF) << 16);
+        assert_eq!(efuse.conflicts().banks[USER_BANK].data, USER >> 8);
+    }
+
+    #[test]
+    fn cntl_copies_are_data() {
+        let mut efuse = planner(CNTL_W_EN_B_KEY_USER);
+        efuse.set_cntl(0).unwrap();
+        let cntl: BankConflict = efuse.conflicts().banks[CNTL_BANK];
+        assert_eq!(cntl, BankConflict { data: (CNTL_W_EN_B_KEY_USER as u32) * (1 | 1 << CNTL_COPY_SHIFT), ecc: 0 });
+        assert_eq!(efuse.conflicts().first(), Some((CNTL_BANK, cntl.data)));
+    }
+}
@@ -0,0 +1,78 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::test_utils::*;
+
+    fn fetched(user: u32) -> EfuseApi {
+        let mut jp = EfuseModelPhy::with_banks(banks_image_ecc(&[0x3C; 32], user, 0));
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.phy_user(), user);
+        efuse
+    }
+
+    #[test]
+    fn user_views_match() {
+        let mut user: u32 = 0x1;
+        for _ in 0..40 {
+            assert_eq!(fetched(user).fetch_report().user, UserConsistency::Match, "user {:08x}", user);
+            user = user.wrapping_mul(0x9E37_79B9).wrapping_add(0x7F4A_7C15);
+        }
+        assert_eq!(fetched(0).fetch_report().user, UserConsistency::Match);
+        assert_eq!(fetched(0xFFFF_FFFF).fetch_report().user, UserConsistency::Match);
+    }
+
+    #[test]
+    fn provisioned_banks_round_trip() {
+        let banks = banks_image_ecc(&[0xA7; 32], 0x5EED_0042, CNTL_W_EN_B_KEY_USER);
+        let mut jp = EfuseModelPhy::with_banks(banks);
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.snapshot().banks, banks);
+        assert!(efuse.phy_banks_logical().iter().all(|view| view.is_consistent()));
+    }
+
+    #[test]
+    fn ecc_fuses_dont_read_back() {
+        let mut banks = banks_image_ecc(&[0xA7; 32], 0, 0);
+        let lone: u32 = !banks[3] & 0x3F00_0000;
+        let lone: u32 = lone & lone.wrapping_neg();
+        banks[3] |= lone;
+        let mut jp = EfuseModelPhy::with_banks(banks);
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        // only the data bits come out, so the image is what they encode to
+        assert_eq!(efuse.snapshot().banks[3], banks[3] & !lone);
+    }
+
+    #[test]
+    fn mismatch_blocks_user_changes() {
+        let mut efuse = fetched(0x0000_1200);
+        // the user bank now decodes differently from what FUSE_USER reported
+        efuse.bank_patch(USER_BANK, 0);
+        assert_eq!(efuse.fetch_report().user, UserConsistency::Mismatch { direct: 0x0000_1200, derived: 0 });
+
+        efuse.stage(&efuse.manifest()).unwrap();
+        efuse.set_key([0x3C; 32]);
+        efuse.set_user(0x0000_5200);
+        assert_eq!(efuse.validate(), Err(EfuseError::UserMismatch { direct: 0x0000_1200, derived: 0 }));
+        let mut jp = EfuseModelPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        assert_eq!(efuse.burn(&mut jm, &mut jp), Err(EfuseError::UserMismatch { direct: 0x0000_1200, derived: 0 }));
+        assert_eq!(jp.cycles(), 0);
+
+        // leaving USER alone is still fine
+        efuse.set_user(0x0000_1200);
+        assert!(efuse.validate().is_ok());
+
+        // as is changing it with the override set
+        efuse.set_user(0x0000_5200);
+        efuse.allow_user_mismatch(true);
+        assert!(efuse.validate().is_ok());
+    }
+}
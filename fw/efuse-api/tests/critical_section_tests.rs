@@ -0,0 +1,122 @@
+#![cfg(feature = "critical-section")]
+
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::test_utils::*;
+    use efuse_api::test_utils::mock_cs::*;
+    use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering::SeqCst};
+    use std::sync::Mutex;
+
+    static CYCLES_OUTSIDE: AtomicU64 = AtomicU64::new(0);
+    static PAUSES_INSIDE: AtomicUsize = AtomicUsize::new(0);
+    /// the counters are global, so the tests take turns
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    /// The model phy, with every cycle and pause checked against the mock
+    struct WatchedPhy(EfuseModelPhy);
+
+    impl JtagPhy for WatchedPhy {
+        fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+            CYCLES.fetch_add(1, SeqCst);
+            if DEPTH.load(SeqCst) == 0 {
+                CYCLES_OUTSIDE.fetch_add(1, SeqCst);
+            }
+            self.0.sync(tdi, tms)
+        }
+        fn nosync(&mut self, tdi: bool, tms: bool, tck: bool) -> bool {
+            self.0.nosync(tdi, tms, tck)
+        }
+        fn pause(&mut self, us: u32) {
+            if DEPTH.load(SeqCst) != 0 {
+                PAUSES_INSIDE.fetch_add(1, SeqCst);
+            }
+            self.0.pause(us)
+        }
+    }
+
+    #[derive(Debug)]
+    struct Stats {
+        cycles: u64,
+        sections: usize,
+        max_depth: usize,
+        longest: u64,
+        cycles_outside: u64,
+        pauses_inside: usize,
+    }
+
+    fn burn(config: BurnConfig) -> (EfuseModelPhy, Stats) {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let mut jp = WatchedPhy(EfuseModelPhy::new());
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(test_key(0xE1));
+        efuse.set_user(0x00C0_FFEE);
+        efuse.set_burn_config(config);
+
+        for counter in [&CYCLES, &START, &LONGEST, &CYCLES_OUTSIDE].iter() {
+            counter.store(0, SeqCst);
+        }
+        for counter in [&DEPTH, &MAX_DEPTH, &SECTIONS, &PAUSES_INSIDE].iter() {
+            counter.store(0, SeqCst);
+        }
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        let stats = Stats {
+            cycles: CYCLES.load(SeqCst),
+            sections: SECTIONS.load(SeqCst),
+            max_depth: MAX_DEPTH.load(SeqCst),
+            longest: LONGEST.load(SeqCst),
+            cycles_outside: CYCLES_OUTSIDE.load(SeqCst),
+            pauses_inside: PAUSES_INSIDE.load(SeqCst),
+        };
+        assert_eq!(jp.0.banks(), banks_image_ecc(&test_key(0xE1), 0x00C0_FFEE, 0));
+        assert_eq!(DEPTH.load(SeqCst), 0);
+        (jp.0, stats)
+    }
+
+    fn policy(critical_sections: CsPolicy) -> BurnConfig {
+        BurnConfig { critical_sections, ..BurnConfig::default() }
+    }
+
+    #[test]
+    fn off_by_default() {
+        let (_, stats) = burn(BurnConfig::default());
+        assert_eq!(stats.sections, 0);
+        assert_eq!(stats.cycles_outside, stats.cycles);
+    }
+
+    #[test]
+    fn per_bit() {
+        let (jp, stats) = burn(policy(CsPolicy::PerBit));
+        assert_eq!(stats.sections as u64, stats.cycles, "{:?}", stats);
+        assert_eq!(stats.longest, 1);
+        assert_eq!(stats.max_depth, 1);
+        assert_eq!(stats.cycles_outside, 0);
+        assert_eq!(stats.pauses_inside, 0);
+        // the same sequence as without critical sections
+        assert_eq!(jp.programmed(), burn(BurnConfig::default()).0.programmed());
+    }
+
+    #[test]
+    fn per_word() {
+        let (_, stats) = burn(policy(CsPolicy::PerWord));
+        assert_eq!(stats.max_depth, 1);
+        assert_eq!(stats.pauses_inside, 0);
+        // whole 64-bit program words go through inside one section, but nothing much longer
+        assert!(stats.longest >= 64 && stats.longest <= CS_MAX_WORD_CYCLES as u64, "{:?}", stats);
+        // only the resets around the burn run outside
+        assert!(stats.cycles_outside > 0 && stats.cycles_outside < 32, "{:?}", stats);
+    }
+
+    #[test]
+    fn jitter_gaps_stay_outside() {
+        let jitter = JitterSpec { min_cycles: 100, max_cycles: 200, seed: 5 };
+        let config = BurnConfig { timing: BurnTiming { inter_bit_jitter: Some(jitter), ..BurnTiming::default() }, ..policy(CsPolicy::PerWord) };
+        let (_, stats) = burn(config);
+        assert!(stats.longest <= CS_MAX_WORD_CYCLES as u64, "{:?}", stats);
+        assert!(stats.cycles_outside >= 100, "{:?}", stats);
+    }
+}
@@ -0,0 +1,160 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::csr::*;
+    use efuse_api::layout::*;
+    use efuse_api::test_utils::*;
+
+    /// The JTAG block in front of `device`. A NEXT write clocks the device and holds READY low
+    /// for `latency` reads of TDO; with MANUAL enabled, a rising TCK clocks it instead.
+    struct FakeBlock<P: JtagPhy> {
+        device: P,
+        map: CsrMap,
+        latency: u32,
+        busy: u32,
+        /// READY never sets again after this many more NEXT writes, if Some
+        hang_after: Option<u32>,
+        tdo: bool,
+        manual: u32,
+        writes: Vec<(usize, u32)>,
+        delayed_us: u64,
+    }
+
+    impl<P: JtagPhy> FakeBlock<P> {
+        fn new(device: P, map: CsrMap) -> Self {
+            FakeBlock { device, map, latency: 2, busy: 0, hang_after: None, tdo: false, manual: 0, writes: Vec::new(), delayed_us: 0 }
+        }
+
+        fn manual_writes(&self) -> Vec<u32> {
+            self.writes.iter().filter(|w| w.0 == self.map.manual).map(|w| w.1).collect()
+        }
+    }
+
+    impl<P: JtagPhy> CsrBlock for FakeBlock<P> {
+        fn read(&mut self, offset: usize) -> u32 {
+            assert_eq!(offset, self.map.tdo, "only TDO is read");
+            let ready: bool = self.busy == 0;
+            if self.busy > 0 && self.busy != u32::MAX {
+                self.busy -= 1;
+            }
+            (self.tdo as u32 * TDO_TDO) | (ready as u32 * TDO_READY)
+        }
+
+        fn write(&mut self, offset: usize, value: u32) {
+            self.writes.push((offset, value));
+            if offset == self.map.next {
+                assert!(self.manual & MANUAL_ENABLE == 0, "NEXT written with the pins taken");
+                assert_eq!(self.busy, 0, "NEXT written while shifting");
+                self.tdo = self.device.sync(value & NEXT_TDI != 0, value & NEXT_TMS != 0);
+                self.busy = self.latency;
+                if let Some(left) = self.hang_after.as_mut() {
+                    if *left == 0 {
+                        self.busy = u32::MAX;
+                    } else {
+                        *left -= 1;
+                    }
+                }
+            } else if offset == self.map.manual {
+                if value & MANUAL_ENABLE != 0 && value & !self.manual & MANUAL_TCK != 0 {
+                    self.tdo = self.device.sync(value & MANUAL_TDI != 0, value & MANUAL_TMS != 0);
+                }
+                self.manual = value;
+            } else {
+                panic!("write to {:#x}, which isn't a register", offset);
+            }
+        }
+
+        fn delay_us(&mut self, us: u32) {
+            self.delayed_us += us as u64;
+        }
+    }
+
+    #[test]
+    fn fetch_and_burn_through_the_block() {
+        let mut model = EfuseModelPhy::new();
+        model.set_idcode(0x0362_D093);
+        let mut jp = CsrPhy::new(FakeBlock::new(model, CsrMap::BETRUSTED), CsrMap::BETRUSTED);
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        assert_eq!(efuse.read_idcode(&mut jm, &mut jp), Ok(0x0362_D093));
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(test_key(0x71));
+        efuse.set_user(0x0BAD_F00D);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert_eq!(jp.regs().device.banks(), banks_image_ecc(&test_key(0x71), 0x0BAD_F00D, 0));
+
+        let mut check: EfuseApi = EfuseApi::new();
+        check.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(check.phy_key(), test_key(0x71));
+        assert_eq!(check.phy_user(), 0x0BAD_F00D);
+        assert!(jp.regs().manual_writes().is_empty());
+    }
+
+    #[test]
+    fn registers_where_the_map_puts_them() {
+        let map = CsrMap { next: 0x40, tdo: 0x44, manual: 0x50 };
+        let mut jp = CsrPhy::new(FakeBlock::new(EfuseModelPhy::new(), map), map);
+        assert_eq!(jp.try_sync(true, false), Ok(false));
+        assert_eq!(jp.try_sync(false, true), Ok(false));
+        assert_eq!(jp.regs().writes, [(0x40, NEXT_TDI), (0x40, NEXT_TMS)]);
+        jp.pause(250);
+        assert_eq!(jp.regs().delayed_us, 250);
+    }
+
+    #[test]
+    fn by_hand_tdi_and_tms_are_held_around_tck() {
+        let mut jp = CsrPhy::new(FakeBlock::new(EfuseModelPhy::new(), CsrMap::BETRUSTED), CsrMap::BETRUSTED);
+        // reset, then into Shift-IR and load IDCODE, clocked by hand with TDI and TMS changing
+        // as TCK falls
+        let mut cycles: Vec<(bool, bool)> = vec![(false, true); 5];
+        cycles.extend_from_slice(&[(false, false), (true, true), (true, true), (false, false), (false, false)]);
+        for n in 0..6 {
+            cycles.push(((0b001001 >> n) & 1 == 1, n == 5));
+        }
+        cycles.extend_from_slice(&[(true, true), (false, false)]);
+        for &(tdi, tms) in cycles.iter() {
+            jp.nosync(tdi, tms, false);
+            jp.nosync(tdi, tms, true);
+        }
+        jp.nosync(false, false, false);
+        assert_eq!(jp.regs().device.ir_history(), [0b001001]);
+        assert_eq!(jp.regs().device.tap(), TapState::RunTestIdle);
+
+        // no write changed TCK along with TDI or TMS
+        let writes: Vec<u32> = jp.regs().manual_writes();
+        let mut was: u32 = MANUAL_ENABLE;
+        for &pins in writes.iter() {
+            assert_eq!(pins & MANUAL_ENABLE, MANUAL_ENABLE);
+            let changed: u32 = was ^ pins;
+            assert!(changed & MANUAL_TCK == 0 || changed & (MANUAL_TDI | MANUAL_TMS) == 0, "{:#x} to {:#x}", was, pins);
+            was = pins;
+        }
+        assert!(writes.len() > 2 * cycles.len() + 1);
+
+        // the shifter gets the pins back for the next sync()
+        assert_eq!(jp.try_sync(false, false), Ok(false));
+        let n: usize = jp.regs().writes.len();
+        assert_eq!(jp.regs().writes[n - 2], (CsrMap::BETRUSTED.manual, 0));
+        assert_eq!(jp.regs().writes[n - 1], (CsrMap::BETRUSTED.next, 0));
+    }
+
+    #[test]
+    fn a_wedged_block() {
+        let mut block = FakeBlock::new(EfuseModelPhy::new(), CsrMap::BETRUSTED);
+        block.busy = u32::MAX;
+        let mut jp = CsrPhy::new(block, CsrMap::BETRUSTED);
+        jp.set_ready_polls(8);
+        // still shifting: nothing is written, and the cycle can be retried
+        assert_eq!(jp.try_sync(true, true), Err(PhyError::Busy));
+        assert!(jp.regs().writes.is_empty());
+
+        // a cycle that starts and never finishes
+        jp.regs_mut().busy = 0;
+        jp.regs_mut().hang_after = Some(3);
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        assert_eq!(efuse.read_idcode(&mut jm, &mut jp), Err(EfuseError::Jtag { phase: Phase::Fetch, err: JtagError::Phy(PhyError::Transport) }));
+        assert_eq!(jp.regs().writes.len(), 4);
+    }
+}
@@ -0,0 +1,169 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::sequences::*;
+    use efuse_api::test_utils::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    struct FakeClock(Rc<Cell<u64>>);
+
+    impl Clock for FakeClock {
+        fn now_us(&self) -> u64 {
+            self.0.get()
+        }
+    }
+
+    /// ScriptedPhy on a fake timeline: each cycle takes 1us, pauses take as long as asked
+    struct TimedPhy {
+        inner: ScriptedPhy,
+        now: Rc<Cell<u64>>,
+    }
+
+    impl JtagPhy for TimedPhy {
+        fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+            self.now.set(self.now.get() + 1);
+            self.inner.sync(tdi, tms)
+        }
+        fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool {
+            unimplemented!();
+        }
+        fn pause(&mut self, us: u32) {
+            self.now.set(self.now.get() + us as u64);
+            self.inner.pause(us);
+        }
+    }
+
+    fn phy(budget: Budget) -> DeadlinePhy<TimedPhy, FakeClock> {
+        let now = Rc::new(Cell::new(0));
+        DeadlinePhy::new(TimedPhy { inner: ScriptedPhy::new(), now: now.clone() }, FakeClock(now), budget)
+    }
+
+    fn cycles(jp: &DeadlinePhy<TimedPhy, FakeClock>) -> usize {
+        jp.inner().inner.cycles()
+    }
+
+    /// time a complete fetch takes
+    fn fetch_duration() -> u64 {
+        let mut jp = phy(Budget::UNLIMITED);
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        jp.inner().now.get()
+    }
+
+    #[test]
+    fn timeout_mid_fetch() {
+        let full: u64 = fetch_duration();
+        let mut jp = phy(Budget::new(None, Some(full / 2)));
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        assert_eq!(efuse.fetch(&mut jm, &mut jp), Err(EfuseError::Timeout { phase: Phase::Fetch }));
+        assert!(!jm.has_pending());
+
+        // the expired phy isn't touched again, whatever the API tries
+        let spent: usize = cycles(&jp);
+        assert!(spent > 0);
+        assert_eq!(efuse.fetch(&mut jm, &mut jp), Err(EfuseError::Timeout { phase: Phase::Fetch }));
+        // nothing was fetched, so burn() won't even try
+        assert_eq!(efuse.burn(&mut jm, &mut jp), Err(EfuseError::NotFetched));
+        assert_eq!(cycles(&jp), spent);
+
+        // a fresh budget lets the same API retry
+        jp.set_budget(Budget::new(None, Some(full)));
+        assert_eq!(efuse.fetch(&mut jm, &mut jp), Ok(()));
+    }
+
+    #[test]
+    fn timeout_mid_burn() {
+        let mut jp = phy(Budget::UNLIMITED);
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key([0x5A; 32]);
+
+        // enough for the opening pauses and a few programming words, nowhere near a full key
+        jp.set_budget(Budget::new(None, Some(20_000)));
+        assert_eq!(efuse.burn(&mut jm, &mut jp), Err(EfuseError::Timeout { phase: Phase::Burn }));
+        assert!(!jm.has_pending());
+        let spent: usize = cycles(&jp);
+        assert!(jp.inner().inner.dr_writes(Ir::FuseCts).count() > 0);
+        // the commit sequence never ran
+        assert_eq!(jp.inner().inner.dr_writes(Ir::User1).count(), 0);
+
+        assert_eq!(efuse.burn(&mut jm, &mut jp), Err(EfuseError::Timeout { phase: Phase::Burn }));
+        assert_eq!(cycles(&jp), spent);
+    }
+
+    #[test]
+    fn wedged_cycle_mid_fetch() {
+        // a single cycle that takes far longer than it should trips the operation budget
+        struct Wedged(TimedPhy, usize);
+        impl JtagPhy for Wedged {
+            fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+                if self.0.inner.cycles() == self.1 {
+                    self.0.now.set(self.0.now.get() + 1_000_000);
+                }
+                self.0.sync(tdi, tms)
+            }
+            fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool {
+                unimplemented!();
+            }
+            fn pause(&mut self, us: u32) {
+                self.0.pause(us);
+            }
+        }
+
+        let now = Rc::new(Cell::new(0));
+        let inner = Wedged(TimedPhy { inner: ScriptedPhy::new(), now: now.clone() }, 100);
+        let mut jp = DeadlinePhy::new(inner, FakeClock(now), Budget::new(Some(1000), None));
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        assert_eq!(efuse.fetch(&mut jm, &mut jp), Err(EfuseError::Timeout { phase: Phase::Fetch }));
+        assert_eq!(jp.inner().0.inner.cycles(), 101);
+    }
+
+    /// ScriptedPhy that stalls every cycle of a DR shift under `ir`
+    struct Stalling {
+        inner: ScriptedPhy,
+        ir: Ir,
+    }
+
+    impl JtagPhy for Stalling {
+        fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+            self.inner.sync(tdi, tms)
+        }
+        fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool {
+            unimplemented!();
+        }
+        fn pause(&mut self, us: u32) {
+            self.inner.pause(us);
+        }
+        fn try_sync(&mut self, tdi: bool, tms: bool) -> Result<bool, PhyError> {
+            if self.inner.tap() == TapState::ShiftDr && self.inner.ir() == Some(self.ir) {
+                return Err(PhyError::Busy);
+            }
+            Ok(self.sync(tdi, tms))
+        }
+    }
+
+    #[test]
+    fn stalled_legs_spend_their_own_budgets() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        let mut jp = Stalling { inner: ScriptedPhy::new(), ir: Ir::FuseKey };
+        let timeout = JtagError::LegTimeout { tag: "fuse", budget: POLL_BUDGET, spent: POLL_BUDGET };
+        assert_eq!(efuse.fetch(&mut jm, &mut jp), Err(EfuseError::Jtag { phase: Phase::Fetch, err: timeout }));
+
+        // the readbacks are fine this time; the first programming word never gets through
+        let mut jp = Stalling { inner: ScriptedPhy::new(), ir: Ir::FuseCts };
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key([0x5A; 32]);
+        let timeout = JtagError::LegTimeout { tag: "KEY_UNLOCK1", budget: UNLOCK_BUDGET, spent: UNLOCK_BUDGET };
+        assert_eq!(efuse.burn(&mut jm, &mut jp), Err(EfuseError::Jtag { phase: Phase::Burn, err: timeout }));
+        assert!(!jm.has_pending());
+        // the JSTART and EFUSE legs ahead of it went through
+        assert_eq!(jp.inner.ir(), Some(Ir::FuseCts));
+    }
+}
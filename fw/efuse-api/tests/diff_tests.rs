@@ -0,0 +1,79 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::test_utils::*;
+
+    fn fetched(banks: [u32; FUSE_BANKS]) -> EfuseApi {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut JtagMach::new(), &mut EfuseModelPhy::with_banks(banks)).unwrap();
+        efuse
+    }
+
+    #[test]
+    fn nothing_to_do() {
+        let mut efuse = fetched([0; FUSE_BANKS]);
+        assert_eq!(efuse.diff(), StateDiff::default());
+        let banks: [u32; FUSE_BANKS] = banks_image_ecc(&[0x3C; 32], 0x1234_5678, CNTL_CFG_AES_ONLY);
+        efuse = fetched(banks);
+        efuse.reset_pending();
+        assert_eq!(efuse.diff(), StateDiff::default());
+        assert!(efuse.diff().is_legal());
+    }
+
+    #[test]
+    fn a_blank_device() {
+        let mut efuse = fetched([0; FUSE_BANKS]);
+        let mut key: [u8; 32] = [0; 32];
+        key[0] = 0x01;
+        key[31] = 0x80;
+        efuse.set_key(key);
+        efuse.set_user(0x0000_0100);
+        efuse.set_cntl(CNTL_CFG_AES_ONLY).unwrap();
+
+        let diff: StateDiff = efuse.diff();
+        assert_eq!(diff.key_set, 0x8000_0001);
+        assert_eq!(diff.changed_key_bytes().collect::<Vec<usize>>(), [0, 31]);
+        assert_eq!(diff.user_set, 0x0000_0100);
+        assert_eq!(diff.cntl_set, CNTL_CFG_AES_ONLY);
+        assert!(diff.is_legal());
+        // key byte 0 is bit 0 of bank 1, key byte 31's top bit is bit 15 of bank 11, USER bit
+        // 8 is bit 0 of bank 12, each with its ECC code; CNTL bit 1 goes in both copies
+        let fuses: u32 = [1, SHARED_BANK, USER_BANK].iter().map(|&index| bank_image_ecc(index, &key, 0x0000_0100, 0).count_ones()).sum::<u32>() + 2;
+        assert_eq!(bank_image_ecc(1, &key, 0, 0) & 0xFF_FFFF, 0x00_0001);
+        assert_eq!(bank_image_ecc(SHARED_BANK, &key, 0, 0) & 0xFF_FFFF, 0x00_8000);
+        assert_eq!(bank_image_ecc(USER_BANK, &[0; 32], 0x0000_0100, 0) & 0xFF_FFFF, 0x00_0001);
+        assert_eq!(diff.fuses, fuses);
+        efuse.allow_weak_key(true);
+        assert_eq!(diff.fuses, efuse.plan().unwrap().pulses);
+    }
+
+    #[test]
+    fn illegal_changes() {
+        let mut key: [u8; 32] = [0; 32];
+        key[4] = 0x0F;
+        let mut efuse = fetched(banks_image_ecc(&key, 0x0000_000F, CNTL_W_EN_B_KEY_USER));
+        // key byte 4 and USER[7:0] swap nibbles, CNTL swaps one bit for another
+        key[4] = 0xF0;
+        efuse.set_key(key);
+        efuse.set_user(0x0000_00F0);
+        efuse.set_cntl(CNTL_CFG_AES_ONLY).unwrap();
+
+        let diff: StateDiff = efuse.diff();
+        assert_eq!((diff.key_set, diff.key_cleared), (1 << 4, 1 << 4));
+        assert_eq!((diff.user_set, diff.user_cleared), (0xF0, 0x0F));
+        assert_eq!((diff.cntl_set, diff.cntl_cleared), (CNTL_CFG_AES_ONLY, CNTL_W_EN_B_KEY_USER));
+        assert!(!diff.is_legal());
+        // the ECC is as conflicts() has it
+        let conflicts: TransitionConflicts = efuse.conflicts();
+        let ecc: u32 = (0..FUSE_BANKS).filter(|&index| conflicts.banks[index].ecc != 0).fold(0, |mask, index| mask | 1 << index);
+        assert_eq!(diff.ecc_cleared, ecc);
+        assert_eq!(diff.ecc_cleared & !(1 << 2 | 1 << SHARED_BANK), 0);
+        // the fuses a burn would blow, ECC included, as if nothing stood in the way
+        let fused: [u32; FUSE_BANKS] = efuse.snapshot().banks;
+        let image: [u32; FUSE_BANKS] = banks_image_ecc(&key, 0x0000_00F0, CNTL_CFG_AES_ONLY);
+        assert_eq!(diff.fuses, (0..FUSE_BANKS).map(|index| (image[index] & !fused[index]).count_ones()).sum::<u32>());
+        assert!(efuse.validate().is_err());
+    }
+}
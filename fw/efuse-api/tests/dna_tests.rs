@@ -0,0 +1,57 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::sequences::*;
+    use efuse_api::test_utils::*;
+
+    /// a 57-bit DNA as it sits in the FUSE_DNA register
+    const RAW: u64 = 0x8B2D_4F60_1C3E_A580;
+
+    #[test]
+    fn reads_the_top_57_bits_twice() {
+        let mut jp = ScriptedPhy::new();
+        jp.on_dr(Ir::FuseDna, 64, RAW as u128);
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        assert_eq!(efuse.dna(&mut jm, &mut jp), Ok(0x0116_5A9E_C038_7D4B));
+
+        // FUSE_DNA is 0b110010, and its DR 64 bits long
+        assert_eq!(Ir::FuseDna.code(), 0b110010);
+        assert_eq!(jp.ir_history().iter().filter(|&&ir| ir == 0b110010).count(), 2);
+        let scans: Vec<&DrWrite> = jp.dr_writes(Ir::FuseDna).collect();
+        assert_eq!(scans.len(), 2);
+        assert!(scans.iter().all(|scan| scan.len() == 64));
+    }
+
+    #[test]
+    fn the_low_bits_dont_count() {
+        let mut jp = ScriptedPhy::new();
+        jp.on_dr_seq(Ir::FuseDna, 64, &[RAW as u128, (RAW | 0x7F) as u128]);
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        assert_eq!(efuse.dna(&mut jm, &mut jp), Ok(RAW >> 7));
+    }
+
+    #[test]
+    fn unstable_reads_are_refused() {
+        let mut jp = ScriptedPhy::new();
+        jp.on_dr_seq(Ir::FuseDna, 64, &[RAW as u128, (RAW ^ 1 << 40) as u128]);
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        assert_eq!(efuse.dna(&mut jm, &mut jp), Err(EfuseError::DnaUnstable { first: RAW >> 7, second: (RAW ^ 1 << 40) >> 7 }));
+    }
+
+    #[test]
+    fn zero_is_refused() {
+        // nothing answers the DR scan
+        let mut jp = ScriptedPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        assert_eq!(efuse.dna(&mut jm, &mut jp), Err(EfuseError::DnaUnreadable));
+
+        let mut jp = EfuseModelPhy::new();
+        jp.set_dna(0x7F);
+        assert_eq!(efuse.dna(&mut jm, &mut jp), Err(EfuseError::DnaUnreadable));
+    }
+}
@@ -0,0 +1,118 @@
+#[cfg(test)]
+mod tests {
+    use efuse_ecc::efuse_ecc::*;
+
+    /// data records whose words every single flip is tried on
+    const REFERENCE: [u32; 5] = [0x00_0000, 0xFF_FFFF, 0x18_1716, 0xA5_C33C, 0x00_0001];
+
+    #[test]
+    fn valid_words_decode_as_they_are() {
+        for &data in REFERENCE.iter() {
+            assert_eq!(decode_ecc(add_ecc(data)), Ok(Corrected { data, flipped: None }));
+            // bits above the word don't count
+            assert_eq!(decode_ecc(add_ecc(data) | 0xC000_0000), Ok(Corrected { data, flipped: None }));
+        }
+    }
+
+    #[test]
+    fn every_single_flip_is_corrected() {
+        for &data in REFERENCE.iter() {
+            for bit in 0..WORD_BITS {
+                let corrected: Corrected = decode_ecc(add_ecc(data) ^ (1 << bit)).unwrap();
+                assert_eq!(corrected, Corrected { data, flipped: Some(bit) }, "data {:#08x}, bit {}", data, bit);
+                assert!(corrected.corrected());
+            }
+        }
+    }
+
+    #[test]
+    fn known_answers() {
+        // the reference bank 3 with ECC bit 1 blank, and with data bit 1 blank
+        assert_eq!(decode_ecc(0x2018_1716), Ok(Corrected { data: 0x18_1716, flipped: Some(25) }));
+        assert_eq!(decode_ecc(add_ecc(0x18_1716) & !0b10), Ok(Corrected { data: 0x18_1716, flipped: Some(1) }));
+    }
+
+    #[test]
+    fn double_errors_that_show_are_refused() {
+        for &data in REFERENCE.iter() {
+            let detected = (0..WORD_BITS).flat_map(|a| (a + 1..WORD_BITS).map(move |b| (a, b)))
+                .filter(|&(a, b)| decode_ecc(add_ecc(data) ^ (1 << a) ^ (1 << b)).is_err())
+                .count();
+            assert!(detected > 0, "data {:#08x}", data);
+        }
+        assert_eq!(decode_ecc(add_ecc(0) ^ 0b11), Err(EccError::Uncorrectable));
+    }
+
+    #[test]
+    fn a_double_error_never_decodes_as_it_was() {
+        // it's refused, or taken for a single error somewhere else; never the original record
+        for &data in REFERENCE.iter() {
+            for a in 0..WORD_BITS {
+                for b in a + 1..WORD_BITS {
+                    if let Ok(corrected) = decode_ecc(add_ecc(data) ^ (1 << a) ^ (1 << b)) {
+                        assert_ne!(corrected.data, data);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Knuth's MMIX LCG: no dependencies, and the same words every run
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self) -> u32 {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (self.0 >> 32) as u32
+        }
+
+        /// a random 24-bit data record
+        fn data(&mut self) -> u32 {
+            self.next() & 0xFF_FFFF
+        }
+    }
+
+    const ROUNDS: usize = 4096;
+
+    #[test]
+    fn random_words_round_trip() {
+        let mut lcg: Lcg = Lcg(0x5EED_0001);
+        for _ in 0..ROUNDS {
+            let data: u32 = lcg.data();
+            assert_eq!(decode_ecc(add_ecc(data)), Ok(Corrected { data, flipped: None }), "data {:#08x}", data);
+            assert!(syndrome(add_ecc(data)).is_clean());
+        }
+    }
+
+    #[test]
+    fn random_single_flips_are_corrected() {
+        let mut lcg: Lcg = Lcg(0x5EED_0002);
+        for _ in 0..ROUNDS {
+            let data: u32 = lcg.data();
+            let bit: u32 = lcg.next() % WORD_BITS;
+            assert_eq!(decode_ecc(add_ecc(data) ^ (1 << bit)), Ok(Corrected { data, flipped: Some(bit) }), "data {:#08x}, bit {}", data, bit);
+        }
+    }
+
+    #[test]
+    fn random_double_flips_are_never_taken_for_clean() {
+        // no two valid words are within two flips of each other, so a double error is always
+        // caught as something: refused, or corrected the wrong way, never passed as it is
+        let mut lcg: Lcg = Lcg(0x5EED_0003);
+        for _ in 0..ROUNDS {
+            let data: u32 = lcg.data();
+            let a: u32 = lcg.next() % WORD_BITS;
+            let b: u32 = (a + 1 + lcg.next() % (WORD_BITS - 1)) % WORD_BITS;
+            let word: u32 = add_ecc(data) ^ (1 << a) ^ (1 << b);
+            assert!(!verify_ecc(word));
+            assert!(!syndrome(word).is_clean());
+            match decode_ecc(word) {
+                Ok(corrected) => {
+                    assert!(corrected.corrected(), "data {:#08x}, bits {} and {}", data, a, b);
+                    assert_ne!(corrected.data, data);
+                }
+                Err(e) => assert_eq!(e, EccError::Uncorrectable),
+            }
+        }
+    }
+}
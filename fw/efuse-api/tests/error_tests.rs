@@ -0,0 +1,77 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::messages::*;
+    use efuse_api::test_utils::*;
+
+    const USER: u32 = 0x0042_1337;
+
+    #[test]
+    fn burn_needs_the_fused_state() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.set_key(test_key(0x5A));
+        efuse.set_user(USER);
+        assert_eq!(efuse.burn(&mut jm, &mut jp), Err(EfuseError::NotFetched));
+        assert!(jp.programmed().is_empty());
+        assert_eq!(efuse.last_report(), None);
+
+        // a snapshot counts as much as a fetch
+        let mut planned: EfuseApi = EfuseApi::new();
+        planned.load_snapshot(&FuseSnapshot { banks: [0; FUSE_BANKS] });
+        planned.set_key(test_key(0x5A));
+        planned.set_user(USER);
+        assert_eq!(planned.burn(&mut jm, &mut jp), Ok(()));
+
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(test_key(0x5A));
+        assert_eq!(efuse.burn(&mut jm, &mut jp), Ok(()));
+    }
+
+    #[test]
+    fn illegal_transition_names_the_lowest_bank() {
+        let mut banks = banks_image_ecc(&test_key(0x5A), USER, 0);
+        // a key bit the staged key has clear in bank 7, a USER bit in bank 12
+        banks[7] |= !banks[7] & (banks[7] + 1) & 0xFF_FFFF;
+        banks[12] |= !banks[12] & (banks[12] + 1) & 0xFF_FFFF;
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.load_snapshot(&FuseSnapshot { banks });
+        efuse.set_key(test_key(0x5A));
+        efuse.set_user(USER);
+        let mask: u32 = banks[7] & !bank_image_ecc(7, &test_key(0x5A), USER, 0);
+        assert_ne!(mask, 0);
+        assert_eq!(efuse.validate().err(), Some(EfuseError::IllegalTransition { bank: 7, mask }));
+        assert!(!efuse.is_valid());
+    }
+
+    #[test]
+    fn reserved_cntl_bits_are_refused() {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.set_cntl(CNTL_R_EN_B_KEY).unwrap();
+        assert_eq!(efuse.set_cntl(0xC0 | CNTL_W_EN_B_KEY_USER), Err(EfuseError::CntlReserved { bits: 0xC0 }));
+        assert_eq!(efuse.api_cntl(), CNTL_R_EN_B_KEY);
+
+        // a manifest with one stages nothing at all
+        let manifest = ProvisioningManifest { key: test_key(0x5A), user: USER, cntl: 0x80 };
+        assert_eq!(efuse.stage(&manifest), Err(EfuseError::CntlReserved { bits: 0x80 }));
+        assert_eq!(efuse.api_key(), [0; 32]);
+        assert_eq!(efuse.api_user(), 0);
+    }
+
+    #[test]
+    fn display() {
+        let cases: [(EfuseError, &str); 5] = [
+            (EfuseError::NotFetched, "the fused state isn't known; fetch first"),
+            (EfuseError::IllegalTransition { bank: 7, mask: 0x40 }, "bank 7: blown fuses 0x00000040 would have to be cleared"),
+            (EfuseError::CntlReserved { bits: 0x80 }, "CNTL bits 0x80 aren't fuses"),
+            (EfuseError::ShortReadback { readback: Readback::User, captured: 9, expected: 32 }, "FUSE_USER readback came back with 9 of 32 bits"),
+            (EfuseError::Timeout { phase: Phase::Burn }, "burn: JTAG time budget ran out"),
+        ];
+        for (err, text) in cases.iter() {
+            assert_eq!(format!("{}", err), *text);
+        }
+    }
+}
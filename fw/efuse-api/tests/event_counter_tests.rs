@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::test_utils::*;
+
+    /// USER bits 12 and 22 can both be added to an otherwise blank bank 12
+    const COUNTER: CounterSpec = CounterSpec { field: (1 << 12) | (1 << 22), refuse_when_exhausted: false };
+
+    fn counted(spec: CounterSpec) -> (EfuseApi, JtagMach, EfuseModelPhy) {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.set_burn_config(BurnConfig { event_counter: Some(spec), ..BurnConfig::default() });
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        (efuse, jm, jp)
+    }
+
+    /// stage `cntl` on top of whatever is fused, burn and fetch again
+    fn burn_cntl_bit(efuse: &mut EfuseApi, jm: &mut JtagMach, jp: &mut EfuseModelPhy, cntl: u8) -> Result<ValidationReport, EfuseError> {
+        efuse.set_key(efuse.phy_key());
+        efuse.set_user(efuse.phy_user());
+        efuse.set_cntl(efuse.phy_cntl() | cntl).unwrap();
+        let report = efuse.validate()?;
+        efuse.burn(jm, jp)?;
+        efuse.fetch(jm, jp).unwrap();
+        Ok(report)
+    }
+
+    #[test]
+    fn burns_are_counted() {
+        let (mut efuse, mut jm, mut jp) = counted(COUNTER);
+        assert_eq!(efuse.provisioning_events(), Some(0));
+        efuse.set_key(test_key(0x96));
+        // whatever is staged in the field is ignored
+        efuse.set_user(0x0040_00AB);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.last_report().unwrap().requested[USER_BANK] & 0xFF_FFFF, 1 << 4);
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.phy_user(), 0x0000_10AB);
+        assert_eq!(efuse.provisioning_events(), Some(1));
+
+        burn_cntl_bit(&mut efuse, &mut jm, &mut jp, CNTL_CFG_AES_ONLY).unwrap();
+        assert_eq!(efuse.phy_user(), 0x0040_10AB);
+        assert_eq!(efuse.provisioning_events(), Some(2));
+        assert_eq!(efuse.fetch_report().user, UserConsistency::Match);
+
+        // a burn that writes nothing isn't counted
+        assert_eq!(efuse.program_words().count(), 0);
+        assert_eq!(efuse.provisioning_events(), Some(2));
+    }
+
+    #[test]
+    fn exhausted_counter_warns() {
+        let (mut efuse, mut jm, mut jp) = counted(COUNTER);
+        efuse.set_key(test_key(0x96));
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        burn_cntl_bit(&mut efuse, &mut jm, &mut jp, CNTL_CFG_AES_ONLY).unwrap();
+
+        let report = burn_cntl_bit(&mut efuse, &mut jm, &mut jp, CNTL_AES_EXCLUSIVE).unwrap();
+        assert_eq!(report.warnings, [ValidationWarning::CounterExhausted { count: 2 }]);
+        assert_eq!(efuse.phy_cntl(), CNTL_CFG_AES_ONLY | CNTL_AES_EXCLUSIVE);
+        assert_eq!(efuse.provisioning_events(), Some(2));
+    }
+
+    #[test]
+    fn exhausted_counter_refuses() {
+        let (mut efuse, mut jm, mut jp) = counted(CounterSpec { refuse_when_exhausted: true, ..COUNTER });
+        efuse.set_key(test_key(0x96));
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        burn_cntl_bit(&mut efuse, &mut jm, &mut jp, CNTL_CFG_AES_ONLY).unwrap();
+
+        let programmed: usize = jp.programmed().len();
+        assert_eq!(burn_cntl_bit(&mut efuse, &mut jm, &mut jp, CNTL_AES_EXCLUSIVE), Err(EfuseError::CounterExhausted { count: 2 }));
+        assert_eq!(jp.programmed().len(), programmed);
+    }
+
+    #[test]
+    fn no_counter() {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        assert_eq!(efuse.provisioning_events(), None);
+        efuse.set_key(test_key(0x96));
+        efuse.set_user(0x0040_00AB);
+        assert!(efuse.program_words().count() > 0);
+        assert!(efuse.validate().unwrap().warnings.is_empty());
+    }
+}
@@ -0,0 +1,102 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::sequences::*;
+    use efuse_api::test_utils::*;
+
+    const USER: u32 = 0x0C0F_FEE5;
+
+    /// DR bits a fetch of a device with a key fused shifts: FUSE_KEY, FUSE_USER, FUSE_CNTL
+    const FETCH_DR_BITS: usize = 256 + 32 + 14;
+
+    /// Passes everything to `inner`, but reads DR bit `flip` (counting from the first DR bit
+    /// shifted) inverted: a fuse that read differently that once
+    struct Flaky<P: JtagPhy> {
+        inner: P,
+        tap: TapState,
+        dr_bits: usize,
+        flip: usize,
+    }
+
+    impl<P: JtagPhy> Flaky<P> {
+        fn new(inner: P, flip: usize) -> Self {
+            Flaky { inner, tap: TapState::TestLogicReset, dr_bits: 0, flip }
+        }
+    }
+
+    impl<P: JtagPhy> JtagPhy for Flaky<P> {
+        fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+            let mut tdo: bool = self.inner.sync(tdi, tms);
+            if self.tap == TapState::ShiftDr {
+                tdo ^= self.dr_bits == self.flip;
+                self.dr_bits += 1;
+            }
+            self.tap = self.tap.next(tms);
+            tdo
+        }
+
+        fn nosync(&mut self, tdi: bool, tms: bool, tck: bool) -> bool {
+            self.inner.nosync(tdi, tms, tck)
+        }
+
+        fn pause(&mut self, us: u32) {
+            self.inner.pause(us);
+        }
+    }
+
+    fn device() -> EfuseModelPhy {
+        EfuseModelPhy::with_banks(banks_image_ecc(&test_key(0x3D), USER, 0))
+    }
+
+    #[test]
+    fn steady_reads_agree() {
+        let mut jp = device();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch_stable(3, &mut jm, &mut jp).unwrap();
+        assert!(efuse.key_matches(&test_key(0x3D)));
+        assert_eq!(efuse.phy_user(), USER);
+        assert_eq!(efuse.snapshot().banks, banks_image_ecc(&test_key(0x3D), USER, 0));
+        assert_eq!(jp.ir_history().iter().filter(|&&ir| ir == Ir::FuseKey.code()).count(), 3);
+    }
+
+    #[test]
+    fn a_flaky_key_bit() {
+        // key byte 4, bit 1, on the second of three reads: bit 9 of bank 2
+        let mut jp = Flaky::new(device(), FETCH_DR_BITS + 4 * 8 + 1);
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        let mut bits: [u32; FUSE_BANKS] = [0; FUSE_BANKS];
+        bits[2] = 1 << 9;
+        assert_eq!(efuse.fetch_stable(3, &mut jm, &mut jp), Err(EfuseError::MarginalBits { bits }));
+        // nothing is planned against it
+        assert_eq!(efuse.burn(&mut jm, &mut jp), Err(EfuseError::NotFetched));
+        assert_eq!(EfuseError::MarginalBits { bits }.to_string(), "fuses read differently from one fetch to the next: bank 2 0x00000200");
+    }
+
+    #[test]
+    fn a_flaky_user_bit_leaves_the_fused_state() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut device()).unwrap();
+
+        // USER bit 3 on the third read lands in bank 11, USER bit 30 on the second in bank 12
+        let mut jp = Flaky::new(device(), 2 * FETCH_DR_BITS + 256 + 3);
+        let mut bits: [u32; FUSE_BANKS] = [0; FUSE_BANKS];
+        bits[SHARED_BANK] = 1 << 19;
+        assert_eq!(efuse.fetch_stable(4, &mut jm, &mut jp), Err(EfuseError::MarginalBits { bits }));
+        let mut jp = Flaky::new(device(), FETCH_DR_BITS + 256 + 30);
+        bits = [0; FUSE_BANKS];
+        bits[USER_BANK] = 1 << 22;
+        assert_eq!(efuse.fetch_stable(2, &mut jm, &mut jp), Err(EfuseError::MarginalBits { bits }));
+        assert_eq!(efuse.phy_user(), USER);
+        assert!(efuse.key_matches(&test_key(0x3D)));
+
+        // a single read can't disagree with itself
+        let mut jp = Flaky::new(device(), FETCH_DR_BITS + 1);
+        efuse.fetch_stable(1, &mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.phy_user(), USER);
+    }
+}
@@ -0,0 +1,138 @@
+#![cfg(feature = "ffi")]
+
+#[cfg(test)]
+mod tests {
+    use efuse_api::*;
+    use efuse_api::ffi::*;
+    use efuse_api::layout::*;
+    use efuse_api::messages::*;
+    use efuse_api::test_utils::*;
+    use std::process::Command;
+    use std::ptr;
+
+    fn snapshot_bytes(banks: [u32; FUSE_BANKS]) -> Vec<u8> {
+        let mut bytes = vec![0u8; FuseSnapshot::WIRE_LEN];
+        FuseSnapshot { banks }.encode(&mut bytes);
+        bytes
+    }
+
+    /// a planner loaded with `banks` and staged with test_key(0x3C), USER 0x8765_4321 and CNTL 0
+    unsafe fn planner(banks: [u32; FUSE_BANKS]) -> *mut EfusePlanner {
+        let p = efuse_planner_new();
+        let snapshot = snapshot_bytes(banks);
+        assert_eq!(efuse_planner_load_snapshot(p, snapshot.as_ptr(), snapshot.len()), EFUSE_OK);
+        assert_eq!(efuse_planner_stage_key(p, test_key(0x3C).as_ptr(), 32), EFUSE_OK);
+        assert_eq!(efuse_planner_stage_user(p, 0x8765_4321u32.to_le_bytes().as_ptr(), 4), EFUSE_OK);
+        assert_eq!(efuse_planner_stage_cntl(p, &0u8, 1), EFUSE_OK);
+        p
+    }
+
+    #[test]
+    fn plan_from_snapshot() {
+        unsafe {
+            let p = planner([0; FUSE_BANKS]);
+            let mut report = [0xAAu8; 64];
+            let mut written: usize = 0;
+            assert_eq!(efuse_planner_validate(p, report.as_mut_ptr(), report.len(), &mut written), EFUSE_OK);
+            assert_eq!(&report[..written], b"ok\0");
+
+            // the words are the ones the Rust API would burn
+            let mut efuse = EfuseApi::new();
+            efuse.set_key(test_key(0x3C));
+            efuse.set_user(0x8765_4321);
+            let expected: Vec<u8> = efuse.program_words().flat_map(|w| w.value.to_le_bytes()).collect();
+            let mut words = vec![0u8; expected.len()];
+            assert_eq!(efuse_planner_burn_words(p, words.as_mut_ptr(), words.len(), &mut written), EFUSE_OK);
+            assert_eq!(written, expected.len());
+            assert_eq!(words, expected);
+
+            // one byte short: nothing past the end is touched, and the length needed comes back
+            let mut short = vec![0u8; expected.len()];
+            assert_eq!(efuse_planner_burn_words(p, short.as_mut_ptr(), expected.len() - 1, &mut written), EFUSE_ERR_BUFFER_TOO_SMALL);
+            assert_eq!(written, expected.len());
+            assert_eq!(short[expected.len() - 8..], [0u8; 8]);
+            assert_eq!(efuse_planner_validate(p, report.as_mut_ptr(), 2, &mut written), EFUSE_ERR_BUFFER_TOO_SMALL);
+            assert_eq!(written, 3);
+            efuse_planner_free(p);
+        }
+    }
+
+    #[test]
+    fn refusals() {
+        unsafe {
+            // a fuse the plan wants clear is already blown
+            let mut banks = banks_image_ecc(&test_key(0x3C), 0x8765_4321, 0);
+            let clear: u32 = !banks[4] & 0xFF_FFFF;
+            banks[4] = clear & clear.wrapping_neg();
+            let p = planner(banks);
+            let mut report = [0u8; 64];
+            let mut written: usize = 0;
+            assert_eq!(efuse_planner_validate(p, report.as_mut_ptr(), report.len(), &mut written), EFUSE_REFUSED_INVALID);
+            let refused: String = format!("refused: IllegalTransition {{ bank: 4, mask: {} }}\0", banks[4]);
+            assert_eq!(&report[..written], refused.as_bytes());
+            let mut words = [0u8; 8];
+            assert_eq!(efuse_planner_burn_words(p, words.as_mut_ptr(), words.len(), &mut written), EFUSE_REFUSED_INVALID);
+            assert_eq!(words, [0u8; 8]);
+
+            // a weak key along with new CNTL bits
+            assert_eq!(efuse_planner_load_snapshot(p, snapshot_bytes([0; FUSE_BANKS]).as_ptr(), FuseSnapshot::WIRE_LEN), EFUSE_OK);
+            assert_eq!(efuse_planner_stage_key(p, [0x11u8; 32].as_ptr(), 32), EFUSE_OK);
+            assert_eq!(efuse_planner_stage_cntl(p, &0x03u8, 1), EFUSE_OK);
+            assert_eq!(efuse_planner_validate(p, ptr::null_mut(), 0, ptr::null_mut()), EFUSE_ERR_BUFFER_TOO_SMALL);
+            assert_eq!(efuse_planner_burn_words(p, ptr::null_mut(), 0, &mut written), EFUSE_REFUSED_WEAK_KEY);
+            efuse_planner_free(p);
+        }
+    }
+
+    #[test]
+    fn bad_calls() {
+        unsafe {
+            let p = efuse_planner_new();
+            let bytes = [0u8; 64];
+            assert_eq!(efuse_planner_load_snapshot(p, bytes.as_ptr(), FuseSnapshot::WIRE_LEN - 1), EFUSE_ERR_LENGTH);
+            assert_eq!(efuse_planner_stage_key(p, bytes.as_ptr(), 31), EFUSE_ERR_LENGTH);
+            assert_eq!(efuse_planner_stage_user(p, bytes.as_ptr(), 8), EFUSE_ERR_LENGTH);
+            assert_eq!(efuse_planner_stage_cntl(p, bytes.as_ptr(), 0), EFUSE_ERR_LENGTH);
+            assert_eq!(efuse_planner_stage_cntl(p, [0x40u8].as_ptr(), 1), EFUSE_ERR_RANGE);
+            assert_eq!(efuse_planner_stage_key(p, ptr::null(), 32), EFUSE_ERR_NULL);
+            assert_eq!(efuse_planner_stage_key(ptr::null_mut(), bytes.as_ptr(), 32), EFUSE_ERR_NULL);
+            assert_eq!(efuse_planner_validate(ptr::null_mut(), ptr::null_mut(), 0, ptr::null_mut()), EFUSE_ERR_NULL);
+            assert_eq!(efuse_planner_burn_words(p, ptr::null_mut(), 8, ptr::null_mut()), EFUSE_ERR_NULL);
+            efuse_planner_free(p);
+            efuse_planner_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn header_declares_every_export() {
+        let header = include_str!("../include/efuse_api.h");
+        let source = include_str!("../src/ffi.rs");
+        let mut exports = 0;
+        for line in source.lines().filter(|l| l.contains("extern \"C\" fn ")) {
+            let name = line.split("fn ").nth(1).unwrap().split('(').next().unwrap();
+            assert!(header.contains(&format!(" *{}(", name)) || header.contains(&format!(" {}(", name)), "{} missing from header", name);
+            exports += 1;
+        }
+        assert_eq!(exports, 8);
+        for line in source.lines().filter(|l| l.starts_with("pub const ")) {
+            let name = line["pub const ".len()..].split(':').next().unwrap();
+            assert!(header.contains(&format!("#define {} ", name)), "{} missing from header", name);
+        }
+    }
+
+    /// compiles tests/c/ffi_check.c against the header with the host C compiler, if there is one
+    #[test]
+    fn header_compiles_as_c() {
+        let dir = env!("CARGO_MANIFEST_DIR");
+        let out = std::env::temp_dir().join(format!("efuse_ffi_check_{}.o", std::process::id()));
+        let status = Command::new(std::env::var("CC").unwrap_or_else(|_| "cc".into()))
+            .args(["-std=c99", "-Wall", "-Wextra", "-Werror", "-c"])
+            .arg(format!("-I{}/include", dir))
+            .arg(format!("{}/tests/c/ffi_check.c", dir))
+            .arg("-o").arg(&out)
+            .status()
+            .expect("no C compiler to check the header with; set CC to one");
+        let _ = std::fs::remove_file(out);
+        assert!(status.success());
+    }
+}
@@ -0,0 +1,74 @@
+#![cfg(feature = "forensics")]
+
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::test_utils::*;
+
+    #[test]
+    fn programs_exactly_the_requested_bits() {
+        // a data bit plus one lone ECC bit: a combination burn() would never produce
+        let ones: u32 = 0x0100_0004;
+        let mut before = [0u32; FUSE_BANKS];
+        before[5] = 0x0000_0010;
+        let mut jp = EfuseModelPhy::with_banks(before);
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+
+        unsafe { efuse.burn_raw_bank(5, ones, &mut jm, &mut jp) }.unwrap();
+
+        let mut expected = before;
+        expected[5] |= ones;
+        assert_eq!(jp.banks(), expected);
+        assert_eq!(jp.programmed(), &[(5, 2), (5, 24)]);
+        assert_eq!(jp.rejected(), 0);
+        assert_eq!(jp.commits(), 1);
+        let report = efuse.last_report().unwrap();
+        assert!(report.committed);
+        assert_eq!(report.requested[5], ones);
+        assert_eq!(report.requested.iter().filter(|&&r| r != 0).count(), 1);
+    }
+
+    #[test]
+    fn blank_bits_in_between_are_skipped() {
+        let mut jp = EfuseModelPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        unsafe { efuse.burn_raw_bank(5, 0b1010_0101, &mut jm, &mut jp) }.unwrap();
+        assert_eq!(jp.programmed(), &[(5, 0), (5, 2), (5, 5), (5, 7)]);
+        assert_eq!(jp.banks()[5], 0b1010_0101);
+        assert_eq!(jp.rejected(), 0);
+    }
+
+    #[test]
+    fn cntl_bank_bits() {
+        let ones: u32 = 1 << CNTL_COPY_SHIFT;
+        let mut jp = EfuseModelPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        unsafe { efuse.burn_raw_bank(CNTL_BANK, ones, &mut jm, &mut jp) }.unwrap();
+        assert_eq!(jp.banks()[CNTL_BANK], ones);
+        assert_eq!(jp.programmed(), &[(CNTL_BANK, CNTL_COPY_SHIFT as u8)]);
+    }
+
+    #[test]
+    fn impossible_requests_never_reach_the_device() {
+        let mut jp = EfuseModelPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        let cases: [(usize, u32); 3] = [
+            (FUSE_BANKS, 0x1),
+            (3, 1 << 30),
+            // between the CNTL bits and their copy
+            (CNTL_BANK, 1 << 8),
+        ];
+        for &(bank, ones) in cases.iter() {
+            assert_eq!(unsafe { efuse.burn_raw_bank(bank, ones, &mut jm, &mut jp) },
+                Err(EfuseError::OutOfRange { bank, ones }));
+        }
+        assert_eq!(jp.cycles(), 0);
+        assert!(efuse.last_report().is_none());
+    }
+}
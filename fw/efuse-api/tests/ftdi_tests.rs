@@ -0,0 +1,254 @@
+#![cfg(feature = "ftdi")]
+
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::ftdi::*;
+    use efuse_api::layout::*;
+    use efuse_api::test_utils::*;
+
+    /// An MPSSE as AN_108 describes it, clocking `device` with the commands written to it, or
+    /// looping TDI back to TDO with the device left alone when loopback is on
+    struct MpsseModel<P: JtagPhy> {
+        device: P,
+        loopback: bool,
+        divisor: Option<u16>,
+        pending: Vec<u8>,
+        replies: Vec<u8>,
+        /// every write, as written
+        writes: Vec<Vec<u8>>,
+        /// reads to fail, as a pulled cable would, before answering again
+        fail_reads: usize,
+        /// what to answer the sync check's bad command with
+        echo: [u8; 2],
+    }
+
+    impl<P: JtagPhy> MpsseModel<P> {
+        fn new(device: P) -> Self {
+            MpsseModel { device, loopback: false, divisor: None, pending: Vec::new(), replies: Vec::new(),
+                writes: Vec::new(), fail_reads: 0, echo: [MPSSE_BAD_COMMAND, 0xAA] }
+        }
+
+        /// one TCK cycle; the TDO sampled on its rising edge
+        fn clock(&mut self, tdi: bool, tms: bool) -> bool {
+            if self.loopback { tdi } else { self.device.sync(tdi, tms) }
+        }
+
+        /// runs the first command in `pending`, if it's all there; returns its length
+        fn execute(&mut self) -> Option<usize> {
+            let c: Vec<u8> = self.pending.clone();
+            let need = |len: usize| if c.len() >= len { Some(len) } else { None };
+            let used: usize = match *c.first()? {
+                MPSSE_DIV5_OFF | MPSSE_ADAPTIVE_OFF | MPSSE_THREE_PHASE_OFF | MPSSE_SEND_IMMEDIATE => 1,
+                MPSSE_LOOPBACK_ON | MPSSE_LOOPBACK_OFF => {
+                    self.loopback = c[0] == MPSSE_LOOPBACK_ON;
+                    1
+                },
+                MPSSE_SET_DIVISOR => {
+                    let used: usize = need(3)?;
+                    self.divisor = Some(u16::from_le_bytes([c[1], c[2]]));
+                    used
+                },
+                MPSSE_SET_LOW => need(3)?,
+                MPSSE_GET_LOW => {
+                    let tdo: bool = self.loopback;
+                    self.replies.push((tdo as u8) << 2);
+                    1
+                },
+                MPSSE_BYTES_OUT_IN => {
+                    let len: usize = u16::from_le_bytes([*c.get(1)?, *c.get(2)?]) as usize + 1;
+                    let data: Vec<u8> = c.get(3..3 + len)?.to_vec();
+                    for byte in data {
+                        let mut tdo: u8 = 0;
+                        for k in 0..8 {
+                            tdo |= (self.clock((byte >> k) & 1 == 1, false) as u8) << k;
+                        }
+                        self.replies.push(tdo);
+                    }
+                    3 + len
+                },
+                MPSSE_BITS_OUT_IN | MPSSE_TMS_OUT_IN => {
+                    let used: usize = need(3)?;
+                    let (bits, byte): (usize, u8) = (c[1] as usize + 1, c[2]);
+                    let tms_out: bool = c[0] == MPSSE_TMS_OUT_IN;
+                    let mut tdo: u8 = 0;
+                    for k in 0..bits {
+                        let bit: bool = (byte >> k) & 1 == 1;
+                        // bits read are shifted in from the top
+                        let sampled: bool = if tms_out { self.clock(byte & 0x80 != 0, bit) } else { self.clock(bit, false) };
+                        tdo = tdo >> 1 | (sampled as u8) << 7;
+                    }
+                    self.replies.push(tdo);
+                    used
+                },
+                _ => {
+                    self.replies.extend_from_slice(&self.echo);
+                    1
+                },
+            };
+            Some(used)
+        }
+    }
+
+    impl<P: JtagPhy> MpsseLink for MpsseModel<P> {
+        fn write(&mut self, data: &[u8]) -> Result<(), FtdiError> {
+            self.writes.push(data.to_vec());
+            self.pending.extend_from_slice(data);
+            while let Some(used) = self.execute() {
+                self.pending.drain(..used);
+            }
+            Ok(())
+        }
+
+        fn read(&mut self, data: &mut [u8]) -> Result<(), FtdiError> {
+            if self.fail_reads > 0 {
+                self.fail_reads -= 1;
+                self.replies.clear();
+                return Err(FtdiError::Timeout);
+            }
+            if self.replies.len() < data.len() {
+                return Err(FtdiError::Timeout);
+            }
+            data.copy_from_slice(&self.replies[..data.len()]);
+            self.replies.drain(..data.len());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn loopback_keeps_the_bit_order() {
+        let mut jp = MpssePhy::new(MpsseModel::new(EfuseModelPhy::new()), 1_000_000).unwrap();
+        jp.set_loopback(true).unwrap();
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        let mut ir: JtagLeg = JtagLeg::new(JtagChain::IR, "ir");
+        ir.push_u32(0b100110, 6, JtagEndian::Little).unwrap();
+        jm.add(ir);
+        let mut long: JtagLeg = JtagLeg::new(JtagChain::DR, "long");
+        long.push_bytes(&test_key(0xC6), JtagEndian::Little).unwrap();
+        long.push_u32(0x5, 3, JtagEndian::Big).unwrap();
+        jm.add(long);
+        let mut short: JtagLeg = JtagLeg::new(JtagChain::DR, "short");
+        short.push_u32(0x2D, 7, JtagEndian::Big).unwrap();
+        jm.add(short);
+        assert_eq!(jm.run_to_completion(&mut jp), Ok(3));
+
+        let mut legs: Vec<JtagLeg> = Vec::new();
+        jm.drain_completed(|leg| legs.push(leg));
+        assert_eq!(legs[0].pop_u32(6, JtagEndian::Little), Some(0b100110));
+        // the last pushed went first, so the key came back last
+        let mut looped = [0u8; 32];
+        assert_eq!(legs[1].pop_bytes(&mut looped, JtagEndian::Little), Some(32));
+        assert_eq!(looped, test_key(0xC6));
+        assert_eq!(legs[1].pop_u32(3, JtagEndian::Big), Some(0x5 << 29));
+        assert_eq!(legs[2].pop_u8(7, JtagEndian::Big), Some(0x2D << 1));
+        // the device saw none of it
+        assert!(jp.link().device.ir_history().is_empty());
+    }
+
+    #[test]
+    fn shifts_as_an_108_has_it() {
+        let mut jp = MpssePhy::new(MpsseModel::new(EfuseModelPhy::new()), 1_000_000).unwrap();
+        jp.set_loopback(true).unwrap();
+        // 19 cycles: two bytes and two bits with TMS low, then one with TMS high
+        let tdi: [u8; 3] = [0xA5, 0x3C, 0b110];
+        let tms: [u8; 3] = [0, 0, 0b100];
+        let mut tdo = [0u8; 3];
+        assert_eq!(jp.shift_bulk(&tdi, &tms, &mut tdo, 19), Ok(19));
+        assert_eq!(tdo, tdi);
+        assert_eq!(jp.link().writes.last().unwrap(), &vec![
+            MPSSE_BYTES_OUT_IN, 1, 0, 0xA5, 0x3C,
+            MPSSE_BITS_OUT_IN, 1, 0b10,
+            MPSSE_TMS_OUT_IN, 0, 0x81,
+            MPSSE_SEND_IMMEDIATE]);
+
+        // a cycle at a time: a one-bit TDI command with TMS low, a TMS command with it high
+        assert_eq!(jp.try_sync(true, false), Ok(true));
+        assert_eq!(jp.link().writes.last().unwrap(), &vec![MPSSE_BITS_OUT_IN, 0, 1, MPSSE_SEND_IMMEDIATE]);
+        assert_eq!(jp.try_sync(false, true), Ok(false));
+        assert_eq!(jp.link().writes.last().unwrap(), &vec![MPSSE_TMS_OUT_IN, 0, 0x01, MPSSE_SEND_IMMEDIATE]);
+    }
+
+    #[test]
+    fn fetch_and_burn_through_the_mpsse() {
+        let mut model = EfuseModelPhy::new();
+        model.set_idcode(0x1362_D093);
+        let mut jp = MpssePhy::new(MpsseModel::new(model), 6_000_000).unwrap();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        assert_eq!(efuse.read_idcode(&mut jm, &mut jp), Ok(0x1362_D093));
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(test_key(0xC6));
+        efuse.set_user(0x00C0_FFEE);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert_eq!(jp.link().device.banks(), banks_image_ecc(&test_key(0xC6), 0x00C0_FFEE, 0));
+
+        let writes: usize = jp.link().writes.len();
+        let mut check: EfuseApi = EfuseApi::new();
+        check.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(check.phy_key(), test_key(0xC6));
+        assert_eq!(check.phy_user(), 0x00C0_FFEE);
+        // the 256 key bits went out in a single write
+        assert!(jp.link().writes[writes..].iter().any(|w| w.len() > 32 && w[0] == MPSSE_BYTES_OUT_IN));
+    }
+
+    #[test]
+    fn a_usb_failure_can_be_recovered_from() {
+        let mut jp = MpssePhy::new(MpsseModel::new(EfuseModelPhy::new()), 1_000_000).unwrap();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        jp.link_mut().fail_reads = 1;
+        assert!(efuse.fetch(&mut jm, &mut jp).is_err());
+        assert_eq!(jp.last_error(), Some(FtdiError::Timeout));
+
+        // the cable is back; the same phy carries on
+        assert_eq!(jp.try_sync(false, true), Ok(false));
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.phy_key(), [0; 32]);
+    }
+
+    #[test]
+    fn tck_frequency() {
+        let mut jp = MpssePhy::new(MpsseModel::new(EfuseModelPhy::new()), 30_000_000).unwrap();
+        assert_eq!(jp.frequency(), 30_000_000);
+        assert_eq!(jp.link().divisor, Some(0));
+        assert_eq!(jp.set_frequency(10_000_000), Ok(10_000_000));
+        assert_eq!(jp.link().divisor, Some(2));
+        // rounded down to what the divisor can make
+        assert_eq!(jp.set_frequency(7_000_000), Ok(6_000_000));
+        assert_eq!(jp.link().divisor, Some(4));
+        assert_eq!(jp.set_frequency(458), Ok(457));
+        assert_eq!(jp.link().divisor, Some(65502));
+        for &hz in [0, 457, 30_000_001].iter() {
+            assert_eq!(jp.set_frequency(hz), Err(FtdiError::Frequency(hz)));
+        }
+        assert_eq!(jp.frequency(), 457);
+    }
+
+    #[test]
+    fn a_link_that_isnt_an_mpsse() {
+        let mut model = MpsseModel::new(EfuseModelPhy::new());
+        model.echo = [0xAA, 0x87];
+        assert!(matches!(MpssePhy::new(model, 1_000_000), Err(FtdiError::Desync)));
+    }
+
+    /// needs an FT2232H plugged in; run with `cargo test --features ftdi -- --ignored`
+    #[test]
+    #[ignore]
+    fn ft2232h_loopback() {
+        let (vid, pid) = FtdiDevice::FT2232H;
+        let mut jp: FtdiPhy = FtdiPhy::open(vid, pid, None, 1_000_000).unwrap();
+        jp.set_loopback(true).unwrap();
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        let mut leg: JtagLeg = JtagLeg::new(JtagChain::DR, "loopback");
+        leg.push_bytes(&test_key(0xC6), JtagEndian::Little).unwrap();
+        jm.add(leg);
+        assert_eq!(jm.run_to_completion(&mut jp), Ok(1));
+        let mut looped = [0u8; 32];
+        jm.get().unwrap().pop_bytes(&mut looped, JtagEndian::Little).unwrap();
+        assert_eq!(looped, test_key(0xC6));
+        jp.set_loopback(false).unwrap();
+    }
+}
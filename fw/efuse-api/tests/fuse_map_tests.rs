@@ -0,0 +1,137 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::fusemap::*;
+    use efuse_api::layout::*;
+    use efuse_api::test_utils::*;
+
+    fn map(efuse: &EfuseApi) -> Vec<String> {
+        let mut out = String::new();
+        efuse.write_fuse_map(&mut out).unwrap();
+        assert!(out.len() <= FUSE_MAP_LEN);
+        assert!(out.lines().all(|line| line.len() < FUSE_MAP_LINE));
+        out.lines().map(String::from).collect()
+    }
+
+    /// mostly blank, so the drawings have only a handful of key bits to show
+    fn key() -> [u8; 32] {
+        let mut key = [0u8; 32];
+        key[0] = 0x81;
+        key[31] = 0x5A;
+        key
+    }
+
+    /// a device with key(), a USER value and W_EN_B_KEY_USER fused, fetched and staged as fused
+    fn provisioned() -> EfuseApi {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::with_banks(banks_image_ecc(&key(), 0x0000_1234, CNTL_W_EN_B_KEY_USER));
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(key());
+        efuse.set_user(0x0000_1234);
+        efuse.set_cntl(CNTL_W_EN_B_KEY_USER).unwrap();
+        efuse
+    }
+
+    #[test]
+    fn fresh_device() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(map(&efuse), [
+            "bank kind   ecc    data                       fields                decode",
+            "   0 cntl              .... ..         ...... cntl[5:0] copy[19:14] copies agree",
+            "   1 key    EEEEEE ........ ........ ........ key[2:0]              ecc ok",
+            "   2 key    EEEEEE ........ ........ ........ key[5:3]              ecc ok",
+            "   3 key    EEEEEE ........ ........ ........ key[8:6]              ecc ok",
+            "   4 key    EEEEEE ........ ........ ........ key[11:9]             ecc ok",
+            "   5 key    EEEEEE ........ ........ ........ key[14:12]            ecc ok",
+            "   6 key    EEEEEE ........ ........ ........ key[17:15]            ecc ok",
+            "   7 key    EEEEEE ........ ........ ........ key[20:18]            ecc ok",
+            "   8 key    EEEEEE ........ ........ ........ key[23:21]            ecc ok",
+            "   9 key    EEEEEE ........ ........ ........ key[26:24]            ecc ok",
+            "  10 key    EEEEEE ........ ........ ........ key[29:27]            ecc ok",
+            "  11 shared EEEEEE ........ ........ ........ key[31:30] user[7:0]  ecc ok",
+            "  12 user   EEEEEE ........ ........ ........ user[31:8]            ecc ok",
+            ". blank  E blank ECC  1 burned  + to burn  ! burned but staged blank",
+        ]);
+    }
+
+    #[test]
+    fn provisioned_device() {
+        let mut efuse = provisioned();
+        assert_eq!(map(&efuse), [
+            "bank kind   ecc    data                       fields                decode",
+            "   0 cntl              ..1. ..         ..1... cntl[5:0] copy[19:14] copies agree",
+            "   1 key    11EE11 ........ ........ 1......1 key[2:0]              ecc ok",
+            "   2 key    EEEEEE ........ ........ ........ key[5:3]              ecc ok",
+            "   3 key    EEEEEE ........ ........ ........ key[8:6]              ecc ok",
+            "   4 key    EEEEEE ........ ........ ........ key[11:9]             ecc ok",
+            "   5 key    EEEEEE ........ ........ ........ key[14:12]            ecc ok",
+            "   6 key    EEEEEE ........ ........ ........ key[17:15]            ecc ok",
+            "   7 key    EEEEEE ........ ........ ........ key[20:18]            ecc ok",
+            "   8 key    EEEEEE ........ ........ ........ key[23:21]            ecc ok",
+            "   9 key    EEEEEE ........ ........ ........ key[26:24]            ecc ok",
+            "  10 key    EEEEEE ........ ........ ........ key[29:27]            ecc ok",
+            "  11 shared 11E11E ..11.1.. .1.11.1. ........ key[31:30] user[7:0]  ecc ok",
+            "  12 user   1EE111 ........ ........ ...1..1. user[31:8]            ecc ok",
+            ". blank  E blank ECC  1 burned  + to burn  ! burned but staged blank",
+        ]);
+
+        // staging the key's readback lock shows it in both copies
+        efuse.set_cntl(CNTL_W_EN_B_KEY_USER | CNTL_R_EN_B_KEY).unwrap();
+        assert_eq!(map(&efuse)[1], "   0 cntl              .+1. ..         .+1... cntl[5:0] copy[19:14] copies agree");
+    }
+
+    #[test]
+    fn conflicting_patch() {
+        // bit 0 of the key cleared and bit 1 set: the data bit and the ECC code both call
+        // for burned fuses to go blank
+        let mut efuse = provisioned();
+        let mut patched: [u8; 32] = key();
+        patched[0] = 0x82;
+        efuse.set_key(patched);
+        assert_eq!(map(&efuse), [
+            "bank kind   ecc    data                       fields                decode",
+            "   0 cntl              ..1. ..         ..1... cntl[5:0] copy[19:14] copies agree",
+            "   1 key    11E+!1 ........ ........ 1.....+! key[2:0]              ecc ok",
+            "   2 key    EEEEEE ........ ........ ........ key[5:3]              ecc ok",
+            "   3 key    EEEEEE ........ ........ ........ key[8:6]              ecc ok",
+            "   4 key    EEEEEE ........ ........ ........ key[11:9]             ecc ok",
+            "   5 key    EEEEEE ........ ........ ........ key[14:12]            ecc ok",
+            "   6 key    EEEEEE ........ ........ ........ key[17:15]            ecc ok",
+            "   7 key    EEEEEE ........ ........ ........ key[20:18]            ecc ok",
+            "   8 key    EEEEEE ........ ........ ........ key[23:21]            ecc ok",
+            "   9 key    EEEEEE ........ ........ ........ key[26:24]            ecc ok",
+            "  10 key    EEEEEE ........ ........ ........ key[29:27]            ecc ok",
+            "  11 shared 11E11E ..11.1.. .1.11.1. ........ key[31:30] user[7:0]  ecc ok",
+            "  12 user   1EE111 ........ ........ ...1..1. user[31:8]            ecc ok",
+            ". blank  E blank ECC  1 burned  + to burn  ! burned but staged blank",
+        ]);
+        assert_eq!(efuse.validate(), Err(EfuseError::IllegalTransition { bank: 1, mask: 0x0200_0001 }));
+    }
+
+    /// writes into a fixed buffer, failing once it's full
+    struct Fixed {
+        buf: [u8; FUSE_MAP_LEN],
+        len: usize,
+    }
+
+    impl core::fmt::Write for Fixed {
+        fn write_str(&mut self, s: &str) -> core::fmt::Result {
+            let end: usize = self.len + s.len();
+            self.buf.get_mut(self.len..end).ok_or(core::fmt::Error)?.copy_from_slice(s.as_bytes());
+            self.len = end;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn fits_a_fixed_buffer() {
+        let mut out = Fixed { buf: [0; FUSE_MAP_LEN], len: 0 };
+        provisioned().write_fuse_map(&mut out).unwrap();
+        assert_eq!(core::str::from_utf8(&out.buf[..out.len]).unwrap().lines().count(), FUSE_BANKS + 2);
+    }
+}
@@ -0,0 +1,325 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::device::*;
+    use efuse_api::layout::{EfuseLogical, FUSE_BANKS};
+    use efuse_api::sequences::*;
+    use efuse_api::sha256::Sha256;
+    use efuse_api::test_utils::*;
+
+    /// Passes everything to `inner`, hashing what's driven: a byte per cycle, TDI in bit 0 and
+    /// TMS in bit 1, and each pause as 0xFF then its length, little-endian
+    struct Golden<P: JtagPhy> {
+        inner: P,
+        hash: Sha256,
+        cycles: u32,
+    }
+
+    impl<P: JtagPhy> Golden<P> {
+        fn new(inner: P) -> Self {
+            Golden { inner, hash: Sha256::new(), cycles: 0 }
+        }
+
+        fn finish(self) -> (u32, [u8; 32]) {
+            (self.cycles, self.hash.finish())
+        }
+    }
+
+    impl<P: JtagPhy> JtagPhy for Golden<P> {
+        fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+            self.hash.update(&[tdi as u8 | (tms as u8) << 1]);
+            self.cycles += 1;
+            self.inner.sync(tdi, tms)
+        }
+
+        fn nosync(&mut self, tdi: bool, tms: bool, tck: bool) -> bool {
+            self.inner.nosync(tdi, tms, tck)
+        }
+
+        fn pause(&mut self, us: u32) {
+            self.hash.update(&[0xFF]);
+            self.hash.update(&us.to_le_bytes());
+            self.inner.pause(us);
+        }
+    }
+
+    /// Passes everything to `inner`, keeping what's driven as fixture lines: one per run of
+    /// cycles, with the pause before it (`-` for none), how many cycles, and the cycles packed
+    /// two to a hex digit, first cycle in the low bits, TDI then TMS
+    struct Trace<P: JtagPhy> {
+        inner: P,
+        runs: Vec<(Option<u32>, Vec<u8>)>,
+    }
+
+    impl<P: JtagPhy> Trace<P> {
+        fn new(inner: P) -> Self {
+            Trace { inner, runs: vec![(None, Vec::new())] }
+        }
+
+        /// forgets what's been driven so far
+        fn clear(&mut self) {
+            self.runs = vec![(None, Vec::new())];
+        }
+
+        fn lines(&self) -> Vec<String> {
+            self.runs.iter().filter(|(pause, cycles)| pause.is_some() || !cycles.is_empty()).map(|(pause, cycles)| {
+                let digits: String = cycles.chunks(2).map(|pair| format!("{:x}", pair[0] | pair.get(1).map_or(0, |c| c << 2))).collect();
+                match pause {
+                    Some(us) => format!("{} {} {}", us, cycles.len(), digits),
+                    None => format!("- {} {}", cycles.len(), digits),
+                }.trim_end().to_string()
+            }).collect()
+        }
+    }
+
+    impl<P: JtagPhy> JtagPhy for Trace<P> {
+        fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+            self.runs.last_mut().unwrap().1.push(tdi as u8 | (tms as u8) << 1);
+            self.inner.sync(tdi, tms)
+        }
+
+        fn nosync(&mut self, tdi: bool, tms: bool, tck: bool) -> bool {
+            self.inner.nosync(tdi, tms, tck)
+        }
+
+        fn pause(&mut self, us: u32) {
+            self.runs.push((Some(us), Vec::new()));
+            self.inner.pause(us);
+        }
+    }
+
+    /// the lines of a trace fixture, comments left out, each with its line number in the file
+    fn fixture(text: &str) -> Vec<(usize, &str)> {
+        text.lines().enumerate().filter(|(_, line)| !line.starts_with('#')).map(|(n, line)| (n + 1, line)).collect()
+    }
+
+    /// fails at the first line `trace` drove differently from `expected`, naming it
+    fn matches<P: JtagPhy>(trace: &Trace<P>, expected: &str) {
+        let lines: Vec<String> = trace.lines();
+        let expected: Vec<(usize, &str)> = fixture(expected);
+        for (line, &(n, golden)) in lines.iter().zip(expected.iter()) {
+            assert_eq!(line, golden, "line {} of the fixture", n);
+        }
+        assert_eq!(lines.len(), expected.len(), "runs of cycles");
+    }
+
+    fn hex(digest: &[u8; 32]) -> String {
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    // Captured from the 7-series path as it was before DeviceFamily existed. A change to any
+    // of these is a change to what's shifted into real parts, so it has to be deliberate.
+
+    #[test]
+    fn fetch() {
+        let mut jp = Golden::new(EfuseModelPhy::new());
+        let mut efuse: EfuseApi<SevenSeries> = EfuseApi::new();
+        let mut jm: JtagMach = JtagMach::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.read_idcode(&mut jm, &mut jp).unwrap();
+        efuse.usercode(&mut jm, &mut jp).unwrap();
+        efuse.read_dna(&mut jm, &mut jp).unwrap();
+        let (cycles, digest) = jp.finish();
+        assert_eq!((cycles, hex(&digest).as_str()), (927, "37a41ad487ade5f13bd8b2f2f567b1c092a201bf9dad682e449ebb1510b3d583"));
+    }
+
+    /// 7-series under another name, taking the provided to_banks(), bank_consistent() and
+    /// holds_key()
+    struct Renamed;
+
+    impl DeviceFamily for Renamed {
+        const PROFILE: DeviceProfile = SevenSeries::PROFILE;
+
+        fn commit_seq(profile: &DeviceProfile) -> Vec<SeqCmd> { SevenSeries::commit_seq(profile) }
+        fn bank_fuses(index: usize) -> u32 { SevenSeries::bank_fuses(index) }
+        fn bank_image(index: usize, logical: &EfuseLogical) -> u32 { SevenSeries::bank_image(index, logical) }
+        fn bank_image_ecc(index: usize, logical: &EfuseLogical) -> u32 { SevenSeries::bank_image_ecc(index, logical) }
+        fn from_banks(banks: &[u32; FUSE_BANKS]) -> EfuseLogical { SevenSeries::from_banks(banks) }
+        fn user_from_banks(banks: &[u32; FUSE_BANKS]) -> u32 { SevenSeries::user_from_banks(banks) }
+    }
+
+    fn burn<F: DeviceFamily>(mut efuse: EfuseApi<F>) -> (u32, [u8; 32]) {
+        let mut jp = Golden::new(EfuseModelPhy::new());
+        let mut jm: JtagMach = JtagMach::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(test_key(0x5C));
+        efuse.set_user(0x8BAD_F00D);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert!(efuse.verify_burn(&mut jm, &mut jp).unwrap().is_clean());
+        jp.finish()
+    }
+
+    #[test]
+    fn burn_and_verify() {
+        let (cycles, digest) = burn(EfuseApi::<SevenSeries>::new());
+        assert_eq!((cycles, hex(&digest).as_str()), (35436, "67ba84d6efc895b0da8609b671f559ce538dc9f663e555f1944577052d3148e4"));
+    }
+
+    #[test]
+    fn burn_without_the_isc_bracket() {
+        // the raw sequence, as shifted before the bracket was added
+        let profile = DeviceProfile { isc: None, ..DeviceProfile::SPARTAN7 };
+        let (cycles, digest) = burn(EfuseApi::new_with_profile(profile));
+        assert_eq!((cycles, hex(&digest).as_str()), (35400, "40aa531dc33d215f35a423656edfc6a713ac4237b28d97ebee5542f84c331707"));
+    }
+
+    #[test]
+    fn a_family_of_its_own() {
+        assert_eq!(burn(EfuseApi::<Renamed>::for_family()), burn(EfuseApi::new()));
+
+        // the provided checks say what 7-series' own do
+        let image: [u32; FUSE_BANKS] = SevenSeries::to_banks(&EfuseLogical { key: test_key(0x5C), user: 0x8BAD_F00D, cntl: 0x18 });
+        for (index, &bank) in image.iter().enumerate() {
+            assert_eq!(Renamed::holds_key(index), SevenSeries::holds_key(index), "bank {}", index);
+            for flip in [0, 1, 1 << 14, 1 << 23, 1 << 24, 1 << 31].iter() {
+                let word: u32 = (bank ^ flip) & SevenSeries::bank_fuses(index);
+                assert_eq!(Renamed::bank_consistent(index, word), SevenSeries::bank_consistent(index, word), "bank {} word {:#x}", index, word);
+            }
+        }
+    }
+
+    #[test]
+    fn the_default_profile() {
+        assert_eq!(DeviceProfile::default(), DeviceProfile::SPARTAN7);
+        assert_eq!(*EfuseApi::new().profile(), DeviceProfile::SPARTAN7);
+        assert_eq!(burn(EfuseApi::new_with_profile(DeviceProfile::SPARTAN7)), burn(EfuseApi::new()));
+    }
+
+    #[test]
+    fn another_profile() {
+        let profile = DeviceProfile {
+            params: DeviceParams { unlock: 0xa08a_28ac_0000_4003, ..DeviceParams::SEVEN_SERIES },
+            fuse_dna: Ir::XscDna.code(),
+            ..DeviceProfile::SPARTAN7
+        };
+        let mut efuse = EfuseApi::new_with_profile(profile);
+        let mut jp = EfuseModelPhy::new();
+        efuse.read_dna(&mut JtagMach::new(), &mut jp).unwrap();
+        assert_eq!(jp.ir_history(), [Ir::XscDna.code()]);
+
+        efuse.load_snapshot(&messages::FuseSnapshot { banks: [0; FUSE_BANKS] });
+        efuse.set_user(1);
+        let words: Vec<ProgramWord> = efuse.program_words().collect();
+        assert_eq!(words[0], ProgramWord { value: 0xa08a_28ac_0000_4003, kind: WordKind::Unlock });
+        assert_eq!(efuse.compile().unwrap().manifest().device_params_id, profile.params.id());
+        assert_ne!(profile.params.id(), DeviceParams::SEVEN_SERIES.id());
+    }
+
+    #[test]
+    fn commit_from_the_profile() {
+        let profile = DeviceProfile { ir_bits: 10, ..DeviceProfile::SPARTAN7 };
+        let spartan7: Vec<SeqCmd> = SevenSeries::commit_seq(&DeviceProfile::SPARTAN7);
+        let commit: Vec<SeqCmd> = SevenSeries::commit_seq(&profile);
+        assert_eq!(commit.len(), spartan7.len());
+        for (cmd, seven) in commit.iter().zip(&spartan7) {
+            assert_eq!((cmd.chain, cmd.value, cmd.tag), (seven.chain, seven.value, seven.tag));
+            match cmd.chain {
+                JtagChain::IR => assert_eq!((cmd.count, seven.count), (10, IR_BITS)),
+                JtagChain::DR => assert_eq!(cmd.count, seven.count),
+            }
+        }
+        assert_eq!(spartan7[0].count, DeviceParams::SEVEN_SERIES.dr_bits);
+    }
+
+    fn burn_cntl(mut efuse: EfuseApi) -> (u32, [u8; 32]) {
+        let mut jp = Golden::new(EfuseModelPhy::new());
+        let mut jm: JtagMach = JtagMach::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.override_boot_verification("golden vectors");
+        efuse.set_cntl_flags(layout::CntlFlags::W_EN_B_KEY_USER | layout::CntlFlags::R_EN_B_KEY);
+        efuse.burn_cntl(&mut jm, &mut jp).unwrap();
+        jp.finish()
+    }
+
+    #[test]
+    fn cntl() {
+        let (cycles, digest) = burn_cntl(EfuseApi::<SevenSeries>::new());
+        assert_eq!((cycles, hex(&digest).as_str()), (4147, "92d2697e99ac7e4773f16334497776744c69acbf67fa218f7159a520f1a58063"));
+        // and the raw sequence
+        let (cycles, digest) = burn_cntl(EfuseApi::new_with_profile(DeviceProfile { isc: None, ..DeviceProfile::SPARTAN7 }));
+        assert_eq!((cycles, hex(&digest).as_str()), (4075, "4883ac37194c9ea3bb22194c843ddf3195e0c8b4d5272f6f44b51fc211cb8f00"));
+    }
+
+    #[test]
+    fn compiled_words() {
+        let mut efuse: EfuseApi<SevenSeries> = EfuseApi::new();
+        efuse.load_snapshot(&messages::FuseSnapshot { banks: [0; layout::FUSE_BANKS] });
+        efuse.set_key(test_key(0x5C));
+        efuse.set_user(0x8BAD_F00D);
+        let manifest = efuse.compile().unwrap().manifest();
+        assert_eq!((manifest.word_count, hex(&manifest.sha256_of_words).as_str(), manifest.device_params_id), (444, "616f98c5b7473b72cfad6b502857271de8e3a29e11240b47096ea3da07b4393d", 883098443));
+    }
+
+    // The same, driven cycle for cycle, from the fixtures in data/: a change shows up as the
+    // line of the first command it touched. Each fixture says how it was recorded.
+
+    const FETCH: &str = include_str!("data/golden_fetch.txt");
+    const KEY_BANK_BURN: &str = include_str!("data/golden_key_bank_burn.txt");
+    const CNTL_BURN: &str = include_str!("data/golden_cntl_burn.txt");
+    const COMMIT: &str = include_str!("data/golden_commit.txt");
+
+    /// a blank device, fetched; what the fetch drove is cleared
+    fn fetched(efuse: &mut EfuseApi<SevenSeries>, jm: &mut JtagMach) -> Trace<EfuseModelPhy> {
+        let mut jp = Trace::new(EfuseModelPhy::new());
+        efuse.fetch(jm, &mut jp).unwrap();
+        jp.clear();
+        jp
+    }
+
+    fn key_bank_burn() -> Trace<EfuseModelPhy> {
+        let mut efuse: EfuseApi<SevenSeries> = EfuseApi::new();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = fetched(&mut efuse, &mut jm);
+        // key bytes 0 to 2, bank 1 alone
+        let mut key: [u8; 32] = [0; 32];
+        key[..3].copy_from_slice(&[0xA5, 0x3C, 0x81]);
+        efuse.set_key(key);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert_eq!(jp.inner.programmed().iter().map(|&(bank, _)| bank).max(), Some(1));
+        jp
+    }
+
+    #[test]
+    fn fetch_trace() {
+        let mut jp = Trace::new(EfuseModelPhy::new());
+        let mut efuse: EfuseApi<SevenSeries> = EfuseApi::new();
+        efuse.fetch(&mut JtagMach::new(), &mut jp).unwrap();
+        matches(&jp, FETCH);
+    }
+
+    #[test]
+    fn key_bank_burn_trace() {
+        matches(&key_bank_burn(), KEY_BANK_BURN);
+    }
+
+    #[test]
+    fn cntl_burn_trace() {
+        let mut efuse: EfuseApi<SevenSeries> = EfuseApi::new();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = fetched(&mut efuse, &mut jm);
+        efuse.override_boot_verification("golden vectors");
+        efuse.set_cntl_flags(layout::CntlFlags::W_EN_B_KEY_USER | layout::CntlFlags::R_EN_B_KEY);
+        efuse.burn_cntl(&mut jm, &mut jp).unwrap();
+        matches(&jp, CNTL_BURN);
+    }
+
+    #[test]
+    fn commit_trace() {
+        // shifted as burn() shifts any sequence, from RUN_TEST_IDLE
+        let mut efuse: EfuseApi<SevenSeries> = EfuseApi::new();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = fetched(&mut efuse, &mut jm);
+        jm.add_seq(&SevenSeries::commit_seq(&DeviceProfile::SPARTAN7)).unwrap();
+        while jm.has_pending() {
+            jp.pause(200);
+            jm.try_next(&mut jp).unwrap();
+        }
+        matches(&jp, COMMIT);
+
+        // and it's what a burn commits with
+        let commit: Vec<&str> = fixture(COMMIT).into_iter().map(|(_, line)| line).collect();
+        let burn: Vec<String> = key_bank_burn().lines();
+        assert!(burn.windows(commit.len()).any(|lines| lines.iter().zip(commit.iter()).all(|(line, golden)| line == golden)));
+    }
+}
@@ -0,0 +1,212 @@
+#![cfg(feature = "gpiod")]
+
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::gpiod::*;
+    use efuse_api::layout::*;
+    use efuse_api::test_utils::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    enum Pin {
+        Tck,
+        Tms,
+        Tdi,
+        Tdo,
+        Trst,
+    }
+
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    enum Event {
+        Set(Pin, bool),
+        Get(Pin),
+    }
+
+    /// The wires between the lines and a device, which is clocked on TCK's rising edge. The
+    /// device drives TDO for a cycle from the falling edge before it, so a read of TDO while
+    /// TCK is low clocks it early and holds the pins still until TCK rises.
+    struct Wire<P: JtagPhy> {
+        device: P,
+        tck: bool,
+        tms: bool,
+        tdi: bool,
+        /// TDO of the cycle under way, once read, with the TDI and TMS it was read with
+        early: Option<(bool, bool, bool)>,
+        events: Vec<Event>,
+        /// the pin to fail, if any
+        broken: Option<Pin>,
+        edges: usize,
+    }
+
+    impl<P: JtagPhy> Wire<P> {
+        fn tdo(&mut self) -> bool {
+            if let Some((tdo, tdi, tms)) = self.early {
+                assert_eq!((tdi, tms), (self.tdi, self.tms), "TDI or TMS changed after TDO was read");
+                return tdo;
+            }
+            let tdo: bool = self.device.sync(self.tdi, self.tms);
+            self.early = Some((tdo, self.tdi, self.tms));
+            tdo
+        }
+    }
+
+    struct MockLine<P: JtagPhy> {
+        wire: Rc<RefCell<Wire<P>>>,
+        pin: Pin,
+    }
+
+    impl<P: JtagPhy> GpioLine for MockLine<P> {
+        fn set(&mut self, high: bool) -> Result<(), GpioError> {
+            let mut wire = self.wire.borrow_mut();
+            if wire.broken == Some(self.pin) {
+                return Err(GpioError::Access { offset: self.pin as u32, message: "gone".to_string() });
+            }
+            wire.events.push(Event::Set(self.pin, high));
+            match self.pin {
+                Pin::Tck => {
+                    if high && !wire.tck {
+                        // the rising edge: the device takes TDI and TMS, if a read hasn't
+                        wire.tdo();
+                        wire.edges += 1;
+                    }
+                    if !high && wire.tck {
+                        wire.early = None;
+                    }
+                    wire.tck = high;
+                },
+                Pin::Tms => wire.tms = high,
+                Pin::Tdi => wire.tdi = high,
+                Pin::Tdo => panic!("TDO is an input"),
+                Pin::Trst => {},
+            }
+            Ok(())
+        }
+
+        fn get(&mut self) -> Result<bool, GpioError> {
+            let mut wire = self.wire.borrow_mut();
+            assert_eq!(self.pin, Pin::Tdo, "only TDO is read");
+            if wire.broken == Some(self.pin) {
+                return Err(GpioError::Access { offset: self.pin as u32, message: "gone".to_string() });
+            }
+            wire.events.push(Event::Get(self.pin));
+            Ok(if wire.tck { wire.early.is_some_and(|e| e.0) } else { wire.tdo() })
+        }
+    }
+
+    /// Notes the TDI and TMS of every cycle on the way to `device`
+    struct Recorder {
+        device: EfuseModelPhy,
+        cycles: Vec<(bool, bool)>,
+    }
+
+    impl JtagPhy for Recorder {
+        fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+            self.cycles.push((tdi, tms));
+            self.device.sync(tdi, tms)
+        }
+        fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool {
+            unimplemented!();
+        }
+        fn pause(&mut self, _us: u32) {}
+    }
+
+    fn mock<P: JtagPhy>(device: P, trst: bool) -> (GpiodPhy<MockLine<P>>, Rc<RefCell<Wire<P>>>) {
+        let wire = Rc::new(RefCell::new(Wire { device, tck: false, tms: false, tdi: false, early: None, events: Vec::new(), broken: None, edges: 0 }));
+        let line = |pin: Pin| MockLine { wire: wire.clone(), pin };
+        let phy = GpiodPhy::new(line(Pin::Tck), line(Pin::Tms), line(Pin::Tdi), line(Pin::Tdo),
+            if trst { Some(line(Pin::Trst)) } else { None }, Duration::ZERO).unwrap();
+        wire.borrow_mut().events.clear();
+        (phy, wire)
+    }
+
+    #[test]
+    fn a_cycle_in_order() {
+        let (mut jp, wire) = mock(EfuseModelPhy::new(), false);
+        assert_eq!(jp.try_sync(true, false), Ok(false));
+        assert_eq!(wire.borrow().events, [Event::Set(Pin::Tdi, true), Event::Set(Pin::Tms, false), Event::Get(Pin::Tdo),
+            Event::Set(Pin::Tck, true), Event::Set(Pin::Tck, false)]);
+        assert_eq!(wire.borrow().edges, 1);
+    }
+
+    #[test]
+    fn fetch_and_burn_over_gpio() {
+        let mut device = EfuseModelPhy::new();
+        device.set_idcode(0x1362_D093);
+        let (mut jp, wire) = mock(device, true);
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        assert_eq!(efuse.read_idcode(&mut jm, &mut jp), Ok(0x1362_D093));
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(test_key(0x3A));
+        efuse.set_user(0x0000_BEEF);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert_eq!(wire.borrow().device.banks(), banks_image_ecc(&test_key(0x3A), 0x0000_BEEF, 0));
+
+        let mut check: EfuseApi = EfuseApi::new();
+        check.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(check.phy_key(), test_key(0x3A));
+        assert_eq!(check.phy_user(), 0x0000_BEEF);
+        // TRST was left alone
+        assert!(wire.borrow().events.iter().all(|e| *e != Event::Set(Pin::Trst, false)));
+    }
+
+    #[test]
+    fn clocked_by_hand() {
+        // the cycles of an IDCODE read, replayed through nosync() with TCK low then high, read
+        // back what they did the first time
+        let mut device = EfuseModelPhy::new();
+        device.set_idcode(0x0362_7093);
+        let mut first = Recorder { device, cycles: Vec::new() };
+        let mut tdos: Vec<bool> = Vec::new();
+        let mut jm: JtagMach = JtagMach::new();
+        assert_eq!(EfuseApi::new().read_idcode(&mut jm, &mut first), Ok(0x0362_7093));
+        let mut device = EfuseModelPhy::new();
+        device.set_idcode(0x0362_7093);
+        let mut again = Recorder { device, cycles: Vec::new() };
+        for &(tdi, tms) in first.cycles.iter() {
+            tdos.push(again.sync(tdi, tms));
+        }
+
+        let mut device = EfuseModelPhy::new();
+        device.set_idcode(0x0362_7093);
+        let (mut jp, wire) = mock(device, false);
+        for (&(tdi, tms), &tdo) in first.cycles.iter().zip(tdos.iter()) {
+            assert_eq!(jp.nosync(tdi, tms, false), tdo);
+            jp.nosync(tdi, tms, true);
+        }
+        jp.nosync(false, false, false);
+        assert_eq!(wire.borrow().edges, first.cycles.len());
+        assert_eq!(wire.borrow().device.ir_history(), first.device.ir_history());
+        assert_eq!(jp.last_error(), None);
+    }
+
+    #[test]
+    fn trst() {
+        let (mut jp, wire) = mock(EfuseModelPhy::new(), true);
+        assert_eq!(jp.pulse_trst(), Ok(true));
+        assert_eq!(wire.borrow().events, [Event::Set(Pin::Trst, false), Event::Set(Pin::Trst, true)]);
+        let (mut jp, wire) = mock(EfuseModelPhy::new(), false);
+        assert_eq!(jp.pulse_trst(), Ok(false));
+        assert!(wire.borrow().events.is_empty());
+    }
+
+    #[test]
+    fn a_failed_line() {
+        let (mut jp, wire) = mock(EfuseModelPhy::new(), false);
+        wire.borrow_mut().broken = Some(Pin::Tdo);
+        assert_eq!(jp.try_sync(false, true), Err(PhyError::Transport));
+        assert_eq!(jp.last_error(), Some(&GpioError::Access { offset: Pin::Tdo as u32, message: "gone".to_string() }));
+        // a fetch fails rather than panicking, and works again once the line does
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        assert!(efuse.fetch(&mut jm, &mut jp).is_err());
+        wire.borrow_mut().broken = None;
+        assert_eq!(jp.try_sync(false, true), Ok(false));
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.phy_key(), [0; 32]);
+    }
+}
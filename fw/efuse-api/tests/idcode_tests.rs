@@ -0,0 +1,95 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::sequences::*;
+    use efuse_api::test_utils::*;
+
+    /// an XC7S50, silicon revision 3
+    const IDCODE: u32 = 0x3362_F093;
+
+    #[test]
+    fn reads_the_32_bit_idcode_after_reset() {
+        let mut jp = ScriptedPhy::new();
+        jp.on_dr(Ir::Idcode, 32, IDCODE as u128);
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        assert_eq!(efuse.idcode(&mut jm, &mut jp), Ok(IDCODE));
+        assert_eq!(jp.ir_history(), &[0b001001]);
+        let scans: Vec<&DrWrite> = jp.dr_writes(Ir::Idcode).collect();
+        assert_eq!(scans.len(), 1);
+        assert_eq!(scans[0].len(), 32);
+
+        // bit 0 of an IDCODE is always 1
+        let mut jp = ScriptedPhy::new();
+        jp.on_dr(Ir::Idcode, 32, 0xFFFF_FFFE);
+        assert_eq!(efuse.idcode(&mut jm, &mut jp), Err(EfuseError::NoIdcode { read: 0xFFFF_FFFE }));
+    }
+
+    /// burns test_key(0x6E) on a device reading back `idcode`, expecting `expected`
+    fn burn(idcode: u32, expected: Option<IdcodeMatch>) -> (Result<(), EfuseError>, EfuseApi, EfuseModelPhy) {
+        let mut jp = EfuseModelPhy::new();
+        jp.set_idcode(idcode);
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(test_key(0x6E));
+        efuse.set_burn_config(BurnConfig { expected_idcode: expected, ..BurnConfig::default() });
+        let result = efuse.burn(&mut jm, &mut jp);
+        (result, efuse, jp)
+    }
+
+    #[test]
+    fn wrong_part_is_refused() {
+        let expected = IdcodeMatch::part(IDCODE);
+        let (result, efuse, jp) = burn(0x0362_D093, Some(expected));
+        assert_eq!(result, Err(EfuseError::IdcodeMismatch { expected: IDCODE, mask: 0x0FFF_FFFF, read: 0x0362_D093 }));
+        assert!(jp.programmed().is_empty());
+        assert_eq!(efuse.last_report(), None);
+    }
+
+    #[test]
+    fn other_revisions_of_the_part_are_fine() {
+        let (result, _, jp) = burn(0x0362_F093, Some(IdcodeMatch::part(IDCODE)));
+        assert_eq!(result, Ok(()));
+        assert!(!jp.programmed().is_empty());
+
+        // unless the mask says otherwise
+        let (result, _, _) = burn(0x0362_F093, Some(IdcodeMatch { idcode: IDCODE, mask: !0 }));
+        assert_eq!(result, Err(EfuseError::IdcodeMismatch { expected: IDCODE, mask: !0, read: 0x0362_F093 }));
+    }
+
+    #[test]
+    fn unchecked_without_an_expected_idcode() {
+        let (result, _, _) = burn(0, None);
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn wrong_part_is_refused_cntl_too() {
+        let expected = IdcodeMatch::part(IDCODE);
+        let mismatch = Err(EfuseError::IdcodeMismatch { expected: IDCODE, mask: 0x0FFF_FFFF, read: 0x0362_D093 });
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        jp.set_idcode(0x0362_D093);
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.set_burn_config(BurnConfig { expected_idcode: Some(expected), ..BurnConfig::default() });
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.override_boot_verification("no key to boot with");
+        efuse.set_cntl(CNTL_W_EN_B_KEY_USER).unwrap();
+        assert_eq!(efuse.burn_cntl(&mut jm, &mut jp), mismatch);
+        assert!(jp.programmed().is_empty());
+        assert_eq!(efuse.last_report(), None);
+
+        // and so is repairing copies that disagree
+        let mut banks = [0u32; FUSE_BANKS];
+        banks[CNTL_BANK] = CntlCopy::Primary.deposit(CNTL_W_EN_B_KEY_USER);
+        let mut jp = EfuseModelPhy::with_banks(banks);
+        jp.set_idcode(0x0362_D093);
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.repair_cntl(&mut jm, &mut jp), mismatch);
+        assert!(jp.programmed().is_empty());
+        assert_eq!(efuse.last_report(), None);
+    }
+}
@@ -0,0 +1,142 @@
+#![cfg(feature = "fault-injection")]
+
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::integrity::*;
+    use efuse_api::layout::*;
+    use efuse_api::test_utils::*;
+
+    const USER: u32 = 0x00C0_FFEE;
+
+    /// a fetched blank device, with test_key(0x6B) and USER staged
+    fn staged(jm: &mut JtagMach, jp: &mut EfuseModelPhy) -> EfuseApi {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(jm, jp).unwrap();
+        efuse.set_key(test_key(0x6B));
+        efuse.set_user(USER);
+        efuse
+    }
+
+    /// a fused bit the staged image doesn't have: a key bit in bank 4
+    fn stray() -> u32 {
+        let image: u32 = bank_image_ecc(4, &test_key(0x6B), USER, 0);
+        (0..24).map(|bit| 1u32 << bit).find(|&bit| image & bit == 0).unwrap()
+    }
+
+    fn fault(path: VerdictPath, stage: VerdictStage) -> Option<VerdictFault> {
+        Some(VerdictFault { path, stage, bank: 4, flip: stray() })
+    }
+
+    #[test]
+    fn paths_agree_without_faults() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let efuse = staged(&mut jm, &mut jp);
+        assert!(efuse.validate().is_ok());
+
+        // really unreachable: both paths say so
+        let mut banks = [0u32; FUSE_BANKS];
+        banks[4] = efuse_ecc::efuse_ecc::add_ecc(stray());
+        let mut jp = EfuseModelPhy::with_banks(banks);
+        let efuse = staged(&mut jm, &mut jp);
+        let mask: u32 = banks[4] & !bank_image_ecc(4, &test_key(0x6B), USER, 0);
+        assert_eq!(efuse.validate().err(), Some(EfuseError::IllegalTransition { bank: 4, mask }));
+    }
+
+    #[test]
+    fn either_path_disagreeing_aborts_validation() {
+        for path in [VerdictPath::PerBank, VerdictPath::Readback].iter().copied() {
+            let mut jm: JtagMach = JtagMach::new();
+            let mut jp = EfuseModelPhy::new();
+            let mut efuse = staged(&mut jm, &mut jp);
+            efuse.inject_fault(fault(path, VerdictStage::Validate));
+            assert_eq!(efuse.validate().err(), Some(EfuseError::ValidationIntegrity), "{:?}", path);
+            assert_eq!(efuse.burn(&mut jm, &mut jp), Err(EfuseError::ValidationIntegrity));
+            assert!(jp.programmed().is_empty());
+            assert_eq!(efuse.last_report(), None);
+
+            efuse.inject_fault(None);
+            assert!(efuse.validate().is_ok());
+        }
+    }
+
+    #[test]
+    fn a_fault_cant_make_an_invalid_plan_valid() {
+        let mut banks = [0u32; FUSE_BANKS];
+        banks[4] = efuse_ecc::efuse_ecc::add_ecc(stray());
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::with_banks(banks);
+        let mut efuse = staged(&mut jm, &mut jp);
+        // the per-bank path no longer sees the stray bit, the readback path still does
+        efuse.inject_fault(Some(VerdictFault { path: VerdictPath::PerBank, stage: VerdictStage::Validate, bank: 4, flip: banks[4] }));
+        assert_eq!(efuse.validate().err(), Some(EfuseError::ValidationIntegrity));
+        // the readback path predicts from what's requested of the real banks, which leaves out
+        // the ECC bits the stray bit already set, so it still doesn't decode
+        efuse.inject_fault(Some(VerdictFault { path: VerdictPath::Readback, stage: VerdictStage::Validate, bank: 4, flip: banks[4] }));
+        let mask: u32 = banks[4] & !bank_image_ecc(4, &test_key(0x6B), USER, 0);
+        assert_eq!(efuse.validate().err(), Some(EfuseError::IllegalTransition { bank: 4, mask }));
+    }
+
+    #[test]
+    fn rechecked_before_the_first_unlock() {
+        for path in [VerdictPath::PerBank, VerdictPath::Readback].iter().copied() {
+            let mut jm: JtagMach = JtagMach::new();
+            let mut jp = EfuseModelPhy::new();
+            let mut efuse = staged(&mut jm, &mut jp);
+            efuse.inject_fault(fault(path, VerdictStage::PreUnlock));
+            // validate() is clean; the burn's own check isn't
+            assert!(efuse.validate().is_ok());
+            assert_eq!(efuse.burn(&mut jm, &mut jp), Err(EfuseError::ValidationIntegrity), "{:?}", path);
+            assert!(jp.programmed().is_empty());
+            assert_eq!(jp.commits(), 0);
+            assert!(!efuse.last_report().unwrap().committed);
+
+            efuse.inject_fault(None);
+            efuse.burn(&mut jm, &mut jp).unwrap();
+            assert_eq!(jp.banks(), banks_image_ecc(&test_key(0x6B), USER, 0));
+        }
+    }
+
+    #[test]
+    fn rechecked_before_each_cntl_copy() {
+        for path in [VerdictPath::PerBank, VerdictPath::Readback].iter().copied() {
+            let mut jm: JtagMach = JtagMach::new();
+            let mut jp = EfuseModelPhy::new();
+            let mut efuse = staged(&mut jm, &mut jp);
+            efuse.burn(&mut jm, &mut jp).unwrap();
+            efuse.fetch(&mut jm, &mut jp).unwrap();
+            efuse.override_boot_verification("no boot image on the bench");
+            efuse.set_cntl(CNTL_W_EN_B_KEY_USER).unwrap();
+            let commits: usize = jp.commits();
+            efuse.inject_fault(fault(path, VerdictStage::PreUnlock));
+            assert!(efuse.validate().is_ok());
+            assert_eq!(efuse.burn_cntl(&mut jm, &mut jp), Err(EfuseError::ValidationIntegrity), "{:?}", path);
+            assert!(!jp.programmed().iter().any(|&(bank, _)| bank == CNTL_BANK));
+            assert_eq!(jp.commits(), commits);
+            assert!(!efuse.last_report().unwrap().committed);
+
+            efuse.inject_fault(None);
+            efuse.burn_cntl(&mut jm, &mut jp).unwrap();
+            assert_eq!(jp.banks(), banks_image_ecc(&test_key(0x6B), USER, CNTL_W_EN_B_KEY_USER));
+        }
+    }
+
+    #[test]
+    fn single_banks_are_rechecked_too() {
+        for path in [VerdictPath::PerBank, VerdictPath::Readback].iter().copied() {
+            let mut jm: JtagMach = JtagMach::new();
+            let mut jp = EfuseModelPhy::new();
+            let mut efuse = staged(&mut jm, &mut jp);
+            efuse.inject_fault(fault(path, VerdictStage::PreUnlock));
+            assert_eq!(efuse.burn_single_bank(Bank::ALL[4], &mut jm, &mut jp), Err(EfuseError::ValidationIntegrity), "{:?}", path);
+            assert!(jp.programmed().is_empty());
+            assert_eq!(jp.commits(), 0);
+
+            // the verdict is on the bank being burned alone
+            efuse.burn_single_bank(Bank::ALL[5], &mut jm, &mut jp).unwrap();
+            assert_eq!(jp.banks()[5], bank_image_ecc(5, &test_key(0x6B), USER, 0));
+        }
+    }
+}
@@ -0,0 +1,57 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::messages::*;
+    use efuse_api::test_utils::*;
+
+    fn intent() -> EfuseIntent {
+        EfuseIntent { key: test_key(0x4D), user: 0x0000_1234, cntl: 0x08, allow_zero_key: false, allow_weak_key: false }
+    }
+
+    #[test]
+    fn applied_and_burned() {
+        let mut jp = EfuseModelPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.apply_intent(&intent()).unwrap();
+        assert_eq!(efuse.manifest(), ProvisioningManifest { key: test_key(0x4D), user: 0x0000_1234, cntl: 0x08 });
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert!(efuse.key_matches(&test_key(0x4D)));
+        assert_eq!((efuse.phy_user(), efuse.phy_cntl()), (0x0000_1234, 0x08));
+    }
+
+    #[test]
+    fn refused_intents_stage_nothing() {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.set_user(0x55);
+        let staged: ProvisioningManifest = efuse.manifest();
+
+        let zero = EfuseIntent { key: [0; 32], ..intent() };
+        assert_eq!(zero.check(), Err(EfuseError::ZeroKey));
+        assert_eq!(efuse.apply_intent(&zero), Err(EfuseError::ZeroKey));
+        let reserved = EfuseIntent { cntl: 0x48, ..intent() };
+        assert_eq!(efuse.apply_intent(&reserved), Err(EfuseError::CntlReserved { bits: 0x40 }));
+        assert_eq!(efuse.manifest(), staged);
+
+        // a zero key the intent allows leaves the key alone
+        efuse.apply_intent(&EfuseIntent { allow_zero_key: true, ..zero }).unwrap();
+        assert_eq!(efuse.api_key(), [0; 32]);
+        assert_eq!(efuse.api_user(), 0x0000_1234);
+    }
+
+    #[test]
+    fn weak_key_override() {
+        let mut jp = EfuseModelPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        let weak = EfuseIntent { key: [0x5A; 32], ..intent() };
+        efuse.apply_intent(&weak).unwrap();
+        assert!(matches!(efuse.validate(), Err(EfuseError::WeakKey { .. })));
+        efuse.apply_intent(&EfuseIntent { allow_weak_key: true, ..weak }).unwrap();
+        assert!(efuse.validate().unwrap().weak_key_overridden);
+    }
+}
@@ -0,0 +1,102 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::sequences::*;
+    use efuse_api::test_utils::*;
+
+    /// Flips the first bit the IR shifts out on the scans numbered in `corrupt`, counting from
+    /// the first after reset; scan `from` and every later one are flipped as well.
+    struct Garbled {
+        inner: EfuseModelPhy,
+        scans: usize,
+        ir_bits: usize,
+        corrupt: Vec<usize>,
+        from: usize,
+    }
+
+    impl Garbled {
+        fn new(corrupt: &[usize], from: usize) -> Self {
+            Garbled { inner: EfuseModelPhy::new(), scans: 0, ir_bits: 0, corrupt: corrupt.to_vec(), from }
+        }
+    }
+
+    impl JtagPhy for Garbled {
+        fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+            let shifting: bool = self.inner.tap() == TapState::ShiftIr;
+            let garble: bool = shifting && self.ir_bits == 0
+                && (self.scans >= self.from || self.corrupt.contains(&self.scans));
+            let tdo: bool = self.inner.sync(tdi, tms) ^ garble;
+            if shifting {
+                self.ir_bits += 1;
+                if self.inner.tap() == TapState::Exit1Ir {
+                    self.scans += 1;
+                    self.ir_bits = 0;
+                }
+            }
+            tdo
+        }
+        fn nosync(&mut self, tdi: bool, tms: bool, tck: bool) -> bool {
+            self.inner.nosync(tdi, tms, tck)
+        }
+        fn pause(&mut self, us: u32) {
+            self.inner.pause(us)
+        }
+    }
+
+    const USER: u32 = 0x0000_A5C3;
+
+    /// IR scans a fetch takes
+    fn fetch_scans() -> usize {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = Garbled::new(&[], usize::MAX);
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        jp.scans
+    }
+
+    #[test]
+    fn efuse_instruction_is_checked() {
+        assert_eq!(DeviceParams::SEVEN_SERIES.ir_verification, IrVerification::Mandatory);
+        assert_eq!(IR_CAPTURE & 0b11, 0b01);
+    }
+
+    #[test]
+    fn corrupt_scan_is_repeated() {
+        let fetch: usize = fetch_scans();
+        let mut reference = Garbled::new(&[], usize::MAX);
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut reference).unwrap();
+        efuse.set_user(USER);
+        efuse.burn(&mut jm, &mut reference).unwrap();
+
+        // ISC_ENABLE and JSTART aren't checked, so garbling them changes nothing; the EFUSE
+        // scan after them is repeated, once
+        let mut jp = Garbled::new(&[fetch, fetch + 1, fetch + 2], usize::MAX);
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_user(USER);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert_eq!(jp.scans, reference.scans + 1);
+        assert_eq!(jp.inner.banks(), reference.inner.banks());
+        assert_eq!(jp.inner.programmed(), reference.inner.programmed());
+    }
+
+    #[test]
+    fn corrupt_twice_stops_the_burn() {
+        let fetch: usize = fetch_scans();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = Garbled::new(&[], fetch);
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_user(USER);
+        let corrupt = JtagError::IrCorrupt { tag: "EFUSE", captured: IR_CAPTURE ^ 0b1 };
+        assert_eq!(efuse.burn(&mut jm, &mut jp), Err(EfuseError::Jtag { phase: Phase::Burn, err: corrupt }));
+        // ISC_ENABLE, JSTART and the two EFUSE scans, and not a word programmed; then
+        // ISC_DISABLE, after a reset
+        assert_eq!(jp.scans, fetch + 5);
+        assert!(jp.inner.programmed().is_empty());
+        assert_eq!(jp.inner.commits(), 0);
+    }
+}
@@ -0,0 +1,118 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::device::*;
+    use efuse_api::layout::*;
+    use efuse_api::sequences::*;
+    use efuse_api::status::*;
+    use efuse_api::test_utils::*;
+
+    /// burns `key` and `user` with `profile` on `jp`, returning the result and the instructions
+    /// the burn latched
+    fn burn(profile: DeviceProfile, key: [u8; 32], user: u32, jp: &mut EfuseModelPhy) -> (Result<(), EfuseError>, Vec<u32>) {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new_with_profile(profile);
+        efuse.fetch(&mut jm, jp).unwrap();
+        efuse.set_key(key);
+        efuse.set_user(user);
+        let fetched: usize = jp.ir_history().len();
+        let result: Result<(), EfuseError> = efuse.burn(&mut jm, jp);
+        (result, jp.ir_history()[fetched..].to_vec())
+    }
+
+    /// burns W_EN_B_KEY_USER on its own with `profile` on `jp`, returning the result and the
+    /// instructions the burn latched
+    fn burn_cntl(profile: DeviceProfile, jp: &mut EfuseModelPhy) -> (Result<(), EfuseError>, Vec<u32>) {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new_with_profile(profile);
+        efuse.fetch(&mut jm, jp).unwrap();
+        efuse.override_boot_verification("no key to boot with");
+        efuse.set_cntl(CNTL_W_EN_B_KEY_USER).unwrap();
+        let fetched: usize = jp.ir_history().len();
+        let result: Result<(), EfuseError> = efuse.burn_cntl(&mut jm, jp);
+        (result, jp.ir_history()[fetched..].to_vec())
+    }
+
+    /// the brackets and the CNTL readbacks in `irs`
+    fn brackets(irs: &[u32]) -> Vec<u32> {
+        let codes: [u32; 3] = [Ir::IscEnable.code(), Ir::IscDisable.code(), Ir::FuseCntl.code()];
+        irs.iter().copied().filter(|code| codes.contains(code)).collect()
+    }
+
+    fn count(irs: &[u32], ir: Ir) -> usize {
+        irs.iter().filter(|&&code| code == ir.code()).count()
+    }
+
+    #[test]
+    fn once_per_burn() {
+        // one bank, and all thirteen less CNTL
+        let mut one: [u8; 32] = [0; 32];
+        one[0] = 0x81;
+        for &(key, user) in [(one, 0), (test_key(0xB4), 0x7E57_AB1E)].iter() {
+            let mut jp = EfuseModelPhy::new();
+            let (result, irs) = burn(DeviceProfile::SPARTAN7, key, user, &mut jp);
+            result.unwrap();
+            assert_eq!((count(&irs, Ir::IscEnable), count(&irs, Ir::IscDisable)), (1, 1));
+            // before anything is programmed, and after the commit and its status read
+            assert_eq!(irs.first(), Some(&Ir::IscEnable.code()));
+            assert_eq!(irs.last(), Some(&Ir::IscDisable.code()));
+            assert_eq!(jp.banks(), banks_image_ecc(&key, user, 0));
+        }
+    }
+
+    #[test]
+    fn the_raw_sequence() {
+        let profile = DeviceProfile { isc: None, ..DeviceProfile::SPARTAN7 };
+        let (bracketed, raw) = (burn(DeviceProfile::SPARTAN7, test_key(0xB4), 0, &mut EfuseModelPhy::new()).1, burn(profile, test_key(0xB4), 0, &mut EfuseModelPhy::new()).1);
+        assert_eq!((count(&raw, Ir::IscEnable), count(&raw, Ir::IscDisable)), (0, 0));
+        assert_eq!(&bracketed[1..bracketed.len() - 1], &raw[..]);
+    }
+
+    #[test]
+    fn other_codes() {
+        let isc = IscBracket { enable: Ir::IscNoop.code(), disable: Ir::Bypass.code(), enable_idle: 100 };
+        let (result, irs) = burn(DeviceProfile { isc: Some(isc), ..DeviceProfile::SPARTAN7 }, test_key(0xB4), 0, &mut EfuseModelPhy::new());
+        result.unwrap();
+        assert_eq!((irs.first(), irs.last()), (Some(&Ir::IscNoop.code()), Some(&Ir::Bypass.code())));
+        assert_eq!(count(&irs, Ir::IscEnable), 0);
+    }
+
+    #[test]
+    fn once_per_cntl_copy() {
+        let mut jp = EfuseModelPhy::new();
+        let (result, irs) = burn_cntl(DeviceProfile::SPARTAN7, &mut jp);
+        result.unwrap();
+        // each copy programmed and committed in a bracket of its own, and read back once it's closed
+        let (enable, disable, cntl) = (Ir::IscEnable.code(), Ir::IscDisable.code(), Ir::FuseCntl.code());
+        assert_eq!(brackets(&irs), [enable, disable, cntl, enable, disable, cntl]);
+        assert_eq!(irs.first(), Some(&enable));
+        assert_eq!(jp.banks()[CNTL_BANK], CntlCopy::Primary.deposit(CNTL_W_EN_B_KEY_USER) | CntlCopy::Redundant.deposit(CNTL_W_EN_B_KEY_USER));
+
+        // without a bracket in the profile, just the readbacks
+        let (result, irs) = burn_cntl(DeviceProfile { isc: None, ..DeviceProfile::SPARTAN7 }, &mut EfuseModelPhy::new());
+        result.unwrap();
+        assert_eq!(brackets(&irs), [cntl, cntl]);
+    }
+
+    #[test]
+    fn closed_after_a_failed_cntl_copy() {
+        let mut jp = EfuseModelPhy::new();
+        jp.status_after_commit(FuseStatus::PROGRAM_ERROR);
+        let (result, irs) = burn_cntl(DeviceProfile::SPARTAN7, &mut jp);
+        assert!(matches!(result, Err(EfuseError::DeviceReportedError { .. })));
+        // the primary copy's bracket is closed, and the redundant copy isn't touched
+        assert_eq!(brackets(&irs), [Ir::IscEnable.code(), Ir::IscDisable.code()]);
+        assert_eq!(irs.last(), Some(&Ir::IscDisable.code()));
+    }
+
+    #[test]
+    fn closed_after_a_failed_burn() {
+        let mut jp = EfuseModelPhy::new();
+        jp.status_after_commit(FuseStatus::PROGRAM_ERROR);
+        let (result, irs) = burn(DeviceProfile::SPARTAN7, test_key(0xB4), 0, &mut jp);
+        assert_eq!(result, Err(EfuseError::DeviceReportedError { bank: None, raw_status: FuseStatus::PROGRAM_ERROR }));
+        assert_eq!((count(&irs, Ir::IscEnable), count(&irs, Ir::IscDisable)), (1, 1));
+        assert_eq!(irs.last(), Some(&Ir::IscDisable.code()));
+    }
+}
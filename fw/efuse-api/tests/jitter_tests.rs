@@ -0,0 +1,190 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::sequences::*;
+    use efuse_api::test_utils::*;
+
+    /// Records the length of every run of TMS=0 cycles spent in RUN_TEST/IDLE
+    struct IdleRecorder {
+        inner: EfuseModelPhy,
+        runs: Vec<u32>,
+        run: u32,
+    }
+
+    impl IdleRecorder {
+        fn new() -> Self {
+            IdleRecorder { inner: EfuseModelPhy::new(), runs: Vec::new(), run: 0 }
+        }
+    }
+
+    impl JtagPhy for IdleRecorder {
+        fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+            if self.inner.tap() == TapState::RunTestIdle && !tms {
+                self.run += 1;
+            } else if self.run != 0 {
+                self.runs.push(self.run);
+                self.run = 0;
+            }
+            self.inner.sync(tdi, tms)
+        }
+        fn nosync(&mut self, tdi: bool, tms: bool, tck: bool) -> bool {
+            self.inner.nosync(tdi, tms, tck)
+        }
+        fn pause(&mut self, us: u32) {
+            self.inner.pause(us)
+        }
+    }
+
+    const JITTER: JitterSpec = JitterSpec { min_cycles: 3, max_cycles: 40, seed: 0x5EED_0F1D_1E00 };
+
+    fn burn(jitter: Option<JitterSpec>) -> (EfuseApi, IdleRecorder) {
+        burn_with(BurnTiming { inter_bit_jitter: jitter, ..BurnTiming::default() })
+    }
+
+    fn burn_with(timing: BurnTiming) -> (EfuseApi, IdleRecorder) {
+        let mut jp = IdleRecorder::new();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(test_key(0x5C));
+        efuse.set_user(0x0042_1337);
+        efuse.set_burn_config(BurnConfig { timing, ..BurnConfig::default() });
+        jp.runs.clear();
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        // every burn opens with the dwell after ISC_ENABLE; the rest are the timing's
+        assert_eq!(jp.runs.remove(0), efuse.profile().isc.unwrap().enable_idle);
+        (efuse, jp)
+    }
+
+    /// the bits and bank select words the last burn of `efuse` shifted
+    fn counts(efuse: &EfuseApi) -> (usize, usize) {
+        let bits: usize = efuse.last_report().unwrap().requested.iter().map(|b| b.count_ones() as usize).sum();
+        let banks: usize = efuse.last_report().unwrap().requested.iter().filter(|&&b| b != 0).count();
+        // each bank opens and closes with a select
+        (bits, 2 * banks)
+    }
+
+    #[test]
+    fn no_idle_gaps_by_default() {
+        let (_, jp) = burn(None);
+        assert_eq!(jp.runs, Vec::<u32>::new());
+    }
+
+    #[test]
+    fn gaps_follow_the_seed() {
+        let (_, plain) = burn(None);
+        let (efuse, jp) = burn(Some(JITTER));
+        assert_eq!(jp.inner.banks(), banks_image_ecc(&test_key(0x5C), 0x0042_1337, 0));
+        assert_eq!(jp.inner.banks(), plain.inner.banks());
+        assert_eq!(jp.inner.programmed(), plain.inner.programmed());
+        assert_eq!(jp.inner.rejected(), 0);
+
+        // one gap per programmed bit, in range, and not all the same
+        let bits: usize = efuse.last_report().unwrap().requested.iter().map(|b| b.count_ones() as usize).sum();
+        assert_eq!(jp.runs.len(), bits);
+        let expected: Vec<u32> = (0..bits).map(|i| JITTER.gap(i)).collect();
+        assert_eq!(jp.runs, expected);
+        assert!(jp.runs.iter().all(|&g| (3..=40).contains(&g)));
+        assert!(jp.runs.iter().any(|&g| g != jp.runs[0]));
+
+        // same seed, same gaps; another seed, other gaps
+        assert_eq!(burn(Some(JITTER)).1.runs, jp.runs);
+        assert_ne!(burn(Some(JitterSpec { seed: 1, ..JITTER })).1.runs, jp.runs);
+    }
+
+    #[test]
+    fn idle_after_each_bit() {
+        let (plain_efuse, plain) = burn(None);
+        let (efuse, jp) = burn_with(BurnTiming { bit_idle_cycles: 25, ..BurnTiming::default() });
+        let (bits, _) = counts(&efuse);
+        assert_eq!(jp.runs, vec![25; bits]);
+        assert_eq!(jp.inner.programmed(), plain.inner.programmed());
+        assert_eq!(jp.inner.cycles(), plain.inner.cycles() + 25 * bits);
+        assert_eq!(counts(&plain_efuse), counts(&efuse));
+
+        // the idle comes before the wait word, the jitter after it
+        let (_, jp) = burn_with(BurnTiming { bit_idle_cycles: 25, inter_bit_jitter: Some(JITTER), ..BurnTiming::default() });
+        let expected: Vec<u32> = (0..bits).flat_map(|i| [25, JITTER.gap(i)]).collect();
+        assert_eq!(jp.runs, expected);
+    }
+
+    #[test]
+    fn idle_after_each_bank_select() {
+        let (efuse, jp) = burn_with(BurnTiming { select_idle_cycles: 9, ..BurnTiming::default() });
+        let (_, selects) = counts(&efuse);
+        assert_eq!(jp.runs, vec![9; selects]);
+        assert_eq!(jp.inner.banks(), banks_image_ecc(&test_key(0x5C), 0x0042_1337, 0));
+    }
+
+    #[test]
+    fn delays_are_paused() {
+        let (efuse, plain) = burn(None);
+        let (bits, selects) = counts(&efuse);
+        let (_, jp) = burn_with(BurnTiming { bit_delay_us: 12, select_delay_us: 300, ..BurnTiming::default() });
+        assert_eq!(jp.runs, Vec::<u32>::new());
+        assert_eq!(jp.inner.cycles(), plain.inner.cycles());
+        assert_eq!(jp.inner.elapsed_us(), plain.inner.elapsed_us() + 12 * bits as u64 + 300 * selects as u64);
+    }
+
+    #[test]
+    fn estimate_includes_the_timing() {
+        let timing = BurnTiming { bit_idle_cycles: 7, bit_delay_us: 5, select_idle_cycles: 3, select_delay_us: 100, inter_bit_jitter: Some(JITTER) };
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(test_key(0x5C));
+        efuse.set_user(0x0042_1337);
+        efuse.set_burn_config(BurnConfig { timing, ..BurnConfig::default() });
+        let estimate: BurnDuration = efuse.estimate_burn_duration();
+        let cycles: usize = jp.cycles();
+        let elapsed: u64 = jp.elapsed_us();
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert_eq!((jp.cycles() - cycles) as u64, estimate.cycles);
+        assert_eq!(jp.elapsed_us() - elapsed, estimate.pause_us);
+    }
+
+    #[test]
+    fn timing_per_word_kind() {
+        let timing = BurnTiming { bit_idle_cycles: 1, bit_delay_us: 2, select_idle_cycles: 3, select_delay_us: 4, inter_bit_jitter: None };
+        assert_eq!(timing.after(WordKind::Bit(17)), (1, 2));
+        assert_eq!(timing.after(WordKind::BankSelect), (3, 4));
+        assert_eq!(timing.after(WordKind::Unlock), (0, 0));
+        assert_eq!(timing.after(WordKind::Wait), (0, 0));
+        assert_eq!(BurnTiming::default().after(WordKind::Bit(0)), (0, 0));
+    }
+
+    #[test]
+    fn gaps_are_bounded() {
+        let huge = JitterSpec { min_cycles: u32::MAX, max_cycles: u32::MAX, seed: 7 };
+        assert!((0..100).all(|i| huge.gap(i) == MAX_JITTER_CYCLES));
+        let inverted = JitterSpec { min_cycles: 50, max_cycles: 10, seed: 7 };
+        assert!((0..100).all(|i| inverted.gap(i) == 10));
+    }
+
+    #[test]
+    fn estimate_includes_jitter() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(test_key(0x5C));
+        efuse.set_user(0x0042_1337);
+        let plain: BurnDuration = efuse.estimate_burn_duration();
+        efuse.set_burn_config(BurnConfig { timing: BurnTiming { inter_bit_jitter: Some(JITTER), ..BurnTiming::default() }, ..BurnConfig::default() });
+        let jittered: BurnDuration = efuse.estimate_burn_duration();
+        let bits: usize = (1..FUSE_BANKS).map(|b| bank_image_ecc(b, &test_key(0x5C), 0x0042_1337, 0).count_ones() as usize).sum();
+        let added: u64 = (0..bits).map(|i| JITTER.gap(i) as u64).sum();
+        assert_eq!(jittered.cycles, plain.cycles + added);
+        assert_eq!(jittered.pause_us, plain.pause_us);
+
+        // and it matches what the burn actually takes
+        let cycles: usize = jp.cycles();
+        let elapsed: u64 = jp.elapsed_us();
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert_eq!((jp.cycles() - cycles) as u64, jittered.cycles);
+        assert_eq!(jp.elapsed_us() - elapsed, jittered.pause_us);
+    }
+}
@@ -1,6 +1,5 @@
 // to see print outputs run with `cargo test -- --nocapture`
 extern crate std;
-#[macro_use]
 
 #[cfg(test)]
 mod tests {
@@ -17,17 +16,21 @@ mod tests {
     pub struct JtagTestPhy {
         time: f64,
         ofile: File,
+        tap: TapState,
+        ir_bits: usize,
     }
     #[cfg(test)]
     impl JtagTestPhy {
         pub fn new(filename: &str) -> Self {
             let path = Path::new(&filename);
-            let mut file = File::create(&path).unwrap();
+            let mut file = File::create(path).unwrap();
 
-            write!(file, "time, clk, tdo, tms, tdi\n").unwrap();
+            writeln!(file, "time, clk, tdo, tms, tdi").unwrap();
             JtagTestPhy {
                 time: 0.05,
                 ofile: file,
+                tap: TapState::TestLogicReset,
+                ir_bits: 0,
             }
         }
     }
@@ -45,20 +48,23 @@ mod tests {
                 local_tms = 1;
             }
             self.time += TIMESTEP;
-            write!(self.ofile, "{:.08}, {}, {}, {}, {}\n", self.time, 0, 0, local_tms, local_tdi).unwrap();
+            writeln!(self.ofile, "{:.08}, {}, {}, {}, {}", self.time, 0, 0, local_tms, local_tdi).unwrap();
             self.time += TIMESTEP;
-            write!(self.ofile, "{:.08}, {}, {}, {}, {}\n", self.time, 1, 0, local_tms, local_tdi).unwrap();
+            writeln!(self.ofile, "{:.08}, {}, {}, {}, {}", self.time, 1, 0, local_tms, local_tdi).unwrap();
             self.time += TIMESTEP;
-            write!(self.ofile, "{:.08}, {}, {}, {}, {}\n", self.time, 0, 0, local_tms, local_tdi).unwrap();
-
-            false
+            writeln!(self.ofile, "{:.08}, {}, {}, {}, {}", self.time, 0, 0, local_tms, local_tdi).unwrap();
+
+            // the IR shifts out the 01 every TAP captures, so that checked IR scans pass; the
+            // trace above only records what's driven
+            let tdo: bool = self.tap == TapState::ShiftIr && self.ir_bits == 0;
+            self.ir_bits = if self.tap == TapState::ShiftIr { self.ir_bits + 1 } else { 0 };
+            self.tap = self.tap.next(tms);
+            tdo
         }
 
         fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool {
             // not actually used, not implemented -- fail if called
-            assert!(false);
-
-            false
+            unimplemented!()
         }
 
         fn pause(&mut self, us: u32) {
@@ -69,11 +75,18 @@ mod tests {
     #[test]
     fn jtag_fetch() {
         let mut jm: JtagMach = JtagMach::new();
-        let mut jp: JtagTestPhy = JtagTestPhy::new("jtag_fetch.csv");
+        let mut buf = [Transition::BLANK; 4096];
+        let mut jp = RecordingPhy::new(JtagTestPhy::new("jtag_fetch.csv"), &mut buf);
 
         let mut efuse: EfuseApi = EfuseApi::new();
 
-        efuse.fetch(&mut jm, &mut jp);
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(jp.dropped(), 0);
+        // KEY, USER and CNTL, then, as they read blank, STAT to rule out a secured device: the
+        // type-1 read, the register itself and the desync. The test phy drives no DR data, so
+        // every one reads back blank
+        let shifts: Vec<(usize, u128)> = jp.iter_dr_shifts().map(|s| (s.len(), s.tdo_value())).collect();
+        assert_eq!(shifts, vec![(256, 0), (32, 0), (14, 0), (160, 0), (32, 0), (128, 0)]);
     }
 
     /// must manually analyze CSV outputs with e.g.:
@@ -86,17 +99,19 @@ mod tests {
 
         let mut efuse: EfuseApi = EfuseApi::new();
 
-        efuse.fetch(&mut jm, &mut jp);
+        efuse.fetch(&mut jm, &mut jp).unwrap();
         let mut key: [u8; 32] = [0; 32];
         key[0] = 0xB;
         key[31] = 0xF0;
         key[29] = 0x1;
         efuse.set_key(key);
         efuse.set_user(0xA000_0002);
-        efuse.set_cntl(0x3);
+        efuse.set_cntl(0x3).unwrap();
+        // a mostly-blank key plus CNTL bits is exactly what validate() warns about
+        efuse.allow_weak_key(true);
 
         assert!(efuse.is_valid());
-        assert!(efuse.burn(&mut jm, &mut jp));
+        efuse.burn(&mut jm, &mut jp).unwrap();
     }
 
     #[test]
@@ -106,7 +121,7 @@ mod tests {
 
         let mut efuse: EfuseApi = EfuseApi::new();
 
-        efuse.fetch(&mut jm, &mut jp);
+        efuse.fetch(&mut jm, &mut jp).unwrap();
         let mut key: [u8; 32] = [0; 32];
 
         // patch in a non-zero but valid value, because the fake PHY can't do this
@@ -123,7 +138,7 @@ mod tests {
         efuse.set_key(key);
 
         assert!(efuse.is_valid());
-        assert!(efuse.burn(&mut jm, &mut jp));
+        efuse.burn(&mut jm, &mut jp).unwrap();
     }
 
 }
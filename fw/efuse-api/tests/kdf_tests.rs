@@ -0,0 +1,80 @@
+#![cfg(feature = "kdf")]
+
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::kdf::*;
+    use efuse_api::test_utils::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn unhex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    const MASTER_INFO: &[u8] = b"betrusted-soc device key v1";
+    const DNA: u64 = 0x0123_4567_89AB_CDEF;
+
+    fn master() -> [u8; 32] {
+        let mut master = [0u8; 32];
+        for (i, m) in master.iter_mut().enumerate() {
+            *m = (i as u8).wrapping_mul(7).wrapping_add(3);
+        }
+        master
+    }
+
+    #[test]
+    fn sha256_vectors() {
+        assert_eq!(hex(&sha256(b"")), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(hex(&sha256(b"abc")), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(hex(&sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1");
+        // fed in uneven pieces
+        let mut h = Sha256::new();
+        for chunk in vec![b'a'; 1_000_000].chunks(999) {
+            h.update(chunk);
+        }
+        assert_eq!(hex(&h.finish()), "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0");
+    }
+
+    #[test]
+    fn hmac_vectors() {
+        // RFC 4231 test cases 1 and 6
+        assert_eq!(hex(&hmac_sha256(&[0x0b; 20], &[b"Hi There"])),
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7");
+        assert_eq!(hex(&hmac_sha256(&[0xaa; 131], &[b"Test Using Larger Than Block-Size Key", b" - Hash Key First"])),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54");
+    }
+
+    #[test]
+    fn hkdf_vector() {
+        // RFC 5869 test case 1
+        let mut okm = [0u8; 42];
+        hkdf_sha256(&unhex("000102030405060708090a0b0c"), &[0x0b; 22], &unhex("f0f1f2f3f4f5f6f7f8f9"), &mut okm);
+        assert_eq!(hex(&okm), "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865");
+    }
+
+    #[test]
+    fn derived_key_is_pinned() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        jp.set_dna(DNA);
+        let mut efuse: EfuseApi = EfuseApi::new();
+        assert_eq!(efuse.read_dna(&mut jm, &mut jp), Ok(DNA));
+
+        let fingerprint = efuse.stage_derived_key(&master(), MASTER_INFO, &mut jm, &mut jp).unwrap();
+        assert_eq!(format!("{}", fingerprint), "d317765dddc67f3e");
+        assert_eq!(fingerprint, KeyFingerprint::of(&efuse.api_key()));
+
+        // another device gets an unrelated key
+        jp.set_dna(DNA ^ 1);
+        let other = efuse.stage_derived_key(&master(), MASTER_INFO, &mut jm, &mut jp).unwrap();
+        assert_eq!(format!("{}", other), "ebf4b90d72221945");
+        // and so does another purpose
+        jp.set_dna(DNA);
+        assert_ne!(efuse.stage_derived_key(&master(), b"something else", &mut jm, &mut jp).unwrap(), fingerprint);
+    }
+}
@@ -0,0 +1,140 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::checksum::*;
+    use efuse_api::layout::*;
+    use efuse_api::test_utils::*;
+
+    fn key(seed: u8) -> [u8; 32] {
+        let mut key = [0u8; 32];
+        for (i, k) in key.iter_mut().enumerate() {
+            *k = (i as u8).wrapping_mul(53) ^ seed;
+        }
+        key
+    }
+
+    /// USER bits 15:8, the low byte of an otherwise blank bank 12
+    const FIELD: u32 = 0xFF << 8;
+
+    fn configured(field: u32) -> EfuseApi {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.set_burn_config(BurnConfig { key_checksum_field: Some(field), ..BurnConfig::default() });
+        efuse
+    }
+
+    #[test]
+    fn recorded_with_the_key() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse: EfuseApi = configured(FIELD);
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.check_key_checksum(&key(0x3C)), Some(KeyChecksumCheck::NotRecorded));
+
+        efuse.set_key(key(0x3C));
+        // whatever is staged in the field is replaced
+        efuse.set_user(0x0000_5AAB);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert_eq!(jp.commits(), 1);
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.phy_key(), key(0x3C));
+        assert_eq!(efuse.phy_user(), 0xAB | (key_checksum(&key(0x3C)) as u32) << 8);
+        assert_eq!(efuse.check_key_checksum(&key(0x3C)), Some(KeyChecksumCheck::Match));
+    }
+
+    #[test]
+    fn checked_once_the_key_is_unreadable() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse: EfuseApi = configured(FIELD);
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(key(0x3C));
+        efuse.set_cntl(CNTL_R_EN_B_KEY | CNTL_W_EN_B_KEY_USER).unwrap();
+        efuse.burn(&mut jm, &mut jp).unwrap();
+
+        jp.enforce_read_disable();
+        let mut locked: EfuseApi = configured(FIELD);
+        locked.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(locked.phy_key(), [0; 32]);
+        assert_eq!(locked.check_key_checksum(&key(0x3C)), Some(KeyChecksumCheck::Match));
+
+        let other: [u8; 32] = (0..=255).map(key).find(|k| key_checksum(k) != key_checksum(&key(0x3C))).unwrap();
+        assert_eq!(locked.check_key_checksum(&other), Some(KeyChecksumCheck::Mismatch {
+            fused: key_checksum(&key(0x3C)),
+            expected: key_checksum(&other),
+        }));
+
+        // with USER unreadable as well there is nothing left to compare against
+        let mut banks: [u32; FUSE_BANKS] = jp.banks();
+        banks[CNTL_BANK] |= CNTL_R_EN_B_USER as u32;
+        let mut jp = EfuseModelPhy::with_banks(banks);
+        jp.enforce_read_disable();
+        locked.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(locked.check_key_checksum(&key(0x3C)), Some(KeyChecksumCheck::NotRecorded));
+    }
+
+    #[test]
+    fn opt_in() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(key(0x3C));
+        efuse.set_user(0x0000_5AAB);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.phy_user(), 0x0000_5AAB);
+        assert_eq!(efuse.check_key_checksum(&key(0x3C)), None);
+
+        // a burn that doesn't write a key leaves the field as staged
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse: EfuseApi = configured(FIELD);
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_user(0x0000_5AAB);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.phy_user(), 0x0000_5AAB);
+    }
+
+    #[test]
+    fn collisions() {
+        // 8 bits can't tell every key apart: among a few hundred keys some share a checksum,
+        // and each checks out as a match for the other
+        let mut seen: [Option<u8>; 256] = [None; 256];
+        let (first, second) = (0..=255u8).find_map(|seed| {
+            let checksum: usize = key_checksum(&key(seed)) as usize;
+            seen[checksum].replace(seed).map(|earlier| (earlier, seed))
+        }).unwrap();
+        assert_ne!(key(first), key(second));
+
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse: EfuseApi = configured(FIELD);
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(key(first));
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.check_key_checksum(&key(second)), Some(KeyChecksumCheck::Match));
+
+        // the recorded checksum is never 0, so a blank field isn't mistaken for one
+        assert!((0..=255).all(|seed| key_checksum(&key(seed)) != 0));
+    }
+
+    #[test]
+    fn narrow_field() {
+        // four bits keep the low four bits of the checksum, and match about one key in 16
+        const NARROW: u32 = 0b1111 << 12;
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse: EfuseApi = configured(NARROW);
+        let seed: u8 = (0..=255).find(|&seed| key_checksum(&key(seed)) & 0xF != 0).unwrap();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(key(seed));
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.phy_user(), ((key_checksum(&key(seed)) & 0xF) as u32) << 12);
+        assert_eq!(efuse.check_key_checksum(&key(seed)), Some(KeyChecksumCheck::Match));
+        let alike: usize = (0..=255).filter(|&other| efuse.check_key_checksum(&key(other)) == Some(KeyChecksumCheck::Match)).count();
+        assert!(alike > 1);
+    }
+}
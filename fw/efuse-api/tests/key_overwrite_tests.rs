@@ -0,0 +1,105 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::test_utils::*;
+
+    const USER: u32 = 0x00C0_FF0E;
+
+    /// test_key(0x2B) with one more bit, that its bank's ECC can take: a different key that's reachable
+    /// with 0->1 transitions alone
+    fn superset_key() -> [u8; 32] {
+        let fused: [u32; FUSE_BANKS] = banks_image_ecc(&test_key(0x2B), USER, 0);
+        (0..30 * 8).map(|bit| {
+                let mut patched: [u8; 32] = test_key(0x2B);
+                patched[bit / 8] |= 1 << (bit % 8);
+                patched
+            })
+            .find(|patched| *patched != test_key(0x2B) && (1..=SHARED_BANK).all(|index| {
+                fused[index] & !bank_image_ecc(index, patched, USER, 0) == 0
+            }))
+            .expect("some key bit can be added")
+    }
+
+    fn programmed() -> EfuseModelPhy {
+        EfuseModelPhy::with_banks(banks_image_ecc(&test_key(0x2B), USER, 0))
+    }
+
+    #[test]
+    fn a_blank_device() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(test_key(0x2B));
+        efuse.set_user(USER);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert_eq!(jp.banks(), banks_image_ecc(&test_key(0x2B), USER, 0));
+    }
+
+    #[test]
+    fn the_same_key_again() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = programmed();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(test_key(0x2B));
+        efuse.set_user(USER);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert_eq!(jp.programmed(), []);
+
+        // USER[7:0] sit in bank 11 with key bytes 30/31, but aren't the key
+        let user: u32 = (0..0x100).map(|low| USER & !0xFF | low)
+            .find(|&user| user & USER == USER && user != USER
+                && jp.banks()[SHARED_BANK] & !bank_image_ecc(SHARED_BANK, &test_key(0x2B), user, 0) == 0)
+            .expect("some USER[7:0] bits can be added");
+        efuse.set_user(user);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert_eq!(jp.banks(), banks_image_ecc(&test_key(0x2B), user, 0));
+    }
+
+    #[test]
+    fn a_different_key() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = programmed();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_user(USER);
+
+        // only 0->1 transitions, and still refused
+        efuse.set_key(superset_key());
+        assert_eq!(efuse.validate(), Err(EfuseError::KeyAlreadyProgrammed));
+        assert_eq!(efuse.burn(&mut jm, &mut jp), Err(EfuseError::KeyAlreadyProgrammed));
+        assert_eq!(jp.programmed(), []);
+        let bank: usize = (1..=SHARED_BANK).find(|&index| efuse.bank(Bank::ALL[index]) != efuse.proposed_bank(Bank::ALL[index])).unwrap();
+        assert_eq!(efuse.burn_single_bank(Bank::ALL[bank], &mut jm, &mut jp), Err(EfuseError::KeyAlreadyProgrammed));
+        assert_eq!(jp.programmed(), []);
+
+        // a key that needs fuses cleared is refused as that first
+        efuse.set_key([0xFF; 32]);
+        assert!(matches!(efuse.validate(), Err(EfuseError::IllegalTransition { .. })));
+
+        efuse.set_key(superset_key());
+        efuse.force_key_patch(true);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert_eq!(jp.banks(), banks_image_ecc(&superset_key(), USER, 0));
+    }
+
+    #[test]
+    fn a_key_burned_partway() {
+        // a burn that stopped after bank 5: the banks left blank can still be burned
+        let mut banks: [u32; FUSE_BANKS] = banks_image_ecc(&test_key(0x2B), 0, 0);
+        for bank in banks[6..].iter_mut() {
+            *bank = 0;
+        }
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::with_banks(banks);
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_ne!(efuse.phy_key(), [0; 32]);
+        efuse.set_key(test_key(0x2B));
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert_eq!(jp.banks(), banks_image_ecc(&test_key(0x2B), 0, 0));
+    }
+}
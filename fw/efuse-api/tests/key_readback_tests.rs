@@ -0,0 +1,89 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::messages::*;
+    use efuse_api::test_utils::*;
+
+    const USER: u32 = 0x1234_5678;
+
+    /// a device with test_key(0x3D) and USER burned and the key readback disabled, enforcing it
+    fn locked() -> EfuseModelPhy {
+        let mut jp = EfuseModelPhy::with_banks(banks_image_ecc(&test_key(0x3D), USER, CNTL_R_EN_B_KEY));
+        jp.enforce_read_disable();
+        jp
+    }
+
+    #[test]
+    fn fetch_marks_the_key_unreadable() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = locked();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.fetch_report().key, KeyReadback::Unreadable);
+        assert_eq!(efuse.phy_key(), [0; 32]);
+        assert_eq!(efuse.phy_user(), USER);
+
+        // without R_EN_B_KEY it's readable
+        let mut jp = EfuseModelPhy::with_banks(banks_image_ecc(&test_key(0x3D), USER, 0));
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.fetch_report().key, KeyReadback::Readable);
+        assert_eq!(efuse.phy_key(), test_key(0x3D));
+    }
+
+    #[test]
+    fn key_changes_are_refused() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = locked();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_user(USER);
+        efuse.set_cntl(CNTL_R_EN_B_KEY).unwrap();
+
+        // even the key that's fused: nothing can tell it from another
+        efuse.set_key(test_key(0x3D));
+        assert_eq!(efuse.validate().err(), Some(EfuseError::KeyUnreadable));
+        assert!(!efuse.is_valid());
+        assert_eq!(efuse.burn(&mut jm, &mut jp), Err(EfuseError::KeyUnreadable));
+        assert!(jp.programmed().is_empty());
+    }
+
+    #[test]
+    fn user_bits_sharing_the_key_bank_are_refused() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = locked();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_cntl(CNTL_R_EN_B_KEY).unwrap();
+        efuse.set_user(USER | 0x80);
+        assert_eq!(efuse.validate().err(), Some(EfuseError::KeyUnreadable));
+
+        // bank 12 doesn't hold any of the key
+        efuse.set_user(USER | 0x8000_0000);
+        assert!(efuse.validate().is_ok());
+    }
+
+    #[test]
+    fn the_rest_can_still_be_burned() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = locked();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_user(USER);
+        efuse.set_cntl(CNTL_R_EN_B_KEY | CNTL_W_EN_B_KEY_USER).unwrap();
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert!(jp.programmed().iter().all(|&(bank, _)| bank == CNTL_BANK));
+        assert_eq!(jp.banks(), banks_image_ecc(&test_key(0x3D), USER, CNTL_R_EN_B_KEY | CNTL_W_EN_B_KEY_USER));
+    }
+
+    #[test]
+    fn snapshots_too() {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.load_snapshot(&FuseSnapshot { banks: banks_image_ecc(&[0; 32], USER, CNTL_R_EN_B_KEY) });
+        assert_eq!(efuse.fetch_report().key, KeyReadback::Unreadable);
+        efuse.set_key(test_key(0x3D));
+        assert_eq!(efuse.validate().err(), Some(EfuseError::KeyUnreadable));
+        assert_eq!(format!("{}", EfuseError::KeyUnreadable), "the key can't be read back, so neither it nor USER[7:0] can be changed");
+    }
+}
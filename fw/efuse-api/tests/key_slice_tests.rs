@@ -0,0 +1,49 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::test_utils::*;
+
+    #[test]
+    fn exact_length() {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        let blob: Vec<u8> = test_key(0x2B).to_vec();
+        assert_eq!(efuse.set_key_slice(&blob), Ok(()));
+        assert_eq!(efuse.api_key(), test_key(0x2B));
+
+        // as good as set_key() for a burn
+        let mut jp = EfuseModelPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert!(efuse.key_matches(&test_key(0x2B)));
+    }
+
+    #[test]
+    fn wrong_lengths_stage_nothing() {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.set_key([0x11; 32]);
+        let long: Vec<u8> = [test_key(0x2B), test_key(0x2B)].concat();
+        for &len in [0usize, 1, 16, 31, 33, 64].iter() {
+            assert_eq!(efuse.set_key_slice(&long[..len]), Err(EfuseError::KeyLength { len }));
+            assert_eq!(efuse.api_key(), [0x11; 32]);
+        }
+        assert_eq!(EfuseError::KeyLength { len: 31 }.to_string(), "a key is 32 bytes, not 31");
+    }
+
+    #[test]
+    fn words() {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        let mut words: [u32; 8] = [0; 8];
+        for (w, bytes) in words.iter_mut().zip(test_key(0x2B).chunks(4)) {
+            *w = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        efuse.set_key_words(&words);
+        assert_eq!(efuse.api_key(), test_key(0x2B));
+
+        efuse.set_key_words(&[0x0302_0100, 0, 0, 0, 0, 0, 0, 0xFFEE_DDCC]);
+        assert_eq!(efuse.api_key()[..4], [0x00, 0x01, 0x02, 0x03]);
+        assert_eq!(efuse.api_key()[28..], [0xCC, 0xDD, 0xEE, 0xFF]);
+    }
+}
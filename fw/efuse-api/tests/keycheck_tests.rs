@@ -0,0 +1,174 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::keycheck::*;
+    use efuse_api::test_utils::*;
+
+    /// a key with no structure to speak of
+    fn good_key() -> [u8; 32] {
+        let mut key = [0u8; 32];
+        let mut x: u32 = 0x1234_5678;
+        for k in key.iter_mut() {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            *k = (x >> 24) as u8;
+        }
+        key
+    }
+
+    #[test]
+    fn random_key_passes() {
+        let key = good_key();
+        assert!(distinct_bytes(&key) >= MIN_DISTINCT_BYTES);
+        assert_eq!(weak_key_reason(&key), None);
+    }
+
+    #[test]
+    fn identical_bytes() {
+        assert!(all_identical(&[0xA5; 32]));
+        assert!(!all_identical(&good_key()));
+        assert_eq!(weak_key_reason(&[0xA5; 32]), Some(WeakKeyReason::AllIdentical { byte: 0xA5 }));
+        assert_eq!(weak_key_reason(&[0; 32]), Some(WeakKeyReason::AllIdentical { byte: 0 }));
+    }
+
+    #[test]
+    fn periods() {
+        let mut key = [0u8; 32];
+        for (i, k) in key.iter_mut().enumerate() {
+            *k = [0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88][i % 8];
+        }
+        assert!(has_period(&key, 8));
+        assert!(!has_period(&key, 4));
+        assert_eq!(weak_key_reason(&key), Some(WeakKeyReason::Periodic { period: 8 }));
+
+        for (i, k) in key.iter_mut().enumerate() {
+            *k = [0xDE, 0xAD, 0xBE, 0xEF][i % 4];
+        }
+        assert!(has_period(&key, 4) && has_period(&key, 8));
+        assert_eq!(weak_key_reason(&key), Some(WeakKeyReason::Periodic { period: 4 }));
+
+        // one byte off breaks the period
+        key[31] ^= 1;
+        assert!(!has_period(&key, 4));
+    }
+
+    #[test]
+    fn fill_runs() {
+        let mut key = good_key();
+        assert!(!matches!(longest_fill_run(&key), Some((_, len)) if len >= MAX_FILL_RUN));
+        key[10..14].copy_from_slice(&[0xFF; 4]);
+        assert_eq!(longest_fill_run(&key), Some((0xFF, 4)));
+        assert_eq!(weak_key_reason(&key), Some(WeakKeyReason::FillRun { byte: 0xFF, len: 4 }));
+
+        // a trailing zero fill, e.g. a 24-byte key copied into a 32-byte buffer
+        let mut key = good_key();
+        key[24..].copy_from_slice(&[0; 8]);
+        assert_eq!(weak_key_reason(&key), Some(WeakKeyReason::FillRun { byte: 0, len: 8 }));
+
+        // alternating 0x00/0xFF isn't a run
+        let mut key = good_key();
+        key[0..6].copy_from_slice(&[0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF]);
+        assert_eq!(longest_fill_run(&key), Some((0x00, 1)));
+    }
+
+    #[test]
+    fn few_distinct() {
+        let mut key = [0u8; 32];
+        for (i, k) in key.iter_mut().enumerate() {
+            // 10 values, in no periodic order
+            *k = 0x30 + ((i * 7 + i / 5) % 10) as u8;
+        }
+        assert_eq!(distinct_bytes(&key), 10);
+        assert_eq!(weak_key_reason(&key), Some(WeakKeyReason::FewDistinct { distinct: 10 }));
+    }
+
+    fn api() -> (EfuseApi, JtagMach, EfuseModelPhy) {
+        let mut jp = EfuseModelPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        (efuse, jm, jp)
+    }
+
+    #[test]
+    fn weak_key_is_a_warning() {
+        let (mut efuse, mut jm, mut jp) = api();
+        // the blank key isn't being changed, so it isn't checked
+        assert_eq!(efuse.validate().unwrap().warnings, vec![]);
+
+        efuse.set_key([0x5A; 32]);
+        let reason = WeakKeyReason::AllIdentical { byte: 0x5A };
+        let report = efuse.validate().unwrap();
+        assert_eq!(report.warnings, vec![ValidationWarning::SuspiciousKey { reason }]);
+        assert!(!report.weak_key_overridden);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert!(!efuse.last_report().unwrap().weak_key_overridden);
+    }
+
+    #[test]
+    fn weak_key_with_lockdown_is_an_error() {
+        let (mut efuse, mut jm, mut jp) = api();
+        efuse.set_key([0x5A; 32]);
+        efuse.set_cntl(0x08).unwrap();
+        let reason = WeakKeyReason::AllIdentical { byte: 0x5A };
+        assert_eq!(efuse.validate(), Err(EfuseError::WeakKey { reason }));
+        assert_eq!(efuse.burn(&mut jm, &mut jp), Err(EfuseError::WeakKey { reason }));
+        assert_eq!(jp.cycles(), efuse_fetch_cycles());
+
+        efuse.allow_weak_key(true);
+        assert!(efuse.validate().unwrap().weak_key_overridden);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert!(efuse.last_report().unwrap().weak_key_overridden);
+
+        // a good key with the same lockdown needs no override
+        let (mut efuse, _, _) = api();
+        efuse.set_key(good_key());
+        efuse.set_cntl(0x08).unwrap();
+        assert_eq!(efuse.validate().unwrap(), ValidationReport::default());
+    }
+
+    #[test]
+    fn constant_time_compare() {
+        let key = good_key();
+        assert!(ct_eq_32(&key, &key));
+        assert!(ct_eq_32(&[0; 32], &[0; 32]));
+        // a single bit off anywhere is a mismatch
+        for i in 0..32 {
+            for bit in 0..8 {
+                let mut other = key;
+                other[i] ^= 1 << bit;
+                assert!(!ct_eq_32(&key, &other));
+                assert!(!ct_eq_32(&other, &key));
+            }
+        }
+
+        // against the fetched key and the staged one
+        let (mut efuse, mut jm, mut jp) = api();
+        efuse.set_key(key);
+        assert!(efuse.key_matches(&[0; 32]));
+        assert!(!efuse.key_matches(&key));
+        assert!(ct_eq_32(&efuse.api_key(), &key));
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert!(efuse.key_matches(&key));
+    }
+
+    #[test]
+    fn constant_time_compare_has_no_branches() {
+        // every byte is looked at and nothing decides on the data before the end
+        let source = include_str!("../src/keycheck.rs");
+        let start = source.find("pub fn ct_eq_32(").unwrap();
+        let body = &source[start..start + source[start..].find("\n}\n").unwrap()];
+        for branch in ["if ", "match ", "return", "break", "while ", "&&", "||", "?", ".all(", ".any(", ".find(", ".position("].iter() {
+            assert!(!body.contains(branch), "ct_eq_32 has {:?}", branch);
+        }
+        assert_eq!(body.matches("==").count(), 1);
+        assert!(body.contains("for i in 0..32 {"));
+    }
+
+    fn efuse_fetch_cycles() -> usize {
+        api().2.cycles()
+    }
+}
@@ -0,0 +1,157 @@
+#[cfg(test)]
+mod tests {
+    use efuse_api::*;
+    use efuse_api::keyhex::*;
+
+    const HEX: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+    /// the key HEX describes, most significant byte first
+    fn key() -> KeyBytes {
+        let mut key = [0u8; 32];
+        for (i, k) in key.iter_mut().enumerate() {
+            *k = 31 - i as u8;
+        }
+        key
+    }
+
+    fn formatted(key: &KeyBytes, case: HexCase, order: KeyOrder) -> String {
+        let mut s = String::new();
+        format_ordered(key, &mut s, case, order).unwrap();
+        s
+    }
+
+    #[test]
+    fn byte_order() {
+        // the first digits are the most significant byte, which is the last byte of the array
+        let key = parse(HEX).unwrap();
+        assert_eq!(key, self::key());
+        assert_eq!(key[31], 0x00);
+        assert_eq!(key[0], 0x1f);
+
+        let lsb = parse_ordered(HEX, KeyOrder::LsbFirst).unwrap();
+        for i in 0..32 {
+            assert_eq!(lsb[i], key[31 - i]);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        for &order in [KeyOrder::MsbFirst, KeyOrder::LsbFirst].iter() {
+            let key = parse_ordered(HEX, order).unwrap();
+            assert_eq!(formatted(&key, HexCase::Lower, order), HEX);
+        }
+        let mut s = String::new();
+        format(&key(), &mut s, HexCase::Upper).unwrap();
+        assert_eq!(s, HEX.to_uppercase());
+        assert_eq!(parse(&s), Ok(key()));
+    }
+
+    #[test]
+    fn matches_the_fuse_layout() {
+        // the console prints the key read back from the fuses MSB first; bank 1 holds key[0..3]
+        let mut key = [0u8; 32];
+        key[0] = 0xAB;
+        key[31] = 0xCD;
+        let s = formatted(&key, HexCase::Lower, KeyOrder::MsbFirst);
+        assert!(s.starts_with("cd00"));
+        assert!(s.ends_with("00ab"));
+        let banks = layout::banks_image_ecc(&parse(&s).unwrap(), 0, 0);
+        assert_eq!(banks[1] & 0xFF, 0xAB);
+        assert_eq!((banks[layout::SHARED_BANK] >> 8) & 0xFF, 0xCD);
+    }
+
+    #[test]
+    fn separators_and_prefix() {
+        let spaced: String = HEX.as_bytes().chunks(8).map(|c| std::str::from_utf8(c).unwrap()).collect::<Vec<_>>().join(" ");
+        assert_eq!(parse(&spaced), Ok(key()));
+        let underscored: String = HEX.as_bytes().chunks(4).map(|c| std::str::from_utf8(c).unwrap()).collect::<Vec<_>>().join("_");
+        assert_eq!(parse(&underscored), Ok(key()));
+        assert_eq!(parse(&format!("  0x{}\n", HEX)), Ok(key()));
+        assert_eq!(parse(&format!("0X{}", HEX.to_uppercase())), Ok(key()));
+        // mixed case
+        assert_eq!(parse(&HEX.replace('a', "A")), Ok(key()));
+    }
+
+    #[test]
+    fn invalid_digits() {
+        let mut bad = String::from(HEX);
+        bad.replace_range(10..11, "g");
+        assert_eq!(parse(&bad), Err(HexError::InvalidDigit { position: 10, found: 'g' }));
+        // positions count from the start of the input, prefix and whitespace included
+        assert_eq!(parse(&format!(" 0x{}", bad)), Err(HexError::InvalidDigit { position: 13, found: 'g' }));
+        // a prefix only counts at the start
+        assert_eq!(parse(&format!("00x{}", HEX)), Err(HexError::InvalidDigit { position: 2, found: 'x' }));
+        assert_eq!(parse(&format!("{}-", &HEX[..63])), Err(HexError::InvalidDigit { position: 63, found: '-' }));
+    }
+
+    #[test]
+    fn wrong_lengths() {
+        assert_eq!(parse(""), Err(HexError::WrongLength { digits: 0 }));
+        assert_eq!(parse("0x"), Err(HexError::WrongLength { digits: 0 }));
+        assert_eq!(parse(&HEX[..63]), Err(HexError::WrongLength { digits: 63 }));
+        assert_eq!(parse(&HEX[..62]), Err(HexError::WrongLength { digits: 62 }));
+        assert_eq!(parse(&format!("{}0", HEX)), Err(HexError::WrongLength { digits: 65 }));
+        assert_eq!(parse(&format!("{}{}", HEX, HEX)), Err(HexError::WrongLength { digits: 128 }));
+    }
+
+    #[test]
+    fn staged_as_hex() {
+        let mut efuse = EfuseApi::new();
+        assert_eq!(efuse.set_key_hex(HEX), Ok(()));
+        assert_eq!(efuse.api_key(), key());
+        // byte 0 of the array is the rightmost pair
+        assert_eq!(efuse.api_key()[0], 0x1f);
+        assert_eq!(efuse.api_key()[31], 0x00);
+        let mut out = [0u8; 64];
+        efuse.format_key_hex(&mut out);
+        assert_eq!(std::str::from_utf8(&out).unwrap(), HEX.to_uppercase());
+
+        // and back again, for a key with every nibble value in it
+        let mut spread = [0u8; 32];
+        for (i, k) in spread.iter_mut().enumerate() {
+            *k = (i as u8).wrapping_mul(0x47) ^ 0x9C;
+        }
+        efuse.set_key(spread);
+        efuse.format_key_hex(&mut out);
+        let mut again = EfuseApi::new();
+        again.set_key_hex(std::str::from_utf8(&out).unwrap()).unwrap();
+        assert_eq!(again.api_key(), spread);
+        let mut lower = [0u8; 64];
+        format_into(&spread, &mut lower, HexCase::Lower, KeyOrder::LsbFirst);
+        assert_eq!(std::str::from_utf8(&lower).unwrap(), formatted(&spread, HexCase::Lower, KeyOrder::LsbFirst));
+    }
+
+    #[test]
+    fn bad_hex_stages_nothing() {
+        let mut efuse = EfuseApi::new();
+        efuse.set_key([0x77; 32]);
+        for (input, err) in [
+            (&HEX[..63], HexError::WrongLength { digits: 63 }),
+            (&HEX[..1], HexError::WrongLength { digits: 1 }),
+            ("", HexError::WrongLength { digits: 0 }),
+        ].iter() {
+            assert_eq!(efuse.set_key_hex(input), Err(EfuseError::KeyHex { err: *err }));
+        }
+        let mut bad = String::from(HEX);
+        bad.replace_range(40..41, "z");
+        assert_eq!(efuse.set_key_hex(&bad), Err(EfuseError::KeyHex { err: HexError::InvalidDigit { position: 40, found: 'z' } }));
+        bad.replace_range(40..41, "\u{e9}");
+        assert_eq!(efuse.set_key_hex(&bad), Err(EfuseError::KeyHex { err: HexError::InvalidDigit { position: 40, found: '\u{e9}' } }));
+        assert_eq!(efuse.set_key_hex(&format!("{}0", HEX)), Err(EfuseError::KeyHex { err: HexError::WrongLength { digits: 65 } }));
+        assert_eq!(efuse.api_key(), [0x77; 32]);
+    }
+
+    #[test]
+    fn nky_files() {
+        let nky = format!("Device xc7s50;\nKey 0 {};\nKey StartCBC 00000000000000000000000000000000;\n", HEX);
+        assert_eq!(key_from_nky(&nky), Ok(key()));
+        assert_eq!(key_from_nky("Device xc7s50;\n"), Err(HexError::MissingKey));
+        assert_eq!(key_from_nky("Key 0 1234;"), Err(HexError::WrongLength { digits: 4 }));
+
+        let mut efuse = EfuseApi::new();
+        efuse.stage_from_nky(&nky).unwrap();
+        assert_eq!(efuse.api_key(), key());
+        assert_eq!(efuse.stage_from_nky("Key StartCBC 00;"), Err(HexError::MissingKey));
+        assert_eq!(efuse.api_key(), key());
+    }
+}
@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::keysource::*;
+    use efuse_api::layout::*;
+    use efuse_api::sequences::*;
+    use efuse_api::test_utils::*;
+
+    /// a device with `key` and `cntl` fused, whose STAT and CTL0 read as given
+    fn device(key: &[u8; 32], cntl: u8, stat: u32, ctl0: u32) -> ScriptedPhy {
+        let mut jp = ScriptedPhy::new();
+        jp.on_dr_bytes(Ir::FuseKey, 256, key);
+        jp.on_dr(Ir::FuseCntl, 14, cntl as u128);
+        jp.on_config_register(REG_STAT, stat);
+        jp.on_config_register(REG_CTL0, ctl0);
+        jp
+    }
+
+    fn key_source(jp: &mut ScriptedPhy) -> KeySourceStatus {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.boot_key_source(&mut jm, jp).unwrap()
+    }
+
+    const KEY: [u8; 32] = [0x5A; 32];
+
+    #[test]
+    fn efuse_forced() {
+        // forced, even with a BBRAM-decrypted bitstream running
+        let status = key_source(&mut device(&KEY, CNTL_CFG_AES_ONLY, STAT_PART_SECURED, 0));
+        assert_eq!(status.source, KeySource::EfuseForced);
+        assert_eq!(status.evidence.bbram, BbramKey::Present);
+        assert_eq!(status.evidence.cntl, CNTL_CFG_AES_ONLY);
+    }
+
+    #[test]
+    fn bbram_present() {
+        let status = key_source(&mut device(&[0; 32], 0, STAT_PART_SECURED, 0));
+        assert_eq!(status.source, KeySource::BbramPresent);
+        assert_eq!(status.evidence.stat, STAT_PART_SECURED);
+        assert!(!status.evidence.efuse_key_set);
+    }
+
+    #[test]
+    fn efuse_by_default() {
+        // running an eFUSE-decrypted bitstream says nothing about BBRAM
+        let status = key_source(&mut device(&KEY, 0, STAT_PART_SECURED, CTL0_EFUSE_KEY));
+        assert_eq!(status.source, KeySource::EfuseByDefault);
+        assert_eq!(status.evidence.bbram, BbramKey::Unknown);
+        assert_eq!(status.evidence.ctl0, CTL0_EFUSE_KEY);
+
+        // nor does a plaintext one
+        assert_eq!(key_source(&mut device(&KEY, 0, 0, 0)).source, KeySource::EfuseByDefault);
+
+        // a key whose readback is disabled reads as zeros, but is there
+        let status = key_source(&mut device(&[0; 32], CNTL_R_EN_B_KEY, 0, 0));
+        assert_eq!(status.source, KeySource::EfuseByDefault);
+        assert!(status.evidence.efuse_key_hidden);
+        assert!(!status.evidence.efuse_key_set);
+    }
+
+    #[test]
+    fn no_key() {
+        let status = key_source(&mut device(&[0; 32], 0, STAT_DEC_ERROR, 0));
+        assert_eq!(status.source, KeySource::NoKey);
+        assert_eq!(status.evidence.stat, STAT_DEC_ERROR);
+    }
+
+    #[test]
+    fn register_reads() {
+        // with a key fused, the fetch doesn't read STAT itself (see access)
+        let mut jp = device(&KEY, 0, 0, 0);
+        key_source(&mut jp);
+        assert_eq!(jp.reads(Ir::CfgOut), 2);
+        // each read is sync, NOOP, a one-word type-1 read, two NOOPs; then a desync
+        let words = |w: &DrWrite| -> Vec<u32> {
+            w.bits.chunks(32).map(|c| c.iter().fold(0u32, |acc, &b| (acc << 1) | b as u32)).collect()
+        };
+        let writes: Vec<Vec<u32>> = jp.dr_writes(Ir::CfgIn).map(words).collect();
+        assert_eq!(writes, [
+            vec![0xAA99_5566, 0x2000_0000, 0x2800_E001, 0x2000_0000, 0x2000_0000],
+            vec![0x3000_8001, 0x0000_000D, 0x2000_0000, 0x2000_0000],
+            vec![0xAA99_5566, 0x2000_0000, 0x2800_A001, 0x2000_0000, 0x2000_0000],
+            vec![0x3000_8001, 0x0000_000D, 0x2000_0000, 0x2000_0000],
+        ]);
+        assert_eq!(KeySource::from_code(KeySource::BbramPresent as u8), Some(KeySource::BbramPresent));
+        assert_eq!(KeySource::from_code(0), None);
+    }
+}
@@ -0,0 +1,177 @@
+#[cfg(test)]
+mod tests {
+    use efuse_api::layout::*;
+    use efuse_ecc::efuse_ecc::*;
+
+    fn reference_key() -> [u8; 32] {
+        let mut key: [u8; 32] = [0; 32];
+        for (i, k) in key.iter_mut().enumerate() {
+            *k = 0x10 + i as u8;
+        }
+        key
+    }
+    const REFERENCE_USER: u32 = 0xA5C3_3C5A;
+    const REFERENCE_CNTL: u8 = 0x2B;
+
+    /// (bank, pre-ECC image, post-ECC image) for the reference key/user/cntl triple.
+    /// This table is the source of truth for the physical mapping; if it changes, the
+    /// fuse layout changed.
+    const REFERENCE_BANKS: [(usize, u32, u32); FUSE_BANKS] = [
+        ( 0, 0x000A_C02B, 0x000A_C02B),
+        ( 1, 0x0012_1110, 0x2D12_1110),
+        ( 2, 0x0015_1413, 0x2115_1413),
+        ( 3, 0x0018_1716, 0x2218_1716),
+        ( 4, 0x001B_1A19, 0x031B_1A19),
+        ( 5, 0x001E_1D1C, 0x271E_1D1C),
+        ( 6, 0x0021_201F, 0x0021_201F),
+        ( 7, 0x0024_2322, 0x2024_2322),
+        ( 8, 0x0027_2625, 0x1D27_2625),
+        ( 9, 0x002A_2928, 0x0F2A_2928),
+        (10, 0x002D_2C2B, 0x232D_2C2B),
+        (11, 0x005A_2F2E, 0x315A_2F2E),
+        (12, 0x00A5_C33C, 0x0FA5_C33C),
+    ];
+
+    #[test]
+    fn reference_images() {
+        let key = reference_key();
+        for &(bank, raw, ecc) in REFERENCE_BANKS.iter() {
+            assert_eq!(bank_image(bank, &key, REFERENCE_USER, REFERENCE_CNTL), raw, "raw image of bank {}", bank);
+            assert_eq!(bank_image_ecc(bank, &key, REFERENCE_USER, REFERENCE_CNTL), ecc, "ecc image of bank {}", bank);
+        }
+
+        let all = banks_image_ecc(&key, REFERENCE_USER, REFERENCE_CNTL);
+        for &(bank, _, ecc) in REFERENCE_BANKS.iter() {
+            assert_eq!(all[bank], ecc);
+        }
+    }
+
+    #[test]
+    fn ecc_only_on_key_user_banks() {
+        let key = reference_key();
+        assert_eq!(bank_image_ecc(CNTL_BANK, &key, REFERENCE_USER, REFERENCE_CNTL),
+            bank_image(CNTL_BANK, &key, REFERENCE_USER, REFERENCE_CNTL));
+        for bank in 1..FUSE_BANKS {
+            assert_eq!(bank_image_ecc(bank, &key, REFERENCE_USER, REFERENCE_CNTL),
+                add_ecc(bank_image(bank, &key, REFERENCE_USER, REFERENCE_CNTL)));
+        }
+    }
+
+    #[test]
+    fn blank_state_is_all_zero() {
+        let banks = banks_image_ecc(&[0; 32], 0, 0);
+        assert_eq!(banks, [0; FUSE_BANKS]);
+    }
+
+    #[test]
+    fn cntl_copy_ignores_reserved_bits() {
+        // only the bottom 6 bits are cntl fuses; both copies are masked the same way
+        assert_eq!(bank_image(CNTL_BANK, &[0; 32], 0, 0xFF), 0x3F | (0x3F << CNTL_COPY_SHIFT));
+    }
+
+    #[test]
+    fn single_byte_placement() {
+        // each key byte lands in exactly one bank, at the expected offset
+        for byte in 0..32 {
+            let mut key: [u8; 32] = [0; 32];
+            key[byte] = 0x81;
+            for bank in 0..FUSE_BANKS {
+                let expected: u32 = if bank == byte / 3 + 1 {
+                    0x81 << ((byte % 3) * 8)
+                } else {
+                    0
+                };
+                assert_eq!(bank_image(bank, &key, 0, 0), expected, "key byte {} in bank {}", byte, bank);
+            }
+        }
+        // user byte 0 shares bank 11 with the key, the rest is in bank 12
+        assert_eq!(bank_image(SHARED_BANK, &[0; 32], 0x0000_00C3, 0), 0x00C3_0000);
+        assert_eq!(bank_image(USER_BANK, &[0; 32], 0x0000_00C3, 0), 0);
+        assert_eq!(bank_image(USER_BANK, &[0; 32], 0x1234_5600, 0), 0x0012_3456);
+    }
+
+    #[test]
+    fn user_decodes_from_banks() {
+        let banks = banks_image_ecc(&reference_key(), REFERENCE_USER, REFERENCE_CNTL);
+        assert_eq!(user_from_banks(&banks), REFERENCE_USER);
+        // the ECC code in bits 29:24 isn't part of the value
+        assert_eq!(user_from_banks(&banks_image_ecc(&[0; 32], 0xFFFF_FFFF, 0)), 0xFFFF_FFFF);
+    }
+
+    /// xorshift32, so the states are the same on every run
+    fn next(x: &mut u32) -> u32 {
+        *x ^= *x << 13;
+        *x ^= *x >> 17;
+        *x ^= *x << 5;
+        *x
+    }
+
+    #[test]
+    fn logical_round_trip() {
+        let reference = EfuseLogical { key: reference_key(), user: REFERENCE_USER, cntl: REFERENCE_CNTL };
+        let banks = to_banks(&reference);
+        assert_eq!(banks, banks_image_ecc(&reference_key(), REFERENCE_USER, REFERENCE_CNTL));
+        assert_eq!(from_banks(&banks), reference);
+        assert_eq!(from_banks(&[0; FUSE_BANKS]), EfuseLogical::default());
+
+        let mut x: u32 = 0x9E37_79B9;
+        for _ in 0..2000 {
+            let mut key: [u8; 32] = [0; 32];
+            for k in key.iter_mut() {
+                *k = next(&mut x) as u8;
+            }
+            let logical = EfuseLogical { key, user: next(&mut x), cntl: next(&mut x) as u8 & CNTL_MASK };
+            let banks = to_banks(&logical);
+            assert_eq!(from_banks(&banks), logical);
+            // every bank carries a valid ECC code, and the two CNTL copies agree
+            assert!(banks_logical(&banks).iter().all(|view| view.is_consistent()), "{:?}", logical);
+        }
+    }
+
+    #[test]
+    fn logical_view_of_a_damaged_image() {
+        let reference = EfuseLogical { key: reference_key(), user: REFERENCE_USER, cntl: REFERENCE_CNTL };
+        let mut banks = to_banks(&reference);
+        // ECC bits, reserved CNTL bits and the redundant CNTL copy aren't part of the value
+        banks[4] ^= 0x3F00_0000;
+        banks[CNTL_BANK] |= 0x40 | (0x3F << CNTL_COPY_SHIFT);
+        assert_eq!(from_banks(&banks), reference);
+        // and a CNTL bit outside the mask doesn't make it into the image
+        assert_eq!(to_banks(&EfuseLogical { cntl: 0xC0 | REFERENCE_CNTL, ..reference }), to_banks(&reference));
+    }
+
+    #[test]
+    fn bank_views_of_reference_image() {
+        let banks = banks_image_ecc(&reference_key(), REFERENCE_USER, REFERENCE_CNTL);
+        let views = banks_logical(&banks);
+        assert_eq!(views[CNTL_BANK], BankView {
+            kind: BankKind::Cntl, data: 0x2B, ecc: 0, ecc_status: EccStatus::Duplicated { primary: 0x2B, copy: 0x2B },
+        });
+        assert_eq!(views[3], BankView { kind: BankKind::Key, data: 0x18_1716, ecc: 0x22, ecc_status: EccStatus::Valid });
+        assert_eq!(views[SHARED_BANK], BankView { kind: BankKind::Shared, data: 0x5A_2F2E, ecc: 0x31, ecc_status: EccStatus::Valid });
+        assert_eq!(views[USER_BANK], BankView { kind: BankKind::User, data: 0xA5_C33C, ecc: 0x0F, ecc_status: EccStatus::Valid });
+        for (index, view) in views.iter().enumerate() {
+            assert!(view.is_consistent(), "bank {}", index);
+            if index != CNTL_BANK {
+                assert_eq!(view.data, bank_image(index, &reference_key(), REFERENCE_USER, REFERENCE_CNTL), "bank {}", index);
+            }
+        }
+    }
+
+    #[test]
+    fn bank_views_flag_damage() {
+        // one ECC bit short of the reference bank 3
+        let view = BankView::decode(3, 0x2018_1716);
+        assert_eq!(view, BankView { kind: BankKind::Key, data: 0x18_1716, ecc: 0x20, ecc_status: EccStatus::Mismatch { expected: 0x22, flipped: Some(25) } });
+        assert!(!view.is_consistent());
+
+        // CNTL copies that disagree
+        let view = BankView::decode(CNTL_BANK, 0x03 | (0x01 << CNTL_COPY_SHIFT));
+        assert_eq!(view.ecc_status, EccStatus::Duplicated { primary: 0x03, copy: 0x01 });
+        assert_eq!(view.data, 0x03);
+        assert!(!view.is_consistent());
+
+        // a blank bank is consistent
+        assert!(BankView::decode(7, 0).is_consistent());
+    }
+}
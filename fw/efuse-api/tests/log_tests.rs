@@ -0,0 +1,102 @@
+#![cfg(feature = "log")]
+
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::events::*;
+    use efuse_api::test_utils::*;
+    use std::cell::RefCell;
+    use std::string::String;
+    use std::sync::Once;
+
+    std::thread_local! {
+        static LINES: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// Keeps each test thread's lines apart, as the tests run side by side
+    struct Capture;
+
+    impl log::Log for Capture {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            assert_eq!(record.target(), "efuse_api");
+            LINES.with(|lines| lines.borrow_mut().push(std::format!("{:?} {}", record.level(), record.args())));
+        }
+
+        fn flush(&self) {}
+    }
+
+    static CAPTURE: Capture = Capture;
+    static INSTALL: Once = Once::new();
+
+    /// the lines logged on this thread while `f` runs
+    fn logged<F: FnOnce()>(f: F) -> Vec<String> {
+        INSTALL.call_once(|| {
+            log::set_logger(&CAPTURE).unwrap();
+            log::set_max_level(log::LevelFilter::Trace);
+        });
+        LINES.with(|lines| lines.borrow_mut().clear());
+        f();
+        LINES.with(|lines| lines.borrow_mut().split_off(0))
+    }
+
+    /// true if `line` has the key in it, as hex either way round or in either case
+    fn leaks_key(line: &str) -> bool {
+        let forward: String = test_key(0x3E).iter().map(|b| std::format!("{:02x}", b)).collect();
+        let backward: String = test_key(0x3E).iter().rev().map(|b| std::format!("{:02x}", b)).collect();
+        let line: String = line.to_lowercase();
+        // any 4 bytes of it in a row would be too many
+        (0..=56).step_by(2).any(|i| line.contains(&forward[i..i + 8]) || line.contains(&backward[i..i + 8]))
+    }
+
+    #[test]
+    fn a_burn_step_by_step() {
+        let mut jp = EfuseModelPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.set_key(test_key(0x3E));
+        efuse.set_user(0x0000_00A5);
+        let lines: Vec<String> = logged(|| {
+            efuse.fetch(&mut jm, &mut jp).unwrap();
+            efuse.burn(&mut jm, &mut jp).unwrap();
+            efuse.fetch(&mut jm, &mut jp).unwrap();
+        });
+        assert!(lines.iter().all(|line| !leaks_key(line)), "{:#?}", lines);
+
+        assert_eq!(lines[0], std::format!("Info fetched: key {:016x}, 0 key fuses blown, user 0x00000000, cntl 0x00", fingerprint(&[0; 32])));
+        let blown: u32 = test_key(0x3E).iter().map(|b| b.count_ones()).sum();
+        let refetched: String = std::format!("Info fetched: key {:016x}, {} key fuses blown, user 0x000000a5, cntl 0x00", fingerprint(&test_key(0x3E)), blown);
+        assert_eq!(lines.last().unwrap(), &refetched);
+
+        let banks: Vec<usize> = efuse.last_report().unwrap().requested.iter().enumerate().filter(|r| *r.1 != 0).map(|r| r.0).collect();
+        let started: usize = lines.iter().filter(|line| line.contains("burning")).count();
+        let finished: usize = lines.iter().filter(|line| line.ends_with(": burned")).count();
+        assert_eq!((started, finished), (banks.len(), banks.len()));
+        let commit: usize = lines.iter().position(|line| line == "Info committing").unwrap();
+        assert_eq!(lines[commit + 1], "Info committed, status clean");
+        assert!(lines[1..commit].iter().all(|line| line.starts_with("Debug bank ")));
+    }
+
+    #[test]
+    fn a_refused_state_names_its_bank() {
+        let mut jp = EfuseModelPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(test_key(0x3E));
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+
+        efuse.set_key([0; 32]);
+        let mut err: Option<EfuseError> = None;
+        let lines: Vec<String> = logged(|| err = efuse.validate().err());
+        let err: EfuseError = err.unwrap();
+        assert!(matches!(err, EfuseError::IllegalTransition { .. }));
+        assert_eq!(lines, [std::format!("Warn validation failed: {}", err)]);
+        assert!(lines[0].contains("bank "));
+    }
+}
@@ -0,0 +1,130 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_ecc::efuse_ecc::add_ecc;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::messages::*;
+    use efuse_api::sequences::*;
+    use efuse_api::test_utils::*;
+
+    const USER: u32 = 0x1357_9BDF;
+
+    /// the sequence qualified on a blank golden unit
+    fn golden() -> CompiledSequence {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(test_key(0x3C));
+        efuse.set_user(USER);
+        let seq = efuse.compile().unwrap();
+        assert_eq!(seq.words().collect::<Vec<_>>(), efuse.program_words().collect::<Vec<_>>());
+        seq
+    }
+
+    #[test]
+    fn manifest_describes_the_sequence() {
+        let seq = golden();
+        let manifest = seq.manifest();
+        assert_eq!(manifest.word_count as usize, seq.words().count());
+        assert_eq!(manifest.device_params_id, DeviceParams::SEVEN_SERIES.id());
+        let image = banks_image_ecc(&test_key(0x3C), USER, 0);
+        for (bits, ones) in manifest.plan_summary.iter().zip(image.iter()) {
+            assert_eq!(*bits as u32, ones.count_ones());
+        }
+        assert_eq!(seq.programmed(), image);
+        // the same plan always compiles to the same words
+        assert_eq!(golden().manifest(), manifest);
+
+        let mut bytes = [0u8; VectorManifest::WIRE_LEN];
+        assert_eq!(manifest.encode(&mut bytes), VectorManifest::WIRE_LEN);
+        assert_eq!(VectorManifest::decode(&bytes), Some(manifest));
+        assert_eq!(VectorManifest::decode(&bytes[1..]), None);
+    }
+
+    #[test]
+    fn replays_on_a_blank_unit() {
+        let seq = golden();
+        let manifest = seq.manifest();
+
+        // the reference: the golden unit burned the usual way
+        let mut jm: JtagMach = JtagMach::new();
+        let mut reference = EfuseModelPhy::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut reference).unwrap();
+        efuse.set_key(test_key(0x3C));
+        efuse.set_user(USER);
+        efuse.burn(&mut jm, &mut reference).unwrap();
+
+        // nothing staged on the production side
+        let mut jp = EfuseModelPhy::new();
+        let mut production: EfuseApi = EfuseApi::new();
+        production.burn_from_manifest(&seq, &manifest, &mut jm, &mut jp).unwrap();
+        assert_eq!(jp.programmed(), reference.programmed());
+        assert_eq!(jp.banks(), banks_image_ecc(&test_key(0x3C), USER, 0));
+        assert_eq!(jp.commits(), 1);
+
+        let report = production.last_report().unwrap();
+        assert!(report.committed);
+        assert_eq!(report.requested, seq.programmed());
+        assert_eq!(report.manifest, Some(manifest));
+        let mut bytes = [0u8; BurnReport::MAX_WIRE_LEN];
+        assert_eq!(report.encode(&mut bytes), BurnReport::MAX_WIRE_LEN);
+        // the fuses programmed and the pulses aren't on the wire
+        assert_eq!(BurnReport::decode(&bytes), Some(BurnReport { programmed: [0; FUSE_BANKS], pulses: 0, ..report }));
+        // a plain burn's report has no manifest, and keeps its old encoding
+        assert_eq!(efuse.last_report().unwrap().manifest, None);
+        assert_eq!(efuse.last_report().unwrap().encode(&mut bytes), BurnReport::WIRE_LEN);
+    }
+
+    #[test]
+    fn refuses_a_manifest_that_doesnt_match() {
+        let seq = golden();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut production: EfuseApi = EfuseApi::new();
+
+        let mut tampered = seq.manifest();
+        tampered.sha256_of_words[7] ^= 0x20;
+        assert_eq!(production.burn_from_manifest(&seq, &tampered, &mut jm, &mut jp), Err(EfuseError::ManifestMismatch));
+        let mut tampered = seq.manifest();
+        tampered.device_params_id ^= 1;
+        assert_eq!(production.burn_from_manifest(&seq, &tampered, &mut jm, &mut jp), Err(EfuseError::ManifestMismatch));
+
+        // the manifest of a different plan
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.set_key(test_key(0x3C));
+        efuse.set_user(USER ^ 0x100);
+        let other = efuse.compile().unwrap().manifest();
+        assert_eq!(production.burn_from_manifest(&seq, &other, &mut jm, &mut jp), Err(EfuseError::ManifestMismatch));
+
+        assert!(jp.programmed().is_empty());
+        assert_eq!(jp.commits(), 0);
+        assert_eq!(production.last_report(), None);
+    }
+
+    #[test]
+    fn refuses_an_incompatible_device() {
+        let seq = golden();
+        let manifest = seq.manifest();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut production: EfuseApi = EfuseApi::new();
+
+        // already provisioned once: every fuse the sequence programs is blown
+        let mut jp = EfuseModelPhy::with_banks(banks_image_ecc(&test_key(0x3C), USER, 0));
+        assert_eq!(production.burn_from_manifest(&seq, &manifest, &mut jm, &mut jp),
+            Err(EfuseError::SequenceIncompatible { bank: 1, fuses: seq.programmed()[1] }));
+        assert!(jp.programmed().is_empty());
+
+        // a key bit the sequence doesn't program: the data doesn't clash, but its ECC does
+        let spare: u32 = (0..24).find(|&bit| seq.programmed()[5] & (1 << bit) == 0).unwrap();
+        let mut banks = [0u32; FUSE_BANKS];
+        banks[5] = add_ecc(1 << spare);
+        let mut jp = EfuseModelPhy::with_banks(banks);
+        assert!(matches!(production.burn_from_manifest(&seq, &manifest, &mut jm, &mut jp),
+            Err(EfuseError::SequenceIncompatible { bank: 5, fuses }) if fuses & 0xFF_FFFF == 0));
+        assert!(jp.programmed().is_empty());
+        assert_eq!(jp.commits(), 0);
+    }
+}
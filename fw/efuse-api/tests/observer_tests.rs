@@ -0,0 +1,144 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::observer::*;
+    use efuse_api::sequences::*;
+    use efuse_api::test_utils::*;
+
+    const USER: u32 = 0x0C0F_FEE5;
+
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    enum Event {
+        Started { bank: usize, bits: u32 },
+        Bit { bank: usize, bit: u32 },
+        Finished { bank: usize },
+    }
+
+    #[derive(Default)]
+    struct Recorder(Vec<Event>);
+
+    impl BurnObserver for Recorder {
+        fn bank_started(&mut self, bank: usize, bits_to_burn: u32) {
+            self.0.push(Event::Started { bank, bits: bits_to_burn });
+        }
+        fn bit_burned(&mut self, bank: usize, bit: u32) {
+            self.0.push(Event::Bit { bank, bit });
+        }
+        fn bank_finished(&mut self, bank: usize) {
+            self.0.push(Event::Finished { bank });
+        }
+    }
+
+    /// the events an ascending burn of `plan` should produce
+    fn expected(plan: &BurnPlan) -> Vec<Event> {
+        let mut events = Vec::new();
+        for (bank, bits) in plan.banks() {
+            events.push(Event::Started { bank, bits: bits.count_ones() });
+            events.extend((0..32).filter(|bit| bits & (1 << bit) != 0).map(|bit| Event::Bit { bank, bit }));
+            events.push(Event::Finished { bank });
+        }
+        events
+    }
+
+    /// a fetched EfuseApi with test_key(0x6B), USER and `cntl` staged
+    fn staged(jm: &mut JtagMach, jp: &mut EfuseModelPhy, cntl: u8) -> EfuseApi {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(jm, jp).unwrap();
+        efuse.set_key(test_key(0x6B));
+        efuse.set_user(USER);
+        efuse.set_cntl(cntl).unwrap();
+        efuse
+    }
+
+    #[test]
+    fn events_follow_the_dry_run_plan() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse = staged(&mut jm, &mut jp, CNTL_W_EN_B_KEY_USER);
+        let plan: BurnPlan = efuse.plan().unwrap();
+        let mut recorder = Recorder::default();
+        efuse.burn_with_observer(&mut recorder, &mut jm, &mut jp).unwrap();
+        assert_eq!(recorder.0, expected(&plan));
+        // one bit event per pulse, in the order they were blown
+        let bits: Vec<(usize, u8)> = recorder.0.iter()
+            .filter_map(|&event| match event { Event::Bit { bank, bit } => Some((bank, bit as u8)), _ => None })
+            .collect();
+        assert_eq!(bits, jp.programmed());
+        assert_eq!(bits.len(), plan.pulses as usize);
+    }
+
+    #[test]
+    fn shuffled_bits_are_reported_as_blown() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse = staged(&mut jm, &mut jp, 0);
+        efuse.set_burn_config(BurnConfig { order: BitOrderPolicy::Shuffled { seed: 0x5EED }, ..BurnConfig::default() });
+        let plan: BurnPlan = efuse.plan().unwrap();
+        let mut recorder = Recorder::default();
+        efuse.burn_with_observer(&mut recorder, &mut jm, &mut jp).unwrap();
+        let bits: Vec<(usize, u8)> = recorder.0.iter()
+            .filter_map(|&event| match event { Event::Bit { bank, bit } => Some((bank, bit as u8)), _ => None })
+            .collect();
+        assert_eq!(bits, jp.programmed());
+        // the same events as an ascending burn, bar the order within each bank
+        let mut sorted: Vec<Event> = recorder.0.clone();
+        sorted.sort_by_key(|&event| match event {
+            Event::Started { bank, .. } => (FUSE_BANKS - bank, 0),
+            Event::Bit { bank, bit } => (FUSE_BANKS - bank, 1 + bit),
+            Event::Finished { bank } => (FUSE_BANKS - bank, 33),
+        });
+        assert_eq!(sorted, expected(&plan));
+    }
+
+    #[test]
+    fn a_failed_bank_is_not_finished() {
+        /// fails every cycle once the model has taken `after` programming pulses
+        struct Dying { inner: EfuseModelPhy, after: usize }
+        impl JtagPhy for Dying {
+            fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+                self.inner.sync(tdi, tms)
+            }
+            fn nosync(&mut self, tdi: bool, tms: bool, tck: bool) -> bool {
+                self.inner.nosync(tdi, tms, tck)
+            }
+            fn pause(&mut self, us: u32) {
+                self.inner.pause(us);
+            }
+            fn try_sync(&mut self, tdi: bool, tms: bool) -> Result<bool, PhyError> {
+                if self.inner.programmed().len() >= self.after {
+                    return Err(PhyError::Transport);
+                }
+                Ok(self.inner.sync(tdi, tms))
+            }
+        }
+
+        let mut jm: JtagMach = JtagMach::new();
+        let mut model = EfuseModelPhy::new();
+        let mut efuse = staged(&mut jm, &mut model, 0);
+        let plan: BurnPlan = efuse.plan().unwrap();
+        let (first, bits) = plan.banks().next().unwrap();
+        let mut jp = Dying { inner: model, after: 3 };
+        let mut recorder = Recorder::default();
+        assert!(efuse.burn_with_observer(&mut recorder, &mut jm, &mut jp).is_err());
+        // the third pulse went out, but its wait word didn't
+        let blown: Vec<Event> = expected(&plan).into_iter().take(3).collect();
+        assert_eq!(recorder.0, blown);
+        assert_eq!(recorder.0[0], Event::Started { bank: first, bits: bits.count_ones() });
+        assert!(!recorder.0.contains(&Event::Finished { bank: first }));
+    }
+
+    #[test]
+    fn burn_is_an_unobserved_burn() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut observed = EfuseModelPhy::new();
+        let mut efuse = staged(&mut jm, &mut jp, 0);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        let mut efuse = staged(&mut jm, &mut observed, 0);
+        efuse.burn_with_observer(&mut (), &mut jm, &mut observed).unwrap();
+        assert_eq!(observed.programmed(), jp.programmed());
+        assert_eq!(observed.cycles(), jp.cycles());
+    }
+}
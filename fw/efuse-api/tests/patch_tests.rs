@@ -0,0 +1,96 @@
+#[cfg(test)]
+mod tests {
+    use efuse_ecc::efuse_ecc::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::messages::*;
+    use efuse_api::patch::*;
+
+    /// records 0-255 of a bank: the low nibble is the value, the high nibble is don't-care
+    const DONT_CARE: u32 = 0xF0;
+
+    /// the record plan_patch() should pick, found the slow way
+    fn brute_force(fused: u32, desired: u32) -> Option<u32> {
+        let base: u32 = desired | (fused & DONT_CARE);
+        (0..256u32)
+            .filter(|&data| data & !DONT_CARE == desired && data & base == base)
+            .filter(|&data| add_ecc(fused) & !add_ecc(data) == 0)
+            .min_by_key(|&data| ((data & !base).count_ones(), data))
+    }
+
+    #[test]
+    fn exhaustive_over_a_byte() {
+        for fused in 0..256u32 {
+            for desired in 0..16u32 {
+                let plan = plan_patch(5, add_ecc(fused), desired, DONT_CARE);
+                match brute_force(fused, desired) {
+                    Some(data) => {
+                        let plan: PatchPlan = plan.unwrap();
+                        assert_eq!(plan.target, add_ecc(data), "fused {:#x}, desired {:#x}", fused, desired);
+                        assert_eq!(plan.program, plan.target & !add_ecc(fused));
+                        assert_eq!(plan.extra, data & !desired);
+                        assert!(verify_ecc(plan.target));
+                    },
+                    None => assert_eq!(plan, Err(EfuseError::NoPatch { bank: 5, conflicts: add_ecc(fused) & !add_ecc(desired) })),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn direct_when_it_can_be() {
+        // a blank bank takes anything as it is
+        for desired in 0..256u32 {
+            let plan: PatchPlan = plan_patch(SHARED_BANK, 0, desired << 16, 0).unwrap();
+            assert_eq!(plan, PatchPlan { bank: SHARED_BANK, target: add_ecc(desired << 16), program: add_ecc(desired << 16), extra: 0 });
+        }
+        // and a fused one itself, with nothing to program
+        let fused: u32 = add_ecc(0x12_3456);
+        assert_eq!(plan_patch(7, fused, 0x12_3456, 0), Ok(PatchPlan { bank: 7, target: fused, program: 0, extra: 0 }));
+    }
+
+    #[test]
+    fn some_patches_need_a_superset() {
+        // the desired record alone would clear an ECC fuse, but one don't-care bit fixes that
+        let found = (0..256u32).flat_map(|fused| (0..16u32).map(move |desired| (fused, desired)))
+            .filter(|&(fused, desired)| add_ecc(fused) & !add_ecc(desired) != 0 && fused & !desired & 0xFF == 0)
+            .find_map(|(fused, desired)| plan_patch(5, add_ecc(fused), desired, DONT_CARE).ok().map(|plan| (fused, desired, plan)));
+        let (fused, desired, plan) = found.unwrap();
+        assert_ne!(plan.extra, 0);
+        assert_eq!(plan.extra & !DONT_CARE, 0);
+        assert_eq!(plan.target & 0xFF_FFFF & !plan.extra, desired | fused);
+    }
+
+    #[test]
+    fn fused_bits_outside_the_record_have_to_be_dont_care() {
+        let fused: u32 = add_ecc(0x80);
+        assert_eq!(plan_patch(3, fused, 0x01, 0), Err(EfuseError::NoPatch { bank: 3, conflicts: fused & !add_ecc(0x01) }));
+        // a fused don't-care bit just stays
+        assert_eq!(plan_patch(3, fused, 0, 0x80), Ok(PatchPlan { bank: 3, target: fused, program: 0, extra: 0x80 }));
+    }
+
+    #[test]
+    fn cntl_has_no_ecc() {
+        let fused: u32 = CNTL_R_EN_B_KEY as u32;
+        let plan: PatchPlan = plan_patch(CNTL_BANK, fused, (CNTL_R_EN_B_KEY | CNTL_CFG_AES_ONLY) as u32, 0).unwrap();
+        assert_eq!(plan.program, CNTL_CFG_AES_ONLY as u32);
+        assert!(plan_patch(CNTL_BANK, fused, CNTL_CFG_AES_ONLY as u32, 0).is_err());
+        assert_eq!(plan_patch(CNTL_BANK, 0, 0x40, 0), Err(EfuseError::OutOfRange { bank: CNTL_BANK, ones: 0x40 }));
+    }
+
+    #[test]
+    fn out_of_range() {
+        assert_eq!(plan_patch(FUSE_BANKS, 0, 1, 0), Err(EfuseError::OutOfRange { bank: FUSE_BANKS, ones: 1 }));
+        assert_eq!(plan_patch(4, 0, 1 << 24, 0), Err(EfuseError::OutOfRange { bank: 4, ones: 1 << 24 }));
+    }
+
+    #[test]
+    fn against_the_fused_state() {
+        let mut banks = [0u32; FUSE_BANKS];
+        banks[USER_BANK] = add_ecc(0x00_0001);
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.load_snapshot(&FuseSnapshot { banks });
+        assert_eq!(efuse.plan_patch(USER_BANK, 0x00_0003, 0xFF_0000), plan_patch(USER_BANK, banks[USER_BANK], 0x00_0003, 0xFF_0000));
+        assert_eq!(efuse.plan_patch(FUSE_BANKS, 0, 0), Err(EfuseError::OutOfRange { bank: FUSE_BANKS, ones: 0 }));
+    }
+}
@@ -0,0 +1,100 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::test_utils::*;
+
+    const USER: u32 = 0x4321_00A6;
+
+    fn fetched(jp: &mut EfuseModelPhy) -> EfuseApi {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut JtagMach::new(), jp).unwrap();
+        efuse
+    }
+
+    #[test]
+    fn back_to_the_fused_state() {
+        let mut jp = EfuseModelPhy::with_banks(banks_image_ecc(&test_key(0xC1), USER, CNTL_CFG_AES_ONLY));
+        let mut efuse = fetched(&mut jp);
+        // a fresh fetch stages nothing, so the blank intended state is all changes
+        assert!(efuse.has_pending_changes());
+        efuse.reset_pending();
+        assert!(!efuse.has_pending_changes());
+        assert!(efuse.key_matches(&efuse.api_key()));
+        assert_eq!((efuse.api_user(), efuse.api_cntl()), (USER, CNTL_CFG_AES_ONLY));
+
+        // after a refused plan
+        efuse.set_key([0xFF; 32]);
+        efuse.set_user(0);
+        efuse.set_cntl(CNTL_W_EN_B_KEY_USER).unwrap();
+        assert!(efuse.validate().is_err());
+        efuse.arm();
+        efuse.reset_pending();
+        assert!(!efuse.is_armed());
+        assert!(!efuse.has_pending_changes());
+        assert!(efuse.validate().is_ok());
+
+        // and a burn has nothing to program
+        let mut jm: JtagMach = JtagMach::new();
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert_eq!(jp.programmed(), []);
+    }
+
+    #[test]
+    fn a_burn_leaves_nothing_pending() {
+        let mut jp = EfuseModelPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse = fetched(&mut jp);
+        efuse.set_key(test_key(0xC1));
+        efuse.set_user(USER);
+        efuse.set_cntl(CNTL_CFG_AES_ONLY).unwrap();
+        assert!(efuse.is_valid());
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert_eq!(jp.banks(), banks_image_ecc(&test_key(0xC1), USER, CNTL_CFG_AES_ONLY));
+
+        // what was staged is what's now fused, bank 12 included
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert!(efuse.is_valid());
+        assert!(!efuse.has_pending_changes());
+        assert_eq!(efuse.diff(), StateDiff::default());
+    }
+
+    #[test]
+    fn the_shared_bank() {
+        let mut jp = EfuseModelPhy::with_banks(banks_image_ecc(&test_key(0xC1), USER, 0));
+        let mut efuse = fetched(&mut jp);
+        efuse.reset_pending();
+
+        // bank 11 holds key bytes 30/31 and USER[7:0]; a change to either is a change to it
+        efuse.set_user(USER ^ 0x01);
+        assert!(efuse.has_pending_changes());
+        assert_ne!(efuse.proposed_bank(Bank::Shared), efuse.bank(Bank::Shared));
+        assert_eq!(efuse.proposed_bank(Bank::User), efuse.bank(Bank::User));
+        efuse.reset_pending();
+        let mut key: [u8; 32] = test_key(0xC1);
+        key[31] ^= 0x80;
+        efuse.set_key(key);
+        assert!(efuse.has_pending_changes());
+        efuse.reset_pending();
+
+        // and USER[31:8] are bank 12's alone
+        efuse.set_user(USER ^ 0x100);
+        assert!(efuse.has_pending_changes());
+        assert_eq!(efuse.proposed_bank(Bank::Shared), efuse.bank(Bank::Shared));
+        efuse.reset_pending();
+        assert!(!efuse.has_pending_changes());
+    }
+
+    #[test]
+    fn an_unreadable_key() {
+        // the key reads as zeros, and staging zeros leaves the key banks alone
+        let mut jp = EfuseModelPhy::with_banks(banks_image_ecc(&test_key(0xC1), USER, CNTL_R_EN_B_KEY));
+        jp.enforce_read_disable();
+        let mut efuse = fetched(&mut jp);
+        efuse.reset_pending();
+        assert_eq!(efuse.api_key(), [0; 32]);
+        assert!(!efuse.has_pending_changes());
+        assert!(efuse.validate().is_ok());
+    }
+}
@@ -0,0 +1,208 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::messages::*;
+    use efuse_api::keysource::KeySource;
+    use efuse_api::protocol::*;
+    use efuse_api::transport::*;
+    use efuse_api::test_utils::*;
+
+    /// A device on the far end of a pair of pipes: the server runs whenever the host reads
+    struct Device {
+        api: EfuseApi,
+        jm: JtagMach,
+        jp: EfuseModelPhy,
+        server: Server,
+        to_dev: Pipe,
+        from_dev: Pipe,
+    }
+
+    impl Device {
+        fn new(jp: EfuseModelPhy) -> Self {
+            Device {
+                api: EfuseApi::new(),
+                jm: JtagMach::new(),
+                jp,
+                server: Server::new(),
+                to_dev: Pipe::new(),
+                from_dev: Pipe::new(),
+            }
+        }
+    }
+
+    impl ByteSink for Device {
+        fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), TransportError> {
+            self.to_dev.write_bytes(bytes)
+        }
+    }
+
+    impl ByteSource for Device {
+        fn read_byte(&mut self) -> Option<u8> {
+            while self.from_dev.is_empty() && !self.to_dev.is_empty() {
+                // the server reads commands from to_dev and answers into from_dev
+                let mut io = Loop { rx: &mut self.to_dev, tx: &mut self.from_dev };
+                self.server.serve_one(&mut self.api, &mut io, &mut self.jm, &mut self.jp).unwrap();
+            }
+            self.from_dev.read_byte()
+        }
+    }
+
+    struct Loop<'a> {
+        rx: &'a mut Pipe,
+        tx: &'a mut Pipe,
+    }
+
+    impl ByteSink for Loop<'_> {
+        fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), TransportError> {
+            self.tx.write_bytes(bytes)
+        }
+    }
+
+    impl ByteSource for Loop<'_> {
+        fn read_byte(&mut self) -> Option<u8> {
+            self.rx.read_byte()
+        }
+    }
+
+    fn manifest() -> ProvisioningManifest {
+        let mut key = [0u8; 32];
+        for (i, k) in key.iter_mut().enumerate() {
+            *k = (i as u8).wrapping_mul(37) ^ 0xA5;
+        }
+        ProvisioningManifest { key, user: 0x1234_5678, cntl: 0x00 }
+    }
+
+    fn client() -> Client<Device> {
+        let mut client = Client::new(Device::new(EfuseModelPhy::new()));
+        assert_eq!(client.hello(), Ok(PROTOCOL_VERSION));
+        client
+    }
+
+    #[test]
+    fn provision_end_to_end() {
+        let mut client = client();
+        assert_eq!(client.snapshot(), Ok(FuseSnapshot { banks: [0; FUSE_BANKS] }));
+        assert_eq!(client.lock_status(), Ok(0));
+        assert_eq!(client.report(), Err(ClientError::Remote(ProtocolError::NoReport)));
+
+        let m = manifest();
+        let report = client.provision(&m).unwrap();
+        assert!(report.committed);
+        let image = banks_image_ecc(&m.key, m.user, m.cntl);
+        assert_eq!(report.requested, image);
+        assert_eq!(client.report(), Ok(report));
+
+        // the fuses read back as the manifest, both through the protocol and in the model
+        assert_eq!(client.snapshot(), Ok(FuseSnapshot { banks: image }));
+        let dev = client.into_inner();
+        assert_eq!(dev.jp.banks(), image);
+        assert_eq!(dev.jp.rejected(), 0);
+        assert_eq!(dev.jp.commits(), 1);
+        assert_eq!(dev.api.phy_key(), m.key);
+        assert_eq!(dev.api.phy_user(), m.user);
+    }
+
+    #[test]
+    fn arming_rules() {
+        let mut client = client();
+        let m = manifest();
+        assert_eq!(client.arm(m.checksum()), Err(ClientError::Remote(ProtocolError::NotStaged)));
+        assert_eq!(client.execute_burn(), Err(ClientError::Remote(ProtocolError::NotArmed)));
+
+        assert_eq!(client.stage(&m), Ok(m.checksum()));
+        assert_eq!(client.arm(m.checksum() ^ 1), Err(ClientError::Remote(ProtocolError::ChecksumMismatch)));
+        assert_eq!(client.execute_burn(), Err(ClientError::Remote(ProtocolError::NotArmed)));
+
+        // restaging or aborting disarms
+        client.arm(m.checksum()).unwrap();
+        client.stage(&m).unwrap();
+        assert_eq!(client.execute_burn(), Err(ClientError::Remote(ProtocolError::NotArmed)));
+        client.arm(m.checksum()).unwrap();
+        client.abort().unwrap();
+        assert_eq!(client.execute_burn(), Err(ClientError::Remote(ProtocolError::NotArmed)));
+        assert_eq!(client.arm(m.checksum()), Err(ClientError::Remote(ProtocolError::NotStaged)));
+
+        let dev = client.into_inner();
+        assert!(dev.jp.programmed().is_empty());
+        // abort put the intended state back to the fused state
+        assert_eq!(dev.api.api_key(), [0; 32]);
+    }
+
+    #[test]
+    fn unreachable_manifest_is_refused() {
+        let m = manifest();
+        let mut banks = banks_image_ecc(&m.key, m.user, m.cntl);
+        // a fuse the manifest wants clear is already blown
+        let clear: u32 = !banks[3] & 0xFF_FFFF;
+        banks[3] |= clear & clear.wrapping_neg();
+        let mut client = Client::new(Device::new(EfuseModelPhy::with_banks(banks)));
+        client.hello().unwrap();
+        assert_eq!(client.provision(&m), Err(ClientError::Remote(ProtocolError::BurnFailed)));
+        assert_eq!(client.into_inner().jp.banks(), banks);
+    }
+
+    #[test]
+    fn session_and_version() {
+        let mut client = Client::new(Device::new(EfuseModelPhy::new()));
+        assert_eq!(client.snapshot(), Err(ClientError::Remote(ProtocolError::NoSession)));
+        let newer = ProtocolVersion { major: PROTOCOL_VERSION.major + 1, minor: 0 };
+        assert_eq!(client.request(&Command::Hello(newer)), Err(ClientError::Remote(ProtocolError::VersionMismatch)));
+        assert_eq!(client.lock_status(), Err(ClientError::Remote(ProtocolError::NoSession)));
+        // a newer minor version is fine
+        let minor = ProtocolVersion { major: PROTOCOL_VERSION.major, minor: PROTOCOL_VERSION.minor + 1 };
+        assert_eq!(client.request(&Command::Hello(minor)), Ok(Response::Hello(PROTOCOL_VERSION)));
+        assert_eq!(client.lock_status(), Ok(0));
+    }
+
+    #[test]
+    fn lock_status_by_host_version() {
+        let mut client = Client::new(Device::new(EfuseModelPhy::new()));
+        client.hello().unwrap();
+        assert_eq!(client.key_source(), Ok(Some(KeySource::NoKey)));
+
+        // a 1.0 host gets the one-byte form it knows
+        let old = ProtocolVersion { major: PROTOCOL_VERSION.major, minor: 0 };
+        assert_eq!(client.request(&Command::Hello(old)), Ok(Response::Hello(PROTOCOL_VERSION)));
+        assert_eq!(client.request(&Command::GetLockStatus), Ok(Response::LockStatus { cntl: 0, key_source: None }));
+    }
+
+    #[test]
+    fn unsupported_and_corrupt_frames() {
+        let mut client = client();
+        send_frame(client.io(), 0x5A, &[1, 2, 3]).unwrap();
+        assert_eq!(Response::receive(client.io()), Ok(Response::Unsupported { opcode: 0x5A }));
+
+        // a known opcode with the wrong operand length
+        send_frame(client.io(), 0x44, &[1, 2]).unwrap();
+        assert_eq!(Response::receive(client.io()), Ok(Response::Error(ProtocolError::BadFrame)));
+
+        // a frame corrupted in flight
+        Command::GetLockStatus.send(client.io()).unwrap();
+        client.io().to_dev.bytes_mut()[1] ^= 0x08;
+        assert_eq!(Response::receive(client.io()), Ok(Response::Error(ProtocolError::BadFrame)));
+
+        // and the session carries on
+        assert_eq!(client.lock_status(), Ok(0));
+    }
+
+    #[test]
+    fn serve_until_closed() {
+        let mut host = Pipe::new();
+        Command::Hello(PROTOCOL_VERSION).send(&mut host).unwrap();
+        Command::GetLockStatus.send(&mut host).unwrap();
+        Command::Abort.send(&mut host).unwrap();
+
+        let mut reply = Pipe::new();
+        let mut api = EfuseApi::new();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        serve(&mut api, &mut Loop { rx: &mut host, tx: &mut reply }, &mut jm, &mut jp).unwrap();
+
+        assert_eq!(Response::receive(&mut reply), Ok(Response::Hello(PROTOCOL_VERSION)));
+        assert_eq!(Response::receive(&mut reply), Ok(Response::LockStatus { cntl: 0, key_source: Some(KeySource::NoKey) }));
+        assert_eq!(Response::receive(&mut reply), Ok(Response::Aborted));
+        assert_eq!(Response::receive(&mut reply), Err(TransportError::Closed));
+    }
+}
@@ -0,0 +1,76 @@
+"""Tests for the Python bindings.
+
+python_tests.rs runs these against the extension registered in an embedded interpreter;
+they also run under pytest against a build installed with maturin.
+"""
+import efuse_api
+
+KEY = bytes(((i * 53) & 0xFF) ^ 0x3C for i in range(32))
+NKY = "Device xc7s50;\nKey 0 " + KEY[::-1].hex() + ";\nKey StartCBC 00;\n"
+
+
+def raises(exception, call):
+    try:
+        call()
+    except exception as e:
+        return e
+    raise AssertionError("expected " + exception.__name__)
+
+
+def blank():
+    return efuse_api.FuseSnapshot([0] * 13)
+
+
+def test_snapshot_diff():
+    banks = [0] * 13
+    banks[0] = 0x3 | 0x3 << 14
+    banks[12] = 0x5A
+    locked = efuse_api.FuseSnapshot(banks)
+    assert blank().diff(locked) == [(0, 0, 0xC003), (12, 0, 0x5A)]
+    assert locked.diff(locked) == []
+    assert efuse_api.FuseSnapshot.decode(locked.encode()) == locked
+    raises(ValueError, lambda: efuse_api.FuseSnapshot([0] * 12))
+    raises(ValueError, lambda: efuse_api.FuseSnapshot.decode(b"\0" * 51))
+
+
+def test_plan():
+    planner = efuse_api.Planner.from_snapshot(blank())
+    assert planner.plan() == []
+    planner.stage_key(KEY)
+    planner.user = 0x87654321
+    assert planner.validate() == []
+    plan = planner.plan()
+    assert plan[0][0] == 12
+    assert [bank for bank, _ in plan] == sorted((bank for bank, _ in plan), reverse=True)
+    assert all(bits > 0 for _, bits in plan)
+    assert planner.estimated_duration_us(1_000_000) > 0
+
+    # the key only ever comes back as a fingerprint
+    assert not hasattr(planner, "key")
+    assert len(planner.key_fingerprint()) == 16
+    assert planner.key_fingerprint() != planner.fused_key_fingerprint()
+    assert efuse_api.nky_fingerprint(NKY) == planner.key_fingerprint()
+    other = efuse_api.Planner.from_snapshot(blank())
+    other.stage_nky(NKY)
+    assert other.key_fingerprint() == planner.key_fingerprint()
+
+
+def test_refusals():
+    fused = efuse_api.Planner.from_snapshot(efuse_api.FuseSnapshot([0] * 12 + [0x00FF_FFFF]))
+    fused.user = 0
+    assert isinstance(raises(efuse_api.InvalidPlan, fused.plan), efuse_api.EfuseError)
+
+    weak = efuse_api.Planner.from_snapshot(blank())
+    weak.stage_key(bytes([0x11] * 32))
+    weak.cntl = 0x03
+    e = raises(efuse_api.WeakKey, weak.validate)
+    assert e.reason.startswith("AllIdentical")
+    weak.allow_weak_key(True)
+    assert weak.validate() == ["SuspiciousKey { reason: AllIdentical { byte: 17 } }"]
+
+    raises(ValueError, lambda: weak.stage_key(bytes(31)))
+    e = raises(efuse_api.KeyFormatError, lambda: weak.stage_nky("Key 0 12g4;"))
+    assert (e.position, e.found) == (2, "g")
+    e = raises(efuse_api.KeyFormatError, lambda: efuse_api.nky_fingerprint("Key 0 1234;"))
+    assert e.digits == 4
+    assert isinstance(e, ValueError)
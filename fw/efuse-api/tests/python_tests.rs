@@ -0,0 +1,40 @@
+#![cfg(feature = "python")]
+
+#[cfg(test)]
+mod tests {
+    use pyo3::prelude::*;
+    use pyo3::types::PyDict;
+
+    const TESTS: &str = include_str!("python/test_efuse_api.py");
+
+    /// runs the test_* functions from python/test_efuse_api.py, with the bindings importable
+    fn run_python_test(name: &str) {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = PyModule::new(py, "efuse_api").unwrap();
+            efuse_api::python::register(py, module).unwrap();
+            py.import("sys").unwrap().getattr("modules").unwrap().set_item("efuse_api", module).unwrap();
+            let globals = PyDict::new(py);
+            py.run(TESTS, Some(globals), None).unwrap();
+            if let Err(e) = globals.get_item(name).unwrap().unwrap().call0() {
+                e.print(py);
+                panic!("{} failed", name);
+            }
+        });
+    }
+
+    #[test]
+    fn snapshot_diff() {
+        run_python_test("test_snapshot_diff");
+    }
+
+    #[test]
+    fn plan() {
+        run_python_test("test_plan");
+    }
+
+    #[test]
+    fn refusals() {
+        run_python_test("test_refusals");
+    }
+}
@@ -0,0 +1,140 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::test_utils::*;
+    use efuse_api::verify::*;
+
+    const USER: u32 = 0x0055_1CE5;
+
+    /// burns test_key(0x2D), USER and `cntl` on `jp`, which starts out blank
+    fn burned(cntl: u8, jm: &mut JtagMach, jp: &mut EfuseModelPhy) -> EfuseApi {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(jm, jp).unwrap();
+        efuse.set_key(test_key(0x2D));
+        efuse.set_user(USER);
+        efuse.set_cntl(cntl).unwrap();
+        efuse.burn(jm, jp).unwrap();
+        efuse
+    }
+
+    /// the lowest bit of `bank`'s data that the image leaves 0
+    fn unplanned(bank: usize, cntl: u8) -> u8 {
+        let image: u32 = bank_image_ecc(bank, &test_key(0x2D), USER, cntl);
+        (0..24).find(|&bit| image & (1 << bit) == 0).unwrap()
+    }
+
+    /// the lowest bit of `bank`'s data that the image sets
+    fn planned(bank: usize) -> u8 {
+        let image: u32 = bank_image_ecc(bank, &test_key(0x2D), USER, 0);
+        (0..24).find(|&bit| image & (1 << bit) != 0).unwrap()
+    }
+
+    #[test]
+    fn clean_burn() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse = burned(CNTL_CFG_AES_ONLY, &mut jm, &mut jp);
+        let outcome: VerificationOutcome = efuse.verify_burn(&mut jm, &mut jp).unwrap();
+        assert!(outcome.is_clean());
+        assert_eq!(outcome.predicted, banks_image_ecc(&test_key(0x2D), USER, CNTL_CFG_AES_ONLY));
+        assert_eq!(outcome.observed[CNTL_BANK], bank_fuses(CNTL_BANK));
+        assert!(outcome.observed[1..].iter().all(|&fuses| fuses == 0xFF_FFFF));
+        for (index, &bank) in jp.banks().iter().enumerate() {
+            assert_eq!(outcome.captured[index], bank & outcome.observed[index]);
+        }
+        assert_eq!(efuse.last_verification(), Some(&outcome));
+    }
+
+    #[test]
+    fn missed_pulse() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let bit: u8 = planned(7);
+        jp.stick(7, 1 << bit);
+        let mut efuse = burned(0, &mut jm, &mut jp);
+        let outcome: VerificationOutcome = efuse.verify_burn(&mut jm, &mut jp).unwrap();
+        assert_eq!(outcome.missing, vec![FusePosition { bank: 7, bit }]);
+        assert!(outcome.stray.is_empty());
+    }
+
+    #[test]
+    fn burn_and_verify_names_each_stuck_fuse() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let (key_bit, user_bit): (u8, u8) = (planned(3), planned(USER_BANK));
+        jp.stick(3, 1 << key_bit);
+        jp.stick(USER_BANK, 1 << user_bit);
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(test_key(0x2D));
+        efuse.set_user(USER);
+        let outcome: VerificationOutcome = efuse.burn_and_verify(&mut jm, &mut jp).unwrap();
+        assert_eq!(outcome.missing, vec![FusePosition { bank: 3, bit: key_bit }, FusePosition { bank: USER_BANK, bit: user_bit }]);
+        assert!(outcome.stray.is_empty());
+        assert_eq!(efuse.last_verification(), Some(&outcome));
+    }
+
+    #[test]
+    fn burn_and_verify_reads_nothing_back_if_the_burn_is_refused() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.set_key(test_key(0x2D));
+        assert_eq!(efuse.burn_and_verify(&mut jm, &mut jp), Err(EfuseError::NotFetched));
+        assert_eq!(jp.cycles(), 0);
+        assert_eq!(efuse.last_verification(), None);
+    }
+
+    #[test]
+    fn collateral_bit() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let bit: u8 = unplanned(USER_BANK, 0);
+        jp.collateral(USER_BANK, 1 << bit);
+        let mut efuse = burned(0, &mut jm, &mut jp);
+        assert_eq!(efuse.verify_burn(&mut jm, &mut jp), Err(EfuseError::StrayFuses { bank: USER_BANK, fuses: 1 << bit }));
+        let outcome: VerificationOutcome = efuse.last_verification().unwrap().clone();
+        assert_eq!(outcome.stray, vec![FusePosition { bank: USER_BANK, bit }]);
+        assert!(outcome.missing.is_empty());
+
+        efuse.allow_stray_fuses(true);
+        assert_eq!(efuse.verify_burn(&mut jm, &mut jp), Ok(outcome));
+    }
+
+    #[test]
+    fn stray_in_the_redundant_cntl_copy() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let stray: u32 = (CNTL_R_EN_B_KEY as u32) << CNTL_COPY_SHIFT;
+        jp.collateral(CNTL_BANK, stray);
+        let mut efuse = burned(CNTL_CFG_AES_ONLY, &mut jm, &mut jp);
+        // the logical readback only decodes the primary copy, so it looks fine
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.phy_cntl(), CNTL_CFG_AES_ONLY);
+        assert_eq!(efuse.verify_burn(&mut jm, &mut jp), Err(EfuseError::StrayFuses { bank: CNTL_BANK, fuses: stray }));
+    }
+
+    #[test]
+    fn ecc_codes_arent_compared() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let image: u32 = bank_image_ecc(2, &test_key(0x2D), USER, 0);
+        let ecc: u32 = (24..30).map(|bit| 1u32 << bit).find(|&bit| image & bit == 0).unwrap();
+        jp.collateral(2, ecc);
+        let mut efuse = burned(0, &mut jm, &mut jp);
+        let outcome: VerificationOutcome = efuse.verify_burn(&mut jm, &mut jp).unwrap();
+        assert!(outcome.is_clean());
+        assert_eq!(outcome.observed[2] & ecc, 0);
+    }
+
+    #[test]
+    fn without_a_burn_the_fetched_state_is_predicted() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::with_banks(banks_image_ecc(&test_key(0x2D), USER, 0));
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert!(efuse.verify_burn(&mut jm, &mut jp).unwrap().is_clean());
+    }
+}
@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::keyhex;
+    use efuse_api::layout::*;
+    use efuse_api::vivado::*;
+
+    const FETCH_TDO: &str = include_str!("data/fetch_tdo.txt");
+    const REPORT: &str = include_str!("data/vivado_efuse_report.txt");
+
+    /// the bits of a capture fixture, comments left out
+    fn capture(text: &str) -> Vec<bool> {
+        text.lines()
+            .filter(|line| !line.starts_with('#'))
+            .flat_map(|line| line.trim().chars())
+            .map(|c| match c {
+                '0' => false,
+                '1' => true,
+                other => panic!("bad bit {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fetch_unpacks_the_capture() {
+        let tdo: Vec<bool> = capture(FETCH_TDO);
+        let mut jp = ReplayPhy::new(&tdo, Exhausted::Fail);
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        // every bit was used, and no more
+        assert!(jp.is_exhausted());
+        assert_eq!(jp.overrun(), 0);
+
+        // the key comes out in .nky order reversed: the first digits are the last byte
+        let key: [u8; 32] = keyhex::parse("8D3A61F0C2B95E17A4086FD3E92C5B71F63E0A1D84C7295BE0D1733A5CF86902").unwrap();
+        assert_eq!(efuse.phy_key(), key);
+        assert_eq!((efuse.phy_key()[0], efuse.phy_key()[31]), (0x02, 0x8D));
+        assert_eq!(efuse.phy_user(), 0x1234_ABCD);
+        assert_eq!(efuse.phy_cntl(), CNTL_CFG_AES_ONLY);
+        assert_eq!(efuse.fetch_report().user, UserConsistency::Match);
+
+        // the shared bank holds the last two key bytes under the bottom byte of USER
+        let banks: [u32; FUSE_BANKS] = efuse.snapshot().banks;
+        assert_eq!(banks[SHARED_BANK] & 0xFF_FFFF, 0xCD_8D3A);
+        assert_eq!(banks[USER_BANK] & 0xFF_FFFF, 0x12_34AB);
+        assert_eq!(banks[1] & 0xFF_FFFF, 0xF8_6902);
+        // CNTL has its copy at bit 14
+        assert_eq!(banks[CNTL_BANK], 0x8002);
+
+        // and it's the device Vivado reported
+        let report = VivadoEfuseReport::parse(REPORT).unwrap();
+        assert!(efuse.compare_with_vivado(&report).is_consistent());
+    }
+
+    #[test]
+    fn cntl_is_read_as_14_bits() {
+        let tdo: Vec<bool> = capture(FETCH_TDO);
+        let mut buf = [Transition::BLANK; 512];
+        let mut jp = RecordingPhy::new(ReplayPhy::new(&tdo, Exhausted::Fail), &mut buf);
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        // KEY, USER, CNTL: with fuses set, there's no check for a secured device after
+        let shifts: Vec<(usize, u128)> = jp.iter_dr_shifts().map(|s| (s.len(), s.tdo_value())).collect();
+        assert_eq!(shifts.len(), 3);
+        assert_eq!((shifts[0].0, shifts[1]), (256, (32, 0x1234_ABCD)));
+        assert_eq!(shifts[2], (14, CNTL_CFG_AES_ONLY as u128));
+    }
+
+    #[test]
+    fn a_short_capture() {
+        let tdo: Vec<bool> = capture(FETCH_TDO);
+        let short: &[bool] = &tdo[..tdo.len() - 1];
+
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        let mut jp = ReplayPhy::new(short, Exhausted::Fail);
+        assert!(efuse.fetch(&mut jm, &mut jp).is_err());
+        assert_eq!(jp.overrun(), 1);
+
+        // filled out with the bit it lacked, it reads the same
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = ReplayPhy::new(short, Exhausted::Fill(tdo[tdo.len() - 1]));
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(jp.overrun(), 1);
+        assert_eq!(efuse.phy_user(), 0x1234_ABCD);
+    }
+}
@@ -0,0 +1,88 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::rollback::*;
+    use efuse_api::test_utils::*;
+
+    /// the top 16 USER bits, all in bank 12
+    const COUNTER: RollbackCounter = RollbackCounter { field: 0xFFFF_0000 };
+
+    /// a fetched EfuseApi with test_key(0x1F) and `user` burned, and staged again
+    fn provisioned(jm: &mut JtagMach, jp: &mut EfuseModelPhy, user: u32) -> EfuseApi {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(jm, jp).unwrap();
+        efuse.set_key(test_key(0x1F));
+        efuse.set_user(user);
+        efuse.burn(jm, jp).unwrap();
+        efuse.fetch(jm, jp).unwrap();
+        efuse
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(RollbackCounter::new(16, 16), COUNTER);
+        assert_eq!(RollbackCounter::new(0, 32).field, !0);
+        assert_eq!(RollbackCounter::new(4, 3).field, 0x70);
+    }
+
+    #[test]
+    #[should_panic]
+    fn past_the_top() {
+        RollbackCounter::new(30, 3);
+    }
+
+    #[test]
+    fn walks_from_zero_to_max() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        // bank 12 is blank; USER[7:0] sits in bank 11
+        let mut efuse = provisioned(&mut jm, &mut jp, 0x0000_005A);
+        assert_eq!(COUNTER.read_count(&efuse), 0);
+        // as for the event counter, the ECC code leaves room for two bits
+        let max: u32 = COUNTER.max_count(&efuse);
+        assert_eq!(max, 2);
+
+        for count in 1..=max {
+            assert_eq!(COUNTER.increment(&mut efuse), Ok(count));
+            // the rest of USER is left as staged
+            assert_eq!(efuse.api_user() & !COUNTER.field, 0x0000_005A);
+            efuse.burn(&mut jm, &mut jp).unwrap();
+            efuse.fetch(&mut jm, &mut jp).unwrap();
+            assert_eq!(efuse.fetch_report().user, UserConsistency::Match);
+            assert_eq!(COUNTER.read_count(&efuse), count);
+            assert_eq!(COUNTER.max_count(&efuse), max);
+        }
+
+        let staged: u32 = efuse.api_user();
+        assert_eq!(COUNTER.increment(&mut efuse), Err(EfuseError::RollbackExhausted { count: max }));
+        assert_eq!(efuse.api_user(), staged);
+        // the fuses read back as what the model holds
+        assert_eq!(jp.banks(), banks_image_ecc(&test_key(0x1F), efuse.phy_user(), 0));
+    }
+
+    #[test]
+    fn staged_field_bits_are_ignored() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse = provisioned(&mut jm, &mut jp, 0);
+        efuse.set_user(0xFFFF_0000);
+        assert_eq!(COUNTER.increment(&mut efuse), Ok(1));
+        assert_eq!(efuse.api_user().count_ones(), 1);
+    }
+
+    #[test]
+    fn refusals_stage_nothing() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse = provisioned(&mut jm, &mut jp, 0);
+        // a key with blown fuses clear again
+        let mut cleared = test_key(0x1F);
+        cleared[0] = 0;
+        efuse.set_key(cleared);
+        efuse.set_user(0x7700);
+        assert!(matches!(COUNTER.increment(&mut efuse), Err(EfuseError::IllegalTransition { .. })));
+        assert_eq!(efuse.api_user(), 0x7700);
+    }
+}
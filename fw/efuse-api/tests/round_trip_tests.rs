@@ -0,0 +1,87 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::test_utils::*;
+
+    fn key(seed: u8) -> [u8; 32] {
+        let mut key = [0u8; 32];
+        for (i, k) in key.iter_mut().enumerate() {
+            *k = (i as u8).wrapping_mul(29).wrapping_add(seed) ^ 0x6B;
+        }
+        key
+    }
+
+    /// what a fresh EfuseApi fetches from `jp`
+    fn fetched(jp: &mut EfuseModelPhy) -> EfuseApi {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, jp).unwrap();
+        efuse
+    }
+
+    /// burns `key`, `user` and `cntl` onto `jp` with an EfuseApi of its own
+    fn burn(jp: &mut EfuseModelPhy, key: [u8; 32], user: u32, cntl: u8) {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, jp).unwrap();
+        efuse.set_key(key);
+        efuse.set_user(user);
+        efuse.set_cntl(cntl).unwrap();
+        efuse.burn(&mut jm, jp).unwrap();
+    }
+
+    #[test]
+    fn burn_then_fetch() {
+        let cntl: u8 = CNTL_CFG_AES_ONLY | CNTL_AES_EXCLUSIVE;
+        for (seed, user) in [(0u8, 0x0000_0001u32), (0x11, 0x8000_0000), (0x5A, 0xC0FF_EE00), (0xF0, 0xFFFF_FFFF)].iter() {
+            let mut jp = EfuseModelPhy::new();
+            burn(&mut jp, key(*seed), *user, cntl);
+            assert_eq!(jp.rejected(), 0);
+            assert_eq!(jp.banks(), banks_image_ecc(&key(*seed), *user, cntl));
+
+            let efuse = fetched(&mut jp);
+            assert_eq!(efuse.phy_key(), key(*seed), "seed {:#x}", seed);
+            assert_eq!(efuse.phy_user(), *user);
+            assert_eq!(efuse.phy_cntl(), cntl);
+            assert_eq!(efuse.fetch_report().user, UserConsistency::Match);
+        }
+    }
+
+    #[test]
+    fn a_second_burn_adds_to_the_first() {
+        let mut jp = EfuseModelPhy::new();
+        burn(&mut jp, [0; 32], 0x0000_0F00, 0);
+        let first: [u32; FUSE_BANKS] = jp.banks();
+
+        // USER can only gain bits, and the key stays blank until it's burned
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse = fetched(&mut jp);
+        assert_eq!(efuse.phy_user(), 0x0000_0F00);
+        efuse.set_key(key(0x33));
+        efuse.set_user(0x0000_0FF0);
+        efuse.set_cntl(CNTL_CFG_AES_ONLY).unwrap();
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert!(jp.banks().iter().zip(first.iter()).all(|(now, was)| now & was == *was));
+
+        let efuse = fetched(&mut jp);
+        assert_eq!(efuse.phy_key(), key(0x33));
+        assert_eq!(efuse.phy_user(), 0x0000_0FF0);
+        assert_eq!(efuse.phy_cntl(), CNTL_CFG_AES_ONLY);
+        assert_eq!(efuse.snapshot().banks, jp.banks());
+    }
+
+    #[test]
+    fn fuses_never_clear() {
+        let mut jp = EfuseModelPhy::with_banks(banks_image_ecc(&key(1), 0x1234_5678, 0));
+        // staging a cleared USER is refused before anything is shifted
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse = fetched(&mut jp);
+        efuse.set_key(key(1));
+        efuse.set_user(0x1234_5670);
+        assert!(efuse.burn(&mut jm, &mut jp).is_err());
+        assert!(jp.programmed().is_empty());
+        assert_eq!(fetched(&mut jp).phy_user(), 0x1234_5678);
+    }
+}
@@ -0,0 +1,178 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::sequences::*;
+    use efuse_api::test_utils::*;
+
+    fn reference_key() -> [u8; 32] {
+        let mut key: [u8; 32] = [0; 32];
+        for (i, k) in key.iter_mut().enumerate() {
+            *k = 0x10 + i as u8;
+        }
+        key
+    }
+
+    fn shift_ir(jm: &mut JtagMach, jp: &mut ScriptedPhy, ir: Ir) {
+        jm.add_seq(&[SeqCmd::new(JtagChain::IR, IR_BITS, ir.code() as u64, "ir")]).unwrap();
+        jm.run_to_completion(jp).unwrap();
+        jm.drain_completed(|_| {});
+    }
+
+    fn read_dr(jm: &mut JtagMach, jp: &mut ScriptedPhy, bits: usize) -> u128 {
+        jm.add_seq(&[SeqCmd::new(JtagChain::DR, bits, 0, "dr")]).unwrap();
+        jm.run_to_completion(jp).unwrap();
+        let mut value: u128 = 0;
+        jm.drain_completed(|mut leg| value = leg.pop_u128(bits, JtagEndian::Little).unwrap());
+        value
+    }
+
+    #[test]
+    fn ir_decoding() {
+        let mut jp = ScriptedPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        assert_eq!(jp.tap(), TapState::TestLogicReset);
+        assert_eq!(jp.ir(), Some(Ir::Idcode));
+
+        for &ir in [Ir::FuseKey, Ir::FuseUser, Ir::FuseCntl, Ir::FuseCts, Ir::Jstart, Ir::Bypass].iter() {
+            shift_ir(&mut jm, &mut jp, ir);
+            assert_eq!(jp.ir(), Some(ir));
+            assert_eq!(jp.tap(), TapState::RunTestIdle);
+        }
+        assert_eq!(jp.ir_history(), &[0b110001, 0b110011, 0b110100, 0b110000, 0b001100, 0b111111]);
+
+        // an undocumented code still latches, it just doesn't decode
+        jm.add_seq(&[SeqCmd::new(JtagChain::IR, IR_BITS, 0b101010, "ir")]).unwrap();
+        jm.run_to_completion(&mut jp).unwrap();
+        assert_eq!(jp.ir(), None);
+        assert_eq!(jp.ir_history().last(), Some(&0b101010));
+
+        jm.reset(&mut jp);
+        assert_eq!(jp.ir(), Some(Ir::Idcode));
+    }
+
+    #[test]
+    fn ir_codes_round_trip() {
+        for code in 0..(1 << IR_BITS) {
+            if let Some(ir) = Ir::from_code(code) {
+                assert_eq!(ir.code(), code);
+            }
+        }
+        assert_eq!(Ir::from_code(CMD_EFUSE), Some(Ir::FuseCts));
+        assert_eq!(Ir::from_code(CMD_JSTART), Some(Ir::Jstart));
+    }
+
+    #[test]
+    fn ir_shift_out_is_capture_value() {
+        let mut jp = ScriptedPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        jm.add_seq(&[SeqCmd::new(JtagChain::IR, IR_BITS, Ir::FuseKey.code() as u64, "ir")]).unwrap();
+        jm.run_to_completion(&mut jp).unwrap();
+        let mut captured: u32 = 0;
+        jm.drain_completed(|mut leg| captured = leg.pop_u32(IR_BITS, JtagEndian::Little).unwrap());
+        assert_eq!(captured, IR_CAPTURE);
+    }
+
+    #[test]
+    fn response_framing() {
+        let mut jp = ScriptedPhy::new();
+        jp.on_dr(Ir::FuseCntl, 14, 0x2B2B);
+        jp.on_dr(Ir::FuseUser, 32, 0xA5C3_3C5A);
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+
+        shift_ir(&mut jm, &mut jp, Ir::FuseUser);
+        assert_eq!(read_dr(&mut jm, &mut jp, 32), 0xA5C3_3C5A);
+        shift_ir(&mut jm, &mut jp, Ir::FuseCntl);
+        assert_eq!(read_dr(&mut jm, &mut jp, 14), 0x2B2B);
+        // bits are framed to the registered width; anything beyond reads as zero
+        assert_eq!(read_dr(&mut jm, &mut jp, 20), 0x2B2B);
+        assert_eq!(jp.reads(Ir::FuseCntl), 2);
+
+        // instructions without a script read back zero
+        shift_ir(&mut jm, &mut jp, Ir::FuseDna);
+        assert_eq!(read_dr(&mut jm, &mut jp, 64), 0);
+    }
+
+    #[test]
+    fn response_sequence() {
+        let mut jp = ScriptedPhy::new();
+        jp.on_dr_seq(Ir::FuseUser, 32, &[1, 2, 3]);
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        shift_ir(&mut jm, &mut jp, Ir::FuseUser);
+        let reads: Vec<u128> = (0..5).map(|_| read_dr(&mut jm, &mut jp, 32)).collect();
+        assert_eq!(reads, vec![1, 2, 3, 3, 3]);
+        assert_eq!(jp.reads(Ir::FuseUser), 5);
+    }
+
+    #[test]
+    fn writes_grouped_by_instruction() {
+        let mut jp = ScriptedPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        jm.add_seq(&[
+            SeqCmd::new(JtagChain::IR, IR_BITS, Ir::User1.code() as u64, "ir"),
+            SeqCmd::new(JtagChain::DR, 17, 0xF000, "dr"),
+            SeqCmd::new(JtagChain::DR, 75, 0xA9, "dr"),
+            SeqCmd::new(JtagChain::IR, IR_BITS, Ir::User2.code() as u64, "ir"),
+            SeqCmd::new(JtagChain::DR, 42, 0x69, "dr"),
+        ]).unwrap();
+        jm.run_to_completion(&mut jp).unwrap();
+
+        let user1: Vec<(usize, u128)> = jp.dr_writes(Ir::User1).map(|w| (w.len(), w.value())).collect();
+        assert_eq!(user1, vec![(17, 0xF000), (75, 0xA9)]);
+        let user2: Vec<(usize, u128)> = jp.dr_writes(Ir::User2).map(|w| (w.len(), w.value())).collect();
+        assert_eq!(user2, vec![(42, 0x69)]);
+        assert_eq!(jp.dr_writes_all().len(), 3);
+        assert_eq!(jp.dr_writes_all()[2].instruction(), Some(Ir::User2));
+    }
+
+    #[test]
+    fn fetch_decodes_scripted_fuses() {
+        // the KEY register reads out the key bytes in order, LSB first
+        let key = reference_key();
+        let mut jp = ScriptedPhy::new();
+        jp.on_dr_bytes(Ir::FuseKey, 256, &key);
+        jp.on_dr(Ir::FuseUser, 32, 0xA5C3_3C5A);
+        jp.on_dr(Ir::FuseCntl, 14, 0x2B | (0x2B << CNTL_COPY_SHIFT));
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+
+        assert_eq!(efuse.phy_key(), key);
+        assert_eq!(efuse.phy_user(), 0xA5C3_3C5A);
+        assert_eq!(efuse.phy_cntl(), 0x2B);
+        assert_eq!(jp.reads(Ir::FuseKey), 1);
+        assert_eq!(jp.dr_writes(Ir::FuseKey).next().unwrap().len(), 256);
+    }
+
+    #[test]
+    fn burn_writes_match_programming_words() {
+        let mut jp = ScriptedPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+
+        let key = reference_key();
+        efuse.set_key(key);
+        efuse.set_user(0xA5C3_3C5A);
+        efuse.set_cntl(0x2B).unwrap();
+        efuse.burn(&mut jm, &mut jp).unwrap();
+
+        let expected: Vec<(usize, u128)> = banks_image_ecc(&key, 0xA5C3_3C5A, 0x2B).iter().enumerate().rev()
+            .flat_map(|(bank, &ones)| dr_words_for_bank(bank, ones, &DeviceParams::SEVEN_SERIES))
+            .map(|w| (64, w.value as u128))
+            .collect();
+        // the commit word is shifted under FUSE_CTS too, after the last bank, and then a wait
+        // word that reads the status
+        let writes: Vec<(usize, u128)> = jp.dr_writes(Ir::FuseCts).map(|w| (w.len(), w.value())).collect();
+        assert_eq!(writes.len(), expected.len() + 2);
+        assert_eq!(&writes[..expected.len()], &expected[..]);
+        assert_eq!(writes[expected.len()], (64, 0xff000000ff));
+        assert_eq!(writes[expected.len() + 1], (64, 0));
+    }
+}
@@ -0,0 +1,106 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::messages::*;
+    use efuse_api::secrets::*;
+    use efuse_api::test_utils::*;
+    use std::mem::{size_of, MaybeUninit};
+
+    /// Holds a T in place, so that its memory can be looked at after it's dropped
+    struct Inspected<T> {
+        slot: MaybeUninit<T>,
+    }
+
+    impl<T> Inspected<T> {
+        fn new(value: T) -> Self {
+            let mut slot = MaybeUninit::uninit();
+            slot.write(value);
+            Inspected { slot }
+        }
+
+        fn get_mut(&mut self) -> &mut T {
+            // safety: new() wrote a value, and only drop_in_place() takes it away
+            unsafe { self.slot.assume_init_mut() }
+        }
+
+        fn bytes(&self) -> Vec<u8> {
+            let base = self.slot.as_ptr() as *const u8;
+            // safety: reads within the slot, which outlives this; volatile, so the reads
+            // happen even though the value is dead
+            (0..size_of::<T>()).map(|i| unsafe { core::ptr::read_volatile(base.add(i)) }).collect()
+        }
+
+        /// drops the value where it is and returns the memory it leaves behind
+        fn drop_in_place(mut self) -> Vec<u8> {
+            // safety: the slot holds a value, and is never read as a T again
+            unsafe { core::ptr::drop_in_place(self.slot.as_mut_ptr()) };
+            self.bytes()
+        }
+    }
+
+    /// true if `memory` holds test_key(0xA7), as a key array or as the banks carrying it. Enums can
+    /// leave stray words of either in bytes their variant doesn't use, so it takes the lot.
+    fn holds_key(memory: &[u8]) -> bool {
+        let banks: Vec<u8> = banks_image_ecc(&test_key(0xA7), 0, 0)[1..SHARED_BANK].iter().flat_map(|b| b.to_ne_bytes()).collect();
+        memory.windows(32).any(|m| m == test_key(0xA7)) || memory.windows(banks.len()).any(|m| m == &banks[..])
+    }
+
+    /// stages test_key(0xA7), burns it and fetches it back
+    fn burn(efuse: &mut EfuseApi, jm: &mut JtagMach, jp: &mut EfuseModelPhy) {
+        efuse.fetch(jm, jp).unwrap();
+        efuse.set_key(test_key(0xA7));
+        efuse.burn(jm, jp).unwrap();
+        efuse.fetch(jm, jp).unwrap();
+        assert!(efuse.key_matches(&test_key(0xA7)));
+    }
+
+    #[test]
+    fn clear_secrets() {
+        let mut jp = EfuseModelPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        burn(&mut efuse, &mut jm, &mut jp);
+        efuse.clear_secrets();
+        assert_eq!(efuse.api_key(), [0; 32]);
+        assert_eq!(efuse.phy_key(), [0; 32]);
+        assert!(efuse.snapshot().banks[1..=SHARED_BANK].iter().all(|&b| b == 0));
+        assert!(efuse.last_report().unwrap().requested[1..=SHARED_BANK].iter().all(|&b| b == 0));
+        // the fused state is forgotten, so nothing is burned against it
+        assert_eq!(efuse.burn(&mut jm, &mut jp), Err(EfuseError::NotFetched));
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert!(efuse.key_matches(&test_key(0xA7)));
+    }
+
+    #[test]
+    fn an_api_dropped_leaves_no_key() {
+        let mut efuse = Inspected::new(EfuseApi::new());
+        burn(efuse.get_mut(), &mut JtagMach::new(), &mut EfuseModelPhy::new());
+        assert!(holds_key(&efuse.bytes()));
+        assert!(!holds_key(&efuse.drop_in_place()));
+    }
+
+    #[test]
+    fn a_phy_dropped_leaves_no_key() {
+        let mut phy = EfusePhy::new();
+        phy.load_snapshot(&FuseSnapshot { banks: banks_image_ecc(&test_key(0xA7), 0x1234_5678, 0) });
+        assert_eq!(phy.key(), test_key(0xA7));
+        let phy = Inspected::new(phy);
+        assert!(holds_key(&phy.bytes()));
+        assert!(!holds_key(&phy.drop_in_place()));
+    }
+
+    #[test]
+    fn wiping() {
+        let mut key = test_key(0xA7);
+        wipe_bytes(&mut key);
+        assert_eq!(key, [0; 32]);
+        let mut banks = banks_image_ecc(&self::test_key(0xA7), 0xFFFF_FFFF, 0x3F);
+        let untouched = banks;
+        wipe_key_banks(&mut banks);
+        assert!(banks[1..=SHARED_BANK].iter().all(|&b| b == 0));
+        assert_eq!(banks[0], untouched[0]);
+        assert_eq!(banks[USER_BANK], untouched[USER_BANK]);
+    }
+}
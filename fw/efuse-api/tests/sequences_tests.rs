@@ -0,0 +1,147 @@
+#[cfg(test)]
+mod tests {
+    use efuse_api::sequences::*;
+
+    const UNLOCK: u64 = 0xa08a_28ac_0000_4001;
+
+    fn words(bank: usize, ones: u32) -> Vec<ProgramWord> {
+        dr_words_for_bank(bank, ones, &DeviceParams::SEVEN_SERIES).collect()
+    }
+
+    fn bracket(select: u64) -> Vec<ProgramWord> {
+        vec![
+            ProgramWord { value: UNLOCK, kind: WordKind::Unlock },
+            ProgramWord { value: UNLOCK, kind: WordKind::Unlock },
+            ProgramWord { value: select, kind: WordKind::BankSelect },
+            ProgramWord { value: 0, kind: WordKind::Wait },
+        ]
+    }
+
+    fn expected(select: u64, bits: &[(u8, u64)]) -> Vec<ProgramWord> {
+        let mut v = bracket(select);
+        for &(bit, value) in bits {
+            v.push(ProgramWord { value, kind: WordKind::Bit(bit) });
+            v.push(ProgramWord { value: 0, kind: WordKind::Wait });
+        }
+        v.extend(bracket(select));
+        v
+    }
+
+    #[test]
+    fn nothing_to_burn() {
+        for bank in 0..13 {
+            assert!(words(bank, 0).is_empty());
+        }
+    }
+
+    #[test]
+    fn cntl_bank() {
+        assert_eq!(words(0, 0b101), expected(0xa08a_28ac_0000_0001, &[
+            (0, 0xa08a_28ac_0000_4003),
+            (2, 0xa08a_28ac_0000_4203),
+        ]));
+    }
+
+    #[test]
+    fn first_key_bank() {
+        assert_eq!(words(1, 0x8000_0010), expected(0xa08a_28ac_0000_00a1, &[
+            (4, 0xa08a_28ac_0000_44a3),
+            (31, 0xa08a_28ac_0000_5fa3),
+        ]));
+    }
+
+    #[test]
+    fn shared_and_user_banks() {
+        assert_eq!(words(11, 0x0001_0000), expected(0xa08a_28ac_0000_00f1, &[
+            (16, 0xa08a_28ac_0000_50f3),
+        ]));
+        assert_eq!(words(12, 0x8000_0001), expected(0xa08a_28ac_0000_00f9, &[
+            (0, 0xa08a_28ac_0000_40fb),
+            (31, 0xa08a_28ac_0000_5ffb),
+        ]));
+    }
+
+    #[test]
+    fn bit_index_only_touches_its_field() {
+        let params = DeviceParams::SEVEN_SERIES;
+        for bank in 0..13 {
+            let base: u64 = params.bit_word(bank, 0);
+            assert_eq!(base, 0xa08a_28ac_0000_4000 | params.word_select(bank) as u64);
+            for bit in 0..32u8 {
+                assert_eq!(params.bit_word(bank, bit), base | (bit as u64) << 8);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn no_bit_past_31() {
+        DeviceParams::SEVEN_SERIES.bit_word(1, 32);
+    }
+
+    #[test]
+    fn bits_in_ascending_order() {
+        let bits: Vec<u8> = words(5, 0xFFFF_FFFF).iter().filter_map(|w| match w.kind {
+            WordKind::Bit(b) => Some(b),
+            _ => None,
+        }).collect();
+        assert_eq!(bits, (0..32).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn blank_bits_are_skipped() {
+        assert_eq!(words(5, 0b1010_0101), expected(0xa08a_28ac_0000_00c1, &[
+            (0, 0xa08a_28ac_0000_40c3),
+            (2, 0xa08a_28ac_0000_42c3),
+            (5, 0xa08a_28ac_0000_45c3),
+            (7, 0xa08a_28ac_0000_47c3),
+        ]));
+    }
+
+    #[test]
+    fn select_codes() {
+        let p = DeviceParams::SEVEN_SERIES;
+        assert_eq!(p.bank_select(0), 0x01);
+        assert_eq!(p.word_select(0), 0x03);
+        for bank in 1..13 {
+            assert_eq!(p.bank_select(bank), 0xA1 + 8 * (bank as u8 - 1));
+            assert_eq!(p.word_select(bank), p.bank_select(bank) | 0b10);
+        }
+    }
+
+    fn bits_of(words: &[ProgramWord]) -> Vec<u8> {
+        words.iter().filter_map(|w| match w.kind {
+            WordKind::Bit(b) => Some(b),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn shuffled_is_a_permutation() {
+        let order = BitOrderPolicy::Shuffled { seed: 0x5EED };
+        let ones: u32 = 0x3A5A_F00F;
+        let shuffled: Vec<ProgramWord> = dr_words_for_bank_ordered(7, ones, &DeviceParams::SEVEN_SERIES, order).collect();
+        let ascending = words(7, ones);
+        assert_eq!(shuffled.len(), ascending.len());
+        // same bracket
+        assert_eq!(shuffled[..4], ascending[..4]);
+        assert_eq!(shuffled[shuffled.len() - 4..], ascending[ascending.len() - 4..]);
+
+        let bits = bits_of(&shuffled);
+        assert_ne!(bits, bits_of(&ascending));
+        let mut sorted = bits.clone();
+        sorted.sort();
+        assert_eq!(sorted, bits_of(&ascending));
+    }
+
+    #[test]
+    fn shuffle_depends_on_seed_and_bank() {
+        let order = |seed| BitOrderPolicy::Shuffled { seed };
+        assert_eq!(bit_order(3, 0xFFFF_FFFF, order(1)), bit_order(3, 0xFFFF_FFFF, order(1)));
+        assert_ne!(bit_order(3, 0xFFFF_FFFF, order(1)), bit_order(3, 0xFFFF_FFFF, order(2)));
+        assert_ne!(bit_order(3, 0xFFFF_FFFF, order(1)), bit_order(4, 0xFFFF_FFFF, order(1)));
+        // nothing to shuffle
+        assert_eq!(bit_order(3, 0x10, order(1)), bit_order(3, 0x10, BitOrderPolicy::Ascending));
+        assert_eq!(bit_order(3, 0, order(1)).1, 0);
+    }
+}
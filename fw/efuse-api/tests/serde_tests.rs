@@ -0,0 +1,78 @@
+#![cfg(feature = "serde")]
+
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::messages::*;
+    use efuse_api::test_utils::*;
+
+    fn intent() -> EfuseIntent {
+        EfuseIntent { key: test_key(0xC3), user: 0x8000_0042, cntl: 0x24, allow_zero_key: false, allow_weak_key: true }
+    }
+
+    /// an EfuseIntent as a server that doesn't check CNTL would send it
+    #[derive(serde::Serialize)]
+    struct Unchecked {
+        key: [u8; 32],
+        user: u32,
+        cntl: u8,
+        allow_zero_key: bool,
+        allow_weak_key: bool,
+    }
+
+    #[test]
+    fn intent_round_trip() {
+        let bytes: Vec<u8> = postcard::to_allocvec(&intent()).unwrap();
+        // the key goes first, byte for byte, whatever the host
+        assert_eq!(bytes[..32], test_key(0xC3));
+        assert_eq!(postcard::from_bytes::<EfuseIntent>(&bytes).unwrap(), intent());
+        assert_eq!(postcard::to_allocvec(&intent()).unwrap(), bytes);
+    }
+
+    #[test]
+    fn reserved_cntl_does_not_deserialize() {
+        let sent = Unchecked { key: test_key(0xC3), user: 0, cntl: 0x40, allow_zero_key: false, allow_weak_key: false };
+        let bytes: Vec<u8> = postcard::to_allocvec(&sent).unwrap();
+        assert!(postcard::from_bytes::<EfuseIntent>(&bytes).is_err());
+
+        let sent = Unchecked { cntl: CNTL_MASK, ..sent };
+        let bytes: Vec<u8> = postcard::to_allocvec(&sent).unwrap();
+        assert_eq!(postcard::from_bytes::<EfuseIntent>(&bytes).unwrap().cntl, CNTL_MASK);
+    }
+
+    #[test]
+    fn plan_and_report_round_trip() {
+        let mut jp = EfuseModelPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.apply_intent(&intent()).unwrap();
+
+        let plan: BurnPlan = efuse.plan().unwrap();
+        let bytes: Vec<u8> = postcard::to_allocvec(&plan).unwrap();
+        assert_eq!(postcard::from_bytes::<BurnPlan>(&bytes).unwrap(), plan);
+
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        let report: BurnReport = efuse.last_report().unwrap();
+        let bytes: Vec<u8> = postcard::to_allocvec(&report).unwrap();
+        let back: BurnReport = postcard::from_bytes(&bytes).unwrap();
+        assert_eq!(back.requested, report.requested);
+        assert_eq!((back.committed, back.weak_key_overridden), (report.committed, report.weak_key_overridden));
+        assert_eq!((back.order, back.manifest), (report.order, report.manifest));
+    }
+
+    #[test]
+    fn a_plan_for_bits_without_fuses() {
+        let mut bits: [u32; FUSE_BANKS] = [0; FUSE_BANKS];
+        bits[0] = !bank_fuses(0);
+        let bytes: Vec<u8> = postcard::to_allocvec(&BurnPlan { bits, pulses: 0 }).unwrap();
+        assert!(postcard::from_bytes::<BurnPlan>(&bytes).is_err());
+
+        // pulses are recounted rather than taken on trust
+        bits[0] = bank_fuses(0);
+        let bytes: Vec<u8> = postcard::to_allocvec(&BurnPlan { bits, pulses: 1 }).unwrap();
+        assert_eq!(postcard::from_bytes::<BurnPlan>(&bytes).unwrap(), BurnPlan::new(bits));
+    }
+}
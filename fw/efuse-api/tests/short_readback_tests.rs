@@ -0,0 +1,68 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::sequences::*;
+    use efuse_api::test_utils::*;
+
+    /// a device with test_key(0x17) and a USER value fused, whose cable drops `after` bits into the
+    /// readback under `ir`
+    fn flaky(ir: Ir, after: usize) -> CutoffPhy<EfuseModelPhy> {
+        CutoffPhy::new(EfuseModelPhy::with_banks(banks_image_ecc(&test_key(0x17), 0x00C0_FFEE, 0)), ir, after)
+    }
+
+    #[test]
+    fn names_the_readback() {
+        for &(ir, readback, expected) in &[(Ir::FuseKey, Readback::Key, 256), (Ir::FuseUser, Readback::User, 32), (Ir::FuseCntl, Readback::Cntl, 14)] {
+            let mut jm: JtagMach = JtagMach::new();
+            let mut jp = flaky(ir, 9);
+            let mut efuse: EfuseApi = EfuseApi::new();
+            assert_eq!(efuse.fetch(&mut jm, &mut jp), Err(EfuseError::ShortReadback { readback, captured: 9, expected }));
+            assert!(jp.dropped());
+        }
+    }
+
+    #[test]
+    fn nothing_captured() {
+        // the cable drops as the shift starts
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = flaky(Ir::FuseUser, 0);
+        let mut efuse: EfuseApi = EfuseApi::new();
+        assert_eq!(efuse.fetch(&mut jm, &mut jp), Err(EfuseError::ShortReadback { readback: Readback::User, captured: 0, expected: 32 }));
+    }
+
+    #[test]
+    fn retried_in_place() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = flaky(Ir::FuseCntl, 5);
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.set_key(test_key(0x17));
+        assert!(matches!(efuse.fetch(&mut jm, &mut jp), Err(EfuseError::ShortReadback { .. })));
+        // what was read before the drop isn't taken for the fused state
+        assert_eq!(efuse.phy_key(), [0; 32]);
+        assert_eq!(efuse.phy_user(), 0);
+
+        // the same API and machine fetch once the cable is back, and keep what was staged
+        jp.reconnect();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.phy_key(), test_key(0x17));
+        assert_eq!(efuse.phy_user(), 0x00C0_FFEE);
+        assert_eq!(efuse.api_key(), test_key(0x17));
+    }
+
+    #[test]
+    fn other_failures_keep_their_errors() {
+        // a transport failure outside the data shift is an ordinary JTAG error
+        struct Unplugged;
+        impl JtagPhy for Unplugged {
+            fn sync(&mut self, _tdi: bool, _tms: bool) -> bool { false }
+            fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool { false }
+            fn pause(&mut self, _us: u32) {}
+            fn try_sync(&mut self, _tdi: bool, _tms: bool) -> Result<bool, PhyError> { Err(PhyError::Transport) }
+        }
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        assert_eq!(efuse.fetch(&mut jm, &mut Unplugged), Err(EfuseError::Jtag { phase: Phase::Fetch, err: JtagError::Phy(PhyError::Transport) }));
+    }
+}
@@ -0,0 +1,204 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::messages::*;
+    use efuse_api::sequences::*;
+    use efuse_api::status::*;
+    use efuse_api::test_utils::*;
+
+    /// an API fetched from `jp`, with test_key(0x69) and a USER value staged
+    fn staged(jm: &mut JtagMach, jp: &mut EfuseModelPhy) -> EfuseApi {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(jm, jp).unwrap();
+        efuse.set_key(test_key(0x69));
+        efuse.set_user(0x0012_3456);
+        efuse
+    }
+
+    #[test]
+    fn decode() {
+        let status: FuseStatus = FuseStatus::from_capture(0xDEAD_0000_0000_A502);
+        assert_eq!(status.raw, 0xA502);
+        assert!(status.is_error() && !status.is_busy() && !status.is_clean());
+        assert!(FuseStatus::from_capture(FuseStatus::BUSY as u128).is_busy());
+        assert!(FuseStatus::from_capture(0xF0).is_clean());
+        assert!(FuseStatus::default().is_clean());
+    }
+
+    #[test]
+    fn clean_burn() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse = staged(&mut jm, &mut jp);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        let report: BurnReport = efuse.last_report().unwrap();
+        assert!(report.committed);
+        assert_eq!(report.status_errors, [0; FUSE_BANKS]);
+    }
+
+    #[test]
+    fn error_while_programming_a_bank() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let raw: u32 = 0x0000_A500 | FuseStatus::PROGRAM_ERROR;
+        jp.report_status(7, raw);
+        let mut efuse = staged(&mut jm, &mut jp);
+        assert_eq!(efuse.burn(&mut jm, &mut jp), Err(EfuseError::DeviceReportedError { bank: Some(7), raw_status: raw }));
+
+        let report: BurnReport = efuse.last_report().unwrap();
+        // the commit did run; it's the status that failed the burn
+        assert!(report.committed);
+        for (index, &errors) in report.status_errors.iter().enumerate() {
+            assert_eq!(errors, if index == 7 { raw } else { 0 }, "bank {}", index);
+        }
+        // and the report's wire form leaves them out
+        let mut bytes = [0u8; BurnReport::WIRE_LEN];
+        report.encode(&mut bytes);
+        assert_eq!(BurnReport::decode(&bytes), Some(BurnReport { status_errors: [0; FUSE_BANKS], programmed: [0; FUSE_BANKS], pulses: 0, ..report }));
+    }
+
+    #[test]
+    fn busy_is_only_an_error_after_the_commit() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        jp.report_status(3, FuseStatus::BUSY);
+        let mut efuse = staged(&mut jm, &mut jp);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.last_report().unwrap().status_errors, [0; FUSE_BANKS]);
+
+        let mut jp = EfuseModelPhy::new();
+        jp.status_after_commit(FuseStatus::BUSY);
+        let mut efuse = staged(&mut jm, &mut jp);
+        assert_eq!(efuse.burn(&mut jm, &mut jp), Err(EfuseError::DeviceReportedError { bank: None, raw_status: FuseStatus::BUSY }));
+        assert_eq!(efuse.last_report().unwrap().status_errors, [0; FUSE_BANKS]);
+    }
+
+    #[test]
+    fn cleared_by_the_next_burn() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        jp.status_after_commit(FuseStatus::PROGRAM_ERROR);
+        let mut efuse = staged(&mut jm, &mut jp);
+        assert!(efuse.burn(&mut jm, &mut jp).is_err());
+
+        // the device stops failing; what the last burn latched doesn't carry over
+        jp.status_after_commit(0);
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_cntl(CNTL_CFG_AES_ONLY).unwrap();
+        efuse.burn(&mut jm, &mut jp).unwrap();
+    }
+
+    #[test]
+    fn checked_before_the_cntl_readback() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse = staged(&mut jm, &mut jp);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        efuse.verify_boot(&mut || Ok(())).unwrap();
+
+        let raw: u32 = FuseStatus::PROGRAM_ERROR | FuseStatus::BUSY;
+        jp.report_status(CNTL_BANK, raw);
+        let commits: usize = jp.commits();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_cntl(CNTL_CFG_AES_ONLY).unwrap();
+        // the copy did program, but the status fails the burn before it's read back, and the
+        // redundant copy isn't touched
+        assert_eq!(efuse.burn_cntl(&mut jm, &mut jp), Err(EfuseError::DeviceReportedError { bank: Some(CNTL_BANK), raw_status: raw }));
+        assert_eq!(jp.commits(), commits + 1);
+        assert_eq!(efuse.last_report().unwrap().status_errors[CNTL_BANK], raw);
+        assert!(efuse.last_report().unwrap().committed);
+    }
+
+    /// Passes everything to `inner`, but has the DR scans numbered in `scans` (counting from
+    /// the first) capture PROGRAM_ERROR as well as what `inner` shifts out
+    struct ErrorOn<P: JtagPhy> {
+        inner: P,
+        tap: TapState,
+        scan: usize,
+        bit: usize,
+        scans: Vec<usize>,
+    }
+
+    impl<P: JtagPhy> JtagPhy for ErrorOn<P> {
+        fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+            let mut tdo: bool = self.inner.sync(tdi, tms);
+            if self.tap == TapState::ShiftDr {
+                tdo |= self.bit == 1 && self.scans.contains(&self.scan);
+                self.bit += 1;
+                if tms {
+                    self.scan += 1;
+                    self.bit = 0;
+                }
+            }
+            self.tap = self.tap.next(tms);
+            tdo
+        }
+
+        fn nosync(&mut self, tdi: bool, tms: bool, tck: bool) -> bool {
+            self.inner.nosync(tdi, tms, tck)
+        }
+
+        fn pause(&mut self, us: u32) {
+            self.inner.pause(us);
+        }
+    }
+
+    /// the DR scans of a clean burn of what staged() stages, as shifted in, and the words it
+    /// programs with where the first of them falls among those scans
+    fn burn_scans() -> (Vec<u128>, Vec<ProgramWord>, usize) {
+        let mut jm: JtagMach = JtagMach::new();
+        let efuse = staged(&mut jm, &mut EfuseModelPhy::new());
+        let words: Vec<ProgramWord> = efuse.program_words().collect();
+        let mut buf = vec![Transition::BLANK; 1 << 20];
+        let mut jp = RecordingPhy::new(EfuseModelPhy::new(), &mut buf);
+        let mut efuse = staged(&mut jm, &mut EfuseModelPhy::new());
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        assert_eq!(jp.dropped(), 0);
+        let scans: Vec<u128> = jp.iter_dr_shifts().map(|shift| shift.tdi_value()).collect();
+        let first: usize = (0..scans.len()).find(|&n| scans[n..].iter().zip(words.iter()).all(|(&scan, word)| scan == word.value as u128)).unwrap();
+        (scans, words, first)
+    }
+
+    /// burns what staged() stages, with the DR scans of the program words numbered in `words`
+    /// capturing an error
+    fn burn_with_errors(words: &[usize]) -> (Result<(), EfuseError>, BurnReport) {
+        let (_, _, first) = burn_scans();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse = staged(&mut jm, &mut EfuseModelPhy::new());
+        let mut jp = ErrorOn { inner: EfuseModelPhy::new(), tap: TapState::TestLogicReset, scan: 0, bit: 0, scans: words.iter().map(|&word| first + word).collect() };
+        let result: Result<(), EfuseError> = efuse.burn(&mut jm, &mut jp);
+        (result, efuse.last_report().unwrap())
+    }
+
+    #[test]
+    fn every_program_word_is_checked() {
+        let (scans, words, first) = burn_scans();
+        // the words are shifted as they were compiled, each a DR scan of its own
+        assert_eq!(scans[first..first + words.len()].iter().map(|&scan| scan as u64).collect::<Vec<u64>>(), words.iter().map(|word| word.value).collect::<Vec<u64>>());
+        let pulse: usize = words.iter().position(|word| matches!(word.kind, WordKind::Bit(_))).unwrap();
+        let (bank, next) = (USER_BANK, SHARED_BANK);
+
+        // a select before any pulse can only be about its own bank
+        let select: usize = words.iter().position(|word| word.kind == WordKind::BankSelect).unwrap();
+        assert!(select < pulse);
+        let (result, report) = burn_with_errors(&[select]);
+        assert_eq!(result, Err(EfuseError::DeviceReportedError { bank: Some(bank), raw_status: FuseStatus::PROGRAM_ERROR }));
+        assert_eq!(report.status_errors.iter().filter(|&&raw| raw != 0).count(), 1);
+
+        // the next bank's unlock shows an error its pulses can't have caused yet
+        let unlock: usize = (pulse..words.len()).find(|&n| words[n].kind == WordKind::Unlock).unwrap();
+        let (result, report) = burn_with_errors(&[unlock]);
+        assert_eq!(result, Err(EfuseError::DeviceReportedError { bank: Some(bank), raw_status: FuseStatus::PROGRAM_ERROR }));
+        assert_eq!(report.status_errors[next], 0);
+
+        // as does its bank select; an error already put down to a bank stays with it
+        let select: usize = (unlock..words.len()).find(|&n| words[n].kind == WordKind::BankSelect).unwrap();
+        for &errors in [[select].as_ref(), &[pulse, unlock, select]].iter() {
+            let (_, report) = burn_with_errors(errors);
+            assert_eq!(report.status_errors[bank], FuseStatus::PROGRAM_ERROR);
+            assert_eq!(report.status_errors.iter().filter(|&&raw| raw != 0).count(), 1);
+        }
+    }
+}
@@ -0,0 +1,161 @@
+#![cfg(feature = "svf")]
+
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::messages::*;
+    use efuse_api::sequences::*;
+    use efuse_api::svf::*;
+    use efuse_api::test_utils::*;
+
+    const USER: u32 = 0x0051_F00D;
+
+    /// A command read back from the SVF
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    enum Command {
+        Sir(Vec<bool>),
+        Sdr(Vec<bool>),
+        IdleCycles(u32),
+        /// microseconds
+        Wait(u64),
+        State(String),
+        Other(String),
+    }
+
+    /// the `len` TDI bits of a hex string, first shifted first
+    fn tdi_bits(len: usize, hex: &str) -> Vec<bool> {
+        let digits: Vec<u32> = hex.chars().rev().map(|c| c.to_digit(16).unwrap()).collect();
+        assert_eq!(digits.len(), len.div_ceil(4));
+        (0..len).map(|n| (digits[n / 4] >> (n % 4)) & 1 == 1).collect()
+    }
+
+    fn parse(svf: &str) -> Vec<Command> {
+        let body: String = svf.lines().filter(|line| !line.starts_with('!')).collect::<Vec<&str>>().join(" ");
+        body.split(';').map(str::trim).filter(|s| !s.is_empty()).map(|statement| {
+            let words: Vec<&str> = statement.split_whitespace().collect();
+            match words[..] {
+                [command @ ("SIR" | "SDR"), len, "TDI", hex] => {
+                    let len: usize = len.parse().unwrap();
+                    let bits = tdi_bits(len, hex.trim_start_matches('(').trim_end_matches(')'));
+                    if command == "SIR" { Command::Sir(bits) } else { Command::Sdr(bits) }
+                },
+                ["RUNTEST", "IDLE", count, "TCK"] => Command::IdleCycles(count.parse().unwrap()),
+                ["RUNTEST", _, seconds, "SEC"] => {
+                    let (whole, fraction) = seconds.split_once('.').unwrap();
+                    Command::Wait(whole.parse::<u64>().unwrap() * 1_000_000 + fraction.parse::<u64>().unwrap())
+                },
+                ["STATE", state] => Command::State(state.to_string()),
+                _ => Command::Other(statement.to_string()),
+            }
+        }).collect()
+    }
+
+    /// a fetched-as-blank EfuseApi with test_key(0x91) and USER staged
+    fn staged() -> EfuseApi {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.load_snapshot(&FuseSnapshot { banks: [0; FUSE_BANKS] });
+        efuse.set_key(test_key(0x91));
+        efuse.set_user(USER);
+        efuse
+    }
+
+    /// burns the staged state of `efuse` into an SVF, recorded by a ScriptedPhy as well
+    fn burn_to_svf(efuse: &mut EfuseApi) -> (ScriptedPhy, Vec<Command>) {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = SvfWriter::new(ScriptedPhy::new(), String::new());
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        let (recorded, svf) = jp.finish().unwrap();
+        (recorded, parse(&svf))
+    }
+
+    #[test]
+    fn burn_round_trips() {
+        let mut efuse = staged();
+        let words: Vec<u64> = efuse.program_words().map(|w| w.value).collect();
+        let (recorded, commands) = burn_to_svf(&mut efuse);
+        // nothing but the header is left over
+        let others: Vec<&Command> = commands.iter().filter(|c| matches!(c, Command::Other(_))).collect();
+        assert_eq!(others.len(), 7);
+        assert_eq!(commands[..7].iter().collect::<Vec<&Command>>(), others);
+
+        // every DR scan, bit for bit
+        let sdrs: Vec<&Vec<bool>> = commands.iter().filter_map(|c| match c { Command::Sdr(bits) => Some(bits), _ => None }).collect();
+        let writes: Vec<&Vec<bool>> = recorded.dr_writes_all().iter().map(|w| &w.bits).collect();
+        assert_eq!(sdrs, writes);
+        // and every instruction
+        let sirs: Vec<u32> = commands.iter().filter_map(|c| match c {
+            Command::Sir(bits) => {
+                assert_eq!(bits.len(), IR_BITS);
+                Some(bits.iter().enumerate().map(|(i, &b)| (b as u32) << i).sum())
+            },
+            _ => None,
+        }).collect();
+        assert_eq!(sirs, recorded.ir_history());
+
+        // the waits add up to the pauses
+        let waited: u64 = commands.iter().map(|c| match c { Command::Wait(us) => *us, _ => 0 }).sum();
+        assert_eq!(waited, recorded.elapsed_us());
+
+        // the programming words are all there, in burn order, with the status reads after them
+        let fuse_cts: Vec<u64> = recorded.dr_writes(Ir::FuseCts).map(|w| w.value() as u64).collect();
+        assert_eq!(fuse_cts[..words.len()], words[..]);
+    }
+
+    #[test]
+    fn idle_cycles_become_runtest() {
+        let mut efuse = staged();
+        efuse.set_burn_config(BurnConfig { timing: BurnTiming { bit_idle_cycles: 40, ..BurnTiming::default() }, ..BurnConfig::default() });
+        let bits: usize = efuse.plan().unwrap().pulses as usize;
+        let (_, commands) = burn_to_svf(&mut efuse);
+        let mut idles: Vec<u32> = commands.iter().filter_map(|c| match c { Command::IdleCycles(n) => Some(*n), _ => None }).collect();
+        // the first is the dwell after ISC_ENABLE
+        assert_eq!(idles.remove(0), efuse.profile().isc.unwrap().enable_idle);
+        assert_eq!(idles, vec![40; bits]);
+    }
+
+    #[test]
+    fn a_short_read() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = SvfWriter::new(ScriptedPhy::new(), String::new());
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.read_idcode(&mut jm, &mut jp).unwrap();
+        jp.pause(1_500_250);
+        let (_, svf) = jp.finish().unwrap();
+        assert_eq!(svf, "\
+! efuse-api JTAG vectors
+TRST ABSENT;
+ENDIR IDLE;
+ENDDR IDLE;
+HIR 0;
+TIR 0;
+HDR 0;
+TDR 0;
+STATE RESET;
+STATE IDLE;
+SIR 6 TDI (09);
+SDR 32 TDI (00000000);
+RUNTEST IDLE 1.500250 SEC;
+");
+        assert_eq!(parse(&svf)[9..], [Command::Sir(tdi_bits(6, "09")), Command::Sdr(vec![false; 32]), Command::Wait(1_500_250)]);
+    }
+
+    #[test]
+    fn a_failing_writer_fails_the_burn() {
+        /// takes `left` bytes, then fails
+        struct Short { left: usize }
+        impl core::fmt::Write for Short {
+            fn write_str(&mut self, s: &str) -> core::fmt::Result {
+                self.left = self.left.checked_sub(s.len()).ok_or(core::fmt::Error)?;
+                Ok(())
+            }
+        }
+
+        let mut efuse = staged();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = SvfWriter::new(ScriptedPhy::new(), Short { left: 2000 });
+        assert!(efuse.burn(&mut jm, &mut jp).is_err());
+        assert!(jp.finish().is_err());
+    }
+}
@@ -0,0 +1,122 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::messages::*;
+    use efuse_api::protocol::*;
+    use efuse_api::sequences::*;
+    use efuse_api::test_utils::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Sync>() {}
+
+    struct NullClock;
+
+    impl Clock for NullClock {
+        fn now_us(&self) -> u64 {
+            0
+        }
+    }
+
+    // checked at compile time: these stop the build if a type stops being Send or Sync
+    const _: fn() = || {
+        assert_send::<EfuseApi>();
+        assert_sync::<EfuseApi>();
+        assert_send::<EfusePhy>();
+        assert_sync::<EfusePhy>();
+        assert_send::<JtagMach>();
+        assert_sync::<JtagMach>();
+        assert_send::<FuseSnapshot>();
+        assert_sync::<FuseSnapshot>();
+        assert_send::<ProvisioningManifest>();
+        assert_sync::<ProvisioningManifest>();
+        assert_send::<BurnReport>();
+        assert_sync::<BurnReport>();
+        assert_send::<ValidationReport>();
+        assert_sync::<ValidationReport>();
+        assert_send::<BurnConfig>();
+        assert_send::<BurnDuration>();
+        assert_send::<ProgramWord>();
+        assert_send::<BankView>();
+        assert_send::<EfuseError>();
+        assert_sync::<EfuseError>();
+        assert_send::<Server>();
+        // the simulators move to worker threads too
+        assert_send::<EfuseModelPhy>();
+        assert_send::<ScriptedPhy>();
+        assert_send::<DeadlinePhy<EfuseModelPhy, NullClock>>();
+    };
+
+    fn key(unit: u8) -> [u8; 32] {
+        let mut key = [0u8; 32];
+        for (i, k) in key.iter_mut().enumerate() {
+            *k = (i as u8).wrapping_mul(41).wrapping_add(unit.wrapping_mul(97)) ^ 0x6B;
+        }
+        key
+    }
+
+    /// unit `unit` with its USER fuse already burned
+    fn snapshot(unit: u8) -> FuseSnapshot {
+        FuseSnapshot { banks: banks_image_ecc(&[0; 32], 0x0100_0000 * unit as u32, 0) }
+    }
+
+    fn plan(unit: u8) -> (Vec<ProgramWord>, BurnDuration) {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.load_snapshot(&snapshot(unit));
+        efuse.set_key(key(unit));
+        efuse.set_user(0x0100_0000 * unit as u32);
+        efuse.validate().unwrap();
+        (efuse.program_words().collect(), efuse.estimate_burn_duration())
+    }
+
+    #[test]
+    fn plans_on_a_thread_pool() {
+        let workers: Vec<_> = (1..=6u8).map(|unit| thread::spawn(move || (unit, plan(unit)))).collect();
+        for worker in workers {
+            let (unit, planned) = worker.join().unwrap();
+            assert_eq!(planned, plan(unit));
+        }
+    }
+
+    #[test]
+    fn shared_planner() {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.load_snapshot(&snapshot(3));
+        efuse.set_key(key(3));
+        efuse.set_user(0x0300_0000);
+        efuse.set_burn_config(BurnConfig { order: BitOrderPolicy::Shuffled { seed: 99 }, ..BurnConfig::default() });
+        let efuse = Arc::new(efuse);
+        let expected: Vec<ProgramWord> = efuse.program_words().collect();
+        let readers: Vec<_> = (0..4).map(|_| {
+            let efuse = Arc::clone(&efuse);
+            thread::spawn(move || efuse.program_words().collect::<Vec<ProgramWord>>())
+        }).collect();
+        for reader in readers {
+            assert_eq!(reader.join().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn two_adapters_at_once() {
+        // each adapter gets its own EfuseApi, JtagMach and phy, all moved to its thread
+        let adapters: Vec<_> = (1..=2u8).map(|unit| thread::spawn(move || {
+            let mut jp = EfuseModelPhy::with_banks(snapshot(unit).banks);
+            let mut jm: JtagMach = JtagMach::new();
+            let mut efuse: EfuseApi = EfuseApi::new();
+            efuse.fetch(&mut jm, &mut jp).unwrap();
+            assert_eq!(efuse.phy_user(), 0x0100_0000 * unit as u32);
+            efuse.set_key(key(unit));
+            efuse.set_user(efuse.phy_user());
+            efuse.burn(&mut jm, &mut jp).unwrap();
+            (unit, efuse, jp)
+        })).collect();
+        for adapter in adapters {
+            let (unit, efuse, jp) = adapter.join().unwrap();
+            assert_eq!(jp.banks(), banks_image_ecc(&key(unit), 0x0100_0000 * unit as u32, 0));
+            assert!(efuse.last_report().unwrap().committed);
+        }
+    }
+}
@@ -0,0 +1,194 @@
+#[cfg(test)]
+mod tests {
+    use efuse_api::messages::*;
+    use efuse_api::transport::*;
+    use efuse_api::test_utils::*;
+    use efuse_api::sequences::BitOrderPolicy;
+    use efuse_api::boot::BootCheck;
+
+    fn snapshot() -> FuseSnapshot {
+        let mut banks = [0u32; 13];
+        for (i, b) in banks.iter_mut().enumerate() {
+            // plenty of zero bytes, to exercise the COBS code path
+            *b = (i as u32) << 8 | 0x2D00_0000;
+        }
+        FuseSnapshot { banks }
+    }
+
+    fn manifest() -> ProvisioningManifest {
+        let mut key = [0u8; 32];
+        for (i, k) in key.iter_mut().enumerate() {
+            *k = 0x10 + i as u8;
+        }
+        ProvisioningManifest { key, user: 0xA5C3_3C5A, cntl: 0x2B }
+    }
+
+    fn report() -> BurnReport {
+        let mut requested = [0u32; 13];
+        requested[12] = 0x0FA5_C33C;
+        BurnReport { requested, committed: true, weak_key_overridden: false, order: BitOrderPolicy::Shuffled { seed: 0x0102_0304_0506_0708 }, manifest: None, status_errors: [0; 13], boot_check: BootCheck::NotRequired, pre_burn: None, programmed: [0; 13], pulses: 0, verify: None }
+    }
+
+    fn replay_report() -> BurnReport {
+        let mut plan_summary = [0u8; 13];
+        plan_summary[12] = 13;
+        let manifest = VectorManifest { sha256_of_words: [0xA7; 32], word_count: 34, device_params_id: 0x00C0_FFEE, plan_summary };
+        BurnReport { manifest: Some(manifest), ..report() }
+    }
+
+    fn messages() -> Vec<Message> {
+        vec![
+            Message::Snapshot(snapshot()),
+            Message::Manifest(manifest()),
+            Message::Report(report()),
+            Message::Report(replay_report()),
+            Message::Error(0x0102),
+        ]
+    }
+
+    fn one_frame(msg: &Message) -> Pipe {
+        let mut pipe = Pipe::new();
+        send(&mut pipe, msg).unwrap();
+        pipe
+    }
+
+    #[test]
+    fn cobs_vectors() {
+        // reference vectors from the COBS paper / wikipedia
+        let cases: [(&[u8], &[u8]); 5] = [
+            (&[], &[0x01]),
+            (&[0x00], &[0x01, 0x01]),
+            (&[0x00, 0x00], &[0x01, 0x01, 0x01]),
+            (&[0x11, 0x22, 0x00, 0x33], &[0x03, 0x11, 0x22, 0x02, 0x33]),
+            (&[0x11, 0x00, 0x00, 0x00], &[0x02, 0x11, 0x01, 0x01, 0x01]),
+        ];
+        for &(raw, encoded) in cases.iter() {
+            let mut out = [0u8; 16];
+            let len = cobs_encode(raw, &mut out).unwrap();
+            assert_eq!(&out[..len], encoded);
+            let mut buf = encoded.to_vec();
+            let len = cobs_decode_in_place(&mut buf).unwrap();
+            assert_eq!(&buf[..len], raw);
+        }
+    }
+
+    #[test]
+    fn cobs_long_runs() {
+        for &n in [253usize, 254, 255, 600].iter() {
+            let raw: Vec<u8> = (0..n).map(|i| (i % 255) as u8 + 1).collect();
+            let mut out = vec![0u8; cobs_max_len(n)];
+            let len = cobs_encode(&raw, &mut out).unwrap();
+            assert!(!out[..len].contains(&0));
+            let len = cobs_decode_in_place(&mut out[..len]).unwrap();
+            assert_eq!(&out[..len], &raw[..]);
+        }
+    }
+
+    #[test]
+    fn crc16_check_value() {
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut pipe = Pipe::new();
+        for msg in messages().iter() {
+            send(&mut pipe, msg).unwrap();
+        }
+        for msg in messages().iter() {
+            assert_eq!(receive(&mut pipe).as_ref(), Ok(msg));
+        }
+        assert!(pipe.is_empty());
+        assert_eq!(receive(&mut pipe), Err(TransportError::Closed));
+    }
+
+    #[test]
+    fn frames_have_no_inner_delimiter() {
+        for msg in messages().iter() {
+            let mut pipe = one_frame(msg);
+            let bytes: Vec<u8> = pipe.bytes_mut().iter().copied().collect();
+            assert!(bytes.len() <= MAX_FRAME + 1);
+            assert_eq!(bytes.iter().position(|&b| b == DELIMITER), Some(bytes.len() - 1));
+        }
+    }
+
+    #[test]
+    fn flipped_byte() {
+        for msg in messages().iter() {
+            let len = one_frame(msg).len();
+            for at in 0..len - 1 {
+                let mut pipe = one_frame(msg);
+                pipe.bytes_mut()[at] ^= 0x40;
+                let err = receive(&mut pipe).unwrap_err();
+                assert!(matches!(err, TransportError::Crc | TransportError::Encoding | TransportError::Truncated),
+                    "{:?} at byte {}", err, at);
+                // the corrupt frame is consumed, so the stream is still in sync
+                send(&mut pipe, &Message::Error(7)).unwrap();
+                assert_eq!(receive(&mut pipe), Ok(Message::Error(7)));
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_frame() {
+        let mut pipe = one_frame(&Message::Manifest(manifest()));
+        let len = pipe.len();
+        // drop the tail of the frame but keep the delimiter
+        pipe.bytes_mut().drain(len - 6..len - 1);
+        assert!(matches!(receive(&mut pipe), Err(TransportError::Crc) | Err(TransportError::Encoding)));
+
+        // a frame that's too short to hold a type and a CRC
+        let mut pipe = Pipe::new();
+        pipe.write_bytes(&[0x03, 0x01, 0x02, DELIMITER]).unwrap();
+        assert_eq!(receive(&mut pipe), Err(TransportError::Truncated));
+    }
+
+    #[test]
+    fn missing_delimiter() {
+        let mut pipe = one_frame(&Message::Report(report()));
+        pipe.bytes_mut().pop_back();
+        assert_eq!(receive(&mut pipe), Err(TransportError::MissingDelimiter));
+    }
+
+    #[test]
+    fn oversized_frame() {
+        let mut pipe = Pipe::new();
+        pipe.write_bytes(&[0x55; MAX_FRAME + 10]).unwrap();
+        pipe.write_bytes(&[DELIMITER]).unwrap();
+        send(&mut pipe, &Message::Error(1)).unwrap();
+        assert_eq!(receive(&mut pipe), Err(TransportError::Oversized));
+        assert_eq!(receive(&mut pipe), Ok(Message::Error(1)));
+    }
+
+    #[test]
+    fn unknown_type_and_bad_length() {
+        // hand-build a valid frame with an unknown type
+        let raw_frame = |raw: &[u8]| -> Pipe {
+            let mut framed = raw.to_vec();
+            framed.extend_from_slice(&crc16(raw).to_le_bytes());
+            let mut out = vec![0u8; cobs_max_len(framed.len())];
+            let len = cobs_encode(&framed, &mut out).unwrap();
+            let mut pipe = Pipe::new();
+            pipe.write_bytes(&out[..len]).unwrap();
+            pipe.write_bytes(&[DELIMITER]).unwrap();
+            pipe
+        };
+        assert_eq!(receive(&mut raw_frame(&[0x42, 1, 2, 3])), Err(TransportError::UnknownType(0x42)));
+        // a snapshot type with a short payload
+        assert_eq!(receive(&mut raw_frame(&[1, 1, 2, 3])), Err(TransportError::Truncated));
+        // an out-of-range bool in a report
+        let mut raw = vec![3u8];
+        raw.extend_from_slice(&[0; 52]);
+        raw.extend_from_slice(&[2, 0, 0]);
+        raw.extend_from_slice(&[0; 8]);
+        assert_eq!(receive(&mut raw_frame(&raw)), Err(TransportError::Truncated));
+    }
+
+    #[test]
+    fn leading_delimiters_skipped() {
+        let mut pipe = Pipe::new();
+        pipe.write_bytes(&[DELIMITER, DELIMITER]).unwrap();
+        send(&mut pipe, &Message::Error(3)).unwrap();
+        assert_eq!(receive(&mut pipe), Ok(Message::Error(3)));
+    }
+}
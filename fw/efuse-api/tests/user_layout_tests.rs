@@ -0,0 +1,95 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::test_utils::*;
+    use efuse_api::userlayout::*;
+
+    /// flags in bank 11, the board revision across banks 11 and 12, a rollback counter in 12
+    fn layout() -> UserLayout {
+        let mut layout = UserLayout::new();
+        layout.register("flags", 0, 4).unwrap();
+        layout.register("board_rev", 4, 8).unwrap();
+        layout.register("rollback", 16, 8).unwrap();
+        layout
+    }
+
+    #[test]
+    fn registration() {
+        let mut layout = layout();
+        assert_eq!(layout.field("board_rev"), Some(UserField { name: "board_rev", offset: 4, width: 8 }));
+        assert_eq!(layout.field("board_rev").unwrap().mask(), 0x0000_0FF0);
+        assert_eq!(layout.unassigned(), 0xFF00_F000);
+
+        assert_eq!(layout.register("hw_id", 10, 4),
+            Err(EfuseError::UserField { err: UserFieldError::Overlap { name: "hw_id", with: "board_rev" } }));
+        assert_eq!(layout.register("flags", 12, 4),
+            Err(EfuseError::UserField { err: UserFieldError::Duplicate { name: "flags" } }));
+        assert_eq!(layout.register("wide", 28, 8),
+            Err(EfuseError::UserField { err: UserFieldError::OutOfRange { name: "wide" } }));
+        assert_eq!(layout.register("empty", 12, 0),
+            Err(EfuseError::UserField { err: UserFieldError::OutOfRange { name: "empty" } }));
+        assert_eq!(layout, self::layout());
+
+        // up against the fields either side, and the top of USER
+        layout.register("hw_id", 12, 4).unwrap();
+        layout.register("spare", 24, 8).unwrap();
+        assert_eq!(layout.unassigned(), 0);
+        let names: Vec<&str> = layout.fields().iter().map(|field| field.name).collect();
+        assert_eq!(names, ["flags", "board_rev", "rollback", "hw_id", "spare"]);
+    }
+
+    #[test]
+    fn a_field_across_the_bank_split() {
+        let layout = layout();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(test_key(0x17));
+        efuse.set_user(0x0033_0009);
+
+        // only the field's bits change
+        efuse.set_field(&layout, "board_rev", 0xA5).unwrap();
+        assert_eq!(efuse.api_user(), 0x0033_0A59);
+        assert_eq!(efuse.get_field(&layout, "board_rev"), Ok(0xA5));
+        assert_eq!(efuse.get_field(&layout, "flags"), Ok(0x9));
+        assert_eq!(efuse.get_field(&layout, "rollback"), Ok(0x33));
+
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        // the low nibble of the field is in bank 11, above the key bytes, the high one in bank 12
+        assert_eq!((jp.banks()[SHARED_BANK] >> 16) & 0xFF, 0x59);
+        assert_eq!(jp.banks()[USER_BANK] & 0xFF, 0x0A);
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.phy_field(&layout, "board_rev"), Ok(0xA5));
+        assert_eq!(efuse.phy_user(), 0x0033_0A59);
+    }
+
+    #[test]
+    fn blown_bits_stay() {
+        let layout = layout();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::with_banks(banks_image_ecc(&test_key(0x17), 0x0000_0A50, 0));
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_user(0x0000_0A50);
+
+        // 0xA5 -> 0x5A clears fuses in both banks
+        assert_eq!(efuse.set_field(&layout, "board_rev", 0x5A), Err(EfuseError::ClearsBlownUserBits { bits: 0x0000_0A50 }));
+        assert_eq!(efuse.set_field(&layout, "board_rev", 0xA4), Err(EfuseError::ClearsBlownUserBits { bits: 0x0000_0010 }));
+        assert_eq!(efuse.api_user(), 0x0000_0A50);
+        // adding bits is fine, on either side of the split
+        efuse.set_field(&layout, "board_rev", 0xE7).unwrap();
+        assert_eq!(efuse.api_user(), 0x0000_0E70);
+
+        // a field keeps its own bits
+        assert_eq!(efuse.set_field(&layout, "flags", 0x10),
+            Err(EfuseError::UserField { err: UserFieldError::TooWide { name: "flags", value: 0x10 } }));
+        assert_eq!(efuse.get_field(&layout, "revision"), Err(EfuseError::UserField { err: UserFieldError::Unknown }));
+        assert_eq!(efuse.set_field(&layout, "revision", 1), Err(EfuseError::UserField { err: UserFieldError::Unknown }));
+        assert_eq!(efuse.api_user(), 0x0000_0E70);
+        assert_eq!(EfuseError::UserField { err: UserFieldError::TooWide { name: "flags", value: 0x10 } }.to_string(),
+            "0x10 doesn't fit in USER field \"flags\"");
+    }
+}
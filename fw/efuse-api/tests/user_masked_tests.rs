@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::checksum::*;
+    use efuse_api::layout::*;
+    use efuse_api::test_utils::*;
+
+    /// an API fetched from a device with `user` fused and nothing else
+    fn fetched(user: u32, config: BurnConfig) -> (EfuseApi, JtagMach, EfuseModelPhy) {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::with_banks(banks_image_ecc(&[0; 32], user, 0));
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.set_burn_config(config);
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        (efuse, jm, jp)
+    }
+
+    #[test]
+    fn leaves_the_rest_alone() {
+        // USER bits 7:0 fused; bank 12 is still blank
+        let (mut efuse, mut jm, mut jp) = fetched(0x0000_0003, BurnConfig::default());
+        efuse.set_user_masked(0xFFAB_FFFF, 0x00FF_0000).unwrap();
+        assert_eq!(efuse.api_user(), 0x00AB_0003);
+        // a later call merges over what is staged
+        efuse.set_user_masked(0x0000_0400, 0x0000_0C00).unwrap();
+        assert_eq!(efuse.api_user(), 0x00AB_0403);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.phy_user(), 0x00AB_0403);
+    }
+
+    #[test]
+    fn blown_bits_stay() {
+        let (mut efuse, _, _) = fetched(0x0000_1203, BurnConfig::default());
+        assert_eq!(efuse.set_user_masked(0x0000_0001, 0x0000_00FF), Err(EfuseError::ClearsBlownUserBits { bits: 0x02 }));
+        assert_eq!(efuse.api_user(), 0);
+        // rewriting blown bits as blown is fine
+        efuse.set_user_masked(0x0000_1203, 0xFFFF_FFFF).unwrap();
+        assert_eq!(efuse.api_user(), 0x0000_1203);
+        efuse.set_user_masked(0x0000_0003, 0x0000_00FF).unwrap();
+        assert_eq!(efuse.api_user(), 0x0000_1203);
+    }
+
+    #[test]
+    fn across_the_bank_split() {
+        // USER bits 7:0 are bits 23:16 of bank 11, the rest start at bit 0 of bank 12
+        let (mut efuse, mut jm, mut jp) = fetched(0, BurnConfig::default());
+        efuse.set_user_masked(0x0000_0180, 0x0000_0180).unwrap();
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        let requested: [u32; FUSE_BANKS] = efuse.last_report().unwrap().requested;
+        assert_eq!(requested[SHARED_BANK] & 0xFF_FFFF, 1 << 23);
+        assert_eq!(requested[USER_BANK] & 0xFF_FFFF, 1 << 0);
+
+        let (mut efuse, mut jm, mut jp) = fetched(0, BurnConfig::default());
+        efuse.set_user_masked(0x0000_0080, 0x0000_0080).unwrap();
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        let requested: [u32; FUSE_BANKS] = efuse.last_report().unwrap().requested;
+        assert_eq!(requested[SHARED_BANK] & 0xFF_FFFF, 1 << 23);
+        assert_eq!(requested[USER_BANK], 0);
+
+        // a clear is caught on either side of the split
+        let (mut efuse, _, _) = fetched(0x0000_0180, BurnConfig::default());
+        assert_eq!(efuse.set_user_masked(0x0000_0100, 0x0000_0180), Err(EfuseError::ClearsBlownUserBits { bits: 0x080 }));
+        assert_eq!(efuse.set_user_masked(0x0000_0080, 0x0000_0180), Err(EfuseError::ClearsBlownUserBits { bits: 0x100 }));
+    }
+
+    #[test]
+    fn with_the_event_counter() {
+        let counter = CounterSpec { field: (1 << 12) | (1 << 22), refuse_when_exhausted: false };
+        let (mut efuse, mut jm, mut jp) = fetched(0, BurnConfig { event_counter: Some(counter), ..BurnConfig::default() });
+        efuse.set_user_masked(0x0000_00AB, 0x0000_00FF).unwrap();
+        // staging the counter's bits is allowed, but the counter decides what they become
+        efuse.set_user_masked(1 << 22, 1 << 22).unwrap();
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.phy_user(), 0x0000_10AB);
+        assert_eq!(efuse.provisioning_events(), Some(1));
+    }
+
+    #[test]
+    fn with_the_key_checksum() {
+        let (mut efuse, mut jm, mut jp) = fetched(0, BurnConfig { key_checksum_field: Some(0xFF << 8), ..BurnConfig::default() });
+        efuse.set_key(test_key(0x5E));
+        efuse.set_user_masked(0x0000_00AB, 0x0000_00FF).unwrap();
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.phy_user(), 0xAB | (key_checksum(&test_key(0x5E)) as u32) << 8);
+
+        // the recorded checksum is blown like any other bits
+        assert_eq!(efuse.set_user_masked(0, 0xFF << 8), Err(EfuseError::ClearsBlownUserBits { bits: (key_checksum(&test_key(0x5E)) as u32) << 8 }));
+    }
+}
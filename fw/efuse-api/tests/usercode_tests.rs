@@ -0,0 +1,96 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::sequences::*;
+    use efuse_api::test_utils::*;
+
+    const USER: u32 = 0x5EED_0A17;
+
+    /// Passes everything to `inner`, keeping the TDO of each cycle for a ReplayPhy
+    struct Tap<P: JtagPhy> {
+        inner: P,
+        tdo: Vec<bool>,
+    }
+
+    impl<P: JtagPhy> JtagPhy for Tap<P> {
+        fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+            let tdo: bool = self.inner.sync(tdi, tms);
+            self.tdo.push(tdo);
+            tdo
+        }
+
+        fn nosync(&mut self, tdi: bool, tms: bool, tck: bool) -> bool {
+            self.inner.nosync(tdi, tms, tck)
+        }
+
+        fn pause(&mut self, us: u32) {
+            self.inner.pause(us);
+        }
+    }
+
+    /// the TDO of reading USERCODE from `model`
+    fn capture(model: EfuseModelPhy) -> Vec<bool> {
+        let mut jp = Tap { inner: model, tdo: Vec::new() };
+        EfuseApi::new().usercode(&mut JtagMach::new(), &mut jp).unwrap();
+        jp.tdo
+    }
+
+    /// an EfuseApi fetched from a device with USER fused
+    fn fetched() -> EfuseApi {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut JtagMach::new(), &mut EfuseModelPhy::with_banks(banks_image_ecc(&[0; 32], USER, 0))).unwrap();
+        efuse
+    }
+
+    #[test]
+    fn usercode_reads_user() {
+        let mut jp = EfuseModelPhy::with_banks(banks_image_ecc(&[0; 32], USER, 0));
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        assert_eq!(efuse.usercode(&mut jm, &mut jp), Ok(USER));
+        assert_eq!(jp.ir_history(), [Ir::Usercode.code()]);
+        assert_eq!(jp.tap(), TapState::RunTestIdle);
+    }
+
+    #[test]
+    fn all_agree() {
+        let tdo: Vec<bool> = capture(EfuseModelPhy::with_banks(banks_image_ecc(&[0; 32], USER, 0)));
+        let mut jp = ReplayPhy::new(&tdo, Exhausted::Fail);
+        let mut efuse: EfuseApi = fetched();
+        assert_eq!(efuse.cross_check_user(&mut JtagMach::new(), &mut jp), Ok(USER));
+        assert!(jp.is_exhausted());
+    }
+
+    #[test]
+    fn usercode_disagrees() {
+        let mut model = EfuseModelPhy::with_banks(banks_image_ecc(&[0; 32], USER, 0));
+        model.set_usercode(USER ^ 0x0000_0100);
+        let tdo: Vec<bool> = capture(model);
+        let mut jp = ReplayPhy::new(&tdo, Exhausted::Fail);
+        let mut efuse: EfuseApi = fetched();
+        let err: EfuseError = efuse.cross_check_user(&mut JtagMach::new(), &mut jp).unwrap_err();
+        assert_eq!(err, EfuseError::Consistency { err: ConsistencyError::FuseUser { usercode: USER ^ 0x100, fuse_user: USER } });
+        assert_eq!(err.to_string(), "USERCODE reads 0x5eed0b17 but FUSE_USER 0x5eed0a17");
+    }
+
+    #[test]
+    fn banks_disagree() {
+        let tdo: Vec<bool> = capture(EfuseModelPhy::with_banks(banks_image_ecc(&[0; 32], USER, 0)));
+        let mut jp = ReplayPhy::new(&tdo, Exhausted::Fail);
+        let mut efuse: EfuseApi = fetched();
+        // a USER bank that decodes wrong, with FUSE_USER as it was
+        efuse.bank_patch(USER_BANK, bank_image_ecc(USER_BANK, &[0; 32], USER | 0x8000_0000, 0));
+        assert_eq!(efuse.cross_check_user(&mut JtagMach::new(), &mut jp),
+            Err(EfuseError::Consistency { err: ConsistencyError::Banks { usercode: USER, derived: USER | 0x8000_0000 } }));
+    }
+
+    #[test]
+    fn not_without_a_fetch() {
+        let mut jp = EfuseModelPhy::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        assert_eq!(efuse.cross_check_user(&mut JtagMach::new(), &mut jp), Err(EfuseError::NotFetched));
+        assert_eq!(jp.cycles(), 0);
+    }
+}
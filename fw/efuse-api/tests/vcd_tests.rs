@@ -0,0 +1,217 @@
+#![cfg(feature = "vcd")]
+
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::test_utils::*;
+    use efuse_api::vcd::*;
+    use std::collections::HashMap;
+
+    /// A trace read back from the VCD
+    struct Trace {
+        timescale: String,
+        /// pin name by identifier
+        vars: HashMap<String, String>,
+        /// (time, pin name, value), in file order
+        changes: Vec<(u64, String, bool)>,
+    }
+
+    impl Trace {
+        /// the pins at every rising TCK edge: (tms, tdi, tdo)
+        fn edges(&self) -> Vec<(bool, bool, bool)> {
+            let mut pins: HashMap<&str, bool> = HashMap::new();
+            let mut edges = Vec::new();
+            let mut rose: bool = false;
+            for (n, (time, name, value)) in self.changes.iter().enumerate() {
+                rose |= name == "tck" && *value;
+                pins.insert(name, *value);
+                // the edge sees every pin written at its time
+                if rose && self.changes.get(n + 1).is_none_or(|next| next.0 != *time) {
+                    edges.push((pins["tms"], pins["tdi"], pins["tdo"]));
+                    rose = false;
+                }
+            }
+            edges
+        }
+    }
+
+    /// parses `vcd`, checking that it is well-formed as it goes
+    fn parse(vcd: &str) -> Trace {
+        let mut lines = vcd.lines();
+        let mut timescale = String::new();
+        let mut vars: HashMap<String, String> = HashMap::new();
+        let mut scopes: i32 = 0;
+        // the declarations, one per line
+        loop {
+            let line: &str = lines.next().expect("no $enddefinitions");
+            let words: Vec<&str> = line.split_whitespace().collect();
+            assert_eq!(words.last(), Some(&"$end"), "{}", line);
+            match words[0] {
+                "$version" => {},
+                "$timescale" => timescale = words[1..words.len() - 1].join(" "),
+                "$scope" => scopes += 1,
+                "$upscope" => scopes -= 1,
+                "$var" => {
+                    assert_eq!(words[1..3], ["wire", "1"]);
+                    assert!(vars.insert(words[3].to_string(), words[4].to_string()).is_none(), "{} declared twice", words[3]);
+                },
+                "$enddefinitions" => break,
+                other => panic!("unexpected {}", other),
+            }
+        }
+        assert_eq!(scopes, 0);
+
+        let mut changes = Vec::new();
+        let mut time: Option<u64> = None;
+        let mut dumpvars: bool = false;
+        for line in lines {
+            match line {
+                "$dumpvars" => dumpvars = true,
+                "$end" => {
+                    assert!(dumpvars);
+                    dumpvars = false;
+                },
+                _ if line.starts_with('#') => {
+                    let t: u64 = line[1..].parse().unwrap();
+                    assert!(time.is_none_or(|last| t > last), "time runs back at {}", line);
+                    time = Some(t);
+                },
+                _ => {
+                    let value: bool = match &line[..1] {
+                        "0" => false,
+                        "1" => true,
+                        other => panic!("bad value {}", other),
+                    };
+                    let name: &String = vars.get(&line[1..]).unwrap_or_else(|| panic!("undeclared {}", line));
+                    changes.push((time.expect("a change before any time"), name.clone(), value));
+                },
+            }
+        }
+        assert!(!dumpvars);
+        Trace { timescale, vars, changes }
+    }
+
+    #[test]
+    fn a_short_trace() {
+        let mut jp = VcdPhy::new(ScriptedPhy::new(), String::new(), 50);
+        // TEST_LOGIC_RESET to SELECT_DR_SCAN and back
+        jp.sync(true, false);
+        jp.sync(false, true);
+        jp.pause(2);
+        jp.sync(false, true);
+        let (_, vcd) = jp.finish().unwrap();
+        assert_eq!(vcd, "\
+$version efuse-api JTAG trace $end
+$timescale 1 ns $end
+$scope module jtag $end
+$var wire 1 ! tck $end
+$var wire 1 \" tms $end
+$var wire 1 # tdi $end
+$var wire 1 $ tdo $end
+$upscope $end
+$enddefinitions $end
+#0
+$dumpvars
+0!
+0\"
+0#
+0$
+$end
+1#
+#50
+1!
+#100
+0!
+1\"
+0#
+#150
+1!
+#200
+0!
+#2250
+1!
+#2300
+0!
+");
+        let trace = parse(&vcd);
+        assert_eq!(trace.timescale, "1 ns");
+        assert_eq!(trace.edges(), vec![(false, true, false), (true, false, false), (true, false, false)]);
+    }
+
+    #[test]
+    fn a_fetch_traces_every_cycle() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = VcdPhy::new(ScriptedPhy::new(), String::new(), 125);
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        let end: u64 = jp.time_ns();
+        let (recorded, vcd) = jp.finish().unwrap();
+
+        let trace = parse(&vcd);
+        let mut names: Vec<&String> = trace.vars.values().collect();
+        names.sort();
+        assert_eq!(names, ["tck", "tdi", "tdo", "tms"]);
+        // TCK starts low, toggles at every change after that, and ends low
+        let tck: Vec<bool> = trace.changes.iter().filter(|c| c.1 == "tck").map(|c| c.2).collect();
+        assert!(tck.iter().enumerate().all(|(n, &high)| high == (n % 2 == 1)));
+        assert_eq!(tck.len(), 2 * recorded.cycles() + 1);
+        assert_eq!(end, 250 * recorded.cycles() as u64 + 1000 * recorded.elapsed_us());
+
+        // replaying the edges walks the TAP as the JtagMach did, and shifts in the instructions it did
+        let mut tap: TapState = TapState::TestLogicReset;
+        let mut ir: Vec<bool> = Vec::new();
+        let mut irs: Vec<u32> = Vec::new();
+        for (tms, tdi, _) in trace.edges() {
+            if tap == TapState::ShiftIr {
+                ir.push(tdi);
+            }
+            tap = tap.next(tms);
+            if tap == TapState::UpdateIr {
+                irs.push(ir.drain(..).enumerate().map(|(i, b)| (b as u32) << i).sum());
+            }
+        }
+        assert_eq!(tap, recorded.tap());
+        assert_eq!(irs, recorded.ir_history());
+    }
+
+    #[test]
+    fn tdo_is_what_the_device_shifted_out() {
+        let mut model = EfuseModelPhy::new();
+        model.set_idcode(0x1362_F093);
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = VcdPhy::new(model, String::new(), 10);
+        let mut efuse: EfuseApi = EfuseApi::new();
+        assert_eq!(efuse.read_idcode(&mut jm, &mut jp), Ok(0x1362_F093));
+        let (_, vcd) = jp.finish().unwrap();
+
+        let mut tap: TapState = TapState::TestLogicReset;
+        let mut dr: Vec<bool> = Vec::new();
+        for (tms, _, tdo) in parse(&vcd).edges() {
+            if tap == TapState::ShiftDr {
+                dr.push(tdo);
+            }
+            tap = tap.next(tms);
+        }
+        assert_eq!(dr.len(), 32);
+        assert_eq!(dr.iter().enumerate().map(|(i, &b)| (b as u32) << i).sum::<u32>(), 0x1362_F093);
+    }
+
+    #[test]
+    fn a_failing_writer_fails_the_fetch() {
+        /// takes `left` bytes, then fails
+        struct Short { left: usize }
+        impl core::fmt::Write for Short {
+            fn write_str(&mut self, s: &str) -> core::fmt::Result {
+                self.left = self.left.checked_sub(s.len()).ok_or(core::fmt::Error)?;
+                Ok(())
+            }
+        }
+
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = VcdPhy::new(ScriptedPhy::new(), Short { left: 2000 }, 50);
+        let mut efuse: EfuseApi = EfuseApi::new();
+        assert!(efuse.fetch(&mut jm, &mut jp).is_err());
+        assert!(jp.finish().is_err());
+    }
+}
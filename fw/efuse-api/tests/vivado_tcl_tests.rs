@@ -0,0 +1,106 @@
+#[cfg(test)]
+mod tests {
+    use efuse_api::*;
+    use efuse_api::keyhex;
+    use efuse_api::layout::*;
+    use efuse_api::messages::*;
+    use efuse_api::vivado::*;
+
+    const KEY: &str = "8D3A61F0C2B95E17A4086FD3E92C5B71F63E0A1D84C7295BE0D1733A5CF86902";
+
+    /// an EfuseApi over a device fused with `banks`, staged with KEY, `user` and `cntl`
+    fn staged(banks: [u32; FUSE_BANKS], user: u32, cntl: u8) -> EfuseApi {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.load_snapshot(&FuseSnapshot { banks });
+        efuse.set_key(keyhex::parse(KEY).unwrap());
+        efuse.set_user(user);
+        efuse.set_cntl(cntl).unwrap();
+        efuse
+    }
+
+    fn export(efuse: &EfuseApi, key: TclKey) -> Result<String, TclExportError> {
+        let mut tcl = String::new();
+        efuse.export_vivado_tcl(&mut tcl, &TclOptions { device: "xc7s50", key })?;
+        Ok(tcl)
+    }
+
+    #[test]
+    fn full_provisioning_with_nky_file() {
+        let efuse = staged([0; FUSE_BANKS], 0x1234_ABCD, CNTL_CFG_AES_ONLY | CNTL_W_EN_B_KEY_USER);
+        assert_eq!(export(&efuse, TclKey::NkyFile("keys/unit7.nky")).unwrap(), "\
+# eFUSE programming script generated by efuse-api
+# fuses are one-time programmable: check the device and values before running
+set hw_device [lindex [get_hw_devices xc7s50*] 0]
+current_hw_device $hw_device
+program_hw_devices -key {efuse} -nky {keys/unit7.nky} -user_efuse {1234ABCD} -control_efuse {CFG_AES_ONLY YES AES_EXCLUSIVE NO W_EN_B_KEY_USER YES R_EN_B_KEY NO R_EN_B_USER NO} $hw_device
+refresh_hw_device $hw_device
+");
+    }
+
+    #[test]
+    fn full_provisioning_inline() {
+        let efuse = staged([0; FUSE_BANKS], 0x1234_ABCD, CNTL_CFG_AES_ONLY);
+        let tcl = export(&efuse, TclKey::Inline { nky_file: "unit7.nky" }).unwrap();
+        assert_eq!(tcl, format!("\
+# eFUSE programming script generated by efuse-api
+# fuses are one-time programmable: check the device and values before running
+set hw_device [lindex [get_hw_devices xc7s50*] 0]
+current_hw_device $hw_device
+set nky [open {{unit7.nky}} w]
+puts $nky {{Device xc7s50;}}
+puts $nky {{Key 0 {};}}
+close $nky
+program_hw_devices -key {{efuse}} -nky {{unit7.nky}} -user_efuse {{1234ABCD}} -control_efuse {{CFG_AES_ONLY YES AES_EXCLUSIVE NO W_EN_B_KEY_USER NO R_EN_B_KEY NO R_EN_B_USER NO}} $hw_device
+refresh_hw_device $hw_device
+", KEY));
+
+        // the .nky the script writes reads back as the staged key
+        let nky: String = tcl.lines()
+            .filter_map(|l| l.strip_prefix("puts $nky {"))
+            .map(|l| format!("{}\n", l.trim_end_matches('}')))
+            .collect();
+        assert_eq!(keyhex::key_from_nky(&nky), Ok(efuse.api_key()));
+    }
+
+    #[test]
+    fn already_fused_parts_are_left_out() {
+        let key = keyhex::parse(KEY).unwrap();
+        // key and USER already burned, only CNTL left to do
+        let efuse = staged(banks_image_ecc(&key, 0x1234_ABCD, 0), 0x1234_ABCD, CNTL_R_EN_B_KEY);
+        let tcl = export(&efuse, TclKey::Inline { nky_file: "unit7.nky" }).unwrap();
+        assert!(!tcl.contains(KEY));
+        assert!(tcl.contains("program_hw_devices -control_efuse {CFG_AES_ONLY NO AES_EXCLUSIVE NO W_EN_B_KEY_USER NO R_EN_B_KEY YES R_EN_B_USER NO} $hw_device\n"));
+
+        let efuse = staged(banks_image_ecc(&key, 0x1234_ABCD, 0), 0x1234_ABCD, 0);
+        assert_eq!(export(&efuse, TclKey::NkyFile("unit7.nky")), Err(TclExportError::NothingToProgram));
+    }
+
+    #[test]
+    fn patch_style_stage_is_refused() {
+        // a partly burned key and USER, with the rest of them staged, plus a reserved CNTL bit
+        let image = banks_image_ecc(&keyhex::parse(KEY).unwrap(), 0x1234_ABCD, 0);
+        let mut banks = [0u32; FUSE_BANKS];
+        banks[1] = image[1] & 0xFF;
+        banks[12] = image[12] & 0xFF00;
+        let mut efuse = staged(banks, 0x1234_ABCD, 1 | CNTL_CFG_AES_ONLY);
+        // bank 1 is partly fused, so completing it is a key patch as far as validate() goes
+        efuse.force_key_patch(true);
+        let mut tcl = String::new();
+        let err = efuse.export_vivado_tcl(&mut tcl, &TclOptions { device: "xc7s50", key: TclKey::NkyFile("unit7.nky") });
+        assert_eq!(err, Err(TclExportError::Inexpressible(vec![
+            Inexpressible::KeyPatch,
+            Inexpressible::UserPatch { fused: 0x0034_0000, staged: 0x1234_ABCD },
+            Inexpressible::ReservedCntl { bits: 1 },
+        ])));
+        assert!(tcl.is_empty());
+    }
+
+    #[test]
+    fn invalid_stage_is_refused() {
+        let mut banks = [0u32; FUSE_BANKS];
+        // a USER bit the staged value has clear
+        banks[12] = 0x10;
+        let efuse = staged(banks, 0x1234_ABCD, 0);
+        assert_eq!(export(&efuse, TclKey::NkyFile("unit7.nky")), Err(TclExportError::Invalid(EfuseError::IllegalTransition { bank: 12, mask: 0x10 })));
+    }
+}
@@ -0,0 +1,101 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::keyhex;
+    use efuse_api::layout::*;
+    use efuse_api::test_utils::*;
+    use efuse_api::vivado::*;
+
+    const SAMPLE: &str = include_str!("data/vivado_efuse_report.txt");
+    const SAMPLE_KEY: &str = "8D3A61F0C2B95E17A4086FD3E92C5B71F63E0A1D84C7295BE0D1733A5CF86902";
+
+    fn sample_key() -> [u8; 32] {
+        keyhex::parse(SAMPLE_KEY).unwrap()
+    }
+
+    /// an EfuseApi fetched from a simulated device fused with `key`, `user` and `cntl`
+    fn fetched(key: &[u8; 32], user: u32, cntl: u8) -> EfuseApi {
+        let mut jp = EfuseModelPhy::with_banks(banks_image_ecc(key, user, cntl));
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse
+    }
+
+    #[test]
+    fn parses_sample() {
+        let report = VivadoEfuseReport::parse(SAMPLE).unwrap();
+        assert_eq!(report.key, Reported::Value(sample_key()));
+        assert_eq!(report.user, Reported::Value(0x1234_ABCD));
+        assert_eq!(report.cntl, Reported::Value(0x0002));
+        // .nky order: the first digits are the last key byte
+        assert_eq!(sample_key()[31], 0x8D);
+        assert_eq!(sample_key()[0], 0x02);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let without_user: String = SAMPLE.lines().filter(|l| !l.contains("FUSE_USER")).collect::<Vec<_>>().join("\n");
+        assert_eq!(VivadoEfuseReport::parse(&without_user), Err(VivadoParseError::Missing { register: Register::User }));
+        let twice = format!("{}REGISTER.EFUSE.FUSE_CNTL string true true 0000\n", SAMPLE);
+        assert_eq!(VivadoEfuseReport::parse(&twice), Err(VivadoParseError::Duplicate { register: Register::Cntl, line: 12 }));
+        let short_key = SAMPLE.replace(SAMPLE_KEY, &SAMPLE_KEY[2..]);
+        assert_eq!(VivadoEfuseReport::parse(&short_key), Err(VivadoParseError::BadValue { register: Register::Key, line: 8 }));
+        let bad_cntl = SAMPLE.replace("true     0002", "true     4002");
+        assert_eq!(VivadoEfuseReport::parse(&bad_cntl), Err(VivadoParseError::BadValue { register: Register::Cntl, line: 6 }));
+        let bad_user = SAMPLE.replace("1234ABCD", "1234ABCG");
+        assert_eq!(VivadoEfuseReport::parse(&bad_user), Err(VivadoParseError::BadValue { register: Register::User, line: 9 }));
+    }
+
+    #[test]
+    fn unreadable_and_other_layouts() {
+        // the register dump from the GUI, with key readback disabled
+        let dump = "FUSE_KEY  N/A\nFUSE_USER 0x0000BEEF\n  FUSE_CNTL 0x0012\nFUSE_USER_128 0123\n";
+        let report = VivadoEfuseReport::parse(dump).unwrap();
+        assert_eq!(report.key, Reported::Unreadable);
+        assert_eq!(report.user, Reported::Value(0xBEEF));
+        assert_eq!(report.cntl, Reported::Value(0x12));
+    }
+
+    #[test]
+    fn matches_simulated_fetch() {
+        let report = VivadoEfuseReport::parse(SAMPLE).unwrap();
+        let result = fetched(&sample_key(), 0x1234_ABCD, CNTL_CFG_AES_ONLY).compare_with_vivado(&report);
+        assert_eq!(result, ComparisonResult { key: Agreement::Match, user: Agreement::Match, cntl: Agreement::Match });
+        assert!(result.is_consistent());
+    }
+
+    #[test]
+    fn mismatches_are_reported_per_register() {
+        let report = VivadoEfuseReport::parse(SAMPLE).unwrap();
+        let mut key = sample_key();
+        key[17] ^= 0x40;
+        let result = fetched(&key, 0x1234_ABCD, CNTL_CFG_AES_ONLY).compare_with_vivado(&report);
+        assert_eq!(result, ComparisonResult { key: Agreement::Mismatch, user: Agreement::Match, cntl: Agreement::Match });
+        assert!(!result.is_consistent());
+
+        let result = fetched(&sample_key(), 0x1234_ABCF, 0).compare_with_vivado(&report);
+        assert_eq!(result, ComparisonResult { key: Agreement::Match, user: Agreement::Mismatch, cntl: Agreement::Mismatch });
+    }
+
+    #[test]
+    fn read_disabled_registers_are_unverifiable() {
+        // Vivado couldn't read the key back
+        let unreadable = VivadoEfuseReport::parse(&SAMPLE.replace(SAMPLE_KEY, "N/A")).unwrap();
+        let result = fetched(&sample_key(), 0x1234_ABCD, CNTL_CFG_AES_ONLY).compare_with_vivado(&unreadable);
+        assert_eq!(result.key, Agreement::Unverifiable);
+        assert!(result.is_consistent());
+
+        // the device has user readback disabled, and the two tools disagree on USER
+        let cntl: u8 = CNTL_CFG_AES_ONLY | CNTL_R_EN_B_USER;
+        let report = VivadoEfuseReport::parse(&SAMPLE.replace("true     0002", "true     0022").replace("1234ABCD", "00000000")).unwrap();
+        let result = fetched(&sample_key(), 0x1234_ABCD, cntl).compare_with_vivado(&report);
+        assert_eq!(result, ComparisonResult { key: Agreement::Match, user: Agreement::Unverifiable, cntl: Agreement::Match });
+
+        // but a disagreement on a readable register still counts
+        let report = VivadoEfuseReport::parse(&SAMPLE.replace("1234ABCD", "00000000")).unwrap();
+        let result = fetched(&sample_key(), 0x1234_ABCD, CNTL_CFG_AES_ONLY).compare_with_vivado(&report);
+        assert_eq!(result.user, Agreement::Mismatch);
+    }
+}
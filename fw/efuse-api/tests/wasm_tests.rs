@@ -0,0 +1,76 @@
+#![cfg(feature = "wasm")]
+
+#[cfg(test)]
+mod tests {
+    use efuse_api::keyhex::{self, HexCase};
+    use efuse_api::layout::*;
+    use efuse_api::test_utils::*;
+    use efuse_api::wasm::*;
+    use serde_json::{json, Value};
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::*;
+
+    /// the key's .nky form, most significant byte first
+    fn key_hex() -> String {
+        let mut hex = String::new();
+        keyhex::format(&test_key(0xC3), &mut hex, HexCase::Lower).unwrap();
+        hex
+    }
+
+    fn validate(banks: [u32; FUSE_BANKS], manifest: Value) -> Value {
+        let snapshot = json!({ "banks": banks }).to_string();
+        serde_json::from_str(&validate_manifest(&snapshot, &manifest.to_string())).unwrap()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn valid_plan() {
+        let report = validate([0; FUSE_BANKS], json!({ "key": key_hex(), "user": 0x1234_5678 }));
+        assert_eq!(report["valid"], true);
+        assert_eq!(report["error"], Value::Null);
+        let image = banks_image_ecc(&test_key(0xC3), 0x1234_5678, 0);
+        let plan: Vec<Value> = (0..FUSE_BANKS).rev()
+            .filter(|&b| image[b] != 0)
+            .map(|b| json!({ "bank": b, "bits": image[b].count_ones() }))
+            .collect();
+        assert_eq!(report["plan"], Value::Array(plan));
+        // the key doesn't come back out
+        assert!(!report.to_string().to_lowercase().contains(&key_hex()[..16]));
+
+        // already burned: nothing left to do
+        let report = validate(image, json!({ "key": key_hex(), "user": 0x1234_5678 }));
+        assert_eq!(report["valid"], true);
+        assert_eq!(report["plan"], json!([]));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn refusals() {
+        let mut banks = [0u32; FUSE_BANKS];
+        banks[12] = 0x00FF_FFFF;
+        let report = validate(banks, json!({ "key": key_hex(), "user": 0 }));
+        assert_eq!(report["valid"], false);
+        assert_eq!(report["error"]["kind"], "Invalid");
+
+        let weak = "11".repeat(32);
+        let report = validate([0; FUSE_BANKS], json!({ "key": weak, "user": 0, "cntl": 3 }));
+        assert_eq!(report["error"]["kind"], "WeakKey");
+        let report = validate([0; FUSE_BANKS], json!({ "key": weak, "user": 0, "cntl": 3, "allow_weak_key": true }));
+        assert_eq!(report["valid"], true);
+        assert_eq!(report["weak_key_overridden"], true);
+        assert_eq!(report["warnings"].as_array().unwrap().len(), 1);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn bad_inputs() {
+        let manifest = json!({ "key": key_hex(), "user": 0 }).to_string();
+        let kind = |report: String| serde_json::from_str::<Value>(&report).unwrap()["error"]["kind"].clone();
+        assert_eq!(kind(validate_manifest("{\"banks\": [1, 2]}", &manifest)), "BadSnapshot");
+        assert_eq!(kind(validate_manifest("not json", &manifest)), "BadSnapshot");
+        let blank = [0u32; FUSE_BANKS];
+        let snapshot = json!({ "banks": blank }).to_string();
+        assert_eq!(kind(validate_manifest(&snapshot, "{\"user\": 0}")), "BadManifest");
+        assert_eq!(kind(validate_manifest(&snapshot, "{\"key\": \"12\", \"user\": 0}")), "BadKey");
+    }
+}
@@ -0,0 +1,114 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::sequences::*;
+    use efuse_api::test_utils::*;
+    use efuse_api::xadc::*;
+
+    /// -10 C
+    const COLD: u16 = 2135 << 4;
+
+    const NOMINAL: XadcReadings = XadcReadings { temp: NOMINAL_TEMP, vccint: NOMINAL_VCCINT, vccaux: NOMINAL_VCCAUX };
+
+    /// a fetched EfuseApi with test_key(0x6C) staged, checking against EnvLimits::SEVEN_SERIES
+    fn checked(jm: &mut JtagMach, jp: &mut EfuseModelPhy) -> EfuseApi {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.set_burn_config(BurnConfig { environment: Some(EnvLimits::SEVEN_SERIES), ..BurnConfig::default() });
+        efuse.fetch(jm, jp).unwrap();
+        efuse.set_key(test_key(0x6C));
+        efuse
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(NOMINAL.temp_mc(), 24_977);
+        assert_eq!(NOMINAL.vccint_mv(), 999);
+        assert_eq!(NOMINAL.vccaux_mv(), 1800);
+        assert_eq!(XadcReadings { temp: COLD, ..NOMINAL }.temp_mc(), -10_458);
+        // the low four bits aren't part of the code
+        assert_eq!(XadcReadings { temp: NOMINAL_TEMP | 0xF, ..NOMINAL }.temp_mc(), 24_977);
+        assert_eq!(XadcReadings { vccint: NOMINAL_VCCINT | 0xF, ..NOMINAL }.vccint_mv(), 999);
+        assert_eq!(format!("{}", NOMINAL), "24.9 C, VCCINT 999 mV, VCCAUX 1800 mV");
+        assert_eq!(format!("{}", XadcReadings { temp: COLD, ..NOMINAL }), "-10.4 C, VCCINT 999 mV, VCCAUX 1800 mV");
+    }
+
+    #[test]
+    fn limits() {
+        let limits = EnvLimits::SEVEN_SERIES;
+        assert_eq!(limits.violation(&NOMINAL), None);
+        assert_eq!(limits.violation(&XadcReadings { temp: COLD, ..NOMINAL }), Some(EnvViolation::Temperature));
+        assert_eq!(limits.violation(&XadcReadings { vccint: 1200 << 4, ..NOMINAL }), Some(EnvViolation::Vccint));
+        assert_eq!(limits.violation(&XadcReadings { vccaux: 2600 << 4, ..NOMINAL }), Some(EnvViolation::Vccaux));
+        // the first one out, in order
+        assert_eq!(limits.violation(&XadcReadings { temp: COLD, vccint: 0, vccaux: 0 }), Some(EnvViolation::Temperature));
+    }
+
+    #[test]
+    fn reads_one_scan_late() {
+        let mut jp = ScriptedPhy::new();
+        jp.on_dr_seq(Ir::XadcDrp, 32, &[0, NOMINAL_TEMP as u128, NOMINAL_VCCINT as u128, NOMINAL_VCCAUX as u128]);
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        let check: PreBurnCheck = efuse.check_environment(&EnvLimits::SEVEN_SERIES, &mut jm, &mut jp).unwrap();
+        assert_eq!(check, PreBurnCheck { readings: NOMINAL, violation: None, overridden: None });
+
+        assert_eq!(Ir::XadcDrp.code(), 0b110111);
+        let words: Vec<u128> = jp.dr_writes(Ir::XadcDrp).map(|scan| scan.value()).collect();
+        assert_eq!(words, [0x0400_0000, 0x0401_0000, 0x0402_0000, 0]);
+        assert!(jp.dr_writes(Ir::XadcDrp).all(|scan| scan.len() == 32));
+    }
+
+    #[test]
+    fn a_cold_die_is_refused() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        jp.set_xadc(REG_TEMP, COLD);
+        let mut efuse = checked(&mut jm, &mut jp);
+        let readings = XadcReadings { temp: COLD, ..NOMINAL };
+        assert_eq!(efuse.burn(&mut jm, &mut jp), Err(EfuseError::OutOfSpec { violation: EnvViolation::Temperature, readings }));
+        assert!(jp.programmed().is_empty());
+        assert_eq!(efuse.last_report(), None);
+        assert_eq!(format!("{}", EfuseError::OutOfSpec { violation: EnvViolation::Temperature, readings }),
+            "Temperature out of limits for burning: -10.4 C, VCCINT 999 mV, VCCAUX 1800 mV");
+
+        // once warm, it goes ahead
+        jp.set_xadc(REG_TEMP, NOMINAL_TEMP);
+        assert_eq!(efuse.burn(&mut jm, &mut jp), Ok(()));
+        let check: PreBurnCheck = efuse.last_report().unwrap().pre_burn.unwrap();
+        assert_eq!(check.readings, NOMINAL);
+        assert!(check.passed());
+    }
+
+    #[test]
+    fn an_override_is_recorded_and_used_up() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        jp.set_xadc(REG_VCCAUX, 1600 << 4);
+        let mut efuse = checked(&mut jm, &mut jp);
+        efuse.override_environment_check("bench supply, VCCAUX measured at the pin");
+        assert_eq!(efuse.burn(&mut jm, &mut jp), Ok(()));
+        let check: PreBurnCheck = efuse.last_report().unwrap().pre_burn.unwrap();
+        assert_eq!(check.violation, Some(EnvViolation::Vccaux));
+        assert_eq!(check.overridden, Some("bench supply, VCCAUX measured at the pin"));
+        assert!(!jp.programmed().is_empty());
+
+        // the next burn is checked again
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_user(0x5A00);
+        assert!(matches!(efuse.burn(&mut jm, &mut jp), Err(EfuseError::OutOfSpec { violation: EnvViolation::Vccaux, .. })));
+    }
+
+    #[test]
+    fn unchecked_unless_configured() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = EfuseModelPhy::new();
+        jp.set_xadc(REG_TEMP, COLD);
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key(test_key(0x6C));
+        assert_eq!(efuse.burn(&mut jm, &mut jp), Ok(()));
+        assert_eq!(efuse.last_report().unwrap().pre_burn, None);
+        assert!(!jp.ir_history().contains(&Ir::XadcDrp.code()));
+    }
+}
@@ -0,0 +1,228 @@
+#![cfg(feature = "svf")]
+
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::layout::*;
+    use efuse_api::messages::*;
+    use efuse_api::sequences::*;
+    use efuse_api::test_utils::*;
+    use efuse_api::xsvf::*;
+
+    const USER: u32 = 0x00AB_C0DE;
+
+    const TCK_HZ: u32 = 1_000_000;
+
+    /// A record read back from the XSVF
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    enum Record {
+        Complete,
+        TdoMask(Vec<bool>),
+        Sir(Vec<bool>),
+        RunTest(u32),
+        Repeat(u8),
+        SdrSize(u32),
+        SdrTdo { tdi: Vec<bool>, tdo: Vec<bool> },
+        State(u8),
+        EndIr(u8),
+        EndDr(u8),
+        Wait { state: u8, end: u8, us: u32 },
+    }
+
+    /// the first `len` bits of XSVF-packed `bytes`, first shifted first
+    fn unpack(len: usize, bytes: &[u8]) -> Vec<bool> {
+        assert_eq!(bytes.len(), len.div_ceil(8));
+        (0..len).map(|n| (bytes[bytes.len() - 1 - n / 8] >> (n % 8)) & 1 == 1).collect()
+    }
+
+    fn decode(xsvf: &[u8]) -> Vec<Record> {
+        let mut records = Vec::new();
+        let mut at: usize = 0;
+        let mut sdr_bits: usize = 0;
+        let take = |n: usize, at: &mut usize| { let bytes = &xsvf[*at..*at + n]; *at += n; bytes.to_vec() };
+        let be32 = |b: &[u8]| u32::from_be_bytes([b[0], b[1], b[2], b[3]]);
+        while at < xsvf.len() {
+            let op: u8 = take(1, &mut at)[0];
+            let record = match op {
+                XCOMPLETE => Record::Complete,
+                XTDOMASK => Record::TdoMask(unpack(sdr_bits, &take(sdr_bits.div_ceil(8), &mut at))),
+                XSIR => {
+                    let len: usize = take(1, &mut at)[0] as usize;
+                    Record::Sir(unpack(len, &take(len.div_ceil(8), &mut at)))
+                },
+                XSIR2 => {
+                    let b = take(2, &mut at);
+                    let len: usize = u16::from_be_bytes([b[0], b[1]]) as usize;
+                    Record::Sir(unpack(len, &take(len.div_ceil(8), &mut at)))
+                },
+                XRUNTEST => Record::RunTest(be32(&take(4, &mut at))),
+                XREPEAT => Record::Repeat(take(1, &mut at)[0]),
+                XSDRSIZE => {
+                    let size: u32 = be32(&take(4, &mut at));
+                    sdr_bits = size as usize;
+                    Record::SdrSize(size)
+                },
+                XSDRTDO => {
+                    let tdi = unpack(sdr_bits, &take(sdr_bits.div_ceil(8), &mut at));
+                    let tdo = unpack(sdr_bits, &take(sdr_bits.div_ceil(8), &mut at));
+                    Record::SdrTdo { tdi, tdo }
+                },
+                XSTATE => Record::State(take(1, &mut at)[0]),
+                XENDIR => Record::EndIr(take(1, &mut at)[0]),
+                XENDDR => Record::EndDr(take(1, &mut at)[0]),
+                XWAIT => {
+                    let b = take(6, &mut at);
+                    Record::Wait { state: b[0], end: b[1], us: be32(&b[2..]) }
+                },
+                _ => panic!("unknown record {:#04x} at {}", op, at - 1),
+            };
+            records.push(record);
+        }
+        records
+    }
+
+    fn value(bits: &[bool]) -> u128 {
+        bits.iter().enumerate().take(128).map(|(i, &b)| (b as u128) << i).sum()
+    }
+
+    /// a fetched-as-blank EfuseApi with test_key(0x2E) and USER staged, and `timing`
+    fn staged(timing: BurnTiming) -> EfuseApi {
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.load_snapshot(&FuseSnapshot { banks: [0; FUSE_BANKS] });
+        efuse.set_key(test_key(0x2E));
+        efuse.set_user(USER);
+        efuse.set_burn_config(BurnConfig { timing, ..BurnConfig::default() });
+        efuse
+    }
+
+    fn burn_to_xsvf(efuse: &mut EfuseApi, tck_hz: u32) -> (ScriptedPhy, Vec<Record>) {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = XsvfWriter::new(ScriptedPhy::new(), tck_hz);
+        efuse.burn(&mut jm, &mut jp).unwrap();
+        let (recorded, xsvf) = jp.finish();
+        (recorded, decode(&xsvf))
+    }
+
+    #[test]
+    fn packing() {
+        let bits: Vec<bool> = (0..17).map(|n| (0x1_2345u32 >> n) & 1 == 1).collect();
+        assert_eq!(pack_bits(&bits), [0x01, 0x23, 0x45]);
+        assert_eq!(pack_bits(&[true; 8]), [0xFF]);
+        assert_eq!(pack_bits(&[]), Vec::<u8>::new());
+        // past 64 bits, as the unlock words and key reads are
+        let wide: Vec<bool> = (0..75).map(|n| n % 3 == 0).collect();
+        let packed = pack_bits(&wide);
+        assert_eq!(packed.len(), 10);
+        assert_eq!(unpack(75, &packed), wide);
+        assert_eq!(xsvf_state(TapState::TestLogicReset), 0x00);
+        assert_eq!(xsvf_state(TapState::RunTestIdle), 0x01);
+        assert_eq!(xsvf_state(TapState::PauseDr), 0x06);
+        assert_eq!(xsvf_state(TapState::UpdateIr), 0x0F);
+    }
+
+    #[test]
+    fn burn_round_trips() {
+        let mut efuse = staged(BurnTiming::default());
+        let words: Vec<u64> = efuse.program_words().map(|w| w.value).collect();
+        let (recorded, records) = burn_to_xsvf(&mut efuse, TCK_HZ);
+        assert_eq!(records[..6], [Record::Repeat(0), Record::EndIr(0), Record::EndDr(0), Record::RunTest(0), Record::State(0), Record::Wait { state: 0, end: 0, us: 2000 }]);
+        assert_eq!(records.last(), Some(&Record::Complete));
+
+        // every DR scan, bit for bit, with the length set before it and nothing compared
+        let mut size: u32 = 0;
+        let mut sdrs: Vec<Vec<bool>> = Vec::new();
+        for record in records.iter() {
+            match record {
+                Record::SdrSize(bits) => size = *bits,
+                Record::TdoMask(mask) => {
+                    assert_eq!(mask.len(), size as usize);
+                    assert!(mask.iter().all(|&b| !b));
+                },
+                Record::SdrTdo { tdi, tdo } => {
+                    assert_eq!(tdi.len(), size as usize);
+                    assert_eq!(tdo.len(), size as usize);
+                    sdrs.push(tdi.clone());
+                },
+                _ => {},
+            }
+        }
+        let writes: Vec<Vec<bool>> = recorded.dr_writes_all().iter().map(|w| w.bits.clone()).collect();
+        assert_eq!(sdrs, writes);
+        // the 64-bit programming words, unlocks included, and the 75-bit commit word
+        let fuse_cts: Vec<u64> = recorded.dr_writes(Ir::FuseCts).map(|w| w.value() as u64).collect();
+        assert_eq!(fuse_cts[..words.len()], words[..]);
+        assert!(words.contains(&DeviceParams::SEVEN_SERIES.unlock));
+        assert!(sdrs.iter().any(|bits| bits.len() == 75 && value(bits) == 0xA9));
+
+        // and every instruction
+        let sirs: Vec<u32> = records.iter().filter_map(|r| match r {
+            Record::Sir(bits) => {
+                assert_eq!(bits.len(), IR_BITS);
+                Some(value(bits) as u32)
+            },
+            _ => None,
+        }).collect();
+        assert_eq!(sirs, recorded.ir_history());
+
+        // the waits add up to the pauses, and to the dwell after ISC_ENABLE
+        let waited: u64 = records.iter().map(|r| match r { Record::Wait { us, .. } => *us as u64, _ => 0 }).sum();
+        let dwell: u64 = (efuse.profile().isc.unwrap().enable_idle as u64 * 1_000_000).div_ceil(TCK_HZ as u64);
+        assert_eq!(waited, recorded.elapsed_us() + dwell);
+    }
+
+    #[test]
+    fn key_reads_carry_their_tdo() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = XsvfWriter::new(EfuseModelPhy::with_banks(banks_image_ecc(&test_key(0x2E), USER, 0)), TCK_HZ);
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        assert_eq!(efuse.phy_key(), test_key(0x2E));
+        let (_, xsvf) = jp.finish();
+        let reads: Vec<Vec<bool>> = decode(&xsvf).into_iter()
+            .filter_map(|r| match r { Record::SdrTdo { tdo, .. } if tdo.len() == 256 => Some(tdo), _ => None })
+            .collect();
+        assert!(!reads.is_empty());
+        let read: Vec<u8> = reads[0].chunks(8).map(|byte| value(byte) as u8).collect();
+        assert_eq!(read, test_key(0x2E));
+    }
+
+    #[test]
+    fn idle_cycles_become_waits() {
+        let timing = BurnTiming { bit_idle_cycles: 25, ..BurnTiming::default() };
+        let bits: usize = staged(timing).plan().unwrap().pulses as usize;
+        let idle = |records: &[Record]| records.iter()
+            .filter(|r| matches!(r, Record::Wait { state: 1, end: 1, .. }))
+            .map(|r| match r { Record::Wait { us, .. } => *us, _ => 0 })
+            .filter(|&us| us != 200 && us != 2000 && us != 2500)
+            .collect::<Vec<u32>>();
+        // the first is the dwell after ISC_ENABLE
+        let dwell: u32 = staged(timing).profile().isc.unwrap().enable_idle;
+        let (_, records) = burn_to_xsvf(&mut staged(timing), TCK_HZ);
+        let mut idles: Vec<u32> = idle(&records);
+        assert_eq!(idles.remove(0), dwell);
+        assert_eq!(idles, vec![25; bits]);
+        // at 3MHz, 25 cycles take 8.33us, rounded up
+        let (_, records) = burn_to_xsvf(&mut staged(timing), 3_000_000);
+        let mut idles: Vec<u32> = idle(&records);
+        assert_eq!(idles.remove(0), dwell.div_ceil(3));
+        assert_eq!(idles, vec![9; bits]);
+    }
+
+    #[test]
+    fn a_short_read() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = XsvfWriter::new(ScriptedPhy::new(), TCK_HZ);
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.read_idcode(&mut jm, &mut jp).unwrap();
+        let (_, xsvf) = jp.finish();
+        assert_eq!(xsvf, [
+            XREPEAT, 0, XENDIR, 0, XENDDR, 0, XRUNTEST, 0, 0, 0, 0, XSTATE, 0,
+            XSTATE, 1,
+            XSIR, 6, 0x09,
+            XSDRSIZE, 0, 0, 0, 32, XTDOMASK, 0, 0, 0, 0,
+            XSDRTDO, 0, 0, 0, 0, 0, 0, 0, 0,
+            XCOMPLETE,
+        ]);
+    }
+}
@@ -0,0 +1,30 @@
+#![cfg(feature = "zeroize")]
+
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use efuse_api::*;
+    use efuse_api::test_utils::*;
+    use zeroize::{Zeroize, ZeroizeOnDrop};
+
+    fn wiped_on_drop<T: Zeroize + ZeroizeOnDrop>(_: &T) {}
+
+    #[test]
+    fn zeroize_clears_secrets() {
+        let mut jp = EfuseModelPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        let mut efuse: EfuseApi = EfuseApi::new();
+        efuse.fetch(&mut jm, &mut jp).unwrap();
+        efuse.set_key([0x3C; 32]);
+        wiped_on_drop(&efuse);
+        efuse.zeroize();
+        assert_eq!(efuse.api_key(), [0; 32]);
+        assert_eq!(efuse.burn(&mut jm, &mut jp), Err(EfuseError::NotFetched));
+
+        let mut phy = EfusePhy::new();
+        phy.bank_patch(1, 0x00AB_CDEF);
+        wiped_on_drop(&phy);
+        phy.zeroize();
+        assert_eq!(phy.key(), [0; 32]);
+    }
+}
@@ -0,0 +1,35 @@
+//! Feeds decode_ecc() arbitrary words: run with `cargo fuzz run decode_ecc` from fw/efuse-ecc
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use efuse_ecc::efuse_ecc::*;
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 4 {
+        return;
+    }
+    let raw: u32 = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+    let word: u32 = raw & 0x3FFF_FFFF;
+
+    // a record with its own code decodes as it is
+    let record: u32 = raw & 0xFF_FFFF;
+    assert_eq!(decode_ecc(add_ecc(record)), Ok(Corrected { data: record, flipped: None }));
+
+    // anything else decodes to a valid word at most one flip away, or is refused
+    match decode_ecc(raw) {
+        Ok(Corrected { data, flipped: None }) => {
+            assert!(verify_ecc(word));
+            assert_eq!(add_ecc(data), word);
+        }
+        Ok(Corrected { data, flipped: Some(bit) }) => {
+            assert!(bit < WORD_BITS);
+            assert!(!verify_ecc(word));
+            assert_eq!(add_ecc(data), word ^ (1 << bit));
+        }
+        Err(EccError::Uncorrectable) => {
+            assert_ne!((0..WORD_BITS).filter(|&bit| verify_ecc(word ^ (1 << bit))).count(), 1);
+            assert!(!verify_ecc(word));
+        }
+    }
+});
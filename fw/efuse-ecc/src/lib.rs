@@ -5,16 +5,19 @@ pub mod efuse_ecc {
     /// a number which is the data + its 6-bit ECC code
     pub fn add_ecc(data: u32) -> u32 {
         assert!(data & 0xFF00_0000 == 0); // if the top 8 bits are filled in, that's an error
+        data | (ecc_code(data) as u32) << 24
+    }
+
+    /// the 6-bit ECC code of a 24-bit data record
+    const fn ecc_code(data: u32) -> u8 {
         const GENERATOR: [u32; 6] = [16_515_312, 14_911_249, 10_180_898, 5_696_068, 3_011_720, 16_777_215];
 
         let mut code: u32 = 0;
 
-        for (i, gen) in GENERATOR.iter().enumerate() {
-            let mut parity: u32 = 0;
-            for bit in 0..24 {
-                parity ^= ((gen & data) >> bit) & 0x1;
-            }
-            code ^= parity << i;
+        let mut i: usize = 0;
+        while i < GENERATOR.len() {
+            code ^= ((GENERATOR[i] & data).count_ones() & 0x1) << i;
+            i += 1;
         }
 
         if (code & 0x20) != 0 {
@@ -23,7 +26,111 @@ pub mod efuse_ecc {
 
         let secded = ((((code >> 5) ^ (code >> 4) ^ (code >> 3) ^ (code >> 2) ^ (code >> 1) ^ code) & 0x1) << 5) | code;
 
-        data | secded << 24
+        secded as u8
+    }
+
+    /// split a fused word into its 24-bit data record and 6-bit ECC code
+    pub fn split_ecc(word: u32) -> (u32, u8) {
+        (word & 0xFF_FFFF, ((word >> 24) & 0x3F) as u8)
+    }
+
+    /// true if the ECC code stored in `word` is the one its data record calls for
+    pub fn verify_ecc(word: u32) -> bool {
+        let (data, _) = split_ecc(word);
+        add_ecc(data) == word & 0x3FFF_FFFF
+    }
+
+    /// position in the word of ECC code bit 0; the code takes bits 24-29
+    pub const ECC_SHIFT: u32 = 24;
+
+    /// positions in the word of the ECC code bits, the five Hamming check bits first and the
+    /// overall parity bit last
+    pub const ECC_POSITIONS: [u32; 6] = [24, 25, 26, 27, 28, 29];
+
+    /// position in the word of the overall parity bit
+    pub const PARITY_BIT: u32 = 29;
+
+    /// How the ECC code stored in a word compares to the one its data record calls for
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct Syndrome {
+        /// the code stored in the word
+        pub stored: u8,
+        /// the code the data record calls for
+        pub computed: u8,
+        /// the Hamming check bits that disagree, `stored ^ computed` without the parity bit;
+        /// bit n stands for word bit ECC_SHIFT + n
+        pub syndrome: u8,
+        /// true if the overall parity bit disagrees
+        pub parity: bool,
+    }
+
+    impl Syndrome {
+        /// true if the stored code is the one the data calls for
+        pub const fn is_clean(&self) -> bool {
+            self.syndrome == 0 && !self.parity
+        }
+
+        /// the word bits of the ECC code that disagree, parity bit included
+        pub const fn mismatched(&self) -> u32 {
+            ((self.stored ^ self.computed) as u32) << ECC_SHIFT
+        }
+    }
+
+    /// Compares the ECC code stored in `raw`, a bank word as read, to the one its data record
+    /// calls for. The bits above the word are ignored. This only says which check bits
+    /// disagree; decode_ecc() is the one that works out which bit to correct, if any.
+    pub const fn syndrome(raw: u32) -> Syndrome {
+        let stored: u8 = ((raw >> ECC_SHIFT) & 0x3F) as u8;
+        let computed: u8 = ecc_code(raw & 0xFF_FFFF);
+        let diff: u8 = stored ^ computed;
+        Syndrome { stored, computed, syndrome: diff & 0x1F, parity: diff & 0x20 != 0 }
+    }
+
+    /// number of fuses in a bank word: 24 data bits and the 6-bit ECC code
+    pub const WORD_BITS: u32 = 30;
+
+    /// A data record recovered from a bank word
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct Corrected {
+        /// the 24-bit data record
+        pub data: u32,
+        /// the position in the word, 0-29, of the one bit that was corrected, if any; 24 and up
+        /// are ECC bits
+        pub flipped: Option<u32>,
+    }
+
+    impl Corrected {
+        /// true if a bit had to be corrected
+        pub fn corrected(&self) -> bool {
+            self.flipped.is_some()
+        }
+    }
+
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub enum EccError {
+        /// the word isn't within one flip of a valid one, so more than one bit is wrong
+        Uncorrectable,
+    }
+
+    /// Recovers the data record from `raw`, a bank word as read, correcting a single wrong
+    /// bit. The bits above the word are ignored.
+    ///
+    /// Every word one flip away from a valid one is one flip away from only that one, so the
+    /// bit to correct is found by trying each in turn. The code doesn't detect every double
+    /// error though: the inverted parity step lets a good part of the words two flips from a
+    /// valid one sit one flip from another, and those decode as a single correction of the
+    /// wrong bit. Nothing in the word can tell the two apart, so a corrected record is only as
+    /// good as the assumption that at most one bit went wrong.
+    pub fn decode_ecc(raw: u32) -> Result<Corrected, EccError> {
+        let word: u32 = raw & 0x3FFF_FFFF;
+        if verify_ecc(word) {
+            return Ok(Corrected { data: split_ecc(word).0, flipped: None });
+        }
+        let mut fixes = (0..WORD_BITS).filter(|&bit| verify_ecc(word ^ (1 << bit)));
+        match (fixes.next(), fixes.next()) {
+            (Some(bit), None) => Ok(Corrected { data: split_ecc(word ^ (1 << bit)).0, flipped: Some(bit) }),
+            _ => Err(EccError::Uncorrectable),
+        }
     }
 }
 
@@ -54,6 +161,52 @@ mod tests {
         }
     }
 
+    #[test]
+    fn split_and_verify() {
+        assert_eq!(split_ecc(0x2708_63C1), (0x8_63C1, 0x27));
+        assert_eq!(split_ecc(0xFFFF_FFFF), (0xFF_FFFF, 0x3F));
+        assert!(verify_ecc(0x2708_63C1));
+        assert!(verify_ecc(0));
+        // a flipped data bit or code bit
+        assert!(!verify_ecc(0x2708_63C0));
+        assert!(!verify_ecc(0x2608_63C1));
+    }
+
+    #[test]
+    fn syndromes() {
+        // a clean word
+        let clean = syndrome(0x2708_63C1);
+        assert_eq!(clean, Syndrome { stored: 0x27, computed: 0x27, syndrome: 0, parity: false });
+        assert!(clean.is_clean());
+        assert_eq!(clean.mismatched(), 0);
+        // the bits above the word don't count
+        assert!(syndrome(0xC000_0000 | add_ecc(0xCC_ABCD)).is_clean());
+
+        // ECC bit 1 blank: the stored code is off by exactly that bit
+        let s = syndrome(0x2508_63C1);
+        assert_eq!(s, Syndrome { stored: 0x25, computed: 0x27, syndrome: 0x02, parity: false });
+        assert_eq!(s.mismatched(), 1 << ECC_POSITIONS[1]);
+
+        // the parity bit blown on its own
+        let s = syndrome(0x2000_0000);
+        assert_eq!(s, Syndrome { stored: 0x20, computed: 0x00, syndrome: 0, parity: true });
+        assert_eq!(s.mismatched(), 1 << PARITY_BIT);
+
+        // data bit 0 blank: the code of 0x8_63C0 is 0x3A, so check bits 0, 2, 3 and 4 disagree
+        // while the parity bit happens to agree
+        assert_eq!(add_ecc(0x8_63C0) >> ECC_SHIFT, 0x3A);
+        let s = syndrome(0x2708_63C0);
+        assert_eq!(s, Syndrome { stored: 0x27, computed: 0x3A, syndrome: 0x1D, parity: false });
+        assert_eq!(s.mismatched(), 0x1D << 24);
+    }
+
+    #[test]
+    fn syndrome_is_const() {
+        const S: Syndrome = syndrome(0x0000_0001);
+        assert!(!S.is_clean());
+        assert_eq!(S.computed as u32, add_ecc(1) >> ECC_SHIFT);
+    }
+
     #[test]
     fn gen_test() {
         assert_eq!(0x2708_63C1, add_ecc(0x8_63C1));
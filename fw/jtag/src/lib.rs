@@ -1,32 +1,33 @@
 #![no_std]
 
-/// Simple JTAG machine implementation
-/// 
-/// Applications calling this implementation first loads queries into the JtagMach pending queue.
-/// Queries are structured as JtagLeg, which is a bit-vector that corresponds to either an IR
-/// or DR sequencee. Reads of the DR should include a dummy "input vector" of corresponding to
-/// the length of the DR readback they are expecting. 
-/// 
-/// At any time, the machine can be asked to step() or next(), which will try to take the
-/// oldest query added to the pending queue and execute it. step() will move one or two JTAG
-/// PHY cycles, whereas next() will attempt to complete the execution of the latest pending
-/// leg, if any are available or in-flight.
-/// 
-/// Legs that have been executed are added to the "done" queue. The calling code can add a
-/// "tag" to the JtagLegs to help decode what data or command they corresponded to. 
-/// 
+//! Simple JTAG machine implementation
+//! 
+//! Applications calling this implementation first loads queries into the JtagMach pending queue.
+//! Queries are structured as JtagLeg, which is a bit-vector that corresponds to either an IR
+//! or DR sequencee. Reads of the DR should include a dummy "input vector" of corresponding to
+//! the length of the DR readback they are expecting. 
+//! 
+//! At any time, the machine can be asked to step() or next(), which will try to take the
+//! oldest query added to the pending queue and execute it. step() will move one or two JTAG
+//! PHY cycles, whereas next() will attempt to complete the execution of the latest pending
+//! leg, if any are available or in-flight.
+//! 
+//! Legs that have been executed are added to the "done" queue. The calling code can add a
+//! "tag" to the JtagLegs to help decode what data or command they corresponded to. 
 
 // Plug in the allocator crate
 extern crate alloc;
+#[cfg(target_arch = "riscv32")]
 extern crate alloc_riscv;
 
 #[cfg(feature = "evt")]
 use betrusted_hal::hal_uart::*;
 
+#[cfg(any(feature = "evt", feature = "dvt", feature = "pvt"))]
 use betrusted_hal::hal_time::*;
 use alloc::vec::Vec;
-use alloc::string::String;
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum JtagState {
     TestReset,
     RunIdle,
@@ -39,7 +40,137 @@ pub enum JtagState {
     Update,
 }
 
-#[derive(Copy, Clone)]
+/// The sixteen states of the IEEE 1149.1 TAP controller, as seen from the device side.
+///
+/// JtagMach only walks the subset of the state graph it needs (see JtagState); this is the full
+/// graph, for phys and models that have to follow the TAP from the TMS stream alone.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TapState {
+    TestLogicReset,
+    RunTestIdle,
+    SelectDrScan,
+    CaptureDr,
+    ShiftDr,
+    Exit1Dr,
+    PauseDr,
+    Exit2Dr,
+    UpdateDr,
+    SelectIrScan,
+    CaptureIr,
+    ShiftIr,
+    Exit1Ir,
+    PauseIr,
+    Exit2Ir,
+    UpdateIr,
+}
+
+impl TapState {
+    /// every state, in declaration order (so `TapState::ALL[s as usize] == s`)
+    pub const ALL: [TapState; 16] = {
+        use TapState::*;
+        [TestLogicReset, RunTestIdle, SelectDrScan, CaptureDr, ShiftDr, Exit1Dr, PauseDr, Exit2Dr,
+            UpdateDr, SelectIrScan, CaptureIr, ShiftIr, Exit1Ir, PauseIr, Exit2Ir, UpdateIr]
+    };
+
+    /// The transition table of IEEE 1149.1 figure 6-1: for each state, in declaration order,
+    /// the state after a TCK rising edge with TMS low and with TMS high.
+    pub const TRANSITIONS: [[TapState; 2]; 16] = {
+        use TapState::*;
+        [
+            [RunTestIdle, TestLogicReset],  // TestLogicReset
+            [RunTestIdle, SelectDrScan],    // RunTestIdle
+            [CaptureDr, SelectIrScan],      // SelectDrScan
+            [ShiftDr, Exit1Dr],             // CaptureDr
+            [ShiftDr, Exit1Dr],             // ShiftDr
+            [PauseDr, UpdateDr],            // Exit1Dr
+            [PauseDr, Exit2Dr],             // PauseDr
+            [ShiftDr, UpdateDr],            // Exit2Dr
+            [RunTestIdle, SelectDrScan],    // UpdateDr
+            [CaptureIr, TestLogicReset],    // SelectIrScan
+            [ShiftIr, Exit1Ir],             // CaptureIr
+            [ShiftIr, Exit1Ir],             // ShiftIr
+            [PauseIr, UpdateIr],            // Exit1Ir
+            [PauseIr, Exit2Ir],             // PauseIr
+            [ShiftIr, UpdateIr],            // Exit2Ir
+            [RunTestIdle, SelectDrScan],    // UpdateIr
+        ]
+    };
+
+    /// TMS held high this many cycles brings the TAP to Test-Logic-Reset from any state; the
+    /// only way there when the current state isn't known
+    pub const RESET_CYCLES: usize = 5;
+
+    /// state the TAP moves to on a TCK rising edge with the given TMS
+    pub fn next(self, tms: bool) -> TapState {
+        TapState::TRANSITIONS[self as usize][tms as usize]
+    }
+
+    /// The shortest TMS sequence from this state to `to`; empty if they're the same state.
+    /// Where two sequences are equally short, the one that goes TMS low first is taken.
+    pub fn path_to(self, to: TapState) -> TmsPath {
+        // breadth first, remembering how each state was first reached
+        let mut via: [Option<(TapState, bool)>; 16] = [None; 16];
+        let mut queue: [TapState; 16] = [self; 16];
+        let (mut head, mut tail) = (0, 1);
+        let mut seen: u16 = 1 << self as usize;
+        while head < tail && seen & (1 << to as usize) == 0 {
+            let state: TapState = queue[head];
+            head += 1;
+            for tms in [false, true].iter().copied() {
+                let next: TapState = state.next(tms);
+                if seen & (1 << next as usize) == 0 {
+                    seen |= 1 << next as usize;
+                    via[next as usize] = Some((state, tms));
+                    queue[tail] = next;
+                    tail += 1;
+                }
+            }
+        }
+        // every state is reachable from every other, so this walks back to `self`
+        let mut path: TmsPath = TmsPath { tms: 0, len: 0 };
+        let mut at: TapState = to;
+        while let Some((from, tms)) = via[at as usize] {
+            path.tms = (path.tms << 1) | tms as u16;
+            path.len += 1;
+            at = from;
+        }
+        path
+    }
+}
+
+/// A TMS sequence planned by TapState::path_to(), yielded first bit first
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TmsPath {
+    /// the bits, first in the LSB
+    tms: u16,
+    len: u8,
+}
+
+impl TmsPath {
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Iterator for TmsPath {
+    type Item = bool;
+
+    fn next(&mut self) -> Option<bool> {
+        if self.len == 0 {
+            return None;
+        }
+        let tms: bool = self.tms & 1 == 1;
+        self.tms >>= 1;
+        self.len -= 1;
+        Some(tms)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum JtagChain {
     DR,
     IR,
@@ -50,6 +181,157 @@ pub enum JtagEndian {
     Little   // LSB-first shiftout
 }
 
+/// Capacity of a single leg, in bytes. Legs never allocate; this bounds the longest IR or DR
+/// sequence a single leg can shift.
+pub const LEG_CAPACITY_BYTES: usize = 64;
+
+/// Fixed-capacity stack of bits. Bits are pushed onto and popped off the end, just like
+/// a Vec<bool> would be used.
+#[derive(Copy, Clone)]
+struct BitStack {
+    bits: [u8; LEG_CAPACITY_BYTES],
+    len: usize,
+}
+
+impl BitStack {
+    const CAPACITY: usize = LEG_CAPACITY_BYTES * 8;
+
+    const fn new() -> Self {
+        BitStack { bits: [0; LEG_CAPACITY_BYTES], len: 0 }
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn available(&self) -> usize {
+        BitStack::CAPACITY - self.len
+    }
+
+    /// callers check available() first; pushing onto a full stack is a bug
+    fn push(&mut self, bit: bool) {
+        assert!(self.len < BitStack::CAPACITY);
+        let mask: u8 = 1 << (self.len % 8);
+        if bit {
+            self.bits[self.len / 8] |= mask;
+        } else {
+            self.bits[self.len / 8] &= !mask;
+        }
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<bool> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        Some((self.bits[self.len / 8] >> (self.len % 8)) & 0x1 == 1)
+    }
+
+    /// bit `n`, counting from the first pushed
+    fn get(&self, n: usize) -> bool {
+        (self.bits[n / 8] >> (n % 8)) & 0x1 == 1
+    }
+}
+
+/// Bits shifted around the addressed device's when it shares the scan chain with others, which
+/// are all kept in BYPASS. Lead bits are shifted first and end up in the devices between the
+/// addressed one and TDO; trail bits are shifted last, into the devices between it and TDI.
+/// IR padding is all ones, the BYPASS instruction; DR padding is zeros, one bit per bypassed
+/// device.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub struct ChainPadding {
+    pub ir_lead: usize,
+    pub ir_trail: usize,
+    pub dr_lead: usize,
+    pub dr_trail: usize,
+}
+
+impl ChainPadding {
+    /// the addressed device is alone on the chain
+    pub const NONE: ChainPadding = ChainPadding { ir_lead: 0, ir_trail: 0, dr_lead: 0, dr_trail: 0 };
+
+    /// The padding that addresses a device with other devices around it, given by their IR
+    /// lengths: `before` are those between TDI and the addressed device, the one TDI reaches
+    /// first first, and `after` those between it and TDO.
+    pub fn around(before: &[usize], after: &[usize]) -> ChainPadding {
+        ChainPadding {
+            ir_lead: after.iter().sum(),
+            ir_trail: before.iter().sum(),
+            dr_lead: after.len(),
+            dr_trail: before.len(),
+        }
+    }
+
+    /// (lead, trail, fill) for a leg on `chain`
+    fn for_chain(&self, chain: JtagChain) -> (usize, usize, bool) {
+        match chain {
+            JtagChain::IR => (self.ir_lead, self.ir_trail, true),
+            JtagChain::DR => (self.dr_lead, self.dr_trail, false),
+        }
+    }
+}
+
+/// Check applied to the bits an IR scan shifts out, which are what Capture-IR loaded
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum IrVerification {
+    #[default]
+    Off,
+    /// the first two bits out must be 1 then 0, as IEEE 1149.1 requires of every device
+    Mandatory,
+    /// as Mandatory, and the captured bits in `mask` must also match `value`; for devices
+    /// whose capture value is known, e.g. because it holds status bits
+    Capture { value: u32, mask: u32 },
+}
+
+impl IrVerification {
+    /// true if `captured`, the bits shifted out with the first as the LSB, passes the check
+    pub fn accepts(self, captured: u32) -> bool {
+        match self {
+            IrVerification::Off => true,
+            IrVerification::Mandatory => captured & 0b11 == 0b01,
+            IrVerification::Capture { value, mask } => captured & 0b11 == 0b01 && (captured ^ value) & mask == 0,
+        }
+    }
+
+    /// a capture value this check accepts, for phys that stand in for a device
+    pub fn capture(self) -> u32 {
+        match self {
+            IrVerification::Off | IrVerification::Mandatory => 0b01,
+            IrVerification::Capture { value, mask } => (value & mask & !0b11) | 0b01,
+        }
+    }
+}
+
+/// Pattern the bits a leg shifts out must match, as SVF's TDO and MASK: each bit set in `mask`
+/// must equal that bit of `expected`, and the others are don't-care. Bits count from the first
+/// shifted out, as the LSB; only the first 128 are compared.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CaptureCompare {
+    pub expected: u128,
+    pub mask: u128,
+}
+
+impl CaptureCompare {
+    pub const fn new(expected: u128, mask: u128) -> Self {
+        CaptureCompare { expected, mask }
+    }
+
+    /// true if `got` matches the pattern
+    pub fn accepts(self, got: u128) -> bool {
+        (got ^ self.expected) & self.mask == 0
+    }
+}
+
+/// Returned when a push doesn't fit in a leg; nothing is pushed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LegOverflow {
+    /// number of bits that were to be pushed
+    pub requested: usize,
+    /// number of bits still free in the leg
+    pub available: usize,
+}
+
 /// option 1: make a "leg" machine that contains the shift-in/shift-out records specific to each leg
 /// option 2: make a comprehensive machine that receives meta-commands to transition between states
 /// 
@@ -57,26 +339,179 @@ pub enum JtagEndian {
 /// data to send into the IR or DR. There should be a state bit that indicates if the data has been
 /// executed; after execution, there is a result vector that is now valid.
 /// 
+/// The bit vectors are fixed-capacity (see LEG_CAPACITY_BYTES), so legs never touch the heap.
 #[derive(Clone)]
 pub struct JtagLeg {
     /// which chain (DR or IR)
     c: JtagChain,
     /// output bit vector to device; chain length is defined by vector length
-    o: Vec<bool>,
+    o: BitStack,
     /// input bit vector from device; length is dynamically allocated as leg traverses
-    i: Vec<bool>,
+    i: BitStack,
     /// a tag for the leg, to be used by higher level logic to track pending/done entries
-    tag: String,
+    tag: &'static str,
+    /// phy operations the traversal may take; see budget()
+    budget: Option<u32>,
+    /// check on what an IR leg shifts out; ignored on DR legs
+    ir_verify: IrVerification,
+    /// pattern what the leg shifts out is compared against
+    compare: Option<CaptureCompare>,
+    /// what the leg shifted out, once it's traversed, if it has a compare
+    compared: Option<u128>,
 }
 
 impl JtagLeg {
-    pub fn new(chain_type: JtagChain, mytag: &str) -> Self {
+    /// maximum number of bits a leg can shift
+    pub const CAPACITY_BITS: usize = BitStack::CAPACITY;
+    /// most TCK cycles a traversal spends outside the shift itself (an IR leg's)
+    pub const OVERHEAD_CYCLES: usize = 6;
+    /// a leg without a budget of its own may take this many phy operations per cycle of a
+    /// clean traversal
+    pub const DEFAULT_BUDGET_FACTOR: u32 = 4;
+
+    pub fn new(chain_type: JtagChain, mytag: &'static str) -> Self {
         JtagLeg {
             c: chain_type,
-            o: Vec::new(),
-            i: Vec::new(),
-            tag: String::from(mytag),
+            o: BitStack::new(),
+            i: BitStack::new(),
+            tag: mytag,
+            budget: None,
+            ir_verify: IrVerification::Off,
+            compare: None,
+            compared: None,
+        }
+    }
+
+    /// Checks what this IR leg shifts out once it's traversed. A scan that fails the check is
+    /// repeated once, before any other leg runs; if the repeat fails too, the machine stops
+    /// with JtagError::IrCorrupt. Has no effect on DR legs.
+    pub fn set_ir_verification(&mut self, verify: IrVerification) {
+        self.ir_verify = verify;
+    }
+
+    /// set_ir_verification(), for building legs in one expression
+    pub fn with_ir_verification(mut self, verify: IrVerification) -> Self {
+        self.set_ir_verification(verify);
+        self
+    }
+
+    pub fn ir_verification(&self) -> IrVerification {
+        self.ir_verify
+    }
+
+    /// Compares what this leg shifts out against `compare` once it's traversed. Unlike an IR
+    /// verification, a mismatch doesn't stop the machine: the leg completes as usual, and the
+    /// outcome is in compare_result().
+    pub fn set_compare(&mut self, compare: CaptureCompare) {
+        self.compare = Some(compare);
+    }
+
+    /// set_compare(), for building legs in one expression
+    pub fn with_compare(mut self, compare: CaptureCompare) -> Self {
+        self.set_compare(compare);
+        self
+    }
+
+    pub fn compare(&self) -> Option<CaptureCompare> {
+        self.compare
+    }
+
+    /// The outcome of the leg's compare: None if it has none or hasn't been traversed yet,
+    /// otherwise Ok, or JtagError::CompareFailed with what was shifted out.
+    pub fn compare_result(&self) -> Option<Result<(), JtagError>> {
+        let compare: CaptureCompare = self.compare?;
+        let got: u128 = self.compared?;
+        Some(if compare.accepts(got) {
+            Ok(())
+        } else {
+            Err(JtagError::CompareFailed { tag: self.tag, expected: compare.expected, mask: compare.mask, got })
+        })
+    }
+
+    /// A copy to traverse with `padding` around it. An explicit budget grows by the padding, so
+    /// it keeps covering the same work.
+    fn padded(&self, padding: ChainPadding) -> Result<JtagLeg, LegOverflow> {
+        let (lead, trail, fill) = padding.for_chain(self.c);
+        let mut leg: JtagLeg = self.clone();
+        if lead + trail == 0 {
+            return Ok(leg);
+        }
+        if lead + trail > self.i.available() {
+            return Err(LegOverflow { requested: lead + trail, available: self.i.available() });
+        }
+        // the last bits pushed are shifted first
+        leg.i = BitStack::new();
+        for _ in 0..trail {
+            leg.i.push(fill);
         }
+        for n in 0..self.i.len() {
+            leg.i.push(self.i.get(n));
+        }
+        for _ in 0..lead {
+            leg.i.push(fill);
+        }
+        leg.budget = self.budget.map(|ops| ops.saturating_add((lead + trail) as u32));
+        Ok(leg)
+    }
+
+    /// drops what the padding shifted out, leaving what the addressed device did
+    fn unpad(&mut self, padding: ChainPadding) {
+        let (lead, trail, _) = padding.for_chain(self.c);
+        if lead + trail == 0 {
+            return;
+        }
+        let shifted: BitStack = self.o;
+        self.o = BitStack::new();
+        for n in lead..shifted.len().saturating_sub(trail) {
+            self.o.push(shifted.get(n));
+        }
+    }
+
+    /// the bits shifted out so far, the first as the LSB; at most the first 128
+    fn shifted_out(&self) -> u128 {
+        let count: usize = self.o.len();
+        let mut o: BitStack = self.o;
+        let mut data: u128 = 0;
+        for _ in 0..count {
+            data = data.wrapping_shl(1) | o.pop().unwrap() as u128;
+        }
+        data
+    }
+
+    /// Caps the phy operations traversing this leg may take, retries of stalled cycles
+    /// included. A clean traversal takes the leg's length plus at most OVERHEAD_CYCLES.
+    pub fn set_budget(&mut self, ops: u32) {
+        self.budget = Some(ops);
+    }
+
+    /// set_budget(), for building legs in one expression
+    pub fn with_budget(mut self, ops: u32) -> Self {
+        self.set_budget(ops);
+        self
+    }
+
+    /// The budget the leg is traversed under: the one set, or else DEFAULT_BUDGET_FACTOR times
+    /// a clean traversal of the bits pushed so far.
+    pub fn budget(&self) -> u32 {
+        self.budget.unwrap_or(((self.i.len() + JtagLeg::OVERHEAD_CYCLES) as u32) * JtagLeg::DEFAULT_BUDGET_FACTOR)
+    }
+
+    /// common implementation of the push_ calls
+    fn push(&mut self, data: u128, count: usize, endian: JtagEndian) -> Result<(), LegOverflow> {
+        if count > self.i.available() {
+            return Err(LegOverflow { requested: count, available: self.i.available() });
+        }
+        for i in 0..count {
+            match endian {
+                JtagEndian::Big => {
+                    self.i.push((data & (1 << i)) != 0);
+                },
+                JtagEndian::Little => {
+                    self.i.push((data & (1 << (count-1-i))) != 0);
+                },
+            }
+        }
+        Ok(())
     }
 
     /// `push` will take data in the form of an unsigned int (either u128 or u32)
@@ -91,46 +526,120 @@ impl JtagLeg {
     /// `101100` into the JTAG chain MSB first, store 0x2C into "data" and specify
     /// a "count" of 6, and an "endian" of JtagEndian::Big. Do not shift
     /// data all the way to the MSB of the containing "data" parameter in this case!
-    pub fn push_u128(&mut self, data: u128, count: usize, endian: JtagEndian) {
+    ///
+    /// If the bits don't fit in the leg, nothing is pushed and an error is returned.
+    pub fn push_u128(&mut self, data: u128, count: usize, endian: JtagEndian) -> Result<(), LegOverflow> {
         assert!(count <= 128);
+        self.push(data, count, endian)
+    }
+
+    pub fn push_u32(&mut self, data: u32, count: usize, endian: JtagEndian) -> Result<(), LegOverflow> {
+        assert!(count <= 32);
+        self.push(data as u128, count, endian)
+    }
+
+    pub fn push_u8(&mut self, data: u8, count: usize, endian: JtagEndian) -> Result<(), LegOverflow> {
+        assert!(count <= 8);
+        self.push(data as u128, count, endian)
+    }
+
+    /// `push_bits` is `push` for numbers of any length: `data` holds the number LSB first,
+    /// bit n in bit n % 8 of byte n / 8, and the bottom `count` bits of it are pushed, MSB or
+    /// LSB first as `endian` says. `data` has to hold at least `count` bits; any bits past
+    /// them are ignored.
+    ///
+    /// If the bits don't fit in the leg, nothing is pushed and an error is returned.
+    pub fn push_bits(&mut self, data: &[u8], count: usize, endian: JtagEndian) -> Result<(), LegOverflow> {
+        assert!(count <= data.len() * 8);
+        if count > self.i.available() {
+            return Err(LegOverflow { requested: count, available: self.i.available() });
+        }
+        let bit = |n: usize| (data[n / 8] >> (n % 8)) & 0x1 == 1;
         for i in 0..count {
             match endian {
-                JtagEndian::Big => {
-                    if (data & (1 << i)) == 0 { self.i.push(false) } else { self.i.push(true) }
-                },
-                JtagEndian::Little => {
-                    if (data & (1 << (count-1-i))) == 0 { self.i.push(false) } else { self.i.push(true) }
-                },
+                JtagEndian::Big => self.i.push(bit(i)),
+                JtagEndian::Little => self.i.push(bit(count - 1 - i)),
             }
         }
+        Ok(())
     }
 
-    pub fn push_u32(&mut self, data: u32, count: usize, endian: JtagEndian) {
-        assert!(count <= 32);
-        for i in 0..count {
-            match endian {
-                JtagEndian::Big => {
-                    if (data & (1 << i)) == 0 { self.i.push(false) } else { self.i.push(true) }
-                },
-                JtagEndian::Little => {
-                    if (data & (1 << (count-1-i))) == 0 { self.i.push(false) } else { self.i.push(true) }
-                },
+    /// `pop_bits` is `pop_u32` for numbers of any length: the last `count` bits shifted out
+    /// are popped into `data`, LSB first as push_bits() takes them, the first of them shifted
+    /// being the LSB or the MSB as `endian` says. Unlike pop_u32(), a JtagEndian::Big number
+    /// shorter than `data` isn't moved to its top: it always starts at bit 0. The bytes the
+    /// `count` bits touch are overwritten, with any bits above them cleared; bytes past
+    /// them are left alone.
+    ///
+    /// If fewer than `count` bits were shifted out, nothing is popped and None is returned;
+    /// otherwise the number of bits popped.
+    pub fn pop_bits(&mut self, data: &mut [u8], count: usize, endian: JtagEndian) -> Option<usize> {
+        assert!(count <= data.len() * 8);
+        if self.o.len() < count {
+            return None;
+        }
+        for byte in data[..count.div_ceil(8)].iter_mut() {
+            *byte = 0;
+        }
+        for k in 0..count {
+            // popped from the last shifted back
+            let n: usize = match endian {
+                JtagEndian::Little => count - 1 - k,
+                JtagEndian::Big => k,
+            };
+            if self.o.pop().unwrap() {
+                data[n / 8] |= 1 << (n % 8);
             }
         }
+        Some(count)
     }
 
-    pub fn push_u8(&mut self, data: u8, count: usize, endian: JtagEndian) {
-        assert!(count <= 8);
-        for i in 0..count {
-            match endian {
-                JtagEndian::Big => {
-                    if (data & (1 << i)) == 0 { self.i.push(false) } else { self.i.push(true) }
-                },
-                JtagEndian::Little => {
-                    if (data & (1 << (count-1-i))) == 0 { self.i.push(false) } else { self.i.push(true) }
-                },
+    /// `push_bytes` pushes whole bytes, data[0] to be shifted first and each byte MSB or LSB
+    /// first as `endian` says. That's push_u8(byte, 8, endian) of every byte, from the last to
+    /// the first, as a leg shifts what was pushed last first. With JtagEndian::Little it's the
+    /// same as push_bits() of all of `data`; with JtagEndian::Big it isn't, as push_bits() would
+    /// shift the MSB of the last byte first.
+    ///
+    /// If the bytes don't fit in the leg, nothing is pushed and an error is returned.
+    pub fn push_bytes(&mut self, data: &[u8], endian: JtagEndian) -> Result<(), LegOverflow> {
+        if data.len() * 8 > self.i.available() {
+            return Err(LegOverflow { requested: data.len() * 8, available: self.i.available() });
+        }
+        for &byte in data.iter().rev() {
+            for i in 0..8 {
+                match endian {
+                    JtagEndian::Big => self.i.push((byte >> i) & 0x1 == 1),
+                    JtagEndian::Little => self.i.push((byte >> (7 - i)) & 0x1 == 1),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// `pop_bytes` undoes push_bytes(): the last `data.len()` bytes shifted out are popped into
+    /// `data`, the first of them shifted into data[0], each as pop_u8(8, endian) would have
+    /// it. With JtagEndian::Little that's the same as pop_bits() of all of `data`.
+    ///
+    /// If fewer bits than `data` holds were shifted out, nothing is popped and None is
+    /// returned; otherwise the number of bytes popped.
+    pub fn pop_bytes(&mut self, data: &mut [u8], endian: JtagEndian) -> Option<usize> {
+        if self.o.len() < data.len() * 8 {
+            return None;
+        }
+        // popped from the last shifted back
+        for byte in data.iter_mut().rev() {
+            *byte = 0;
+            for k in 0..8 {
+                let n: usize = match endian {
+                    JtagEndian::Little => 7 - k,
+                    JtagEndian::Big => k,
+                };
+                if self.o.pop().unwrap() {
+                    *byte |= 1 << n;
+                }
             }
         }
+        Some(data.len())
     }
 
     pub fn pop_u32(&mut self, count: usize, endian: JtagEndian) -> Option<u32> {
@@ -211,8 +720,8 @@ impl JtagLeg {
     }
 
 
-    pub fn tag(&self) -> String {
-        self.tag.clone()
+    pub fn tag(&self) -> &'static str {
+        self.tag
     }
 
     pub fn dbg_i_len(&self) -> usize {
@@ -223,10 +732,117 @@ impl JtagLeg {
     }
 }
 
+/// Errors a phy can report when it fails to complete a cycle
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PhyError {
+    /// the underlying transport failed to carry out the cycle
+    Transport,
+    /// the time budget for the operation or session ran out (see DeadlinePhy)
+    DeadlineExceeded,
+    /// the phy couldn't carry out the cycle yet and drove nothing, so it can be retried. The
+    /// JtagMach retries stalled cycles within a leg until the leg's budget is spent.
+    Busy,
+}
+
+/// Errors reported by the JtagMach
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum JtagError {
+    /// the phy failed; the TAP is in an unknown state
+    Phy(PhyError),
+    /// a previous phy failure left the TAP in an unknown state; reset() before driving again
+    Desynchronized,
+    /// a sequence didn't fit in the pending queue; nothing was queued
+    QueueFull(QueueFull),
+    /// data didn't fit in a leg; nothing was pushed
+    LegOverflow(LegOverflow),
+    /// the leg tagged `tag` had spent its budget of phy operations without completing; it
+    /// stays at the head of the pending queue, and the TAP is in an unknown state
+    LegTimeout { tag: &'static str, budget: u32, spent: u32 },
+    /// the IR leg tagged `tag` failed its IrVerification twice running; `captured` is what the
+    /// second scan shifted out. An unknown instruction may be latched; the leg stays at the
+    /// head of the pending queue, and the machine must be reset.
+    IrCorrupt { tag: &'static str, captured: u32 },
+    /// what the leg tagged `tag` shifted out, `got`, doesn't match `expected` in the bits set
+    /// in `mask` (see CaptureCompare); the leg completed
+    CompareFailed { tag: &'static str, expected: u128, mask: u128, got: u128 },
+    /// the TAP was in `found` where `expected` was needed (see JtagMach::expect_state)
+    WrongState { expected: TapState, found: TapState },
+}
+
+/// Returned when legs can't be queued because the pending queue is at capacity
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct QueueFull {
+    /// number of legs that were to be queued
+    pub requested: usize,
+    /// number of free slots in the queue at the time
+    pub available: usize,
+}
+
+impl From<LegOverflow> for JtagError {
+    fn from(err: LegOverflow) -> Self {
+        JtagError::LegOverflow(err)
+    }
+}
+
+impl From<QueueFull> for JtagError {
+    fn from(err: QueueFull) -> Self {
+        JtagError::QueueFull(err)
+    }
+}
+
+impl From<PhyError> for JtagError {
+    fn from(err: PhyError) -> Self {
+        JtagError::Phy(err)
+    }
+}
+
+/// Bit-level access to a JTAG port.
+///
+/// Whether a phy is Send or Sync is up to the implementation. JtagUartPhy and JtagGpioPhy drive
+/// SoC peripherals and belong to the firmware's single thread; DeadlinePhy is Send when the phy
+/// and clock it wraps are. A JtagMach is plain data, so it can move along with its phy.
 pub trait JtagPhy {
     fn sync(&mut self, tdi: bool, tms: bool) -> bool; 
     fn nosync(&mut self, tdi: bool, tms: bool, tck: bool) -> bool;
     fn pause(&mut self, us: u32);
+
+    /// Fallible version of sync(). Phys whose transport can fail should override this; the
+    /// default simply forwards to sync(). The JtagMach only ever drives the phy through this call.
+    fn try_sync(&mut self, tdi: bool, tms: bool) -> Result<bool, PhyError> {
+        Ok(self.sync(tdi, tms))
+    }
+
+    /// Shifts up to `nbits` cycles in one call, for phys with hardware that can (an FTDI
+    /// MPSSE, a CSR shifter). Bit n of `tdi`, `tms` and `tdo_out` is bit n % 8 of byte n / 8,
+    /// bit 0 going first; `tdo_out` gets what TDO was sampled at each cycle, and bits past those
+    /// shifted are left alone. The JtagMach uses this for DR legs of BULK_SHIFT_BITS or more.
+    ///
+    /// Returns how many cycles were shifted, at least one. A phy may stop short, e.g. when it
+    /// would otherwise stall; the JtagMach calls again with the rest, and counts the stop as a
+    /// stall against the leg's budget. An error means no cycle was shifted, as for try_sync():
+    /// a cycle that fails after others have been shifted ends the call short, and the next
+    /// call tries it again, so that wrappers see every cycle that was. The default shifts a
+    /// cycle at a time through try_sync(), stopping short at the first that fails, so every
+    /// phy works, and spends its budget, as it did.
+    fn shift_bulk(&mut self, tdi: &[u8], tms: &[u8], tdo_out: &mut [u8], nbits: usize) -> Result<usize, PhyError> {
+        let bit = |bytes: &[u8], n: usize| (bytes[n / 8] >> (n % 8)) & 0x1 == 1;
+        for n in 0..nbits {
+            match self.try_sync(bit(tdi, n), bit(tms, n)) {
+                Ok(tdo) => {
+                    if tdo {
+                        tdo_out[n / 8] |= 1 << (n % 8);
+                    } else {
+                        tdo_out[n / 8] &= !(1 << (n % 8));
+                    }
+                },
+                Err(_) if n > 0 => return Ok(n),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(nbits)
+    }
 }
 
 #[cfg(feature = "evt")]
@@ -343,9 +959,492 @@ impl JtagPhy for JtagGpioPhy {
     }
 }
 
+/// Monotonic time source, for phys that need to keep track of time
+pub trait Clock {
+    /// microseconds since an arbitrary fixed point; never goes backwards
+    fn now_us(&self) -> u64;
+}
+
+/// Time budget enforced by DeadlinePhy. A None field is not enforced.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Budget {
+    /// longest a single cycle may take
+    pub operation_us: Option<u64>,
+    /// total time allowed since the budget was set, pauses included
+    pub session_us: Option<u64>,
+}
+
+impl Budget {
+    pub const UNLIMITED: Budget = Budget { operation_us: None, session_us: None };
+
+    pub const fn new(operation_us: Option<u64>, session_us: Option<u64>) -> Self {
+        Budget { operation_us, session_us }
+    }
+}
+
+/// Wraps a phy and fails every cycle with PhyError::DeadlineExceeded once its time budget is
+/// spent, so that a wedged transport or target can't hang the caller.
+///
+/// The deadline is checked against `clock` around every cycle. Once it has passed the wrapper
+/// latches the expiry: the wrapped phy is never called again, pauses return immediately, and
+/// every try_sync() fails, until a new budget is set with set_budget().
+pub struct DeadlinePhy<T: JtagPhy, C: Clock> {
+    inner: T,
+    clock: C,
+    budget: Budget,
+    session_start: u64,
+    expired: bool,
+}
+
+impl<T: JtagPhy, C: Clock> DeadlinePhy<T, C> {
+    pub fn new(inner: T, clock: C, budget: Budget) -> Self {
+        let session_start: u64 = clock.now_us();
+        DeadlinePhy { inner, clock, budget, session_start, expired: false }
+    }
+
+    /// replace the budget and restart the session from now; this also clears a previous expiry
+    pub fn set_budget(&mut self, budget: Budget) {
+        self.budget = budget;
+        self.session_start = self.clock.now_us();
+        self.expired = false;
+    }
+
+    pub fn budget(&self) -> Budget {
+        self.budget
+    }
+
+    /// true once the budget has run out
+    pub fn expired(&self) -> bool {
+        self.expired
+    }
+
+    /// time left in the session, if the session is budgeted
+    pub fn remaining_us(&self) -> Option<u64> {
+        let spent: u64 = self.clock.now_us().saturating_sub(self.session_start);
+        self.budget.session_us.map(|limit| limit.saturating_sub(spent))
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn check_session(&mut self) {
+        if self.remaining_us() == Some(0) {
+            self.expired = true;
+        }
+    }
+}
+
+impl<T: JtagPhy, C: Clock> JtagPhy for DeadlinePhy<T, C> {
+    fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+        self.try_sync(tdi, tms).unwrap_or(false)
+    }
+
+    fn nosync(&mut self, tdi: bool, tms: bool, tck: bool) -> bool {
+        if self.expired {
+            return false;
+        }
+        self.inner.nosync(tdi, tms, tck)
+    }
+
+    fn pause(&mut self, us: u32) {
+        if self.expired {
+            return;
+        }
+        self.inner.pause(us);
+        self.check_session();
+    }
+
+    fn try_sync(&mut self, tdi: bool, tms: bool) -> Result<bool, PhyError> {
+        self.check_session();
+        if self.expired {
+            return Err(PhyError::DeadlineExceeded);
+        }
+        let start: u64 = self.clock.now_us();
+        let tdo: bool = self.inner.try_sync(tdi, tms)?;
+        if let Some(limit) = self.budget.operation_us {
+            if self.clock.now_us().saturating_sub(start) > limit {
+                self.expired = true;
+                return Err(PhyError::DeadlineExceeded);
+            }
+        }
+        Ok(tdo)
+    }
+}
+
+/// One sync() cycle as recorded by RecordingPhy
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Transition {
+    /// the TAP state the cycle was clocked in
+    pub state: TapState,
+    pub tdi: bool,
+    pub tms: bool,
+    /// what the wrapped phy returned
+    pub tdo: bool,
+}
+
+impl Transition {
+    /// filler for the buffer handed to RecordingPhy::new()
+    pub const BLANK: Transition = Transition { state: TapState::TestLogicReset, tdi: false, tms: false, tdo: false };
+}
+
+/// A DR scan found among the recorded transitions, one transition per bit shifted, first
+/// shifted first
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DrShift<'a> {
+    pub bits: &'a [Transition],
+}
+
+impl<'a> DrShift<'a> {
+    pub fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.is_empty()
+    }
+
+    /// the bottom 128 bits shifted in, as a number
+    pub fn tdi_value(&self) -> u128 {
+        self.bits.iter().take(128).enumerate().fold(0, |acc, (i, t)| acc | ((t.tdi as u128) << i))
+    }
+
+    /// the bottom 128 bits shifted out, as a number
+    pub fn tdo_value(&self) -> u128 {
+        self.bits.iter().take(128).enumerate().fold(0, |acc, (i, t)| acc | ((t.tdo as u128) << i))
+    }
+}
+
+/// Wraps a phy and records every sync() cycle into a buffer the caller provides, so traffic can
+/// be checked or dumped without an allocator.
+///
+/// The buffer is a ring: once it is full, each new cycle overwrites the oldest one, and
+/// dropped() counts those lost. The TAP is followed from the TMS stream, starting from
+/// TEST_LOGIC_RESET as the JtagMach does, so that each transition carries the state it was
+/// clocked in. nosync() and pause() are passed through unrecorded.
+pub struct RecordingPhy<'a, T: JtagPhy> {
+    inner: T,
+    buf: &'a mut [Transition],
+    /// slot of the oldest cycle held
+    start: usize,
+    /// cycles recorded since new() or clear(), including any the ring has dropped
+    count: usize,
+    tap: TapState,
+}
+
+impl<'a, T: JtagPhy> RecordingPhy<'a, T> {
+    pub fn new(inner: T, buf: &'a mut [Transition]) -> Self {
+        RecordingPhy { inner, buf, start: 0, count: 0, tap: TapState::TestLogicReset }
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// the TAP state the next cycle will be clocked in
+    pub fn tap(&self) -> TapState {
+        self.tap
+    }
+
+    /// cycles recorded since new() or clear(), including any the ring has dropped
+    pub fn bit_count(&self) -> usize {
+        self.count
+    }
+
+    /// cycles the ring has dropped to make room for newer ones
+    pub fn dropped(&self) -> usize {
+        self.count.saturating_sub(self.buf.len())
+    }
+
+    /// forgets every recorded cycle; the TAP state is kept
+    pub fn clear(&mut self) {
+        self.start = 0;
+        self.count = 0;
+    }
+
+    /// The cycles still held, oldest first. Takes `&mut self` because the ring is rotated in
+    /// place to make them contiguous.
+    pub fn transitions(&mut self) -> &[Transition] {
+        let held: usize = self.count.min(self.buf.len());
+        self.buf.rotate_left(self.start);
+        self.start = 0;
+        &self.buf[..held]
+    }
+
+    /// The DR scans among the cycles still held, in order. A scan whose start the ring has
+    /// dropped, or that hasn't left SHIFT_DR yet, is left out; one paused in PAUSE_DR, which
+    /// the JtagMach never does, shows up as two.
+    pub fn iter_dr_shifts(&mut self) -> impl Iterator<Item = DrShift<'_>> {
+        let cut: bool = self.dropped() != 0;
+        let held: &[Transition] = self.transitions();
+        let cut_first: bool = cut && held.first().map(|t| t.state) == Some(TapState::ShiftDr);
+        held.split(|t| t.state != TapState::ShiftDr)
+            .enumerate()
+            .filter(move |(n, bits)| !bits.is_empty() && (*n != 0 || !cut_first))
+            .map(|(_, bits)| DrShift { bits })
+            .filter(|shift| shift.bits.last().map(|t| t.tms) == Some(true))
+    }
+}
+
+impl<'a, T: JtagPhy> JtagPhy for RecordingPhy<'a, T> {
+    fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+        self.try_sync(tdi, tms).unwrap_or(false)
+    }
+
+    fn nosync(&mut self, tdi: bool, tms: bool, tck: bool) -> bool {
+        self.inner.nosync(tdi, tms, tck)
+    }
+
+    fn pause(&mut self, us: u32) {
+        self.inner.pause(us);
+    }
+
+    fn try_sync(&mut self, tdi: bool, tms: bool) -> Result<bool, PhyError> {
+        let tdo: bool = self.inner.try_sync(tdi, tms)?;
+        let len: usize = self.buf.len();
+        if len != 0 {
+            let slot: usize = if self.count < len {
+                self.count
+            } else {
+                let oldest: usize = self.start;
+                self.start = (self.start + 1) % len;
+                oldest
+            };
+            self.buf[slot] = Transition { state: self.tap, tdi, tms, tdo };
+        }
+        self.count += 1;
+        self.tap = self.tap.next(tms);
+        Ok(tdo)
+    }
+}
+
+/// What ReplayPhy does once its capture has run out
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Exhausted {
+    /// fail the cycle with PhyError::Transport
+    Fail,
+    /// go on returning this bit
+    Fill(bool),
+}
+
+/// Plays back a captured TDO stream, one bit per sync(), whatever is driven, so that the code
+/// reading a device can be run against a capture of one.
+///
+/// The capture has to line up with the cycles the code under test drives, as it does when it
+/// was recorded (by RecordingPhy, say) running that same code. pause() and nosync() consume
+/// nothing.
+pub struct ReplayPhy<'a> {
+    tdo: &'a [bool],
+    cursor: usize,
+    exhausted: Exhausted,
+    /// cycles driven past the end of the capture
+    overrun: usize,
+}
+
+impl<'a> ReplayPhy<'a> {
+    pub fn new(tdo: &'a [bool], exhausted: Exhausted) -> Self {
+        ReplayPhy { tdo, cursor: 0, exhausted, overrun: 0 }
+    }
+
+    /// bits played back so far
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// bits of the capture not played back yet
+    pub fn remaining(&self) -> usize {
+        self.tdo.len() - self.cursor
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.cursor == self.tdo.len()
+    }
+
+    /// cycles driven past the end of the capture, failed or filled
+    pub fn overrun(&self) -> usize {
+        self.overrun
+    }
+
+    /// starts playing back from the beginning again
+    pub fn rewind(&mut self) {
+        self.cursor = 0;
+        self.overrun = 0;
+    }
+}
+
+impl<'a> JtagPhy for ReplayPhy<'a> {
+    fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+        self.try_sync(tdi, tms).unwrap_or(false)
+    }
+
+    /// the bit the next cycle will play back, or the filler; nothing is consumed
+    fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool {
+        match (self.tdo.get(self.cursor), self.exhausted) {
+            (Some(&tdo), _) => tdo,
+            (None, Exhausted::Fill(tdo)) => tdo,
+            (None, Exhausted::Fail) => false,
+        }
+    }
+
+    fn pause(&mut self, _us: u32) {}
+
+    fn try_sync(&mut self, _tdi: bool, _tms: bool) -> Result<bool, PhyError> {
+        if let Some(&tdo) = self.tdo.get(self.cursor) {
+            self.cursor += 1;
+            return Ok(tdo);
+        }
+        self.overrun += 1;
+        match self.exhausted {
+            Exhausted::Fail => Err(PhyError::Transport),
+            Exhausted::Fill(tdo) => Ok(tdo),
+        }
+    }
+}
+
+/// One entry of a command table: a value to shift into the IR or DR, LSB first.
+#[derive(Copy, Clone, Debug)]
+pub struct SeqCmd {
+    pub chain: JtagChain,
+    /// number of bits to shift
+    pub count: usize,
+    pub value: u64,
+    pub tag: &'static str,
+    /// the leg's budget of phy operations; the leg's default if None
+    pub budget: Option<u32>,
+    /// check on what an IR command shifts out
+    pub ir_verify: IrVerification,
+    /// pattern what the command shifts out is compared against
+    pub compare: Option<CaptureCompare>,
+}
+
+impl SeqCmd {
+    pub const fn new(chain: JtagChain, count: usize, value: u64, tag: &'static str) -> Self {
+        SeqCmd { chain, count, value, tag, budget: None, ir_verify: IrVerification::Off, compare: None }
+    }
+
+    /// this command, with its leg traversed under a budget of `ops` phy operations
+    pub const fn with_budget(self, ops: u32) -> Self {
+        SeqCmd { budget: Some(ops), ..self }
+    }
+
+    /// this command, with what its IR scan shifts out checked; see JtagLeg::set_ir_verification
+    pub const fn with_ir_verification(self, verify: IrVerification) -> Self {
+        SeqCmd { ir_verify: verify, ..self }
+    }
+
+    /// this command, with what it shifts out compared; see JtagLeg::set_compare
+    pub const fn with_compare(self, expected: u128, mask: u128) -> Self {
+        SeqCmd { compare: Some(CaptureCompare::new(expected, mask)), ..self }
+    }
+
+    /// build the leg that shifts this command
+    pub fn leg(&self) -> JtagLeg {
+        let mut leg: JtagLeg = JtagLeg::new(self.chain, self.tag);
+        // a command is at most 128 bits, which always fits in a fresh leg
+        leg.push_u128(self.value as u128, self.count, JtagEndian::Little).unwrap();
+        if let Some(ops) = self.budget {
+            leg.set_budget(ops);
+        }
+        leg.set_ir_verification(self.ir_verify);
+        leg.compare = self.compare;
+        leg
+    }
+}
+
+/// Phy operations spent on the leg being traversed, against its budget
+#[derive(Copy, Clone, Debug)]
+struct LegMeter {
+    tag: &'static str,
+    budget: u32,
+    spent: u32,
+}
+
+impl LegMeter {
+    fn new(leg: &JtagLeg) -> Self {
+        LegMeter { tag: leg.tag, budget: leg.budget(), spent: 0 }
+    }
+
+    /// one TCK cycle of the leg, retrying while the phy is busy and the budget lasts
+    fn sync<T: JtagPhy>(&mut self, phy: &mut T, tdi: bool, tms: bool) -> Result<bool, JtagError> {
+        loop {
+            if self.spent >= self.budget {
+                return Err(JtagError::LegTimeout { tag: self.tag, budget: self.budget, spent: self.spent });
+            }
+            self.spent += 1;
+            match phy.try_sync(tdi, tms) {
+                Err(PhyError::Busy) => continue,
+                result => return result.map_err(JtagError::Phy),
+            }
+        }
+    }
+
+    /// Shifts all of `i` through JtagPhy::shift_bulk(), last pushed first as the shift state
+    /// does a bit at a time, onto `o`; the last bit goes with TMS high, to leave for Exit1. A
+    /// cycle costs what a sync() would, and so does each stall, stopping short included; no
+    /// call is given more cycles than the budget has left.
+    fn shift_bulk<T: JtagPhy>(&mut self, phy: &mut T, i: &mut BitStack, o: &mut BitStack) -> Result<(), JtagError> {
+        while i.len() > 0 {
+            if self.spent >= self.budget {
+                return Err(JtagError::LegTimeout { tag: self.tag, budget: self.budget, spent: self.spent });
+            }
+            let nbits: usize = i.len().min((self.budget - self.spent) as usize);
+            let mut tdi: [u8; LEG_CAPACITY_BYTES] = [0; LEG_CAPACITY_BYTES];
+            let mut tms: [u8; LEG_CAPACITY_BYTES] = [0; LEG_CAPACITY_BYTES];
+            let mut tdo: [u8; LEG_CAPACITY_BYTES] = [0; LEG_CAPACITY_BYTES];
+            for n in 0..nbits {
+                if i.get(i.len() - 1 - n) {
+                    tdi[n / 8] |= 1 << (n % 8);
+                }
+            }
+            if nbits == i.len() {
+                tms[(nbits - 1) / 8] |= 1 << ((nbits - 1) % 8);
+            }
+            match phy.shift_bulk(&tdi[..nbits.div_ceil(8)], &tms[..nbits.div_ceil(8)], &mut tdo[..nbits.div_ceil(8)], nbits) {
+                // shifting nothing is a stall, however the phy puts it
+                Err(PhyError::Busy) | Ok(0) => self.spent += 1,
+                Err(e) => return Err(JtagError::Phy(e)),
+                Ok(shifted) => {
+                    assert!(shifted <= nbits, "the phy shifted more than it was given");
+                    self.spent += shifted as u32 + (shifted < nbits) as u32;
+                    for n in 0..shifted {
+                        i.pop();
+                        o.push((tdo[n / 8] >> (n % 8)) & 0x1 == 1);
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Default number of legs the pending queue accepts through the capacity-checked calls
+pub const DEFAULT_QUEUE_CAPACITY: usize = 32;
+
+/// DR legs of this many bits or more, padding included, are shifted through
+/// JtagPhy::shift_bulk() in a single step; shorter legs and IR legs a bit per step
+pub const BULK_SHIFT_BITS: usize = 64;
+
 pub struct JtagMach {
     /// current state (could be in one of two generics, or in DR/IR chain; check top of Vector for current chain)
     s: JtagState,
+    /// the TAP controller state the device is in, as far as the TMS driven so far says
+    tap: TapState,
     /// a vector of legs to traverse. An entry stays in pending until the traversal is complete. Aborted
     /// traversals leave the leg in place
     pending: Vec<JtagLeg>,
@@ -353,51 +1452,177 @@ pub struct JtagMach {
     done: Vec<JtagLeg>,
     /// the current leg being processed
     current: Option<JtagLeg>,
+    /// budget and phy operations spent on the current leg
+    meter: LegMeter,
+    /// set when the current leg is a repeat of an IR scan that failed its verification
+    ir_retried: bool,
+    /// set when a phy error left the TAP in an unknown state; cleared by reset()
+    desync: bool,
+    /// bits shifted for the other devices on the chain
+    padding: ChainPadding,
+    /// maximum length of the pending queue, as enforced by try_add/add_all/add_seq
+    capacity: usize,
     /// an integer for debug help
     debug: u32,
 }
 
 impl JtagMach {
     pub fn new() -> Self {
+        JtagMach::with_capacity(DEFAULT_QUEUE_CAPACITY)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
         JtagMach {
             s: JtagState::TestReset,
+            tap: TapState::TestLogicReset,
             pending: Vec::new(),
             done: Vec::new(),
             current: None,
+            meter: LegMeter { tag: "", budget: 0, spent: 0 },
+            ir_retried: false,
+            desync: false,
+            padding: ChainPadding::NONE,
+            capacity,
             debug: 0,
         }
     }
 
-    /// add() -- add a leg to the pending queue
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Addresses one device of several on the scan chain: every leg is shifted with `padding`
+    /// around it, and the legs in the done queue hold only what that device shifted out.
+    /// Applies from the next leg the machine starts on; reset() afterwards so that no device
+    /// is left with an instruction latched under the old padding.
+    pub fn set_padding(&mut self, padding: ChainPadding) {
+        self.padding = padding;
+    }
+
+    pub fn padding(&self) -> ChainPadding {
+        self.padding
+    }
+
+    /// the TAP state `s` stands for while traversing a leg on `chain`
+    fn tap_state(s: JtagState, chain: JtagChain) -> TapState {
+        use TapState::*;
+        let (dr, ir) = match s {
+            JtagState::TestReset => (TestLogicReset, TestLogicReset),
+            JtagState::RunIdle => (RunTestIdle, RunTestIdle),
+            JtagState::Select => (SelectDrScan, SelectIrScan),
+            JtagState::Capture => (CaptureDr, CaptureIr),
+            JtagState::Shift => (ShiftDr, ShiftIr),
+            JtagState::Exit1 => (Exit1Dr, Exit1Ir),
+            JtagState::Pause => (PauseDr, PauseIr),
+            JtagState::Exit2 => (Exit2Dr, Exit2Ir),
+            JtagState::Update => (UpdateDr, UpdateIr),
+        };
+        if chain == JtagChain::IR { ir } else { dr }
+    }
+
+    /// chain of the leg being traversed; DR if there is none
+    fn chain(&self) -> JtagChain {
+        self.current.as_ref().map_or(JtagChain::DR, |cur| cur.c)
+    }
+
+    /// drive the TAP to the state `s` stands for, along the planned path, on the current leg's
+    /// budget; returns `s`
+    fn walk<T: JtagPhy>(&mut self, phy: &mut T, s: JtagState) -> Result<JtagState, JtagError> {
+        let to: TapState = JtagMach::tap_state(s, self.chain());
+        for tms in self.tap.path_to(to) {
+            self.meter.sync(phy, false, tms)?;
+        }
+        self.tap = to;
+        Ok(s)
+    }
+
+    /// start traversing the leg at the head of the pending queue
+    fn begin_leg(&mut self) -> Result<(), JtagError> {
+        let leg: JtagLeg = self.pending[0].padded(self.padding)?;
+        self.meter = LegMeter::new(&leg);
+        self.current = Some(leg);
+        Ok(())
+    }
+
+    /// add() -- add a leg to the pending queue. This does not check the queue capacity.
     pub fn add(&mut self, leg: JtagLeg) {
         self.pending.push(leg);
     }
 
+    /// try_add() -- add a leg to the pending queue if there is room for it
+    pub fn try_add(&mut self, leg: JtagLeg) -> Result<(), QueueFull> {
+        self.add_all(core::iter::once(leg))
+    }
+
+    /// add_all() -- add a whole sequence of legs to the pending queue, or none of them.
+    /// If the length of the sequence is known up front it is checked before anything is
+    /// queued; otherwise legs are queued as they come and rolled back if the queue fills up.
+    pub fn add_all<I: IntoIterator<Item = JtagLeg>>(&mut self, legs: I) -> Result<(), QueueFull> {
+        let legs = legs.into_iter();
+        let available: usize = self.capacity.saturating_sub(self.pending.len());
+        if let (lower, Some(upper)) = legs.size_hint() {
+            if lower == upper && upper > available {
+                return Err(QueueFull { requested: upper, available });
+            }
+        }
+
+        let start: usize = self.pending.len();
+        let mut requested: usize = 0;
+        for leg in legs {
+            requested += 1;
+            if requested > available {
+                continue; // keep counting so the error reports the full request
+            }
+            self.pending.push(leg);
+        }
+        if requested > available {
+            self.pending.truncate(start);
+            return Err(QueueFull { requested, available });
+        }
+        Ok(())
+    }
+
+    /// add_seq() -- build and queue the legs for a command table, or none of them
+    pub fn add_seq(&mut self, cmds: &[SeqCmd]) -> Result<(), QueueFull> {
+        self.add_all(cmds.iter().map(SeqCmd::leg))
+    }
+
     /// get() -- get the oldest result in the done queue. Returns an option.
     pub fn get(&mut self) -> Option<JtagLeg> {
-        if self.done.len() > 0 {
+        if !self.done.is_empty() {
             Some(self.done.remove(0))
         } else {
             None
         }
     }
 
+    /// get_by_tag() -- take the oldest result in the done queue tagged `tag`, leaving the others
+    /// where they are. Returns an option.
+    pub fn get_by_tag(&mut self, tag: &str) -> Option<JtagLeg> {
+        let index: usize = self.done.iter().position(|leg| leg.tag() == tag)?;
+        Some(self.done.remove(index))
+    }
+
+    /// done_tags() -- the tags of the results in the done queue, oldest first
+    pub fn done_tags(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.done.iter().map(|leg| leg.tag())
+    }
+
     /// has_pending() -- tells if the jtag machine has a pending leg to traverse. Returns the tag of the pending item, or None.
     pub fn has_pending(&self) -> bool {
-        if self.pending.len() > 0 {
-            true
-        } else {
-            false
-        }
+        !self.pending.is_empty()
     }
 
     /// has_done() -- tells if the jtag machine has any legs that are done to read out. Returns the tag of the done item, or None.
     pub fn has_done(&self) -> bool {
-        if self.done.len() > 0 {
-            true
-        } else {
-            false
-        }
+        !self.done.is_empty()
+    }
+
+    /// clear_pending() -- discard every leg that hasn't been traversed yet, e.g. to abandon a
+    /// sequence after an error
+    pub fn clear_pending(&mut self) {
+        self.pending.clear();
+        self.current = None;
     }
 
     /// for debug
@@ -418,65 +1643,89 @@ impl JtagMach {
     /// step() -- move state machine by one cycle
     /// if there is nothing in the pending queue, stay in idle
     /// if something in the pending queue, traverse to execute it
+    /// Phy errors are dropped; use try_step() to see them.
     pub fn step<T: JtagPhy>(&mut self, phy: &mut T) {
-        self.s = match self.s {
+        let _ = self.try_step(phy);
+    }
+
+    /// try_step() -- fallible version of step()
+    /// On a phy error, when the leg being traversed runs out of budget, or when an IR leg fails
+    /// its IrVerification twice, the leg is abandoned but stays at the head of the pending
+    /// queue, and the machine refuses to drive the phy until reset() is called.
+    pub fn try_step<T: JtagPhy>(&mut self, phy: &mut T) -> Result<(), JtagError> {
+        if self.desync {
+            return Err(JtagError::Desynchronized);
+        }
+        match self.advance(phy) {
+            Ok(next) => {
+                self.s = next;
+                Ok(())
+            },
+            Err(e) => {
+                // the traversal copy is discarded; the original is still in pending
+                self.current = None;
+                self.s = JtagState::TestReset;
+                self.desync = true;
+                Err(e)
+            }
+        }
+    }
+
+    /// compute the state transition for one step, driving the phy as needed
+    fn advance<T: JtagPhy>(&mut self, phy: &mut T) -> Result<JtagState, JtagError> {
+        Ok(match self.s {
             JtagState::TestReset => {
-                phy.sync(false, false);
+                for tms in self.tap.path_to(TapState::RunTestIdle) {
+                    phy.try_sync(false, tms)?;
+                }
+                self.tap = TapState::RunTestIdle;
                 JtagState::RunIdle
             },
             JtagState::RunIdle => {
                 // we have a current item, traverse to the correct tree based on the type
-                if let Some(ref mut cur) = self.current {
-                    match cur.c {
-                        JtagChain::DR => {
-                            self.debug = 2;
-                            phy.sync(false, true);
-                        },
-                        JtagChain::IR => {
-                            self.debug = 3;
-                            // must be IR -- do two TMS high pulses to get to the IR leg
-                            phy.sync(false, true);
-                            phy.sync(false, true);
-                        }
-                    }
-                    JtagState::Select
+                if self.current.is_some() {
+                    self.debug = if self.chain() == JtagChain::IR { 3 } else { 2 };
+                    self.walk(phy, JtagState::Select)?
                 } else {
-                    if self.pending.len() > 0 {
+                    if !self.pending.is_empty() {
                         // nothing current, but has pending --> assign a current
                         // don't pop the entry, though, until we are finished traversing the leg,
                         // hence we make a clone of the entry
-                        self.current = Some(self.pending[0].clone());
+                        self.begin_leg()?;
+                        self.ir_retried = false;
                     } else {
                         // nothing pending, nothing current
                         // stay in the current state
-                        phy.sync(false, false);
+                        phy.try_sync(false, false)?;
                     }
                     JtagState::RunIdle
                 }
             },
-            JtagState::Select => {
-                phy.sync(false, false);
-                JtagState::Capture
-            },
-            JtagState::Capture => {
-                // always move to shift, because leg structures always have data
-                phy.sync(false, false);
-                JtagState::Shift
-            },
+            JtagState::Select => self.walk(phy, JtagState::Capture)?,
+            // always move to shift, because leg structures always have data
+            JtagState::Capture => self.walk(phy, JtagState::Shift)?,
             JtagState::Shift => {
                 // shift data until the input vector is exhausted
+                let exit: TapState = JtagMach::tap_state(JtagState::Exit1, self.chain());
                 if let Some(ref mut cur) = self.current {
-                    if let Some(tdi) = cur.i.pop() {
+                    if cur.c == JtagChain::DR && cur.i.len() >= BULK_SHIFT_BITS {
+                        self.meter.shift_bulk(phy, &mut cur.i, &mut cur.o)?;
+                        self.tap = exit;
+                        JtagState::Exit1
+                    } else if let Some(tdi) = cur.i.pop() {
                         if cur.i.len() > 0 {
-                            let tdo: bool = phy.sync(tdi, false);
+                            let tdo: bool = self.meter.sync(phy, tdi, false)?;
                             cur.o.push(tdo);
                             self.current = Some(cur.clone());
                             JtagState::Shift
                         } else {
-                            // last element should leave the state
-                            let tdo: bool = phy.sync(tdi, true);
+                            // last element should leave the state: its TMS is the one step
+                            // from Shift to Exit1
+                            let tms: bool = self.tap.path_to(exit).next().unwrap_or(true);
+                            let tdo: bool = self.meter.sync(phy, tdi, tms)?;
                             cur.o.push(tdo);
                             self.current = Some(cur.clone());
+                            self.tap = exit;
                             JtagState::Exit1
                         }
                     } else {
@@ -488,60 +1737,86 @@ impl JtagMach {
                     JtagState::Exit1
                 }
             },
-            JtagState::Exit1 => {
-                phy.sync(false, true);
-                JtagState::Update
-            },
-            JtagState::Pause => {
-                phy.sync(false, true);
-                JtagState::Exit2
-            },
-            JtagState::Exit2 => {
-                phy.sync(false, true);
-                JtagState::Update
-            },
+            JtagState::Exit1 => self.walk(phy, JtagState::Update)?,
+            JtagState::Pause => self.walk(phy, JtagState::Exit2)?,
+            JtagState::Exit2 => self.walk(phy, JtagState::Update)?,
             JtagState::Update => {
-                phy.sync(false, false);
+                self.walk(phy, JtagState::RunIdle)?;
 
+                if let Some(ref mut cur) = self.current {
+                    cur.unpad(self.padding);
+                    if cur.compare.is_some() {
+                        cur.compared = Some(cur.shifted_out());
+                    }
+                }
+                let failed: Option<(&'static str, u32)> = match self.current {
+                    Some(ref cur) if cur.c == JtagChain::IR && cur.ir_verify != IrVerification::Off => {
+                        let captured: u32 = cur.shifted_out() as u32;
+                        if cur.ir_verify.accepts(captured) { None } else { Some((cur.tag, captured)) }
+                    },
+                    _ => None,
+                };
+                if let Some((tag, captured)) = failed {
+                    if self.ir_retried {
+                        return Err(JtagError::IrCorrupt { tag, captured });
+                    }
+                    // the wrong instruction may have latched: scan the leg again, from a fresh
+                    // copy, before anything runs under it
+                    self.ir_retried = true;
+                    self.begin_leg()?;
+                    return Ok(JtagState::RunIdle);
+                }
                 self.pending.remove(0); // remove the oldest entry
                 if let Some(next) = self.current.take() {
                     self.done.push(next);
                 }
                 JtagState::RunIdle
             }
-        }
+        })
     }
 
     /// reset() -- bring the state machine back to the TEST_RESET state
+    /// Phy errors are dropped; use try_reset() to see them.
     pub fn reset<T: JtagPhy>(&mut self, phy: &mut T) {
+        let _ = self.try_reset(phy);
+    }
+
+    /// try_reset() -- fallible version of reset(). A successful reset also recovers the machine
+    /// from a previous phy error.
+    pub fn try_reset<T: JtagPhy>(&mut self, phy: &mut T) -> Result<(), JtagError> {
+        self.current = None;
+        self.s = JtagState::TestReset;
+        self.desync = true;
         // regardless of what state we are in, 5 cycles of TMS=1 will bring us to RESET
-        for _ in 0..5 {
-            phy.sync(false, true);
+        for _ in 0..TapState::RESET_CYCLES {
+            phy.try_sync(false, true)?;
         }
-        self.s = JtagState::TestReset;
+        self.tap = TapState::TestLogicReset;
+        self.desync = false;
+        Ok(())
     }
 
     /// next() -- advance until a RUN_IDLE state. If currently RUN_IDLE, traverse the next available leg, if one exists
+    /// Phy errors are dropped; use try_next() to see them.
     pub fn next<T: JtagPhy>(&mut self, phy: &mut T) {
+        let _ = self.try_next(phy);
+    }
+
+    /// try_next() -- fallible version of next()
+    pub fn try_next<T: JtagPhy>(&mut self, phy: &mut T) -> Result<(), JtagError> {
         match self.s {
             JtagState::RunIdle | JtagState::TestReset => {
                 if self.has_pending() {
                     // if pending, step until we're into a leg
-                    loop {
-                        match self.s {
-                            JtagState::RunIdle | JtagState::TestReset => self.step(phy),
-                            _ => break,
-                        }
+                    while matches!(self.s, JtagState::RunIdle | JtagState::TestReset) {
+                        self.try_step(phy)?;
                     }
                     // then step until we're out of the leg
-                    loop {
-                        match self.s {
-                            JtagState::RunIdle | JtagState::TestReset => break,
-                            _ => self.step(phy),
-                        }
+                    while !matches!(self.s, JtagState::RunIdle | JtagState::TestReset) {
+                        self.try_step(phy)?;
                     }
                 } else {
-                    self.step(phy); // this should be a single step with no state change
+                    self.try_step(phy)?; // this should be a single step with no state change
                 }
             },
             _ => {
@@ -549,10 +1824,121 @@ impl JtagMach {
                 loop {
                     match self.s {
                         JtagState::RunIdle | JtagState::TestReset => break,
-                        _ => self.step(phy),
+                        _ => self.try_step(phy)?,
                     }
                 }
             },
         }
+        Ok(())
+    }
+
+    /// current_state() -- the state the TAP is in, as far as the TMS driven so far says; None
+    /// when a phy error has left it unknown, until reset()
+    pub fn current_state(&self) -> Option<TapState> {
+        if self.desync { None } else { Some(self.tap) }
+    }
+
+    /// expect_state() -- Ok if the TAP is in `expected`; otherwise WrongState, or Desynchronized
+    /// if its state is unknown
+    pub fn expect_state(&self, expected: TapState) -> Result<(), JtagError> {
+        match self.current_state() {
+            Some(found) if found == expected => Ok(()),
+            Some(found) => Err(JtagError::WrongState { expected, found }),
+            None => Err(JtagError::Desynchronized),
+        }
+    }
+
+    /// goto() -- walk the TAP to `to` by the shortest TMS sequence
+    /// Phy errors are dropped; use try_goto() to see them.
+    pub fn goto<T: JtagPhy>(&mut self, phy: &mut T, to: TapState) {
+        let _ = self.try_goto(phy, to);
     }
-}
\ No newline at end of file
+
+    /// try_goto() -- fallible version of goto(). A leg already being traversed is finished
+    /// first; pending legs are left for the next call to next(), which walks back to
+    /// RUN_TEST/IDLE before starting one. The walk may pass through UPDATE-IR or UPDATE-DR,
+    /// which latch whatever the register holds, and TDI is held low throughout.
+    pub fn try_goto<T: JtagPhy>(&mut self, phy: &mut T, to: TapState) -> Result<(), JtagError> {
+        loop {
+            match self.s {
+                JtagState::RunIdle | JtagState::TestReset => break,
+                _ => self.try_step(phy)?,
+            }
+        }
+        if self.desync {
+            return Err(JtagError::Desynchronized);
+        }
+        for tms in self.tap.path_to(to) {
+            if let Err(e) = phy.try_sync(false, tms) {
+                self.current = None;
+                self.s = JtagState::TestReset;
+                self.desync = true;
+                return Err(JtagError::Phy(e));
+            }
+            self.tap = self.tap.next(tms);
+        }
+        // anywhere but RUN_TEST/IDLE, the TestReset step walks back there from wherever the TAP is
+        self.s = if to == TapState::RunTestIdle { JtagState::RunIdle } else { JtagState::TestReset };
+        Ok(())
+    }
+
+    /// try_idle() -- hold the TAP in RUN_TEST/IDLE for `cycles` TCK cycles, e.g. to let an
+    /// operation started by the last leg run. A leg already being traversed is finished first;
+    /// pending legs are left for the next call to next().
+    pub fn try_idle<T: JtagPhy>(&mut self, phy: &mut T, cycles: u32) -> Result<(), JtagError> {
+        loop {
+            match self.s {
+                JtagState::RunIdle | JtagState::TestReset => break,
+                _ => self.try_step(phy)?,
+            }
+        }
+        if self.desync {
+            return Err(JtagError::Desynchronized);
+        }
+        // after a goto() the TAP can be anywhere; the TestReset step walks it back to idle
+        if self.tap != TapState::TestLogicReset && self.tap != TapState::RunTestIdle {
+            self.try_step(phy)?;
+        }
+        for _ in 0..cycles {
+            // from Test-Logic-Reset the first cycle is the path to Run-Test/Idle; then it holds
+            let tms: bool = self.tap.path_to(TapState::RunTestIdle).next().unwrap_or(false);
+            if let Err(e) = phy.try_sync(false, tms) {
+                self.current = None;
+                self.s = JtagState::TestReset;
+                self.desync = true;
+                return Err(JtagError::Phy(e));
+            }
+            self.s = JtagState::RunIdle;
+            self.tap = TapState::RunTestIdle;
+        }
+        Ok(())
+    }
+
+    /// run_to_completion() -- traverse legs until nothing is pending. Returns the number of legs
+    /// that completed during the call; their results are in the done queue.
+    ///
+    /// If the phy fails partway, the error is returned and the queues are left as follows: legs
+    /// that completed before the failure are in the done queue, the leg that was in flight is
+    /// still at the head of the pending queue (unmodified), and the legs behind it are untouched.
+    /// The TAP is in an unknown state, so the machine won't drive the phy again until reset().
+    pub fn run_to_completion<T: JtagPhy>(&mut self, phy: &mut T) -> Result<usize, JtagError> {
+        let mut completed: usize = 0;
+        while self.has_pending() {
+            let before: usize = self.done.len();
+            self.try_next(phy)?;
+            completed += self.done.len() - before;
+        }
+        Ok(completed)
+    }
+
+    /// drain_completed() -- hand each leg in the done queue, oldest first, to `f`, emptying the queue
+    pub fn drain_completed<F: FnMut(JtagLeg)>(&mut self, mut f: F) {
+        for leg in self.done.drain(..) {
+            f(leg);
+        }
+    }
+}
+
+impl Default for JtagMach {
+    fn default() -> Self { JtagMach::new() }
+}
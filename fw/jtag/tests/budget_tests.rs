@@ -0,0 +1,135 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+
+    /// Loops tdi back to tdo. The `stall_for` operations after the first `stall_at` are refused
+    /// as Busy, then the phy carries on.
+    struct StallPhy {
+        ops: usize,
+        cycles: usize,
+        stall_at: usize,
+        stall_for: usize,
+    }
+
+    impl StallPhy {
+        fn new() -> Self {
+            StallPhy { ops: 0, cycles: 0, stall_at: usize::MAX, stall_for: 0 }
+        }
+        fn stalling(at: usize, stall_for: usize) -> Self {
+            StallPhy { stall_at: at, stall_for, ..StallPhy::new() }
+        }
+    }
+
+    impl JtagPhy for StallPhy {
+        fn sync(&mut self, tdi: bool, _tms: bool) -> bool {
+            self.cycles += 1;
+            tdi
+        }
+        fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool {
+            unimplemented!();
+        }
+        fn pause(&mut self, _us: u32) {}
+        fn try_sync(&mut self, tdi: bool, tms: bool) -> Result<bool, PhyError> {
+            self.ops += 1;
+            if self.ops > self.stall_at && self.ops <= self.stall_at + self.stall_for {
+                return Err(PhyError::Busy);
+            }
+            Ok(self.sync(tdi, tms))
+        }
+    }
+
+    fn leg(chain: JtagChain, bits: usize, tag: &'static str) -> JtagLeg {
+        let mut leg: JtagLeg = JtagLeg::new(chain, tag);
+        leg.push_u32(0x5A5, bits, JtagEndian::Little).unwrap();
+        leg
+    }
+
+    /// an IR leg with the default budget, then two DR legs with budgets of their own
+    fn queue(jm: &mut JtagMach) {
+        jm.add(leg(JtagChain::IR, 6, "ir"));
+        jm.add(leg(JtagChain::DR, 8, "dr1").with_budget(20));
+        jm.add(leg(JtagChain::DR, 12, "dr2").with_budget(100));
+    }
+
+    /// phy operations before the dr1 and dr2 legs start: the reset, the step out of
+    /// Test-Logic-Reset, then the legs ahead
+    const DR1_START: usize = 5 + 1 + 6 + 6;
+    const DR2_START: usize = DR1_START + 8 + 5;
+
+    #[test]
+    fn default_budget() {
+        let ir: JtagLeg = leg(JtagChain::IR, 6, "ir");
+        assert_eq!(ir.budget(), (6 + JtagLeg::OVERHEAD_CYCLES as u32) * JtagLeg::DEFAULT_BUDGET_FACTOR);
+        assert_eq!(ir.with_budget(9).budget(), 9);
+
+        let cmd = SeqCmd::new(JtagChain::DR, 64, 0, "dr");
+        assert_eq!(cmd.leg().budget(), (64 + JtagLeg::OVERHEAD_CYCLES as u32) * JtagLeg::DEFAULT_BUDGET_FACTOR);
+        assert_eq!(cmd.with_budget(70).leg().budget(), 70);
+    }
+
+    #[test]
+    fn clean_traversal_fits_its_length() {
+        // an IR leg takes its length plus OVERHEAD_CYCLES, a DR leg one less
+        let mut jp = StallPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        jm.add(leg(JtagChain::IR, 6, "ir").with_budget(12));
+        jm.add(leg(JtagChain::DR, 8, "dr").with_budget(13));
+        assert_eq!(jm.run_to_completion(&mut jp), Ok(2));
+
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        jm.add(leg(JtagChain::DR, 8, "dr").with_budget(12));
+        assert_eq!(jm.run_to_completion(&mut jp), Err(JtagError::LegTimeout { tag: "dr", budget: 12, spent: 12 }));
+    }
+
+    #[test]
+    fn stalls_within_budget_are_retried() {
+        let mut reference = StallPhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut reference);
+        queue(&mut jm);
+        jm.run_to_completion(&mut reference).unwrap();
+
+        // dr2 has room for 100 operations and needs 17
+        let mut jp = StallPhy::stalling(DR2_START + 4, 60);
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        queue(&mut jm);
+        assert_eq!(jm.run_to_completion(&mut jp), Ok(3));
+        assert_eq!(jp.cycles, reference.cycles);
+        assert_eq!(jp.ops, reference.ops + 60);
+    }
+
+    #[test]
+    fn stall_trips_only_its_own_leg() {
+        // dr1 has room for 20 operations and needs 13
+        let mut jp = StallPhy::stalling(DR1_START + 3, 30);
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        queue(&mut jm);
+        assert_eq!(jm.run_to_completion(&mut jp), Err(JtagError::LegTimeout { tag: "dr1", budget: 20, spent: 20 }));
+        // the IR leg went through; dr1 is still at the head of the queue
+        assert_eq!(jm.done_len(), 1);
+        assert_eq!(jm.pending_len(), 2);
+        assert_eq!(jm.try_next(&mut jp), Err(JtagError::Desynchronized));
+
+        // dr1 starts over with its full budget, and a later stall in dr2 doesn't touch it
+        // (the reset, the step out of Test-Logic-Reset, then dr1)
+        jp.stall_at = jp.ops + 5 + 1 + 8 + 5 + 4;
+        jp.stall_for = 60;
+        jm.reset(&mut jp);
+        assert_eq!(jm.run_to_completion(&mut jp), Ok(2));
+        assert!(jp.ops > jp.stall_at + jp.stall_for);
+        let mut tags: Vec<&str> = Vec::new();
+        jm.drain_completed(|leg| tags.push(leg.tag()));
+        assert_eq!(tags, vec!["ir", "dr1", "dr2"]);
+    }
+
+    #[test]
+    fn stall_outside_a_leg() {
+        let mut jp = StallPhy::stalling(2, 1);
+        let mut jm: JtagMach = JtagMach::new();
+        assert_eq!(jm.try_reset(&mut jp), Err(JtagError::Phy(PhyError::Busy)));
+    }
+}
@@ -0,0 +1,209 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+
+    /// Loops TDI back to TDO inverted, a cycle at a time, counting the calls
+    struct CyclePhy {
+        syncs: usize,
+        trace: Vec<(bool, bool)>,
+    }
+
+    impl CyclePhy {
+        fn new() -> Self {
+            CyclePhy { syncs: 0, trace: Vec::new() }
+        }
+    }
+
+    impl JtagPhy for CyclePhy {
+        fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+            self.syncs += 1;
+            self.trace.push((tdi, tms));
+            !tdi
+        }
+        fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool {
+            unimplemented!();
+        }
+        fn pause(&mut self, _us: u32) {}
+    }
+
+    /// As CyclePhy, but takes up to `batch` cycles per shift_bulk() call, as hardware with a
+    /// shift register of that size would
+    struct BatchPhy {
+        inner: CyclePhy,
+        batch: usize,
+        bulk_calls: usize,
+    }
+
+    impl BatchPhy {
+        fn new(batch: usize) -> Self {
+            BatchPhy { inner: CyclePhy::new(), batch, bulk_calls: 0 }
+        }
+    }
+
+    impl JtagPhy for BatchPhy {
+        fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+            self.inner.sync(tdi, tms)
+        }
+        fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool {
+            unimplemented!();
+        }
+        fn pause(&mut self, _us: u32) {}
+        fn shift_bulk(&mut self, tdi: &[u8], tms: &[u8], tdo_out: &mut [u8], nbits: usize) -> Result<usize, PhyError> {
+            self.bulk_calls += 1;
+            let shifted: usize = nbits.min(self.batch);
+            for n in 0..shifted {
+                let bit = |bytes: &[u8]| (bytes[n / 8] >> (n % 8)) & 0x1 == 1;
+                self.inner.trace.push((bit(tdi), bit(tms)));
+                if bit(tdi) {
+                    tdo_out[n / 8] &= !(1 << (n % 8));
+                } else {
+                    tdo_out[n / 8] |= 1 << (n % 8);
+                }
+            }
+            Ok(shifted)
+        }
+    }
+
+    /// Refuses every `every`th call as Busy, through either path
+    struct StallPhy {
+        inner: CyclePhy,
+        every: usize,
+        calls: usize,
+    }
+
+    impl JtagPhy for StallPhy {
+        fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+            self.inner.sync(tdi, tms)
+        }
+        fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool {
+            unimplemented!();
+        }
+        fn pause(&mut self, _us: u32) {}
+        fn try_sync(&mut self, tdi: bool, tms: bool) -> Result<bool, PhyError> {
+            self.calls += 1;
+            if self.calls.is_multiple_of(self.every) {
+                return Err(PhyError::Busy);
+            }
+            Ok(self.sync(tdi, tms))
+        }
+    }
+
+    /// a DR leg of `bits` zeros
+    fn zeros(tag: &'static str, bits: usize) -> JtagLeg {
+        let mut leg: JtagLeg = JtagLeg::new(JtagChain::DR, tag);
+        let mut left: usize = bits;
+        while left > 0 {
+            leg.push_u128(0, left.min(128), JtagEndian::Little).unwrap();
+            left -= left.min(128);
+        }
+        leg
+    }
+
+    /// an IR scan, then a 256-bit DR scan and a short one, as a key readback does
+    fn run<T: JtagPhy>(jp: &mut T) -> Vec<JtagLeg> {
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(jp);
+        let mut ir: JtagLeg = JtagLeg::new(JtagChain::IR, "ir");
+        ir.push_u32(0b110001, 6, JtagEndian::Little).unwrap();
+        jm.add(ir);
+        let mut key: JtagLeg = JtagLeg::new(JtagChain::DR, "key");
+        key.push_u128(0x0123_4567_89AB_CDEF_FEDC_BA98_7654_3210, 128, JtagEndian::Little).unwrap();
+        key.push_u128(0x5A5A_C3C3_0FF0_9669_A5A5_3C3C_F00F_6996, 128, JtagEndian::Little).unwrap();
+        jm.add(key);
+        let mut user: JtagLeg = JtagLeg::new(JtagChain::DR, "user");
+        user.push_u32(0x1234_ABCD, 32, JtagEndian::Little).unwrap();
+        jm.add(user);
+        assert_eq!(jm.run_to_completion(jp), Ok(3));
+        let mut legs: Vec<JtagLeg> = Vec::new();
+        jm.drain_completed(|leg| legs.push(leg));
+        legs
+    }
+
+    fn captured(legs: &mut [JtagLeg]) -> (u128, u128, u32) {
+        let low: u128 = legs[1].pop_u128(128, JtagEndian::Little).unwrap();
+        let high: u128 = legs[1].pop_u128(128, JtagEndian::Little).unwrap();
+        (high, low, legs[2].pop_u32(32, JtagEndian::Little).unwrap())
+    }
+
+    #[test]
+    fn batching_cuts_the_calls() {
+        let mut cycles = CyclePhy::new();
+        let mut by_cycle: Vec<JtagLeg> = run(&mut cycles);
+        let mut batch = BatchPhy::new(usize::MAX);
+        let mut by_batch: Vec<JtagLeg> = run(&mut batch);
+
+        // the same cycles on the wire, and the same bits captured
+        assert_eq!(batch.inner.trace, cycles.trace);
+        assert_eq!(captured(&mut by_batch), captured(&mut by_cycle));
+        assert_eq!(captured(&mut run(&mut CyclePhy::new())).2, !0x1234_ABCD);
+
+        // the 256 key bits took one call instead of 256; the short legs went a cycle at a time
+        assert_eq!(batch.bulk_calls, 1);
+        assert_eq!(cycles.syncs, batch.inner.syncs + 256);
+    }
+
+    #[test]
+    fn a_phy_can_stop_short() {
+        let mut cycles = CyclePhy::new();
+        let mut by_cycle: Vec<JtagLeg> = run(&mut cycles);
+        let mut batch = BatchPhy::new(24);
+        let mut by_batch: Vec<JtagLeg> = run(&mut batch);
+        assert_eq!(batch.inner.trace, cycles.trace);
+        assert_eq!(captured(&mut by_batch), captured(&mut by_cycle));
+        assert_eq!(batch.bulk_calls, 256usize.div_ceil(24));
+    }
+
+    #[test]
+    fn only_long_dr_legs_go_in_bulk() {
+        let mut jp = BatchPhy::new(usize::MAX);
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        jm.add(zeros("short", BULK_SHIFT_BITS - 1));
+        let mut ir: JtagLeg = JtagLeg::new(JtagChain::IR, "ir");
+        ir.push_u128(0, BULK_SHIFT_BITS, JtagEndian::Little).unwrap();
+        jm.add(ir);
+        assert_eq!(jm.run_to_completion(&mut jp), Ok(2));
+        assert_eq!(jp.bulk_calls, 0);
+
+        // padding counts towards the length
+        jm.set_padding(ChainPadding { dr_lead: 1, ..ChainPadding::NONE });
+        jm.reset(&mut jp);
+        jm.add(zeros("short", BULK_SHIFT_BITS - 1));
+        assert_eq!(jm.run_to_completion(&mut jp), Ok(1));
+        assert_eq!(jp.bulk_calls, 1);
+    }
+
+    #[test]
+    fn stalls_cost_what_they_did() {
+        // the default shift_bulk() stops at a stall and the JtagMach retries, so a stalling
+        // phy shifts the same cycles and spends the same budget either way
+        let mut jp = StallPhy { inner: CyclePhy::new(), every: 7, calls: 0 };
+        let mut stalled: Vec<JtagLeg> = run(&mut jp);
+        let mut cycles = CyclePhy::new();
+        assert_eq!(captured(&mut stalled), captured(&mut run(&mut cycles)));
+        assert_eq!(jp.inner.trace, cycles.trace);
+
+        // the budget a cycle at a time would have spent: Select, Capture and Shift, 256 bits,
+        // Update and Idle, and every seventh call refused, after the one call out of reset
+        let (mut ops, mut cycles, mut call): (u32, u32, usize) = (0, 0, 1);
+        while cycles < 3 + 256 + 2 {
+            call += 1;
+            ops += 1;
+            if call % 7 != 0 {
+                cycles += 1;
+            }
+        }
+        for &(budget, completes) in [(ops, true), (ops - 1, false)].iter() {
+            let mut jp = StallPhy { inner: CyclePhy::new(), every: 7, calls: 0 };
+            let mut jm: JtagMach = JtagMach::new();
+            jm.reset(&mut jp);
+            jp.calls = 0;
+            jm.add(zeros("key", 256).with_budget(budget));
+            if completes {
+                assert_eq!(jm.run_to_completion(&mut jp), Ok(1));
+            } else {
+                assert!(matches!(jm.run_to_completion(&mut jp), Err(JtagError::LegTimeout { tag: "key", .. })));
+            }
+        }
+    }
+}
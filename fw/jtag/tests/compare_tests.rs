@@ -0,0 +1,134 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+
+    /// Follows the TAP and shifts `capture` out of every DR scan, first bit the LSB; IR scans
+    /// shift out the mandatory 01
+    struct DrPhy {
+        tap: TapState,
+        bits: usize,
+        capture: u128,
+    }
+
+    impl DrPhy {
+        fn new(capture: u128) -> Self {
+            DrPhy { tap: TapState::TestLogicReset, bits: 0, capture }
+        }
+    }
+
+    impl JtagPhy for DrPhy {
+        fn sync(&mut self, _tdi: bool, tms: bool) -> bool {
+            let tdo: bool = match self.tap {
+                TapState::ShiftDr => (self.capture >> self.bits) & 0x1 == 1,
+                TapState::ShiftIr => self.bits == 0,
+                _ => false,
+            };
+            self.bits = match self.tap {
+                TapState::ShiftDr | TapState::ShiftIr => self.bits + 1,
+                _ => 0,
+            };
+            self.tap = self.tap.next(tms);
+            tdo
+        }
+        fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool {
+            unimplemented!();
+        }
+        fn pause(&mut self, _us: u32) {}
+    }
+
+    /// runs `cmds` and returns each leg's compare outcome, in order
+    fn run(jp: &mut DrPhy, cmds: &[SeqCmd]) -> Vec<Option<Result<(), JtagError>>> {
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(jp);
+        jm.add_seq(cmds).unwrap();
+        jm.run_to_completion(jp).unwrap();
+        let mut outcomes = Vec::new();
+        jm.drain_completed(|leg| outcomes.push(leg.compare_result()));
+        outcomes
+    }
+
+    #[test]
+    fn pattern() {
+        let status = CaptureCompare::new(0b0110, 0b1111);
+        assert!(status.accepts(0b0110));
+        assert!(!status.accepts(0b0111));
+        // bits outside the mask are don't-care, whatever `expected` says of them
+        assert!(status.accepts(0xF0 | 0b0110));
+        assert!(CaptureCompare::new(0x55, 0).accepts(0xAA));
+        assert!(CaptureCompare::new(1 << 127, 1 << 127).accepts(u128::MAX));
+
+        let cmd = SeqCmd::new(JtagChain::DR, 8, 0, "dr");
+        assert_eq!(cmd.leg().compare(), None);
+        assert_eq!(cmd.with_compare(0x12, 0xFF).leg().compare(), Some(CaptureCompare::new(0x12, 0xFF)));
+        // nothing to report before the leg is traversed
+        assert_eq!(cmd.with_compare(0x12, 0xFF).leg().compare_result(), None);
+    }
+
+    #[test]
+    fn pass() {
+        let mut jp = DrPhy::new(0xA5);
+        let outcomes = run(&mut jp, &[
+            SeqCmd::new(JtagChain::DR, 8, 0, "ack").with_compare(0xA5, 0xFF),
+            SeqCmd::new(JtagChain::DR, 8, 0, "plain"),
+        ]);
+        assert_eq!(outcomes, [Some(Ok(())), None]);
+    }
+
+    #[test]
+    fn fail() {
+        let mut jp = DrPhy::new(0xA4);
+        let outcomes = run(&mut jp, &[
+            SeqCmd::new(JtagChain::IR, 6, 0b110000, "ir"),
+            SeqCmd::new(JtagChain::DR, 8, 0, "ack").with_compare(0xA5, 0xFF),
+            SeqCmd::new(JtagChain::DR, 8, 0, "after"),
+        ]);
+        // a mismatch doesn't stop the machine; the legs behind it still run
+        assert_eq!(outcomes, [
+            None,
+            Some(Err(JtagError::CompareFailed { tag: "ack", expected: 0xA5, mask: 0xFF, got: 0xA4 })),
+            None,
+        ]);
+    }
+
+    #[test]
+    fn masked_bits_dont_care() {
+        // only the two status bits at the bottom are checked
+        let mut jp = DrPhy::new(0xDEAD_BEE0);
+        let outcomes = run(&mut jp, &[SeqCmd::new(JtagChain::DR, 32, 0, "status").with_compare(0, 0b11)]);
+        assert_eq!(outcomes, [Some(Ok(()))]);
+
+        let mut jp = DrPhy::new(0xDEAD_BEE2);
+        let outcomes = run(&mut jp, &[SeqCmd::new(JtagChain::DR, 32, 0, "status").with_compare(0, 0b11)]);
+        assert_eq!(outcomes, [Some(Err(JtagError::CompareFailed { tag: "status", expected: 0, mask: 0b11, got: 0xDEAD_BEE2 }))]);
+    }
+
+    #[test]
+    fn ir_captures_and_wide_legs() {
+        // IR legs are compared too, alongside or instead of an IrVerification
+        let mut jp = DrPhy::new(0);
+        let outcomes = run(&mut jp, &[SeqCmd::new(JtagChain::IR, 6, 0b111111, "ir").with_compare(0b01, 0b11)]);
+        assert_eq!(outcomes, [Some(Ok(()))]);
+
+        // a 100-bit leg is compared across all of it
+        let capture: u128 = (1 << 99) | 0x1234;
+        let mut jp = DrPhy::new(capture);
+        let outcomes = run(&mut jp, &[SeqCmd::new(JtagChain::DR, 100, 0, "wide").with_compare(capture, (1 << 100) - 1)]);
+        assert_eq!(outcomes, [Some(Ok(()))]);
+        let outcomes = run(&mut jp, &[SeqCmd::new(JtagChain::DR, 100, 0, "wide").with_compare(0x1234, (1 << 100) - 1)]);
+        assert!(matches!(outcomes[0], Some(Err(JtagError::CompareFailed { got, .. })) if got == capture));
+    }
+
+    #[test]
+    fn padded_legs_compare_the_addressed_device() {
+        // one bypassed device ahead on the chain delays the capture by a bit
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = DrPhy::new(0xA5 << 1);
+        jm.reset(&mut jp);
+        jm.set_padding(ChainPadding { ir_lead: 0, ir_trail: 0, dr_lead: 1, dr_trail: 0 });
+        jm.add_seq(&[SeqCmd::new(JtagChain::DR, 8, 0, "ack").with_compare(0xA5, 0xFF)]).unwrap();
+        jm.run_to_completion(&mut jp).unwrap();
+        let mut outcome = None;
+        jm.drain_completed(|leg| outcome = leg.compare_result());
+        assert_eq!(outcome, Some(Ok(())));
+    }
+}
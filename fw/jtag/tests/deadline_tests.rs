@@ -0,0 +1,120 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    struct FakeClock(Rc<Cell<u64>>);
+
+    impl Clock for FakeClock {
+        fn now_us(&self) -> u64 {
+            self.0.get()
+        }
+    }
+
+    /// Takes 1us per cycle, except for cycle `stall_at`, which takes `stall_us`
+    struct SlowPhy {
+        now: Rc<Cell<u64>>,
+        cycles: usize,
+        stall_at: Option<usize>,
+        stall_us: u64,
+    }
+
+    impl JtagPhy for SlowPhy {
+        fn sync(&mut self, tdi: bool, _tms: bool) -> bool {
+            let us: u64 = if Some(self.cycles) == self.stall_at { self.stall_us } else { 1 };
+            self.now.set(self.now.get() + us);
+            self.cycles += 1;
+            tdi
+        }
+        fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool {
+            unimplemented!();
+        }
+        fn pause(&mut self, us: u32) {
+            self.now.set(self.now.get() + us as u64);
+        }
+    }
+
+    fn phy(stall_at: Option<usize>, budget: Budget) -> DeadlinePhy<SlowPhy, FakeClock> {
+        let now = Rc::new(Cell::new(1000));
+        let inner = SlowPhy { now: now.clone(), cycles: 0, stall_at, stall_us: 500 };
+        DeadlinePhy::new(inner, FakeClock(now), budget)
+    }
+
+    fn queue(jm: &mut JtagMach) {
+        jm.add_seq(&[
+            SeqCmd::new(JtagChain::IR, 6, 0b001001, "ir"),
+            SeqCmd::new(JtagChain::DR, 32, 0, "dr"),
+        ]).unwrap();
+    }
+
+    #[test]
+    fn unlimited_is_transparent() {
+        let mut jp = phy(Some(3), Budget::UNLIMITED);
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        queue(&mut jm);
+        assert_eq!(jm.run_to_completion(&mut jp), Ok(2));
+        assert!(!jp.expired());
+        assert_eq!(jp.remaining_us(), None);
+    }
+
+    #[test]
+    fn session_budget() {
+        let mut jp = phy(None, Budget::new(None, Some(20)));
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        assert_eq!(jp.remaining_us(), Some(15));
+        queue(&mut jm);
+        assert_eq!(jm.run_to_completion(&mut jp), Err(JtagError::Phy(PhyError::DeadlineExceeded)));
+        assert!(jp.expired());
+        assert_eq!(jp.inner().cycles, 20);
+
+        // once expired, nothing reaches the wrapped phy
+        jm.clear_pending();
+        jm.reset(&mut jp);
+        jp.pause(100);
+        assert_eq!(jp.try_sync(false, false), Err(PhyError::DeadlineExceeded));
+        assert!(!jp.sync(true, false));
+        assert_eq!(jp.inner().cycles, 20);
+        assert_eq!(jp.inner().now.get(), 1020);
+    }
+
+    #[test]
+    fn pauses_count_against_session() {
+        let mut jp = phy(None, Budget::new(None, Some(1000)));
+        assert!(jp.try_sync(false, false).is_ok());
+        jp.pause(2000);
+        assert!(jp.expired());
+        assert_eq!(jp.try_sync(false, false), Err(PhyError::DeadlineExceeded));
+        assert_eq!(jp.inner().cycles, 1);
+    }
+
+    #[test]
+    fn operation_budget() {
+        let mut jp = phy(Some(12), Budget::new(Some(100), None));
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        queue(&mut jm);
+        assert_eq!(jm.run_to_completion(&mut jp), Err(JtagError::Phy(PhyError::DeadlineExceeded)));
+        assert_eq!(jp.inner().cycles, 13);
+        assert_eq!(jm.try_reset(&mut jp), Err(JtagError::Phy(PhyError::DeadlineExceeded)));
+        assert_eq!(jp.inner().cycles, 13);
+    }
+
+    #[test]
+    fn set_budget_recovers() {
+        let mut jp = phy(Some(12), Budget::new(Some(100), None));
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        queue(&mut jm);
+        assert!(jm.run_to_completion(&mut jp).is_err());
+
+        jp.set_budget(Budget::new(Some(100), Some(1000)));
+        assert!(!jp.expired());
+        assert_eq!(jp.remaining_us(), Some(1000));
+        jm.reset(&mut jp);
+        assert_eq!(jm.run_to_completion(&mut jp), Ok(2));
+        assert_eq!(jp.budget(), Budget::new(Some(100), Some(1000)));
+    }
+}
@@ -0,0 +1,132 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+
+    /// Follows the TAP and shifts `captures[n]` out of the nth IR scan, the last one repeating
+    /// for any later scans. DR scans loop tdi back.
+    struct CapturePhy {
+        tap: TapState,
+        ir_bits: usize,
+        captures: Vec<u32>,
+        ir_scans: usize,
+    }
+
+    impl CapturePhy {
+        fn new(captures: &[u32]) -> Self {
+            CapturePhy { tap: TapState::TestLogicReset, ir_bits: 0, captures: captures.to_vec(), ir_scans: 0 }
+        }
+    }
+
+    impl JtagPhy for CapturePhy {
+        fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+            let tdo: bool = match self.tap {
+                TapState::ShiftIr => {
+                    let capture: u32 = self.captures[self.ir_scans.min(self.captures.len() - 1)];
+                    self.ir_bits += 1;
+                    (capture >> (self.ir_bits - 1)) & 0x1 == 1
+                },
+                TapState::ShiftDr => tdi,
+                _ => false,
+            };
+            if self.tap == TapState::Exit1Ir {
+                self.ir_scans += 1;
+                self.ir_bits = 0;
+            }
+            self.tap = self.tap.next(tms);
+            tdo
+        }
+        fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool {
+            unimplemented!();
+        }
+        fn pause(&mut self, _us: u32) {}
+    }
+
+    fn ir(verify: IrVerification) -> JtagLeg {
+        let mut leg: JtagLeg = JtagLeg::new(JtagChain::IR, "ir");
+        leg.push_u32(0b110001, 6, JtagEndian::Little).unwrap();
+        leg.with_ir_verification(verify)
+    }
+
+    fn dr() -> JtagLeg {
+        let mut leg: JtagLeg = JtagLeg::new(JtagChain::DR, "dr");
+        leg.push_u32(0xA5, 8, JtagEndian::Little).unwrap();
+        leg
+    }
+
+    /// two fixed status bits above the mandatory 01
+    const STATUS: IrVerification = IrVerification::Capture { value: 0b100101, mask: 0b100100 };
+
+    #[test]
+    fn checks() {
+        assert!(IrVerification::Off.accepts(0));
+        assert!(IrVerification::Mandatory.accepts(0b110001));
+        assert!(!IrVerification::Mandatory.accepts(0b110011));
+        assert!(!IrVerification::Mandatory.accepts(0b110000));
+        assert!(STATUS.accepts(0b110101));
+        assert!(!STATUS.accepts(0b010101));
+        assert!(!STATUS.accepts(0b100110));
+        assert!(STATUS.accepts(STATUS.capture()));
+        assert!(IrVerification::Mandatory.accepts(IrVerification::Mandatory.capture()));
+
+        let cmd = SeqCmd::new(JtagChain::IR, 6, 0b110001, "ir");
+        assert_eq!(cmd.leg().ir_verification(), IrVerification::Off);
+        assert_eq!(cmd.with_ir_verification(STATUS).leg().ir_verification(), STATUS);
+    }
+
+    #[test]
+    fn unchecked_scans_ignore_the_capture() {
+        let mut jp = CapturePhy::new(&[0]);
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        jm.add(ir(IrVerification::Off));
+        assert_eq!(jm.run_to_completion(&mut jp), Ok(1));
+        assert_eq!(jp.ir_scans, 1);
+    }
+
+    #[test]
+    fn corrupt_capture_is_rescanned() {
+        let mut jp = CapturePhy::new(&[0b100111, 0b100101]);
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        jm.add(ir(STATUS));
+        jm.add(dr());
+        assert_eq!(jm.run_to_completion(&mut jp), Ok(2));
+        assert_eq!(jp.ir_scans, 2);
+
+        // the done queue holds the good scan, once
+        let mut legs: Vec<JtagLeg> = Vec::new();
+        jm.drain_completed(|leg| legs.push(leg));
+        assert_eq!(legs.len(), 2);
+        assert_eq!(legs[0].tag(), "ir");
+        assert_eq!(legs[0].pop_u32(6, JtagEndian::Little), Some(0b100101));
+        assert_eq!(legs[1].tag(), "dr");
+
+        // each leg gets its own retry
+        let mut jp = CapturePhy::new(&[0b100100, 0b100101, 0b000101, 0b100101]);
+        jm.reset(&mut jp);
+        jm.add(ir(STATUS));
+        jm.add(ir(STATUS));
+        assert_eq!(jm.run_to_completion(&mut jp), Ok(2));
+        assert_eq!(jp.ir_scans, 4);
+    }
+
+    #[test]
+    fn corrupt_twice_stops() {
+        let mut jp = CapturePhy::new(&[0b000110]);
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        jm.add(ir(IrVerification::Mandatory));
+        jm.add(dr());
+        assert_eq!(jm.run_to_completion(&mut jp), Err(JtagError::IrCorrupt { tag: "ir", captured: 0b000110 }));
+        assert_eq!(jp.ir_scans, 2);
+
+        // nothing ran under the bad instruction
+        assert_eq!(jm.done_len(), 0);
+        assert_eq!(jm.pending_len(), 2);
+        assert_eq!(jm.try_next(&mut jp), Err(JtagError::Desynchronized));
+
+        jp.captures = vec![0b000101];
+        jm.reset(&mut jp);
+        assert_eq!(jm.run_to_completion(&mut jp), Ok(2));
+    }
+}
@@ -0,0 +1,347 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+
+    /// Loops TDI back to TDO, and records the TDI stream.
+    struct LoopbackPhy {
+        tdi: Vec<bool>,
+    }
+
+    impl JtagPhy for LoopbackPhy {
+        fn sync(&mut self, tdi: bool, _tms: bool) -> bool {
+            self.tdi.push(tdi);
+            tdi
+        }
+        fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool {
+            unimplemented!();
+        }
+        fn pause(&mut self, _us: u32) {}
+    }
+
+    /// shift `leg` through a loopback, returning the captured leg and the TDI bits of the shift
+    fn shift(leg: JtagLeg) -> (JtagLeg, Vec<bool>) {
+        let mut jp = LoopbackPhy { tdi: Vec::new() };
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        jm.add(leg);
+        assert_eq!(jm.run_to_completion(&mut jp), Ok(1));
+        let captured = jm.get().unwrap();
+        // strip the TAP navigation: reset (5) + idle (1) + select/capture (3) on the way in,
+        // exit/update (2) on the way out
+        let bits: Vec<bool> = jp.tdi[9..jp.tdi.len() - 2].to_vec();
+        (captured, bits)
+    }
+
+    const HI: u128 = 0xF_0123_4567_89AB_CDEF_FEDC_BA98; // 100 bits
+    const LO: u128 = 0x5_A5A5_5A5A_C3C3_3C3C_0FF0_F00F; // 100 bits
+
+    #[test]
+    fn capacity() {
+        assert_eq!(JtagLeg::CAPACITY_BITS, LEG_CAPACITY_BYTES * 8);
+        let mut leg: JtagLeg = JtagLeg::new(JtagChain::DR, "full");
+        for _ in 0..JtagLeg::CAPACITY_BITS / 128 {
+            leg.push_u128(u128::MAX, 128, JtagEndian::Little).unwrap();
+        }
+        assert_eq!(leg.dbg_i_len(), JtagLeg::CAPACITY_BITS);
+        assert_eq!(leg.push_u8(1, 1, JtagEndian::Little), Err(LegOverflow { requested: 1, available: 0 }));
+        assert_eq!(leg.dbg_i_len(), JtagLeg::CAPACITY_BITS);
+    }
+
+    #[test]
+    fn overflow_pushes_nothing() {
+        let mut leg: JtagLeg = JtagLeg::new(JtagChain::DR, "partial");
+        leg.push_u128(0, 128, JtagEndian::Big).unwrap();
+        leg.push_u128(0, 128, JtagEndian::Big).unwrap();
+        leg.push_u128(0, 128, JtagEndian::Big).unwrap();
+        leg.push_u128(0, 116, JtagEndian::Big).unwrap();
+        assert_eq!(leg.push_u32(0xFFFF_FFFF, 32, JtagEndian::Big), Err(LegOverflow { requested: 32, available: 12 }));
+        assert_eq!(leg.dbg_i_len(), 500);
+        assert_eq!(leg.push_u32(0xFFF, 12, JtagEndian::Big), Ok(()));
+        assert_eq!(leg.dbg_i_len(), 512);
+    }
+
+    #[test]
+    fn little_endian_straddles_128() {
+        // successive little-endian pushes stack up like one long value, most significant part
+        // first; split the same 200-bit value at 100 and at 128 bits and compare
+        let mut split100: JtagLeg = JtagLeg::new(JtagChain::DR, "100");
+        split100.push_u128(HI, 100, JtagEndian::Little).unwrap();
+        split100.push_u128(LO, 100, JtagEndian::Little).unwrap();
+
+        let low128: u128 = LO | (HI << 100);
+        let high72: u128 = HI >> 28;
+        let mut split128: JtagLeg = JtagLeg::new(JtagChain::DR, "128");
+        split128.push_u128(high72, 72, JtagEndian::Little).unwrap();
+        split128.push_u128(low128, 128, JtagEndian::Little).unwrap();
+
+        let (mut a, tdi_a) = shift(split100);
+        let (mut b, tdi_b) = shift(split128);
+        assert_eq!(tdi_a.len(), 200);
+        assert_eq!(tdi_a, tdi_b);
+        // LSB goes out first
+        assert_eq!(tdi_a[0], LO & 1 == 1);
+        assert_eq!(tdi_a[199], (HI >> 99) & 1 == 1);
+
+        // captured data pops back most significant part first
+        assert_eq!(a.pop_u128(100, JtagEndian::Little), Some(HI));
+        assert_eq!(a.pop_u128(100, JtagEndian::Little), Some(LO));
+        assert_eq!(b.pop_u128(72, JtagEndian::Little), Some(high72));
+        assert_eq!(b.pop_u128(128, JtagEndian::Little), Some(low128));
+    }
+
+    #[test]
+    fn big_endian_straddles_128() {
+        // successive big-endian pushes go out last push first, each MSB first
+        let mut split100: JtagLeg = JtagLeg::new(JtagChain::DR, "100");
+        split100.push_u128(LO, 100, JtagEndian::Big).unwrap();
+        split100.push_u128(HI, 100, JtagEndian::Big).unwrap();
+
+        let low128: u128 = LO | (HI << 100);
+        let high72: u128 = HI >> 28;
+        let mut split128: JtagLeg = JtagLeg::new(JtagChain::DR, "128");
+        split128.push_u128(low128, 128, JtagEndian::Big).unwrap();
+        split128.push_u128(high72, 72, JtagEndian::Big).unwrap();
+
+        let (mut a, tdi_a) = shift(split100);
+        let (_, tdi_b) = shift(split128);
+        assert_eq!(tdi_a.len(), 200);
+        assert_eq!(tdi_a, tdi_b);
+        // MSB goes out first
+        assert_eq!(tdi_a[0], (HI >> 99) & 1 == 1);
+        assert_eq!(tdi_a[199], LO & 1 == 1);
+
+        // big-endian pops fill from the top of the word: the last 100 bits captured were LO,
+        // MSB first, so they come back left-justified
+        assert_eq!(a.pop_u128(100, JtagEndian::Big), Some(LO << 28));
+        assert_eq!(a.pop_u128(100, JtagEndian::Big), Some(HI << 28));
+    }
+
+    #[test]
+    fn narrow_pops_across_boundary() {
+        let mut leg: JtagLeg = JtagLeg::new(JtagChain::DR, "mixed");
+        leg.push_u128(LO, 124, JtagEndian::Little).unwrap();
+        leg.push_u32(0xDEAD_BEEF, 32, JtagEndian::Little).unwrap();
+        leg.push_u8(0xA5, 8, JtagEndian::Little).unwrap();
+        let (mut captured, tdi) = shift(leg);
+        assert_eq!(tdi.len(), 164);
+        // pops come back in push order
+        assert_eq!(captured.pop_u128(124, JtagEndian::Little), Some(LO));
+        assert_eq!(captured.pop_u32(32, JtagEndian::Little), Some(0xDEAD_BEEF));
+        assert_eq!(captured.pop_u8(8, JtagEndian::Little), Some(0xA5));
+        assert_eq!(captured.pop_u8(1, JtagEndian::Little), None);
+    }
+
+    #[test]
+    fn full_capacity_shift() {
+        let mut leg: JtagLeg = JtagLeg::new(JtagChain::DR, "full");
+        for i in 0..(JtagLeg::CAPACITY_BITS / 128) as u128 {
+            leg.push_u128(HI ^ i, 128, JtagEndian::Little).unwrap();
+        }
+        let (mut captured, tdi) = shift(leg);
+        assert_eq!(tdi.len(), JtagLeg::CAPACITY_BITS);
+        for i in 0..(JtagLeg::CAPACITY_BITS / 128) as u128 {
+            assert_eq!(captured.pop_u128(128, JtagEndian::Little), Some(HI ^ i));
+        }
+    }
+
+    /// xorshift, for lengths and data that aren't picked by hand
+    fn random(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    /// the LSB-first bytes of a number, bits from `count` up cleared
+    fn masked(bytes: &[u8], count: usize) -> Vec<u8> {
+        bytes.iter().enumerate().map(|(n, &b)| {
+            if n * 8 + 8 <= count { b } else if n * 8 >= count { 0 } else { b & ((1 << (count % 8)) - 1) }
+        }).collect()
+    }
+
+    #[test]
+    fn push_pop_bits_round_trip() {
+        let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+        for _ in 0..200 {
+            let count: usize = 1 + (random(&mut state) % JtagLeg::CAPACITY_BITS as u64) as usize;
+            let data: Vec<u8> = (0..LEG_CAPACITY_BYTES).map(|_| random(&mut state) as u8).collect();
+            for &big in [false, true].iter() {
+                let endian = || if big { JtagEndian::Big } else { JtagEndian::Little };
+                let mut leg: JtagLeg = JtagLeg::new(JtagChain::DR, "bits");
+                leg.push_bits(&data, count, endian()).unwrap();
+                let (mut captured, tdi) = shift(leg);
+                assert_eq!(tdi.len(), count);
+                // first shifted is the LSB or the MSB
+                let first: usize = if big { count - 1 } else { 0 };
+                assert_eq!(tdi[0], (data[first / 8] >> (first % 8)) & 1 == 1);
+
+                let mut out: Vec<u8> = vec![0xA5; LEG_CAPACITY_BYTES];
+                assert_eq!(captured.pop_bits(&mut out, count, endian()), Some(count));
+                let touched: usize = count.div_ceil(8);
+                assert_eq!(out[..touched], masked(&data, count)[..touched], "{} bits, big {}", count, big);
+                assert!(out[touched..].iter().all(|&b| b == 0xA5));
+            }
+        }
+    }
+
+    #[test]
+    fn bits_agree_with_u128() {
+        let mut state: u64 = 0x0123_4567_89AB_CDEF;
+        for count in 1..=128 {
+            let value: u128 = ((random(&mut state) as u128) << 64 | random(&mut state) as u128) & (u128::MAX >> (128 - count));
+            for &big in [false, true].iter() {
+                let endian = || if big { JtagEndian::Big } else { JtagEndian::Little };
+                let mut by_bits: JtagLeg = JtagLeg::new(JtagChain::DR, "bits");
+                by_bits.push_bits(&value.to_le_bytes(), count, endian()).unwrap();
+                let mut by_u128: JtagLeg = JtagLeg::new(JtagChain::DR, "u128");
+                by_u128.push_u128(value, count, endian()).unwrap();
+                let (mut captured, tdi) = shift(by_bits);
+                assert_eq!(tdi, shift(by_u128).1);
+
+                let mut out = [0u8; 16];
+                captured.pop_bits(&mut out, count, endian()).unwrap();
+                assert_eq!(u128::from_le_bytes(out), value);
+            }
+        }
+    }
+
+    #[test]
+    fn pop_bits_across_bytes() {
+        // 13 bits, MSB first, straddling a byte boundary
+        let mut leg: JtagLeg = JtagLeg::new(JtagChain::DR, "odd");
+        leg.push_bits(&[0x6B, 0x15], 13, JtagEndian::Big).unwrap();
+        let (mut captured, tdi) = shift(leg);
+        // 0x156B: 1 0101 0110 1011
+        assert_eq!(tdi, [true, false, true, false, true, false, true, true, false, true, false, true, true]);
+        // popped in two goes: the last 5 shifted, then the first 8
+        let mut low = [0xFFu8];
+        assert_eq!(captured.pop_bits(&mut low, 5, JtagEndian::Big), Some(5));
+        assert_eq!(low, [0x0B]);
+        let mut high = [0u8];
+        assert_eq!(captured.pop_bits(&mut high, 8, JtagEndian::Big), Some(8));
+        assert_eq!(high, [0xAB]);
+    }
+
+    #[test]
+    fn pop_bits_past_the_capture() {
+        let mut leg: JtagLeg = JtagLeg::new(JtagChain::DR, "short");
+        leg.push_bits(&[0x3C, 0x01], 9, JtagEndian::Little).unwrap();
+        let (mut captured, _) = shift(leg);
+        let mut out = [0x55u8; 2];
+        assert_eq!(captured.pop_bits(&mut out, 10, JtagEndian::Little), None);
+        // nothing was taken or written, so the right count still works
+        assert_eq!(out, [0x55; 2]);
+        assert_eq!(captured.dbg_o_len(), 9);
+        assert_eq!(captured.pop_bits(&mut out, 9, JtagEndian::Little), Some(9));
+        assert_eq!(out, [0x3C, 0x01]);
+        assert_eq!(captured.pop_bits(&mut out, 0, JtagEndian::Little), Some(0));
+    }
+
+    #[test]
+    fn push_bits_overflow() {
+        let mut leg: JtagLeg = JtagLeg::new(JtagChain::DR, "full");
+        leg.push_u32(0, 8, JtagEndian::Big).unwrap();
+        let data = [0xFFu8; LEG_CAPACITY_BYTES];
+        assert_eq!(leg.push_bits(&data, JtagLeg::CAPACITY_BITS, JtagEndian::Little),
+            Err(LegOverflow { requested: JtagLeg::CAPACITY_BITS, available: JtagLeg::CAPACITY_BITS - 8 }));
+        assert_eq!(leg.dbg_i_len(), 8);
+        assert_eq!(leg.push_bits(&data, JtagLeg::CAPACITY_BITS - 8, JtagEndian::Little), Ok(()));
+    }
+
+    #[test]
+    fn bytes_agree_with_u8() {
+        let mut state: u64 = 0xDEAD_BEEF_0BAD_F00D;
+        for len in 0..=LEG_CAPACITY_BYTES {
+            let data: Vec<u8> = (0..len).map(|_| random(&mut state) as u8).collect();
+            for &big in [false, true].iter() {
+                let endian = || if big { JtagEndian::Big } else { JtagEndian::Little };
+                let mut by_bytes: JtagLeg = JtagLeg::new(JtagChain::DR, "bytes");
+                by_bytes.push_bytes(&data, endian()).unwrap();
+                // the last pushed is shifted first
+                let mut by_u8: JtagLeg = JtagLeg::new(JtagChain::DR, "u8");
+                for &byte in data.iter().rev() {
+                    by_u8.push_u8(byte, 8, endian()).unwrap();
+                }
+                if len == 0 {
+                    assert_eq!(by_bytes.dbg_i_len(), 0);
+                    continue;
+                }
+                let (mut captured, tdi) = shift(by_bytes);
+                let (mut captured_u8, tdi_u8) = shift(by_u8);
+                assert_eq!(tdi, tdi_u8);
+                // data[0] goes first, LSB or MSB first
+                assert_eq!(tdi[0], data[0] & if big { 0x80 } else { 0x01 } != 0);
+
+                let mut out: Vec<u8> = vec![0; len];
+                assert_eq!(captured.pop_bytes(&mut out, endian()), Some(len));
+                assert_eq!(out, data);
+                let mut out_u8: Vec<u8> = vec![0; len];
+                for byte in out_u8.iter_mut().rev() {
+                    *byte = captured_u8.pop_u8(8, endian()).unwrap();
+                }
+                assert_eq!(out_u8, data);
+            }
+        }
+    }
+
+    #[test]
+    fn little_bytes_are_bits() {
+        let data: [u8; 16] = LO.to_le_bytes();
+        let mut by_bytes: JtagLeg = JtagLeg::new(JtagChain::DR, "bytes");
+        by_bytes.push_bytes(&data, JtagEndian::Little).unwrap();
+        let mut by_u128: JtagLeg = JtagLeg::new(JtagChain::DR, "u128");
+        by_u128.push_u128(LO, 128, JtagEndian::Little).unwrap();
+        let (mut captured, tdi) = shift(by_bytes);
+        assert_eq!(tdi, shift(by_u128).1);
+        let mut out = [0u8; 16];
+        assert_eq!(captured.pop_bytes(&mut out, JtagEndian::Little), Some(16));
+        assert_eq!(u128::from_le_bytes(out), LO);
+    }
+
+    #[test]
+    fn big_bytes_keep_their_order() {
+        // each byte MSB first, but the bytes still in slice order, unlike push_u32()
+        let mut by_bytes: JtagLeg = JtagLeg::new(JtagChain::DR, "bytes");
+        by_bytes.push_bytes(&[0x12, 0x34, 0x56, 0x78], JtagEndian::Big).unwrap();
+        let mut by_u32: JtagLeg = JtagLeg::new(JtagChain::DR, "u32");
+        by_u32.push_u32(0x1234_5678, 32, JtagEndian::Big).unwrap();
+        let (mut captured, tdi) = shift(by_bytes);
+        assert_eq!(tdi, shift(by_u32).1);
+        let mut out = [0u8; 4];
+        assert_eq!(captured.pop_bytes(&mut out, JtagEndian::Big), Some(4));
+        assert_eq!(out, [0x12, 0x34, 0x56, 0x78]);
+
+        // push_bits() of the same bytes is a little-endian number, so it goes the other way
+        let mut by_bits: JtagLeg = JtagLeg::new(JtagChain::DR, "bits");
+        by_bits.push_bits(&[0x12, 0x34, 0x56, 0x78], 32, JtagEndian::Big).unwrap();
+        let mut reversed: JtagLeg = JtagLeg::new(JtagChain::DR, "reversed");
+        reversed.push_bytes(&[0x78, 0x56, 0x34, 0x12], JtagEndian::Big).unwrap();
+        assert_eq!(shift(by_bits).1, shift(reversed).1);
+    }
+
+    #[test]
+    fn pop_bytes_past_the_capture() {
+        let mut leg: JtagLeg = JtagLeg::new(JtagChain::DR, "short");
+        leg.push_u32(0x1_A55A, 17, JtagEndian::Little).unwrap();
+        let (mut captured, _) = shift(leg);
+        let mut out = [0x55u8; 3];
+        assert_eq!(captured.pop_bytes(&mut out, JtagEndian::Little), None);
+        assert_eq!(out, [0x55; 3]);
+        assert_eq!(captured.dbg_o_len(), 17);
+        // the last two bytes shifted, 0x1_A55A >> 1, leaving the first bit
+        assert_eq!(captured.pop_bytes(&mut out[..2], JtagEndian::Little), Some(2));
+        assert_eq!(out[..2], [0xAD, 0xD2]);
+        assert_eq!(captured.dbg_o_len(), 1);
+    }
+
+    #[test]
+    fn push_bytes_overflow() {
+        let mut leg: JtagLeg = JtagLeg::new(JtagChain::DR, "full");
+        leg.push_u32(0, 1, JtagEndian::Big).unwrap();
+        let data = [0xFFu8; LEG_CAPACITY_BYTES];
+        assert_eq!(leg.push_bytes(&data, JtagEndian::Big),
+            Err(LegOverflow { requested: JtagLeg::CAPACITY_BITS, available: JtagLeg::CAPACITY_BITS - 1 }));
+        assert_eq!(leg.dbg_i_len(), 1);
+        assert_eq!(leg.push_bytes(&data[1..], JtagEndian::Big), Ok(()));
+    }
+}
@@ -0,0 +1,428 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+
+    /// Records every cycle; optionally fails every cycle from `fail_at` onwards.
+    struct TracePhy {
+        trace: Vec<(bool, bool)>,
+        fail_at: Option<usize>,
+    }
+
+    impl TracePhy {
+        fn new() -> Self {
+            TracePhy { trace: Vec::new(), fail_at: None }
+        }
+        fn failing_at(cycle: usize) -> Self {
+            TracePhy { trace: Vec::new(), fail_at: Some(cycle) }
+        }
+    }
+
+    impl JtagPhy for TracePhy {
+        fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+            self.trace.push((tdi, tms));
+            tdi
+        }
+        fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool {
+            unimplemented!();
+        }
+        fn pause(&mut self, _us: u32) {}
+        fn try_sync(&mut self, tdi: bool, tms: bool) -> Result<bool, PhyError> {
+            if let Some(limit) = self.fail_at {
+                if self.trace.len() >= limit {
+                    return Err(PhyError::Transport);
+                }
+            }
+            Ok(self.sync(tdi, tms))
+        }
+    }
+
+    fn queue(jm: &mut JtagMach) {
+        let mut ir: JtagLeg = JtagLeg::new(JtagChain::IR, "ir");
+        ir.push_u32(0b001001, 6, JtagEndian::Little).unwrap();
+        jm.add(ir);
+        let mut dr1: JtagLeg = JtagLeg::new(JtagChain::DR, "dr1");
+        dr1.push_u32(0xA5, 8, JtagEndian::Little).unwrap();
+        jm.add(dr1);
+        let mut dr2: JtagLeg = JtagLeg::new(JtagChain::DR, "dr2");
+        dr2.push_u32(0x3C3, 12, JtagEndian::Little).unwrap();
+        jm.add(dr2);
+    }
+
+    #[test]
+    fn run_to_completion_matches_next() {
+        let mut jp_next = TracePhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp_next);
+        queue(&mut jm);
+        while jm.has_pending() {
+            jm.next(&mut jp_next);
+        }
+        assert_eq!(jm.done_len(), 3);
+
+        let mut jp_run = TracePhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp_run);
+        queue(&mut jm);
+        assert_eq!(jm.run_to_completion(&mut jp_run), Ok(3));
+        assert!(!jm.has_pending());
+        assert_eq!(jm.done_len(), 3);
+
+        assert_eq!(jp_next.trace, jp_run.trace);
+    }
+
+    #[test]
+    fn drain_in_order() {
+        let mut jp = TracePhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        queue(&mut jm);
+        jm.run_to_completion(&mut jp).unwrap();
+
+        let mut tags: Vec<&str> = Vec::new();
+        let mut dr1: u32 = 0;
+        jm.drain_completed(|mut leg| {
+            if leg.tag() == "dr1" {
+                dr1 = leg.pop_u32(8, JtagEndian::Little).unwrap();
+            }
+            tags.push(leg.tag());
+        });
+        assert_eq!(tags, vec!["ir", "dr1", "dr2"]);
+        // the trace phy loops tdi back to tdo
+        assert_eq!(dr1, 0xA5);
+        assert!(!jm.has_done());
+    }
+
+    #[test]
+    fn get_by_tag_out_of_order() {
+        let mut jp = TracePhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        queue(&mut jm);
+        let mut again: JtagLeg = JtagLeg::new(JtagChain::DR, "dr1");
+        again.push_u32(0x5A, 8, JtagEndian::Little).unwrap();
+        jm.add(again);
+        jm.run_to_completion(&mut jp).unwrap();
+        assert_eq!(jm.done_tags().collect::<Vec<&str>>(), vec!["ir", "dr1", "dr2", "dr1"]);
+
+        let mut dr2: JtagLeg = jm.get_by_tag("dr2").unwrap();
+        assert_eq!(dr2.pop_u32(12, JtagEndian::Little), Some(0x3C3));
+        // of two legs with the same tag, the older comes out first
+        assert_eq!(jm.get_by_tag("dr1").unwrap().pop_u32(8, JtagEndian::Little), Some(0xA5));
+        assert!(jm.get_by_tag("dr2").is_none());
+        assert_eq!(jm.done_tags().collect::<Vec<&str>>(), vec!["ir", "dr1"]);
+        assert_eq!(jm.get_by_tag("dr1").unwrap().pop_u32(8, JtagEndian::Little), Some(0x5A));
+        assert!(jm.get_by_tag("nothing").is_none());
+
+        // the rest is left for get(), in order
+        assert_eq!(jm.get().map(|leg| leg.tag()), Some("ir"));
+        assert!(!jm.has_done());
+        assert_eq!(jm.done_tags().count(), 0);
+    }
+
+    #[test]
+    fn nothing_pending() {
+        let mut jp = TracePhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        let cycles = jp.trace.len();
+        assert_eq!(jm.run_to_completion(&mut jp), Ok(0));
+        assert_eq!(jp.trace.len(), cycles);
+    }
+
+    #[test]
+    fn error_mid_drive() {
+        // find out how many cycles the first two legs take, then fail partway into the second
+        let mut jp = TracePhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        queue(&mut jm);
+        jm.next(&mut jp);
+        let first_leg_end = jp.trace.len();
+
+        let mut jp = TracePhy::failing_at(first_leg_end + 4);
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        queue(&mut jm);
+        assert_eq!(jm.run_to_completion(&mut jp), Err(JtagError::Phy(PhyError::Transport)));
+
+        // the first leg completed, the in-flight leg and the one behind it are still pending
+        assert_eq!(jm.done_len(), 1);
+        assert_eq!(jm.pending_len(), 2);
+
+        // the machine won't touch the phy again until it's reset
+        let cycles = jp.trace.len();
+        assert_eq!(jm.run_to_completion(&mut jp), Err(JtagError::Desynchronized));
+        assert_eq!(jm.try_next(&mut jp), Err(JtagError::Desynchronized));
+        assert_eq!(jp.trace.len(), cycles);
+
+        // once the phy recovers, a reset resumes with the leg that was in flight, from scratch
+        jp.fail_at = None;
+        jm.reset(&mut jp);
+        assert_eq!(jm.run_to_completion(&mut jp), Ok(2));
+        let mut tags: Vec<&str> = Vec::new();
+        jm.drain_completed(|leg| tags.push(leg.tag()));
+        assert_eq!(tags, vec!["ir", "dr1", "dr2"]);
+    }
+
+    #[test]
+    fn clear_after_error() {
+        let mut jp = TracePhy::failing_at(10);
+        let mut jm: JtagMach = JtagMach::new();
+        assert!(jm.try_reset(&mut jp).is_ok());
+        queue(&mut jm);
+        assert!(jm.run_to_completion(&mut jp).is_err());
+        jm.clear_pending();
+        assert!(!jm.has_pending());
+        jp.fail_at = None;
+        jm.reset(&mut jp);
+        assert_eq!(jm.run_to_completion(&mut jp), Ok(0));
+    }
+
+    #[test]
+    fn idle_cycles() {
+        let mut jp = TracePhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        queue(&mut jm);
+        jm.next(&mut jp);
+        let before: usize = jp.trace.len();
+        jm.try_idle(&mut jp, 17).unwrap();
+        assert_eq!(&jp.trace[before..], &[(false, false); 17][..]);
+        // the pending legs are untouched, and still run as usual
+        assert_eq!(jm.pending_len(), 2);
+        jm.run_to_completion(&mut jp).unwrap();
+        assert_eq!(jm.done_len(), 3);
+    }
+
+    #[test]
+    fn idle_error() {
+        let mut jp = TracePhy::failing_at(8);
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp);
+        assert_eq!(jm.try_idle(&mut jp, 10), Err(JtagError::Phy(PhyError::Transport)));
+        assert_eq!(jm.try_idle(&mut jp, 1), Err(JtagError::Desynchronized));
+    }
+
+    fn legs(n: usize) -> Vec<JtagLeg> {
+        (0..n).map(|_| {
+            let mut leg: JtagLeg = JtagLeg::new(JtagChain::DR, "leg");
+            leg.push_u32(0, 4, JtagEndian::Little).unwrap();
+            leg
+        }).collect()
+    }
+
+    #[test]
+    fn add_all_within_capacity() {
+        let mut jm: JtagMach = JtagMach::with_capacity(4);
+        assert_eq!(jm.add_all(legs(3)), Ok(()));
+        assert_eq!(jm.pending_len(), 3);
+        assert_eq!(jm.try_add(legs(1).pop().unwrap()), Ok(()));
+        assert_eq!(jm.pending_len(), 4);
+        assert_eq!(jm.add_all(legs(0)), Ok(()));
+    }
+
+    #[test]
+    fn add_all_rejects_known_size_up_front() {
+        let mut jm: JtagMach = JtagMach::with_capacity(4);
+        jm.add_all(legs(2)).unwrap();
+        assert_eq!(jm.add_all(legs(3)), Err(QueueFull { requested: 3, available: 2 }));
+        assert_eq!(jm.pending_len(), 2);
+    }
+
+    #[test]
+    fn add_all_rolls_back_unknown_size() {
+        let mut jm: JtagMach = JtagMach::with_capacity(4);
+        jm.add_all(legs(2)).unwrap();
+        // filter() hides the length, so the overflow is only found partway through
+        let mut seen: usize = 0;
+        let unsized_legs = legs(5).into_iter().filter(|_| { seen += 1; true });
+        assert_eq!(jm.add_all(unsized_legs), Err(QueueFull { requested: 5, available: 2 }));
+        assert_eq!(seen, 5);
+        assert_eq!(jm.pending_len(), 2);
+
+        // the queue is still usable, and holds only the original legs
+        let mut jp = TracePhy::new();
+        jm.reset(&mut jp);
+        assert_eq!(jm.run_to_completion(&mut jp), Ok(2));
+    }
+
+    #[test]
+    fn try_add_when_full() {
+        let mut jm: JtagMach = JtagMach::with_capacity(1);
+        jm.try_add(legs(1).pop().unwrap()).unwrap();
+        assert_eq!(jm.try_add(legs(1).pop().unwrap()), Err(QueueFull { requested: 1, available: 0 }));
+        assert_eq!(jm.pending_len(), 1);
+    }
+
+    #[test]
+    fn add_seq_matches_manual_legs() {
+        const SEQ: [SeqCmd; 3] = [
+            SeqCmd::new(JtagChain::IR, 6, 0b001001, "ir"),
+            SeqCmd::new(JtagChain::DR, 8, 0xA5, "dr1"),
+            SeqCmd::new(JtagChain::DR, 12, 0x3C3, "dr2"),
+        ];
+        let mut jp_seq = TracePhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp_seq);
+        jm.add_seq(&SEQ).unwrap();
+        assert_eq!(jm.run_to_completion(&mut jp_seq), Ok(3));
+
+        let mut jp_manual = TracePhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut jp_manual);
+        queue(&mut jm);
+        jm.run_to_completion(&mut jp_manual).unwrap();
+
+        assert_eq!(jp_seq.trace, jp_manual.trace);
+    }
+
+    #[test]
+    fn add_seq_too_long() {
+        let seq = [SeqCmd::new(JtagChain::DR, 1, 0, "x"); DEFAULT_QUEUE_CAPACITY + 1];
+        let mut jm: JtagMach = JtagMach::new();
+        assert_eq!(jm.add_seq(&seq), Err(QueueFull { requested: DEFAULT_QUEUE_CAPACITY + 1, available: DEFAULT_QUEUE_CAPACITY }));
+        assert!(!jm.has_pending());
+    }
+
+    #[test]
+    fn padding_is_shifted_around_each_leg() {
+        let mut plain = TracePhy::new();
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(&mut plain);
+        queue(&mut jm);
+        jm.run_to_completion(&mut plain).unwrap();
+        jm.drain_completed(|_| {});
+
+        let padding = ChainPadding { ir_lead: 5, ir_trail: 3, dr_lead: 2, dr_trail: 1 };
+        let mut jp = TracePhy::new();
+        jm.set_padding(padding);
+        jm.reset(&mut jp);
+        queue(&mut jm);
+        assert_eq!(jm.run_to_completion(&mut jp), Ok(3));
+        assert_eq!(jp.trace.len(), plain.trace.len() + 8 + 3 + 3);
+
+        // BYPASS into the IRs either side of the addressed device's, which gets its instruction
+        // LSB first
+        let ir_in: Vec<bool> = [true; 5].iter().chain([true, false, false, true, false, false].iter())
+            .chain([true; 3].iter()).copied().collect();
+        assert!(jp.trace.windows(ir_in.len()).any(|w| w.iter().map(|&(tdi, _)| tdi).eq(ir_in.iter().copied())));
+
+        // the done queue only holds what the addressed device shifted out
+        let mut legs: Vec<JtagLeg> = Vec::new();
+        jm.drain_completed(|leg| legs.push(leg));
+        assert_eq!(legs[0].dbg_o_len(), 6);
+        assert_eq!(legs[0].pop_u32(6, JtagEndian::Little), Some(0b001001));
+        assert_eq!(legs[1].pop_u32(8, JtagEndian::Little), Some(0xA5));
+        assert_eq!(legs[2].pop_u32(12, JtagEndian::Little), Some(0x3C3));
+        assert_eq!(legs[2].dbg_o_len(), 0);
+    }
+
+    /// A device on a simulated chain: an IR of `ir_len` bits, and in DR either a 32-bit IDCODE
+    /// or the one-bit BYPASS register, as the latched instruction picks
+    struct Device {
+        ir_len: usize,
+        idcode: u32,
+        shift: Vec<bool>,
+        ir: Vec<bool>,
+    }
+
+    impl Device {
+        fn new(ir_len: usize, idcode: u32) -> Self {
+            Device { ir_len, idcode, shift: Vec::new(), ir: vec![true; ir_len] }
+        }
+
+        /// clocks the device in `state`, returning what it drives onto TDO
+        fn clock(&mut self, state: TapState, tdi: bool) -> bool {
+            match state {
+                TapState::CaptureIr => {
+                    self.shift = (0..self.ir_len).map(|n| n == 0).collect();
+                },
+                TapState::CaptureDr => {
+                    self.shift = if self.ir.iter().all(|&b| b) {
+                        vec![false]
+                    } else {
+                        (0..32).map(|n| (self.idcode >> n) & 1 == 1).collect()
+                    };
+                },
+                TapState::ShiftIr | TapState::ShiftDr => {
+                    self.shift.push(tdi);
+                    return self.shift.remove(0);
+                },
+                TapState::UpdateIr => self.ir = self.shift.clone(),
+                _ => {},
+            }
+            false
+        }
+    }
+
+    /// Devices in a chain, device 0 nearest TDI; records the TDO of every DR shift
+    struct ChainPhy {
+        tap: TapState,
+        devices: Vec<Device>,
+        dr_tdo: Vec<bool>,
+        ir_cycles: usize,
+    }
+
+    impl JtagPhy for ChainPhy {
+        fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+            let state: TapState = self.tap;
+            let tdo: bool = self.devices.iter_mut().fold(tdi, |bit, device| device.clock(state, bit));
+            match state {
+                TapState::ShiftDr => self.dr_tdo.push(tdo),
+                TapState::ShiftIr => self.ir_cycles += 1,
+                _ => {},
+            }
+            self.tap = state.next(tms);
+            tdo
+        }
+        fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool {
+            unimplemented!();
+        }
+        fn pause(&mut self, _us: u32) {}
+    }
+
+    #[test]
+    fn padding_around_other_devices() {
+        assert_eq!(ChainPadding::around(&[], &[]), ChainPadding::NONE);
+        assert_eq!(ChainPadding::around(&[4], &[6, 8]), ChainPadding { ir_lead: 14, ir_trail: 4, dr_lead: 2, dr_trail: 1 });
+
+        // a 4-bit device nearer TDI, and two more between the target and TDO
+        let mut jp = ChainPhy {
+            tap: TapState::TestLogicReset,
+            devices: vec![Device::new(4, 0x1111_1111), Device::new(6, 0x1362_D093), Device::new(6, 0x2222_2222), Device::new(8, 0x3333_3333)],
+            dr_tdo: Vec::new(),
+            ir_cycles: 0,
+        };
+        let mut jm: JtagMach = JtagMach::new();
+        jm.set_padding(ChainPadding::around(&[4], &[6, 8]));
+        jm.reset(&mut jp);
+        let mut ir: JtagLeg = JtagLeg::new(JtagChain::IR, "idcode");
+        ir.push_u32(0b001001, 6, JtagEndian::Little).unwrap();
+        jm.add(ir);
+        let mut dr: JtagLeg = JtagLeg::new(JtagChain::DR, "idcode");
+        dr.push_u32(0, 32, JtagEndian::Little).unwrap();
+        jm.add(dr);
+        assert_eq!(jm.run_to_completion(&mut jp), Ok(2));
+
+        // the target latched the instruction, everything else BYPASS
+        assert_eq!(jp.ir_cycles, 4 + 6 + 14);
+        assert_eq!(jp.devices[1].ir, [true, false, false, true, false, false]);
+        for index in [0, 2, 3].iter() {
+            assert!(jp.devices[*index].ir.iter().all(|&b| b), "device {}", index);
+        }
+
+        // the two BYPASS bits nearest TDO come out first, then the IDCODE LSB first, then the
+        // BYPASS bit of the device nearer TDI
+        assert_eq!(jp.dr_tdo.len(), 2 + 32 + 1);
+        let idcode: Vec<bool> = (0..32).map(|n| (0x1362_D093u32 >> n) & 1 == 1).collect();
+        assert_eq!(jp.dr_tdo[..2], [false, false]);
+        assert_eq!(jp.dr_tdo[2..34], idcode[..]);
+        assert!(!jp.dr_tdo[34]);
+
+        // and the captured leg holds only the target's bits
+        let mut legs: Vec<JtagLeg> = Vec::new();
+        jm.drain_completed(|leg| legs.push(leg));
+        assert_eq!(legs[1].dbg_o_len(), 32);
+        assert_eq!(legs[1].pop_u32(32, JtagEndian::Little), Some(0x1362_D093));
+    }
+}
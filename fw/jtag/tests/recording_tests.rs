@@ -0,0 +1,113 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+
+    /// Shifts TDI back out inverted, so TDO can be told apart from it
+    struct InvertPhy {
+        cycles: usize,
+    }
+
+    impl JtagPhy for InvertPhy {
+        fn sync(&mut self, tdi: bool, _tms: bool) -> bool {
+            self.cycles += 1;
+            !tdi
+        }
+        fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool {
+            unimplemented!();
+        }
+        fn pause(&mut self, _us: u32) {}
+    }
+
+    /// an IR scan and two DR scans, as in the mach tests
+    fn run<T: JtagPhy>(jp: &mut T) {
+        let mut jm: JtagMach = JtagMach::new();
+        jm.reset(jp);
+        let mut ir: JtagLeg = JtagLeg::new(JtagChain::IR, "ir");
+        ir.push_u32(0b001001, 6, JtagEndian::Little).unwrap();
+        jm.add(ir);
+        let mut dr1: JtagLeg = JtagLeg::new(JtagChain::DR, "dr1");
+        dr1.push_u32(0xA5, 8, JtagEndian::Little).unwrap();
+        jm.add(dr1);
+        let mut dr2: JtagLeg = JtagLeg::new(JtagChain::DR, "dr2");
+        dr2.push_u32(0x3C3, 12, JtagEndian::Little).unwrap();
+        jm.add(dr2);
+        jm.run_to_completion(jp).unwrap();
+    }
+
+    #[test]
+    fn records_every_cycle() {
+        let mut buf = [Transition::BLANK; 256];
+        let mut jp = RecordingPhy::new(InvertPhy { cycles: 0 }, &mut buf);
+        run(&mut jp);
+        let cycles: usize = jp.inner().cycles;
+        assert_eq!(jp.bit_count(), cycles);
+        assert_eq!(jp.dropped(), 0);
+
+        let mut tap: TapState = TapState::TestLogicReset;
+        for t in jp.transitions() {
+            assert_eq!(t.state, tap);
+            assert_eq!(t.tdo, !t.tdi);
+            tap = tap.next(t.tms);
+        }
+        assert_eq!(tap, jp.tap());
+        assert_eq!(jp.transitions().len(), cycles);
+
+        let shifts: Vec<(usize, u128, u128)> = jp.iter_dr_shifts().map(|s| (s.len(), s.tdi_value(), s.tdo_value())).collect();
+        assert_eq!(shifts, vec![(8, 0xA5, 0x5A), (12, 0x3C3, 0xC3C)]);
+    }
+
+    #[test]
+    fn the_ring_keeps_the_newest() {
+        let mut full = [Transition::BLANK; 256];
+        let mut jp = RecordingPhy::new(InvertPhy { cycles: 0 }, &mut full);
+        run(&mut jp);
+        let all: Vec<Transition> = jp.transitions().to_vec();
+
+        // just enough room for the last DR scan, from CAPTURE_DR on
+        let tail: usize = all.len() - all.iter().rposition(|t| t.state == TapState::CaptureDr).unwrap();
+        let mut buf = [Transition::BLANK; 64];
+        let mut jp = RecordingPhy::new(InvertPhy { cycles: 0 }, &mut buf[..tail]);
+        run(&mut jp);
+        assert_eq!(jp.bit_count(), all.len());
+        assert_eq!(jp.dropped(), all.len() - tail);
+        assert_eq!(jp.transitions(), &all[all.len() - tail..]);
+        // reading back doesn't disturb the ring
+        assert_eq!(jp.transitions(), &all[all.len() - tail..]);
+        assert_eq!(jp.iter_dr_shifts().map(|s| s.tdi_value()).collect::<Vec<u128>>(), vec![0x3C3]);
+
+        // one cycle less, and the last scan is cut short, so it's left out
+        let mut jp = RecordingPhy::new(InvertPhy { cycles: 0 }, &mut buf[..tail - 1]);
+        run(&mut jp);
+        assert_eq!(jp.iter_dr_shifts().count(), 0);
+
+        jp.clear();
+        assert_eq!(jp.bit_count(), 0);
+        assert!(jp.transitions().is_empty());
+    }
+
+    #[test]
+    fn no_buffer_still_counts() {
+        let mut jp = RecordingPhy::new(InvertPhy { cycles: 0 }, &mut []);
+        run(&mut jp);
+        assert_eq!(jp.bit_count(), jp.inner().cycles);
+        assert!(jp.transitions().is_empty());
+        assert_eq!(jp.iter_dr_shifts().count(), 0);
+    }
+
+    #[test]
+    fn an_unfinished_scan_is_left_out() {
+        let mut buf = [Transition::BLANK; 16];
+        let mut jp = RecordingPhy::new(InvertPhy { cycles: 0 }, &mut buf);
+        // RUN_TEST/IDLE, SELECT_DR_SCAN, CAPTURE_DR, SHIFT_DR, and two bits shifted
+        for tms in [false, true, false, false, false, false].iter() {
+            jp.sync(true, *tms);
+        }
+        assert_eq!(jp.tap(), TapState::ShiftDr);
+        assert_eq!(jp.iter_dr_shifts().count(), 0);
+        // the last bit leaves for EXIT1_DR
+        jp.sync(false, true);
+        let shifts: Vec<DrShift> = jp.iter_dr_shifts().collect();
+        assert_eq!(shifts.len(), 1);
+        assert_eq!((shifts[0].len(), shifts[0].tdi_value()), (3, 0b011));
+    }
+}
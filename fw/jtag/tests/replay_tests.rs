@@ -0,0 +1,75 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+
+    const TDO: [bool; 4] = [true, false, false, true];
+
+    #[test]
+    fn plays_back_in_order() {
+        let mut jp = ReplayPhy::new(&TDO, Exhausted::Fail);
+        assert_eq!(jp.remaining(), 4);
+        // whatever is driven
+        assert!(jp.sync(false, false));
+        assert!(!jp.sync(true, true));
+        jp.pause(100);
+        assert!(!jp.nosync(true, false, true));
+        assert_eq!(jp.cursor(), 2);
+        assert_eq!(jp.try_sync(false, true), Ok(false));
+        assert_eq!(jp.try_sync(false, true), Ok(true));
+        assert!(jp.is_exhausted());
+        assert_eq!(jp.overrun(), 0);
+
+        jp.rewind();
+        assert_eq!(jp.cursor(), 0);
+        assert_eq!(jp.try_sync(false, false), Ok(true));
+    }
+
+    #[test]
+    fn when_exhausted() {
+        let mut jp = ReplayPhy::new(&TDO[..1], Exhausted::Fail);
+        assert_eq!(jp.try_sync(false, false), Ok(true));
+        assert_eq!(jp.try_sync(false, false), Err(PhyError::Transport));
+        assert!(!jp.sync(false, false));
+        assert_eq!(jp.overrun(), 2);
+
+        let mut jp = ReplayPhy::new(&[], Exhausted::Fill(true));
+        assert_eq!(jp.try_sync(false, false), Ok(true));
+        assert!(jp.nosync(false, false, false));
+        assert_eq!(jp.overrun(), 1);
+        assert_eq!(jp.cursor(), 0);
+    }
+
+    #[test]
+    fn a_recorded_scan_replays() {
+        // a DR scan of 0x5A through a phy that echoes TDI, recorded, then replayed
+        struct EchoPhy;
+        impl JtagPhy for EchoPhy {
+            fn sync(&mut self, tdi: bool, _tms: bool) -> bool {
+                tdi
+            }
+            fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool {
+                unimplemented!();
+            }
+            fn pause(&mut self, _us: u32) {}
+        }
+
+        fn scan<T: JtagPhy>(jp: &mut T, value: u32) -> u32 {
+            let mut jm: JtagMach = JtagMach::new();
+            jm.reset(jp);
+            let mut dr: JtagLeg = JtagLeg::new(JtagChain::DR, "dr");
+            dr.push_u32(value, 8, JtagEndian::Little).unwrap();
+            jm.add(dr);
+            jm.run_to_completion(jp).unwrap();
+            jm.get().unwrap().pop_u32(8, JtagEndian::Little).unwrap()
+        }
+
+        let mut buf = [Transition::BLANK; 64];
+        let mut jp = RecordingPhy::new(EchoPhy, &mut buf);
+        assert_eq!(scan(&mut jp, 0x5A), 0x5A);
+        let tdo: Vec<bool> = jp.transitions().iter().map(|t| t.tdo).collect();
+
+        let mut jp = ReplayPhy::new(&tdo, Exhausted::Fail);
+        assert_eq!(scan(&mut jp, 0), 0x5A);
+        assert!(jp.is_exhausted());
+    }
+}
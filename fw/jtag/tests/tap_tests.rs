@@ -0,0 +1,226 @@
+#[cfg(test)]
+mod tests {
+    use jtag::*;
+    use jtag::TapState::*;
+
+    /// IEEE 1149.1 figure 6-1, edge by edge: (from, TMS, to)
+    const DIAGRAM: [(TapState, bool, TapState); 32] = [
+        (TestLogicReset, false, RunTestIdle), (TestLogicReset, true, TestLogicReset),
+        (RunTestIdle, false, RunTestIdle), (RunTestIdle, true, SelectDrScan),
+        (SelectDrScan, false, CaptureDr), (SelectDrScan, true, SelectIrScan),
+        (CaptureDr, false, ShiftDr), (CaptureDr, true, Exit1Dr),
+        (ShiftDr, false, ShiftDr), (ShiftDr, true, Exit1Dr),
+        (Exit1Dr, false, PauseDr), (Exit1Dr, true, UpdateDr),
+        (PauseDr, false, PauseDr), (PauseDr, true, Exit2Dr),
+        (Exit2Dr, false, ShiftDr), (Exit2Dr, true, UpdateDr),
+        (UpdateDr, false, RunTestIdle), (UpdateDr, true, SelectDrScan),
+        (SelectIrScan, false, CaptureIr), (SelectIrScan, true, TestLogicReset),
+        (CaptureIr, false, ShiftIr), (CaptureIr, true, Exit1Ir),
+        (ShiftIr, false, ShiftIr), (ShiftIr, true, Exit1Ir),
+        (Exit1Ir, false, PauseIr), (Exit1Ir, true, UpdateIr),
+        (PauseIr, false, PauseIr), (PauseIr, true, Exit2Ir),
+        (Exit2Ir, false, ShiftIr), (Exit2Ir, true, UpdateIr),
+        (UpdateIr, false, RunTestIdle), (UpdateIr, true, SelectDrScan),
+    ];
+
+    /// distance from `from` to every state, by brute force over TMS sequences of growing length
+    fn distances(from: TapState) -> [usize; 16] {
+        let mut distance = [usize::MAX; 16];
+        for len in 0..12 {
+            for bits in 0..(1u32 << len) {
+                let to = (0..len).fold(from, |state, i| state.next(bits & (1 << i) != 0));
+                distance[to as usize] = distance[to as usize].min(len);
+            }
+        }
+        distance
+    }
+
+    #[test]
+    fn every_transition() {
+        for &(from, tms, to) in DIAGRAM.iter() {
+            assert_eq!(from.next(tms), to, "{:?} with TMS {}", from, tms);
+        }
+        for (index, state) in TapState::ALL.iter().enumerate() {
+            assert_eq!(*state as usize, index);
+        }
+    }
+
+    #[test]
+    fn paths_arrive_in_the_fewest_cycles() {
+        for &from in TapState::ALL.iter() {
+            let distance = distances(from);
+            for &to in TapState::ALL.iter() {
+                let path: TmsPath = from.path_to(to);
+                assert_eq!(path.len(), distance[to as usize], "{:?} -> {:?}", from, to);
+                assert_eq!(path.fold(from, |state, tms| state.next(tms)), to, "{:?} -> {:?}", from, to);
+            }
+            assert!(from.path_to(from).is_empty());
+        }
+    }
+
+    #[test]
+    fn paths_the_machine_takes() {
+        let path = |from: TapState, to: TapState| from.path_to(to).collect::<Vec<bool>>();
+        assert_eq!(path(RunTestIdle, SelectDrScan), [true]);
+        assert_eq!(path(RunTestIdle, SelectIrScan), [true, true]);
+        assert_eq!(path(RunTestIdle, ShiftDr), [true, false, false]);
+        assert_eq!(path(RunTestIdle, ShiftIr), [true, true, false, false]);
+        assert_eq!(path(ShiftDr, RunTestIdle), [true, true, false]);
+        assert_eq!(path(TestLogicReset, RunTestIdle), [false]);
+        // out of a pause, through Exit2
+        assert_eq!(path(PauseDr, UpdateDr), [true, true]);
+    }
+
+    #[test]
+    fn reset_from_anywhere() {
+        for &from in TapState::ALL.iter() {
+            let state = (0..TapState::RESET_CYCLES).fold(from, |state, _| state.next(true));
+            assert_eq!(state, TestLogicReset, "from {:?}", from);
+        }
+        // and no fewer would do
+        let short = |from: TapState| (0..TapState::RESET_CYCLES - 1).fold(from, |state, _| state.next(true));
+        assert!(TapState::ALL.iter().any(|&from| short(from) != TestLogicReset));
+    }
+
+    /// Follows the TAP from the TMS stream
+    struct TapPhy {
+        tap: TapState,
+        visited: Vec<TapState>,
+    }
+
+    impl JtagPhy for TapPhy {
+        fn sync(&mut self, tdi: bool, tms: bool) -> bool {
+            self.tap = self.tap.next(tms);
+            self.visited.push(self.tap);
+            tdi
+        }
+        fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool {
+            unimplemented!();
+        }
+        fn pause(&mut self, _us: u32) {}
+    }
+
+    #[test]
+    fn machine_follows_the_diagram() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = TapPhy { tap: Exit2Ir, visited: Vec::new() };
+        jm.reset(&mut jp);
+        assert_eq!(jp.tap, TestLogicReset);
+
+        let mut ir = JtagLeg::new(JtagChain::IR, "ir");
+        ir.push_u32(0b10_1010, 6, JtagEndian::Little).unwrap();
+        let mut dr = JtagLeg::new(JtagChain::DR, "dr");
+        dr.push_u32(0, 9, JtagEndian::Little).unwrap();
+        jm.add(ir);
+        jm.add(dr);
+        jp.visited.clear();
+        jm.run_to_completion(&mut jp).unwrap();
+
+        assert_eq!(jp.tap, RunTestIdle);
+        assert_eq!(jp.visited.iter().filter(|&&s| s == ShiftIr).count(), 6);
+        assert_eq!(jp.visited.iter().filter(|&&s| s == ShiftDr).count(), 9);
+        assert_eq!(jp.visited.iter().filter(|&&s| s == UpdateIr).count(), 1);
+        assert_eq!(jp.visited.iter().filter(|&&s| s == UpdateDr).count(), 1);
+        assert!(!jp.visited.contains(&PauseDr) && !jp.visited.contains(&PauseIr));
+
+        jm.try_idle(&mut jp, 3).unwrap();
+        assert_eq!(jp.visited[jp.visited.len() - 3..], [RunTestIdle; 3]);
+    }
+
+    #[test]
+    fn current_state_follows_every_step() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = TapPhy { tap: ShiftDr, visited: Vec::new() };
+        jm.reset(&mut jp);
+        assert_eq!(jm.current_state(), Some(TestLogicReset));
+
+        let mut ir = JtagLeg::new(JtagChain::IR, "ir");
+        ir.push_u32(0b01_0011, 6, JtagEndian::Little).unwrap();
+        let mut dr = JtagLeg::new(JtagChain::DR, "dr");
+        dr.push_u32(0x5, 3, JtagEndian::Little).unwrap();
+        jm.add(ir);
+        jm.add(dr);
+        let mut seen: Vec<TapState> = Vec::new();
+        while jm.has_pending() {
+            jm.step(&mut jp);
+            assert_eq!(jm.current_state(), Some(jp.tap));
+            if seen.last() != Some(&jp.tap) {
+                seen.push(jp.tap);
+            }
+        }
+        // a step can take more than one cycle: SELECT-DR-SCAN is passed on the way to SELECT-IR-SCAN
+        assert_eq!(seen, [RunTestIdle, SelectIrScan, CaptureIr, ShiftIr, Exit1Ir, UpdateIr, RunTestIdle,
+            SelectDrScan, CaptureDr, ShiftDr, Exit1Dr, UpdateDr, RunTestIdle]);
+        assert_eq!(jm.expect_state(RunTestIdle), Ok(()));
+        assert_eq!(jm.expect_state(ShiftDr), Err(JtagError::WrongState { expected: ShiftDr, found: RunTestIdle }));
+    }
+
+    #[test]
+    fn goto_takes_the_shortest_path() {
+        for &from in TapState::ALL.iter() {
+            for &to in TapState::ALL.iter() {
+                let mut jm: JtagMach = JtagMach::new();
+                let mut jp = TapPhy { tap: RunTestIdle, visited: Vec::new() };
+                jm.reset(&mut jp);
+                jm.try_goto(&mut jp, from).unwrap();
+                jp.visited.clear();
+                jm.try_goto(&mut jp, to).unwrap();
+                assert_eq!(jp.tap, to);
+                assert_eq!(jm.current_state(), Some(to));
+                assert_eq!(jp.visited.len(), from.path_to(to).len(), "{:?} to {:?}", from, to);
+            }
+        }
+    }
+
+    #[test]
+    fn legs_after_a_goto() {
+        let mut jm: JtagMach = JtagMach::new();
+        let mut jp = TapPhy { tap: TestLogicReset, visited: Vec::new() };
+        jm.reset(&mut jp);
+        jm.goto(&mut jp, PauseIr);
+        assert_eq!((jp.tap, jm.current_state()), (PauseIr, Some(PauseIr)));
+
+        // the next leg walks back to idle first
+        let mut dr = JtagLeg::new(JtagChain::DR, "dr");
+        dr.push_u32(0x1, 4, JtagEndian::Little).unwrap();
+        jm.add(dr);
+        jp.visited.clear();
+        jm.run_to_completion(&mut jp).unwrap();
+        assert_eq!(jp.visited[..3], [Exit2Ir, UpdateIr, RunTestIdle]);
+        assert_eq!(jp.visited.iter().filter(|&&s| s == ShiftDr).count(), 4);
+        assert_eq!(jm.current_state(), Some(RunTestIdle));
+
+        // and so do idle cycles, which are then all spent in idle
+        jm.goto(&mut jp, ShiftDr);
+        jp.visited.clear();
+        jm.try_idle(&mut jp, 2).unwrap();
+        assert_eq!(jp.visited, [Exit1Dr, UpdateDr, RunTestIdle, RunTestIdle, RunTestIdle]);
+    }
+
+    #[test]
+    fn unknown_after_a_phy_error() {
+        struct Broken;
+        impl JtagPhy for Broken {
+            fn sync(&mut self, _tdi: bool, _tms: bool) -> bool {
+                false
+            }
+            fn nosync(&mut self, _tdi: bool, _tms: bool, _tck: bool) -> bool {
+                unimplemented!();
+            }
+            fn pause(&mut self, _us: u32) {}
+            fn try_sync(&mut self, _tdi: bool, _tms: bool) -> Result<bool, PhyError> {
+                Err(PhyError::Transport)
+            }
+        }
+
+        let mut jm: JtagMach = JtagMach::new();
+        assert_eq!(jm.try_goto(&mut Broken, ShiftIr), Err(JtagError::Phy(PhyError::Transport)));
+        assert_eq!(jm.current_state(), None);
+        assert_eq!(jm.expect_state(TestLogicReset), Err(JtagError::Desynchronized));
+        let mut jp = TapPhy { tap: ShiftIr, visited: Vec::new() };
+        assert_eq!(jm.try_goto(&mut jp, RunTestIdle), Err(JtagError::Desynchronized));
+        assert!(jp.visited.is_empty());
+        jm.reset(&mut jp);
+        assert_eq!(jm.current_state(), Some(TestLogicReset));
+    }
+}
@@ -530,7 +530,7 @@ impl Repl {
             } else if command.trim() == "id" {
                 self.jtag.reset(&mut self.jtagphy);
                 let mut id_leg: JtagLeg = JtagLeg::new(JtagChain::IR, "idcode");
-                id_leg.push_u32(0b001001, 6, JtagEndian::Little);
+                id_leg.push_u32(0b001001, 6, JtagEndian::Little).unwrap();
                 self.jtag.add(id_leg);
                 self.jtag.next(&mut self.jtagphy);
                 // NOW: - check the return data on .get() before using it
@@ -540,7 +540,7 @@ impl Repl {
                 }
 
                 let mut data_leg: JtagLeg = JtagLeg::new(JtagChain::DR, "iddata");
-                data_leg.push_u32(0, 32, JtagEndian::Little);
+                data_leg.push_u32(0, 32, JtagEndian::Little).unwrap();
                 self.jtag.add(data_leg);
                 self.jtag.dbg_reset();
                 self.jtag.next(&mut self.jtagphy);
@@ -551,28 +551,34 @@ impl Repl {
                     self.text.add_text(&mut format!("ID data not in get queue!"));
                 }
                 } else if command.trim() == "fk" { // crypto fuse
-                self.efuse.fetch(&mut self.jtag, &mut self.jtagphy);
+                if self.efuse.fetch(&mut self.jtag, &mut self.jtagphy).is_err() {
+                    self.text.add_text(&mut format!("Fuse readback failed!"));
+                    return;
+                }
                 let key: [u8; 32] = self.efuse.phy_key();
                 self.text.add_text(&mut String::from("Key, in hex:"));
-                let mut line = String::from("");
-                for i in (16..32).rev() {
-                    line = line + &format!("{:02x}", key[i]);
-                }
-                self.text.add_text(&mut line);
-                line = String::from("");
-                for i in (0..16).rev() {
-                    line = line + &format!("{:02x}", key[i]);
-                }
-                self.text.add_text(&mut line);
+                let mut hex = String::from("");
+                keyhex::format(&key, &mut hex, keyhex::HexCase::Lower).unwrap();
+                self.text.add_text(&mut String::from(&hex[..32]));
+                self.text.add_text(&mut String::from(&hex[32..]));
             } else if command.trim() == "fu" {
-                self.efuse.fetch(&mut self.jtag, &mut self.jtagphy);
+                if self.efuse.fetch(&mut self.jtag, &mut self.jtagphy).is_err() {
+                    self.text.add_text(&mut format!("Fuse readback failed!"));
+                    return;
+                }
                 self.text.add_text(&mut format!("user: 0x{:08x}", self.efuse.phy_user()));
             } else if command.trim() == "fc" {
-                self.efuse.fetch(&mut self.jtag, &mut self.jtagphy);
+                if self.efuse.fetch(&mut self.jtag, &mut self.jtagphy).is_err() {
+                    self.text.add_text(&mut format!("Fuse readback failed!"));
+                    return;
+                }
                 self.text.add_text(&mut format!("cntl: 0x{:02x}", self.efuse.phy_cntl()));
                 // comment out burning routines for now
             }  else if command.trim() == "burnkey" {
-                self.efuse.fetch(&mut self.jtag, &mut self.jtagphy);
+                if self.efuse.fetch(&mut self.jtag, &mut self.jtagphy).is_err() {
+                    self.text.add_text(&mut format!("Fuse readback failed!"));
+                    return;
+                }
                 let mut key: [u8; 32] = [0xab, 0x89, 0xaa, 0xaa, 0x9a, 0x78, 0xaa, 0xaa,
                                         0x89, 0x67, 0xaa, 0xaa, 0x78, 0x56, 0xaa, 0xaa,
                                         0x67, 0x45, 0xaa, 0xaa, 0x56, 0x34, 0xaa, 0xaa,
@@ -583,11 +589,13 @@ impl Repl {
                 } else {
                     self.text.add_text(&mut format!("Patch is not valid."));
                 }
-                self.efuse.burn(&mut self.jtag, &mut self.jtagphy);
+                if self.efuse.burn(&mut self.jtag, &mut self.jtagphy).is_err() {
+                    self.text.add_text(&mut format!("Burn failed."));
+                }
             }  else if command.trim() == "dna" { // dna
                 self.jtag.reset(&mut self.jtagphy);
                 let mut ir_leg: JtagLeg = JtagLeg::new(JtagChain::IR, "cmd");
-                ir_leg.push_u32(0b110010, 6, JtagEndian::Little);
+                ir_leg.push_u32(0b110010, 6, JtagEndian::Little).unwrap();
                 self.jtag.add(ir_leg);
                 self.jtag.next(&mut self.jtagphy);
                 if self.jtag.get().is_none() { // discard ID code but check that there's something
@@ -596,7 +604,7 @@ impl Repl {
                 }
 
                 let mut data_leg: JtagLeg = JtagLeg::new(JtagChain::DR, "dna");
-                data_leg.push_u128(0, 64, JtagEndian::Little);
+                data_leg.push_u128(0, 64, JtagEndian::Little).unwrap();
                 self.jtag.add(data_leg);
                 self.jtag.next(&mut self.jtagphy);
                 if let Some(mut data) = self.jtag.get() {